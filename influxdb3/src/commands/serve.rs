@@ -20,7 +20,10 @@ use influxdb3_process::{
 use influxdb3_server::{
     auth::AllOrNothingAuthorizer,
     builder::ServerBuilder,
-    query_executor::{CreateQueryExecutorArgs, QueryExecutorImpl},
+    query_executor::{
+        ColumnNames, CreateQueryExecutorArgs, QueryExecutorImpl, ReplayPolicy,
+        DEFAULT_QUERY_JOB_EXPIRY_INTERVAL,
+    },
     serve, CommonServerState,
 };
 use influxdb3_sys_events::SysEventStore;
@@ -162,6 +165,32 @@ pub struct Config {
     )]
     pub exec_mem_pool_bytes: MemorySize,
 
+    /// Size of the memory budget for the aggregate operators of each query, in bytes. Aggregates
+    /// that exceed this budget emit early or spill, even if the query execution memory pool has
+    /// room, which keeps heavy aggregations from starving other queries. It can be overridden
+    /// with the `influxdb3.aggregate_mem_pool_size` DataFusion config option, in bytes.
+    ///
+    /// Can be given as absolute value or in percentage of the total available memory (e.g. `10%`).
+    #[clap(
+        long = "exec-aggregate-mem-pool-bytes",
+        env = "INFLUXDB3_EXEC_AGGREGATE_MEM_POOL_BYTES",
+        action
+    )]
+    pub exec_aggregate_mem_pool_bytes: Option<MemorySize>,
+
+    /// High-water mark for the bytes reserved from the query execution memory pool. Queries wait
+    /// briefly for the pool to drop below it before executing, and are rejected with a retryable
+    /// error if it does not, rather than failing part way through their execution. Queries are
+    /// not held back by default.
+    ///
+    /// Can be given as absolute value or in percentage of the total available memory (e.g. `10%`).
+    #[clap(
+        long = "exec-mem-pool-high-water-mark",
+        env = "INFLUXDB3_EXEC_MEM_POOL_HIGH_WATER_MARK",
+        action
+    )]
+    pub exec_mem_pool_high_water_mark: Option<MemorySize>,
+
     /// bearer token to be set for requests
     #[clap(long = "bearer-token", env = "INFLUXDB3_BEARER_TOKEN", action)]
     pub bearer_token: Option<String>,
@@ -219,6 +248,228 @@ pub struct Config {
     )]
     pub query_log_size: usize,
 
+    /// How long the results of a query submitted to run in the background are retained after
+    /// the query finishes, expressed as a human-readable time, e.g., "30m", "1h".
+    #[clap(
+        long = "query-job-ttl",
+        env = "INFLUXDB3_QUERY_JOB_TTL",
+        default_value = "1h",
+        action
+    )]
+    pub query_job_ttl: humantime::Duration,
+
+    /// How many times to retry executing a query that fails with a transient error, such as a
+    /// network error reading from the object store, before any results have been returned.
+    #[clap(
+        long = "query-transient-retries",
+        env = "INFLUXDB3_QUERY_TRANSIENT_RETRIES",
+        default_value = "2",
+        action
+    )]
+    pub query_transient_retries: usize,
+
+    /// The maximum time spent planning a query, expressed as a human-readable time, e.g.,
+    /// "10s". Queries that take longer to plan fail, regardless of how long they would take to
+    /// execute. Planning time is unlimited by default.
+    #[clap(
+        long = "query-max-planning-time",
+        env = "INFLUXDB3_QUERY_MAX_PLANNING_TIME",
+        action
+    )]
+    pub query_max_planning_time: Option<humantime::Duration>,
+
+    /// Log a warning, with the text of the query, for queries that take longer than this to
+    /// plan, expressed as a human-readable time, e.g., "500ms". The cached results of such
+    /// queries are evicted from the result cache after those of other queries. Slow planning is
+    /// not logged by default.
+    #[clap(
+        long = "query-slow-planning-threshold",
+        env = "INFLUXDB3_QUERY_SLOW_PLANNING_THRESHOLD",
+        action
+    )]
+    pub query_slow_planning_threshold: Option<humantime::Duration>,
+
+    /// Record an event in the `system.events` table, with the text of the query, for queries
+    /// whose results take longer than this to be read, expressed as a human-readable time,
+    /// e.g., "10s". Slow queries are not recorded by default.
+    #[clap(
+        long = "query-slow-threshold",
+        env = "INFLUXDB3_QUERY_SLOW_THRESHOLD",
+        action
+    )]
+    pub query_slow_threshold: Option<humantime::Duration>,
+
+    /// Reject queries that join tables without a join condition, i.e., that produce a
+    /// cartesian product, if the join is estimated to produce more than this many rows. Cross
+    /// joins are not limited by default.
+    #[clap(
+        long = "query-cross-join-row-limit",
+        env = "INFLUXDB3_QUERY_CROSS_JOIN_ROW_LIMIT",
+        action
+    )]
+    pub query_cross_join_row_limit: Option<usize>,
+
+    /// Maximum span of time that a query may select, e.g., "30d". Queries whose condition on
+    /// time selects a wider range, or that have no lower bound on time, are rejected. Queries
+    /// are not limited by default.
+    #[clap(
+        long = "query-max-time-range",
+        env = "INFLUXDB3_QUERY_MAX_TIME_RANGE",
+        action
+    )]
+    pub query_max_time_range: Option<humantime::Duration>,
+
+    /// Maximum number of buckets that the `GROUP BY time()` of an InfluxQL query may produce
+    /// over the range of time that it selects. Queries that would produce more are rejected
+    /// before they run. Queries are not limited by default.
+    #[clap(
+        long = "query-max-time-buckets",
+        env = "INFLUXDB3_QUERY_MAX_TIME_BUCKETS",
+        action
+    )]
+    pub query_max_time_buckets: Option<usize>,
+
+    /// Maximum number of GET requests that a query may make to the object store, counted as one
+    /// per parquet file that it scans. Queries that would make more are rejected before they
+    /// run. Queries are not limited by default.
+    #[clap(
+        long = "query-max-storage-requests",
+        env = "INFLUXDB3_QUERY_MAX_STORAGE_REQUESTS",
+        action
+    )]
+    pub query_max_storage_requests: Option<usize>,
+
+    /// Maximum number of queries executed at a time. Further queries wait, once they are
+    /// planned, for the results of an executing query to be read, or dropped. Queries are not
+    /// limited by default.
+    #[clap(
+        long = "query-max-concurrency",
+        env = "INFLUXDB3_QUERY_MAX_CONCURRENCY",
+        action
+    )]
+    pub query_max_concurrency: Option<NonZeroUsize>,
+
+    /// Maximum number of nodes in the logical plan of a query, including those of its
+    /// subqueries. Queries whose plans have more, e.g., because they nest many subqueries, are
+    /// rejected before they are optimized. Queries are not limited by default.
+    #[clap(
+        long = "query-max-plan-nodes",
+        env = "INFLUXDB3_QUERY_MAX_PLAN_NODES",
+        action
+    )]
+    pub query_max_plan_nodes: Option<usize>,
+
+    /// Maximum cost of the resources that a query may consume while it runs, where each byte
+    /// that it scans costs one, and each row output by each of its operators costs
+    /// `--query-cost-row-weight`. Queries that exceed it are cancelled. Queries are not limited
+    /// by default.
+    #[clap(long = "query-max-cost", env = "INFLUXDB3_QUERY_MAX_COST", action)]
+    pub query_max_cost: Option<u64>,
+
+    /// The cost of each row output by each operator of a query, counted toward
+    /// `--query-max-cost`, relative to each byte that the query scans
+    #[clap(
+        long = "query-cost-row-weight",
+        env = "INFLUXDB3_QUERY_COST_ROW_WEIGHT",
+        default_value = "64",
+        action
+    )]
+    pub query_cost_row_weight: u64,
+
+    /// The maximum time a query may take, from when it is received until its results have been
+    /// read, expressed as a human-readable time, e.g., "5m". Queries that take longer are
+    /// cancelled. It can be overridden with the `influxdb3.query_timeout` DataFusion config
+    /// option. Queries are not limited by default.
+    #[clap(long = "query-timeout", env = "INFLUXDB3_QUERY_TIMEOUT", action)]
+    pub query_timeout: Option<humantime::Duration>,
+
+    /// Run queries with the batch priority on a separate pool of this many threads, so that
+    /// they do not hold up interactive queries. If not set, all queries share the same pool.
+    #[clap(
+        long = "datafusion-batch-num-threads",
+        env = "INFLUXDB3_DATAFUSION_BATCH_NUM_THREADS",
+        action
+    )]
+    pub datafusion_batch_num_threads: Option<NonZeroUsize>,
+
+    /// Size of the cache for the results of queries that opt into result caching, in bytes.
+    /// Cached results are invalidated by writes to the tables that they were read from. If not
+    /// set, query results are not cached.
+    ///
+    /// Can be given as absolute value or in percentage of the total available memory (e.g. `10%`).
+    #[clap(
+        long = "query-result-cache-bytes",
+        env = "INFLUXDB3_QUERY_RESULT_CACHE_BYTES",
+        action
+    )]
+    pub query_result_cache_bytes: Option<MemorySize>,
+
+    /// Size of the buffer for the results of a query that identical queries made while it is in
+    /// flight share, rather than being executed themselves, in bytes. Queries arriving once the
+    /// buffer is full are executed separately. If not set, identical queries are not coalesced.
+    ///
+    /// Can be given as absolute value or in percentage of the total available memory (e.g. `10%`).
+    #[clap(
+        long = "query-coalesce-buffer-bytes",
+        env = "INFLUXDB3_QUERY_COALESCE_BUFFER_BYTES",
+        action
+    )]
+    pub query_coalesce_buffer_bytes: Option<MemorySize>,
+
+    /// Number of parquet files to prefetch into the parquet cache ahead of each table scan, so
+    /// that scans of a high-latency object store do not wait on each file in turn. If not set,
+    /// or if the parquet cache is disabled, files are only read as they are scanned.
+    #[clap(
+        long = "query-scan-read-ahead",
+        env = "INFLUXDB3_QUERY_SCAN_READ_AHEAD",
+        action
+    )]
+    pub query_scan_read_ahead: Option<NonZeroUsize>,
+
+    /// Maximum size of the parquet files being prefetched ahead of each table scan at a time.
+    /// Files larger than this are only read as they are scanned.
+    ///
+    /// Can be given as absolute value or in percentage of the total available memory (e.g. `10%`).
+    #[clap(
+        long = "query-scan-read-ahead-bytes",
+        env = "INFLUXDB3_QUERY_SCAN_READ_AHEAD_BYTES",
+        default_value = "268435456", // 256 MiB
+        action
+    )]
+    pub query_scan_read_ahead_bytes: MemorySize,
+
+    /// How queries are handled while the write-ahead log is being replayed, when the data that
+    /// they would read is incomplete: `reject` fails them, so that clients retry, while `warn`
+    /// serves them from the data replayed so far.
+    #[clap(
+        long = "query-replay-policy",
+        env = "INFLUXDB3_QUERY_REPLAY_POLICY",
+        default_value = "warn",
+        action
+    )]
+    pub query_replay_policy: ReplayPolicy,
+
+    /// The name of the retention policy reported for databases, e.g., by `SHOW RETENTION
+    /// POLICIES`, for deployments migrated from a default policy not named `autogen`.
+    #[clap(
+        long = "default-retention-policy",
+        env = "INFLUXDB3_DEFAULT_RETENTION_POLICY",
+        default_value = "autogen",
+        action
+    )]
+    pub default_retention_policy: String,
+
+    /// The names of the columns named after the internals of the query engine in the output of
+    /// `SHOW DATABASES` and `SHOW RETENTION POLICIES`: `legacy` keeps names like
+    /// `iox::database`, while `friendly` uses names like `database`.
+    #[clap(
+        long = "show-column-names",
+        env = "INFLUXDB3_SHOW_COLUMN_NAMES",
+        default_value = "legacy",
+        action
+    )]
+    pub show_column_names: ColumnNames,
+
     // TODO - make this default to 70% of available memory:
     /// The size limit of the buffered data. If this limit is passed a snapshot will be forced.
     #[clap(
@@ -452,6 +703,37 @@ pub async fn command(config: Config) -> Result<()> {
     let runtime_env = exec.new_context().inner().runtime_env();
     register_iox_object_store(runtime_env, parquet_store.id(), Arc::clone(&object_store));
 
+    let batch_exec = config
+        .datafusion_batch_num_threads
+        .map(|num_threads| {
+            info!(
+                num_threads = num_threads.get(),
+                "Creating batch query executor"
+            );
+            let mut batch_config = tokio_datafusion_config.clone();
+            batch_config.num_threads = Some(num_threads);
+            let batch_exec = Arc::new(Executor::new_with_config_and_executor(
+                ExecutorConfig {
+                    target_query_partitions: num_threads,
+                    object_stores: [&parquet_store]
+                        .into_iter()
+                        .map(|store| (store.id(), Arc::clone(store.object_store())))
+                        .collect(),
+                    metric_registry: Arc::clone(&metrics),
+                    mem_pool_size: config.exec_mem_pool_bytes.bytes(),
+                },
+                DedicatedExecutor::new(
+                    "datafusion_batch",
+                    batch_config.builder().map_err(Error::TokioRuntime)?,
+                    Arc::clone(&metrics),
+                ),
+            ));
+            let runtime_env = batch_exec.new_context().inner().runtime_env();
+            register_iox_object_store(runtime_env, parquet_store.id(), Arc::clone(&object_store));
+            Ok::<_, Error>(batch_exec)
+        })
+        .transpose()?;
+
     let trace_header_parser = TraceHeaderParser::new()
         .with_jaeger_trace_context_header_name(
             config
@@ -500,7 +782,7 @@ pub async fn command(config: Config) -> Result<()> {
         time_provider: Arc::<SystemProvider>::clone(&time_provider),
         executor: Arc::clone(&exec),
         wal_config,
-        parquet_cache,
+        parquet_cache: parquet_cache.clone(),
         metric_registry: Arc::clone(&metrics),
     })
     .await
@@ -537,12 +819,43 @@ pub async fn command(config: Config) -> Result<()> {
         catalog: write_buffer.catalog(),
         write_buffer: Arc::clone(&write_buffer),
         exec: Arc::clone(&exec),
+        batch_exec,
         metrics: Arc::clone(&metrics),
+        time_provider: Arc::<SystemProvider>::clone(&time_provider),
         datafusion_config: Arc::new(config.iox_query_datafusion_config.build()),
         query_log_size: config.query_log_size,
         telemetry_store: Arc::clone(&telemetry_store),
         sys_events_store: Arc::clone(&sys_events_store),
+        persister: Arc::clone(&persister),
+        query_job_ttl: config.query_job_ttl.into(),
+        aggregate_mem_pool_size: config.exec_aggregate_mem_pool_bytes.map(|s| s.bytes()),
+        mem_pool_high_water_mark: config.exec_mem_pool_high_water_mark.map(|s| s.bytes()),
+        max_transient_retries: config.query_transient_retries,
+        max_planning_time: config.query_max_planning_time.map(Into::into),
+        slow_planning_threshold: config.query_slow_planning_threshold.map(Into::into),
+        slow_query_threshold: config.query_slow_threshold.map(Into::into),
+        cross_join_row_limit: config.query_cross_join_row_limit,
+        max_query_time_range: config.query_max_time_range.map(Into::into),
+        max_time_buckets: config.query_max_time_buckets,
+        max_storage_requests: config.query_max_storage_requests,
+        max_plan_nodes: config.query_max_plan_nodes,
+        max_query_cost: config.query_max_cost,
+        query_cost_row_weight: config.query_cost_row_weight,
+        query_timeout: config.query_timeout.map(Into::into),
+        result_cache_size: config.query_result_cache_bytes.map(|s| s.bytes()),
+        coalesce_buffer_size: config.query_coalesce_buffer_bytes.map(|s| s.bytes()),
+        default_retention_policy: config.default_retention_policy,
+        parquet_cache,
+        scan_read_ahead: config.query_scan_read_ahead,
+        scan_read_ahead_bytes: config.query_scan_read_ahead_bytes.bytes(),
+        replay_policy: config.query_replay_policy,
+        column_names: config.show_column_names,
+        max_concurrent_queries: config.query_max_concurrency,
     }));
+    info!("setting up background expiry of query jobs");
+    query_executor
+        .start_query_job_expiry(DEFAULT_QUERY_JOB_EXPIRY_INTERVAL)
+        .await;
 
     let listener = TcpListener::bind(*config.http_bind_address)
         .await
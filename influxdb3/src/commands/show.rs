@@ -34,6 +34,11 @@ pub struct DatabaseConfig {
     #[clap(long = "show-deleted", default_value = "false")]
     show_deleted: bool,
 
+    /// Include the number of tables, and the number of rows and bytes persisted, of each database
+    /// in the output
+    #[clap(long = "show-stats", default_value = "false")]
+    show_stats: bool,
+
     /// The format in which to output the list of databases
     #[clap(value_enum, long = "format", default_value = "pretty")]
     output_format: Format,
@@ -65,6 +70,7 @@ pub(crate) async fn command(config: Config) -> Result<(), Box<dyn Error>> {
             host_url,
             auth_token,
             show_deleted,
+            show_stats,
             output_format,
         }) => {
             let mut client = influxdb3_client::Client::new(host_url)?;
@@ -77,6 +83,7 @@ pub(crate) async fn command(config: Config) -> Result<(), Box<dyn Error>> {
                 .api_v3_configure_db_show()
                 .with_format(output_format.into())
                 .with_show_deleted(show_deleted)
+                .with_show_stats(show_stats)
                 .send()
                 .await?;
 
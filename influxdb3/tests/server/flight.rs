@@ -117,12 +117,17 @@ async fn flight() -> Result<(), influxdb3_client::Error> {
                 "| public       | information_schema | tables                     | VIEW       |",
                 "| public       | information_schema | views                      | VIEW       |",
                 "| public       | iox                | cpu                        | BASE TABLE |",
+                "| public       | system             | catalog                    | BASE TABLE |",
+                "| public       | system             | chunks                     | BASE TABLE |",
                 "| public       | system             | distinct_caches            | BASE TABLE |",
+                "| public       | system             | events                     | BASE TABLE |",
                 "| public       | system             | last_caches                | BASE TABLE |",
+                "| public       | system             | overlapping_chunks         | BASE TABLE |",
                 "| public       | system             | parquet_files              | BASE TABLE |",
                 "| public       | system             | processing_engine_plugins  | BASE TABLE |",
                 "| public       | system             | processing_engine_triggers | BASE TABLE |",
                 "| public       | system             | queries                    | BASE TABLE |",
+                "| public       | system             | table_summary              | BASE TABLE |",
                 "+--------------+--------------------+----------------------------+------------+",
             ],
             &batches
@@ -49,6 +49,7 @@ pub struct TestConfig {
     auth_token: Option<(String, String)>,
     host_id: Option<String>,
     plugin_dir: Option<String>,
+    args: Vec<String>,
 }
 
 impl TestConfig {
@@ -73,6 +74,16 @@ impl TestConfig {
         self.plugin_dir = Some(plugin_dir.into());
         self
     }
+
+    /// Pass further command line arguments to the spawned [`TestServer`]
+    pub fn with_args<I, S>(mut self, args: I) -> Self
+    where
+        I: IntoIterator<Item = S>,
+        S: Into<String>,
+    {
+        self.args.extend(args.into_iter().map(Into::into));
+        self
+    }
 }
 
 impl ConfigProvider for TestConfig {
@@ -94,6 +105,7 @@ impl ConfigProvider for TestConfig {
             "--object-store".to_string(),
             "memory".to_string(),
         ]);
+        args.extend(self.args.iter().cloned());
         args
     }
 
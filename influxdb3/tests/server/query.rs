@@ -177,17 +177,332 @@ async fn api_v3_query_sql_params() {
         let status = resp.status();
         let body = resp.text().await.unwrap();
 
-        // TODO - it would be nice if this was a 4xx error, because this is really
-        //   a user error; however, the underlying error that occurs when Logical
-        //   planning is DatafusionError::Internal, and is not so convenient to deal
-        //   with. This may get addressed in:
-        //
-        //   https://github.com/apache/arrow-datafusion/issues/9738
-        assert!(status.is_server_error());
-        assert_contains!(body, "No value found for placeholder with name $host");
+        // placeholders are checked before planning, so this is reported as a user error:
+        assert_eq!(StatusCode::BAD_REQUEST, status);
+        assert_contains!(
+            body,
+            "invalid value for parameter 'host': no value was given for it"
+        );
+    }
+}
+
+#[tokio::test]
+async fn api_v3_query_sql_column_casts() {
+    let server = TestServer::spawn().await;
+
+    server
+        .write_lp_to_db("foo", "cpu,host=a count=3i 1", Precision::Second)
+        .await
+        .unwrap();
+
+    let client = reqwest::Client::new();
+    let url = format!("{base}/api/v3/query_sql", base = server.client_addr());
+    let query = "SELECT host, count FROM cpu";
+
+    // Use a POST request
+    {
+        let resp = client
+            .post(&url)
+            .json(&json!({
+                "db": "foo",
+                "q": query,
+                "column_casts": {"count": "Utf8"},
+                "format": "json",
+            }))
+            .send()
+            .await
+            .unwrap()
+            .json::<Value>()
+            .await
+            .unwrap();
+        assert_eq!(json!([{"host": "a", "count": "3"}]), resp);
+    }
+
+    // Use a GET request
+    {
+        let column_casts = serde_json::to_string(&json!({"count": "Utf8"})).unwrap();
+        let resp = client
+            .get(&url)
+            .query(&[
+                ("db", "foo"),
+                ("q", query),
+                ("column_casts", column_casts.as_str()),
+                ("format", "json"),
+            ])
+            .send()
+            .await
+            .unwrap()
+            .json::<Value>()
+            .await
+            .unwrap();
+        assert_eq!(json!([{"host": "a", "count": "3"}]), resp);
+    }
+
+    // a type that is not known is rejected, naming the column:
+    {
+        let resp = client
+            .post(&url)
+            .json(&json!({
+                "db": "foo",
+                "q": query,
+                "column_casts": {"count": "NotAType"},
+            }))
+            .send()
+            .await
+            .unwrap();
+        assert_eq!(StatusCode::BAD_REQUEST, resp.status());
+        assert_contains!(resp.text().await.unwrap(), "column 'count'");
+    }
+}
+
+#[tokio::test]
+async fn api_v3_query_sql_options() {
+    let server = TestServer::spawn().await;
+
+    // the write is held in the in-memory buffer, and is not persisted by the test:
+    server
+        .write_lp_to_db("foo", "cpu,host=a count=3i 1", Precision::Second)
+        .await
+        .unwrap();
+
+    let client = reqwest::Client::new();
+    let url = format!("{base}/api/v3/query_sql", base = server.client_addr());
+    let query = "SELECT host, count FROM cpu";
+
+    // Use a POST request
+    for (storage, expected) in [
+        ("read_buffer", json!([{"host": "a", "count": 3}])),
+        ("object_store", json!([])),
+    ] {
+        let resp = client
+            .post(&url)
+            .json(&json!({
+                "db": "foo",
+                "q": query,
+                "options": {"storage": storage},
+                "format": "json",
+            }))
+            .send()
+            .await
+            .unwrap()
+            .json::<Value>()
+            .await
+            .unwrap();
+        assert_eq!(expected, resp, "storage: {storage}");
+    }
+
+    // the budget of requests to the object store is not spent by reading the buffer:
+    {
+        let resp = client
+            .post(&url)
+            .json(&json!({
+                "db": "foo",
+                "q": query,
+                "options": {"storage": "read_buffer", "max_storage_requests": 0},
+            }))
+            .send()
+            .await
+            .unwrap();
+        assert_eq!(StatusCode::OK, resp.status());
+        assert_eq!(
+            json!([{"host": "a", "count": 3}]),
+            resp.json::<Value>().await.unwrap()
+        );
+    }
+
+    // Use a GET request
+    {
+        let options = serde_json::to_string(&json!({"storage": "object_store"})).unwrap();
+        let resp = client
+            .get(&url)
+            .query(&[
+                ("db", "foo"),
+                ("q", query),
+                ("options", options.as_str()),
+                ("format", "json"),
+            ])
+            .send()
+            .await
+            .unwrap()
+            .json::<Value>()
+            .await
+            .unwrap();
+        assert_eq!(json!([]), resp);
+    }
+
+    // an invalid option is rejected, naming the option:
+    {
+        let resp = client
+            .post(&url)
+            .json(&json!({
+                "db": "foo",
+                "q": query,
+                "options": {"storage": "disk"},
+            }))
+            .send()
+            .await
+            .unwrap();
+        assert_eq!(StatusCode::BAD_REQUEST, resp.status());
+        assert_contains!(resp.text().await.unwrap(), "query option 'storage'");
     }
 }
 
+#[tokio::test]
+async fn api_v3_query_sql_allow_cross_joins() {
+    let server = TestServer::configure()
+        .with_args(["--query-cross-join-row-limit", "1"])
+        .spawn()
+        .await;
+
+    server
+        .write_lp_to_db(
+            "foo",
+            "cpu,host=a usage=1 1\n\
+            cpu,host=b usage=2 1",
+            Precision::Second,
+        )
+        .await
+        .unwrap();
+
+    let client = reqwest::Client::new();
+    let url = format!("{base}/api/v3/query_sql", base = server.client_addr());
+    let query = |options: Value| {
+        client
+            .post(&url)
+            .json(&json!({
+                "db": "foo",
+                "q": "SELECT a.host, b.host AS other FROM cpu a CROSS JOIN cpu b",
+                "options": options,
+            }))
+            .send()
+    };
+
+    // the join exceeds the server's limit, unless the query allows cross joins:
+    let resp = query(json!({})).await.unwrap();
+    assert_eq!(StatusCode::BAD_REQUEST, resp.status());
+    assert_contains!(resp.text().await.unwrap(), "without a join condition");
+
+    let resp = query(json!({"allow_cross_joins": true})).await.unwrap();
+    assert_eq!(StatusCode::OK, resp.status());
+    assert_eq!(4, resp.json::<Vec<Value>>().await.unwrap().len());
+}
+
+#[tokio::test]
+async fn api_v3_query_sql_allow_unbounded_time_range() {
+    let server = TestServer::configure()
+        .with_args(["--query-max-time-range", "1h"])
+        .spawn()
+        .await;
+
+    server
+        .write_lp_to_db("foo", "cpu,host=a usage=1 1", Precision::Second)
+        .await
+        .unwrap();
+
+    let client = reqwest::Client::new();
+    let url = format!("{base}/api/v3/query_sql", base = server.client_addr());
+    let query = |q: &'static str, options: Value| {
+        client
+            .post(&url)
+            .json(&json!({"db": "foo", "q": q, "options": options}))
+            .send()
+    };
+
+    // a narrow range is accepted, while a wide one is rejected unless the query lifts the limit:
+    let narrow = "SELECT host FROM cpu WHERE time >= 0 AND time < 60000000000";
+    let resp = query(narrow, json!({})).await.unwrap();
+    assert_eq!(StatusCode::OK, resp.status());
+
+    let wide = "SELECT host FROM cpu WHERE time >= 0";
+    let resp = query(wide, json!({})).await.unwrap();
+    assert_eq!(StatusCode::BAD_REQUEST, resp.status());
+    assert_contains!(resp.text().await.unwrap(), "exceeds the maximum time range");
+
+    let resp = query(wide, json!({"allow_unbounded_time_range": true}))
+        .await
+        .unwrap();
+    assert_eq!(StatusCode::OK, resp.status());
+    assert_eq!(json!([{"host": "a"}]), resp.json::<Value>().await.unwrap());
+}
+
+#[tokio::test]
+async fn api_v3_query_sql_log() {
+    let server = TestServer::spawn().await;
+
+    server
+        .write_lp_to_db("foo", "cpu,host=a usage=1 1", Precision::Second)
+        .await
+        .unwrap();
+
+    let client = reqwest::Client::new();
+    let url = format!("{base}/api/v3/query_sql", base = server.client_addr());
+    let query = |q: &'static str, options: Value| {
+        client
+            .post(&url)
+            .json(&json!({"db": "foo", "q": q, "options": options}))
+            .send()
+    };
+
+    // both queries run, but only the one that is logged shows up in the query log:
+    for (q, log) in [
+        ("SELECT host AS logged FROM cpu", true),
+        ("SELECT host AS unlogged FROM cpu", false),
+    ] {
+        let resp = query(q, json!({"log": log})).await.unwrap();
+        assert_eq!(StatusCode::OK, resp.status());
+        assert_eq!(1, resp.json::<Vec<Value>>().await.unwrap().len());
+    }
+    let resp = query(
+        "SELECT query_text FROM system.queries WHERE query_text LIKE 'SELECT host AS %'",
+        json!({}),
+    )
+    .await
+    .unwrap()
+    .json::<Value>()
+    .await
+    .unwrap();
+    assert_eq!(
+        json!([{"query_text": "SELECT host AS logged FROM cpu"}]),
+        resp
+    );
+}
+
+#[tokio::test]
+async fn api_v3_query_sql_table_rewrites() {
+    let server = TestServer::spawn().await;
+
+    server
+        .write_lp_to_db(
+            "foo",
+            "cpu,host=a usage=1 1\n\
+            cpu_v2,host=a usage=2 1",
+            Precision::Second,
+        )
+        .await
+        .unwrap();
+
+    let client = reqwest::Client::new();
+    let url = format!("{base}/api/v3/query_sql", base = server.client_addr());
+    let query = |table_rewrites: Value| {
+        client
+            .post(&url)
+            .json(&json!({
+                "db": "foo",
+                "q": "SELECT usage FROM cpu",
+                "options": {"table_rewrites": table_rewrites},
+            }))
+            .send()
+    };
+
+    let resp = query(json!({"cpu": "cpu_v2"})).await.unwrap();
+    assert_eq!(StatusCode::OK, resp.status());
+    assert_eq!(json!([{"usage": 2.0}]), resp.json::<Value>().await.unwrap());
+
+    // a rewrite to a table that does not exist fails the query:
+    let resp = query(json!({"cpu": "cpu_v3"})).await.unwrap();
+    assert_eq!(StatusCode::BAD_REQUEST, resp.status());
+}
+
 #[tokio::test]
 async fn api_v3_query_influxql() {
     let server = TestServer::spawn().await;
@@ -359,30 +674,30 @@ async fn api_v3_query_influxql() {
         TestCase {
             database: None,
             query: "SHOW RETENTION POLICIES",
-            expected: "+---------------+---------+----------+\n\
-                    | iox::database | name    | duration |\n\
-                    +---------------+---------+----------+\n\
-                    | bar           | autogen |          |\n\
-                    | foo           | autogen |          |\n\
-                    +---------------+---------+----------+",
+            expected: "+---------------+---------+----------+---------+-------+\n\
+                    | iox::database | name    | duration | default | error |\n\
+                    +---------------+---------+----------+---------+-------+\n\
+                    | bar           | autogen |          | true    |       |\n\
+                    | foo           | autogen |          | true    |       |\n\
+                    +---------------+---------+----------+---------+-------+",
         },
         TestCase {
             database: None,
             query: "SHOW RETENTION POLICIES ON foo",
-            expected: "+---------------+---------+----------+\n\
-                    | iox::database | name    | duration |\n\
-                    +---------------+---------+----------+\n\
-                    | foo           | autogen |          |\n\
-                    +---------------+---------+----------+",
+            expected: "+---------------+---------+----------+---------+-------+\n\
+                    | iox::database | name    | duration | default | error |\n\
+                    +---------------+---------+----------+---------+-------+\n\
+                    | foo           | autogen |          | true    |       |\n\
+                    +---------------+---------+----------+---------+-------+",
         },
         TestCase {
             database: Some("foo"),
             query: "SHOW RETENTION POLICIES",
-            expected: "+---------------+---------+----------+\n\
-                    | iox::database | name    | duration |\n\
-                    +---------------+---------+----------+\n\
-                    | foo           | autogen |          |\n\
-                    +---------------+---------+----------+",
+            expected: "+---------------+---------+----------+---------+-------+\n\
+                    | iox::database | name    | duration | default | error |\n\
+                    +---------------+---------+----------+---------+-------+\n\
+                    | foo           | autogen |          | true    |       |\n\
+                    +---------------+---------+----------+---------+-------+",
         },
     ];
 
@@ -410,6 +725,53 @@ struct TestCase<'a> {
     expected: &'a str,
 }
 
+#[tokio::test]
+async fn api_v3_query_influxql_epoch() {
+    let server = TestServer::spawn().await;
+
+    server
+        .write_lp_to_db("foo", "cpu,host=a usage=0.9 2", Precision::Second)
+        .await
+        .unwrap();
+
+    let client = reqwest::Client::new();
+    let url = format!("{base}/api/v3/query_influxql", base = server.client_addr());
+
+    for (epoch, time) in [("s", 2), ("ms", 2_000)] {
+        let resp = client
+            .post(&url)
+            .json(&json!({
+                "db": "foo",
+                "q": "SELECT usage FROM cpu",
+                "options": {"epoch": epoch},
+            }))
+            .send()
+            .await
+            .unwrap()
+            .json::<Value>()
+            .await
+            .unwrap();
+        assert_eq!(
+            json!([{"iox::measurement": "cpu", "time": time, "usage": 0.9}]),
+            resp,
+            "epoch: {epoch}"
+        );
+    }
+
+    // the v1 query API takes its own epoch parameter instead:
+    let resp = client
+        .get(format!("{base}/query", base = server.client_addr()))
+        .query(&[
+            ("db", "foo"),
+            ("q", "SELECT usage FROM cpu"),
+            ("options", r#"{"epoch": "s"}"#),
+        ])
+        .send()
+        .await
+        .unwrap();
+    assert_eq!(StatusCode::BAD_REQUEST, resp.status());
+}
+
 #[tokio::test]
 async fn api_v3_query_influxql_params() {
     let server = TestServer::spawn().await;
@@ -518,17 +880,10 @@ async fn api_v3_query_influxql_params() {
         let status = resp.status();
         let body = resp.text().await.unwrap();
 
-        // TODO - it would be nice if this was a 4xx error, because this is really
-        //   a user error; however, the underlying error that occurs when Logical
-        //   planning is DatafusionError::Internal, and is not so convenient to deal
-        //   with. This may get addressed in:
-        //
-        //   https://github.com/apache/arrow-datafusion/issues/9738
-        assert!(status.is_server_error());
+        assert_eq!(StatusCode::BAD_REQUEST, status);
         assert_contains!(
             body,
-            "Bind parameter '$host' was referenced in the InfluxQL \
-            statement but its value is undefined"
+            "invalid value for parameter 'host': no value was given for it"
         );
     }
 }
@@ -674,6 +1029,7 @@ async fn api_v3_query_json_format() {
                 {
                   "iox::database": "foo",
                   "name": "autogen",
+                  "default": true,
                 },
             ]),
         },
@@ -772,7 +1128,7 @@ async fn api_v3_query_jsonl_format() {
         TestCase {
             database: None,
             query: "SHOW RETENTION POLICIES",
-            expected: "{\"iox::database\":\"foo\",\"name\":\"autogen\"}\n".into(),
+            expected: "{\"iox::database\":\"foo\",\"name\":\"autogen\",\"default\":true}\n".into(),
         },
     ];
     for t in test_cases {
@@ -1583,6 +1939,70 @@ async fn api_v1_query_uri_and_body() {
     }
 }
 
+#[tokio::test]
+async fn api_v1_query_options() {
+    let server = TestServer::spawn().await;
+
+    // the write is held in the in-memory buffer, and is not persisted by the test:
+    server
+        .write_lp_to_db("foo", "cpu,host=a usage=0.9 1", Precision::Second)
+        .await
+        .unwrap();
+
+    let client = reqwest::Client::new();
+    let url = format!("{base}/query", base = server.client_addr());
+    let query = |options: Value| {
+        let options = options.to_string();
+        let client = &client;
+        let url = &url;
+        async move {
+            client
+                .get(url)
+                .query(&[
+                    ("db", "foo"),
+                    ("q", "SELECT time, host, usage FROM cpu"),
+                    ("options", options.as_str()),
+                ])
+                .send()
+                .await
+                .unwrap()
+        }
+    };
+
+    let resp = query(json!({"storage": "object_store"}))
+        .await
+        .json::<Value>()
+        .await
+        .unwrap();
+    assert_eq!(json!({"results":[{"statement_id":0}]}), resp);
+
+    let resp = query(json!({"storage": "read_buffer"}))
+        .await
+        .json::<Value>()
+        .await
+        .unwrap();
+    assert_eq!(
+        json!({
+          "results": [
+            {
+              "series": [
+                {
+                  "columns": ["time", "host", "usage"],
+                  "name": "cpu",
+                  "values": [["1970-01-01T00:00:01Z", "a", 0.9]]
+                }
+              ],
+              "statement_id": 0
+            }
+          ]
+        }),
+        resp
+    );
+
+    let resp = query(json!({"not_an_option": true})).await;
+    assert_eq!(StatusCode::BAD_REQUEST, resp.status());
+}
+
 #[tokio::test]
 async fn api_v3_query_sql_distinct_cache() {
     let server = TestServer::spawn().await;
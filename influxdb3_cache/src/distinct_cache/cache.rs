@@ -57,6 +57,9 @@ pub(crate) struct DistinctCache {
 struct DistinctCacheState {
     /// The current number of unique value combinations in the cache
     cardinality: usize,
+    /// The nanosecond timestamp since which the cache has seen every value written to its
+    /// columns, i.e., when it was created or when it last evicted entries that had not expired
+    complete_since_ns: i64,
 }
 
 /// Arguments to create a new [`DistinctCache`]
@@ -163,10 +166,13 @@ impl DistinctCache {
             builder.push(Arc::new(Field::new(col.name.as_ref(), data_type, false)));
         }
         Ok(Self {
-            time_provider,
             max_cardinality: max_cardinality.into(),
             max_age: max_age.into(),
-            state: DistinctCacheState::default(),
+            state: DistinctCacheState {
+                cardinality: 0,
+                complete_since_ns: time_provider.now().timestamp_nanos(),
+            },
+            time_provider,
             schema: Arc::new(builder.finish()),
             column_ids,
             data: Node::default(),
@@ -277,7 +283,10 @@ impl DistinctCache {
     /// This first prunes entries that are older than the `max_age` of the cache. If the cardinality
     /// of the cache is still over its `max_cardinality`, it will do another pass to bring the cache
     /// size down.
-    pub(crate) fn prune(&mut self) {
+    ///
+    /// Returns the number of entries that were removed from the cache.
+    pub(crate) fn prune(&mut self) -> usize {
+        let cardinality = self.state.cardinality;
         let before_time_ns = self.expired_time_ns();
         let _ = self.data.remove_before(before_time_ns);
         self.state.cardinality = self.data.cardinality();
@@ -285,12 +294,24 @@ impl DistinctCache {
             let n_to_remove = self.state.cardinality - self.max_cardinality;
             self.data.remove_n_oldest(n_to_remove);
             self.state.cardinality = self.data.cardinality();
+            self.state.complete_since_ns = self.time_provider.now().timestamp_nanos();
         }
+        cardinality.saturating_sub(self.state.cardinality)
+    }
+
+    /// Whether the cache holds every distinct value combination written to its columns within its
+    /// `max_age`
+    ///
+    /// This is not the case until the cache has been populated for its `max_age`, since values
+    /// written before it was created are not in the cache, nor until entries that were evicted to
+    /// keep the cache within its `max_cardinality` would have expired.
+    pub(crate) fn is_complete(&self) -> bool {
+        self.state.complete_since_ns <= self.expired_time_ns()
     }
 
     /// Get the nanosecond timestamp as an `i64`, before which, entries that have not been seen
     /// since are considered expired.
-    fn expired_time_ns(&self) -> i64 {
+    pub(crate) fn expired_time_ns(&self) -> i64 {
         self.time_provider
             .now()
             .checked_sub(self.max_age)
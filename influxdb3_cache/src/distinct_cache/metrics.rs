@@ -0,0 +1,53 @@
+use std::borrow::Cow;
+
+use metric::{Metric, Registry, U64Counter};
+
+#[derive(Debug)]
+pub(super) struct DistinctCacheMetrics {
+    access: Metric<U64Counter>,
+    evictions: Metric<U64Counter>,
+}
+
+pub(super) const DISTINCT_CACHE_ACCESS_NAME: &str = "influxdb3_distinct_cache_access";
+pub(super) const DISTINCT_CACHE_EVICTIONS_NAME: &str = "influxdb3_distinct_cache_evictions";
+
+impl DistinctCacheMetrics {
+    pub(super) fn new(metric_registry: &Registry) -> Self {
+        let access = metric_registry.register_metric::<U64Counter>(
+            DISTINCT_CACHE_ACCESS_NAME,
+            "track queries to the distinct value cache, and whether they produced any values",
+        );
+        let evictions = metric_registry.register_metric::<U64Counter>(
+            DISTINCT_CACHE_EVICTIONS_NAME,
+            "track number of values evicted from the distinct value cache",
+        );
+        Self { access, evictions }
+    }
+
+    pub(super) fn record_cache_hit(&self, db: &str, table: &str) {
+        self.record_access(db, table, "hit");
+    }
+
+    pub(super) fn record_cache_miss(&self, db: &str, table: &str) {
+        self.record_access(db, table, "miss");
+    }
+
+    fn record_access(&self, db: &str, table: &str, status: &'static str) {
+        self.access
+            .recorder([
+                ("db", Cow::from(db.to_string())),
+                ("table", Cow::from(table.to_string())),
+                ("status", Cow::from(status)),
+            ])
+            .inc(1);
+    }
+
+    pub(super) fn record_evictions(&self, db: &str, table: &str, n_values: u64) {
+        self.evictions
+            .recorder([
+                ("db", Cow::from(db.to_string())),
+                ("table", Cow::from(table.to_string())),
+            ])
+            .inc(n_values);
+    }
+}
@@ -2,10 +2,13 @@
 
 mod cache;
 pub use cache::{CacheError, CreateDistinctCacheArgs, MaxAge, MaxCardinality};
+mod metrics;
 mod provider;
 pub use provider::{DistinctCacheProvider, ProviderError};
 mod table_function;
+pub use table_function::DistinctCacheFallback;
 pub use table_function::DistinctCacheFunction;
+pub use table_function::DISTINCT_CACHE_COMPLETE_METADATA_KEY;
 pub use table_function::DISTINCT_CACHE_UDTF_NAME;
 
 #[cfg(test)]
@@ -13,13 +16,16 @@ mod tests {
     use arrow::array::AsArray;
     use datafusion::{assert_batches_eq, assert_batches_sorted_eq, prelude::SessionContext};
     use indexmap::IndexMap;
+    use influxdb3_catalog::catalog::TableDefinition;
     use influxdb3_id::ColumnId;
     use iox_time::{MockProvider, Time, TimeProvider};
+    use metric::{Attributes, Metric, Registry, U64Counter};
     use std::{sync::Arc, time::Duration};
 
     use crate::{
         distinct_cache::{
             cache::{CreateDistinctCacheArgs, DistinctCache, MaxAge, MaxCardinality, Predicate},
+            metrics::{DISTINCT_CACHE_ACCESS_NAME, DISTINCT_CACHE_EVICTIONS_NAME},
             DistinctCacheFunction, DistinctCacheProvider, DISTINCT_CACHE_UDTF_NAME,
         },
         test_helpers::TestWriter,
@@ -838,4 +844,184 @@ mod tests {
             );
         }
     }
+
+    #[tokio::test]
+    async fn distinct_cache_udtf_with_removed_column() {
+        let writer = TestWriter::new();
+        let _ = writer.write_lp_to_write_batch("cpu,region=us-east,host=a usage=100", 0);
+
+        // create a cache on tag columns 'region' and 'host':
+        let db_schema = writer.db_schema();
+        let table_def = db_schema.table_definition("cpu").unwrap();
+        let column_ids: Vec<ColumnId> = ["region", "host"]
+            .into_iter()
+            .map(|name| table_def.column_name_to_id_unchecked(name))
+            .collect();
+        let time_provider = Arc::new(MockProvider::new(Time::from_timestamp_nanos(0)));
+        let distinct_provider =
+            DistinctCacheProvider::new_from_catalog(time_provider, writer.catalog()).unwrap();
+        distinct_provider
+            .create_cache(
+                db_schema.id,
+                None,
+                CreateDistinctCacheArgs {
+                    table_def: Arc::clone(&table_def),
+                    max_cardinality: MaxCardinality::default(),
+                    max_age: MaxAge::default(),
+                    column_ids,
+                },
+            )
+            .unwrap();
+
+        let ctx = SessionContext::new();
+        ctx.register_udtf(
+            DISTINCT_CACHE_UDTF_NAME,
+            Arc::new(DistinctCacheFunction::new(
+                db_schema.id,
+                Arc::clone(&distinct_provider),
+            )),
+        );
+        ctx.sql("SELECT * FROM distinct_cache('cpu')")
+            .await
+            .expect("cache columns are all in the table");
+
+        // replace the table in the catalog with one that no longer has the 'region' tag:
+        let region_id = table_def.column_name_to_id_unchecked("region");
+        let columns = table_def
+            .columns
+            .values()
+            .filter(|c| c.id != region_id)
+            .map(|c| (c.id, Arc::clone(&c.name), c.data_type))
+            .collect();
+        let series_key = table_def
+            .series_key
+            .iter()
+            .copied()
+            .filter(|id| *id != region_id)
+            .collect();
+        let new_table_def = TableDefinition::new(
+            table_def.table_id,
+            Arc::clone(&table_def.table_name),
+            columns,
+            series_key,
+        )
+        .unwrap();
+        let mut new_db_schema = (*db_schema).clone();
+        new_db_schema
+            .tables
+            .insert(table_def.table_id, Arc::new(new_table_def));
+        writer.catalog().insert_database(new_db_schema);
+
+        let error = ctx
+            .sql("SELECT * FROM distinct_cache('cpu')")
+            .await
+            .expect_err("the cache uses a column that is no longer in the table");
+        assert!(
+            error
+                .to_string()
+                .contains("uses columns that are no longer in the table: region"),
+            "unexpected error: {error}"
+        );
+    }
+    #[tokio::test]
+    async fn metrics() {
+        let writer = TestWriter::new();
+        let _ = writer.write_lp_to_write_batch("cpu,region=us-east,host=a usage=100", 0);
+
+        let db_schema = writer.db_schema();
+        let table_def = db_schema.table_definition("cpu").unwrap();
+        let column_ids: Vec<ColumnId> = ["region", "host"]
+            .into_iter()
+            .map(|name| table_def.column_name_to_id_unchecked(name))
+            .collect();
+        let time_provider = Arc::new(MockProvider::new(Time::from_timestamp_nanos(0)));
+        let distinct_provider = DistinctCacheProvider::new_from_catalog(
+            Arc::clone(&time_provider) as _,
+            writer.catalog(),
+        )
+        .unwrap();
+        let metric_registry = Registry::new();
+        distinct_provider.register_metrics(&metric_registry);
+        distinct_provider
+            .create_cache(
+                db_schema.id,
+                None,
+                CreateDistinctCacheArgs {
+                    table_def,
+                    max_cardinality: MaxCardinality::default(),
+                    max_age: MaxAge::from(Duration::from_secs(1)),
+                    column_ids,
+                },
+            )
+            .unwrap();
+
+        let write_batch = writer.write_lp_to_write_batch(
+            "\
+            cpu,region=us-east,host=a usage=100\n\
+            cpu,region=us-east,host=b usage=100\n\
+            cpu,region=us-west,host=c usage=100\n\
+            ",
+            0,
+        );
+        let wal_contents = influxdb3_wal::create::wal_contents(
+            (0, 1, 0),
+            [influxdb3_wal::create::write_batch_op(write_batch)],
+        );
+        distinct_provider.write_wal_contents_to_cache(&wal_contents);
+
+        let ctx = SessionContext::new();
+        ctx.register_udtf(
+            DISTINCT_CACHE_UDTF_NAME,
+            Arc::new(DistinctCacheFunction::new(
+                db_schema.id,
+                Arc::clone(&distinct_provider),
+            )),
+        );
+
+        // two queries produce values from the cache, and one does not:
+        for sql in [
+            "SELECT * FROM distinct_cache('cpu')",
+            "SELECT * FROM distinct_cache('cpu') WHERE region = 'us-west'",
+            "SELECT * FROM distinct_cache('cpu') WHERE region = 'eu-west'",
+        ] {
+            ctx.sql(sql).await.unwrap().collect().await.unwrap();
+        }
+
+        let access = metric_registry
+            .get_instrument::<Metric<U64Counter>>(DISTINCT_CACHE_ACCESS_NAME)
+            .unwrap();
+        let fetch_access = |status: &'static str| {
+            access
+                .get_observer(&Attributes::from(&[
+                    ("db", "test_db"),
+                    ("table", "cpu"),
+                    ("status", status),
+                ]))
+                .unwrap()
+                .fetch()
+        };
+        assert_eq!(2, fetch_access("hit"));
+        assert_eq!(1, fetch_access("miss"));
+
+        // once the max age has passed, all three values are evicted:
+        time_provider.set(Time::from_timestamp(2, 0).unwrap());
+        distinct_provider.evict_cache_entries();
+        let evictions = metric_registry
+            .get_instrument::<Metric<U64Counter>>(DISTINCT_CACHE_EVICTIONS_NAME)
+            .unwrap()
+            .get_observer(&Attributes::from(&[("db", "test_db"), ("table", "cpu")]))
+            .unwrap()
+            .fetch();
+        assert_eq!(3, evictions);
+
+        // the cache is now empty, so querying it is a miss:
+        ctx.sql("SELECT * FROM distinct_cache('cpu')")
+            .await
+            .unwrap()
+            .collect()
+            .await
+            .unwrap();
+        assert_eq!(2, fetch_access("hit"));
+        assert_eq!(2, fetch_access("miss"));
+    }
 }
@@ -1,4 +1,8 @@
-use std::{collections::HashMap, sync::Arc, time::Duration};
+use std::{
+    collections::HashMap,
+    sync::{Arc, OnceLock},
+    time::Duration,
+};
 
 use anyhow::Context;
 use arrow::datatypes::SchemaRef;
@@ -6,12 +10,14 @@ use influxdb3_catalog::catalog::{Catalog, TableDefinition};
 use influxdb3_id::{DbId, TableId};
 use influxdb3_wal::{DistinctCacheDefinition, WalContents, WalOp};
 use iox_time::TimeProvider;
+use metric::Registry;
 use parking_lot::RwLock;
 
 use crate::distinct_cache::cache::{MaxAge, MaxCardinality};
 
 use super::{
     cache::{CreateDistinctCacheArgs, DistinctCache},
+    metrics::DistinctCacheMetrics,
     CacheError,
 };
 
@@ -36,6 +42,9 @@ pub struct DistinctCacheProvider {
     pub(crate) time_provider: Arc<dyn TimeProvider>,
     pub(crate) catalog: Arc<Catalog>,
     pub(crate) cache_map: CacheMap,
+    /// Set once the provider's metrics are registered, see
+    /// [`DistinctCacheProvider::register_metrics`]
+    metrics: OnceLock<DistinctCacheMetrics>,
 }
 
 impl DistinctCacheProvider {
@@ -49,6 +58,7 @@ impl DistinctCacheProvider {
             time_provider,
             catalog: Arc::clone(&catalog),
             cache_map: Default::default(),
+            metrics: OnceLock::new(),
         });
         for db_schema in catalog.list_db_schema() {
             for table_def in db_schema.tables() {
@@ -94,6 +104,45 @@ impl DistinctCacheProvider {
         Ok(provider)
     }
 
+    /// Register counters for the hits, misses, and evictions of the caches in this provider with
+    /// the given [`Registry`]
+    ///
+    /// Nothing is recorded until this is called, and only the first registry it is called with
+    /// is used.
+    pub fn register_metrics(&self, metric_registry: &Registry) {
+        self.metrics
+            .get_or_init(|| DistinctCacheMetrics::new(metric_registry));
+    }
+
+    /// Record a query to a cache on the given table, which is a hit if it produced any values
+    pub(crate) fn record_access(&self, db_id: &DbId, table_name: &str, hit: bool) {
+        let Some(metrics) = self.metrics.get() else {
+            return;
+        };
+        let Some(db_schema) = self.catalog.db_schema_by_id(db_id) else {
+            return;
+        };
+        if hit {
+            metrics.record_cache_hit(&db_schema.name, table_name);
+        } else {
+            metrics.record_cache_miss(&db_schema.name, table_name);
+        }
+    }
+
+    /// Record the values evicted from the caches on the given table
+    fn record_evictions(&self, db_id: &DbId, table_id: &TableId, n_values: usize) {
+        let Some(metrics) = self.metrics.get().filter(|_| n_values > 0) else {
+            return;
+        };
+        let Some(db_schema) = self.catalog.db_schema_by_id(db_id) else {
+            return;
+        };
+        let Some(table_name) = db_schema.table_id_to_name(table_id) else {
+            return;
+        };
+        metrics.record_evictions(&db_schema.name, &table_name, n_values as u64);
+    }
+
     /// Get a particular cache's name and arrow schema
     ///
     /// This is used for the implementation of DataFusion's `TableFunctionImpl` and
@@ -126,6 +175,22 @@ impl DistinctCacheProvider {
             })
     }
 
+    /// Get whether a particular cache holds every distinct value within its `max_age`, along
+    /// with the nanosecond timestamp before which values have expired from the cache
+    pub(crate) fn get_cache_completeness(
+        &self,
+        db_id: DbId,
+        table_id: TableId,
+        cache_name: &str,
+    ) -> Option<(bool, i64)> {
+        self.cache_map
+            .read()
+            .get(&db_id)
+            .and_then(|db| db.get(&table_id))
+            .and_then(|table| table.get(cache_name))
+            .map(|cache| (cache.is_complete(), cache.expired_time_ns()))
+    }
+
     /// Get a list of [`DistinctCacheDefinition`]s for the given database
     pub fn get_cache_definitions_for_db(&self, db_id: &DbId) -> Vec<DistinctCacheDefinition> {
         let db_schema = self
@@ -341,9 +406,13 @@ impl DistinctCacheProvider {
     /// Run eviction across all caches in the provider.
     pub fn evict_cache_entries(&self) {
         let mut lock = self.cache_map.write();
-        lock.iter_mut().for_each(|(_, db_caches)| {
-            db_caches.iter_mut().for_each(|(_, table_caches)| {
-                table_caches.iter_mut().for_each(|(_, cache)| cache.prune())
+        lock.iter_mut().for_each(|(db_id, db_caches)| {
+            db_caches.iter_mut().for_each(|(table_id, table_caches)| {
+                let n_values = table_caches
+                    .iter_mut()
+                    .map(|(_, cache)| cache.prune())
+                    .sum();
+                self.record_evictions(db_id, table_id, n_values);
             })
         });
     }
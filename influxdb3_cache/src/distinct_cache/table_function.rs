@@ -1,23 +1,26 @@
-use std::{any::Any, sync::Arc};
+use std::{any::Any, collections::HashMap, fmt::Debug, sync::Arc};
 
-use arrow::{array::RecordBatch, datatypes::SchemaRef};
+use arrow::{
+    array::RecordBatch,
+    datatypes::{DataType, SchemaRef},
+};
 use async_trait::async_trait;
 use datafusion::{
     catalog::{Session, TableProvider},
     common::{internal_err, plan_err, DFSchema, Result},
-    datasource::{function::TableFunctionImpl, TableType},
+    datasource::{function::TableFunctionImpl, provider_as_source, TableType, ViewTable},
     execution::context::ExecutionProps,
-    logical_expr::TableProviderFilterPushDown,
+    logical_expr::{cast, LogicalPlanBuilder, TableProviderFilterPushDown},
     physical_expr::{
         create_physical_expr,
         utils::{Guarantee, LiteralGuarantee},
     },
     physical_plan::{memory::MemoryExec, DisplayAs, DisplayFormatType, ExecutionPlan},
-    prelude::Expr,
+    prelude::{ident, lit, Expr},
     scalar::ScalarValue,
 };
 use indexmap::IndexMap;
-use influxdb3_catalog::catalog::TableDefinition;
+use influxdb3_catalog::catalog::{TableDefinition, TIME_COLUMN_NAME};
 use influxdb3_id::{ColumnId, DbId};
 
 use super::{cache::Predicate, DistinctCacheProvider};
@@ -25,6 +28,18 @@ use super::{cache::Predicate, DistinctCacheProvider};
 /// The name used to call the distinct value cache in SQL queries
 pub const DISTINCT_CACHE_UDTF_NAME: &str = "distinct_cache";
 
+/// The key of the metadata on the schema output by the [`DistinctCacheFunction`] that says
+/// whether the output holds every distinct value within the cache's `max_age`, as `true` or
+/// `false`
+pub const DISTINCT_CACHE_COMPLETE_METADATA_KEY: &str = "iox::distinct_cache::complete";
+
+/// Provides the tables that distinct value caches are on, for the [`DistinctCacheFunction`] to
+/// scan in place of a cache that may not hold every distinct value within its `max_age`
+pub trait DistinctCacheFallback: Debug + Send + Sync {
+    /// Get the [`TableProvider`] for the named table, if it exists
+    fn table_provider(&self, table_name: &str) -> Result<Option<Arc<dyn TableProvider>>>;
+}
+
 /// Implementor of the [`TableProvider`] trait that is produced a call to the [`DistinctCacheFunction`]
 #[derive(Debug)]
 struct DistinctCacheFunctionProvider {
@@ -97,6 +112,12 @@ impl TableProvider for DistinctCacheFunctionProvider {
         } else {
             (vec![], None)
         };
+        drop(read);
+        self.provider.record_access(
+            &self.db_id,
+            &self.table_def.table_name,
+            batches.iter().any(|batch| batch.num_rows() > 0),
+        );
 
         let mut distinct_exec = DistinctCacheExec::try_new(
             predicates,
@@ -220,11 +241,28 @@ fn convert_filter_exprs(
 pub struct DistinctCacheFunction {
     db_id: DbId,
     provider: Arc<DistinctCacheProvider>,
+    /// Scans the table in place of a cache that may be incomplete, if set
+    fallback: Option<Arc<dyn DistinctCacheFallback>>,
 }
 
 impl DistinctCacheFunction {
     pub fn new(db_id: DbId, provider: Arc<DistinctCacheProvider>) -> Self {
-        Self { db_id, provider }
+        Self {
+            db_id,
+            provider,
+            fallback: None,
+        }
+    }
+
+    /// Answer calls on a cache that may not hold every distinct value within its `max_age` by
+    /// scanning the table from the `fallback` for its distinct values, rather than with the
+    /// contents of the cache
+    ///
+    /// Without a fallback, the contents of the cache are output regardless, and whether they are
+    /// complete is given by the [`DISTINCT_CACHE_COMPLETE_METADATA_KEY`] of the output schema.
+    pub fn with_fallback(mut self, fallback: Arc<dyn DistinctCacheFallback>) -> Self {
+        self.fallback = Some(fallback);
+        self
     }
 }
 
@@ -256,6 +294,40 @@ impl TableFunctionImpl for DistinctCacheFunction {
         ) else {
             return plan_err!("could not find distinct value cache for the given arguments");
         };
+        // the cache's columns may no longer be in the table if its schema has changed since the
+        // cache was created, in which case its contents would be inconsistent with the table:
+        let missing = schema
+            .fields()
+            .iter()
+            .filter(|field| table_def.column_name_to_id(field.name().as_str()).is_none())
+            .map(|field| field.name().as_str())
+            .collect::<Vec<_>>();
+        if !missing.is_empty() {
+            return plan_err!(
+                "distinct value cache ({cache_name}) on table ({table_name}) uses columns that are \
+                no longer in the table: {}, the cache must be deleted and re-created",
+                missing.join(", ")
+            );
+        }
+        let Some((complete, expired_time_ns)) =
+            self.provider
+                .get_cache_completeness(self.db_id, table_def.table_id, &cache_name)
+        else {
+            return plan_err!("could not find distinct value cache for the given arguments");
+        };
+        if let (false, Some(fallback)) = (complete, &self.fallback) {
+            let Some(table) = fallback.table_provider(table_name)? else {
+                return plan_err!("provided table name ({}) is invalid", table_name);
+            };
+            // the values are read from the table instead of the incomplete cache:
+            self.provider
+                .record_access(&self.db_id, &table_def.table_name, false);
+            return scan_distinct_values(table, table_name, &schema, expired_time_ns);
+        }
+        let schema = Arc::new(schema.as_ref().clone().with_metadata(HashMap::from([(
+            DISTINCT_CACHE_COMPLETE_METADATA_KEY.to_string(),
+            complete.to_string(),
+        )])));
         Ok(Arc::new(DistinctCacheFunctionProvider {
             schema,
             provider: Arc::clone(&self.provider),
@@ -266,6 +338,45 @@ impl TableFunctionImpl for DistinctCacheFunction {
     }
 }
 
+/// Produce the distinct values of the cache's columns in the `table` that the cache would hold
+/// if it were complete, i.e., those of rows with a time after `expired_time_ns`, in the order in
+/// which the cache outputs them
+fn scan_distinct_values(
+    table: Arc<dyn TableProvider>,
+    table_name: &str,
+    cache_schema: &SchemaRef,
+    expired_time_ns: i64,
+) -> Result<Arc<dyn TableProvider>> {
+    let columns = cache_schema
+        .fields()
+        .iter()
+        .map(|field| ident(field.name()))
+        .collect::<Vec<_>>();
+    // the cache only holds rows that have a value for each of its columns:
+    let predicate = columns.iter().cloned().map(Expr::is_not_null).fold(
+        ident(TIME_COLUMN_NAME).gt(lit(ScalarValue::TimestampNanosecond(
+            Some(expired_time_ns),
+            None,
+        ))),
+        Expr::and,
+    );
+    // tags are dictionary encoded, so are cast through strings to the types of the cache:
+    let casts = cache_schema.fields().iter().map(|field| {
+        cast(
+            cast(ident(field.name()), DataType::Utf8),
+            field.data_type().clone(),
+        )
+        .alias(field.name())
+    });
+    let plan = LogicalPlanBuilder::scan(table_name, provider_as_source(table), None)?
+        .filter(predicate)?
+        .aggregate(columns.clone(), Vec::<Expr>::new())?
+        .project(casts)?
+        .sort(columns.into_iter().map(|column| column.sort(true, false)))?
+        .build()?;
+    Ok(Arc::new(ViewTable::try_new(plan, None)?))
+}
+
 /// Custom implementor of the [`ExecutionPlan`] trait for use by the distinct value cache
 ///
 /// Wraps a [`MemoryExec`] from DataFusion, and mostly re-uses that. The special functionality
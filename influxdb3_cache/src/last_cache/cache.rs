@@ -468,9 +468,11 @@ impl LastCache {
             .collect()
     }
 
-    /// Remove expired values from the internal cache state
-    pub(crate) fn remove_expired(&mut self) {
+    /// Remove expired values from the internal cache state, returning the number of rows removed
+    pub(crate) fn remove_expired(&mut self) -> usize {
+        let n_rows = self.state.n_rows();
         self.state.remove_expired();
+        n_rows - self.state.n_rows()
     }
 
     /// Convert the `LastCache` into a `LastCacheDefinition`
@@ -686,6 +688,16 @@ impl LastCacheState {
         }
     }
 
+    /// Get the number of rows held in the stores nested within this [`LastCacheState`], including
+    /// those that have expired but not yet been removed
+    fn n_rows(&self) -> usize {
+        match self {
+            LastCacheState::Key(k) => k.value_map.values().map(|s| s.n_rows()).sum(),
+            LastCacheState::Store(s) => s.instants.len(),
+            LastCacheState::Init => 0,
+        }
+    }
+
     /// Remove expired values from this [`LastCacheState`]
     fn remove_expired(&mut self) -> bool {
         match self {
@@ -0,0 +1,53 @@
+use std::borrow::Cow;
+
+use metric::{Metric, Registry, U64Counter};
+
+#[derive(Debug)]
+pub(super) struct LastCacheMetrics {
+    access: Metric<U64Counter>,
+    evictions: Metric<U64Counter>,
+}
+
+pub(super) const LAST_CACHE_ACCESS_NAME: &str = "influxdb3_last_cache_access";
+pub(super) const LAST_CACHE_EVICTIONS_NAME: &str = "influxdb3_last_cache_evictions";
+
+impl LastCacheMetrics {
+    pub(super) fn new(metric_registry: &Registry) -> Self {
+        let access = metric_registry.register_metric::<U64Counter>(
+            LAST_CACHE_ACCESS_NAME,
+            "track queries to the last cache, and whether they produced any rows",
+        );
+        let evictions = metric_registry.register_metric::<U64Counter>(
+            LAST_CACHE_EVICTIONS_NAME,
+            "track number of rows evicted from the last cache once they expired",
+        );
+        Self { access, evictions }
+    }
+
+    pub(super) fn record_cache_hit(&self, db: &str, table: &str) {
+        self.record_access(db, table, "hit");
+    }
+
+    pub(super) fn record_cache_miss(&self, db: &str, table: &str) {
+        self.record_access(db, table, "miss");
+    }
+
+    fn record_access(&self, db: &str, table: &str, status: &'static str) {
+        self.access
+            .recorder([
+                ("db", Cow::from(db.to_string())),
+                ("table", Cow::from(table.to_string())),
+                ("status", Cow::from(status)),
+            ])
+            .inc(1);
+    }
+
+    pub(super) fn record_evictions(&self, db: &str, table: &str, n_rows: u64) {
+        self.evictions
+            .recorder([
+                ("db", Cow::from(db.to_string())),
+                ("table", Cow::from(table.to_string())),
+            ])
+            .inc(n_rows);
+    }
+}
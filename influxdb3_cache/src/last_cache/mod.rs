@@ -4,6 +4,7 @@ use influxdb3_id::ColumnId;
 
 mod cache;
 pub use cache::{CreateLastCacheArgs, LastCacheTtl};
+mod metrics;
 mod provider;
 pub use provider::LastCacheProvider;
 mod table_function;
@@ -52,6 +53,7 @@ mod tests {
     use influxdb3_catalog::catalog::{Catalog, DatabaseSchema, TableDefinition};
     use influxdb3_id::{ColumnId, DbId, SerdeVecMap, TableId};
     use influxdb3_wal::{LastCacheDefinition, LastCacheSize};
+    use metric::{Attributes, Metric, Registry, U64Counter};
 
     use crate::{
         last_cache::{
@@ -59,6 +61,7 @@ mod tests {
                 KeyValue, LastCache, LastCacheKeyColumnsArg, LastCacheValueColumnsArg, Predicate,
                 DEFAULT_CACHE_TTL,
             },
+            metrics::{LAST_CACHE_ACCESS_NAME, LAST_CACHE_EVICTIONS_NAME},
             CreateLastCacheArgs, LastCacheFunction, LastCacheProvider, LAST_CACHE_UDTF_NAME,
         },
         test_helpers::{column_ids_for_names, TestWriter},
@@ -1270,6 +1273,9 @@ mod tests {
             processing_engine_plugins: Default::default(),
             processing_engine_triggers: Default::default(),
             deleted: false,
+            retention_period_ns: None,
+            retention_policies: vec![],
+            datafusion_config: Default::default(),
         };
         let table_id = TableId::from(0);
         use schema::InfluxColumnType::*;
@@ -1632,4 +1638,92 @@ mod tests {
             );
         }
     }
+    #[tokio::test]
+    async fn metrics() {
+        let writer = TestWriter::new();
+        let _ = writer.write_lp_to_write_batch("cpu,region=us-east,host=a usage=99", 0);
+
+        let db_schema = writer.db_schema();
+        let table_def = db_schema.table_definition("cpu").unwrap();
+        let provider = LastCacheProvider::new_from_catalog(writer.catalog()).unwrap();
+        let metric_registry = Registry::new();
+        provider.register_metrics(&metric_registry);
+        provider
+            .create_cache(
+                db_schema.id,
+                None,
+                CreateLastCacheArgs {
+                    table_def,
+                    count: LastCacheSize::default(),
+                    ttl: Duration::from_millis(500).into(),
+                    key_columns: LastCacheKeyColumnsArg::SeriesKey,
+                    value_columns: LastCacheValueColumnsArg::AcceptNew,
+                },
+            )
+            .unwrap();
+
+        let write_batch = writer.write_lp_to_write_batch(
+            "\
+            cpu,region=us-east,host=a usage=77\n\
+            cpu,region=us-east,host=b usage=77\n\
+            cpu,region=us-west,host=c usage=77\n\
+            ",
+            1_000,
+        );
+        let wal_contents = influxdb3_wal::create::wal_contents(
+            (0, 1, 0),
+            [influxdb3_wal::create::write_batch_op(write_batch)],
+        );
+        provider.write_wal_contents_to_cache(&wal_contents);
+
+        let ctx = SessionContext::new();
+        let last_cache_fn = LastCacheFunction::new(db_schema.id, Arc::clone(&provider));
+        ctx.register_udtf(LAST_CACHE_UDTF_NAME, Arc::new(last_cache_fn));
+
+        // two queries produce rows from the cache, and one does not:
+        for sql in [
+            "SELECT * FROM last_cache('cpu')",
+            "SELECT * FROM last_cache('cpu') WHERE host = 'a'",
+            "SELECT * FROM last_cache('cpu') WHERE host = 'z'",
+        ] {
+            ctx.sql(sql).await.unwrap().collect().await.unwrap();
+        }
+
+        let access = metric_registry
+            .get_instrument::<Metric<U64Counter>>(LAST_CACHE_ACCESS_NAME)
+            .unwrap();
+        let fetch_access = |status: &'static str| {
+            access
+                .get_observer(&Attributes::from(&[
+                    ("db", "test_db"),
+                    ("table", "cpu"),
+                    ("status", status),
+                ]))
+                .unwrap()
+                .fetch()
+        };
+        assert_eq!(2, fetch_access("hit"));
+        assert_eq!(1, fetch_access("miss"));
+
+        // once the TTL has passed, all three rows are evicted:
+        thread::sleep(Duration::from_millis(500));
+        provider.evict_expired_cache_entries();
+        let evictions = metric_registry
+            .get_instrument::<Metric<U64Counter>>(LAST_CACHE_EVICTIONS_NAME)
+            .unwrap()
+            .get_observer(&Attributes::from(&[("db", "test_db"), ("table", "cpu")]))
+            .unwrap()
+            .fetch();
+        assert_eq!(3, evictions);
+
+        // the cache is now empty, so querying it is a miss:
+        ctx.sql("SELECT * FROM last_cache('cpu')")
+            .await
+            .unwrap()
+            .collect()
+            .await
+            .unwrap();
+        assert_eq!(2, fetch_access("hit"));
+        assert_eq!(2, fetch_access("miss"));
+    }
 }
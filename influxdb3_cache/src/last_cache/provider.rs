@@ -1,15 +1,21 @@
-use std::{collections::HashMap, sync::Arc, time::Duration};
+use std::{
+    collections::HashMap,
+    sync::{Arc, OnceLock},
+    time::Duration,
+};
 
 use arrow::{array::RecordBatch, datatypes::SchemaRef as ArrowSchemaRef, error::ArrowError};
 
 use influxdb3_catalog::catalog::{Catalog, TableDefinition};
 use influxdb3_id::{DbId, TableId};
 use influxdb3_wal::{LastCacheDefinition, LastCacheValueColumnsDef, WalContents, WalOp};
+use metric::Registry;
 use observability_deps::tracing::debug;
 use parking_lot::RwLock;
 
 use super::{
     cache::{LastCache, LastCacheValueColumnsArg},
+    metrics::LastCacheMetrics,
     CreateLastCacheArgs, Error,
 };
 
@@ -21,6 +27,8 @@ type CacheMap = RwLock<HashMap<DbId, HashMap<TableId, HashMap<Arc<str>, LastCach
 pub struct LastCacheProvider {
     pub(crate) catalog: Arc<Catalog>,
     pub(crate) cache_map: CacheMap,
+    /// Set once the provider's metrics are registered, see [`LastCacheProvider::register_metrics`]
+    metrics: OnceLock<LastCacheMetrics>,
 }
 
 impl std::fmt::Debug for LastCacheProvider {
@@ -35,6 +43,7 @@ impl LastCacheProvider {
         let provider = Arc::new(LastCacheProvider {
             catalog: Arc::clone(&catalog),
             cache_map: Default::default(),
+            metrics: OnceLock::new(),
         });
         for db_schema in catalog.list_db_schema() {
             for table_def in db_schema.tables() {
@@ -84,6 +93,45 @@ impl LastCacheProvider {
         Ok(provider)
     }
 
+    /// Register counters for the hits, misses, and evictions of the caches in this provider with
+    /// the given [`Registry`]
+    ///
+    /// Nothing is recorded until this is called, and only the first registry it is called with
+    /// is used.
+    pub fn register_metrics(&self, metric_registry: &Registry) {
+        self.metrics
+            .get_or_init(|| LastCacheMetrics::new(metric_registry));
+    }
+
+    /// Record a query to a cache on the given table, which is a hit if it produced any rows
+    pub(crate) fn record_access(&self, db_id: &DbId, table_name: &str, hit: bool) {
+        let Some(metrics) = self.metrics.get() else {
+            return;
+        };
+        let Some(db_schema) = self.catalog.db_schema_by_id(db_id) else {
+            return;
+        };
+        if hit {
+            metrics.record_cache_hit(&db_schema.name, table_name);
+        } else {
+            metrics.record_cache_miss(&db_schema.name, table_name);
+        }
+    }
+
+    /// Record the rows evicted from the caches on the given table
+    fn record_evictions(&self, db_id: &DbId, table_id: &TableId, n_rows: usize) {
+        let Some(metrics) = self.metrics.get().filter(|_| n_rows > 0) else {
+            return;
+        };
+        let Some(db_schema) = self.catalog.db_schema_by_id(db_id) else {
+            return;
+        };
+        let Some(table_name) = db_schema.table_id_to_name(table_id) else {
+            return;
+        };
+        metrics.record_evictions(&db_schema.name, &table_name, n_rows as u64);
+    }
+
     /// Get a particular cache's name and arrow schema
     ///
     /// This is used for the implementation of DataFusion's `TableFunctionImpl` and `TableProvider`
@@ -328,9 +376,11 @@ impl LastCacheProvider {
     /// time-to-live (TTL).
     pub fn evict_expired_cache_entries(&self) {
         let mut cache_map = self.cache_map.write();
-        cache_map.iter_mut().for_each(|(_, db)| {
-            db.iter_mut()
-                .for_each(|(_, table)| table.iter_mut().for_each(|(_, lc)| lc.remove_expired()))
+        cache_map.iter_mut().for_each(|(db_id, db)| {
+            db.iter_mut().for_each(|(table_id, table)| {
+                let n_rows = table.iter_mut().map(|(_, lc)| lc.remove_expired()).sum();
+                self.record_evictions(db_id, table_id, n_rows);
+            })
         });
     }
 
@@ -95,6 +95,11 @@ impl TableProvider for LastCacheFunctionProvider {
             (None, vec![])
         };
         drop(read);
+        self.provider.record_access(
+            &self.db_id,
+            &self.table_def.table_name,
+            batches.iter().any(|batch| batch.num_rows() > 0),
+        );
         let mut exec = LastCacheExec::try_new(
             predicates,
             Arc::clone(&self.table_def),
@@ -4,7 +4,7 @@ use std::{
     fmt::Debug,
     ops::Range,
     sync::{
-        atomic::{AtomicI64, AtomicUsize, Ordering},
+        atomic::{AtomicI64, AtomicU64, AtomicUsize, Ordering},
         Arc,
     },
     time::Duration,
@@ -33,6 +33,9 @@ use tokio::sync::{
 };
 
 mod metrics;
+mod table_function;
+
+pub use table_function::{ParquetCacheFunction, PARQUET_CACHE_UDTF_NAME};
 
 /// Shared future type for cache values that are being fetched
 type SharedCacheValueFuture = Shared<BoxFuture<'static, Result<Arc<CacheValue>, DynError>>>;
@@ -71,6 +74,25 @@ pub trait ParquetCacheOracle: Send + Sync + Debug {
 
     // Get a receiver that is notified when a prune takes place and how much memory was freed
     fn prune_notifier(&self) -> watch::Receiver<usize>;
+
+    /// List the files that are currently cached, without affecting which are evicted
+    ///
+    /// Oracles that do not hold the cache themselves have no files to list.
+    fn cached_files(&self) -> Vec<CachedFile> {
+        vec![]
+    }
+}
+
+/// A file held in the parquet cache, see [`ParquetCacheOracle::cached_files`]
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct CachedFile {
+    pub path: Path,
+    /// The memory used by the file in the cache
+    pub size_bytes: usize,
+    /// The nano-second timestamp of when the file was cached, or last hit in the cache
+    pub last_access_ns: i64,
+    /// The number of requests for the file that have been served by the cache
+    pub hit_count: u64,
 }
 
 /// Concrete implementation of the [`ParquetCacheOracle`]
@@ -80,6 +102,7 @@ pub trait ParquetCacheOracle: Send + Sync + Debug {
 pub struct MemCacheOracle {
     cache_request_tx: Sender<CacheRequest>,
     prune_notifier_tx: watch::Sender<usize>,
+    cache: Arc<Cache>,
 }
 
 // TODO(trevor): make this configurable with reasonable default
@@ -92,6 +115,7 @@ impl MemCacheOracle {
     /// * one to handle registered [`CacheRequest`]s
     /// * one to prune deleted and un-needed cache entries on an interval
     fn new(mem_cached_store: Arc<MemCachedObjectStore>, prune_interval: Duration) -> Self {
+        let cache = Arc::clone(&mem_cached_store.cache);
         let (cache_request_tx, cache_request_rx) = channel(CACHE_REQUEST_BUFFER_SIZE);
         background_cache_request_handler(Arc::clone(&mem_cached_store), cache_request_rx);
         let (prune_notifier_tx, _prune_notifier_rx) = watch::channel(0);
@@ -99,6 +123,7 @@ impl MemCacheOracle {
         Self {
             cache_request_tx,
             prune_notifier_tx,
+            cache,
         }
     }
 }
@@ -116,6 +141,10 @@ impl ParquetCacheOracle for MemCacheOracle {
     fn prune_notifier(&self) -> watch::Receiver<usize> {
         self.prune_notifier_tx.subscribe()
     }
+
+    fn cached_files(&self) -> Vec<CachedFile> {
+        self.cache.cached_files()
+    }
 }
 
 /// Helper function for creation of a [`MemCachedObjectStore`] and [`MemCacheOracle`]
@@ -198,6 +227,8 @@ struct CacheEntry {
     state: CacheEntryState,
     /// The nano-second timestamp of when this value was last hit
     hit_time: AtomicI64,
+    /// The number of times that this value has been hit
+    hit_count: AtomicU64,
 }
 
 impl CacheEntry {
@@ -306,6 +337,7 @@ impl Cache {
             entry
                 .hit_time
                 .store(self.time_provider.now().timestamp_nanos(), Ordering::SeqCst);
+            entry.hit_count.fetch_add(1, Ordering::Relaxed);
         } else if entry.is_fetching() {
             self.access_metrics.record_cache_miss_while_fetching();
         }
@@ -326,6 +358,7 @@ impl Cache {
         let entry = CacheEntry {
             state: CacheEntryState::Fetching(fut),
             hit_time: AtomicI64::new(self.time_provider.now().timestamp_nanos()),
+            hit_count: AtomicU64::new(0),
         };
         let additional = entry.size();
         self.size_metrics
@@ -361,6 +394,25 @@ impl Cache {
         }
     }
 
+    /// List the entries that have been fetched successfully
+    ///
+    /// This does not update the hit time of the entries
+    fn cached_files(&self) -> Vec<CachedFile> {
+        self.map
+            .iter()
+            .filter(|map_ref| map_ref.value().is_success())
+            .map(|map_ref| {
+                let entry = map_ref.value();
+                CachedFile {
+                    path: map_ref.key().clone(),
+                    size_bytes: entry.size(),
+                    last_access_ns: entry.hit_time.load(Ordering::SeqCst),
+                    hit_count: entry.hit_count.load(Ordering::Relaxed),
+                }
+            })
+            .collect()
+    }
+
     /// Remove an entry from the cache, as well as its associated size from the used capacity
     fn remove(&self, path: &Path) {
         let Some((_, entry)) = self.map.remove(path) else {
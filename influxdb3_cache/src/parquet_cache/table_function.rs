@@ -0,0 +1,116 @@
+use std::{any::Any, sync::Arc};
+
+use arrow::{
+    array::{ArrayRef, StringArray, TimestampNanosecondArray, UInt64Array},
+    datatypes::{DataType, Field, Schema, SchemaRef, TimeUnit},
+    record_batch::RecordBatch,
+};
+use async_trait::async_trait;
+use datafusion::{
+    catalog::{Session, TableProvider},
+    common::plan_err,
+    datasource::{function::TableFunctionImpl, TableType},
+    error::DataFusionError,
+    physical_plan::{memory::MemoryExec, ExecutionPlan},
+    prelude::Expr,
+};
+
+use super::ParquetCacheOracle;
+
+/// The name of the function that is called to list the contents of the parquet cache
+pub const PARQUET_CACHE_UDTF_NAME: &str = "parquet_cache";
+
+fn parquet_cache_schema() -> SchemaRef {
+    let columns = vec![
+        Field::new("path", DataType::Utf8, false),
+        Field::new("size_bytes", DataType::UInt64, false),
+        Field::new(
+            "last_access",
+            DataType::Timestamp(TimeUnit::Nanosecond, None),
+            false,
+        ),
+        Field::new("hit_count", DataType::UInt64, false),
+    ];
+    Arc::new(Schema::new(columns))
+}
+
+/// Implementor of the [`TableFunctionImpl`] trait, to be registered as a user-defined table
+/// function in the DataFusion `SessionContext`, which lists the files held in the parquet cache
+/// along with their size, when they were last accessed, and how many times they have been hit
+#[derive(Debug)]
+pub struct ParquetCacheFunction {
+    oracle: Arc<dyn ParquetCacheOracle>,
+}
+
+impl ParquetCacheFunction {
+    pub fn new(oracle: Arc<dyn ParquetCacheOracle>) -> Self {
+        Self { oracle }
+    }
+}
+
+impl TableFunctionImpl for ParquetCacheFunction {
+    fn call(&self, args: &[Expr]) -> Result<Arc<dyn TableProvider>, DataFusionError> {
+        if !args.is_empty() {
+            return plan_err!("{PARQUET_CACHE_UDTF_NAME} does not take any arguments");
+        }
+        Ok(Arc::new(ParquetCacheFunctionProvider {
+            schema: parquet_cache_schema(),
+            oracle: Arc::clone(&self.oracle),
+        }))
+    }
+}
+
+/// Implementor of the [`TableProvider`] trait that is produced with a call to the
+/// [`ParquetCacheFunction`]
+#[derive(Debug)]
+struct ParquetCacheFunctionProvider {
+    schema: SchemaRef,
+    oracle: Arc<dyn ParquetCacheOracle>,
+}
+
+#[async_trait]
+impl TableProvider for ParquetCacheFunctionProvider {
+    fn as_any(&self) -> &dyn Any {
+        self as &dyn Any
+    }
+
+    fn schema(&self) -> SchemaRef {
+        Arc::clone(&self.schema)
+    }
+
+    fn table_type(&self) -> TableType {
+        TableType::Temporary
+    }
+
+    async fn scan(
+        &self,
+        _ctx: &dyn Session,
+        projection: Option<&Vec<usize>>,
+        _filters: &[Expr],
+        _limit: Option<usize>,
+    ) -> Result<Arc<dyn ExecutionPlan>, DataFusionError> {
+        // the files are listed as the query is planned, sorted by path for a stable order:
+        let mut files = self.oracle.cached_files();
+        files.sort_unstable_by(|a, b| a.path.cmp(&b.path));
+        let columns: Vec<ArrayRef> = vec![
+            Arc::new(StringArray::from_iter_values(
+                files.iter().map(|file| file.path.as_ref()),
+            )),
+            Arc::new(UInt64Array::from_iter_values(
+                files.iter().map(|file| file.size_bytes as u64),
+            )),
+            Arc::new(TimestampNanosecondArray::from_iter_values(
+                files.iter().map(|file| file.last_access_ns),
+            )),
+            Arc::new(UInt64Array::from_iter_values(
+                files.iter().map(|file| file.hit_count),
+            )),
+        ];
+        let batch = RecordBatch::try_new(self.schema(), columns)?;
+        Ok(Arc::new(MemoryExec::try_new(
+            &[vec![batch]],
+            self.schema(),
+            projection.cloned(),
+        )?))
+    }
+}
@@ -9,10 +9,12 @@ use hashbrown::HashMap;
 use indexmap::IndexMap;
 use influxdb3_id::{ColumnId, DbId, SerdeVecMap, TableId};
 use influxdb3_wal::{
-    CatalogBatch, CatalogOp, DeleteDatabaseDefinition, DeletePluginDefinition,
-    DeleteTableDefinition, DeleteTriggerDefinition, DistinctCacheDefinition, DistinctCacheDelete,
-    FieldAdditions, FieldDefinition, LastCacheDefinition, LastCacheDelete, OrderedCatalogBatch,
-    PluginDefinition, TriggerDefinition, TriggerIdentifier,
+    CatalogBatch, CatalogOp, ComputedColumnDefinition, DatabaseConfigDefinition,
+    DeleteDatabaseDefinition, DeletePluginDefinition, DeleteTableDefinition,
+    DeleteTriggerDefinition, DistinctCacheDefinition, DistinctCacheDelete, FieldAdditions,
+    FieldDefinition, LastCacheDefinition, LastCacheDelete, OrderedCatalogBatch, PluginDefinition,
+    RenameTableDefinition, RetentionPeriodDefinition, RetentionPolicyDefinition,
+    TablePolicyDefinition, TriggerDefinition, TriggerIdentifier,
 };
 use influxdb_line_protocol::FieldValue;
 use iox_time::Time;
@@ -127,6 +129,22 @@ pub enum Error {
         plugin_name: String,
         database_name: String,
     },
+    #[error(
+        "Computed column {} cannot be defined on table {}, which stores a column of that name",
+        column_name,
+        table_name
+    )]
+    ComputedColumnConflict {
+        table_name: String,
+        column_name: String,
+    },
+
+    #[error("Table {} already exists in DB {}", table_name, db_name)]
+    TableAlreadyExists {
+        db_name: Arc<str>,
+        table_name: Arc<str>,
+    },
+
     #[error("Processing Engine Unimplemented: {}", feature_description)]
     ProcessingEngineUnimplemented { feature_description: String },
 
@@ -534,6 +552,26 @@ pub struct DatabaseSchema {
     // TODO: care about performance of triggers
     pub processing_engine_triggers: HashMap<String, TriggerDefinition>,
     pub deleted: bool,
+    /// Data older than this many nanoseconds is expired, and is not queried, while data is
+    /// retained indefinitely if this is not set
+    pub retention_period_ns: Option<i64>,
+    /// The named retention policies of the database, sorted by name
+    ///
+    /// The duration of the default policy, if there is one, is kept the same as the
+    /// [`retention_period_ns`][Self::retention_period_ns] of the database when either is set.
+    pub retention_policies: Vec<RetentionPolicy>,
+    /// Options of the DataFusion config that queries against the database are run with, which
+    /// override the options of the server's config
+    pub datafusion_config: BTreeMap<String, String>,
+}
+
+/// A named retention policy of a database, see [`RetentionPolicyDefinition`]
+#[derive(Debug, Clone, Eq, PartialEq)]
+pub struct RetentionPolicy {
+    pub name: Arc<str>,
+    /// The duration of the policy in nanoseconds, or `None` if data is retained indefinitely
+    pub duration_ns: Option<i64>,
+    pub default: bool,
 }
 
 impl DatabaseSchema {
@@ -546,6 +584,9 @@ impl DatabaseSchema {
             processing_engine_plugins: HashMap::new(),
             processing_engine_triggers: HashMap::new(),
             deleted: false,
+            retention_period_ns: None,
+            retention_policies: vec![],
+            datafusion_config: BTreeMap::new(),
         }
     }
 
@@ -721,6 +762,7 @@ impl UpdateDatabaseSchema for CatalogOp {
             }
             CatalogOp::DeleteDatabase(delete_database) => delete_database.update_schema(schema),
             CatalogOp::DeleteTable(delete_table) => delete_table.update_schema(schema),
+            CatalogOp::RenameTable(rename_table) => rename_table.update_schema(schema),
             CatalogOp::DeletePlugin(delete_plugin) => delete_plugin.update_schema(schema),
             CatalogOp::CreatePlugin(create_plugin) => create_plugin.update_schema(schema),
             CatalogOp::CreateTrigger(create_trigger) => create_trigger.update_schema(schema),
@@ -731,6 +773,17 @@ impl UpdateDatabaseSchema for CatalogOp {
             CatalogOp::DisableTrigger(trigger_identifier) => {
                 DisableTrigger(trigger_identifier.clone()).update_schema(schema)
             }
+            CatalogOp::SetTablePolicy(table_policy) => table_policy.update_schema(schema),
+            CatalogOp::CreateComputedColumn(computed_column) => {
+                computed_column.update_schema(schema)
+            }
+            CatalogOp::SetRetentionPeriod(retention_period) => {
+                retention_period.update_schema(schema)
+            }
+            CatalogOp::CreateRetentionPolicy(retention_policy) => {
+                retention_policy.update_schema(schema)
+            }
+            CatalogOp::SetDatabaseConfig(config) => config.update_schema(schema),
         }
     }
 }
@@ -772,6 +825,75 @@ impl UpdateDatabaseSchema for DeleteDatabaseDefinition {
     }
 }
 
+impl UpdateDatabaseSchema for RetentionPeriodDefinition {
+    fn update_schema<'a>(
+        &self,
+        mut schema: Cow<'a, DatabaseSchema>,
+    ) -> Result<Cow<'a, DatabaseSchema>> {
+        if schema.retention_period_ns != self.retention_period_ns {
+            schema.to_mut().retention_period_ns = self.retention_period_ns;
+        }
+        // the retention period is the duration of the default policy, if there is one:
+        if let Some(index) = schema
+            .retention_policies
+            .iter()
+            .position(|policy| policy.default && policy.duration_ns != self.retention_period_ns)
+        {
+            schema.to_mut().retention_policies[index].duration_ns = self.retention_period_ns;
+        }
+        Ok(schema)
+    }
+}
+
+impl UpdateDatabaseSchema for RetentionPolicyDefinition {
+    fn update_schema<'a>(
+        &self,
+        mut schema: Cow<'a, DatabaseSchema>,
+    ) -> Result<Cow<'a, DatabaseSchema>> {
+        let policy = RetentionPolicy {
+            name: Arc::clone(&self.name),
+            duration_ns: self.duration_ns,
+            default: self.default,
+        };
+        let mut policies = schema.retention_policies.clone();
+        if self.default {
+            policies
+                .iter_mut()
+                .for_each(|policy| policy.default = false);
+        }
+        match policies.binary_search_by(|existing| existing.name.cmp(&policy.name)) {
+            Ok(index) => policies[index] = policy,
+            Err(index) => policies.insert(index, policy),
+        }
+        if policies != schema.retention_policies {
+            schema.to_mut().retention_policies = policies;
+        }
+        if self.default && schema.retention_period_ns != self.duration_ns {
+            schema.to_mut().retention_period_ns = self.duration_ns;
+        }
+        Ok(schema)
+    }
+}
+
+impl UpdateDatabaseSchema for DatabaseConfigDefinition {
+    fn update_schema<'a>(
+        &self,
+        mut schema: Cow<'a, DatabaseSchema>,
+    ) -> Result<Cow<'a, DatabaseSchema>> {
+        let mut config = schema.datafusion_config.clone();
+        for (key, value) in &self.options {
+            match value {
+                Some(value) => config.insert(key.clone(), value.clone()),
+                None => config.remove(key),
+            };
+        }
+        if config != schema.datafusion_config {
+            schema.to_mut().datafusion_config = config;
+        }
+        Ok(schema)
+    }
+}
+
 impl UpdateDatabaseSchema for DeleteTableDefinition {
     fn update_schema<'a>(
         &self,
@@ -797,6 +919,39 @@ impl UpdateDatabaseSchema for DeleteTableDefinition {
     }
 }
 
+impl UpdateDatabaseSchema for RenameTableDefinition {
+    fn update_schema<'a>(
+        &self,
+        mut schema: Cow<'a, DatabaseSchema>,
+    ) -> Result<Cow<'a, DatabaseSchema>> {
+        let Some(table) = schema.tables.get(&self.table_id) else {
+            return Err(TableNotFound {
+                db_name: Arc::clone(&schema.name),
+                table_name: Arc::clone(&self.table_name),
+            });
+        };
+        if table.table_name == self.new_table_name {
+            return Ok(schema);
+        }
+        if schema.table_map.contains_right(&self.new_table_name) {
+            return Err(Error::TableAlreadyExists {
+                db_name: Arc::clone(&schema.name),
+                table_name: Arc::clone(&self.new_table_name),
+            });
+        }
+        let mut_schema = schema.to_mut();
+        if let Some(renamed_table) = mut_schema.tables.get_mut(&self.table_id) {
+            let new_table_def = Arc::make_mut(renamed_table);
+            new_table_def.table_name = Arc::clone(&self.new_table_name);
+            mut_schema.table_map.insert(
+                new_table_def.table_id,
+                Arc::clone(&new_table_def.table_name),
+            );
+        }
+        Ok(schema)
+    }
+}
+
 impl UpdateDatabaseSchema for DeletePluginDefinition {
     fn update_schema<'a>(
         &self,
@@ -965,6 +1120,11 @@ pub struct TableDefinition {
     pub last_caches: HashMap<Arc<str>, LastCacheDefinition>,
     pub distinct_caches: HashMap<Arc<str>, DistinctCacheDefinition>,
     pub deleted: bool,
+    /// Whether queries against the table must have a lower bound on time
+    pub require_time_predicate: bool,
+    /// The SQL expressions of the columns computed from the stored columns of the table when it
+    /// is queried, by column name
+    pub computed_columns: BTreeMap<Arc<str>, Arc<str>>,
 }
 
 impl TableDefinition {
@@ -1026,6 +1186,8 @@ impl TableDefinition {
             last_caches: HashMap::new(),
             distinct_caches: HashMap::new(),
             deleted: false,
+            require_time_predicate: false,
+            computed_columns: BTreeMap::new(),
         })
     }
 
@@ -1375,6 +1537,56 @@ impl TableUpdate for LastCacheDelete {
     }
 }
 
+impl TableUpdate for TablePolicyDefinition {
+    fn table_id(&self) -> TableId {
+        self.table_id
+    }
+    fn table_name(&self) -> Arc<str> {
+        Arc::clone(&self.table_name)
+    }
+
+    fn update_table<'a>(
+        &self,
+        mut table: Cow<'a, TableDefinition>,
+    ) -> Result<Cow<'a, TableDefinition>> {
+        if table.require_time_predicate != self.require_time_predicate {
+            table.to_mut().require_time_predicate = self.require_time_predicate;
+        }
+        Ok(table)
+    }
+}
+
+impl TableUpdate for ComputedColumnDefinition {
+    fn table_id(&self) -> TableId {
+        self.table_id
+    }
+    fn table_name(&self) -> Arc<str> {
+        Arc::clone(&self.table_name)
+    }
+
+    fn update_table<'a>(
+        &self,
+        mut table: Cow<'a, TableDefinition>,
+    ) -> Result<Cow<'a, TableDefinition>> {
+        if table
+            .column_name_to_id(Arc::clone(&self.column_name))
+            .is_some()
+        {
+            return Err(Error::ComputedColumnConflict {
+                table_name: self.table_name.to_string(),
+                column_name: self.column_name.to_string(),
+            });
+        }
+        if table.computed_columns.get(&self.column_name) != Some(&self.expression) {
+            table
+                .to_mut()
+                .computed_columns
+                .insert(Arc::clone(&self.column_name), Arc::clone(&self.expression));
+        }
+        Ok(table)
+    }
+}
+
 #[derive(Debug, Eq, PartialEq, Clone)]
 pub struct ColumnDefinition {
     pub id: ColumnId,
@@ -1436,6 +1648,9 @@ mod tests {
             processing_engine_plugins: Default::default(),
             processing_engine_triggers: Default::default(),
             deleted: false,
+            retention_period_ns: None,
+            retention_policies: vec![],
+            datafusion_config: BTreeMap::new(),
         };
         use InfluxColumnType::*;
         use InfluxFieldType::*;
@@ -1647,6 +1862,9 @@ mod tests {
             processing_engine_plugins: Default::default(),
             processing_engine_triggers: Default::default(),
             deleted: false,
+            retention_period_ns: None,
+            retention_policies: vec![],
+            datafusion_config: BTreeMap::new(),
         };
         database.tables.insert(
             TableId::from(0),
@@ -1706,6 +1924,9 @@ mod tests {
             processing_engine_plugins: Default::default(),
             processing_engine_triggers: Default::default(),
             deleted: false,
+            retention_period_ns: None,
+            retention_policies: vec![],
+            datafusion_config: BTreeMap::new(),
         };
         use InfluxColumnType::*;
         use InfluxFieldType::*;
@@ -1763,6 +1984,9 @@ mod tests {
             processing_engine_plugins: Default::default(),
             processing_engine_triggers: Default::default(),
             deleted: false,
+            retention_period_ns: None,
+            retention_policies: vec![],
+            datafusion_config: BTreeMap::new(),
         };
         use InfluxColumnType::*;
         use InfluxFieldType::*;
@@ -1864,6 +2088,9 @@ mod tests {
             processing_engine_plugins: Default::default(),
             processing_engine_triggers: Default::default(),
             deleted: false,
+            retention_period_ns: None,
+            retention_policies: vec![],
+            datafusion_config: BTreeMap::new(),
         };
         let deleted_table_id = TableId::new();
         let table_name = Arc::from("boo");
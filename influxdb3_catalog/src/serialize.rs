@@ -1,5 +1,6 @@
 use crate::catalog::ColumnDefinition;
 use crate::catalog::DatabaseSchema;
+use crate::catalog::RetentionPolicy;
 use crate::catalog::TableDefinition;
 use arrow::datatypes::DataType as ArrowDataType;
 use bimap::BiHashMap;
@@ -15,6 +16,7 @@ use schema::InfluxColumnType;
 use schema::InfluxFieldType;
 use schema::TIME_DATA_TIMEZONE;
 use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
 use std::sync::Arc;
 
 impl Serialize for DatabaseSchema {
@@ -46,6 +48,20 @@ struct DatabaseSnapshot {
     #[serde(default)]
     processing_engine_triggers: SerdeVecMap<String, ProcessingEngineTriggerSnapshot>,
     deleted: bool,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    retention_period_ns: Option<i64>,
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    retention_policies: Vec<RetentionPolicySnapshot>,
+    #[serde(default, skip_serializing_if = "BTreeMap::is_empty")]
+    datafusion_config: BTreeMap<String, String>,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+struct RetentionPolicySnapshot {
+    name: Arc<str>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    duration_ns: Option<i64>,
+    default: bool,
 }
 
 impl From<&DatabaseSchema> for DatabaseSnapshot {
@@ -69,6 +85,17 @@ impl From<&DatabaseSchema> for DatabaseSnapshot {
                 .map(|(name, trigger)| (name.clone(), trigger.into()))
                 .collect(),
             deleted: db.deleted,
+            retention_period_ns: db.retention_period_ns,
+            retention_policies: db
+                .retention_policies
+                .iter()
+                .map(|policy| RetentionPolicySnapshot {
+                    name: Arc::clone(&policy.name),
+                    duration_ns: policy.duration_ns,
+                    default: policy.default,
+                })
+                .collect(),
+            datafusion_config: db.datafusion_config.clone(),
         }
     }
 }
@@ -121,6 +148,17 @@ impl From<DatabaseSnapshot> for DatabaseSchema {
             processing_engine_plugins,
             processing_engine_triggers,
             deleted: snap.deleted,
+            retention_period_ns: snap.retention_period_ns,
+            retention_policies: snap
+                .retention_policies
+                .into_iter()
+                .map(|policy| RetentionPolicy {
+                    name: policy.name,
+                    duration_ns: policy.duration_ns,
+                    default: policy.default,
+                })
+                .collect(),
+            datafusion_config: snap.datafusion_config,
         }
     }
 }
@@ -159,6 +197,16 @@ struct TableSnapshot {
     #[serde(default, skip_serializing_if = "Vec::is_empty")]
     last_caches: Vec<LastCacheSnapshot>,
     deleted: bool,
+    #[serde(default, skip_serializing_if = "std::ops::Not::not")]
+    require_time_predicate: bool,
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    computed_columns: Vec<ComputedColumnSnapshot>,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+struct ComputedColumnSnapshot {
+    name: Arc<str>,
+    expression: Arc<str>,
 }
 
 #[derive(Debug, Serialize, Deserialize)]
@@ -321,6 +369,15 @@ impl From<&TableDefinition> for TableSnapshot {
                 .collect(),
             last_caches: def.last_caches.values().map(Into::into).collect(),
             deleted: def.deleted,
+            require_time_predicate: def.require_time_predicate,
+            computed_columns: def
+                .computed_columns
+                .iter()
+                .map(|(name, expression)| ComputedColumnSnapshot {
+                    name: Arc::clone(name),
+                    expression: Arc::clone(expression),
+                })
+                .collect(),
         }
     }
 }
@@ -405,6 +462,12 @@ impl From<TableSnapshot> for TableDefinition {
                 .into_iter()
                 .map(|lc_snap| (Arc::clone(&lc_snap.name), lc_snap.into()))
                 .collect(),
+            require_time_predicate: snap.require_time_predicate,
+            computed_columns: snap
+                .computed_columns
+                .into_iter()
+                .map(|cc_snap| (cc_snap.name, cc_snap.expression))
+                .collect(),
             ..table_def
         }
     }
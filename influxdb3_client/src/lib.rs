@@ -332,6 +332,7 @@ impl Client {
         ShowDatabasesRequestBuilder {
             client: self,
             show_deleted: false,
+            show_stats: false,
             format: Format::Json,
         }
     }
@@ -1099,6 +1100,7 @@ pub struct ShowDatabasesRequestBuilder<'c> {
     client: &'c Client,
     format: Format,
     show_deleted: bool,
+    show_stats: bool,
 }
 
 impl<'c> ShowDatabasesRequestBuilder<'c> {
@@ -1108,6 +1110,13 @@ impl<'c> ShowDatabasesRequestBuilder<'c> {
         self
     }
 
+    /// Specify whether or not to show the number of tables, rows, and bytes of each database in
+    /// the output
+    pub fn with_show_stats(mut self, show_stats: bool) -> Self {
+        self.show_stats = show_stats;
+        self
+    }
+
     /// Specify the [`Format`] of the returned `Bytes`
     pub fn with_format(mut self, format: Format) -> Self {
         self.format = format;
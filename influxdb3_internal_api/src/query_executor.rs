@@ -1,12 +1,17 @@
 use async_trait::async_trait;
+use datafusion::arrow::datatypes::DataType;
 use datafusion::arrow::error::ArrowError;
 use datafusion::common::DataFusionError;
 use datafusion::execution::SendableRecordBatchStream;
+use datafusion::scalar::ScalarValue;
 use iox_query::query_log::QueryLogEntries;
 use iox_query::{QueryDatabase, QueryNamespace};
 use iox_query_params::StatementParams;
+use std::collections::HashMap;
 use std::fmt::Debug;
+use std::str::FromStr;
 use std::sync::Arc;
+use std::time::Duration;
 use trace::ctx::SpanContext;
 use trace::span::Span;
 use trace_http::ctx::RequestLogContext;
@@ -24,10 +29,521 @@ pub enum QueryExecutorError {
     DatabasesToRecordBatch(#[source] ArrowError),
     #[error("unable to compose record batches from retention policies: {0}")]
     RetentionPoliciesToRecordBatch(#[source] ArrowError),
+    #[error("column '{name}' not found{}", format_suggestions(.suggestions))]
+    UnknownColumn {
+        name: String,
+        suggestions: Vec<String>,
+    },
+    #[error("query job not found: {id}")]
+    QueryJobNotFound { id: String },
+    #[error("query job {id} has no results available, status: {status}")]
+    QueryJobNotComplete { id: String, status: String },
+    #[error("unable to store or retrieve query job results: {0}")]
+    QueryJobResults(#[source] DataFusionError),
+    #[error("table '{table}' has no columns yet and cannot be queried")]
+    TableNotReady { table: String },
+    #[error("unable to cast column '{column}' from {from} to {to}")]
+    InvalidColumnCast {
+        column: String,
+        from: DataType,
+        to: DataType,
+    },
+    #[error("column '{column}' has conflicting types across the chunks being queried: {types:?}")]
+    FieldTypeConflict {
+        column: String,
+        types: Vec<DataType>,
+    },
+    #[error("parquet file {file_id} not found for table '{table}'")]
+    ParquetFileNotFound { table: String, file_id: u64 },
+    #[error("query planning exceeded the maximum planning time of {limit:?}")]
+    PlanningTimeout { limit: Duration },
+    #[error(
+        "query contains a join without a join condition that is estimated to produce \
+        {estimated_rows} rows, which exceeds the limit of {limit}"
+    )]
+    UnboundedJoin { estimated_rows: usize, limit: usize },
+    #[error("unable to accept new queries: {reason}")]
+    Unavailable { reason: String },
+    #[error("query cannot output partial aggregates: {reason}")]
+    PartialAggregateUnsupported { reason: String },
+    #[error(
+        "invalid duration '{literal}', expected an integer followed by one of the units \
+        ns, u, µ, ms, s, m, h, d, or w, e.g., 30m or 1h30m"
+    )]
+    InvalidDuration { literal: String },
+    #[error(
+        "query spans {}, which exceeds the maximum time range of {max:?}, restrict it with a \
+        narrower condition on time",
+        .span.map_or_else(|| "an unbounded time range".to_string(), |span| format!("{span:?}"))
+    )]
+    TimeRangeTooLarge {
+        /// The time spanned by the query, or `None` if it has no lower bound on time
+        span: Option<Duration>,
+        max: Duration,
+    },
+    #[error(
+        "query would produce {count} GROUP BY time() buckets, which exceeds the maximum of {max}, \
+        use a coarser interval or a narrower condition on time"
+    )]
+    TooManyTimeBuckets { count: usize, max: usize },
+    #[error("table '{table}' is rewritten to '{target}', which does not exist")]
+    TableRewriteTargetNotFound { table: String, target: String },
+    #[error(
+        "cannot GROUP BY '{column}', which is a field of measurement '{measurement}', InfluxQL \
+        only groups by tags"
+    )]
+    GroupByField { measurement: String, column: String },
+    #[error("the write-ahead log is being replayed, so results would be incomplete, retry later")]
+    ReplayInProgress,
+    #[error("queries against table '{table}' must have a lower bound on time")]
+    TimePredicateRequired { table: String },
+    #[error(
+        "query would make {count} requests to the object store, which exceeds the maximum of \
+        {max}, use a narrower condition on time"
+    )]
+    StorageRequestBudgetExceeded { count: usize, max: usize },
+    #[error(
+        "query outputs more than one column named '{name}', alias the columns to distinct names"
+    )]
+    DuplicateOutputColumn { name: String },
+    #[error(
+        "query plan has {nodes} nodes, which exceeds the maximum of {max}, simplify the query \
+        or reduce the nesting of its subqueries"
+    )]
+    PlanTooComplex { nodes: usize, max: usize },
+    #[error(
+        "query was cancelled after consuming resources at a cost of {used}, which exceeds the \
+        maximum of {max}"
+    )]
+    ResourceBudgetExceeded { used: u64, max: u64 },
+    #[error("table '{table}' already exists, use CREATE OR REPLACE TABLE to replace it")]
+    TableAlreadyExists { table: String },
+    #[error("invalid CREATE TABLE AS statement: {reason}")]
+    InvalidCreateTableAs { reason: String },
+    #[error("unable to write query results to table '{table}': {reason}")]
+    WriteResults { table: String, reason: String },
+    #[error("invalid value for parameter '{name}': {reason}")]
+    InvalidParameter { name: String, reason: String },
+    #[error("query timed out after {elapsed:?}")]
+    Timeout { elapsed: Duration },
+    #[error(
+        "the query execution memory pool has {reserved} bytes reserved, which is above its \
+        high-water mark of {high_water_mark} bytes, retry later"
+    )]
+    MemoryPressure {
+        reserved: usize,
+        high_water_mark: usize,
+    },
+}
+
+impl QueryExecutorError {
+    /// The [`QueryExecutorError`] that caused a query to fail while it was being planned or
+    /// executed, e.g., a timeout or budget that was exceeded part way through, or this error if
+    /// it was not caused by another
+    pub fn into_root(self) -> Self {
+        match self {
+            Self::QueryPlanning(DataFusionError::External(e)) => match e.downcast::<Self>() {
+                Ok(e) => e.into_root(),
+                Err(e) => Self::QueryPlanning(DataFusionError::External(e)),
+            },
+            Self::ExecuteStream(DataFusionError::External(e)) => match e.downcast::<Self>() {
+                Ok(e) => e.into_root(),
+                Err(e) => Self::ExecuteStream(DataFusionError::External(e)),
+            },
+            e => e,
+        }
+    }
+}
+
+fn format_suggestions(suggestions: &[String]) -> String {
+    if suggestions.is_empty() {
+        String::new()
+    } else {
+        format!(", did you mean: {}?", suggestions.join(", "))
+    }
+}
+
+/// Options that control how an individual query is executed, see
+/// [`QueryExecutor::query_with_options`]
+///
+/// Those that a client can choose for itself can be given to the HTTP query APIs, in their
+/// `column_casts` and `options` parameters, while none can be given to queries made over Flight.
+/// The others are for servers that embed the query executor, and make queries through it on
+/// behalf of their clients.
+#[derive(Debug, Clone)]
+pub struct QueryOptions {
+    /// Cast the named output columns to the given types
+    pub column_casts: HashMap<String, DataType>,
+    /// The storage tiers that the query reads data from
+    pub storage: StorageHint,
+    /// Report the dictionary size and number of values for each dictionary encoded column
+    /// scanned by the query in the query log
+    pub dictionary_stats: bool,
+    /// Named constants that can be used anywhere in the query as functions that take no
+    /// arguments, e.g., a constant named `threshold` is referenced as `threshold()`
+    pub constants: HashMap<String, ScalarValue>,
+    /// Fail the query with [`QueryExecutorError::FieldTypeConflict`] if the chunks of a table
+    /// being queried disagree on the type of a field, rather than coercing them to the type in
+    /// the catalog
+    pub strict_field_types: bool,
+    /// The unit of the timestamp columns output by the query
+    pub time_precision: TimePrecision,
+    /// Allow joins without a join condition regardless of how many rows they are estimated to
+    /// produce
+    pub allow_cross_joins: bool,
+    /// Allow the query to span more time than the server's maximum query time range, which is
+    /// intended to be set only for trusted principals
+    pub allow_unbounded_time_range: bool,
+    /// How boolean columns are output by InfluxQL queries, which some 1.x clients expect as
+    /// strings
+    pub influxql_boolean_format: BooleanFormat,
+    /// Output the `time` column of InfluxQL queries as integer timestamps since the epoch, in
+    /// this unit, as the `epoch` parameter of the 1.x query API does
+    pub influxql_epoch: Option<TimePrecision>,
+    /// Fail InfluxQL queries with [`QueryExecutorError::GroupByField`] if they `GROUP BY` a
+    /// field of a measurement that they select from, which InfluxQL would otherwise group as if
+    /// it were a tag that has no values
+    pub influxql_strict_group_by: bool,
+    /// Which executor pool the query is planned and run on
+    pub priority: QueryPriority,
+    /// Output exactly these columns, in this order, regardless of the order in which the query
+    /// selects them. Fails with [`QueryExecutorError::UnknownColumn`] if any of them are not
+    /// output by the query.
+    pub output_columns: Vec<String>,
+    /// Serve the results of the query from the result cache, if the server has one, and none of
+    /// the tables the query scans have been written to since its results were cached
+    ///
+    /// This is intended for queries against tables that are rarely written to; note that
+    /// queries whose results depend on the time that they are run, e.g., through `now()`, are
+    /// served the cached results regardless.
+    pub cache_results: bool,
+    /// Output the partial state of the query's aggregates, rather than their final values, for
+    /// the states output by several servers to be merged by a coordinator, see
+    /// `influxdb3_server::query_executor::merge_partials`
+    ///
+    /// Only `mean`, `count`, and `sum` are supported, and only when the aggregated values are not
+    /// operated on further, e.g., by `HAVING` or `LIMIT`.
+    pub partial_aggregates: bool,
+    /// Tags that attribute the query to, e.g., a team or dashboard, which are shown for the
+    /// query in `system.queries`, and under which its cost is aggregated
+    pub tags: HashMap<String, String>,
+    /// Record the query in the query log, and so in `system.queries`, which is on by default
+    ///
+    /// This is intended to be turned off for frequent internal queries, e.g., health checks,
+    /// that would otherwise crowd the queries of users out of the log.
+    pub log: bool,
+    /// How strings are compared by the filters and `ORDER BY` clauses of the query, where the
+    /// strings that are output are unchanged
+    pub collation: Collation,
+    /// Read the table named by each value wherever the query references the table named by its
+    /// key, e.g., to move queries over to a new table during a migration without changing them
+    ///
+    /// Only the tables of the database are rewritten, not system tables, and the query fails
+    /// with [`QueryExecutorError::TableRewriteTargetNotFound`] if a referenced table is
+    /// rewritten to one that does not exist.
+    pub table_rewrites: HashMap<String, String>,
+    /// How the `distinct_cache` function answers the query when the cache may not hold every
+    /// distinct value within its maximum age
+    pub distinct_cache_mode: DistinctCacheMode,
+    /// Fail the query if it would make more than this many GET requests to the object store,
+    /// in place of the server's maximum, which is intended to be set per principal, e.g., to
+    /// give trusted principals a larger budget
+    pub max_storage_requests: Option<usize>,
+    /// Append a `__row_id` column to the results of the query, which numbers its rows from zero
+    /// in the order that they are output, so that a client can tell which rows of the results it
+    /// has already received, e.g., when it retries the query after a transport error
+    ///
+    /// The ids are only consistent within a single execution of the query, unless the query
+    /// orders its results completely.
+    pub row_ids: bool,
+    /// Keep the rows that each partition of the query's execution outputs together, and in
+    /// their order, rather than interleaving the partitions as their results become ready
+    ///
+    /// This does not sort the results, but partitions that are each ordered on time, e.g.,
+    /// because the chunks that they scan are time-disjoint and sorted, are merged into results
+    /// that are ordered on time as a whole.
+    pub preserve_partition_order: bool,
+    /// What to do with output columns that share a name with an earlier column, e.g., the
+    /// `host` columns selected from both sides of a join, which clients that key columns by
+    /// name cannot tell apart
+    pub duplicate_columns: DuplicateColumnPolicy,
+    /// The order in which the chunks of each table are merged, which decides the row that wins
+    /// deduplication among rows with the same tags and time
+    ///
+    /// This is intended as a debugging aid, to observe which of a set of duplicate writes
+    /// surfaces, and leaves chunks in the order that they are stored by default.
+    pub chunk_order: ChunkOrderHint,
+    /// Count the distinct values of InfluxQL `count(distinct(...))` aggregates approximately,
+    /// with HyperLogLog, which is much cheaper than an exact count on high cardinality fields
+    pub approx_count_distinct: bool,
+}
+
+impl Default for QueryOptions {
+    fn default() -> Self {
+        Self {
+            column_casts: Default::default(),
+            storage: Default::default(),
+            dictionary_stats: false,
+            constants: Default::default(),
+            strict_field_types: false,
+            time_precision: Default::default(),
+            allow_cross_joins: false,
+            allow_unbounded_time_range: false,
+            influxql_boolean_format: Default::default(),
+            influxql_epoch: None,
+            influxql_strict_group_by: false,
+            priority: Default::default(),
+            output_columns: Default::default(),
+            cache_results: false,
+            partial_aggregates: false,
+            tags: Default::default(),
+            log: true,
+            collation: Default::default(),
+            table_rewrites: Default::default(),
+            distinct_cache_mode: Default::default(),
+            max_storage_requests: None,
+            row_ids: false,
+            preserve_partition_order: false,
+            duplicate_columns: Default::default(),
+            chunk_order: Default::default(),
+            approx_count_distinct: false,
+        }
+    }
+}
+
+/// Which storage tiers a query reads data from
+///
+/// This is intended as a debugging aid, to isolate discrepancies between the data held in the
+/// in-memory buffer and the data persisted to object storage.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum StorageHint {
+    /// Read data from both the in-memory buffer and object storage
+    #[default]
+    Both,
+    /// Only read data that is held in the in-memory buffer
+    ReadBuffer,
+    /// Only read data that has been persisted to object storage
+    ObjectStore,
+}
+
+#[derive(Debug, thiserror::Error)]
+#[error("invalid storage hint '{0}', expected one of 'read_buffer', 'object_store', or 'both'")]
+pub struct InvalidStorageHint(String);
+
+impl FromStr for StorageHint {
+    type Err = InvalidStorageHint;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "both" => Ok(Self::Both),
+            "read_buffer" => Ok(Self::ReadBuffer),
+            "object_store" => Ok(Self::ObjectStore),
+            _ => Err(InvalidStorageHint(s.to_string())),
+        }
+    }
+}
+
+/// The order in which the chunks of a table are merged by a query, where the rows of later chunks
+/// win deduplication over the rows of earlier ones
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum ChunkOrderHint {
+    /// Merge the chunks in the order that they are stored, so that the most recent write wins
+    #[default]
+    Stored,
+    /// Merge the chunks by their sequence, as they are stored, but renumbered from zero
+    Sequence,
+    /// Merge the chunks by the earliest time that they hold, so that the rows of the chunk with
+    /// the latest data win
+    MinTime,
+    /// Merge the chunks in the reverse of the order that they are stored, so that the earliest
+    /// write wins
+    Reversed,
+}
+
+#[derive(Debug, thiserror::Error)]
+#[error(
+    "invalid chunk order '{0}', expected one of 'stored', 'sequence', 'min_time', or 'reversed'"
+)]
+pub struct InvalidChunkOrderHint(String);
+
+impl FromStr for ChunkOrderHint {
+    type Err = InvalidChunkOrderHint;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "stored" => Ok(Self::Stored),
+            "sequence" => Ok(Self::Sequence),
+            "min_time" => Ok(Self::MinTime),
+            "reversed" => Ok(Self::Reversed),
+            _ => Err(InvalidChunkOrderHint(s.to_string())),
+        }
+    }
+}
+
+/// The unit that timestamps are converted to in the output of a query, where timestamps are
+/// truncated when converting to a coarser unit
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum TimePrecision {
+    /// Output timestamps as they are stored
+    #[default]
+    Nanosecond,
+    Microsecond,
+    Millisecond,
+    Second,
+}
+
+#[derive(Debug, thiserror::Error)]
+#[error(
+    "invalid time precision '{0}', expected one of 'nanosecond', 'microsecond', 'millisecond', \
+    or 'second'"
+)]
+pub struct InvalidTimePrecision(String);
+
+impl FromStr for TimePrecision {
+    type Err = InvalidTimePrecision;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "nanosecond" | "ns" => Ok(Self::Nanosecond),
+            "microsecond" | "us" => Ok(Self::Microsecond),
+            "millisecond" | "ms" => Ok(Self::Millisecond),
+            "second" | "s" => Ok(Self::Second),
+            _ => Err(InvalidTimePrecision(s.to_string())),
+        }
+    }
+}
+
+/// How boolean values are output
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum BooleanFormat {
+    /// Output booleans as Arrow booleans
+    #[default]
+    Native,
+    /// Output booleans as the strings `true` and `false`
+    Words,
+    /// Output booleans as the strings `t` and `f`
+    Letters,
+}
+
+/// The executor pool that a query is planned and run on, so that long-running analytical queries
+/// do not hold up the latency-sensitive queries made by dashboards and other interactive clients
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum QueryPriority {
+    /// Run the query on the interactive pool
+    #[default]
+    Interactive,
+    /// Run the query on the batch pool, if one is configured, otherwise on the interactive pool
+    Batch,
+}
+
+#[derive(Debug, thiserror::Error)]
+#[error("invalid query priority '{0}', expected one of 'interactive' or 'batch'")]
+pub struct InvalidQueryPriority(String);
+
+impl FromStr for QueryPriority {
+    type Err = InvalidQueryPriority;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "interactive" => Ok(Self::Interactive),
+            "batch" => Ok(Self::Batch),
+            _ => Err(InvalidQueryPriority(s.to_string())),
+        }
+    }
+}
+
+/// How strings are compared by the filters and `ORDER BY` clauses of a query
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum Collation {
+    /// Compare strings by their bytes, so that, e.g., `B` sorts before `a`
+    #[default]
+    Binary,
+    /// Compare strings regardless of case, so that, e.g., `host = 'A'` matches `a`
+    CaseInsensitive,
+}
+
+#[derive(Debug, thiserror::Error)]
+#[error("invalid collation '{0}', expected one of 'binary' or 'case_insensitive'")]
+pub struct InvalidCollation(String);
+
+impl FromStr for Collation {
+    type Err = InvalidCollation;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "binary" => Ok(Self::Binary),
+            "case_insensitive" => Ok(Self::CaseInsensitive),
+            _ => Err(InvalidCollation(s.to_string())),
+        }
+    }
+}
+
+/// How the `distinct_cache` function answers a query from a distinct value cache that may not hold
+/// every distinct value within its maximum age, i.e., one that has not been populated for its
+/// maximum age since it was created, or since it last evicted values to stay within its maximum
+/// cardinality
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum DistinctCacheMode {
+    /// Output the contents of the cache, flagging whether they are complete in the
+    /// `iox::distinct_cache::complete` metadata of the output schema
+    #[default]
+    Fast,
+    /// Scan the table for its distinct values instead of reading an incomplete cache
+    Exact,
+}
+
+#[derive(Debug, thiserror::Error)]
+#[error("invalid distinct cache mode '{0}', expected one of 'fast' or 'exact'")]
+pub struct InvalidDistinctCacheMode(String);
+
+impl FromStr for DistinctCacheMode {
+    type Err = InvalidDistinctCacheMode;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "fast" => Ok(Self::Fast),
+            "exact" => Ok(Self::Exact),
+            _ => Err(InvalidDistinctCacheMode(s.to_string())),
+        }
+    }
+}
+
+/// What to do with the output columns of a query that share a name with an earlier column
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum DuplicateColumnPolicy {
+    /// Output the columns under the same name
+    #[default]
+    Allow,
+    /// Suffix the name of each duplicate with the lowest number that makes it unique, so that,
+    /// e.g., the columns `a`, `a` are output as `a`, `a_1`
+    Rename,
+    /// Fail the query with [`QueryExecutorError::DuplicateOutputColumn`]
+    Reject,
+}
+
+#[derive(Debug, thiserror::Error)]
+#[error("invalid duplicate column policy '{0}', expected one of 'allow', 'rename', or 'reject'")]
+pub struct InvalidDuplicateColumnPolicy(String);
+
+impl FromStr for DuplicateColumnPolicy {
+    type Err = InvalidDuplicateColumnPolicy;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "allow" => Ok(Self::Allow),
+            "rename" => Ok(Self::Rename),
+            "reject" => Ok(Self::Reject),
+            _ => Err(InvalidDuplicateColumnPolicy(s.to_string())),
+        }
+    }
 }
 
 #[async_trait]
 pub trait QueryExecutor: QueryDatabase + Debug + Send + Sync + 'static {
+    /// Run the query `q` against the `database`
+    ///
+    /// Dropping the returned stream before it has been read to the end cancels the execution of
+    /// the query, and records it as failed in the query log.
     async fn query(
         &self,
         database: &str,
@@ -36,11 +552,37 @@ pub trait QueryExecutor: QueryDatabase + Debug + Send + Sync + 'static {
         kind: QueryKind,
         span_ctx: Option<SpanContext>,
         external_span_ctx: Option<RequestLogContext>,
+    ) -> Result<SendableRecordBatchStream, QueryExecutorError> {
+        self.query_with_options(
+            database,
+            q,
+            params,
+            kind,
+            QueryOptions::default(),
+            span_ctx,
+            external_span_ctx,
+        )
+        .await
+    }
+
+    #[allow(clippy::too_many_arguments)]
+    async fn query_with_options(
+        &self,
+        database: &str,
+        q: &str,
+        params: Option<StatementParams>,
+        kind: QueryKind,
+        options: QueryOptions,
+        span_ctx: Option<SpanContext>,
+        external_span_ctx: Option<RequestLogContext>,
     ) -> Result<SendableRecordBatchStream, QueryExecutorError>;
 
+    /// List the databases, including those marked as deleted if `include_deleted` is set, and
+    /// the size of each if `include_stats` is set
     fn show_databases(
         &self,
         include_deleted: bool,
+        include_stats: bool,
     ) -> Result<SendableRecordBatchStream, QueryExecutorError>;
 
     async fn show_retention_policies(
@@ -56,15 +598,23 @@ pub trait QueryExecutor: QueryDatabase + Debug + Send + Sync + 'static {
 pub enum QueryKind {
     Sql,
     InfluxQl,
+    /// InfluxQL, planned as [`Self::InfluxQl`], whose results describe how their rows are
+    /// grouped into the series of the InfluxDB 1.x query API in the metadata of their schema
+    InfluxQlV1Compat,
 }
 
 impl QueryKind {
     pub fn query_type(&self) -> &'static str {
         match self {
             Self::Sql => "sql",
-            Self::InfluxQl => "influxql",
+            Self::InfluxQl | Self::InfluxQlV1Compat => "influxql",
         }
     }
+
+    /// Whether the query is InfluxQL, whatever the shape of its results
+    pub fn is_influxql(&self) -> bool {
+        matches!(self, Self::InfluxQl | Self::InfluxQlV1Compat)
+    }
 }
 
 #[derive(Debug, Copy, Clone)]
@@ -95,12 +645,13 @@ impl QueryDatabase for UnimplementedQueryExecutor {
 
 #[async_trait]
 impl QueryExecutor for UnimplementedQueryExecutor {
-    async fn query(
+    async fn query_with_options(
         &self,
         _database: &str,
         _q: &str,
         _params: Option<StatementParams>,
         _kind: QueryKind,
+        _options: QueryOptions,
         _span_ctx: Option<SpanContext>,
         _external_span_ctx: Option<RequestLogContext>,
     ) -> Result<SendableRecordBatchStream, QueryExecutorError> {
@@ -112,6 +663,7 @@ impl QueryExecutor for UnimplementedQueryExecutor {
     fn show_databases(
         &self,
         _include_deleted: bool,
+        _include_stats: bool,
     ) -> Result<SendableRecordBatchStream, QueryExecutorError> {
         Err(QueryExecutorError::DatabaseNotFound {
             db_name: "unimplemented".to_string(),
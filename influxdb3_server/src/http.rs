@@ -1,6 +1,7 @@
 //! HTTP API service implementations for `server`
 
 use crate::CommonServerState;
+use arrow::datatypes::DataType;
 use arrow::record_batch::RecordBatch;
 use arrow::util::pretty;
 use authz::http::AuthorizationHeaderExtension;
@@ -23,7 +24,9 @@ use hyper::{Body, Method, Request, Response, StatusCode};
 use influxdb3_cache::distinct_cache::{self, CreateDistinctCacheArgs, MaxAge, MaxCardinality};
 use influxdb3_cache::last_cache;
 use influxdb3_catalog::catalog::Error as CatalogError;
-use influxdb3_internal_api::query_executor::{QueryExecutor, QueryExecutorError, QueryKind};
+use influxdb3_internal_api::query_executor::{
+    QueryExecutor, QueryExecutorError, QueryKind, QueryOptions, StorageHint, TimePrecision,
+};
 use influxdb3_process::{INFLUXDB3_GIT_HASH_SHORT, INFLUXDB3_VERSION};
 use influxdb3_processing_engine::manager::ProcessingEngineManager;
 use influxdb3_wal::{PluginType, TriggerSpecificationDefinition};
@@ -197,6 +200,17 @@ pub enum Error {
     #[error("must provide only one InfluxQl statement per query")]
     InfluxqlSingleStatement,
 
+    #[error("invalid type '{data_type}' to cast column '{column}' to: {source}")]
+    InvalidColumnCastType {
+        column: String,
+        data_type: String,
+        #[source]
+        source: arrow::error::ArrowError,
+    },
+
+    #[error("invalid query option '{name}': {reason}")]
+    InvalidQueryOption { name: &'static str, reason: String },
+
     #[error("must specify a 'db' parameter, or provide the database in the InfluxQL query")]
     InfluxqlNoDatabase,
 
@@ -244,8 +258,18 @@ struct ErrorMessage<T: Serialize> {
 
 impl Error {
     /// Convert this error into an HTTP [`Response`]
+    ///
+    /// A query that fails part way through, e.g., because it times out while it is being
+    /// planned, gets the same response as if it had failed up front.
     fn into_response(self) -> Response<Body> {
         debug!(error = ?self, "API error");
+        match self {
+            Self::Query(e) => Self::Query(e.into_root()).response(),
+            e => e.response(),
+        }
+    }
+
+    fn response(self) -> Response<Body> {
         match self {
             Self::WriteBuffer(err @ WriteBufferError::DatabaseNotFound { db_name: _ }) => {
                 Response::builder()
@@ -394,7 +418,10 @@ impl Error {
                     .body(body)
                     .unwrap()
             }
-            Self::Query(QueryExecutorError::DatabaseNotFound { .. }) => {
+            Self::Query(
+                QueryExecutorError::DatabaseNotFound { .. }
+                | QueryExecutorError::ParquetFileNotFound { .. },
+            ) => {
                 let err: ErrorMessage<()> = ErrorMessage {
                     error: self.to_string(),
                     data: None,
@@ -406,6 +433,79 @@ impl Error {
                     .body(body)
                     .unwrap()
             }
+            Self::Query(QueryExecutorError::TableAlreadyExists { .. }) => {
+                let err: ErrorMessage<()> = ErrorMessage {
+                    error: self.to_string(),
+                    data: None,
+                };
+                let serialized = serde_json::to_string(&err).unwrap();
+                let body = Body::from(serialized);
+                Response::builder()
+                    .status(StatusCode::CONFLICT)
+                    .body(body)
+                    .unwrap()
+            }
+            Self::Query(
+                QueryExecutorError::Unavailable { .. }
+                | QueryExecutorError::ReplayInProgress
+                | QueryExecutorError::MemoryPressure { .. },
+            ) => {
+                let err: ErrorMessage<()> = ErrorMessage {
+                    error: self.to_string(),
+                    data: None,
+                };
+                let serialized = serde_json::to_string(&err).unwrap();
+                let body = Body::from(serialized);
+                Response::builder()
+                    .status(StatusCode::SERVICE_UNAVAILABLE)
+                    .body(body)
+                    .unwrap()
+            }
+            Self::Query(
+                QueryExecutorError::Timeout { .. } | QueryExecutorError::PlanningTimeout { .. },
+            ) => {
+                let err: ErrorMessage<()> = ErrorMessage {
+                    error: self.to_string(),
+                    data: None,
+                };
+                let serialized = serde_json::to_string(&err).unwrap();
+                let body = Body::from(serialized);
+                Response::builder()
+                    .status(StatusCode::GATEWAY_TIMEOUT)
+                    .body(body)
+                    .unwrap()
+            }
+            Self::Query(
+                QueryExecutorError::UnknownColumn { .. }
+                | QueryExecutorError::TableNotReady { .. }
+                | QueryExecutorError::InvalidColumnCast { .. }
+                | QueryExecutorError::FieldTypeConflict { .. }
+                | QueryExecutorError::UnboundedJoin { .. }
+                | QueryExecutorError::InvalidDuration { .. }
+                | QueryExecutorError::InvalidParameter { .. }
+                | QueryExecutorError::PartialAggregateUnsupported { .. }
+                | QueryExecutorError::TimeRangeTooLarge { .. }
+                | QueryExecutorError::TooManyTimeBuckets { .. }
+                | QueryExecutorError::TableRewriteTargetNotFound { .. }
+                | QueryExecutorError::GroupByField { .. }
+                | QueryExecutorError::TimePredicateRequired { .. }
+                | QueryExecutorError::InvalidCreateTableAs { .. }
+                | QueryExecutorError::StorageRequestBudgetExceeded { .. }
+                | QueryExecutorError::ResourceBudgetExceeded { .. }
+                | QueryExecutorError::PlanTooComplex { .. }
+                | QueryExecutorError::DuplicateOutputColumn { .. },
+            ) => {
+                let err: ErrorMessage<()> = ErrorMessage {
+                    error: self.to_string(),
+                    data: None,
+                };
+                let serialized = serde_json::to_string(&err).unwrap();
+                let body = Body::from(serialized);
+                Response::builder()
+                    .status(StatusCode::BAD_REQUEST)
+                    .body(body)
+                    .unwrap()
+            }
             Self::SerdeJson(_) => Response::builder()
                 .status(StatusCode::BAD_REQUEST)
                 .body(Body::from(self.to_string()))
@@ -425,7 +525,9 @@ impl Error {
             Self::MissingQueryParams
             | Self::MissingQueryV1Params
             | Self::MissingWriteParams
-            | Self::MissingDeleteDatabaseParams => Response::builder()
+            | Self::MissingDeleteDatabaseParams
+            | Self::InvalidColumnCastType { .. }
+            | Self::InvalidQueryOption { .. } => Response::builder()
                 .status(StatusCode::BAD_REQUEST)
                 .body(Body::from(self.to_string()))
                 .unwrap(),
@@ -537,13 +639,27 @@ where
             query_str,
             format,
             params,
+            column_casts,
+            options,
         } = self.extract_query_request::<String>(req).await?;
 
         info!(%database, %query_str, ?format, "handling query_sql");
 
+        let options = QueryOptions {
+            column_casts: column_casts.unwrap_or_default(),
+            ..options.unwrap_or_default().into_query_options()?
+        };
         let stream = self
             .query_executor
-            .query(&database, &query_str, params, QueryKind::Sql, None, None)
+            .query_with_options(
+                &database,
+                &query_str,
+                params,
+                QueryKind::Sql,
+                options,
+                None,
+                None,
+            )
             .await?;
 
         Response::builder()
@@ -559,12 +675,18 @@ where
             query_str,
             format,
             params,
+            column_casts,
+            options,
         } = self.extract_query_request::<Option<String>>(req).await?;
 
         info!(?database, %query_str, ?format, "handling query_influxql");
 
+        let options = QueryOptions {
+            column_casts: column_casts.unwrap_or_default(),
+            ..options.unwrap_or_default().into_query_options()?
+        };
         let stream = self
-            .query_influxql_inner(database, &query_str, params)
+            .query_influxql_inner(database, &query_str, params, options)
             .await?;
 
         Response::builder()
@@ -690,24 +812,37 @@ where
     async fn extract_query_request<D: DeserializeOwned>(
         &self,
         req: Request<Body>,
-    ) -> Result<QueryRequest<D, QueryFormat, StatementParams>> {
+    ) -> Result<QueryRequest<D, QueryFormat, StatementParams, ColumnCasts, QueryOptionParams>> {
         let header_format = QueryFormat::try_from_headers(req.headers())?;
         let request = match *req.method() {
             Method::GET => {
                 let query = req.uri().query().ok_or(Error::MissingQueryParams)?;
-                let r = serde_urlencoded::from_str::<QueryRequest<D, Option<QueryFormat>, String>>(
-                    query,
-                )?;
+                let r = serde_urlencoded::from_str::<
+                    QueryRequest<D, Option<QueryFormat>, String, String, String>,
+                >(query)?;
                 QueryRequest {
                     database: r.database,
                     query_str: r.query_str,
                     format: r.format,
                     params: r.params.map(|s| serde_json::from_str(&s)).transpose()?,
+                    column_casts: r
+                        .column_casts
+                        .map(|s| serde_json::from_str(&s))
+                        .transpose()?,
+                    options: r.options.map(|s| serde_json::from_str(&s)).transpose()?,
                 }
             }
             Method::POST => {
                 let body = self.read_body(req).await?;
-                serde_json::from_slice(body.as_ref())?
+                serde_json::from_slice::<
+                    QueryRequest<
+                        D,
+                        Option<QueryFormat>,
+                        StatementParams,
+                        ColumnCastNames,
+                        QueryOptionParams,
+                    >,
+                >(body.as_ref())?
             }
             _ => return Err(Error::UnsupportedMethod),
         };
@@ -717,6 +852,8 @@ where
             query_str: request.query_str,
             format: request.format.unwrap_or(header_format),
             params: request.params,
+            column_casts: request.column_casts.map(parse_column_casts).transpose()?,
+            options: request.options,
         })
     }
 
@@ -729,6 +866,7 @@ where
         database: Option<String>,
         query_str: &str,
         params: Option<StatementParams>,
+        options: QueryOptions,
     ) -> Result<SendableRecordBatchStream> {
         let mut statements = rewrite::parse_statements(query_str)?;
 
@@ -753,7 +891,7 @@ where
         };
 
         if statement.statement().is_show_databases() {
-            self.query_executor.show_databases(true)
+            self.query_executor.show_databases(true, false)
         } else if statement.statement().is_show_retention_policies() {
             self.query_executor
                 .show_retention_policies(database.as_deref(), None)
@@ -764,13 +902,14 @@ where
             };
 
             self.query_executor
-                .query(
+                .query_with_options(
                     &database,
                     // TODO - implement an interface that takes the statement directly,
                     // so we don't need to double down on the parsing
                     &statement.to_statement().to_string(),
                     params,
                     QueryKind::InfluxQl,
+                    options,
                     None,
                     None,
                 )
@@ -1128,8 +1267,11 @@ where
         let ShowDatabasesRequest {
             format,
             show_deleted,
+            show_stats,
         } = serde_urlencoded::from_str(query)?;
-        let stream = self.query_executor.show_databases(show_deleted)?;
+        let stream = self
+            .query_executor
+            .show_databases(show_deleted, show_stats)?;
         Response::builder()
             .status(StatusCode::OK)
             .header(CONTENT_TYPE, format.as_content_type())
@@ -1387,13 +1529,131 @@ pub enum ValidateDbNameError {
 }
 
 #[derive(Debug, Deserialize)]
-pub(crate) struct QueryRequest<D, F, P> {
+pub(crate) struct QueryRequest<D, F, P, C, O> {
     #[serde(rename = "db")]
     pub(crate) database: D,
     #[serde(rename = "q")]
     pub(crate) query_str: String,
     pub(crate) format: F,
     pub(crate) params: Option<P>,
+    /// Cast the named output columns to the given types, see [`QueryOptions::column_casts`],
+    /// which are given as a JSON object of column names to Arrow type names, e.g.,
+    /// `{"usage": "Utf8"}`, that is encoded in the query string of a GET request
+    #[serde(default)]
+    pub(crate) column_casts: Option<C>,
+    /// The options for the query, see [`QueryOptionParams`], which are given as a JSON object
+    /// that is encoded in the query string of a GET request, e.g., `{"storage": "read_buffer"}`
+    #[serde(default)]
+    pub(crate) options: Option<O>,
+}
+
+/// The options that a query request can give for the query, which are those of the
+/// [`QueryOptions`] that a client can choose for itself
+///
+/// Queries made over Flight cannot give these, as their tickets have no room for them, and so
+/// are run with the default options.
+#[derive(Debug, Default, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub(crate) struct QueryOptionParams {
+    /// The storage tiers that the query reads data from, one of `both`, `read_buffer`, or
+    /// `object_store`, see [`QueryOptions::storage`]
+    #[serde(default)]
+    storage: Option<String>,
+    /// Allow joins without a join condition regardless of the server's cross join row limit,
+    /// see [`QueryOptions::allow_cross_joins`]
+    #[serde(default)]
+    allow_cross_joins: bool,
+    /// Allow the query to span more time than the server's maximum query time range, see
+    /// [`QueryOptions::allow_unbounded_time_range`]
+    ///
+    /// The server authorizes requests with a single token, so any client that may query it is
+    /// trusted to lift the limit for its own queries.
+    #[serde(default)]
+    allow_unbounded_time_range: bool,
+    /// Record the query in the query log, which is on unless this is `false`, see
+    /// [`QueryOptions::log`]
+    #[serde(default)]
+    log: Option<bool>,
+    /// Output the `time` column of InfluxQL queries as integer timestamps in this unit, one of
+    /// `ns`, `us`, `ms`, or `s`, see [`QueryOptions::influxql_epoch`]
+    #[serde(default)]
+    epoch: Option<String>,
+    /// Read the table named by each value wherever the query references the table named by its
+    /// key, see [`QueryOptions::table_rewrites`]
+    #[serde(default)]
+    table_rewrites: std::collections::HashMap<String, String>,
+    /// Fail the query if it would make more than this many GET requests to the object store,
+    /// in place of the server's maximum, see [`QueryOptions::max_storage_requests`]
+    ///
+    /// As for [`Self::allow_unbounded_time_range`], any client that may query the server is
+    /// trusted to set this for its own queries.
+    #[serde(default)]
+    max_storage_requests: Option<usize>,
+    /// Count the distinct values of InfluxQL `count(distinct(...))` aggregates approximately, see
+    /// [`QueryOptions::approx_count_distinct`]
+    #[serde(default)]
+    approx_count_distinct: bool,
+}
+
+impl QueryOptionParams {
+    /// The [`QueryOptions`] given by these parameters, where those that are not given are left
+    /// at their defaults
+    pub(crate) fn into_query_options(self) -> Result<QueryOptions> {
+        let mut options = QueryOptions {
+            allow_cross_joins: self.allow_cross_joins,
+            allow_unbounded_time_range: self.allow_unbounded_time_range,
+            table_rewrites: self.table_rewrites,
+            max_storage_requests: self.max_storage_requests,
+            approx_count_distinct: self.approx_count_distinct,
+            ..Default::default()
+        };
+        if let Some(log) = self.log {
+            options.log = log;
+        }
+        if let Some(epoch) = self.epoch {
+            options.influxql_epoch = Some(
+                epoch
+                    .parse::<TimePrecision>()
+                    .map_err(invalid_query_option("epoch"))?,
+            );
+        }
+        if let Some(storage) = self.storage {
+            options.storage = storage
+                .parse::<StorageHint>()
+                .map_err(invalid_query_option("storage"))?;
+        }
+        Ok(options)
+    }
+}
+
+/// Map an error parsing the query option with the given `name` to an [`Error`]
+fn invalid_query_option<E: std::fmt::Display>(name: &'static str) -> impl FnOnce(E) -> Error {
+    move |e| Error::InvalidQueryOption {
+        name,
+        reason: e.to_string(),
+    }
+}
+
+/// The types to cast the output columns of a query to, by column name
+type ColumnCasts = std::collections::HashMap<String, DataType>;
+
+/// The names of the types to cast the output columns of a query to, by column name, as they
+/// are given in a query request
+type ColumnCastNames = std::collections::HashMap<String, String>;
+
+/// Parse the names of the types that each of the `casts` casts its column to
+fn parse_column_casts(casts: ColumnCastNames) -> Result<ColumnCasts> {
+    casts
+        .into_iter()
+        .map(|(column, data_type)| match data_type.parse() {
+            Ok(parsed) => Ok((column, parsed)),
+            Err(source) => Err(Error::InvalidColumnCastType {
+                column,
+                data_type,
+                source,
+            }),
+        })
+        .collect()
 }
 
 #[derive(Debug, Deserialize)]
@@ -1635,6 +1895,8 @@ struct ShowDatabasesRequest {
     format: QueryFormat,
     #[serde(default)]
     show_deleted: bool,
+    #[serde(default)]
+    show_stats: bool,
 }
 
 #[derive(Debug, Deserialize)]
@@ -1813,7 +2075,14 @@ fn legacy_write_error_to_response(e: WriteParseError) -> Response<Body> {
 
 #[cfg(test)]
 mod tests {
+    use std::time::Duration;
+
+    use datafusion::error::DataFusionError;
+    use hyper::StatusCode;
+    use influxdb3_internal_api::query_executor::QueryExecutorError;
+
     use super::validate_db_name;
+    use super::Error;
     use super::ValidateDbNameError;
 
     macro_rules! assert_validate_db_name {
@@ -1842,4 +2111,34 @@ mod tests {
         assert_validate_db_name!("_foo", false, Err(ValidateDbNameError::InvalidStartChar));
         assert_validate_db_name!("", false, Err(ValidateDbNameError::Empty));
     }
+
+    #[test]
+    fn query_error_status() {
+        let status = |e: QueryExecutorError| Error::Query(e).into_response().status();
+        let timeout = || QueryExecutorError::Timeout {
+            elapsed: Duration::from_secs(1),
+        };
+        let budget = || QueryExecutorError::ResourceBudgetExceeded { used: 2, max: 1 };
+        assert_eq!(StatusCode::GATEWAY_TIMEOUT, status(timeout()));
+        assert_eq!(
+            StatusCode::GATEWAY_TIMEOUT,
+            status(QueryExecutorError::PlanningTimeout {
+                limit: Duration::from_secs(1)
+            })
+        );
+        assert_eq!(StatusCode::BAD_REQUEST, status(budget()));
+        // including when raised part way through a query:
+        assert_eq!(
+            StatusCode::GATEWAY_TIMEOUT,
+            status(QueryExecutorError::QueryPlanning(
+                DataFusionError::External(Box::new(timeout()))
+            ))
+        );
+        assert_eq!(
+            StatusCode::BAD_REQUEST,
+            status(QueryExecutorError::ExecuteStream(
+                DataFusionError::External(Box::new(budget()))
+            ))
+        );
+    }
 }
@@ -29,7 +29,7 @@ use schema::{INFLUXQL_MEASUREMENT_COLUMN_NAME, TIME_COLUMN_NAME};
 use serde::{Deserialize, Serialize};
 use serde_json::Value;
 
-use super::{Error, HttpApi, Result};
+use super::{Error, HttpApi, QueryOptionParams, Result};
 
 const DEFAULT_CHUNK_SIZE: usize = 10_000;
 
@@ -65,15 +65,27 @@ where
             epoch,
             pretty,
             query,
+            options,
         } = qualified_params;
 
         if pretty {
             format = format.to_pretty();
         }
 
+        let options = options.into_query_options()?;
+        // the API formats the time column itself, as given by its own `epoch` parameter:
+        if options.influxql_epoch.is_some() {
+            return Err(Error::InvalidQueryOption {
+                name: "epoch",
+                reason: "use the epoch parameter of the v1 query API".to_string(),
+            });
+        }
+
         // TODO - Currently not supporting parameterized queries, see
         //        https://github.com/influxdata/influxdb/issues/24805
-        let stream = self.query_influxql_inner(database, &query, None).await?;
+        let stream = self
+            .query_influxql_inner(database, &query, None, options)
+            .await?;
         let stream =
             QueryResponseStream::new(0, stream, chunk_size, format, epoch).map_err(QueryError)?;
         let body = Body::wrap_stream(stream);
@@ -116,6 +128,10 @@ struct QueryParams {
     /// then from the body and combine the two sources.
     #[serde(rename = "q")]
     query: Option<String>,
+    /// The options for the query, given as a JSON object, see [`QueryOptionParams`]
+    ///
+    /// This is not a parameter of the original API.
+    options: Option<String>,
 }
 
 impl QueryParams {
@@ -134,6 +150,7 @@ impl QueryParams {
             epoch: self.epoch.or(other.epoch),
             pretty: self.pretty.or(other.pretty),
             query: self.query.or(other.query),
+            options: self.options.or(other.options),
         }
     }
 
@@ -144,12 +161,18 @@ impl QueryParams {
             .unwrap_or_default()
             .then(|| self.chunk_size.unwrap_or(DEFAULT_CHUNK_SIZE));
         let query = self.query.ok_or(Error::MissingQueryV1Params)?;
+        let options = self
+            .options
+            .map(|s| serde_json::from_str(&s))
+            .transpose()?
+            .unwrap_or_default();
         Ok(QualifiedQueryParams {
             chunk_size,
             database: self.database,
             epoch: self.epoch,
             pretty: self.pretty.unwrap_or_default(),
             query,
+            options,
         })
     }
 }
@@ -162,6 +185,7 @@ struct QualifiedQueryParams {
     epoch: Option<Precision>,
     pretty: bool,
     query: String,
+    options: QueryOptionParams,
 }
 
 /// Enum representing the query format for the v1/query API.
@@ -653,17 +677,20 @@ impl QueryResponseStream {
 }
 
 /// Convert an epoch time in nanoseconds to the provided precision
+///
+/// Times are floored, so that those before the epoch fall in the same interval of the precision,
+/// e.g., the same `GROUP BY time()` bucket, as they do in nanoseconds.
 fn convert_ns_epoch(value: Value, precision: Precision) -> Result<Value, anyhow::Error> {
     let epoch_ns = value
         .as_i64()
         .context("the provided nanosecond epoch time was not a valid i64")?;
     Ok(match precision {
         Precision::Nanoseconds => epoch_ns,
-        Precision::Microseconds => epoch_ns / 1_000,
-        Precision::Milliseconds => epoch_ns / 1_000_000,
-        Precision::Seconds => epoch_ns / 1_000_000_000,
-        Precision::Minutes => epoch_ns / (1_000_000_000 * 60),
-        Precision::Hours => epoch_ns / (1_000_000_000 * 60 * 60),
+        Precision::Microseconds => epoch_ns.div_euclid(1_000),
+        Precision::Milliseconds => epoch_ns.div_euclid(1_000_000),
+        Precision::Seconds => epoch_ns.div_euclid(1_000_000_000),
+        Precision::Minutes => epoch_ns.div_euclid(1_000_000_000 * 60),
+        Precision::Hours => epoch_ns.div_euclid(1_000_000_000 * 60 * 60),
     }
     .into())
 }
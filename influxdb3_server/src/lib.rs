@@ -212,7 +212,10 @@ pub async fn wait_for_signal() {
 mod tests {
     use crate::auth::DefaultAuthorizer;
     use crate::builder::ServerBuilder;
-    use crate::query_executor::{CreateQueryExecutorArgs, QueryExecutorImpl};
+    use crate::query_executor::{
+        CreateQueryExecutorArgs, QueryExecutorImpl, AUTOGEN_RETENTION_POLICY,
+        DEFAULT_QUERY_COST_ROW_WEIGHT, DEFAULT_QUERY_JOB_TTL,
+    };
     use crate::serve;
     use datafusion::parquet::data_type::AsBytes;
     use hyper::{body, Body, Client, Request, Response, StatusCode};
@@ -803,11 +806,38 @@ mod tests {
             catalog: write_buffer.catalog(),
             write_buffer: Arc::clone(&write_buffer),
             exec: Arc::clone(&exec),
+            batch_exec: None,
             metrics: Arc::clone(&metrics),
+            time_provider: Arc::clone(&time_provider) as _,
             datafusion_config: Default::default(),
             query_log_size: 10,
             telemetry_store: Arc::clone(&sample_telem_store),
             sys_events_store: Arc::clone(&sys_events_store),
+            persister: Arc::clone(&persister),
+            query_job_ttl: DEFAULT_QUERY_JOB_TTL,
+            aggregate_mem_pool_size: None,
+            mem_pool_high_water_mark: None,
+            max_transient_retries: 0,
+            max_planning_time: None,
+            slow_planning_threshold: None,
+            slow_query_threshold: None,
+            cross_join_row_limit: None,
+            max_query_time_range: None,
+            max_time_buckets: None,
+            max_storage_requests: None,
+            max_plan_nodes: None,
+            max_query_cost: None,
+            query_timeout: None,
+            query_cost_row_weight: DEFAULT_QUERY_COST_ROW_WEIGHT,
+            result_cache_size: None,
+            coalesce_buffer_size: None,
+            default_retention_policy: AUTOGEN_RETENTION_POLICY.to_string(),
+            parquet_cache: None,
+            scan_read_ahead: None,
+            scan_read_ahead_bytes: 0,
+            replay_policy: Default::default(),
+            column_names: Default::default(),
+            max_concurrent_queries: None,
         });
 
         // bind to port 0 will assign a random available port:
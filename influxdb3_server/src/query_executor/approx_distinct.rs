@@ -0,0 +1,105 @@
+//! Approximate counts of distinct values, see [`QueryOptions::approx_count_distinct`][approx]
+//!
+//! The InfluxQL planner plans `count(distinct(...))` as an exact `COUNT(DISTINCT ...)`, which is
+//! rewritten to the HyperLogLog based `approx_distinct` aggregate once the query is planned. The
+//! approximate count is cast back to the type of the exact count, and output under its name, so
+//! that the rest of the plan is unchanged.
+//!
+//! [approx]: influxdb3_internal_api::query_executor::QueryOptions::approx_count_distinct
+use std::sync::Arc;
+
+use arrow::datatypes::DataType;
+use datafusion::{
+    common::{
+        tree_node::{Transformed, TreeNode},
+        Column,
+    },
+    config::ConfigOptions,
+    error::DataFusionError,
+    functions_aggregate::expr_fn::approx_distinct,
+    logical_expr::{
+        cast,
+        expr::{AggregateFunction, Alias},
+        Aggregate, Expr, LogicalPlan, Projection,
+    },
+    optimizer::analyzer::AnalyzerRule,
+};
+
+/// Count the distinct values of `count(distinct(...))` aggregates approximately
+#[derive(Debug, Default)]
+pub(super) struct ApproxCountDistinct;
+
+impl AnalyzerRule for ApproxCountDistinct {
+    fn analyze(
+        &self,
+        plan: LogicalPlan,
+        _config: &ConfigOptions,
+    ) -> Result<LogicalPlan, DataFusionError> {
+        plan.transform_up_with_subqueries(|plan| match plan {
+            LogicalPlan::Aggregate(aggregate) => approximate(aggregate),
+            plan => Ok(Transformed::no(plan)),
+        })
+        .map(|transformed| transformed.data)
+    }
+
+    fn name(&self) -> &str {
+        "approx_count_distinct"
+    }
+}
+
+/// Replace the exact distinct counts of the `aggregate` with approximate ones, projected under
+/// the names and types of the exact counts
+fn approximate(aggregate: Aggregate) -> Result<Transformed<LogicalPlan>, DataFusionError> {
+    // the name of each approximate count in the rewritten aggregate, and of the exact count that
+    // it replaces:
+    let mut renames = vec![];
+    let aggr_expr = aggregate
+        .aggr_expr
+        .iter()
+        .map(|expr| match approx_count(expr) {
+            Some(approx) => {
+                let name = format!("__approx_count_distinct_{}", renames.len());
+                renames.push((name.clone(), expr.schema_name().to_string()));
+                approx.alias(name)
+            }
+            None => expr.clone(),
+        })
+        .collect::<Vec<_>>();
+    if renames.is_empty() {
+        return Ok(Transformed::no(LogicalPlan::Aggregate(aggregate)));
+    }
+
+    let rewritten = Aggregate::try_new(aggregate.input, aggregate.group_expr, aggr_expr)?;
+    let exprs = rewritten
+        .schema
+        .iter()
+        .map(|(qualifier, field)| {
+            let column = Expr::Column(Column::new(qualifier.cloned(), field.name()));
+            match renames.iter().find(|(approx, _)| approx == field.name()) {
+                Some((_, exact)) => cast(column, DataType::Int64).alias(exact),
+                None => column,
+            }
+        })
+        .collect();
+    let projection = Projection::try_new(exprs, Arc::new(LogicalPlan::Aggregate(rewritten)))?;
+    Ok(Transformed::yes(LogicalPlan::Projection(projection)))
+}
+
+/// The approximate form of the `expr`, if it counts the distinct values of a single expression
+fn approx_count(expr: &Expr) -> Option<Expr> {
+    let expr = match expr {
+        Expr::Alias(Alias { expr, .. }) => expr.as_ref(),
+        expr => expr,
+    };
+    match expr {
+        Expr::AggregateFunction(AggregateFunction {
+            func,
+            args,
+            distinct: true,
+            filter: None,
+            order_by: None,
+            ..
+        }) if func.name() == "count" && args.len() == 1 => Some(approx_distinct(args[0].clone())),
+        _ => None,
+    }
+}
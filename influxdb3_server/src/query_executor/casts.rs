@@ -0,0 +1,273 @@
+//! Casts of query output columns to the types requested in [`QueryOptions::column_casts`], to
+//! the unit requested in [`QueryOptions::time_precision`], and to the formats requested in
+//! [`QueryOptions::influxql_boolean_format`] and [`QueryOptions::influxql_epoch`]
+//!
+//! [`QueryOptions::column_casts`]: influxdb3_internal_api::query_executor::QueryOptions
+//! [`QueryOptions::time_precision`]: influxdb3_internal_api::query_executor::QueryOptions
+//! [`QueryOptions::influxql_boolean_format`]: influxdb3_internal_api::query_executor::QueryOptions
+//! [`QueryOptions::influxql_epoch`]: influxdb3_internal_api::query_executor::QueryOptions
+use std::{collections::HashMap, sync::Arc};
+
+use arrow::{
+    compute::can_cast_types,
+    datatypes::{DataType, Field, Schema, TimeUnit},
+};
+use datafusion::{
+    error::DataFusionError,
+    logical_expr::Operator,
+    physical_plan::{
+        expressions::{binary, cast, col, lit, CaseExpr},
+        projection::ProjectionExec,
+        ExecutionPlan, PhysicalExpr,
+    },
+};
+use influxdb3_internal_api::query_executor::{BooleanFormat, QueryExecutorError, TimePrecision};
+use schema::TIME_COLUMN_NAME;
+
+use super::suggestions;
+
+/// Apply a final projection to the `plan` that casts its output columns to the types given in
+/// `casts`, keyed by column name
+///
+/// The `plan` is returned unchanged if there are no casts to apply.
+pub(super) fn apply_column_casts(
+    plan: Arc<dyn ExecutionPlan>,
+    casts: &HashMap<String, DataType>,
+) -> Result<Arc<dyn ExecutionPlan>, QueryExecutorError> {
+    if casts.is_empty() {
+        return Ok(plan);
+    }
+    let schema = plan.schema();
+
+    if let Some(name) = casts
+        .keys()
+        .find(|name| schema.column_with_name(name).is_none())
+    {
+        let candidates = schema
+            .fields()
+            .iter()
+            .map(|f| f.name().to_owned())
+            .collect::<Vec<_>>();
+        return Err(QueryExecutorError::UnknownColumn {
+            name: name.to_owned(),
+            suggestions: suggestions::closest_matches(name, &candidates),
+        });
+    }
+
+    project_casts(plan, |field| {
+        let to = casts.get(field.name())?;
+        (to != field.data_type()).then(|| to.clone())
+    })
+}
+
+/// Apply a final projection to the `plan` that converts all of its timestamp output columns to
+/// the unit given by the `precision`
+///
+/// Timestamps converted to a coarser unit are floored, rather than truncated towards zero, so
+/// that each lands on the start of the interval of that unit that it falls in, in the same way as
+/// the buckets of an InfluxQL `GROUP BY time()`. Otherwise, rows before the epoch would be moved
+/// forward into the next interval, and across the boundary of the bucket that they belong to.
+///
+/// The `plan` is returned unchanged if it has no timestamp columns to convert.
+pub(super) fn apply_time_precision(
+    plan: Arc<dyn ExecutionPlan>,
+    precision: TimePrecision,
+) -> Result<Arc<dyn ExecutionPlan>, QueryExecutorError> {
+    let unit = time_unit(precision);
+    let schema = plan.schema();
+    if !schema
+        .fields()
+        .iter()
+        .any(|field| matches!(field.data_type(), DataType::Timestamp(from, _) if *from != unit))
+    {
+        return Ok(plan);
+    }
+    let exprs = schema
+        .fields()
+        .iter()
+        .map(|field| {
+            let name = field.name();
+            let expr = col(name, &schema)?;
+            let expr = match field.data_type() {
+                DataType::Timestamp(from, tz) if unit_nanos(*from) < unit_nanos(unit) => {
+                    let divisor = unit_nanos(unit) / unit_nanos(*from);
+                    let floored =
+                        floor_div(cast(expr, &schema, DataType::Int64)?, divisor, &schema)?;
+                    cast(floored, &schema, DataType::Timestamp(unit, tz.clone()))?
+                }
+                DataType::Timestamp(from, tz) if *from != unit => {
+                    cast(expr, &schema, DataType::Timestamp(unit, tz.clone()))?
+                }
+                _ => expr,
+            };
+            Ok((expr, name.to_owned()))
+        })
+        .collect::<Result<Vec<_>, DataFusionError>>()
+        .map_err(QueryExecutorError::QueryPlanning)?;
+
+    Ok(Arc::new(
+        ProjectionExec::try_new(exprs, plan).map_err(QueryExecutorError::QueryPlanning)?,
+    ))
+}
+
+/// Apply a final projection to the `plan` that converts its `time` output column to integer
+/// timestamps since the epoch in the unit given by the `epoch`, which are floored in the same way
+/// as by [`apply_time_precision`]
+///
+/// The `plan` is returned unchanged if no `epoch` is given, or it has no `time` column.
+pub(super) fn apply_influxql_epoch(
+    plan: Arc<dyn ExecutionPlan>,
+    epoch: Option<TimePrecision>,
+) -> Result<Arc<dyn ExecutionPlan>, QueryExecutorError> {
+    let Some(to) = epoch.map(time_unit) else {
+        return Ok(plan);
+    };
+    let schema = plan.schema();
+    let Some(DataType::Timestamp(from, _)) = schema
+        .field_with_name(TIME_COLUMN_NAME)
+        .ok()
+        .map(|field| field.data_type())
+    else {
+        return Ok(plan);
+    };
+    let from = *from;
+    let exprs = schema
+        .fields()
+        .iter()
+        .map(|field| {
+            let name = field.name();
+            let mut expr = col(name, &schema)?;
+            if name == TIME_COLUMN_NAME {
+                expr = cast(expr, &schema, DataType::Int64)?;
+                if unit_nanos(from) < unit_nanos(to) {
+                    expr = floor_div(expr, unit_nanos(to) / unit_nanos(from), &schema)?;
+                } else if unit_nanos(from) > unit_nanos(to) {
+                    let factor = lit(unit_nanos(from) / unit_nanos(to));
+                    expr = binary(expr, Operator::Multiply, factor, &schema)?;
+                }
+            }
+            Ok((expr, name.to_owned()))
+        })
+        .collect::<Result<Vec<_>, DataFusionError>>()
+        .map_err(QueryExecutorError::QueryPlanning)?;
+
+    Ok(Arc::new(
+        ProjectionExec::try_new(exprs, plan).map_err(QueryExecutorError::QueryPlanning)?,
+    ))
+}
+
+fn time_unit(precision: TimePrecision) -> TimeUnit {
+    match precision {
+        TimePrecision::Nanosecond => TimeUnit::Nanosecond,
+        TimePrecision::Microsecond => TimeUnit::Microsecond,
+        TimePrecision::Millisecond => TimeUnit::Millisecond,
+        TimePrecision::Second => TimeUnit::Second,
+    }
+}
+
+fn unit_nanos(unit: TimeUnit) -> i64 {
+    match unit {
+        TimeUnit::Nanosecond => 1,
+        TimeUnit::Microsecond => 1_000,
+        TimeUnit::Millisecond => 1_000_000,
+        TimeUnit::Second => 1_000_000_000,
+    }
+}
+
+/// Divide the integer `expr` by the positive `divisor`, rounding towards negative infinity, as
+/// `(expr - ((expr % divisor) + divisor) % divisor) / divisor`
+fn floor_div(
+    expr: Arc<dyn PhysicalExpr>,
+    divisor: i64,
+    schema: &Schema,
+) -> Result<Arc<dyn PhysicalExpr>, DataFusionError> {
+    let remainder = binary(Arc::clone(&expr), Operator::Modulo, lit(divisor), schema)?;
+    let remainder = binary(remainder, Operator::Plus, lit(divisor), schema)?;
+    let remainder = binary(remainder, Operator::Modulo, lit(divisor), schema)?;
+    binary(
+        binary(expr, Operator::Minus, remainder, schema)?,
+        Operator::Divide,
+        lit(divisor),
+        schema,
+    )
+}
+
+/// Apply a final projection to the `plan` that converts all of its boolean output columns to
+/// strings in the given `format`
+///
+/// The `plan` is returned unchanged if the `format` is [`BooleanFormat::Native`], or it has no
+/// boolean columns.
+pub(super) fn apply_boolean_format(
+    plan: Arc<dyn ExecutionPlan>,
+    format: BooleanFormat,
+) -> Result<Arc<dyn ExecutionPlan>, QueryExecutorError> {
+    let (t, f) = match format {
+        BooleanFormat::Native => return Ok(plan),
+        BooleanFormat::Words => ("true", "false"),
+        BooleanFormat::Letters => ("t", "f"),
+    };
+    let schema = plan.schema();
+    if !schema
+        .fields()
+        .iter()
+        .any(|field| field.data_type() == &DataType::Boolean)
+    {
+        return Ok(plan);
+    }
+    let exprs = schema
+        .fields()
+        .iter()
+        .map(|field| {
+            let name = field.name();
+            let mut expr = col(name, &schema)?;
+            if field.data_type() == &DataType::Boolean {
+                expr = Arc::new(CaseExpr::try_new(
+                    Some(expr),
+                    vec![(lit(true), lit(t)), (lit(false), lit(f))],
+                    None,
+                )?) as Arc<dyn PhysicalExpr>;
+            }
+            Ok((expr, name.to_owned()))
+        })
+        .collect::<Result<Vec<_>, DataFusionError>>()
+        .map_err(QueryExecutorError::QueryPlanning)?;
+
+    Ok(Arc::new(
+        ProjectionExec::try_new(exprs, plan).map_err(QueryExecutorError::QueryPlanning)?,
+    ))
+}
+
+/// Project all of the output columns of the `plan`, casting those for which `cast_to` gives a
+/// type
+fn project_casts(
+    plan: Arc<dyn ExecutionPlan>,
+    cast_to: impl Fn(&Field) -> Option<DataType>,
+) -> Result<Arc<dyn ExecutionPlan>, QueryExecutorError> {
+    let schema = plan.schema();
+    let exprs = schema
+        .fields()
+        .iter()
+        .map(|field| {
+            let name = field.name();
+            let expr = col(name, &schema).map_err(QueryExecutorError::QueryPlanning)?;
+            let expr = match cast_to(field) {
+                Some(to) => {
+                    if !can_cast_types(field.data_type(), &to) {
+                        return Err(QueryExecutorError::InvalidColumnCast {
+                            column: name.to_owned(),
+                            from: field.data_type().clone(),
+                            to,
+                        });
+                    }
+                    cast(expr, &schema, to).map_err(QueryExecutorError::QueryPlanning)?
+                }
+                None => expr,
+            };
+            Ok((expr, name.to_owned()))
+        })
+        .collect::<Result<Vec<_>, _>>()?;
+
+    Ok(Arc::new(
+        ProjectionExec::try_new(exprs, plan).map_err(QueryExecutorError::QueryPlanning)?,
+    ))
+}
@@ -0,0 +1,45 @@
+//! Reordering of the chunks scanned by a query, see [`QueryOptions::chunk_order`][chunk_order]
+//!
+//! Rows that share a primary key, i.e., the same tags and time, are deduplicated in favour of the
+//! row from the chunk with the highest [`ChunkOrder`], so renumbering the chunks of a table
+//! changes which of the duplicate rows surfaces in the results of a query.
+//!
+//! [chunk_order]: influxdb3_internal_api::query_executor::QueryOptions::chunk_order
+use std::sync::Arc;
+
+use data_types::ChunkOrder;
+use influxdb3_internal_api::query_executor::ChunkOrderHint;
+use influxdb3_write::chunk::{BufferChunk, ParquetChunk};
+use iox_query::QueryChunk;
+
+use super::time_range;
+
+/// Order the `chunks` as given by the `hint`, renumbering them so that later chunks win
+/// deduplication over earlier ones
+///
+/// Chunks of a type that cannot be renumbered are left with the order that they have.
+pub(super) fn order_chunks(chunks: &mut Vec<Arc<dyn QueryChunk>>, hint: ChunkOrderHint) {
+    match hint {
+        ChunkOrderHint::Stored => return,
+        ChunkOrderHint::Sequence => chunks.sort_by_key(|chunk| chunk.order()),
+        ChunkOrderHint::MinTime => chunks.sort_by_key(|chunk| {
+            (
+                time_range::chunk_time_range(chunk.as_ref()).map(|(min, _)| min),
+                chunk.order(),
+            )
+        }),
+        ChunkOrderHint::Reversed => {
+            chunks.sort_by_key(|chunk| chunk.order());
+            chunks.reverse();
+        }
+    }
+    for (order, chunk) in chunks.iter_mut().enumerate() {
+        let order = ChunkOrder::new(order as i64);
+        let any = chunk.as_any();
+        if let Some(buffer) = any.downcast_ref::<BufferChunk>() {
+            *chunk = Arc::new(buffer.with_chunk_order(order));
+        } else if let Some(parquet) = any.downcast_ref::<ParquetChunk>() {
+            *chunk = Arc::new(parquet.with_chunk_order(order));
+        }
+    }
+}
@@ -0,0 +1,108 @@
+//! Case-insensitive comparison of strings, see [`QueryOptions::collation`][collation]
+//!
+//! Strings are compared regardless of case by rewriting the filters and sorts of the logical plan
+//! to compare the lowercase form of each string, before filters are pushed down to the table
+//! scans, or sorts are removed because the data is already ordered. The strings that are output
+//! are not changed.
+//!
+//! [collation]: influxdb3_internal_api::query_executor::QueryOptions::collation
+use std::sync::Arc;
+
+use arrow::datatypes::DataType;
+use datafusion::{
+    common::{
+        tree_node::{Transformed, TreeNode},
+        DFSchema,
+    },
+    config::ConfigOptions,
+    error::DataFusionError,
+    functions::expr_fn::lower,
+    logical_expr::{cast, expr::InList, BinaryExpr, Expr, ExprSchemable, LogicalPlan, Operator},
+    optimizer::analyzer::AnalyzerRule,
+};
+
+/// Compare the strings in the filters and sorts of a query regardless of their case
+#[derive(Debug, Default)]
+pub(super) struct CaseInsensitiveCollation;
+
+impl AnalyzerRule for CaseInsensitiveCollation {
+    fn analyze(
+        &self,
+        plan: LogicalPlan,
+        _config: &ConfigOptions,
+    ) -> Result<LogicalPlan, DataFusionError> {
+        plan.transform_up_with_subqueries(|plan| match plan {
+            LogicalPlan::Filter(_) => {
+                let schema = Arc::clone(plan.schema());
+                plan.map_expressions(|expr| fold_comparisons(expr, &schema))
+            }
+            LogicalPlan::Sort(_) => {
+                let schema = Arc::clone(plan.schema());
+                plan.map_expressions(|expr| fold_case(expr, &schema))
+            }
+            plan => Ok(Transformed::no(plan)),
+        })
+        .map(|transformed| transformed.data)
+    }
+
+    fn name(&self) -> &str {
+        "case_insensitive_collation"
+    }
+}
+
+/// Rewrite the comparisons of strings in the `expr` to compare their lowercase forms
+fn fold_comparisons(expr: Expr, schema: &DFSchema) -> Result<Transformed<Expr>, DataFusionError> {
+    expr.transform_up(|expr| match expr {
+        Expr::BinaryExpr(BinaryExpr { left, op, right })
+            if matches!(
+                op,
+                Operator::Eq
+                    | Operator::NotEq
+                    | Operator::Lt
+                    | Operator::LtEq
+                    | Operator::Gt
+                    | Operator::GtEq
+            ) && is_string(&left.get_type(schema)?)
+                && is_string(&right.get_type(schema)?) =>
+        {
+            Ok(Transformed::yes(Expr::BinaryExpr(BinaryExpr {
+                left: Box::new(lowercase(*left)),
+                op,
+                right: Box::new(lowercase(*right)),
+            })))
+        }
+        Expr::InList(InList {
+            expr,
+            list,
+            negated,
+        }) if is_string(&expr.get_type(schema)?) => Ok(Transformed::yes(Expr::InList(InList {
+            expr: Box::new(lowercase(*expr)),
+            list: list.into_iter().map(lowercase).collect(),
+            negated,
+        }))),
+        expr => Ok(Transformed::no(expr)),
+    })
+}
+
+/// Replace the `expr` with its lowercase form, if it is a string
+fn fold_case(expr: Expr, schema: &DFSchema) -> Result<Transformed<Expr>, DataFusionError> {
+    Ok(if is_string(&expr.get_type(schema)?) {
+        Transformed::yes(lowercase(expr))
+    } else {
+        Transformed::no(expr)
+    })
+}
+
+/// The lowercase form of the string `expr`, where dictionary encoded strings, i.e., tags, are
+/// decoded first
+fn lowercase(expr: Expr) -> Expr {
+    lower(cast(expr, DataType::Utf8))
+}
+
+fn is_string(data_type: &DataType) -> bool {
+    match data_type {
+        DataType::Utf8 | DataType::LargeUtf8 | DataType::Utf8View => true,
+        DataType::Dictionary(_, value_type) => is_string(value_type),
+        _ => false,
+    }
+}
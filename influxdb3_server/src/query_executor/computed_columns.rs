@@ -0,0 +1,44 @@
+//! Columns of a table that are computed from its stored columns when it is queried, see
+//! [`DatabaseManager::create_computed_column`][create]
+//!
+//! A table with computed columns is queried through a view that projects the stored columns of
+//! the table along with the expression of each computed column. The computed columns that a query
+//! does not select are then pruned from the view by the optimizer, and filters on them are
+//! rewritten in terms of the stored columns, to be pushed down to the scan of the table.
+//!
+//! [create]: influxdb3_write::DatabaseManager::create_computed_column
+use std::{collections::BTreeMap, sync::Arc};
+
+use datafusion::{
+    catalog::TableProvider,
+    datasource::{provider_as_source, ViewTable},
+    error::DataFusionError,
+    execution::context::SessionContext,
+    logical_expr::LogicalPlanBuilder,
+    prelude::{ident, Expr},
+};
+
+/// Wrap the `table` in a view that adds the `computed_columns` to its stored columns, where the
+/// table is referred to as `table_name` by the query
+///
+/// A computed column whose name has since been taken by a stored column of the table is left
+/// out, so that the stored column is queried instead.
+pub(super) fn with_computed_columns(
+    table_name: &str,
+    table: Arc<dyn TableProvider>,
+    computed_columns: &BTreeMap<Arc<str>, Arc<str>>,
+) -> Result<Arc<dyn TableProvider>, DataFusionError> {
+    let schema = table.schema();
+    let scan = LogicalPlanBuilder::scan(table_name, provider_as_source(table), None)?;
+    let ctx = SessionContext::new();
+    let mut columns: Vec<Expr> = schema.fields().iter().map(|f| ident(f.name())).collect();
+    for (name, expression) in computed_columns {
+        if schema.column_with_name(name).is_some() {
+            continue;
+        }
+        let expr = ctx.parse_sql_expr(expression, scan.schema())?;
+        columns.push(expr.alias(name.as_ref()));
+    }
+    let plan = scan.project(columns)?.build()?;
+    Ok(Arc::new(ViewTable::try_new(plan, None)?))
+}
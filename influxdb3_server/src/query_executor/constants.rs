@@ -0,0 +1,55 @@
+//! Named constants that are bound for the duration of a query, see
+//! [`QueryOptions::constants`][constants]
+//!
+//! [constants]: influxdb3_internal_api::query_executor::QueryOptions::constants
+use std::any::Any;
+
+use arrow::datatypes::DataType;
+use datafusion::{
+    error::DataFusionError,
+    logical_expr::{ColumnarValue, ScalarUDF, ScalarUDFImpl, Signature, Volatility},
+    scalar::ScalarValue,
+};
+
+/// Create a function that takes no arguments and returns `value`, so that the constant can be
+/// referenced in queries as `name()`
+pub(super) fn constant_udf(name: &str, value: &ScalarValue) -> ScalarUDF {
+    ScalarUDF::new_from_impl(ConstantUdf {
+        name: name.to_string(),
+        value: value.clone(),
+        signature: Signature::exact(vec![], Volatility::Immutable),
+    })
+}
+
+#[derive(Debug)]
+struct ConstantUdf {
+    name: String,
+    value: ScalarValue,
+    signature: Signature,
+}
+
+impl ScalarUDFImpl for ConstantUdf {
+    fn as_any(&self) -> &dyn Any {
+        self
+    }
+
+    fn name(&self) -> &str {
+        &self.name
+    }
+
+    fn signature(&self) -> &Signature {
+        &self.signature
+    }
+
+    fn return_type(&self, _arg_types: &[DataType]) -> Result<DataType, DataFusionError> {
+        Ok(self.value.data_type())
+    }
+
+    fn invoke(&self, _args: &[ColumnarValue]) -> Result<ColumnarValue, DataFusionError> {
+        Ok(ColumnarValue::Scalar(self.value.clone()))
+    }
+
+    fn invoke_no_args(&self, _number_rows: usize) -> Result<ColumnarValue, DataFusionError> {
+        Ok(ColumnarValue::Scalar(self.value.clone()))
+    }
+}
@@ -0,0 +1,283 @@
+//! Materialization of the results of a query into a new table, e.g., to build a downsampled rollup
+//! of another table:
+//!
+//! ```text
+//! CREATE TABLE cpu_1h AS
+//!   SELECT date_bin(INTERVAL '1 hour', time) AS time, host, avg(usage) AS usage
+//!   FROM cpu GROUP BY 1, 2
+//! ```
+//!
+//! The table is created in the catalog from the schema of the results, where the `time` column
+//! is the time of each row, string dictionary columns, i.e., tags selected from other tables, are
+//! tags, and the remaining columns are fields. The results are then written to the table as line
+//! protocol through the write buffer, so a query whose results cannot be represented in line
+//! protocol, e.g., that has a NaN field value, or a row without any fields, fails without writing
+//! any of them.
+use std::{fmt::Write, sync::Arc};
+
+use arrow::{
+    array::{Array, ArrayRef, AsArray},
+    compute::cast,
+    datatypes::{
+        DataType, Field, Float64Type, Int64Type, SchemaRef, TimeUnit, TimestampNanosecondType,
+        UInt64Type,
+    },
+    record_batch::RecordBatch,
+};
+use datafusion::sql::{
+    parser::{DFParser, Statement as DFStatement},
+    sqlparser::ast::{CreateTable, Statement},
+};
+use influxdb3_catalog::catalog::TIME_COLUMN_NAME;
+use influxdb3_internal_api::query_executor::QueryExecutorError;
+
+/// A `CREATE [OR REPLACE] TABLE <table> AS <query>` statement
+#[derive(Debug)]
+pub(super) struct CreateTableAs {
+    /// The name of the table to create
+    pub(super) table: String,
+    /// Replace the table if it already exists, rather than failing
+    pub(super) or_replace: bool,
+    /// Leave the table as it is if it already exists, rather than failing
+    pub(super) if_not_exists: bool,
+    /// The query whose results are written to the table
+    pub(super) query: String,
+}
+
+/// Parse the SQL `query` as a `CREATE TABLE ... AS` statement, returning `None` if it is any
+/// other statement, or does not parse, which is left for the planner to report
+pub(super) fn parse_create_table_as(
+    query: &str,
+) -> Result<Option<CreateTableAs>, QueryExecutorError> {
+    let Ok(mut statements) = DFParser::parse_sql(query) else {
+        return Ok(None);
+    };
+    let [DFStatement::Statement(statement)] = statements.make_contiguous() else {
+        return Ok(None);
+    };
+    let Statement::CreateTable(CreateTable {
+        name,
+        columns,
+        query: Some(query),
+        or_replace,
+        if_not_exists,
+        ..
+    }) = statement.as_ref()
+    else {
+        return Ok(None);
+    };
+    let [table] = name.0.as_slice() else {
+        return Err(invalid(format!(
+            "the table must be in the queried database, got '{name}'"
+        )));
+    };
+    if !columns.is_empty() {
+        return Err(invalid(
+            "the columns of the table are given by the query, and cannot be declared",
+        ));
+    }
+    Ok(Some(CreateTableAs {
+        table: table.value.clone(),
+        or_replace: *or_replace,
+        if_not_exists: *if_not_exists,
+        query: query.to_string(),
+    }))
+}
+
+/// How a column of the query's results is stored in the table
+#[derive(Debug, Clone, Copy)]
+enum ColumnKind {
+    Time,
+    Tag,
+    /// A field of the given type, named as [`DatabaseManager::create_table`][create_table]
+    /// expects it
+    ///
+    /// [create_table]: influxdb3_write::DatabaseManager::create_table
+    Field(&'static str),
+}
+
+fn column_kind(field: &Field) -> Result<ColumnKind, QueryExecutorError> {
+    let kind = match field.data_type() {
+        DataType::Timestamp(..) if field.name() == TIME_COLUMN_NAME => ColumnKind::Time,
+        _ if field.name() == TIME_COLUMN_NAME => {
+            return Err(invalid(format!(
+                "the '{TIME_COLUMN_NAME}' column must be a timestamp, got {}",
+                field.data_type()
+            )))
+        }
+        DataType::Dictionary(_, value) if value.as_ref() == &DataType::Utf8 => ColumnKind::Tag,
+        DataType::Float64 => ColumnKind::Field("float64"),
+        DataType::Int64 => ColumnKind::Field("int64"),
+        DataType::UInt64 => ColumnKind::Field("uint64"),
+        DataType::Boolean => ColumnKind::Field("bool"),
+        DataType::Utf8 | DataType::Utf8View | DataType::LargeUtf8 => ColumnKind::Field("utf8"),
+        other => {
+            return Err(invalid(format!(
+                "column '{}' is of type {other}, which cannot be stored in a table, cast it to \
+                one of Float64, Int64, UInt64, Boolean, or Utf8",
+                field.name()
+            )))
+        }
+    };
+    Ok(kind)
+}
+
+/// Get the tags and fields of the table that holds results with the given `schema`, which must
+/// have a `time` column and at least one field
+pub(super) fn table_columns(
+    schema: &SchemaRef,
+) -> Result<(Vec<String>, Vec<(String, String)>), QueryExecutorError> {
+    let mut has_time = false;
+    let mut tags = vec![];
+    let mut fields = vec![];
+    for field in schema.fields() {
+        match column_kind(field)? {
+            ColumnKind::Time => has_time = true,
+            ColumnKind::Tag => tags.push(field.name().clone()),
+            ColumnKind::Field(data_type) => {
+                fields.push((field.name().clone(), data_type.to_string()))
+            }
+        }
+    }
+    if !has_time {
+        return Err(invalid(format!(
+            "the query must output a '{TIME_COLUMN_NAME}' column"
+        )));
+    }
+    if fields.is_empty() {
+        return Err(invalid("the query must output at least one field column"));
+    }
+    Ok((tags, fields))
+}
+
+/// Convert the rows of the `batch` to line protocol for the `table`
+///
+/// Null and empty tag values are left out of each line, as are null fields. Rows that line
+/// protocol cannot represent, i.e., without a time, without any non-null fields, or with a NaN or
+/// infinite field value, fail the conversion.
+pub(super) fn to_line_protocol(
+    table: &str,
+    batch: &RecordBatch,
+) -> Result<String, QueryExecutorError> {
+    let schema = batch.schema();
+    let mut times = None;
+    let mut tags = vec![];
+    let mut fields = vec![];
+    for (field, column) in schema.fields().iter().zip(batch.columns()) {
+        match column_kind(field)? {
+            ColumnKind::Time => {
+                times = Some(cast_column(
+                    column,
+                    &DataType::Timestamp(TimeUnit::Nanosecond, None),
+                )?)
+            }
+            ColumnKind::Tag => tags.push((field.name(), cast_column(column, &DataType::Utf8)?)),
+            ColumnKind::Field("utf8") => {
+                fields.push((field.name(), cast_column(column, &DataType::Utf8)?))
+            }
+            ColumnKind::Field(_) => fields.push((field.name(), Arc::clone(column))),
+        }
+    }
+    let times = times.ok_or_else(|| {
+        invalid(format!(
+            "the query must output a '{TIME_COLUMN_NAME}' column"
+        ))
+    })?;
+    let times = times.as_primitive::<TimestampNanosecondType>();
+
+    let mut lp = String::new();
+    let mut line = String::new();
+    for row in 0..batch.num_rows() {
+        if times.is_null(row) {
+            return Err(invalid(format!(
+                "the results have a row without a '{TIME_COLUMN_NAME}', which cannot be written"
+            )));
+        }
+        let time = times.value(row);
+        line.clear();
+        escape(&mut line, table, &[',', ' ']);
+        for (name, values) in &tags {
+            let values = values.as_string::<i32>();
+            if values.is_null(row) || values.value(row).is_empty() {
+                continue;
+            }
+            line.push(',');
+            escape(&mut line, name, &[',', '=', ' ']);
+            line.push('=');
+            escape(&mut line, values.value(row), &[',', '=', ' ']);
+        }
+        let mut separator = ' ';
+        for (name, values) in &fields {
+            if values.is_null(row) {
+                continue;
+            }
+            let mut value = String::new();
+            match values.data_type() {
+                DataType::Float64 => {
+                    let v = values.as_primitive::<Float64Type>().value(row);
+                    // line protocol has no representation of NaN or infinity:
+                    if !v.is_finite() {
+                        return Err(invalid(format!(
+                            "the value of field '{name}' at time {time} is {v}, which cannot be \
+                            written"
+                        )));
+                    }
+                    write!(value, "{v}").expect("writing to a string does not fail");
+                }
+                DataType::Int64 => {
+                    let v = values.as_primitive::<Int64Type>().value(row);
+                    write!(value, "{v}i").expect("writing to a string does not fail");
+                }
+                DataType::UInt64 => {
+                    let v = values.as_primitive::<UInt64Type>().value(row);
+                    write!(value, "{v}u").expect("writing to a string does not fail");
+                }
+                DataType::Boolean => {
+                    let v = values.as_boolean().value(row);
+                    write!(value, "{v}").expect("writing to a string does not fail");
+                }
+                _ => {
+                    value.push('"');
+                    escape(
+                        &mut value,
+                        values.as_string::<i32>().value(row),
+                        &['"', '\\'],
+                    );
+                    value.push('"');
+                }
+            }
+            line.push(separator);
+            separator = ',';
+            escape(&mut line, name, &[',', '=', ' ']);
+            line.push('=');
+            line.push_str(&value);
+        }
+        if separator == ' ' {
+            return Err(invalid(format!(
+                "the row at time {time} has no non-null fields, and cannot be written"
+            )));
+        }
+        writeln!(lp, "{line} {time}").expect("writing to a string does not fail");
+    }
+    Ok(lp)
+}
+
+/// Append `s` to `out`, escaping each of the `special` characters with a backslash
+fn escape(out: &mut String, s: &str, special: &[char]) {
+    for c in s.chars() {
+        if special.contains(&c) {
+            out.push('\\');
+        }
+        out.push(c);
+    }
+}
+
+fn cast_column(column: &ArrayRef, to: &DataType) -> Result<ArrayRef, QueryExecutorError> {
+    cast(column, to).map_err(|e| invalid(e.to_string()))
+}
+
+fn invalid(reason: impl Into<String>) -> QueryExecutorError {
+    QueryExecutorError::InvalidCreateTableAs {
+        reason: reason.into(),
+    }
+}
@@ -0,0 +1,228 @@
+//! Statistics on the dictionary encoded columns encountered while scanning tables for a query
+use std::{
+    any::Any,
+    collections::{BTreeMap, HashSet},
+    fmt,
+    sync::Arc,
+};
+
+use arrow::{array::AsArray, record_batch::RecordBatch};
+use datafusion::{
+    error::DataFusionError,
+    execution::{SendableRecordBatchStream, TaskContext},
+    physical_plan::{
+        stream::RecordBatchStreamAdapter, DisplayAs, DisplayFormatType, ExecutionPlan,
+        PlanProperties,
+    },
+};
+use futures::StreamExt;
+use parking_lot::Mutex;
+
+/// Dictionary statistics for a single column, accumulated over all of the scans in a query
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub(crate) struct DictionaryColumnStats {
+    pub(crate) column: String,
+    /// The number of distinct values referenced by the column
+    pub(crate) distinct_values: usize,
+    /// The total number of entries in the dictionaries of the scanned record batches
+    pub(crate) dictionary_size: usize,
+    /// The number of non-null values in the column
+    pub(crate) value_count: usize,
+}
+
+impl fmt::Display for DictionaryColumnStats {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "{}(distinct={}, dictionary_size={}, values={})",
+            self.column, self.distinct_values, self.dictionary_size, self.value_count
+        )
+    }
+}
+
+#[derive(Debug, Default)]
+struct ColumnDictionaries {
+    distinct: HashSet<String>,
+    dictionary_size: usize,
+    value_count: usize,
+}
+
+/// Collects [`DictionaryColumnStats`] from the record batches produced by table scans
+#[derive(Debug, Default)]
+pub(super) struct DictionaryStatsCollector {
+    columns: Mutex<BTreeMap<String, ColumnDictionaries>>,
+}
+
+impl DictionaryStatsCollector {
+    fn observe(&self, batch: &RecordBatch) {
+        let schema = batch.schema();
+        let mut columns = self.columns.lock();
+        for (field, array) in schema.fields().iter().zip(batch.columns()) {
+            let Some(dictionary) = array.as_any_dictionary_opt() else {
+                continue;
+            };
+            let Some(values) = dictionary.values().as_string_opt::<i32>() else {
+                continue;
+            };
+            let keys = dictionary.keys();
+            let mut referenced = vec![false; values.len()];
+            let mut value_count = 0;
+            for (i, key) in dictionary.normalized_keys().into_iter().enumerate() {
+                if keys.is_valid(i) {
+                    referenced[key] = true;
+                    value_count += 1;
+                }
+            }
+
+            let stats = columns.entry(field.name().to_owned()).or_default();
+            stats.dictionary_size += values.len();
+            stats.value_count += value_count;
+            for (key, _) in referenced.iter().enumerate().filter(|(_, r)| **r) {
+                if !stats.distinct.contains(values.value(key)) {
+                    stats.distinct.insert(values.value(key).to_owned());
+                }
+            }
+        }
+    }
+
+    /// The statistics for each dictionary encoded column observed, ordered by column name
+    pub(super) fn stats(&self) -> Vec<DictionaryColumnStats> {
+        self.columns
+            .lock()
+            .iter()
+            .map(|(column, stats)| DictionaryColumnStats {
+                column: column.to_owned(),
+                distinct_values: stats.distinct.len(),
+                dictionary_size: stats.dictionary_size,
+                value_count: stats.value_count,
+            })
+            .collect()
+    }
+}
+
+/// Wrap a table scan so that the record batches it produces are observed by the `collector`
+pub(super) fn observe_scan(
+    scan: Arc<dyn ExecutionPlan>,
+    collector: Arc<DictionaryStatsCollector>,
+) -> Arc<dyn ExecutionPlan> {
+    Arc::new(DictionaryStatsExec {
+        input: scan,
+        collector,
+    })
+}
+
+#[derive(Debug)]
+struct DictionaryStatsExec {
+    input: Arc<dyn ExecutionPlan>,
+    collector: Arc<DictionaryStatsCollector>,
+}
+
+impl DisplayAs for DictionaryStatsExec {
+    fn fmt_as(&self, t: DisplayFormatType, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match t {
+            DisplayFormatType::Default | DisplayFormatType::Verbose => {
+                write!(f, "DictionaryStatsExec")
+            }
+        }
+    }
+}
+
+impl ExecutionPlan for DictionaryStatsExec {
+    fn name(&self) -> &str {
+        "DictionaryStatsExec"
+    }
+
+    fn as_any(&self) -> &dyn Any {
+        self
+    }
+
+    fn properties(&self) -> &PlanProperties {
+        self.input.properties()
+    }
+
+    fn maintains_input_order(&self) -> Vec<bool> {
+        vec![true]
+    }
+
+    fn benefits_from_input_partitioning(&self) -> Vec<bool> {
+        vec![false]
+    }
+
+    fn children(&self) -> Vec<&Arc<dyn ExecutionPlan>> {
+        vec![&self.input]
+    }
+
+    fn with_new_children(
+        self: Arc<Self>,
+        mut children: Vec<Arc<dyn ExecutionPlan>>,
+    ) -> Result<Arc<dyn ExecutionPlan>, DataFusionError> {
+        if children.len() != 1 {
+            return Err(DataFusionError::Internal(format!(
+                "DictionaryStatsExec expects a single child, got {}",
+                children.len()
+            )));
+        }
+        Ok(observe_scan(
+            children.remove(0),
+            Arc::clone(&self.collector),
+        ))
+    }
+
+    fn execute(
+        &self,
+        partition: usize,
+        context: Arc<TaskContext>,
+    ) -> Result<SendableRecordBatchStream, DataFusionError> {
+        let stream = self.input.execute(partition, context)?;
+        let schema = stream.schema();
+        let collector = Arc::clone(&self.collector);
+        Ok(Box::pin(RecordBatchStreamAdapter::new(
+            schema,
+            stream.inspect(move |batch| {
+                if let Ok(batch) = batch {
+                    collector.observe(batch);
+                }
+            }),
+        )))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::sync::Arc;
+
+    use arrow::{
+        array::{DictionaryArray, Int64Array, RecordBatch},
+        datatypes::Int32Type,
+    };
+
+    use super::{DictionaryColumnStats, DictionaryStatsCollector};
+
+    #[test]
+    fn collects_distinct_values_across_batches() {
+        let collector = DictionaryStatsCollector::default();
+        let batch = |hosts: Vec<Option<&str>>| {
+            let values = Int64Array::from_iter_values(0..hosts.len() as i64);
+            RecordBatch::try_from_iter([
+                (
+                    "host",
+                    Arc::new(hosts.into_iter().collect::<DictionaryArray<Int32Type>>()) as _,
+                ),
+                ("value", Arc::new(values) as _),
+            ])
+            .unwrap()
+        };
+        collector.observe(&batch(vec![Some("a"), Some("b"), Some("a"), None]));
+        collector.observe(&batch(vec![Some("b"), Some("c")]));
+
+        assert_eq!(
+            vec![DictionaryColumnStats {
+                column: "host".to_string(),
+                distinct_values: 3,
+                dictionary_size: 4,
+                value_count: 5,
+            }],
+            collector.stats()
+        );
+    }
+}
@@ -0,0 +1,280 @@
+//! Validation of the duration literals in InfluxQL queries before they are planned
+//!
+//! Malformed durations otherwise only surface as a generic parse error from the planner, so the
+//! literals in `GROUP BY time()` intervals and offsets, `now()` offsets, and retention policy
+//! `DURATION` clauses are checked up front to report the offending literal.
+//!
+//! The same scan is used to find the `GROUP BY time()` intervals of a query, whose number of
+//! buckets is limited by [`CreateQueryExecutorArgs::max_time_buckets`][max].
+//!
+//! [max]: super::CreateQueryExecutorArgs::max_time_buckets
+use std::{iter::Peekable, str::CharIndices, time::Duration};
+
+use influxdb3_internal_api::query_executor::QueryExecutorError;
+
+/// The units accepted for InfluxQL durations, along with their length in nanoseconds
+const UNITS: [(&str, u64); 9] = [
+    ("ns", 1),
+    ("u", 1_000),
+    ("µ", 1_000),
+    ("ms", 1_000_000),
+    ("s", 1_000_000_000),
+    ("m", 60_000_000_000),
+    ("h", 3_600_000_000_000),
+    ("d", 86_400_000_000_000),
+    ("w", 604_800_000_000_000),
+];
+
+/// Check that each of the duration literals in the InfluxQL `query` is well formed
+///
+/// Only literals in positions that require a duration are checked; anything else, including
+/// errors in the rest of the query, is left for the planner to report.
+pub(super) fn validate_influxql_durations(query: &str) -> Result<(), QueryExecutorError> {
+    let tokens = tokenize(query);
+    for (i, token) in tokens.iter().enumerate() {
+        let literals = match token {
+            // GROUP BY time(<interval>[, <offset>]):
+            Token::Word(w) if w.eq_ignore_ascii_case("time") => match tokens.get(i + 1) {
+                Some(Token::Punct("(")) => tokens[i + 2..]
+                    .iter()
+                    .take_while(|t| !matches!(t, Token::Punct(")")))
+                    .collect::<Vec<_>>(),
+                _ => continue,
+            },
+            // now() +/- <offset>:
+            Token::Word(w) if w.eq_ignore_ascii_case("now") => match tokens.get(i + 1..i + 5) {
+                Some([Token::Punct("("), Token::Punct(")"), Token::Punct("+" | "-"), offset]) => {
+                    vec![offset]
+                }
+                _ => continue,
+            },
+            // [SHARD] DURATION <duration>:
+            Token::Word(w) if w.eq_ignore_ascii_case("duration") => {
+                tokens.get(i + 1).into_iter().collect()
+            }
+            _ => continue,
+        };
+        if let Some(literal) = literals.into_iter().find_map(|t| match t {
+            Token::Number(literal) if !is_valid_duration(literal) => Some(literal),
+            _ => None,
+        }) {
+            return Err(QueryExecutorError::InvalidDuration {
+                literal: literal.to_string(),
+            });
+        }
+    }
+    Ok(())
+}
+
+/// The smallest of the `GROUP BY time()` intervals in the InfluxQL `query`, if it has any
+///
+/// Intervals that are not well formed are ignored, since they are reported by
+/// [`validate_influxql_durations`].
+pub(super) fn group_by_time_interval(query: &str) -> Option<Duration> {
+    let tokens = tokenize(query);
+    tokens
+        .windows(3)
+        .filter_map(|window| match window {
+            [Token::Word(w), Token::Punct("("), Token::Number(interval)]
+                if w.eq_ignore_ascii_case("time") =>
+            {
+                parse_duration(interval)
+            }
+            _ => None,
+        })
+        .min()
+}
+
+/// A plain integer, in nanoseconds, or one or more integers each followed by a unit, e.g.,
+/// `1h30m`
+fn is_valid_duration(literal: &str) -> bool {
+    parse_duration(literal).is_some()
+}
+
+/// Parse a duration literal, see [`is_valid_duration`], where durations too long to be
+/// represented saturate
+fn parse_duration(literal: &str) -> Option<Duration> {
+    if literal.bytes().all(|b| b.is_ascii_digit()) {
+        return Some(Duration::from_nanos(literal.parse().unwrap_or(u64::MAX)));
+    }
+    let mut nanos = 0u64;
+    let mut rest = literal;
+    while !rest.is_empty() {
+        let digits = rest.len() - rest.trim_start_matches(|c: char| c.is_ascii_digit()).len();
+        if digits == 0 {
+            return None;
+        }
+        let value = rest[..digits].parse::<u64>().unwrap_or(u64::MAX);
+        rest = &rest[digits..];
+        // the longest matching unit is taken, so that `ms` is not read as `m`:
+        let (unit, unit_nanos) = UNITS
+            .iter()
+            .filter(|(unit, _)| rest.starts_with(unit))
+            .max_by_key(|(unit, _)| unit.len())?;
+        nanos = nanos.saturating_add(value.saturating_mul(*unit_nanos));
+        rest = &rest[unit.len()..];
+    }
+    Some(Duration::from_nanos(nanos))
+}
+
+#[derive(Debug, PartialEq)]
+pub(super) enum Token<'a> {
+    Word(&'a str),
+    /// Anything starting with a digit, including what follows it up to the next delimiter
+    Number(&'a str),
+    Punct(&'a str),
+}
+
+/// Split the `query` into the tokens needed to find duration literals, and placeholders, skipping
+/// over string literals, quoted identifiers, regular expressions, and comments
+pub(super) fn tokenize(query: &str) -> Vec<Token<'_>> {
+    let mut tokens = Vec::new();
+    let mut chars = query.char_indices().peekable();
+    while let Some((start, c)) = chars.next() {
+        match c {
+            c if c.is_whitespace() => {}
+            '\'' | '"' => skip_quoted(&mut chars, c),
+            '-' if chars.peek().is_some_and(|(_, c)| *c == '-') => {
+                chars
+                    .by_ref()
+                    .take_while(|(_, c)| *c != '\n')
+                    .for_each(drop);
+            }
+            '/' if chars.peek().is_some_and(|(_, c)| *c == '*') => {
+                chars.next();
+                let mut prev = ' ';
+                for (_, c) in chars.by_ref() {
+                    if prev == '*' && c == '/' {
+                        break;
+                    }
+                    prev = c;
+                }
+            }
+            // a regular expression can only follow a regex operator, FROM, or a comma in a list
+            // of measurements:
+            '/' if tokens.last().is_some_and(|t| match t {
+                Token::Punct(p) => matches!(*p, "=~" | "!~" | ","),
+                Token::Word(w) => w.eq_ignore_ascii_case("from"),
+                Token::Number(_) => false,
+            }) =>
+            {
+                skip_quoted(&mut chars, '/')
+            }
+            c if c.is_alphanumeric() || c == '_' => {
+                let end = take_while(&mut chars, start + c.len_utf8(), |c| {
+                    c.is_alphanumeric() || c == '_' || c == '.'
+                });
+                let text = &query[start..end];
+                tokens.push(if c.is_ascii_digit() {
+                    Token::Number(text)
+                } else {
+                    Token::Word(text)
+                });
+            }
+            '=' | '!' if chars.peek().is_some_and(|(_, c)| *c == '~') => {
+                chars.next();
+                tokens.push(Token::Punct(&query[start..start + 2]));
+            }
+            _ => tokens.push(Token::Punct(&query[start..start + c.len_utf8()])),
+        }
+    }
+    tokens
+}
+
+/// Advance the `chars` past the closing `quote`, allowing it to be escaped with a backslash
+fn skip_quoted(chars: &mut Peekable<CharIndices<'_>>, quote: char) {
+    while let Some((_, c)) = chars.next() {
+        match c {
+            '\\' => {
+                chars.next();
+            }
+            c if c == quote => return,
+            _ => {}
+        }
+    }
+}
+
+/// Advance the `chars` while they match the `predicate`, returning the end offset of the last
+/// one taken
+fn take_while(
+    chars: &mut Peekable<CharIndices<'_>>,
+    mut end: usize,
+    predicate: impl Fn(char) -> bool,
+) -> usize {
+    while let Some((i, c)) = chars.next_if(|(_, c)| predicate(*c)) {
+        end = i + c.len_utf8();
+    }
+    end
+}
+
+#[cfg(test)]
+mod tests {
+    use influxdb3_internal_api::query_executor::QueryExecutorError;
+
+    use std::time::Duration;
+
+    use super::{group_by_time_interval, validate_influxql_durations};
+
+    #[test]
+    fn valid_durations() {
+        for query in [
+            "SELECT mean(usage) FROM cpu WHERE time > now() - 1h GROUP BY time(30m)",
+            "SELECT mean(usage) FROM cpu GROUP BY time(1d, -5m), host",
+            "SELECT mean(usage) FROM cpu WHERE time > now() - 1h30m GROUP BY time(100ms)",
+            "SELECT usage FROM cpu WHERE time > now() - 1000000000 AND time < now() + 2w",
+            "SELECT usage FROM /cpu_1x/ WHERE host =~ /1x/ AND region = 'time(1x)'",
+            "SELECT \"1x\" FROM cpu -- GROUP BY time(1x)",
+            "CREATE RETENTION POLICY \"one_day\" ON \"mydb\" DURATION 1d REPLICATION 1 SHARD DURATION 1h",
+        ] {
+            validate_influxql_durations(query).unwrap_or_else(|e| panic!("{query}: {e}"));
+        }
+    }
+
+    #[test]
+    fn invalid_durations() {
+        for (query, expected) in [
+            ("SELECT mean(usage) FROM cpu GROUP BY time(1x)", "1x"),
+            ("SELECT mean(usage) FROM cpu GROUP BY time(1h, 5y)", "5y"),
+            ("SELECT usage FROM cpu WHERE time > now() - 1.5h", "1.5h"),
+            ("SELECT usage FROM cpu WHERE time > now() - 1h30", "1h30"),
+            (
+                "CREATE RETENTION POLICY \"rp\" ON \"mydb\" DURATION 3mo",
+                "3mo",
+            ),
+        ] {
+            match validate_influxql_durations(query) {
+                Err(QueryExecutorError::InvalidDuration { literal }) => {
+                    assert_eq!(expected, literal, "{query}")
+                }
+                other => panic!("{query}: expected an invalid duration, got {other:?}"),
+            }
+        }
+    }
+
+    #[test]
+    fn group_by_time_intervals() {
+        for (query, expected) in [
+            (
+                "SELECT mean(usage) FROM cpu GROUP BY time(1h30m)",
+                Some(5_400),
+            ),
+            (
+                "SELECT mean(usage) FROM cpu GROUP BY host, time(1d, -5m)",
+                Some(86_400),
+            ),
+            (
+                "SELECT max(m) FROM (SELECT mean(usage) AS m FROM cpu GROUP BY time(1m)) \
+                GROUP BY time(1h)",
+                Some(60),
+            ),
+            ("SELECT mean(usage) FROM cpu GROUP BY host", None),
+            ("SELECT mean(usage) FROM cpu WHERE host = 'time(1s)'", None),
+        ] {
+            assert_eq!(
+                expected.map(Duration::from_secs),
+                group_by_time_interval(query),
+                "{query}"
+            );
+        }
+    }
+}
@@ -0,0 +1,46 @@
+//! Reporting of a query as it is run once the server has rewritten it, see
+//! [`QueryExecutorImpl::effective_query`][effective_query]
+//!
+//! [effective_query]: super::QueryExecutorImpl::effective_query
+use std::{collections::HashMap, sync::Arc};
+
+use arrow::{
+    array::{ArrayRef, StringArray},
+    datatypes::{DataType, Field, Schema},
+    record_batch::RecordBatch,
+};
+use datafusion::{error::DataFusionError, logical_expr::utils::conjunction, prelude::Expr};
+
+/// Describe the `query` as it was planned, followed by the filters of each scan of each table in
+/// the `scan_filters`, ordered by table name
+///
+/// The query is reported with a null `table_name`, and a scan without any filters as `true`.
+pub(super) fn summarize(
+    query: &str,
+    scan_filters: &HashMap<Arc<str>, Vec<Vec<Expr>>>,
+) -> Result<RecordBatch, DataFusionError> {
+    let mut tables = scan_filters.iter().collect::<Vec<_>>();
+    tables.sort_by(|(a, _), (b, _)| a.cmp(b));
+
+    let mut table_name = vec![None];
+    let mut effective = vec![query.to_string()];
+    for (table, scans) in tables {
+        for filters in scans {
+            table_name.push(Some(table.to_string()));
+            effective.push(
+                conjunction(filters.iter().cloned())
+                    .map_or_else(|| "true".to_string(), |filter| filter.to_string()),
+            );
+        }
+    }
+
+    let schema = Arc::new(Schema::new(vec![
+        Field::new("table_name", DataType::Utf8, true),
+        Field::new("effective", DataType::Utf8, false),
+    ]));
+    let columns: Vec<ArrayRef> = vec![
+        Arc::new(StringArray::from(table_name)),
+        Arc::new(StringArray::from(effective)),
+    ];
+    Ok(RecordBatch::try_new(schema, columns)?)
+}
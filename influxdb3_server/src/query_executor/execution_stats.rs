@@ -0,0 +1,123 @@
+//! Statistics returned alongside the results of a query, see
+//! [`QueryExecutorImpl::query_with_stats`][query_with_stats]
+//!
+//! Unlike `EXPLAIN ANALYZE`, gathering the statistics does not change the results of the query;
+//! they are finalized once the results have been read, and resolved through an
+//! [`ExecutionStatsFuture`] that is returned with the result stream.
+//!
+//! [query_with_stats]: super::QueryExecutorImpl::query_with_stats
+use std::{
+    future::Future,
+    pin::Pin,
+    task::{Context, Poll},
+    time::{Duration, Instant},
+};
+
+use arrow::{datatypes::SchemaRef, record_batch::RecordBatch};
+use datafusion::{
+    error::DataFusionError,
+    execution::{RecordBatchStream, SendableRecordBatchStream},
+};
+use futures::{FutureExt, Stream, StreamExt};
+use tokio::sync::oneshot;
+
+/// Statistics for the execution of a single query
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct ExecutionStats {
+    /// The number of rows in the results
+    pub rows: usize,
+    /// The in-memory size of the results, in bytes
+    pub bytes: usize,
+    /// The number of chunks scanned by the query, which is zero if its results were not produced
+    /// by executing it, i.e., they were served from the result cache, or shared by an identical
+    /// query that was already in flight
+    pub chunks: usize,
+    /// The time taken from the query being issued until its results were read
+    pub elapsed: Duration,
+    /// Whether the results were served from the result cache
+    pub cache_hit: bool,
+    /// Whether the write buffer was replaying the write-ahead log when the query was issued, in
+    /// which case the results may be incomplete, see [`ReplayPolicy::Warn`][warn]
+    ///
+    /// [warn]: super::ReplayPolicy::Warn
+    pub replay_in_progress: bool,
+}
+
+/// Resolves to the [`ExecutionStats`] of a query once its result stream has ended, or has been
+/// dropped, in which case the statistics only cover the results that were read
+#[derive(Debug)]
+pub struct ExecutionStatsFuture(oneshot::Receiver<ExecutionStats>);
+
+impl Future for ExecutionStatsFuture {
+    type Output = ExecutionStats;
+
+    fn poll(mut self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Self::Output> {
+        // the stream always sends its statistics before the sender is dropped:
+        self.0.poll_unpin(cx).map(|stats| stats.unwrap_or_default())
+    }
+}
+
+/// Wraps the result stream of a query to gather its [`ExecutionStats`]
+pub(super) struct ExecutionStatsStream {
+    inner: SendableRecordBatchStream,
+    started: Instant,
+    stats: ExecutionStats,
+    sender: Option<oneshot::Sender<ExecutionStats>>,
+}
+
+impl ExecutionStatsStream {
+    /// Wrap the results of a query that was issued at `started`, whose statistics that are known
+    /// before its results are read are given in `stats`, returning the future that resolves to
+    /// the statistics
+    pub(super) fn new(
+        inner: SendableRecordBatchStream,
+        started: Instant,
+        stats: ExecutionStats,
+    ) -> (Self, ExecutionStatsFuture) {
+        let (sender, receiver) = oneshot::channel();
+        let stream = Self {
+            inner,
+            started,
+            stats,
+            sender: Some(sender),
+        };
+        (stream, ExecutionStatsFuture(receiver))
+    }
+
+    fn finalize(&mut self) {
+        if let Some(sender) = self.sender.take() {
+            self.stats.elapsed = self.started.elapsed();
+            // the receiver is dropped by callers that are not interested in the statistics:
+            let _ = sender.send(self.stats);
+        }
+    }
+}
+
+impl Stream for ExecutionStatsStream {
+    type Item = Result<RecordBatch, DataFusionError>;
+
+    fn poll_next(mut self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Option<Self::Item>> {
+        let poll = self.inner.poll_next_unpin(cx);
+        match &poll {
+            Poll::Ready(Some(Ok(batch))) => {
+                self.stats.rows += batch.num_rows();
+                self.stats.bytes += batch.get_array_memory_size();
+            }
+            Poll::Ready(None) => self.finalize(),
+            _ => (),
+        }
+        poll
+    }
+}
+
+impl RecordBatchStream for ExecutionStatsStream {
+    fn schema(&self) -> SchemaRef {
+        self.inner.schema()
+    }
+}
+
+impl Drop for ExecutionStatsStream {
+    fn drop(&mut self) {
+        self.finalize();
+    }
+}
@@ -0,0 +1,120 @@
+//! Reporting of the chunks that a query would scan, see
+//! [`QueryExecutorImpl::explain_chunks`][explain_chunks]
+//!
+//! [explain_chunks]: super::QueryExecutorImpl::explain_chunks
+use std::{collections::HashMap, sync::Arc};
+
+use arrow::{
+    array::{ArrayRef, Int64Array, StringArray, UInt64Array},
+    datatypes::{DataType, Field, Schema, SchemaRef},
+    record_batch::RecordBatch,
+};
+use datafusion::{error::DataFusionError, prelude::Expr};
+use influxdb3_write::chunk::{BufferChunk, ParquetChunk};
+use iox_query::QueryChunk;
+use parking_lot::Mutex;
+
+use super::time_range;
+
+/// The filters pushed down to each scan of each table in a query, keyed by table name
+pub(super) type ScanFilters = Mutex<HashMap<Arc<str>, Vec<Vec<Expr>>>>;
+
+fn explain_chunks_schema() -> SchemaRef {
+    let columns = vec![
+        Field::new("table_name", DataType::Utf8, false),
+        Field::new("chunk_id", DataType::Utf8, false),
+        Field::new("storage", DataType::Utf8, false),
+        Field::new("parquet_file_id", DataType::UInt64, true),
+        Field::new("path", DataType::Utf8, true),
+        Field::new("min_time", DataType::Int64, true),
+        Field::new("max_time", DataType::Int64, true),
+        Field::new("row_count", DataType::UInt64, true),
+        Field::new("size_bytes", DataType::UInt64, false),
+    ];
+    Arc::new(Schema::new(columns))
+}
+
+/// Summarize the chunks of each table that overlap the time range of any of the scans of the
+/// table, in the order that the tables and their chunks are given
+///
+/// A chunk without statistics on time is always reported, as is every chunk of a table whose
+/// scans are not known.
+pub(super) fn summarize_chunks(
+    tables: &[(Arc<str>, Vec<Arc<dyn QueryChunk>>)],
+    scan_filters: &HashMap<Arc<str>, Vec<Vec<Expr>>>,
+) -> Result<RecordBatch, DataFusionError> {
+    let mut table_name = vec![];
+    let mut chunk_id = vec![];
+    let mut storage = vec![];
+    let mut parquet_file_id = vec![];
+    let mut path = vec![];
+    let mut min_time = vec![];
+    let mut max_time = vec![];
+    let mut row_count = vec![];
+    let mut size_bytes = vec![];
+    for (table, chunks) in tables {
+        let scans = scan_filters
+            .get(table)
+            .map(|scans| {
+                scans
+                    .iter()
+                    .map(|filters| time_range::time_bounds(filters))
+                    .collect::<Vec<_>>()
+            })
+            .unwrap_or_default();
+        for chunk in chunks {
+            let range = time_range::chunk_time_range(chunk.as_ref());
+            let overlaps = |(lower, upper): &(Option<i64>, Option<i64>)| match range {
+                Some((min, max)) => {
+                    !lower.is_some_and(|lower| lower > max)
+                        && !upper.is_some_and(|upper| upper < min)
+                }
+                None => true,
+            };
+            if !scans.is_empty() && !scans.iter().any(overlaps) {
+                continue;
+            }
+            let (tier, file_id, file_path, size) =
+                if let Some(parquet) = chunk.as_any().downcast_ref::<ParquetChunk>() {
+                    let meta = parquet.object_meta();
+                    (
+                        "object_store",
+                        Some(parquet.file_id().as_u64()),
+                        Some(meta.location.to_string()),
+                        meta.size,
+                    )
+                } else if let Some(buffer) = chunk.as_any().downcast_ref::<BufferChunk>() {
+                    let size = buffer
+                        .batches
+                        .iter()
+                        .map(RecordBatch::get_array_memory_size)
+                        .sum();
+                    ("read_buffer", None, None, size)
+                } else {
+                    ("unknown", None, None, 0)
+                };
+            table_name.push(Some(table.to_string()));
+            chunk_id.push(Some(chunk.id().get().to_string()));
+            storage.push(Some(tier));
+            parquet_file_id.push(file_id);
+            path.push(file_path);
+            min_time.push(range.map(|(min, _)| min));
+            max_time.push(range.map(|(_, max)| max));
+            row_count.push(chunk.stats().num_rows.get_value().map(|n| *n as u64));
+            size_bytes.push(Some(size as u64));
+        }
+    }
+
+    let columns: Vec<ArrayRef> = vec![
+        Arc::new(StringArray::from(table_name)),
+        Arc::new(StringArray::from(chunk_id)),
+        Arc::new(StringArray::from(storage)),
+        Arc::new(UInt64Array::from(parquet_file_id)),
+        Arc::new(StringArray::from(path)),
+        Arc::new(Int64Array::from(min_time)),
+        Arc::new(Int64Array::from(max_time)),
+        Arc::new(UInt64Array::from(row_count)),
+        Arc::new(UInt64Array::from(size_bytes)),
+    ];
+    Ok(RecordBatch::try_new(explain_chunks_schema(), columns)?)
+}
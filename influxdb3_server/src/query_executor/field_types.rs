@@ -0,0 +1,70 @@
+//! Detection of fields whose type differs between the chunks of a table, see
+//! [`QueryOptions::strict_field_types`][strict]
+//!
+//! [strict]: influxdb3_internal_api::query_executor::QueryOptions::strict_field_types
+use std::collections::BTreeMap;
+
+use arrow::datatypes::DataType;
+use influxdb3_internal_api::query_executor::QueryExecutorError;
+use schema::{InfluxColumnType, Schema};
+
+/// Check that each field has the same type in all of the given chunk `schemas`
+///
+/// The first conflicting field, by column name, is reported along with its types in the order
+/// that they were encountered.
+pub(super) fn check_field_types<'a>(
+    schemas: impl IntoIterator<Item = &'a Schema>,
+) -> Result<(), QueryExecutorError> {
+    let mut fields: BTreeMap<&str, Vec<DataType>> = BTreeMap::new();
+    for schema in schemas {
+        for (column_type, field) in schema.iter() {
+            if !matches!(column_type, InfluxColumnType::Field(_)) {
+                continue;
+            }
+            let types = fields.entry(field.name()).or_default();
+            if !types.contains(field.data_type()) {
+                types.push(field.data_type().clone());
+            }
+        }
+    }
+    match fields.into_iter().find(|(_, types)| types.len() > 1) {
+        Some((column, types)) => Err(QueryExecutorError::FieldTypeConflict {
+            column: column.to_owned(),
+            types,
+        }),
+        None => Ok(()),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use arrow::datatypes::DataType;
+    use influxdb3_internal_api::query_executor::QueryExecutorError;
+    use schema::{InfluxFieldType, Schema, SchemaBuilder};
+
+    use super::check_field_types;
+
+    fn chunk_schema(usage: InfluxFieldType) -> Schema {
+        SchemaBuilder::new()
+            .tag("host")
+            .influx_field("usage", usage)
+            .timestamp()
+            .build()
+            .unwrap()
+    }
+
+    #[test]
+    fn conflicting_field_types() {
+        let float = chunk_schema(InfluxFieldType::Float);
+        let integer = chunk_schema(InfluxFieldType::Integer);
+
+        check_field_types([&float, &float]).unwrap();
+
+        let error = check_field_types([&float, &integer, &float]).unwrap_err();
+        let QueryExecutorError::FieldTypeConflict { column, types } = error else {
+            panic!("unexpected error: {error}");
+        };
+        assert_eq!("usage", column);
+        assert_eq!(vec![DataType::Float64, DataType::Int64], types);
+    }
+}
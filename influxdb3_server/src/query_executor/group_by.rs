@@ -0,0 +1,83 @@
+//! Validation of the `GROUP BY` clauses of InfluxQL queries, see
+//! [`QueryOptions::influxql_strict_group_by`][strict]
+//!
+//! InfluxQL only groups by tags, so a field in a `GROUP BY` clause is grouped as if it were a tag
+//! that has no values, putting every row in the same group. Users coming from SQL expect it to be
+//! grouped by its values instead, so the columns grouped by are checked against the catalog
+//! before the query is planned.
+//!
+//! [strict]: influxdb3_internal_api::query_executor::QueryOptions::influxql_strict_group_by
+use influxdb3_catalog::catalog::DatabaseSchema;
+use influxdb3_internal_api::query_executor::QueryExecutorError;
+use influxdb_influxql_parser::{
+    common::MeasurementName,
+    select::{Dimension, MeasurementSelection, SelectStatement},
+    statement::Statement,
+};
+use schema::InfluxColumnType;
+
+/// Check that none of the `SELECT` statements in the InfluxQL `query`, including any subqueries,
+/// group by a field of a measurement that they select from
+///
+/// Queries that do not parse are left for the planner to report.
+pub(super) fn check_group_by_tags(
+    query: &str,
+    db_schema: &DatabaseSchema,
+) -> Result<(), QueryExecutorError> {
+    let Ok(statements) = iox_query_influxql_rewrite::parse_statements(query) else {
+        return Ok(());
+    };
+    for statement in statements {
+        let mut statement = statement.statement();
+        while let Statement::Explain(explain) = statement {
+            statement = &*explain.statement;
+        }
+        if let Statement::Select(select) = statement {
+            check_select(select, db_schema)?;
+        }
+    }
+    Ok(())
+}
+
+fn check_select(
+    select: &SelectStatement,
+    db_schema: &DatabaseSchema,
+) -> Result<(), QueryExecutorError> {
+    let mut measurements = vec![];
+    for selection in select.from.iter() {
+        match selection {
+            MeasurementSelection::Name(name) => match &name.name {
+                MeasurementName::Name(name) => measurements.push(name.as_str()),
+                // the measurements matched by a regex are not known until the query is planned:
+                MeasurementName::Regex(_) => (),
+            },
+            MeasurementSelection::Subquery(subquery) => check_select(subquery, db_schema)?,
+        }
+    }
+    let Some(group_by) = &select.group_by else {
+        return Ok(());
+    };
+    for dimension in group_by.iter() {
+        let Dimension::VarRef(column) = dimension else {
+            continue;
+        };
+        let column = column.name.as_str();
+        for measurement in &measurements {
+            let is_field = db_schema
+                .table_definition(*measurement)
+                .and_then(|table| {
+                    table
+                        .column_definition(column)
+                        .map(|c| matches!(c.data_type, InfluxColumnType::Field(_)))
+                })
+                .unwrap_or(false);
+            if is_field {
+                return Err(QueryExecutorError::GroupByField {
+                    measurement: measurement.to_string(),
+                    column: column.to_string(),
+                });
+            }
+        }
+    }
+    Ok(())
+}
@@ -0,0 +1,81 @@
+//! Partitioning of the results of a query by the values of some of its columns, see
+//! [`QueryExecutorImpl::query_grouped`][query_grouped]
+//!
+//! [query_grouped]: super::QueryExecutorImpl::query_grouped
+use std::collections::HashMap;
+
+use arrow::{
+    array::UInt32Array, compute::take_record_batch, datatypes::SchemaRef, record_batch::RecordBatch,
+};
+use datafusion::{error::DataFusionError, scalar::ScalarValue};
+use influxdb3_internal_api::query_executor::QueryExecutorError;
+
+use super::suggestions::closest_matches;
+
+/// The values of the group key columns shared by the rows of a group, in the order that the
+/// columns were given
+pub type GroupKey = Vec<ScalarValue>;
+
+/// Split the rows of the `batches`, which have the given `schema`, into groups that have the same values in each of the
+/// `group_keys` columns, in the order that the groups first appear
+///
+/// The rows of each group keep their order, and dictionary encoded values, i.e., tags, are
+/// decoded in the keys.
+pub(super) fn partition_by_group(
+    schema: &SchemaRef,
+    batches: &[RecordBatch],
+    group_keys: &[&str],
+) -> Result<Vec<(GroupKey, Vec<RecordBatch>)>, QueryExecutorError> {
+    let indices = group_keys
+        .iter()
+        .map(|key| {
+            schema.index_of(key).map_err(|_| {
+                let columns = schema
+                    .fields()
+                    .iter()
+                    .map(|f| f.name().to_string())
+                    .collect::<Vec<_>>();
+                QueryExecutorError::UnknownColumn {
+                    name: key.to_string(),
+                    suggestions: closest_matches(key, &columns),
+                }
+            })
+        })
+        .collect::<Result<Vec<_>, _>>()?;
+
+    let mut groups: Vec<(GroupKey, Vec<RecordBatch>)> = vec![];
+    let mut positions: HashMap<GroupKey, usize> = HashMap::new();
+    for batch in batches {
+        // the rows of the batch in each group, by the position of the group:
+        let mut rows: HashMap<usize, Vec<u32>> = HashMap::new();
+        for row in 0..batch.num_rows() {
+            let key = indices
+                .iter()
+                .map(|&i| ScalarValue::try_from_array(batch.column(i), row).map(decode))
+                .collect::<Result<GroupKey, _>>()
+                .map_err(QueryExecutorError::ExecuteStream)?;
+            let group = match positions.get(&key) {
+                Some(group) => *group,
+                None => {
+                    positions.insert(key.clone(), groups.len());
+                    groups.push((key, vec![]));
+                    groups.len() - 1
+                }
+            };
+            rows.entry(group).or_default().push(row as u32);
+        }
+        for (group, group_rows) in rows {
+            let batch = take_record_batch(batch, &UInt32Array::from(group_rows))
+                .map_err(|e| QueryExecutorError::ExecuteStream(DataFusionError::from(e)))?;
+            groups[group].1.push(batch);
+        }
+    }
+    Ok(groups)
+}
+
+fn decode(value: ScalarValue) -> ScalarValue {
+    match value {
+        ScalarValue::Dictionary(_, value) => *value,
+        value => value,
+    }
+}
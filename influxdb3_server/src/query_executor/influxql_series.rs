@@ -0,0 +1,200 @@
+//! Partitioning of the results of an InfluxQL query into the series of the InfluxDB 1.x query
+//! API, see [`QueryExecutorImpl::query_influxql_series`][series]
+//!
+//! The InfluxQL planner outputs the measurement of each row in its first column, along with the
+//! tags of its `GROUP BY` clause, which it describes in the [`INFLUXQL_METADATA_KEY`] metadata of
+//! the output schema. Each distinct combination of measurement and tag values is a series.
+//!
+//!
+//! The results of a [`QueryKind::InfluxQlV1Compat`] query are not split, and instead describe
+//! their series in the [`SERIES_METADATA_KEY`] metadata of their schema, so that they can be
+//! reshaped into the series of the 1.x query API as they are streamed.
+//!
+//! [series]: super::QueryExecutorImpl::query_influxql_series
+use std::{collections::BTreeMap, sync::Arc};
+
+use arrow::{
+    datatypes::{Schema, SchemaRef},
+    record_batch::RecordBatch,
+};
+use datafusion::{
+    error::DataFusionError, execution::SendableRecordBatchStream,
+    physical_plan::stream::RecordBatchStreamAdapter, scalar::ScalarValue,
+};
+use futures::StreamExt;
+use influxdb3_internal_api::query_executor::{QueryExecutorError, QueryKind};
+use schema::{
+    InfluxQlMetadata, TagKeyColumn, INFLUXQL_MEASUREMENT_COLUMN_NAME, INFLUXQL_METADATA_KEY,
+};
+use serde::{Deserialize, Serialize};
+
+use super::grouped;
+
+/// The key of the [`SeriesMetadata`] in the schema of the results of a
+/// [`QueryKind::InfluxQlV1Compat`] query
+pub const SERIES_METADATA_KEY: &str = "influxdb3::influxql::series";
+
+/// How the rows of the results of an InfluxQL query are grouped into the series of the InfluxDB
+/// 1.x query API, where each distinct combination of the values of the measurement column and
+/// the tag columns is a series
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct SeriesMetadata {
+    /// The column that holds the measurement of each row
+    pub measurement_column: String,
+    /// The tags of the query's `GROUP BY` clause, in order
+    pub tags: Vec<SeriesTag>,
+    /// The columns of the rows of each series, which leave out the measurement, and the tags
+    /// that are grouped by without being selected
+    pub columns: Vec<String>,
+}
+
+/// A tag of the `GROUP BY` clause of an InfluxQL query, see [`SeriesMetadata::tags`]
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct SeriesTag {
+    /// The key of the tag, as it is reported in the tags of each series
+    pub key: String,
+    /// The column that holds the value of the tag
+    pub column: String,
+}
+
+/// Describe the series of the `results` of an InfluxQL query in the [`SERIES_METADATA_KEY`]
+/// metadata of their schema, leaving their rows as they are
+pub(super) fn add_series_metadata(
+    results: SendableRecordBatchStream,
+) -> Result<SendableRecordBatchStream, QueryExecutorError> {
+    let input_schema = results.schema();
+    let tag_columns = tag_key_columns(&input_schema)?;
+    let series = SeriesMetadata {
+        measurement_column: INFLUXQL_MEASUREMENT_COLUMN_NAME.to_string(),
+        tags: tag_columns
+            .iter()
+            .map(|tag| SeriesTag {
+                key: tag.tag_key.clone(),
+                column: input_schema.field(tag.column_index as usize).name().clone(),
+            })
+            .collect(),
+        columns: series_projection(&input_schema, &tag_columns)
+            .into_iter()
+            .map(|index| input_schema.field(index).name().clone())
+            .collect(),
+    };
+    let mut metadata = input_schema.metadata().clone();
+    metadata.insert(
+        SERIES_METADATA_KEY.to_string(),
+        serde_json::to_string(&series).expect("series metadata serializes to JSON"),
+    );
+    let schema = Arc::new(Schema::new_with_metadata(
+        input_schema.fields().clone(),
+        metadata,
+    ));
+    let batches = results.map({
+        let schema = Arc::clone(&schema);
+        move |batch| {
+            batch?
+                .with_schema(Arc::clone(&schema))
+                .map_err(DataFusionError::from)
+        }
+    });
+    Ok(Box::pin(RecordBatchStreamAdapter::new(schema, batches)))
+}
+
+/// The tags of the `GROUP BY` clause of the InfluxQL query whose results have the `schema`
+fn tag_key_columns(schema: &SchemaRef) -> Result<Vec<TagKeyColumn>, QueryExecutorError> {
+    // a query without a `GROUP BY` clause on tags has no metadata:
+    match schema.metadata().get(INFLUXQL_METADATA_KEY) {
+        Some(metadata) => {
+            let metadata: InfluxQlMetadata = serde_json::from_str(metadata).map_err(|e| {
+                QueryExecutorError::ExecuteStream(DataFusionError::External(Box::new(e)))
+            })?;
+            Ok(metadata.tag_key_columns)
+        }
+        None => Ok(vec![]),
+    }
+}
+
+/// The indices of the columns of the rows of each series, see [`SeriesMetadata::columns`]
+fn series_projection(schema: &SchemaRef, tag_columns: &[TagKeyColumn]) -> Vec<usize> {
+    schema
+        .fields()
+        .iter()
+        .enumerate()
+        .filter(|(index, field)| {
+            field.name() != INFLUXQL_MEASUREMENT_COLUMN_NAME
+                && !tag_columns
+                    .iter()
+                    .any(|tag| tag.column_index as usize == *index && !tag.is_projected)
+        })
+        .map(|(index, _)| index)
+        .collect()
+}
+
+/// A series of the results of an InfluxQL query, with the structure of a series in the response
+/// of the InfluxDB 1.x query API
+#[derive(Debug)]
+pub struct InfluxQlSeries {
+    /// The measurement of the series
+    pub name: String,
+    /// The values of the tags in the query's `GROUP BY` clause that are shared by the rows of the
+    /// series, where a tag without a value has an empty one
+    pub tags: BTreeMap<String, String>,
+    /// The names of the columns of the rows, which leave out the measurement, and the tags that
+    /// are grouped by without being selected
+    pub columns: Vec<String>,
+    /// The rows of the series, in the order that the query output them
+    pub values: Vec<RecordBatch>,
+}
+
+/// Split the `batches` output by an InfluxQL query, which have the given `schema`, into a series
+/// for each distinct measurement and set of `GROUP BY` tag values, in the order that the series
+/// first appear
+pub(super) fn split_series(
+    schema: &SchemaRef,
+    batches: &[RecordBatch],
+) -> Result<Vec<InfluxQlSeries>, QueryExecutorError> {
+    let tag_columns = tag_key_columns(schema)?;
+    let mut keys = vec![INFLUXQL_MEASUREMENT_COLUMN_NAME];
+    keys.extend(
+        tag_columns
+            .iter()
+            .map(|tag| schema.field(tag.column_index as usize).name().as_str()),
+    );
+    let projection = series_projection(schema, &tag_columns);
+    let columns = projection
+        .iter()
+        .map(|&index| schema.field(index).name().to_owned())
+        .collect::<Vec<_>>();
+
+    grouped::partition_by_group(schema, batches, &keys)?
+        .into_iter()
+        .map(|(key, batches)| {
+            let mut values = key.into_iter().map(to_string);
+            let name = values.next().unwrap_or_default();
+            let tags = tag_columns
+                .iter()
+                .map(|tag| tag.tag_key.clone())
+                .zip(values)
+                .collect();
+            let values = batches
+                .iter()
+                .map(|batch| batch.project(&projection))
+                .collect::<Result<Vec<_>, _>>()
+                .map_err(|e| QueryExecutorError::ExecuteStream(DataFusionError::from(e)))?;
+            Ok(InfluxQlSeries {
+                name,
+                tags,
+                columns: columns.clone(),
+                values,
+            })
+        })
+        .collect()
+}
+
+fn to_string(value: ScalarValue) -> String {
+    match value {
+        ScalarValue::Utf8(value) | ScalarValue::LargeUtf8(value) | ScalarValue::Utf8View(value) => {
+            value.unwrap_or_default()
+        }
+        value if value.is_null() => String::new(),
+        value => value.to_string(),
+    }
+}
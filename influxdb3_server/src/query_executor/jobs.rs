@@ -0,0 +1,438 @@
+//! Asynchronous query jobs, whose results are written to object storage so they can be fetched
+//! once the query has completed
+use std::{
+    collections::HashMap, fmt::Display, future::Future, str::FromStr, sync::Arc, time::Duration,
+};
+
+use arrow::{
+    buffer::Buffer,
+    datatypes::SchemaRef,
+    ipc::{reader::StreamDecoder, writer::StreamWriter},
+    record_batch::RecordBatch,
+};
+use bytes::Bytes;
+use datafusion::{
+    error::DataFusionError, execution::SendableRecordBatchStream,
+    physical_plan::stream::RecordBatchStreamAdapter,
+};
+use futures::{stream::BoxStream, Stream, StreamExt, TryStreamExt};
+use influxdb3_write::persister::Persister;
+use iox_time::{Time, TimeProvider};
+use object_store::{path::Path as ObjPath, WriteMultipart};
+use observability_deps::tracing::{info, warn};
+use parking_lot::Mutex;
+use tokio::task::JoinHandle;
+use uuid::Uuid;
+
+use super::tickets::TicketToken;
+
+/// The default duration for which a finished query job, and its results, are retained
+pub const DEFAULT_QUERY_JOB_TTL: Duration = Duration::from_secs(60 * 60);
+
+/// The default interval at which finished query jobs are checked for expiry
+pub const DEFAULT_QUERY_JOB_EXPIRY_INTERVAL: Duration = Duration::from_secs(60);
+
+/// File extension for query job results, which are stored in the Arrow IPC streaming format
+const QUERY_JOB_RESULT_FILE_EXTENSION: &str = "arrows";
+
+/// How many parts of the results of a query job can be uploaded to object storage concurrently,
+/// which bounds how much of the results are buffered in memory
+const MAX_CONCURRENT_RESULT_PARTS: usize = 4;
+
+/// Identifies a query submitted with [`QueryExecutorImpl::submit_query`][submit]
+///
+/// Ids are random, so that they are not reused when the server restarts, and cannot be guessed
+/// in order to fetch the results of another client's query.
+///
+/// [submit]: super::QueryExecutorImpl::submit_query
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct QueryJobId(Uuid);
+
+impl QueryJobId {
+    fn new() -> Self {
+        Self(Uuid::new_v4())
+    }
+}
+
+impl Display for QueryJobId {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}", self.0)
+    }
+}
+
+impl FromStr for QueryJobId {
+    type Err = uuid::Error;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        s.parse().map(Self)
+    }
+}
+
+/// The lifecycle of a query job
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum QueryJobStatus {
+    /// The job was submitted, but has not started executing
+    Pending,
+    /// The query is executing, and its results are being written to object storage
+    Running,
+    /// The query completed, and its results are ready to be fetched
+    Done,
+    /// The query failed with the given error
+    Failed { error: String },
+}
+
+impl QueryJobStatus {
+    /// The job is finished, whether it succeeded or failed
+    pub fn is_finished(&self) -> bool {
+        matches!(self, Self::Done | Self::Failed { .. })
+    }
+}
+
+impl Display for QueryJobStatus {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Self::Pending => write!(f, "pending"),
+            Self::Running => write!(f, "running"),
+            Self::Done => write!(f, "done"),
+            Self::Failed { error } => write!(f, "failed: {error}"),
+        }
+    }
+}
+
+#[derive(Debug)]
+struct QueryJob {
+    status: QueryJobStatus,
+    finished_at: Option<Time>,
+    /// The database that the query was run against
+    database: Arc<str>,
+    /// The schema of the results, once they have been written
+    schema: Option<SchemaRef>,
+    /// The token of the ticket issued for the results, if any, which must be presented in order
+    /// to fetch them through a ticket
+    ticket: Option<TicketToken>,
+    /// The task running the query, once it has been spawned
+    task: Option<JoinHandle<()>>,
+}
+
+impl QueryJob {
+    /// Fail the job if its task has ended without the job finishing, i.e., it panicked, or was
+    /// cancelled, so that it is not left running indefinitely
+    fn reap(&mut self, now: Time) {
+        let ended = self.task.as_ref().is_some_and(|task| task.is_finished());
+        if ended && !self.status.is_finished() {
+            self.status = QueryJobStatus::Failed {
+                error: "the query job ended before it finished".to_string(),
+            };
+            self.finished_at = Some(now);
+        }
+    }
+}
+
+/// Tracks the state of query jobs and stores their results in object storage
+#[derive(Debug)]
+pub(super) struct QueryJobs {
+    jobs: Mutex<HashMap<QueryJobId, QueryJob>>,
+    persister: Arc<Persister>,
+    time_provider: Arc<dyn TimeProvider>,
+    ttl: Duration,
+}
+
+impl QueryJobs {
+    pub(super) fn new(
+        persister: Arc<Persister>,
+        time_provider: Arc<dyn TimeProvider>,
+        ttl: Duration,
+    ) -> Self {
+        Self {
+            jobs: Default::default(),
+            persister,
+            time_provider,
+            ttl,
+        }
+    }
+
+    /// Register a new job against the `database` in the [`QueryJobStatus::Pending`] state, whose
+    /// results can be fetched through a ticket carrying the given `ticket` token, if any
+    pub(super) fn register(&self, database: &str, ticket: Option<TicketToken>) -> QueryJobId {
+        let id = QueryJobId::new();
+        self.jobs.lock().insert(
+            id,
+            QueryJob {
+                status: QueryJobStatus::Pending,
+                finished_at: None,
+                database: database.into(),
+                schema: None,
+                ticket,
+                task: None,
+            },
+        );
+        id
+    }
+
+    /// Run the job in the background, setting its status from the result of `run`
+    ///
+    /// The task is tracked with the job, so that the job fails if the task ends without it
+    /// finishing.
+    pub(super) fn spawn(
+        self: &Arc<Self>,
+        id: QueryJobId,
+        run: impl Future<Output = Result<(), String>> + Send + 'static,
+    ) {
+        let jobs = Arc::clone(self);
+        let task = tokio::spawn(async move {
+            jobs.set_status(id, QueryJobStatus::Running);
+            let status = match run.await {
+                Ok(()) => QueryJobStatus::Done,
+                Err(error) => QueryJobStatus::Failed { error },
+            };
+            jobs.set_status(id, status);
+        });
+        if let Some(job) = self.jobs.lock().get_mut(&id) {
+            job.task = Some(task);
+        }
+    }
+
+    pub(super) fn set_status(&self, id: QueryJobId, status: QueryJobStatus) {
+        if let Some(job) = self.jobs.lock().get_mut(&id) {
+            if status.is_finished() {
+                job.finished_at = Some(self.time_provider.now());
+            }
+            job.status = status;
+        }
+    }
+
+    pub(super) fn status(&self, id: QueryJobId) -> Option<QueryJobStatus> {
+        let now = self.time_provider.now();
+        self.jobs.lock().get_mut(&id).map(|job| {
+            job.reap(now);
+            job.status.clone()
+        })
+    }
+
+    /// The time at which the job, and its results, expire, if it has finished
+    pub(super) fn expires_at(&self, id: QueryJobId) -> Option<Time> {
+        self.jobs
+            .lock()
+            .get(&id)
+            .and_then(|job| job.finished_at)
+            .and_then(|finished_at| finished_at.checked_add(self.ttl))
+    }
+
+    /// The schema of the results of a job that is [`QueryJobStatus::Done`], unless it has
+    /// expired, whether or not it has been removed by [`Self::remove_expired`] yet
+    pub(super) fn result_schema(&self, id: QueryJobId) -> Option<SchemaRef> {
+        self.with_results(id, |job| job.schema.clone())
+    }
+
+    /// The schema of the results of a job, as for [`Self::result_schema`], if it was run
+    /// against the `database` and a ticket was issued for it with the given `token`
+    pub(super) fn ticket_result_schema(
+        &self,
+        id: QueryJobId,
+        token: TicketToken,
+        database: &str,
+    ) -> Option<SchemaRef> {
+        self.with_results(id, |job| {
+            job.schema
+                .clone()
+                .filter(|_| job.ticket == Some(token) && job.database.as_ref() == database)
+        })
+    }
+
+    /// Apply `f` to a job that is [`QueryJobStatus::Done`], and has not expired
+    fn with_results<T>(&self, id: QueryJobId, f: impl FnOnce(&QueryJob) -> Option<T>) -> Option<T> {
+        let now = self.time_provider.now();
+        let jobs = self.jobs.lock();
+        let job = jobs.get(&id)?;
+        let expired = job
+            .finished_at
+            .and_then(|t| now.checked_duration_since(t))
+            .is_some_and(|elapsed| elapsed > self.ttl);
+        match job.status {
+            QueryJobStatus::Done if !expired => f(job),
+            _ => None,
+        }
+    }
+
+    fn results_prefix(&self) -> ObjPath {
+        ObjPath::from(format!(
+            "{host_prefix}/query_jobs",
+            host_prefix = self.persister.host_identifier_prefix(),
+        ))
+    }
+
+    fn result_path(&self, id: QueryJobId) -> ObjPath {
+        self.results_prefix()
+            .child(format!("{id}.{QUERY_JOB_RESULT_FILE_EXTENSION}"))
+    }
+
+    /// Write the `results` of a job to object storage as they are produced, in a multipart
+    /// upload, which is aborted if the results fail
+    pub(super) async fn write_results(
+        &self,
+        id: QueryJobId,
+        mut results: SendableRecordBatchStream,
+    ) -> Result<(), DataFusionError> {
+        let schema = results.schema();
+        let upload = self
+            .persister
+            .object_store()
+            .put_multipart(&self.result_path(id))
+            .await?;
+        let mut upload = WriteMultipart::new(upload);
+        if let Err(e) = write_ipc_stream(&mut upload, &schema, &mut results).await {
+            if let Err(error) = upload.abort().await {
+                warn!(%id, %error, "failed to abort upload of query job results");
+            }
+            return Err(e);
+        }
+        upload.finish().await?;
+        if let Some(job) = self.jobs.lock().get_mut(&id) {
+            job.schema = Some(schema);
+        }
+        Ok(())
+    }
+
+    /// Stream the results of a job, which have the given `schema`, back from object storage
+    ///
+    /// The results are fetched once the stream is first polled, and each batch is decoded as
+    /// soon as its bytes have arrived, so the results are never held in memory in full.
+    pub(super) fn read_results(
+        &self,
+        id: QueryJobId,
+        schema: SchemaRef,
+    ) -> SendableRecordBatchStream {
+        let store = self.persister.object_store();
+        let path = self.result_path(id);
+        let bytes = futures::stream::once(async move { store.get(&path).await })
+            .map_ok(|result| result.into_stream())
+            .try_flatten()
+            .map_err(DataFusionError::from)
+            .boxed();
+        Box::pin(RecordBatchStreamAdapter::new(
+            schema,
+            read_ipc_stream(bytes),
+        ))
+    }
+
+    /// Remove jobs that finished longer ago than the configured TTL, along with their results
+    pub(super) async fn remove_expired(&self) {
+        let now = self.time_provider.now();
+        let expired = {
+            let mut jobs = self.jobs.lock();
+            for job in jobs.values_mut() {
+                job.reap(now);
+            }
+            let expired = jobs
+                .iter()
+                .filter(|(_, job)| {
+                    job.finished_at
+                        .and_then(|t| now.checked_duration_since(t))
+                        .is_some_and(|elapsed| elapsed > self.ttl)
+                })
+                .map(|(id, job)| (*id, job.status == QueryJobStatus::Done))
+                .collect::<Vec<_>>();
+            for (id, _) in &expired {
+                jobs.remove(id);
+            }
+            expired
+        };
+        for (id, _) in expired.into_iter().filter(|(_, has_results)| *has_results) {
+            if let Err(error) = self
+                .persister
+                .object_store()
+                .delete(&self.result_path(id))
+                .await
+            {
+                warn!(%id, %error, "failed to delete expired query job results");
+            }
+        }
+    }
+
+    /// Remove results from object storage that do not belong to a known job, i.e., that were
+    /// left by a previous run of the server, and so can no longer be fetched
+    pub(super) async fn remove_stale_results(&self) -> Result<usize, object_store::Error> {
+        let store = self.persister.object_store();
+        let stale = store
+            .list(Some(&self.results_prefix()))
+            .map_ok(|meta| meta.location)
+            .try_filter(|path| {
+                let known = path
+                    .filename()
+                    .and_then(|name| name.strip_suffix(QUERY_JOB_RESULT_FILE_EXTENSION))
+                    .and_then(|stem| stem.strip_suffix('.'))
+                    .and_then(|id| id.parse::<QueryJobId>().ok())
+                    .is_some_and(|id| self.jobs.lock().contains_key(&id));
+                futures::future::ready(!known)
+            })
+            .boxed();
+        let removed = store.delete_stream(stale).try_collect::<Vec<_>>().await?;
+        Ok(removed.len())
+    }
+}
+
+/// Write the `results` to the `upload` in the Arrow IPC streaming format, handing each batch to
+/// the upload as it is encoded, so that only the parts being uploaded are held in memory
+async fn write_ipc_stream(
+    upload: &mut WriteMultipart,
+    schema: &SchemaRef,
+    results: &mut SendableRecordBatchStream,
+) -> Result<(), DataFusionError> {
+    let mut writer = StreamWriter::try_new(Vec::new(), schema)?;
+    while let Some(batch) = results.try_next().await? {
+        writer.write(&batch)?;
+        upload.write(&std::mem::take(writer.get_mut()));
+        upload
+            .wait_for_capacity(MAX_CONCURRENT_RESULT_PARTS)
+            .await?;
+    }
+    writer.finish()?;
+    upload.write(&writer.into_inner()?);
+    Ok(())
+}
+
+/// Decode the batches of an Arrow IPC stream from its `bytes`, as they arrive
+fn read_ipc_stream(
+    bytes: BoxStream<'static, Result<Bytes, DataFusionError>>,
+) -> impl Stream<Item = Result<RecordBatch, DataFusionError>> + Send {
+    let state = (
+        bytes,
+        StreamDecoder::new(),
+        Buffer::from_vec(Vec::<u8>::new()),
+    );
+    futures::stream::try_unfold(state, |(mut bytes, mut decoder, mut buffer)| async move {
+        loop {
+            if let Some(batch) = decoder.decode(&mut buffer)? {
+                return Ok(Some((batch, (bytes, decoder, buffer))));
+            }
+            // the decoder has consumed the whole buffer if it did not produce a batch
+            match bytes.try_next().await? {
+                Some(chunk) => buffer = Buffer::from(chunk),
+                None => {
+                    decoder.finish()?;
+                    return Ok(None);
+                }
+            }
+        }
+    })
+}
+
+/// Remove the results left in object storage by a previous run of the server, then remove
+/// expired jobs, and their results, every `interval`
+pub(super) async fn background_expiry_process(
+    jobs: Arc<QueryJobs>,
+    interval: Duration,
+) -> JoinHandle<()> {
+    match jobs.remove_stale_results().await {
+        Ok(removed) => info!(removed, "removed stale query job results"),
+        Err(error) => warn!(%error, "failed to remove stale query job results"),
+    }
+    tokio::spawn(async move {
+        let mut interval = tokio::time::interval(interval);
+        interval.set_missed_tick_behavior(tokio::time::MissedTickBehavior::Skip);
+        loop {
+            interval.tick().await;
+            jobs.remove_expired().await;
+        }
+    })
+}
@@ -0,0 +1,54 @@
+//! Detection of joins without a join condition, i.e., cartesian products, that are estimated to
+//! produce too many rows
+use std::sync::Arc;
+
+use datafusion::physical_plan::{
+    joins::{CrossJoinExec, NestedLoopJoinExec},
+    ExecutionPlan,
+};
+use influxdb3_internal_api::query_executor::QueryExecutorError;
+
+/// Check that none of the joins in the `plan` that lack a join condition are estimated to
+/// produce more than `limit` rows
+///
+/// The estimate is the product of the number of rows on either side of the join, taken from the
+/// statistics of the chunks being scanned. Joins whose inputs have no row count statistics are
+/// not rejected.
+pub(super) fn check_cross_joins(
+    plan: &Arc<dyn ExecutionPlan>,
+    limit: usize,
+) -> Result<(), QueryExecutorError> {
+    if let Some((left, right)) = unconditional_join_inputs(plan) {
+        if let (Some(left), Some(right)) = (estimated_rows(left), estimated_rows(right)) {
+            let estimated_rows = left.saturating_mul(right);
+            if estimated_rows > limit {
+                return Err(QueryExecutorError::UnboundedJoin {
+                    estimated_rows,
+                    limit,
+                });
+            }
+        }
+    }
+    plan.children()
+        .into_iter()
+        .try_for_each(|child| check_cross_joins(child, limit))
+}
+
+/// The inputs of the `plan`, if it is a join without a join condition
+fn unconditional_join_inputs(
+    plan: &Arc<dyn ExecutionPlan>,
+) -> Option<(&Arc<dyn ExecutionPlan>, &Arc<dyn ExecutionPlan>)> {
+    let any = plan.as_any();
+    if let Some(join) = any.downcast_ref::<CrossJoinExec>() {
+        return Some((join.left(), join.right()));
+    }
+    any.downcast_ref::<NestedLoopJoinExec>()
+        .filter(|join| join.filter().is_none())
+        .map(|join| (join.left(), join.right()))
+}
+
+fn estimated_rows(plan: &Arc<dyn ExecutionPlan>) -> Option<usize> {
+    plan.statistics()
+        .ok()
+        .and_then(|stats| stats.num_rows.get_value().copied())
+}
@@ -0,0 +1,99 @@
+//! A maintenance mode in which new queries are rejected, while queries that are already running
+//! are allowed to finish
+use std::{
+    pin::Pin,
+    sync::{
+        atomic::{AtomicBool, AtomicUsize, Ordering},
+        Arc,
+    },
+    task::{Context, Poll},
+};
+
+use arrow::{datatypes::SchemaRef, record_batch::RecordBatch};
+use datafusion::{
+    error::DataFusionError,
+    execution::{RecordBatchStream, SendableRecordBatchStream},
+};
+use futures::{Stream, StreamExt};
+use influxdb3_internal_api::query_executor::QueryExecutorError;
+use tokio::sync::Notify;
+
+/// Tracks whether maintenance mode is enabled, and the queries that are active
+#[derive(Debug, Default)]
+pub(super) struct Maintenance {
+    enabled: AtomicBool,
+    active: AtomicUsize,
+    drained: Notify,
+}
+
+impl Maintenance {
+    pub(super) fn set_enabled(&self, enabled: bool) {
+        self.enabled.store(enabled, Ordering::SeqCst);
+    }
+
+    /// Start a new query, which is rejected if maintenance mode is enabled
+    ///
+    /// The query is active until the returned [`ActiveQuery`] is dropped.
+    pub(super) fn start_query(self: &Arc<Self>) -> Result<ActiveQuery, QueryExecutorError> {
+        if self.enabled.load(Ordering::SeqCst) {
+            return Err(QueryExecutorError::Unavailable {
+                reason: "the server is in maintenance mode".to_string(),
+            });
+        }
+        self.active.fetch_add(1, Ordering::SeqCst);
+        Ok(ActiveQuery(Arc::clone(self)))
+    }
+
+    /// Wait until there are no active queries
+    pub(super) async fn drain(&self) {
+        loop {
+            // register for the notification before checking, so that it cannot be missed:
+            let drained = self.drained.notified();
+            if self.active.load(Ordering::SeqCst) == 0 {
+                return;
+            }
+            drained.await;
+        }
+    }
+}
+
+/// A query that is active until this is dropped
+#[derive(Debug)]
+pub(super) struct ActiveQuery(Arc<Maintenance>);
+
+impl ActiveQuery {
+    /// Keep the query active until its output `stream` is dropped
+    pub(super) fn track(self, stream: SendableRecordBatchStream) -> SendableRecordBatchStream {
+        Box::pin(ActiveQueryStream {
+            inner: stream,
+            _active: self,
+        })
+    }
+}
+
+impl Drop for ActiveQuery {
+    fn drop(&mut self) {
+        if self.0.active.fetch_sub(1, Ordering::SeqCst) == 1 {
+            self.0.drained.notify_waiters();
+        }
+    }
+}
+
+struct ActiveQueryStream {
+    inner: SendableRecordBatchStream,
+    _active: ActiveQuery,
+}
+
+impl Stream for ActiveQueryStream {
+    type Item = Result<RecordBatch, DataFusionError>;
+
+    fn poll_next(mut self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Option<Self::Item>> {
+        self.inner.poll_next_unpin(cx)
+    }
+}
+
+impl RecordBatchStream for ActiveQueryStream {
+    fn schema(&self) -> SchemaRef {
+        self.inner.schema()
+    }
+}
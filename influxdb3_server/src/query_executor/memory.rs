@@ -0,0 +1,337 @@
+//! A memory budget for aggregate operators that is separate from the executor's memory pool
+//!
+//! Aggregates that exceed their budget handle the out-of-memory condition in the same way as
+//! when the executor's memory pool is exhausted, i.e., by emitting early or spilling, while
+//! other operators in the same query continue to allocate from the executor's memory pool.
+//!
+//! The budget is set for each session in its config, see [`set_session_budget`], from the
+//! [`AGGREGATE_MEM_POOL_SIZE_CONFIG_KEY`] option of the DataFusion config that the session is
+//! made with, if it is given, or from the server's default otherwise.
+//!
+//! Queries can also be held back from execution while the executor's memory pool is under
+//! pressure, see [`admit_query`].
+use std::{
+    any::Any,
+    collections::HashMap,
+    fmt,
+    sync::{
+        atomic::{AtomicUsize, Ordering},
+        Arc,
+    },
+    time::Duration,
+};
+
+use datafusion::{
+    error::DataFusionError,
+    execution::{
+        disk_manager::{DiskManager, DiskManagerConfig},
+        memory_pool::{MemoryConsumer, MemoryPool, MemoryReservation},
+        runtime_env::RuntimeEnv,
+        SendableRecordBatchStream, TaskContext,
+    },
+    physical_plan::{DisplayAs, DisplayFormatType, ExecutionPlan, PlanProperties},
+};
+use influxdb3_internal_api::query_executor::QueryExecutorError;
+use iox_query::exec::IOxSessionContext;
+use observability_deps::tracing::warn;
+use tokio::time::Instant;
+
+/// The DataFusion config option that overrides the memory budget of the aggregate operators of
+/// each query, in bytes
+///
+/// This is not passed on to DataFusion, which does not know of it.
+pub const AGGREGATE_MEM_POOL_SIZE_CONFIG_KEY: &str = "influxdb3.aggregate_mem_pool_size";
+
+/// Get the aggregate memory budget from the DataFusion `config`, if it is given and valid
+pub(super) fn config_budget(config: &HashMap<String, String>) -> Option<usize> {
+    let value = config.get(AGGREGATE_MEM_POOL_SIZE_CONFIG_KEY)?;
+    match value.parse() {
+        Ok(limit) => Some(limit),
+        Err(error) => {
+            warn!(
+                %value,
+                %error,
+                "ignoring invalid {AGGREGATE_MEM_POOL_SIZE_CONFIG_KEY} config option"
+            );
+            None
+        }
+    }
+}
+
+/// The aggregate memory budget of the queries run in a session, which is kept as an extension
+/// of the session's config
+#[derive(Debug)]
+struct SessionBudget(usize);
+
+/// Limit the memory used by the aggregate operators of each query run in the session `ctx` to
+/// `limit` bytes
+pub(super) fn set_session_budget(ctx: &IOxSessionContext, limit: usize) {
+    ctx.inner()
+        .state_ref()
+        .write()
+        .config_mut()
+        .set_extension(Arc::new(SessionBudget(limit)));
+}
+
+/// The aggregate memory budget of the session `ctx`, if it has one, see [`set_session_budget`]
+pub(super) fn session_budget(ctx: &IOxSessionContext) -> Option<usize> {
+    ctx.inner()
+        .state_ref()
+        .read()
+        .config()
+        .get_extension::<SessionBudget>()
+        .map(|budget| budget.0)
+}
+
+/// How long a query waits for the executor's memory pool to drop to its high-water mark before
+/// it is rejected, see [`admit_query`]
+pub(super) const MEMORY_PRESSURE_WAIT: Duration = Duration::from_millis(100);
+
+/// How often the executor's memory pool is checked while a query waits to be admitted
+const MEMORY_PRESSURE_POLL_INTERVAL: Duration = Duration::from_millis(10);
+
+/// Admit a query for execution once no more than `high_water_mark` bytes are reserved from the
+/// executor's memory `pool`
+///
+/// The query waits up to [`MEMORY_PRESSURE_WAIT`] for executing queries to release their memory,
+/// and is rejected with [`QueryExecutorError::MemoryPressure`] if the pool is still above its
+/// high-water mark by then, rather than failing with an out-of-memory error part way through
+/// its execution.
+pub(super) async fn admit_query(
+    pool: &dyn MemoryPool,
+    high_water_mark: usize,
+) -> Result<(), QueryExecutorError> {
+    let started = Instant::now();
+    loop {
+        let reserved = pool.reserved();
+        if reserved <= high_water_mark {
+            return Ok(());
+        }
+        if started.elapsed() >= MEMORY_PRESSURE_WAIT {
+            return Err(QueryExecutorError::MemoryPressure {
+                reserved,
+                high_water_mark,
+            });
+        }
+        tokio::time::sleep(MEMORY_PRESSURE_POLL_INTERVAL).await;
+    }
+}
+
+/// Wrap the `plan` so that the memory used by its aggregate operators is limited to `limit`
+/// bytes in total
+pub(super) fn limit_aggregate_memory(
+    plan: Arc<dyn ExecutionPlan>,
+    limit: usize,
+) -> Arc<dyn ExecutionPlan> {
+    Arc::new(AggregateBudgetExec {
+        input: plan,
+        budget: Arc::new(AggregateBudget {
+            limit,
+            used: AtomicUsize::new(0),
+        }),
+    })
+}
+
+/// The memory used by aggregate operators in a single query
+#[derive(Debug)]
+struct AggregateBudget {
+    limit: usize,
+    used: AtomicUsize,
+}
+
+/// Executes its input with a [`MemoryPool`] that applies the [`AggregateBudget`] on top of the
+/// executor's memory pool
+#[derive(Debug)]
+struct AggregateBudgetExec {
+    input: Arc<dyn ExecutionPlan>,
+    budget: Arc<AggregateBudget>,
+}
+
+impl DisplayAs for AggregateBudgetExec {
+    fn fmt_as(&self, t: DisplayFormatType, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match t {
+            DisplayFormatType::Default | DisplayFormatType::Verbose => {
+                write!(f, "AggregateBudgetExec: limit={}", self.budget.limit)
+            }
+        }
+    }
+}
+
+impl ExecutionPlan for AggregateBudgetExec {
+    fn name(&self) -> &str {
+        "AggregateBudgetExec"
+    }
+
+    fn as_any(&self) -> &dyn Any {
+        self
+    }
+
+    fn properties(&self) -> &PlanProperties {
+        self.input.properties()
+    }
+
+    fn children(&self) -> Vec<&Arc<dyn ExecutionPlan>> {
+        vec![&self.input]
+    }
+
+    fn with_new_children(
+        self: Arc<Self>,
+        mut children: Vec<Arc<dyn ExecutionPlan>>,
+    ) -> Result<Arc<dyn ExecutionPlan>, DataFusionError> {
+        if children.len() != 1 {
+            return Err(DataFusionError::Internal(format!(
+                "AggregateBudgetExec expects a single child, got {}",
+                children.len()
+            )));
+        }
+        Ok(Arc::new(Self {
+            input: children.remove(0),
+            budget: Arc::clone(&self.budget),
+        }))
+    }
+
+    fn execute(
+        &self,
+        partition: usize,
+        context: Arc<TaskContext>,
+    ) -> Result<SendableRecordBatchStream, DataFusionError> {
+        let runtime = context.runtime_env();
+        // aggregates that exceed their budget spill to disk, even if the executor does not
+        // otherwise spill:
+        let disk_manager = if runtime.disk_manager.tmp_files_enabled() {
+            Arc::clone(&runtime.disk_manager)
+        } else {
+            DiskManager::try_new(DiskManagerConfig::NewOs)?
+        };
+        let runtime = Arc::new(RuntimeEnv {
+            memory_pool: Arc::new(AggregateMemoryPool {
+                inner: Arc::clone(&runtime.memory_pool),
+                budget: Arc::clone(&self.budget),
+            }),
+            disk_manager,
+            cache_manager: Arc::clone(&runtime.cache_manager),
+            object_store_registry: Arc::clone(&runtime.object_store_registry),
+        });
+        let context = Arc::new(TaskContext::new(
+            context.task_id(),
+            context.session_id(),
+            context.session_config().clone(),
+            context.scalar_functions().clone(),
+            context.aggregate_functions().clone(),
+            context.window_functions().clone(),
+            runtime,
+        ));
+        self.input.execute(partition, context)
+    }
+}
+
+/// Memory consumers registered by DataFusion's aggregate operators are named after their
+/// stream, e.g., `GroupedHashAggregateStream[0]`
+///
+/// A change to these names is caught by the `aggregate_budget_spills` test of the query executor.
+fn is_aggregate(consumer: &MemoryConsumer) -> bool {
+    consumer.name().contains("AggregateStream")
+}
+
+/// A [`MemoryPool`] that limits the memory reserved by aggregate operators to the
+/// [`AggregateBudget`], and otherwise defers to the `inner` pool
+#[derive(Debug)]
+struct AggregateMemoryPool {
+    inner: Arc<dyn MemoryPool>,
+    budget: Arc<AggregateBudget>,
+}
+
+impl MemoryPool for AggregateMemoryPool {
+    fn register(&self, consumer: &MemoryConsumer) {
+        self.inner.register(consumer)
+    }
+
+    fn unregister(&self, consumer: &MemoryConsumer) {
+        self.inner.unregister(consumer)
+    }
+
+    fn grow(&self, reservation: &MemoryReservation, additional: usize) {
+        if is_aggregate(reservation.consumer()) {
+            self.budget.used.fetch_add(additional, Ordering::Relaxed);
+        }
+        self.inner.grow(reservation, additional)
+    }
+
+    fn shrink(&self, reservation: &MemoryReservation, shrink: usize) {
+        if is_aggregate(reservation.consumer()) {
+            self.budget.used.fetch_sub(shrink, Ordering::Relaxed);
+        }
+        self.inner.shrink(reservation, shrink)
+    }
+
+    fn try_grow(
+        &self,
+        reservation: &MemoryReservation,
+        additional: usize,
+    ) -> Result<(), DataFusionError> {
+        if !is_aggregate(reservation.consumer()) {
+            return self.inner.try_grow(reservation, additional);
+        }
+        let limit = self.budget.limit;
+        self.budget
+            .used
+            .fetch_update(Ordering::Relaxed, Ordering::Relaxed, |used| {
+                used.checked_add(additional).filter(|n| *n <= limit)
+            })
+            .map_err(|used| {
+                DataFusionError::ResourcesExhausted(format!(
+                    "Failed to allocate additional {additional} bytes for {} with {used} bytes \
+                    already allocated for aggregates - maximum available is {}",
+                    reservation.consumer().name(),
+                    limit.saturating_sub(used),
+                ))
+            })?;
+        self.inner
+            .try_grow(reservation, additional)
+            .inspect_err(|_| {
+                self.budget.used.fetch_sub(additional, Ordering::Relaxed);
+            })
+    }
+
+    fn reserved(&self) -> usize {
+        self.inner.reserved()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::sync::{atomic::AtomicUsize, Arc};
+
+    use datafusion::execution::memory_pool::{GreedyMemoryPool, MemoryConsumer, MemoryPool};
+
+    use super::{AggregateBudget, AggregateMemoryPool};
+
+    #[test]
+    fn aggregates_limited_to_budget() {
+        let pool: Arc<dyn MemoryPool> = Arc::new(AggregateMemoryPool {
+            inner: Arc::new(GreedyMemoryPool::new(1024)),
+            budget: Arc::new(AggregateBudget {
+                limit: 100,
+                used: AtomicUsize::new(0),
+            }),
+        });
+
+        let mut aggregate = MemoryConsumer::new("GroupedHashAggregateStream[0]").register(&pool);
+        let mut join = MemoryConsumer::new("HashJoinInput[0]").register(&pool);
+
+        // the aggregate can allocate up to its budget, but no further, which would cause it to
+        // emit early or spill:
+        aggregate.try_grow(80).unwrap();
+        aggregate.try_grow(40).unwrap_err();
+        assert_eq!(80, aggregate.size());
+
+        // a join in the same query is only limited by the executor's memory pool:
+        join.try_grow(500).unwrap();
+        assert_eq!(580, pool.reserved());
+
+        // freeing aggregate memory makes room in the budget again:
+        aggregate.shrink(60);
+        aggregate.try_grow(40).unwrap();
+        assert_eq!(60, aggregate.size());
+    }
+}
@@ -1,47 +1,90 @@
 //! module for query executor
 use crate::system_tables::{SystemSchemaProvider, SYSTEM_SCHEMA_NAME};
 use crate::{query_planner::Planner, system_tables::AllSystemSchemaTablesProvider};
-use arrow::array::{ArrayRef, Int64Builder, StringBuilder, StructArray};
+use arrow::array::{
+    ArrayRef, BooleanBuilder, Int64Builder, StringBuilder, StructArray, UInt64Array,
+};
 use arrow::datatypes::SchemaRef;
 use arrow::record_batch::RecordBatch;
 use arrow_array::{Array, BooleanArray};
 use async_trait::async_trait;
-use data_types::NamespaceId;
+use create_table::CreateTableAs;
+use data_types::{NamespaceId, NamespaceName};
 use datafusion::catalog::{CatalogProvider, SchemaProvider, Session};
 use datafusion::common::arrow::array::StringArray;
 use datafusion::common::arrow::datatypes::{DataType, Field, Schema as DatafusionSchema};
 use datafusion::datasource::{TableProvider, TableType};
 use datafusion::error::DataFusionError;
 use datafusion::execution::SendableRecordBatchStream;
+use datafusion::functions_aggregate::approx_distinct::approx_distinct_udaf;
 use datafusion::logical_expr::TableProviderFilterPushDown;
+use datafusion::physical_plan::empty::EmptyExec;
+use datafusion::physical_plan::stream::RecordBatchStreamAdapter;
 use datafusion::physical_plan::ExecutionPlan;
 use datafusion::prelude::Expr;
+use datafusion::sql::parser::DFParser;
 use datafusion_util::config::DEFAULT_SCHEMA;
 use datafusion_util::MemoryStream;
-use influxdb3_cache::distinct_cache::{DistinctCacheFunction, DISTINCT_CACHE_UDTF_NAME};
+use dictionary_stats::DictionaryStatsCollector;
+use execution_stats::ExecutionStatsStream;
+use explain_chunks::ScanFilters;
+use futures::{Stream, StreamExt, TryStreamExt};
+use influxdb3_cache::distinct_cache::{
+    DistinctCacheFallback, DistinctCacheFunction, DISTINCT_CACHE_UDTF_NAME,
+};
 use influxdb3_cache::last_cache::{LastCacheFunction, LAST_CACHE_UDTF_NAME};
+use influxdb3_cache::parquet_cache::{
+    ParquetCacheFunction, ParquetCacheOracle, PARQUET_CACHE_UDTF_NAME,
+};
 use influxdb3_catalog::catalog::{Catalog, DatabaseSchema};
-use influxdb3_internal_api::query_executor::{QueryExecutor, QueryExecutorError, QueryKind};
+use influxdb3_id::{ParquetFileId, TableId};
+use influxdb3_internal_api::query_executor::{
+    Collation, DistinctCacheMode, QueryExecutor, QueryExecutorError, QueryKind, QueryOptions,
+    QueryPriority, StorageHint,
+};
 use influxdb3_sys_events::SysEventStore;
 use influxdb3_telemetry::store::TelemetryStore;
-use influxdb3_write::WriteBuffer;
+use influxdb3_write::chunk::{BufferChunk, ParquetChunk};
+use influxdb3_write::persister::Persister;
+use influxdb3_write::write_buffer::parquet_chunk_from_file;
+use influxdb3_write::{Precision, WriteBuffer};
 use iox_query::exec::{Executor, IOxSessionContext, QueryConfig};
 use iox_query::provider::ProviderBuilder;
 use iox_query::query_log::QueryLog;
 use iox_query::query_log::QueryText;
 use iox_query::query_log::StateReceived;
-use iox_query::query_log::{QueryCompletedToken, QueryLogEntries};
+use iox_query::query_log::{QueryCompletedToken, QueryLogEntries, QueryLogEntryState};
 use iox_query::QueryDatabase;
 use iox_query::{QueryChunk, QueryNamespace};
 use iox_query_params::StatementParams;
+use iox_time::{Time, TimeProvider};
+use jobs::QueryJobs;
+use maintenance::{ActiveQuery, Maintenance};
 use metric::Registry;
-use observability_deps::tracing::{debug, info};
-use schema::Schema;
+use observability_deps::tracing::{debug, info, warn};
+use parking_lot::Mutex;
+use progress::{QueryProgress, ScanProgress};
+use query_events::QueryEvents;
+use read_ahead::ReadAhead;
+use request_budget::RequestBudget;
+use resource_budget::ResourceBudget;
+use result_cache::{CacheKey, ResultCache, TableGenerations};
+use schema::{InfluxColumnType, Schema};
+use single_flight::{InFlightQueries, Joined};
+use stats::StatsRecordingStream;
 use std::any::Any;
 use std::cmp::Ordering;
-use std::collections::HashMap;
+use std::collections::{BTreeMap, HashMap};
 use std::fmt::Debug;
+use std::num::NonZeroUsize;
+use std::ops::Range;
+use std::path::Path;
+use std::str::FromStr;
+use std::sync::atomic::{AtomicBool, Ordering as AtomicOrdering};
 use std::sync::Arc;
+use std::time::{Duration, Instant};
+use time_range::TimeBucketLimit;
+use timeout::Deadline;
 use tokio::sync::Semaphore;
 use trace::ctx::SpanContext;
 use trace::span::{Span, SpanExt, SpanRecorder};
@@ -50,16 +93,176 @@ use tracker::{
     AsyncSemaphoreMetrics, InstrumentedAsyncOwnedSemaphorePermit, InstrumentedAsyncSemaphore,
 };
 
+mod approx_distinct;
+mod casts;
+mod chunk_order;
+mod collation;
+mod computed_columns;
+mod constants;
+mod create_table;
+mod dictionary_stats;
+mod durations;
+mod effective_query;
+mod execution_stats;
+mod explain_chunks;
+mod field_types;
+mod group_by;
+mod grouped;
+mod influxql_series;
+mod jobs;
+mod joins;
+mod maintenance;
+mod memory;
+mod output_columns;
+mod params;
+mod partial_aggregates;
+mod partition_order;
+mod plan_complexity;
+mod planning;
+mod progress;
+mod query_events;
+mod read_ahead;
+mod reader;
+mod request_budget;
+mod resource_budget;
+mod result_cache;
+mod retention;
+mod retry;
+mod row_ids;
+mod single_flight;
+mod stats;
+mod suggestions;
+mod tickets;
+mod time_range;
+mod timeout;
+mod wildcards;
+mod workload;
+
+pub use execution_stats::{ExecutionStats, ExecutionStatsFuture};
+pub use grouped::GroupKey;
+pub use influxql_series::{InfluxQlSeries, SeriesMetadata, SeriesTag, SERIES_METADATA_KEY};
+pub use jobs::{
+    QueryJobId, QueryJobStatus, DEFAULT_QUERY_JOB_EXPIRY_INTERVAL, DEFAULT_QUERY_JOB_TTL,
+};
+pub use memory::AGGREGATE_MEM_POOL_SIZE_CONFIG_KEY;
+pub use partial_aggregates::merge_partials;
+pub use query_events::{QueryFailed, SlowQuery};
+pub use reader::QueryResultReader;
+pub use row_ids::ROW_ID_COLUMN_NAME;
+pub use stats::QueryTagCost;
+use stats::ResultSource;
+pub(crate) use stats::{QueryLogStats, QueryStats};
+use tickets::TicketToken;
+pub use tickets::{ResultTicket, QUERY_RESULT_UDTF_NAME};
+pub use timeout::QUERY_TIMEOUT_CONFIG_KEY;
+pub use workload::{ReplayedQuery, WorkloadError, WorkloadQuery};
+
 #[derive(Debug, Clone)]
 pub struct QueryExecutorImpl {
     catalog: Arc<Catalog>,
     write_buffer: Arc<dyn WriteBuffer>,
     exec: Arc<Executor>,
+    batch_exec: Option<Arc<Executor>>,
     datafusion_config: Arc<HashMap<String, String>>,
     query_execution_semaphore: Arc<InstrumentedAsyncSemaphore>,
     query_log: Arc<QueryLog>,
+    /// Issues the tokens of queries that are not recorded in the `query_log`, see
+    /// [`QueryOptions::log`]
+    unlogged_query_log: Arc<QueryLog>,
+    query_log_stats: Arc<QueryLogStats>,
+    aggregate_mem_pool_size: Option<usize>,
+    mem_pool_high_water_mark: Option<usize>,
+    max_transient_retries: usize,
+    max_planning_time: Option<Duration>,
+    slow_planning_threshold: Option<Duration>,
+    slow_query_threshold: Option<Duration>,
+    cross_join_row_limit: Option<usize>,
+    max_query_time_range: Option<Duration>,
+    max_time_buckets: Option<usize>,
+    max_storage_requests: Option<usize>,
+    max_plan_nodes: Option<usize>,
+    max_query_cost: Option<u64>,
+    query_cost_row_weight: u64,
+    query_timeout: Option<Duration>,
     telemetry_store: Arc<TelemetryStore>,
     sys_events_store: Arc<SysEventStore>,
+    persister: Arc<Persister>,
+    time_provider: Arc<dyn TimeProvider>,
+    query_jobs: Arc<QueryJobs>,
+    query_progress: Arc<QueryProgress>,
+    maintenance: Arc<Maintenance>,
+    result_cache: Option<Arc<ResultCache>>,
+    in_flight: Option<Arc<InFlightQueries>>,
+    parquet_cache: Option<Arc<dyn ParquetCacheOracle>>,
+    read_ahead: Option<ReadAhead>,
+    replay_policy: ReplayPolicy,
+    default_retention_policy: Arc<str>,
+    column_names: ColumnNames,
+}
+
+/// How queries are handled while the write buffer is replaying the write-ahead log, e.g., on
+/// startup, when the data that it holds is incomplete
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum ReplayPolicy {
+    /// Reject queries with [`QueryExecutorError::ReplayInProgress`], so that clients retry them
+    Reject,
+    /// Serve queries from the data replayed so far, flagging them with
+    /// [`ExecutionStats::replay_in_progress`]
+    #[default]
+    Warn,
+}
+
+#[derive(Debug, thiserror::Error)]
+#[error("invalid replay policy '{0}', expected one of 'reject' or 'warn'")]
+pub struct InvalidReplayPolicy(String);
+
+impl FromStr for ReplayPolicy {
+    type Err = InvalidReplayPolicy;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "reject" => Ok(Self::Reject),
+            "warn" => Ok(Self::Warn),
+            _ => Err(InvalidReplayPolicy(s.to_string())),
+        }
+    }
+}
+
+/// The names of the columns in the output of `SHOW DATABASES` and `SHOW RETENTION POLICIES`
+/// that are named after the internals of the query engine, e.g., `iox::database`
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum ColumnNames {
+    /// Keep the internal names, which clients of earlier versions expect
+    #[default]
+    Legacy,
+    /// Use names without the `iox::` prefix, e.g., `database`
+    Friendly,
+}
+
+impl ColumnNames {
+    /// The name of the column holding the name of each database
+    fn database(self) -> &'static str {
+        match self {
+            Self::Legacy => "iox::database",
+            Self::Friendly => "database",
+        }
+    }
+}
+
+#[derive(Debug, thiserror::Error)]
+#[error("invalid column names '{0}', expected one of 'legacy' or 'friendly'")]
+pub struct InvalidColumnNames(String);
+
+impl FromStr for ColumnNames {
+    type Err = InvalidColumnNames;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "legacy" => Ok(Self::Legacy),
+            "friendly" => Ok(Self::Friendly),
+            _ => Err(InvalidColumnNames(s.to_string())),
+        }
+    }
 }
 
 /// Arguments for [`QueryExecutorImpl::new`]
@@ -68,11 +271,94 @@ pub struct CreateQueryExecutorArgs {
     pub catalog: Arc<Catalog>,
     pub write_buffer: Arc<dyn WriteBuffer>,
     pub exec: Arc<Executor>,
+    /// Run queries with [`QueryPriority::Batch`] on this executor, so that they do not compete
+    /// for threads with interactive queries, which are run on `exec`
+    pub batch_exec: Option<Arc<Executor>>,
     pub metrics: Arc<Registry>,
+    /// The time at which the results of `CREATE TABLE ... AS` statements are written, and at
+    /// which query jobs expire
+    pub time_provider: Arc<dyn TimeProvider>,
     pub datafusion_config: Arc<HashMap<String, String>>,
     pub query_log_size: usize,
     pub telemetry_store: Arc<TelemetryStore>,
     pub sys_events_store: Arc<SysEventStore>,
+    /// Used to store the results of queries submitted with [`QueryExecutorImpl::submit_query`]
+    pub persister: Arc<Persister>,
+    /// How long the results of a finished query job are retained
+    pub query_job_ttl: Duration,
+    /// Limit the memory used by the aggregate operators of each query to this many bytes,
+    /// independently of the memory pool of the executor, unless the DataFusion config of the
+    /// query overrides it with [`AGGREGATE_MEM_POOL_SIZE_CONFIG_KEY`]
+    pub aggregate_mem_pool_size: Option<usize>,
+    /// Reject queries with [`QueryExecutorError::MemoryPressure`] while more than this many bytes
+    /// of the executor's memory pool are reserved, once they have waited briefly for it to drop,
+    /// rather than have them fail with an out-of-memory error as they execute
+    pub mem_pool_high_water_mark: Option<usize>,
+    /// How many times to retry executing a query that fails with a transient error, e.g., a
+    /// network error reading from the object store, before it has produced any results
+    pub max_transient_retries: usize,
+    /// Fail queries whose planning takes longer than this, independently of how long their
+    /// execution takes
+    pub max_planning_time: Option<Duration>,
+    /// Log a warning with the text of queries whose planning takes longer than this, and keep
+    /// their results in the result cache in preference to those of queries that plan quickly
+    pub slow_planning_threshold: Option<Duration>,
+    /// Record a [`SlowQuery`] event in the `system.events` table for queries whose results take
+    /// longer than this to be read, from the query being issued
+    pub slow_query_threshold: Option<Duration>,
+    /// Reject queries that join tables without a join condition, if the join is estimated to
+    /// produce more than this many rows, unless [`QueryOptions::allow_cross_joins`] is set
+    pub cross_join_row_limit: Option<usize>,
+    /// Reject queries whose condition on time spans more than this, or that have no lower bound
+    /// on time, unless [`QueryOptions::allow_unbounded_time_range`] is set
+    pub max_query_time_range: Option<Duration>,
+    /// Reject InfluxQL queries whose `GROUP BY time()` would divide their condition on time into
+    /// more than this many buckets
+    pub max_time_buckets: Option<usize>,
+    /// Reject queries that would make more than this many GET requests to the object store,
+    /// unless [`QueryOptions::max_storage_requests`] overrides it for the query
+    pub max_storage_requests: Option<usize>,
+    /// Reject queries whose logical plans have more than this many nodes, see
+    /// [`QueryExecutorError::PlanTooComplex`]
+    pub max_plan_nodes: Option<usize>,
+    /// Cancel queries once the cost of the resources they consume exceeds this, see
+    /// [`QueryExecutorError::ResourceBudgetExceeded`]
+    pub max_query_cost: Option<u64>,
+    /// The cost of each row output by each operator of a query, relative to each byte that it
+    /// scans, which costs one
+    pub query_cost_row_weight: u64,
+    /// Cancel queries that have not completed this long after they were received, including the
+    /// time taken to read their results, unless the DataFusion config that they are run with
+    /// overrides it with [`QUERY_TIMEOUT_CONFIG_KEY`]
+    pub query_timeout: Option<Duration>,
+    /// Cache the results of queries made with [`QueryOptions::cache_results`] set, up to this
+    /// many bytes in total
+    ///
+    /// Queries against databases with a retention period are not cached, as their results
+    /// change as data expires, without any writes that would invalidate them.
+    pub result_cache_size: Option<usize>,
+    /// Coalesce identical queries made while one of them is in flight into a single execution,
+    /// buffering up to this many bytes of its results for the queries that arrive late
+    pub coalesce_buffer_size: Option<usize>,
+    /// The name of the retention policy reported for databases whose name does not include one,
+    /// which is [`AUTOGEN_RETENTION_POLICY`] unless migrated deployments used another name
+    pub default_retention_policy: String,
+    /// The parquet cache that the files scanned by queries are read ahead into, and whose contents
+    /// are listed by the [`PARQUET_CACHE_UDTF_NAME`] table function
+    pub parquet_cache: Option<Arc<dyn ParquetCacheOracle>>,
+    /// Prefetch the parquet files of up to this many chunks ahead of a scan into the
+    /// `parquet_cache`, so that the scan does not wait on the object store for each in turn
+    pub scan_read_ahead: Option<NonZeroUsize>,
+    /// Limit the parquet files being read ahead of a scan to this many bytes at a time
+    pub scan_read_ahead_bytes: usize,
+    /// How queries are handled while the write buffer is replaying the write-ahead log
+    pub replay_policy: ReplayPolicy,
+    /// Execute at most this many queries at a time, where further queries wait for one of them
+    /// to complete once they are planned. Queries are not limited if this is not given.
+    pub max_concurrent_queries: Option<NonZeroUsize>,
+    /// The names of the internal columns in the output of `SHOW DATABASES` and `SHOW RETENTION
+    /// POLICIES`
+    pub column_names: ColumnNames,
 }
 
 impl QueryExecutorImpl {
@@ -81,182 +367,1555 @@ impl QueryExecutorImpl {
             catalog,
             write_buffer,
             exec,
+            batch_exec,
             metrics,
+            time_provider,
             datafusion_config,
             query_log_size,
             telemetry_store,
             sys_events_store,
+            persister,
+            query_job_ttl,
+            aggregate_mem_pool_size,
+            mem_pool_high_water_mark,
+            max_transient_retries,
+            max_planning_time,
+            slow_planning_threshold,
+            slow_query_threshold,
+            cross_join_row_limit,
+            max_query_time_range,
+            max_time_buckets,
+            max_storage_requests,
+            max_plan_nodes,
+            max_query_cost,
+            query_cost_row_weight,
+            query_timeout,
+            result_cache_size,
+            coalesce_buffer_size,
+            default_retention_policy,
+            parquet_cache,
+            scan_read_ahead,
+            scan_read_ahead_bytes,
+            replay_policy,
+            column_names,
+            max_concurrent_queries,
         }: CreateQueryExecutorArgs,
     ) -> Self {
         let semaphore_metrics = Arc::new(AsyncSemaphoreMetrics::new(
             &metrics,
             &[("semaphore", "query_execution")],
         ));
-        let query_execution_semaphore =
-            Arc::new(semaphore_metrics.new_semaphore(Semaphore::MAX_PERMITS));
+        let query_execution_semaphore = Arc::new(
+            semaphore_metrics.new_semaphore(
+                max_concurrent_queries
+                    .map(NonZeroUsize::get)
+                    .unwrap_or(Semaphore::MAX_PERMITS),
+            ),
+        );
+        write_buffer
+            .last_cache_provider()
+            .register_metrics(&metrics);
+        write_buffer
+            .distinct_cache_provider()
+            .register_metrics(&metrics);
+        let log_time_provider: Arc<dyn TimeProvider> = Arc::new(iox_time::SystemProvider::new());
         let query_log = Arc::new(QueryLog::new(
             query_log_size,
-            Arc::new(iox_time::SystemProvider::new()),
+            Arc::clone(&log_time_provider),
+        ));
+        let unlogged_query_log = Arc::new(QueryLog::new(0, log_time_provider));
+        let query_log_stats = Arc::new(QueryLogStats::new(query_log_size));
+        let result_cache = result_cache_size.map(|size| {
+            let cache = Arc::new(ResultCache::new(size, &metrics));
+            // the cache is notified of writes to invalidate the results that they affect:
+            write_buffer
+                .wal()
+                .add_file_notifier(Arc::clone(&cache) as _);
+            cache
+        });
+        let in_flight =
+            coalesce_buffer_size.map(|size| Arc::new(InFlightQueries::new(size, &metrics)));
+        let query_jobs = Arc::new(QueryJobs::new(
+            Arc::clone(&persister),
+            Arc::clone(&time_provider),
+            query_job_ttl,
         ));
         Self {
             catalog,
             write_buffer,
             exec,
+            batch_exec,
             datafusion_config,
             query_execution_semaphore,
             query_log,
+            unlogged_query_log,
+            query_log_stats,
+            aggregate_mem_pool_size,
+            mem_pool_high_water_mark,
+            max_transient_retries,
+            max_planning_time,
+            slow_planning_threshold,
+            slow_query_threshold,
+            cross_join_row_limit,
+            max_query_time_range,
+            max_time_buckets,
+            max_storage_requests,
+            max_plan_nodes,
+            max_query_cost,
+            query_cost_row_weight,
+            query_timeout,
             telemetry_store,
             sys_events_store,
+            persister,
+            time_provider,
+            query_jobs,
+            query_progress: Default::default(),
+            maintenance: Default::default(),
+            result_cache,
+            in_flight,
+            parquet_cache: parquet_cache.clone(),
+            read_ahead: parquet_cache
+                .zip(scan_read_ahead)
+                .map(|(cache, depth)| ReadAhead::new(cache, depth, scan_read_ahead_bytes)),
+            replay_policy,
+            default_retention_policy: default_retention_policy.into(),
+            column_names,
         }
     }
-}
 
-#[async_trait]
-impl QueryExecutor for QueryExecutorImpl {
-    async fn query(
+    fn database(&self, name: &str) -> Result<Database, QueryExecutorError> {
+        let db_schema =
+            self.catalog
+                .db_schema(name)
+                .ok_or_else(|| QueryExecutorError::DatabaseNotFound {
+                    db_name: name.into(),
+                })?;
+        let datafusion_config =
+            merge_datafusion_config(&self.datafusion_config, &db_schema.datafusion_config);
+        Ok(Database::new(
+            db_schema,
+            Arc::clone(&self.write_buffer),
+            Arc::clone(&self.exec),
+            datafusion_config,
+            Arc::clone(&self.query_log),
+            Arc::clone(&self.unlogged_query_log),
+            Arc::clone(&self.query_log_stats),
+            Arc::clone(&self.sys_events_store),
+            Arc::clone(&self.query_jobs),
+        )
+        .with_parquet_cache(self.parquet_cache.clone()))
+    }
+
+    /// Submit a query to be run in the background, returning an id that can be used to poll its
+    /// status with [`Self::job_status`] and to fetch its results with [`Self::fetch_result`]
+    /// once it is [`QueryJobStatus::Done`].
+    ///
+    /// The results are written to object storage, and are retained there until the configured
+    /// TTL has elapsed since the job finished, once [`Self::start_query_job_expiry`] has been
+    /// called.
+    pub async fn submit_query(
+        &self,
+        database: &str,
+        query: &str,
+        params: Option<StatementParams>,
+        kind: QueryKind,
+    ) -> Result<QueryJobId, QueryExecutorError> {
+        if self.catalog.db_schema(database).is_none() {
+            return Err(QueryExecutorError::DatabaseNotFound {
+                db_name: database.to_string(),
+            });
+        }
+        let id = self.query_jobs.register(database, None);
+        let executor = self.clone();
+        let database = database.to_string();
+        let query = query.to_string();
+        self.query_jobs.spawn(id, async move {
+            executor
+                .run_query_job(id, &database, &query, params, kind)
+                .await
+                .map_err(|e| e.to_string())
+        });
+
+        Ok(id)
+    }
+
+    /// Remove the query job results left in object storage by a previous run of the server,
+    /// whose job ids are reused, and then, in the background, remove finished query jobs and
+    /// their results every `interval` once their TTL has elapsed
+    ///
+    /// This should be called once, before any queries are submitted.
+    pub async fn start_query_job_expiry(&self, interval: Duration) -> tokio::task::JoinHandle<()> {
+        jobs::background_expiry_process(Arc::clone(&self.query_jobs), interval).await
+    }
+
+    /// Run a query and store its results, returning a [`ResultTicket`] that can be used to fetch
+    /// them again, e.g., over Flight, without re-running the query
+    ///
+    /// The results are stored in the same way as those of [`Self::submit_query`], and so can be
+    /// fetched until the configured TTL has elapsed since they were stored.
+    pub async fn issue_ticket(
+        &self,
+        database: &str,
+        query: &str,
+        params: Option<StatementParams>,
+        kind: QueryKind,
+    ) -> Result<ResultTicket, QueryExecutorError> {
+        let token = TicketToken::random();
+        let id = self.query_jobs.register(database, Some(token));
+        self.query_jobs.set_status(id, QueryJobStatus::Running);
+        let result = self.run_query_job(id, database, query, params, kind).await;
+        let status = match &result {
+            Ok(()) => QueryJobStatus::Done,
+            Err(e) => QueryJobStatus::Failed {
+                error: e.to_string(),
+            },
+        };
+        self.query_jobs.set_status(id, status);
+        result?;
+
+        let expires_at = self
+            .query_jobs
+            .expires_at(id)
+            .expect("finished query jobs have an expiry");
+        Ok(ResultTicket::new(id, token, expires_at))
+    }
+
+    /// Run a query, returning the [`ExecutionStats`] of the query alongside its results
+    ///
+    /// The statistics are finalized once the results have been read, so the returned future
+    /// resolves only after the result stream has ended, or has been dropped.
+    ///
+    /// A SQL `CREATE [OR REPLACE] TABLE <table> AS <query>` statement writes the results of the
+    /// query to a new table, see [`create_table`], and outputs the number of rows written.
+    ///
+    /// The results of a [`QueryKind::InfluxQlV1Compat`] query describe their series in the
+    /// [`SERIES_METADATA_KEY`] metadata of their schema.
+    ///
+    /// A [`QueryFailed`] event is recorded in the `system.events` table for queries that fail,
+    /// and a [`SlowQuery`] event for those that are slow, see
+    /// [`CreateQueryExecutorArgs::slow_query_threshold`].
+    #[allow(clippy::too_many_arguments)]
+    pub async fn query_with_stats(
         &self,
         database: &str,
         query: &str,
         params: Option<StatementParams>,
         kind: QueryKind,
+        options: QueryOptions,
         span_ctx: Option<SpanContext>,
         external_span_ctx: Option<RequestLogContext>,
-    ) -> Result<SendableRecordBatchStream, QueryExecutorError> {
-        info!(%database, %query, ?params, ?kind, "QueryExecutorImpl as QueryExecutor::query");
-        let db = self
-            .namespace(database, span_ctx.child_span("get database"), false)
+    ) -> Result<(SendableRecordBatchStream, ExecutionStatsFuture), QueryExecutorError> {
+        let create = match kind {
+            QueryKind::Sql => create_table::parse_create_table_as(query)?,
+            QueryKind::InfluxQl | QueryKind::InfluxQlV1Compat => None,
+        };
+        let events = QueryEvents::new(
+            Arc::clone(&self.sys_events_store),
+            self.slow_query_threshold,
+            database,
+            query,
+        );
+        let row_ids = options.row_ids;
+        let (results, stats) = match create {
+            Some(create) => {
+                self.create_table_as(
+                    database,
+                    create,
+                    params,
+                    options,
+                    span_ctx,
+                    external_span_ctx,
+                )
+                .await
+            }
+            None => {
+                self.execute_query(
+                    database,
+                    query,
+                    params,
+                    kind,
+                    options,
+                    span_ctx,
+                    external_span_ctx,
+                    None,
+                )
+                .await
+            }
+        }
+        .inspect_err(|e| events.failed(e))?;
+        let results = match kind {
+            QueryKind::InfluxQlV1Compat => {
+                influxql_series::add_series_metadata(results).inspect_err(|e| events.failed(e))?
+            }
+            QueryKind::Sql | QueryKind::InfluxQl => results,
+        };
+        let results = if row_ids {
+            row_ids::add_row_ids(results)
+        } else {
+            results
+        };
+        Ok((events.track(results), stats))
+    }
+
+    /// Run a `CREATE TABLE ... AS` statement, writing the results of its query to the table once
+    /// they have all been read, and outputting the number of rows written
+    async fn create_table_as(
+        &self,
+        database: &str,
+        create: CreateTableAs,
+        params: Option<StatementParams>,
+        options: QueryOptions,
+        span_ctx: Option<SpanContext>,
+        external_span_ctx: Option<RequestLogContext>,
+    ) -> Result<(SendableRecordBatchStream, ExecutionStatsFuture), QueryExecutorError> {
+        let started = Instant::now();
+        let exists = self
+            .catalog
+            .db_schema(database)
+            .is_some_and(|db| db.table_definition(create.table.as_str()).is_some());
+        let output = |rows: usize, stats: ExecutionStats| {
+            let schema = Arc::new(DatafusionSchema::new(vec![Field::new(
+                "count",
+                DataType::UInt64,
+                false,
+            )]));
+            let count = Arc::new(UInt64Array::from(vec![rows as u64])) as ArrayRef;
+            let batch =
+                RecordBatch::try_new(schema, vec![count]).expect("the count matches the schema");
+            let (results, stats) =
+                ExecutionStatsStream::new(Box::pin(MemoryStream::new(vec![batch])), started, stats);
+            (Box::pin(results) as SendableRecordBatchStream, stats)
+        };
+        if exists && !create.or_replace {
+            if create.if_not_exists {
+                return Ok(output(0, ExecutionStats::default()));
+            }
+            return Err(QueryExecutorError::TableAlreadyExists {
+                table: create.table,
+            });
+        }
+
+        let (results, stats) = self
+            .execute_query(
+                database,
+                &create.query,
+                params,
+                QueryKind::Sql,
+                options,
+                span_ctx,
+                external_span_ctx,
+                None,
+            )
+            .await?;
+        let schema = results.schema();
+        let (tags, fields) = create_table::table_columns(&schema)?;
+        let batches: Vec<RecordBatch> = results
+            .try_collect()
             .await
-            .map_err(|_| QueryExecutorError::DatabaseNotFound {
-                db_name: database.to_string(),
-            })?
-            .ok_or_else(|| QueryExecutorError::DatabaseNotFound {
-                db_name: database.to_string(),
-            })?;
+            .map_err(QueryExecutorError::ExecuteStream)?;
+        let stats = stats.await;
+        // the results are written to a staging table that is only swapped in for an existing
+        // table once they have all been written, so that it is left as it is if they cannot be:
+        let now = self.time_provider.now();
+        let staging = format!("{}-staging-{}", create.table, now.timestamp_nanos());
+        let lines = batches
+            .iter()
+            .map(|batch| create_table::to_line_protocol(&staging, batch))
+            .collect::<Result<Vec<_>, _>>()?;
+
+        let write_error = |reason: String| QueryExecutorError::WriteResults {
+            table: create.table.clone(),
+            reason,
+        };
+        self.write_buffer
+            .create_table(database.to_string(), staging.clone(), tags, fields)
+            .await
+            .map_err(|e| write_error(e.to_string()))?;
+        let rows = match self.write_lines(database, &lines, now).await {
+            Ok(rows) => rows,
+            Err(e) => {
+                if let Err(error) = self
+                    .write_buffer
+                    .soft_delete_table(database.to_string(), staging.clone())
+                    .await
+                {
+                    warn!(%database, table = %staging, %error, "failed to delete staging table");
+                }
+                return Err(write_error(e));
+            }
+        };
+        if exists {
+            self.write_buffer
+                .soft_delete_table(database.to_string(), create.table.clone())
+                .await
+                .map_err(|e| write_error(e.to_string()))?;
+        }
+        self.write_buffer
+            .rename_table(database.to_string(), staging, create.table.clone())
+            .await
+            .map_err(|e| write_error(e.to_string()))?;
+        info!(%database, table = %create.table, rows, "created table from query results");
+        Ok(output(
+            rows,
+            ExecutionStats {
+                chunks: stats.chunks,
+                replay_in_progress: stats.replay_in_progress,
+                ..Default::default()
+            },
+        ))
+    }
+
+    /// Write the line protocol `lines` to the `database` at the given ingest time, outputting the
+    /// number of rows written
+    async fn write_lines(
+        &self,
+        database: &str,
+        lines: &[String],
+        ingest_time: Time,
+    ) -> Result<usize, String> {
+        let namespace = NamespaceName::new(database.to_string()).map_err(|e| e.to_string())?;
+        let mut rows = 0;
+        for lp in lines.iter().filter(|lp| !lp.is_empty()) {
+            let written = self
+                .write_buffer
+                .write_lp(
+                    namespace.clone(),
+                    lp,
+                    ingest_time,
+                    false,
+                    Precision::Nanosecond,
+                )
+                .await
+                .map_err(|e| e.to_string())?;
+            rows += written.line_count;
+        }
+        Ok(rows)
+    }
+
+    /// The checks made before a query is run, whichever of the query methods it is run through
+    ///
+    /// The query is rejected if the server is in maintenance mode, see
+    /// [`Self::set_maintenance_mode`], or if the WAL is being replayed and the [`ReplayPolicy`] is
+    /// to reject queries until it has finished.
+    fn start_query(&self, database: &str) -> Result<QueryStart, QueryExecutorError> {
+        let started = Instant::now();
+        let active = self.maintenance.start_query()?;
+        let replay_in_progress = self.write_buffer.replay_state().in_progress();
+        if replay_in_progress {
+            match self.replay_policy {
+                ReplayPolicy::Reject => return Err(QueryExecutorError::ReplayInProgress),
+                ReplayPolicy::Warn => {
+                    warn!(%database, "serving query during WAL replay, results may be incomplete")
+                }
+            }
+        }
+        Ok(QueryStart {
+            started,
+            active,
+            replay_in_progress,
+        })
+    }
+
+    /// The deadline of a query against `db` that started at `started`, see [`timeout`]
+    fn deadline(&self, started: Instant, db: &Database) -> Deadline {
+        Deadline::new(started, db.query_timeout().or(self.query_timeout))
+    }
+
+    /// Wait for a query that is run in the `ctx` to be admitted for execution, once the memory
+    /// pool of its executor is no longer under pressure, see [`memory::admit_query`], and a permit
+    /// from the query execution semaphore is available, unless it shares the given `permit` with
+    /// other queries. The query holds the permit until its results have been read, or dropped.
+    async fn admit_query(
+        &self,
+        ctx: &IOxSessionContext,
+        deadline: Deadline,
+        permit: Option<Arc<InstrumentedAsyncOwnedSemaphorePermit>>,
+    ) -> Result<Arc<InstrumentedAsyncOwnedSemaphorePermit>, QueryExecutorError> {
+        if let Some(high_water_mark) = self.mem_pool_high_water_mark {
+            let pool = Arc::clone(&ctx.inner().runtime_env().memory_pool);
+            deadline
+                .run(memory::admit_query(pool.as_ref(), high_water_mark))
+                .await??;
+        }
+        match permit {
+            Some(permit) => Ok(permit),
+            None => deadline
+                .run(self.acquire_semaphore(None))
+                .await
+                .map(Arc::new),
+        }
+    }
+
+    /// Acquire a permit from the query execution semaphore for several queries against the
+    /// `database` to share, see [`Self::admit_query`], so that they are admitted together, rather
+    /// than each waiting on those before it to be read
+    async fn shared_permit(
+        &self,
+        database: &str,
+    ) -> Result<Arc<InstrumentedAsyncOwnedSemaphorePermit>, QueryExecutorError> {
+        let start = self.start_query(database)?;
+        let db = self.database(database)?;
+        self.deadline(start.started, &db)
+            .run(self.acquire_semaphore(None))
+            .await
+            .map(Arc::new)
+    }
+
+    /// Run a query, see [`Self::query_with_stats`], which is admitted with the given `permit`, if
+    /// it shares one with other queries, see [`Self::admit_query`]
+    #[allow(clippy::too_many_arguments)]
+    async fn execute_query(
+        &self,
+        database: &str,
+        query: &str,
+        params: Option<StatementParams>,
+        kind: QueryKind,
+        options: QueryOptions,
+        span_ctx: Option<SpanContext>,
+        external_span_ctx: Option<RequestLogContext>,
+        permit: Option<Arc<InstrumentedAsyncOwnedSemaphorePermit>>,
+    ) -> Result<(SendableRecordBatchStream, ExecutionStatsFuture), QueryExecutorError> {
+        info!(
+            %database,
+            %query,
+            ?params,
+            ?kind,
+            ?options,
+            "QueryExecutorImpl as QueryExecutor::query"
+        );
+        let QueryStart {
+            started,
+            active,
+            replay_in_progress,
+        } = self.start_query(database)?;
+        validate_query(query, kind, params.as_ref())?;
+        let options = Arc::new(options);
+        let db = {
+            let _span_recorder = SpanRecorder::new(span_ctx.child_span("get database"));
+            self.database(database)?
+                .with_options(Arc::clone(&options))
+                .with_exec(Arc::clone(self.executor_for(options.priority)))
+                .with_max_time_range(
+                    self.max_query_time_range
+                        .filter(|_| !options.allow_unbounded_time_range),
+                )
+                .with_time_bucket_limit(
+                    self.max_time_buckets
+                        .filter(|_| kind.is_influxql())
+                        .and_then(|max| {
+                            durations::group_by_time_interval(query)
+                                .map(|interval| TimeBucketLimit { interval, max })
+                        }),
+                )
+                .with_read_ahead(self.read_ahead.clone())
+                .with_request_budget(options.max_storage_requests.or(self.max_storage_requests))
+                .with_max_plan_nodes(self.max_plan_nodes)
+                .with_aggregate_mem_pool_size(self.aggregate_mem_pool_size)
+        };
+        if kind.is_influxql() && options.influxql_strict_group_by {
+            group_by::check_group_by_tags(query, &db.db_schema)?;
+        }
+        let deadline = self.deadline(started, &db);
+        let expanded_query = match kind {
+            QueryKind::Sql => wildcards::expand_wildcard_exclusions(
+                query,
+                &db.db_schema,
+                &options.table_rewrites,
+            )?,
+            QueryKind::InfluxQl | QueryKind::InfluxQlV1Compat => None,
+        };
 
         let params = params.unwrap_or_default();
+        let key = CacheKey::new(database, kind, query, &params, &options);
+        let tags = options
+            .tags
+            .iter()
+            .map(|(k, v)| (k.clone(), v.clone()))
+            .collect::<BTreeMap<_, _>>();
+
+        // the generations of the tables have to be taken before they are scanned, so that writes
+        // made while the query runs invalidate its results, which are not cached if they may be
+        // incomplete, or if they expire with the retention period of the database:
+        let cache = self
+            .result_cache
+            .as_ref()
+            .filter(|_| {
+                options.cache_results && !replay_in_progress && db.retention_time_ns().is_none()
+            })
+            .map(|cache| {
+                let generations = cache.generations(db.db_schema.id);
+                (cache, key.clone(), generations)
+            });
+        if let Some(results) = cache.as_ref().and_then(|(cache, key, _)| cache.get(key)) {
+            let results = self.log_shared_results(
+                &db,
+                external_span_ctx.as_ref(),
+                kind,
+                query,
+                &params,
+                tags,
+                started,
+                ResultSource::Cache,
+                results,
+            );
+            let stats = ExecutionStats {
+                cache_hit: true,
+                ..Default::default()
+            };
+            let (results, stats) = ExecutionStatsStream::new(active.track(results), started, stats);
+            return Ok((Box::pin(results), stats));
+        }
+
+        // an identical query that is in flight is followed rather than executing this one, unless
+        // it fails before producing results, in which case this one is run to fail by itself:
+        let leader = match &self.in_flight {
+            Some(in_flight) => match in_flight.join(key) {
+                Joined::Leader(leader) => Some(leader),
+                Joined::Follower(follower) => match follower.results(in_flight).await {
+                    Some(results) => {
+                        let results = self.log_shared_results(
+                            &db,
+                            external_span_ctx.as_ref(),
+                            kind,
+                            query,
+                            &params,
+                            tags,
+                            started,
+                            ResultSource::Coalesced,
+                            results,
+                        );
+                        let stats = ExecutionStats {
+                            replay_in_progress,
+                            ..Default::default()
+                        };
+                        let (results, stats) =
+                            ExecutionStatsStream::new(active.track(results), started, stats);
+                        return Ok((Box::pin(results), stats));
+                    }
+                    None => None,
+                },
+            },
+            None => None,
+        };
 
-        let token = db.record_query(
+        let (query_id, token) = db.record_query_with_id(
             external_span_ctx.as_ref().map(RequestLogContext::ctx),
             kind.query_type(),
             Box::new(query.to_string()),
             params.clone(),
         );
+        if let Some(id) = query_id.as_deref().filter(|_| !tags.is_empty()) {
+            self.query_log_stats.set_tags(id, tags.clone());
+        }
+        let db = match &query_id {
+            Some(id) => db.with_progress(self.query_progress.register(id)),
+            None => db,
+        };
 
         // NOTE - we use the default query configuration on the IOxSessionContext here:
         let ctx = db.new_query_context(span_ctx, Default::default());
+        if options.approx_count_distinct && kind.is_influxql() {
+            ctx.inner()
+                .add_analyzer_rule(Arc::new(approx_distinct::ApproxCountDistinct));
+        }
         let planner = Planner::new(&ctx);
-        let query = query.to_string();
+        let query = expanded_query.unwrap_or_else(|| query.to_string());
 
         // Perform query planning on a separate threadpool than the IO runtime that is servicing
         // this request by using `IOxSessionContext::run`.
-        let plan = ctx
-            .run(async move {
-                match kind {
-                    QueryKind::Sql => planner.sql(query, params).await,
-                    QueryKind::InfluxQl => planner.influxql(query, params).await,
+        let planning_started = Instant::now();
+        let query_text = query.clone();
+        let plan = deadline
+            .run(planning::with_planning_timeout(
+                self.max_planning_time,
+                ctx.run(async move {
+                    match kind {
+                        QueryKind::Sql => planner.sql(query, params).await,
+                        QueryKind::InfluxQl | QueryKind::InfluxQlV1Compat => {
+                            planner.influxql(query, params).await
+                        }
+                    }
+                }),
+            ))
+            .await
+            .and_then(|planned| planned)
+            .and_then(|plan| plan.map_err(|e| self.planning_error(database, e)));
+        // results that were slow to plan are the last to be evicted from the result cache:
+        let slow_to_plan = planning::log_slow_planning(
+            self.slow_planning_threshold,
+            planning_started.elapsed(),
+            database,
+            &query_text,
+        );
+
+        let plan = match plan {
+            Ok(plan) => plan,
+            Err(e) => {
+                token.fail();
+                self.query_log_stats.complete(query_id.as_deref());
+                return Err(e);
+            }
+        };
+        if let Some(limit) = self
+            .cross_join_row_limit
+            .filter(|_| !options.allow_cross_joins)
+        {
+            if let Err(e) = joins::check_cross_joins(&plan, limit) {
+                token.fail();
+                self.query_log_stats.complete(query_id.as_deref());
+                return Err(e);
+            }
+        }
+        let plan = partial_aggregates::apply_partial_aggregates(plan, options.partial_aggregates)
+            .and_then(|plan| casts::apply_column_casts(plan, &options.column_casts))
+            .and_then(|plan| casts::apply_time_precision(plan, options.time_precision))
+            .and_then(|plan| match kind {
+                QueryKind::Sql => Ok(plan),
+                QueryKind::InfluxQl | QueryKind::InfluxQlV1Compat => {
+                    casts::apply_boolean_format(plan, options.influxql_boolean_format)
+                        .and_then(|plan| casts::apply_influxql_epoch(plan, options.influxql_epoch))
                 }
             })
-            .await;
-
-        let plan = match plan.map_err(QueryExecutorError::QueryPlanning) {
+            .and_then(|plan| {
+                output_columns::apply_duplicate_columns(plan, options.duplicate_columns)
+            })
+            .and_then(|plan| output_columns::apply_output_columns(plan, &options.output_columns))
+            .map(|plan| {
+                partition_order::apply_partition_order(plan, options.preserve_partition_order)
+            });
+        let plan = match plan {
             Ok(plan) => plan,
             Err(e) => {
                 token.fail();
+                self.query_log_stats.complete(query_id.as_deref());
                 return Err(e);
             }
         };
+        let plan = match memory::session_budget(&ctx) {
+            Some(limit) => memory::limit_aggregate_memory(plan, limit),
+            None => plan,
+        };
+        let plan = match self.max_query_cost {
+            Some(max) => {
+                let budget = Arc::new(ResourceBudget::new(max, self.query_cost_row_weight));
+                match resource_budget::track_plan(plan, &budget) {
+                    Ok(plan) => plan,
+                    Err(e) => {
+                        token.fail();
+                        self.query_log_stats.complete(query_id.as_deref());
+                        return Err(QueryExecutorError::QueryPlanning(e));
+                    }
+                }
+            }
+            None => plan,
+        };
         let token = token.planned(&ctx, Arc::clone(&plan));
 
-        // TODO: Enforce concurrency limit here
+        let permit = match self.admit_query(&ctx, deadline, permit).await {
+            Ok(permit) => permit,
+            Err(e) => {
+                token.fail();
+                self.query_log_stats.complete(query_id.as_deref());
+                return Err(e);
+            }
+        };
         let token = token.permit();
 
         self.telemetry_store.update_num_queries();
 
-        match ctx.execute_stream(Arc::clone(&plan)).await {
-            Ok(query_results) => {
-                token.success();
-                Ok(query_results)
+        let results = deadline
+            .run(retry::execute_with_retry(
+                plan,
+                self.max_transient_retries,
+                retry::TRANSIENT_ERROR_BACKOFF,
+                |plan| ctx.execute_stream(plan),
+            ))
+            .await
+            .and_then(|results| results.map_err(QueryExecutorError::ExecuteStream));
+        match results {
+            Ok((query_results, plan)) => {
+                // the query log entry is completed once the results have been read, and failed
+                // if they are dropped before then:
+                let mut results: SendableRecordBatchStream = Box::pin(
+                    StatsRecordingStream::new(
+                        deadline.limit_stream(query_results),
+                        plan,
+                        Arc::clone(&self.query_log_stats),
+                        query_id,
+                    )
+                    .with_token(token)
+                    .with_dictionary_stats(db.dictionary_stats.clone())
+                    .with_tags(tags, started),
+                );
+                if let Some((cache, key, generations)) = cache {
+                    if let Some(tables) = db.scanned_tables(&generations) {
+                        results = cache.cache_results(
+                            key,
+                            db.db_schema.id,
+                            tables,
+                            slow_to_plan,
+                            results,
+                        );
+                    }
+                }
+                if let Some(leader) = leader {
+                    results = leader.share(results);
+                }
+                let stats = ExecutionStats {
+                    chunks: db.scanned_chunks().iter().map(|(_, c)| c.len()).sum(),
+                    replay_in_progress,
+                    ..Default::default()
+                };
+                let (results, stats) = ExecutionStatsStream::new(
+                    active.track(hold_guard(results, &permit)),
+                    started,
+                    stats,
+                );
+                Ok((Box::pin(results), stats))
             }
             Err(err) => {
                 token.fail();
-                Err(QueryExecutorError::ExecuteStream(err))
+                self.query_log_stats.complete(query_id.as_deref());
+                Err(err)
             }
         }
     }
 
-    fn show_databases(
+    /// Record a query whose `results` were not executed for it, but were read from the given
+    /// `source`, in the query log, returning the results, which complete its entry once they
+    /// have been read
+    #[allow(clippy::too_many_arguments)]
+    fn log_shared_results(
         &self,
-        include_deleted: bool,
-    ) -> Result<SendableRecordBatchStream, QueryExecutorError> {
-        let mut databases = self.catalog.list_db_schema();
-        // sort them to ensure consistent order, first by deleted, then by name:
-        databases.sort_unstable_by(|a, b| match a.deleted.cmp(&b.deleted) {
-            Ordering::Equal => a.name.cmp(&b.name),
-            ordering => ordering,
-        });
-        if !include_deleted {
-            databases.retain(|db| !db.deleted);
-        }
-        let mut fields = Vec::with_capacity(2);
-        fields.push(Field::new("iox::database", DataType::Utf8, false));
-        let mut arrays = Vec::with_capacity(2);
-        let names: StringArray = databases
-            .iter()
-            .map(|db| db.name.as_ref())
-            .collect::<Vec<&str>>()
-            .into();
-        let names = Arc::new(names) as Arc<dyn Array>;
-        arrays.push(names);
-        if include_deleted {
-            fields.push(Field::new("deleted", DataType::Boolean, false));
-            let deleted: BooleanArray = databases
-                .iter()
-                .map(|db| db.deleted)
-                .collect::<Vec<bool>>()
-                .into();
-            let deleted = Arc::new(deleted);
-            arrays.push(deleted);
+        db: &Database,
+        external_span_ctx: Option<&RequestLogContext>,
+        kind: QueryKind,
+        query: &str,
+        params: &StatementParams,
+        tags: BTreeMap<String, String>,
+        started: Instant,
+        source: ResultSource,
+        results: SendableRecordBatchStream,
+    ) -> SendableRecordBatchStream {
+        let (query_id, token) = db.record_query_with_id(
+            external_span_ctx.map(RequestLogContext::ctx),
+            kind.query_type(),
+            Box::new(query.to_string()),
+            params.clone(),
+        );
+        if let Some(id) = query_id.as_deref() {
+            self.query_log_stats.set_result_source(id, source);
+            if !tags.is_empty() {
+                self.query_log_stats.set_tags(id, tags.clone());
+            }
         }
-        let schema = DatafusionSchema::new(fields);
-        let batch = RecordBatch::try_new(Arc::new(schema), arrays)
-            .map_err(QueryExecutorError::DatabasesToRecordBatch)?;
-        Ok(Box::pin(MemoryStream::new(vec![batch])))
+        // nothing is planned or executed for the query, which is logged with an empty plan:
+        let ctx = db.new_query_context(None, Default::default());
+        let plan: Arc<dyn ExecutionPlan> = Arc::new(EmptyExec::new(results.schema()));
+        let token = token.planned(&ctx, Arc::clone(&plan)).permit();
+        Box::pin(
+            StatsRecordingStream::new(results, plan, Arc::clone(&self.query_log_stats), query_id)
+                .with_token(token)
+                .with_tags(tags, started),
+        )
     }
 
-    async fn show_retention_policies(
+    async fn run_query_job(
         &self,
-        database: Option<&str>,
-        span_ctx: Option<SpanContext>,
-    ) -> Result<SendableRecordBatchStream, QueryExecutorError> {
-        let mut databases = if let Some(db) = database {
-            vec![db.to_owned()]
-        } else {
-            self.catalog.db_names()
-        };
-        // sort them to ensure consistent order:
-        databases.sort_unstable();
-
+        id: QueryJobId,
+        database: &str,
+        query: &str,
+        params: Option<StatementParams>,
+        kind: QueryKind,
+    ) -> Result<(), QueryExecutorError> {
+        let stream = self
+            .query(database, query, params, kind, None, None)
+            .await?;
+        self.query_jobs
+            .write_results(id, stream)
+            .await
+            .map_err(QueryExecutorError::QueryJobResults)
+    }
+
+    /// Get the status of a job submitted with [`Self::submit_query`]
+    pub fn job_status(&self, id: QueryJobId) -> Result<QueryJobStatus, QueryExecutorError> {
+        self.query_jobs
+            .status(id)
+            .ok_or_else(|| QueryExecutorError::QueryJobNotFound { id: id.to_string() })
+    }
+
+    /// Stream the results of a job submitted with [`Self::submit_query`]
+    ///
+    /// This will fail if the job has not finished, or if it failed.
+    pub async fn fetch_result(
+        &self,
+        id: QueryJobId,
+    ) -> Result<SendableRecordBatchStream, QueryExecutorError> {
+        match self.job_status(id)? {
+            QueryJobStatus::Done => {
+                let schema = self
+                    .query_jobs
+                    .result_schema(id)
+                    .ok_or_else(|| QueryExecutorError::QueryJobNotFound { id: id.to_string() })?;
+                Ok(self.query_jobs.read_results(id, schema))
+            }
+            status => Err(QueryExecutorError::QueryJobNotComplete {
+                id: id.to_string(),
+                status: status.to_string(),
+            }),
+        }
+    }
+
+    /// Query each of the given `tables` over the `time_range`, returning a separate stream of
+    /// results for each table, labeled with its name.
+    ///
+    /// Each table is queried as by [`QueryExecutor::query_with_options`], with the given
+    /// `options`, and is recorded in the query log in its own right. The queries hold a single
+    /// permit from the query execution semaphore until all of the returned streams have been
+    /// dropped.
+    pub async fn query_per_measurement(
+        &self,
+        database: &str,
+        tables: &[&str],
+        time_range: Range<Time>,
+        options: QueryOptions,
+    ) -> Result<Vec<(String, SendableRecordBatchStream)>, QueryExecutorError> {
+        let permit = self.shared_permit(database).await?;
+        let mut streams = Vec::with_capacity(tables.len());
+        for table in tables {
+            let query = format!(
+                "SELECT * FROM \"{table}\" \
+                WHERE time >= to_timestamp_nanos({start}) AND time < to_timestamp_nanos({end})",
+                table = table.replace('"', "\"\""),
+                start = time_range.start.timestamp_nanos(),
+                end = time_range.end.timestamp_nanos(),
+            );
+            let (stream, _) = self
+                .execute_query(
+                    database,
+                    &query,
+                    None,
+                    QueryKind::Sql,
+                    options.clone(),
+                    None,
+                    None,
+                    Some(Arc::clone(&permit)),
+                )
+                .await?;
+            streams.push((table.to_string(), stream));
+        }
+
+        Ok(streams)
+    }
+
+    /// Run a query, returning a separate stream of its results for each group of rows that have
+    /// the same values in the `group_keys` columns, labeled with those values, in the order that
+    /// the groups first appear in the results
+    ///
+    /// The results are read in full to be partitioned, so this is intended for queries whose
+    /// results fit in memory, e.g., for report generators that write each series to its own
+    /// output. Fails with [`QueryExecutorError::UnknownColumn`] if any of the `group_keys` are not
+    /// output by the query.
+    pub async fn query_grouped(
+        &self,
+        database: &str,
+        query: &str,
+        params: Option<StatementParams>,
+        kind: QueryKind,
+        group_keys: &[&str],
+    ) -> Result<Vec<(GroupKey, SendableRecordBatchStream)>, QueryExecutorError> {
+        let stream = self
+            .query(database, query, params, kind, None, None)
+            .await?;
+        let schema = stream.schema();
+        let batches: Vec<RecordBatch> = stream
+            .try_collect()
+            .await
+            .map_err(QueryExecutorError::ExecuteStream)?;
+        let groups = grouped::partition_by_group(&schema, &batches, group_keys)?;
+        Ok(groups
+            .into_iter()
+            .map(|(key, batches)| {
+                let stream: SendableRecordBatchStream =
+                    Box::pin(MemoryStream::new_with_schema(batches, Arc::clone(&schema)));
+                (key, stream)
+            })
+            .collect())
+    }
+
+    /// Run an InfluxQL query, returning its results split into a series for each distinct
+    /// measurement and set of `GROUP BY` tag values, in the order that the series first appear,
+    /// so that they map directly onto the series of the InfluxDB 1.x query API
+    ///
+    /// As with [`Self::query_grouped`], the results are read in full to be split.
+    pub async fn query_influxql_series(
+        &self,
+        database: &str,
+        query: &str,
+        params: Option<StatementParams>,
+    ) -> Result<Vec<InfluxQlSeries>, QueryExecutorError> {
+        let stream = self
+            .query(database, query, params, QueryKind::InfluxQl, None, None)
+            .await?;
+        let schema = stream.schema();
+        let batches: Vec<RecordBatch> = stream
+            .try_collect()
+            .await
+            .map_err(QueryExecutorError::ExecuteStream)?;
+        influxql_series::split_series(&schema, &batches)
+    }
+
+    /// Report the chunks that the `query` would scan, without executing it
+    ///
+    /// The query is planned, which selects the chunks of each table that it reads in the same
+    /// way as when it is run, and the chunks whose time range overlaps that of any scan of their
+    /// table are listed with their storage tier, time range, and size, ordered by table name.
+    /// This is intended as a debugging aid for the pruning of chunks by time, so no data is read.
+    pub async fn explain_chunks(
+        &self,
+        database: &str,
+        query: &str,
+        params: Option<StatementParams>,
+        kind: QueryKind,
+    ) -> Result<RecordBatch, QueryExecutorError> {
+        let start = self.start_query(database)?;
+        validate_query(query, kind, params.as_ref())?;
+        let scan_filters = Arc::new(ScanFilters::default());
+        let db = self
+            .database(database)?
+            .with_scan_filters(Arc::clone(&scan_filters));
+        let deadline = self.deadline(start.started, &db);
+        let ctx = db.new_query_context(None, Default::default());
+        let planner = Planner::new(&ctx);
+        let query = query.to_string();
+        let params = params.unwrap_or_default();
+        deadline
+            .run(planning::with_planning_timeout(
+                self.max_planning_time,
+                ctx.run(async move {
+                    match kind {
+                        QueryKind::Sql => planner.sql(query, params).await,
+                        QueryKind::InfluxQl | QueryKind::InfluxQlV1Compat => {
+                            planner.influxql(query, params).await
+                        }
+                    }
+                }),
+            ))
+            .await??
+            .map_err(|e| self.planning_error(database, e))?;
+
+        let tables = db.scanned_chunks();
+        let scan_filters = scan_filters.lock();
+        explain_chunks::summarize_chunks(&tables, &scan_filters)
+            .map_err(QueryExecutorError::ExecuteStream)
+    }
+
+    /// Report the `query` as it is run once the server has rewritten it, without executing it
+    ///
+    /// The query is reported as it is planned, i.e., once its wildcards with exclusions have been
+    /// expanded, in a row without a `table_name`. It is followed by a row for each scan of each
+    /// table that it reads, with the filters that are applied to the scan, which include the
+    /// filter on time that enforces the retention period of the database. This is intended to
+    /// explain why a query returns less data than its text selects.
+    pub async fn effective_query(
+        &self,
+        database: &str,
+        query: &str,
+        params: Option<StatementParams>,
+        kind: QueryKind,
+    ) -> Result<RecordBatch, QueryExecutorError> {
+        let start = self.start_query(database)?;
+        validate_query(query, kind, params.as_ref())?;
+        let scan_filters = Arc::new(ScanFilters::default());
+        let db = self
+            .database(database)?
+            .with_scan_filters(Arc::clone(&scan_filters));
+        let deadline = self.deadline(start.started, &db);
+        let query = match kind {
+            QueryKind::Sql => {
+                wildcards::expand_wildcard_exclusions(query, &db.db_schema, &Default::default())?
+            }
+            QueryKind::InfluxQl | QueryKind::InfluxQlV1Compat => None,
+        }
+        .unwrap_or_else(|| query.to_string());
+        let ctx = db.new_query_context(None, Default::default());
+        let planner = Planner::new(&ctx);
+        let planned_query = query.clone();
+        let params = params.unwrap_or_default();
+        deadline
+            .run(planning::with_planning_timeout(
+                self.max_planning_time,
+                ctx.run(async move {
+                    match kind {
+                        QueryKind::Sql => planner.sql(planned_query, params).await,
+                        QueryKind::InfluxQl | QueryKind::InfluxQlV1Compat => {
+                            planner.influxql(planned_query, params).await
+                        }
+                    }
+                }),
+            ))
+            .await??
+            .map_err(|e| self.planning_error(database, e))?;
+
+        let scan_filters = scan_filters.lock();
+        effective_query::summarize(&query, &scan_filters).map_err(QueryExecutorError::ExecuteStream)
+    }
+
+    /// Run each of the statements in the multi-statement `query` in order, returning a separate
+    /// stream of results for each statement.
+    ///
+    /// Each statement is run as by [`QueryExecutor::query_with_options`], with the given
+    /// `params` and `options`, and so is checked, planned, and recorded in the query log in the
+    /// same way as a query of a single statement. Like [`Self::query_per_measurement`], the
+    /// statements hold a single permit from the query execution semaphore until all of the
+    /// returned streams have been dropped.
+    ///
+    /// A statement that fails to plan or execute is reported in its position, without affecting
+    /// the statements around it, while a `query` that cannot be split into statements fails as
+    /// a whole.
+    pub async fn query_multi(
+        &self,
+        database: &str,
+        query: &str,
+        params: Option<StatementParams>,
+        kind: QueryKind,
+        options: QueryOptions,
+    ) -> Result<Vec<Result<SendableRecordBatchStream, QueryExecutorError>>, QueryExecutorError>
+    {
+        let statements = split_statements(query, kind)?;
+        let permit = self.shared_permit(database).await?;
+
+        let mut streams = Vec::with_capacity(statements.len());
+        for statement in statements {
+            let stream = self
+                .execute_query(
+                    database,
+                    &statement,
+                    params.clone(),
+                    kind,
+                    options.clone(),
+                    None,
+                    None,
+                    Some(Arc::clone(&permit)),
+                )
+                .await
+                .map(|(stream, _)| stream);
+            streams.push(stream);
+        }
+
+        Ok(streams)
+    }
+
+    /// Run the SQL `query` against a single persisted parquet file of the `table`, identified by
+    /// its id in `system.parquet_files`
+    ///
+    /// All other chunks of the `table`, whether persisted or in the buffer, are ignored, so that
+    /// the contents of the file can be inspected in isolation.
+    pub async fn query_file(
+        &self,
+        database: &str,
+        table: &str,
+        file_id: ParquetFileId,
+        query: &str,
+    ) -> Result<SendableRecordBatchStream, QueryExecutorError> {
+        info!(
+            %database,
+            %table,
+            file_id = file_id.as_u64(),
+            %query,
+            "QueryExecutorImpl::query_file"
+        );
+        let start = self.start_query(database)?;
+        validate_query(query, QueryKind::Sql, None)?;
+        let db = self.database(database)?;
+        let file_not_found = || QueryExecutorError::ParquetFileNotFound {
+            table: table.to_string(),
+            file_id: file_id.as_u64(),
+        };
+        let table_def = db
+            .db_schema
+            .table_definition(table)
+            .ok_or_else(file_not_found)?;
+        let file = self
+            .write_buffer
+            .parquet_files(db.db_schema.id, table_def.table_id)
+            .into_iter()
+            .find(|f| f.id == file_id)
+            .ok_or_else(file_not_found)?;
+        let chunk = parquet_chunk_from_file(
+            &file,
+            &table_def.schema,
+            self.persister.object_store_url().clone(),
+            self.persister.object_store(),
+            0,
+        );
+        let db = db.with_file_chunk(Arc::clone(&table_def.table_name), Arc::new(chunk));
+        let deadline = self.deadline(start.started, &db);
+
+        let (query_id, token) = db.record_query_with_id(
+            None,
+            QueryKind::Sql.query_type(),
+            Box::new(query.to_string()),
+            StatementParams::default(),
+        );
+        let ctx = db.new_query_context(None, Default::default());
+        let planner = Planner::new(&ctx);
+        let query = query.to_string();
+        let plan = deadline
+            .run(planning::with_planning_timeout(
+                self.max_planning_time,
+                ctx.run(async move { planner.sql(query, StatementParams::default()).await }),
+            ))
+            .await
+            .and_then(|planned| planned)
+            .and_then(|plan| plan.map_err(|e| self.planning_error(database, e)));
+        let plan = match plan {
+            Ok(plan) => plan,
+            Err(e) => {
+                token.fail();
+                self.query_log_stats.complete(query_id.as_deref());
+                return Err(e);
+            }
+        };
+        let token = token.planned(&ctx, Arc::clone(&plan));
+        let permit = match self.admit_query(&ctx, deadline, None).await {
+            Ok(permit) => permit,
+            Err(e) => {
+                token.fail();
+                self.query_log_stats.complete(query_id.as_deref());
+                return Err(e);
+            }
+        };
+        let token = token.permit();
+        self.telemetry_store.update_num_queries();
+
+        let stream = deadline
+            .run(ctx.execute_stream(Arc::clone(&plan)))
+            .await
+            .and_then(|stream| stream.map_err(QueryExecutorError::ExecuteStream));
+        match stream {
+            Ok(stream) => {
+                token.success();
+                let stream = Box::pin(StatsRecordingStream::new(
+                    deadline.limit_stream(stream),
+                    plan,
+                    Arc::clone(&self.query_log_stats),
+                    query_id,
+                ));
+                Ok(start.active.track(hold_guard(stream, &permit)))
+            }
+            Err(err) => {
+                token.fail();
+                self.query_log_stats.complete(query_id.as_deref());
+                Err(err)
+            }
+        }
+    }
+
+    /// Subscribe to the query log, receiving the state of each query's entry as the query
+    /// completes
+    ///
+    /// A subscriber that falls too far behind has its subscription ended, rather than holding on
+    /// to completed queries on its behalf.
+    pub fn subscribe_query_log(
+        &self,
+    ) -> impl Stream<Item = Arc<QueryLogEntryState>> + Send + 'static {
+        let receiver = self.query_log_stats.subscribe();
+        let query_log = Arc::clone(&self.query_log);
+        futures::stream::unfold(
+            (receiver, query_log),
+            |(mut receiver, query_log)| async move {
+                loop {
+                    // a lagging subscriber receives an error, which ends the subscription:
+                    let id = receiver.recv().await.ok()?;
+                    let entry = query_log
+                        .entries()
+                        .entries
+                        .iter()
+                        .rev()
+                        .map(|e| e.state())
+                        .find(|state| state.id.to_string() == id);
+                    // the entry may already have been evicted from the query log:
+                    if let Some(entry) = entry {
+                        return Some((entry, (receiver, query_log)));
+                    }
+                }
+            },
+        )
+    }
+
+    /// Capture the queries in the query log that succeeded to the file at `path`, so that they
+    /// can be replayed later on with [`Self::replay_workload`], returning how many were captured
+    pub async fn capture_workload(&self, path: impl AsRef<Path>) -> Result<usize, WorkloadError> {
+        let queries = self
+            .query_log
+            .entries()
+            .entries
+            .iter()
+            .map(|e| e.state())
+            .filter(|state| state.success)
+            .map(|state| WorkloadQuery::from(state.as_ref()))
+            .collect::<Vec<_>>();
+        workload::write(path, &queries).await?;
+        Ok(queries.len())
+    }
+
+    /// Run each of the queries captured to the file at `path` by [`Self::capture_workload`] in
+    /// turn, reporting how long each took
+    ///
+    /// A query that fails is reported along with the others, rather than ending the replay.
+    pub async fn replay_workload(
+        &self,
+        path: impl AsRef<Path>,
+    ) -> Result<Vec<ReplayedQuery>, WorkloadError> {
+        let mut replayed = Vec::new();
+        for query in workload::read(path).await? {
+            let kind = query.kind()?;
+            let start = Instant::now();
+            let result = match self
+                .query(
+                    &query.database,
+                    &query.query,
+                    Some(query.params.clone()),
+                    kind,
+                    None,
+                    None,
+                )
+                .await
+            {
+                Ok(stream) => stream
+                    .try_fold(0, |rows, batch| async move { Ok(rows + batch.num_rows()) })
+                    .await
+                    .map_err(QueryExecutorError::ExecuteStream),
+                Err(e) => Err(e),
+            };
+            replayed.push(ReplayedQuery {
+                query,
+                latency: start.elapsed(),
+                result,
+            });
+        }
+        Ok(replayed)
+    }
+
+    /// The cost of the queries made with each of the [`QueryOptions::tags`], ordered by tag key
+    /// and value
+    ///
+    /// A query's cost is added once its results have been read to completion.
+    pub fn query_costs_by_tag(&self) -> Vec<QueryTagCost> {
+        self.query_log_stats.tag_costs()
+    }
+
+    /// The estimated completion percentage, between 0 and 100, of the running query with the
+    /// given query log entry `id`, or `None` if no such query is running
+    ///
+    /// The estimate is based on the number of rows scanned so far, out of the number of rows in
+    /// the chunks being scanned.
+    pub fn query_progress(&self, id: &str) -> Option<f64> {
+        self.query_progress.get(id).map(|p| p.percentage())
+    }
+
+    /// Enable or disable maintenance mode, in which new queries are rejected with
+    /// [`QueryExecutorError::Unavailable`], while queries that have already started continue
+    pub fn set_maintenance_mode(&self, enabled: bool) {
+        info!(enabled, "setting query executor maintenance mode");
+        self.maintenance.set_enabled(enabled);
+    }
+
+    /// Wait for all active queries to finish, i.e., for their output streams to be dropped
+    ///
+    /// This is intended to be used along with [`Self::set_maintenance_mode`], otherwise new
+    /// queries may keep it from completing.
+    pub async fn drain(&self) {
+        self.maintenance.drain().await
+    }
+
+    /// The executor that queries with the given `priority` are planned and run on
+    fn executor_for(&self, priority: QueryPriority) -> &Arc<Executor> {
+        match (priority, &self.batch_exec) {
+            (QueryPriority::Batch, Some(batch_exec)) => batch_exec,
+            _ => &self.exec,
+        }
+    }
+
+    /// Convert an error produced while planning a query into a [`QueryExecutorError`], providing
+    /// suggestions for references to columns that do not exist
+    ///
+    /// The checks made while planning, e.g., by the table providers and analyzer rules, fail with
+    /// a [`QueryExecutorError`] wrapped in [`DataFusionError::External`], which is returned as it
+    /// is.
+    fn planning_error(&self, database: &str, error: DataFusionError) -> QueryExecutorError {
+        let error = match into_query_executor_error(error) {
+            Ok(error) => return error,
+            Err(error) => error,
+        };
+        match self
+            .catalog
+            .db_schema(database)
+            .and_then(|db_schema| suggestions::unknown_column(&error, &db_schema))
+        {
+            Some((name, suggestions)) => QueryExecutorError::UnknownColumn { name, suggestions },
+            None => QueryExecutorError::QueryPlanning(error),
+        }
+    }
+}
+
+#[async_trait]
+impl QueryExecutor for QueryExecutorImpl {
+    async fn query_with_options(
+        &self,
+        database: &str,
+        query: &str,
+        params: Option<StatementParams>,
+        kind: QueryKind,
+        options: QueryOptions,
+        span_ctx: Option<SpanContext>,
+        external_span_ctx: Option<RequestLogContext>,
+    ) -> Result<SendableRecordBatchStream, QueryExecutorError> {
+        self.query_with_stats(
+            database,
+            query,
+            params,
+            kind,
+            options,
+            span_ctx,
+            external_span_ctx,
+        )
+        .await
+        .map(|(results, _)| results)
+    }
+
+    fn show_databases(
+        &self,
+        include_deleted: bool,
+        include_stats: bool,
+    ) -> Result<SendableRecordBatchStream, QueryExecutorError> {
+        let mut databases = self.catalog.list_db_schema();
+        // sort them to ensure consistent order, first by deleted, then by name:
+        databases.sort_unstable_by(|a, b| match a.deleted.cmp(&b.deleted) {
+            Ordering::Equal => a.name.cmp(&b.name),
+            ordering => ordering,
+        });
+        if !include_deleted {
+            databases.retain(|db| !db.deleted);
+        }
+        let mut fields = Vec::with_capacity(2);
+        fields.push(Field::new(
+            self.column_names.database(),
+            DataType::Utf8,
+            false,
+        ));
+        let mut arrays = Vec::with_capacity(2);
+        let names: StringArray = databases
+            .iter()
+            .map(|db| db.name.as_ref())
+            .collect::<Vec<&str>>()
+            .into();
+        let names = Arc::new(names) as Arc<dyn Array>;
+        arrays.push(names);
+        if include_deleted {
+            fields.push(Field::new("deleted", DataType::Boolean, false));
+            let deleted: BooleanArray = databases
+                .iter()
+                .map(|db| db.deleted)
+                .collect::<Vec<bool>>()
+                .into();
+            let deleted = Arc::new(deleted);
+            arrays.push(deleted);
+        }
+        if include_stats {
+            // the rows and bytes are taken from the totals of the persisted files kept by the
+            // write buffer, rather than by listing the files, so data that is only buffered in
+            // memory is not counted:
+            let (row_counts, sizes): (Vec<u64>, Vec<u64>) = databases
+                .iter()
+                .map(|db| {
+                    self.write_buffer.table_summaries(db.id).into_iter().fold(
+                        (0, 0),
+                        |(rows, bytes), (_, summary)| {
+                            (rows + summary.row_count, bytes + summary.size_bytes)
+                        },
+                    )
+                })
+                .unzip();
+            fields.push(Field::new("table_count", DataType::UInt64, false));
+            arrays.push(Arc::new(UInt64Array::from_iter_values(
+                databases.iter().map(|db| db.table_count() as u64),
+            )));
+            fields.push(Field::new("total_row_count", DataType::UInt64, false));
+            arrays.push(Arc::new(UInt64Array::from(row_counts)));
+            fields.push(Field::new("estimated_size_bytes", DataType::UInt64, false));
+            arrays.push(Arc::new(UInt64Array::from(sizes)));
+        }
+        let schema = DatafusionSchema::new(fields);
+        let batch = RecordBatch::try_new(Arc::new(schema), arrays)
+            .map_err(QueryExecutorError::DatabasesToRecordBatch)?;
+        Ok(Box::pin(MemoryStream::new(vec![batch])))
+    }
+
+    async fn show_retention_policies(
+        &self,
+        database: Option<&str>,
+        span_ctx: Option<SpanContext>,
+    ) -> Result<SendableRecordBatchStream, QueryExecutorError> {
+        let mut databases = if let Some(db) = database {
+            vec![db.to_owned()]
+        } else {
+            self.catalog.db_names()
+        };
+        // sort them to ensure consistent order:
+        databases.sort_unstable();
+
+        let show_all = database.is_none();
         let mut rows = Vec::with_capacity(databases.len());
         for database in databases {
-            let db = self
+            let db = match self
                 .namespace(&database, span_ctx.child_span("get database"), false)
                 .await
-                .map_err(|_| QueryExecutorError::DatabaseNotFound {
-                    db_name: database.to_string(),
-                })?
-                .ok_or_else(|| QueryExecutorError::DatabaseNotFound {
-                    db_name: database.to_string(),
-                })?;
-            let duration = db.retention_time_ns();
-            let (db_name, rp_name) = split_database_name(&database);
-            rows.push(RetentionPolicyRow {
-                database: db_name,
-                name: rp_name,
-                duration,
-            });
+            {
+                Ok(Some(db)) => db,
+                // a database deleted since the names were listed is left out of the listing of
+                // all databases, rather than failing it:
+                Ok(None) | Err(_) if show_all => {
+                    debug!(%database, "database deleted while showing retention policies");
+                    continue;
+                }
+                Ok(None) | Err(_) => {
+                    return Err(QueryExecutorError::DatabaseNotFound {
+                        db_name: database.to_string(),
+                    })
+                }
+            };
+            // invalid durations are listed as they are configured, along with why they are
+            // invalid:
+            let duration = db.db_schema.retention_period_ns;
+            let (db_name, rp_name) = split_database_name(&database, &self.default_retention_policy);
+            if db.db_schema.retention_policies.is_empty() {
+                rows.push(RetentionPolicyRow {
+                    database: db_name,
+                    name: rp_name,
+                    duration,
+                    default: true,
+                    error: retention::invalid_period(duration),
+                });
+                continue;
+            }
+            rows.extend(
+                db.db_schema
+                    .retention_policies
+                    .iter()
+                    .map(|policy| RetentionPolicyRow {
+                        database: db_name.clone(),
+                        name: policy.name.to_string(),
+                        duration: policy.duration_ns,
+                        default: policy.default,
+                        error: retention::invalid_period(policy.duration_ns),
+                    }),
+            );
         }
+        rows.sort_unstable_by(|a, b| (&a.database, &a.name).cmp(&(&b.database, &b.name)));
 
-        let batch = retention_policy_rows_to_batch(&rows);
+        let batch = retention_policy_rows_to_batch(&rows, self.column_names);
         Ok(Box::pin(MemoryStream::new(vec![batch])))
     }
 
@@ -276,6 +1935,9 @@ struct RetentionPolicyRow {
     database: String,
     name: String,
     duration: Option<i64>,
+    default: bool,
+    /// Why the duration is invalid, if it is
+    error: Option<String>,
 }
 
 #[derive(Debug, Default)]
@@ -283,6 +1945,8 @@ struct RetentionPolicyRowBuilder {
     database: StringBuilder,
     name: StringBuilder,
     duration: Int64Builder,
+    default: BooleanBuilder,
+    error: StringBuilder,
 }
 
 impl RetentionPolicyRowBuilder {
@@ -290,14 +1954,16 @@ impl RetentionPolicyRowBuilder {
         self.database.append_value(row.database.as_str());
         self.name.append_value(row.name.as_str());
         self.duration.append_option(row.duration);
+        self.default.append_value(row.default);
+        self.error.append_option(row.error.as_deref());
     }
 
     // Note: may be able to use something simpler than StructArray here, this is just based
     // directly on the arrow docs: https://docs.rs/arrow/latest/arrow/array/builder/index.html
-    fn finish(&mut self) -> StructArray {
+    fn finish(&mut self, column_names: ColumnNames) -> StructArray {
         StructArray::from(vec![
             (
-                Arc::new(Field::new("iox::database", DataType::Utf8, false)),
+                Arc::new(Field::new(column_names.database(), DataType::Utf8, false)),
                 Arc::new(self.database.finish()) as ArrayRef,
             ),
             (
@@ -308,6 +1974,14 @@ impl RetentionPolicyRowBuilder {
                 Arc::new(Field::new("duration", DataType::Int64, true)),
                 Arc::new(self.duration.finish()) as ArrayRef,
             ),
+            (
+                Arc::new(Field::new("default", DataType::Boolean, false)),
+                Arc::new(self.default.finish()) as ArrayRef,
+            ),
+            (
+                Arc::new(Field::new("error", DataType::Utf8, true)),
+                Arc::new(self.error.finish()) as ArrayRef,
+            ),
         ])
     }
 }
@@ -318,22 +1992,126 @@ impl<'a> Extend<&'a RetentionPolicyRow> for RetentionPolicyRowBuilder {
     }
 }
 
-fn retention_policy_rows_to_batch(rows: &[RetentionPolicyRow]) -> RecordBatch {
+fn retention_policy_rows_to_batch(
+    rows: &[RetentionPolicyRow],
+    column_names: ColumnNames,
+) -> RecordBatch {
     let mut builder = RetentionPolicyRowBuilder::default();
     builder.extend(rows);
-    RecordBatch::from(&builder.finish())
+    RecordBatch::from(&builder.finish(column_names))
 }
 
-const AUTOGEN_RETENTION_POLICY: &str = "autogen";
+/// The default name of the retention policy of databases whose name does not include one
+pub const AUTOGEN_RETENTION_POLICY: &str = "autogen";
 
-fn split_database_name(db_name: &str) -> (String, String) {
+fn split_database_name(db_name: &str, default_retention_policy: &str) -> (String, String) {
     let mut split = db_name.split('/');
     (
         split.next().unwrap().to_owned(),
-        split.next().unwrap_or(AUTOGEN_RETENTION_POLICY).to_owned(),
+        split.next().unwrap_or(default_retention_policy).to_owned(),
     )
 }
 
+/// Merge the DataFusion config options set for a database over those of the server, where the
+/// options of the database take precedence
+fn merge_datafusion_config(
+    server: &Arc<HashMap<String, String>>,
+    database: &BTreeMap<String, String>,
+) -> Arc<HashMap<String, String>> {
+    if database.is_empty() {
+        return Arc::clone(server);
+    }
+    let mut config = server.as_ref().clone();
+    config.extend(
+        database
+            .iter()
+            .map(|(key, value)| (key.clone(), value.clone())),
+    );
+    Arc::new(config)
+}
+
+/// Split a multi-statement `query` into its top-level statements
+fn split_statements(query: &str, kind: QueryKind) -> Result<Vec<String>, QueryExecutorError> {
+    match kind {
+        QueryKind::Sql => DFParser::parse_sql(query)
+            .map(|statements| statements.iter().map(ToString::to_string).collect())
+            .map_err(|e| QueryExecutorError::QueryPlanning(e.into())),
+        QueryKind::InfluxQl | QueryKind::InfluxQlV1Compat => {
+            iox_query_influxql_rewrite::parse_statements(query)
+                .map(|statements| {
+                    statements
+                        .into_iter()
+                        .map(|s| s.to_statement().to_string())
+                        .collect()
+                })
+                .map_err(|e| {
+                    QueryExecutorError::QueryPlanning(DataFusionError::External(Box::new(e)))
+                })
+        }
+    }
+}
+
+/// A query that has been started through any of the query methods, see
+/// [`QueryExecutorImpl::start_query`]
+struct QueryStart {
+    started: Instant,
+    /// The query is active, see [`QueryExecutorImpl::drain`], until this is dropped
+    active: ActiveQuery,
+    /// Whether the query is served while the WAL is being replayed, see [`ReplayPolicy::Warn`]
+    replay_in_progress: bool,
+}
+
+/// Check the `query` for the errors that are reported before it is planned, whichever of the
+/// query methods it is run through, i.e., malformed InfluxQL durations, and placeholders that are
+/// not given a valid value by the `params`
+fn validate_query(
+    query: &str,
+    kind: QueryKind,
+    params: Option<&StatementParams>,
+) -> Result<(), QueryExecutorError> {
+    if kind.is_influxql() {
+        durations::validate_influxql_durations(query)?;
+    }
+    params::validate_params(query, params)
+}
+
+/// Take the [`QueryExecutorError`] that the `error` wraps in [`DataFusionError::External`], within
+/// any context that DataFusion added to it, or return the `error` as it was if it wraps none
+fn into_query_executor_error(
+    error: DataFusionError,
+) -> Result<QueryExecutorError, DataFusionError> {
+    match error {
+        DataFusionError::External(e) => match e.downcast::<QueryExecutorError>() {
+            Ok(e) => Ok(*e),
+            Err(e) => match e.downcast::<DataFusionError>() {
+                Ok(e) => into_query_executor_error(*e)
+                    .map_err(|e| DataFusionError::External(Box::new(e))),
+                Err(e) => Err(DataFusionError::External(e)),
+            },
+        },
+        DataFusionError::Context(context, e) => into_query_executor_error(*e)
+            .map_err(|e| DataFusionError::Context(context, Box::new(e))),
+        error => Err(error),
+    }
+}
+
+/// Hold a reference to the `guard`, e.g., a permit from the query execution semaphore, for as
+/// long as the `stream` is alive, so that a guard shared by several streams is released once they
+/// have all been dropped
+fn hold_guard<T: Send + Sync + 'static>(
+    stream: SendableRecordBatchStream,
+    guard: &Arc<T>,
+) -> SendableRecordBatchStream {
+    let schema = stream.schema();
+    let guard = Arc::clone(guard);
+    Box::pin(RecordBatchStreamAdapter::new(
+        schema,
+        stream.inspect(move |_| {
+            let _ = &guard;
+        }),
+    ))
+}
+
 // This implementation is for the Flight service
 #[async_trait]
 impl QueryDatabase for QueryExecutorImpl {
@@ -346,19 +2124,10 @@ impl QueryDatabase for QueryExecutorImpl {
     ) -> Result<Option<Arc<dyn QueryNamespace>>, DataFusionError> {
         let _span_recorder = SpanRecorder::new(span);
 
-        let db_schema = self.catalog.db_schema(name).ok_or_else(|| {
-            DataFusionError::External(Box::new(QueryExecutorError::DatabaseNotFound {
-                db_name: name.into(),
-            }))
-        })?;
-        Ok(Some(Arc::new(Database::new(
-            db_schema,
-            Arc::clone(&self.write_buffer),
-            Arc::clone(&self.exec),
-            Arc::clone(&self.datafusion_config),
-            Arc::clone(&self.query_log),
-            Arc::clone(&self.sys_events_store),
-        ))))
+        let db = self
+            .database(name)
+            .map_err(|e| DataFusionError::External(Box::new(e)))?;
+        Ok(Some(Arc::new(db)))
     }
 
     async fn acquire_semaphore(&self, span: Option<Span>) -> InstrumentedAsyncOwnedSemaphorePermit {
@@ -373,6 +2142,14 @@ impl QueryDatabase for QueryExecutorImpl {
     }
 }
 
+/// The default cost of each row output by each operator of a query, relative to each byte that it
+/// scans, see [`CreateQueryExecutorArgs::query_cost_row_weight`]
+pub const DEFAULT_QUERY_COST_ROW_WEIGHT: u64 = 64;
+
+/// The name under which the HyperLogLog based `approx_distinct` aggregate is also available, as
+/// a cheaper alternative to `COUNT(DISTINCT ...)` on high cardinality columns
+pub const APPROX_COUNT_DISTINCT_UDAF_NAME: &str = "approx_count_distinct";
+
 #[derive(Debug, Clone)]
 pub struct Database {
     db_schema: Arc<DatabaseSchema>,
@@ -380,22 +2157,77 @@ pub struct Database {
     exec: Arc<Executor>,
     datafusion_config: Arc<HashMap<String, String>>,
     query_log: Arc<QueryLog>,
+    unlogged_query_log: Arc<QueryLog>,
+    query_log_stats: Arc<QueryLogStats>,
     system_schema_provider: Arc<SystemSchemaProvider>,
+    options: Arc<QueryOptions>,
+    dictionary_stats: Option<Arc<DictionaryStatsCollector>>,
+    /// A single chunk that replaces all other chunks of the named table, see
+    /// [`QueryExecutorImpl::query_file`]
+    file_chunk: Option<(Arc<str>, Arc<dyn QueryChunk>)>,
+    /// The chunks of each table scanned by the query, see [`QueryTable::chunks`]
+    chunk_snapshots: Arc<ChunkSnapshots>,
+    progress: Option<Arc<ScanProgress>>,
+    max_time_range: Option<Duration>,
+    time_bucket_limit: Option<TimeBucketLimit>,
+    /// The parquet cache listed by the [`PARQUET_CACHE_UDTF_NAME`] table function
+    parquet_cache: Option<Arc<dyn ParquetCacheOracle>>,
+    read_ahead: Option<ReadAhead>,
+    request_budget: Option<Arc<RequestBudget>>,
+    max_plan_nodes: Option<usize>,
+    /// The default memory budget for the aggregate operators of each query, see
+    /// [`memory::limit_aggregate_memory`]
+    aggregate_mem_pool_size: Option<usize>,
+    /// Records the filters of each scan, see [`QueryExecutorImpl::explain_chunks`]
+    scan_filters: Option<Arc<ScanFilters>>,
+    /// Holds the results of queries issued a [`ResultTicket`], see [`QUERY_RESULT_UDTF_NAME`]
+    query_jobs: Arc<QueryJobs>,
+    /// Set if the query references any system tables, see [`Self::scanned_tables`]
+    system_tables_used: Arc<AtomicBool>,
 }
 
-impl Database {
-    pub fn new(
-        db_schema: Arc<DatabaseSchema>,
-        write_buffer: Arc<dyn WriteBuffer>,
+/// A scan of a table made by a query, see [`QueryTable::chunks`]
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+struct ScanKey {
+    table_name: Arc<str>,
+    filters: Vec<Expr>,
+    projection: Option<Vec<usize>>,
+}
+
+/// The chunks of each table scan made by a query
+type ChunkSnapshots = Mutex<HashMap<ScanKey, Vec<Arc<dyn QueryChunk>>>>;
+
+/// Whether two chunks scanned by a query hold the same data, i.e., read the same parquet file,
+/// or the same partition of the write buffer
+fn same_chunk(a: &dyn QueryChunk, b: &dyn QueryChunk) -> bool {
+    match (
+        a.as_any().downcast_ref::<ParquetChunk>(),
+        b.as_any().downcast_ref::<ParquetChunk>(),
+    ) {
+        (Some(a), Some(b)) => a.file_id() == b.file_id(),
+        (None, None) => a.partition_id() == b.partition_id(),
+        _ => false,
+    }
+}
+
+impl Database {
+    #[allow(clippy::too_many_arguments)]
+    pub(crate) fn new(
+        db_schema: Arc<DatabaseSchema>,
+        write_buffer: Arc<dyn WriteBuffer>,
         exec: Arc<Executor>,
         datafusion_config: Arc<HashMap<String, String>>,
         query_log: Arc<QueryLog>,
+        unlogged_query_log: Arc<QueryLog>,
+        query_log_stats: Arc<QueryLogStats>,
         sys_events_store: Arc<SysEventStore>,
+        query_jobs: Arc<QueryJobs>,
     ) -> Self {
         let system_schema_provider = Arc::new(SystemSchemaProvider::AllSystemSchemaTables(
             AllSystemSchemaTablesProvider::new(
                 Arc::clone(&db_schema),
                 Arc::clone(&query_log),
+                Arc::clone(&query_log_stats),
                 Arc::clone(&write_buffer),
                 Arc::clone(&sys_events_store),
             ),
@@ -406,8 +2238,148 @@ impl Database {
             exec,
             datafusion_config,
             query_log,
+            unlogged_query_log,
+            query_log_stats,
             system_schema_provider,
+            options: Default::default(),
+            dictionary_stats: None,
+            file_chunk: None,
+            chunk_snapshots: Default::default(),
+            progress: None,
+            max_time_range: None,
+            time_bucket_limit: None,
+            parquet_cache: None,
+            read_ahead: None,
+            request_budget: None,
+            max_plan_nodes: None,
+            aggregate_mem_pool_size: None,
+            scan_filters: None,
+            query_jobs,
+            system_tables_used: Default::default(),
+        }
+    }
+
+    /// Apply the given [`QueryOptions`] to queries made against this database
+    fn with_options(mut self, options: Arc<QueryOptions>) -> Self {
+        self.dictionary_stats = options
+            .dictionary_stats
+            .then(|| Arc::new(DictionaryStatsCollector::default()));
+        self.options = options;
+        self
+    }
+
+    /// Plan and run queries against this database on the given executor
+    fn with_exec(mut self, exec: Arc<Executor>) -> Self {
+        self.exec = exec;
+        self
+    }
+
+    /// Count the rows scanned by queries against this database toward the `progress`
+    fn with_progress(mut self, progress: Arc<ScanProgress>) -> Self {
+        self.progress = Some(progress);
+        self
+    }
+
+    /// Fail queries against this database whose condition on time spans more than `max`, see
+    /// [`time_range::check_time_range`]
+    fn with_max_time_range(mut self, max: Option<Duration>) -> Self {
+        self.max_time_range = max;
+        self
+    }
+
+    /// Fail queries against this database whose `GROUP BY time()` would produce too many buckets,
+    /// see [`time_range::check_time_buckets`]
+    fn with_time_bucket_limit(mut self, limit: Option<TimeBucketLimit>) -> Self {
+        self.time_bucket_limit = limit;
+        self
+    }
+
+    /// List the contents of the `parquet_cache` through the [`PARQUET_CACHE_UDTF_NAME`] table
+    /// function in queries against this database
+    fn with_parquet_cache(mut self, parquet_cache: Option<Arc<dyn ParquetCacheOracle>>) -> Self {
+        self.parquet_cache = parquet_cache;
+        self
+    }
+
+    /// Prefetch the parquet files scanned by queries against this database, see [`ReadAhead`]
+    fn with_read_ahead(mut self, read_ahead: Option<ReadAhead>) -> Self {
+        self.read_ahead = read_ahead;
+        self
+    }
+
+    /// Fail queries against this database that would make more than `max` GET requests to the
+    /// object store, see [`RequestBudget`]
+    ///
+    /// Each database is made for a single query, so the budget is shared by all of its scans.
+    fn with_request_budget(mut self, max: Option<usize>) -> Self {
+        self.request_budget = max.map(|max| Arc::new(RequestBudget::new(max)));
+        self
+    }
+
+    /// The query timeout given by the DataFusion config of this database, if any, see
+    /// [`QUERY_TIMEOUT_CONFIG_KEY`]
+    fn query_timeout(&self) -> Option<Duration> {
+        timeout::config_timeout(&self.datafusion_config)
+    }
+
+    /// Fail queries against this database whose logical plans have more than `max` nodes, see
+    /// [`plan_complexity::PlanComplexityLimit`]
+    fn with_max_plan_nodes(mut self, max: Option<usize>) -> Self {
+        self.max_plan_nodes = max;
+        self
+    }
+
+    /// Limit the memory used by the aggregate operators of each query against this database to
+    /// `limit` bytes, unless its DataFusion config gives another budget, see
+    /// [`AGGREGATE_MEM_POOL_SIZE_CONFIG_KEY`]
+    fn with_aggregate_mem_pool_size(mut self, limit: Option<usize>) -> Self {
+        self.aggregate_mem_pool_size = limit;
+        self
+    }
+
+    /// Record the filters of the scans made by queries against this database in `scan_filters`
+    fn with_scan_filters(mut self, scan_filters: Arc<ScanFilters>) -> Self {
+        self.scan_filters = Some(scan_filters);
+        self
+    }
+
+    /// Only scan the given `chunk` when querying the table named `table_name`
+    fn with_file_chunk(mut self, table_name: Arc<str>, chunk: Arc<dyn QueryChunk>) -> Self {
+        self.file_chunk = Some((table_name, chunk));
+        self
+    }
+
+    /// The tables scanned by the query made against this database, along with their generation
+    /// in `generations`, or `None` if the query's results cannot be cached, i.e., because it does
+    /// not scan any tables, or also references system tables, which are not written to
+    fn scanned_tables(&self, generations: &HashMap<TableId, u64>) -> Option<TableGenerations> {
+        if self.system_tables_used.load(AtomicOrdering::Relaxed) {
+            return None;
+        }
+        let tables = self
+            .scanned_chunks()
+            .into_iter()
+            .filter_map(|(name, _)| self.db_schema.table_name_to_id(name))
+            .map(|id| (id, generations.get(&id).copied().unwrap_or_default()))
+            .collect::<Vec<_>>();
+        (!tables.is_empty()).then_some(tables)
+    }
+
+    /// The chunks of each table scanned by the query made against this database, ordered by
+    /// table name
+    ///
+    /// A table scanned more than once lists each chunk read by any of its scans once.
+    fn scanned_chunks(&self) -> Vec<(Arc<str>, Vec<Arc<dyn QueryChunk>>)> {
+        let mut tables: BTreeMap<Arc<str>, Vec<Arc<dyn QueryChunk>>> = BTreeMap::new();
+        for (scan, chunks) in self.chunk_snapshots.lock().iter() {
+            let table = tables.entry(Arc::clone(&scan.table_name)).or_default();
+            for chunk in chunks {
+                if !table.iter().any(|c| same_chunk(c.as_ref(), chunk.as_ref())) {
+                    table.push(Arc::clone(chunk));
+                }
+            }
         }
+        tables.into_iter().collect()
     }
 
     fn from_namespace(db: &Self) -> Self {
@@ -417,29 +2389,114 @@ impl Database {
             exec: Arc::clone(&db.exec),
             datafusion_config: Arc::clone(&db.datafusion_config),
             query_log: Arc::clone(&db.query_log),
+            unlogged_query_log: Arc::clone(&db.unlogged_query_log),
+            query_log_stats: Arc::clone(&db.query_log_stats),
             system_schema_provider: Arc::clone(&db.system_schema_provider),
+            options: Arc::clone(&db.options),
+            dictionary_stats: db.dictionary_stats.clone(),
+            file_chunk: db.file_chunk.clone(),
+            chunk_snapshots: Arc::clone(&db.chunk_snapshots),
+            progress: db.progress.clone(),
+            max_time_range: db.max_time_range,
+            time_bucket_limit: db.time_bucket_limit,
+            parquet_cache: db.parquet_cache.clone(),
+            read_ahead: db.read_ahead.clone(),
+            request_budget: db.request_budget.clone(),
+            max_plan_nodes: db.max_plan_nodes,
+            aggregate_mem_pool_size: db.aggregate_mem_pool_size,
+            scan_filters: db.scan_filters.clone(),
+            query_jobs: Arc::clone(&db.query_jobs),
+            system_tables_used: Arc::clone(&db.system_tables_used),
         }
     }
 
-    async fn query_table(&self, table_name: &str) -> Option<Arc<QueryTable>> {
-        let table_name: Arc<str> = table_name.into();
-        self.db_schema
-            .table_schema(Arc::clone(&table_name))
-            .map(|schema| {
-                Arc::new(QueryTable {
-                    db_schema: Arc::clone(&self.db_schema),
-                    table_name,
-                    schema: schema.clone(),
-                    write_buffer: Arc::clone(&self.write_buffer),
-                })
-            })
+    /// Record a query in the query log, returning the id of its entry in the log along with the
+    /// token used to track its progress
+    fn record_query_with_id(
+        &self,
+        span_ctx: Option<&SpanContext>,
+        query_type: &'static str,
+        query_text: QueryText,
+        query_params: StatementParams,
+    ) -> (Option<String>, QueryCompletedToken<StateReceived>) {
+        let trace_id = span_ctx.map(|ctx| ctx.trace_id);
+        let namespace_name: Arc<str> = Arc::from("influxdb3 oss");
+        if !self.options.log {
+            // the query still needs a token to be run, which is issued by a log that keeps no
+            // entries, and without an id, so that no stats or progress are kept for it either:
+            let token = self.unlogged_query_log.push(
+                NamespaceId::new(0),
+                namespace_name,
+                query_type,
+                query_text,
+                query_params,
+                trace_id,
+            );
+            return (None, token);
+        }
+        let token = self.query_log.push(
+            NamespaceId::new(0),
+            namespace_name,
+            query_type,
+            query_text,
+            query_params,
+            trace_id,
+        );
+        let id = token.entry().map(|e| e.state().id.to_string());
+        (id, token)
+    }
+
+    fn query_table(&self, table_name: &str) -> Result<Option<Arc<QueryTable>>, QueryExecutorError> {
+        let table_name: Arc<str> = match self.options.table_rewrites.get(table_name) {
+            Some(target) if self.db_schema.table_name_to_id(target.as_str()).is_none() => {
+                return Err(QueryExecutorError::TableRewriteTargetNotFound {
+                    table: table_name.to_string(),
+                    target: target.clone(),
+                });
+            }
+            Some(target) => target.as_str().into(),
+            None => table_name.into(),
+        };
+        let Some(schema) = self.db_schema.table_schema(Arc::clone(&table_name)) else {
+            return Ok(None);
+        };
+        // a table that is in the catalog, but whose columns have not been created yet, would
+        // otherwise produce a confusing empty result:
+        if schema
+            .iter()
+            .all(|(column_type, _)| column_type == InfluxColumnType::Timestamp)
+        {
+            return Err(QueryExecutorError::TableNotReady {
+                table: table_name.to_string(),
+            });
+        }
+        Ok(Some(Arc::new(QueryTable {
+            db_schema: Arc::clone(&self.db_schema),
+            schema,
+            write_buffer: Arc::clone(&self.write_buffer),
+            options: Arc::clone(&self.options),
+            dictionary_stats: self.dictionary_stats.clone(),
+            file_chunk: self
+                .file_chunk
+                .as_ref()
+                .filter(|(name, _)| *name == table_name)
+                .map(|(_, chunk)| Arc::clone(chunk)),
+            chunk_snapshots: Arc::clone(&self.chunk_snapshots),
+            progress: self.progress.clone(),
+            max_time_range: self.max_time_range,
+            time_bucket_limit: self.time_bucket_limit,
+            read_ahead: self.read_ahead.clone(),
+            request_budget: self.request_budget.clone(),
+            scan_filters: self.scan_filters.clone(),
+            table_name,
+        })))
     }
 }
 
 #[async_trait]
 impl QueryNamespace for Database {
     fn retention_time_ns(&self) -> Option<i64> {
-        None
+        retention::retention_period(&self.db_schema)
     }
 
     fn record_query(
@@ -449,16 +2506,8 @@ impl QueryNamespace for Database {
         query_text: QueryText,
         query_params: StatementParams,
     ) -> QueryCompletedToken<StateReceived> {
-        let trace_id = span_ctx.map(|ctx| ctx.trace_id);
-        let namespace_name: Arc<str> = Arc::from("influxdb3 oss");
-        self.query_log.push(
-            NamespaceId::new(0),
-            namespace_name,
-            query_type,
-            query_text,
-            query_params,
-            trace_id,
-        )
+        let (_, token) = self.record_query_with_id(span_ctx, query_type, query_text, query_params);
+        token
     }
 
     fn new_query_context(
@@ -473,10 +2522,18 @@ impl QueryNamespace for Database {
             .with_span_context(span_ctx);
 
         for (k, v) in self.datafusion_config.as_ref() {
+            if k == QUERY_TIMEOUT_CONFIG_KEY || k == AGGREGATE_MEM_POOL_SIZE_CONFIG_KEY {
+                continue;
+            }
             cfg = cfg.with_config_option(k, v);
         }
 
         let ctx = cfg.build();
+        if let Some(limit) =
+            memory::config_budget(&self.datafusion_config).or(self.aggregate_mem_pool_size)
+        {
+            memory::set_session_budget(&ctx, limit);
+        }
         ctx.inner().register_udtf(
             LAST_CACHE_UDTF_NAME,
             Arc::new(LastCacheFunction::new(
@@ -484,13 +2541,49 @@ impl QueryNamespace for Database {
                 self.write_buffer.last_cache_provider(),
             )),
         );
+        let distinct_cache = DistinctCacheFunction::new(
+            self.db_schema.id,
+            self.write_buffer.distinct_cache_provider(),
+        );
+        let distinct_cache = match self.options.distinct_cache_mode {
+            DistinctCacheMode::Fast => distinct_cache,
+            DistinctCacheMode::Exact => {
+                distinct_cache.with_fallback(Arc::new(Self::from_namespace(self)))
+            }
+        };
+        ctx.inner()
+            .register_udtf(DISTINCT_CACHE_UDTF_NAME, Arc::new(distinct_cache));
+        if let Some(parquet_cache) = &self.parquet_cache {
+            ctx.inner().register_udtf(
+                PARQUET_CACHE_UDTF_NAME,
+                Arc::new(ParquetCacheFunction::new(Arc::clone(parquet_cache))),
+            );
+        }
         ctx.inner().register_udtf(
-            DISTINCT_CACHE_UDTF_NAME,
-            Arc::new(DistinctCacheFunction::new(
-                self.db_schema.id,
-                self.write_buffer.distinct_cache_provider(),
+            QUERY_RESULT_UDTF_NAME,
+            Arc::new(tickets::QueryResultFunction::new(
+                Arc::clone(&self.db_schema.name),
+                Arc::clone(&self.query_jobs),
             )),
         );
+        ctx.inner().register_udaf(
+            approx_distinct_udaf()
+                .as_ref()
+                .clone()
+                .with_aliases([APPROX_COUNT_DISTINCT_UDAF_NAME]),
+        );
+        for (name, value) in &self.options.constants {
+            ctx.inner()
+                .register_udf(constants::constant_udf(name, value));
+        }
+        if self.options.collation == Collation::CaseInsensitive {
+            ctx.inner()
+                .add_analyzer_rule(Arc::new(collation::CaseInsensitiveCollation));
+        }
+        if let Some(max) = self.max_plan_nodes {
+            ctx.inner()
+                .add_analyzer_rule(Arc::new(plan_complexity::PlanComplexityLimit::new(max)));
+        }
         ctx
     }
 
@@ -518,7 +2611,10 @@ impl CatalogProvider for Database {
         debug!(schema_name = %name, "Database as CatalogProvider::schema");
         match name {
             DEFAULT_SCHEMA => Some(Arc::new(Self::from_namespace(self))),
-            SYSTEM_SCHEMA_NAME => Some(Arc::clone(&self.system_schema_provider) as _),
+            SYSTEM_SCHEMA_NAME => {
+                self.system_tables_used.store(true, AtomicOrdering::Relaxed);
+                Some(Arc::clone(&self.system_schema_provider) as _)
+            }
             _ => None,
         }
     }
@@ -542,11 +2638,39 @@ impl SchemaProvider for Database {
         &self,
         table_name: &str,
     ) -> Result<Option<Arc<dyn TableProvider>>, DataFusionError> {
-        Ok(self.query_table(table_name).await.map(|qt| qt as _))
+        let Some(table) = self
+            .query_table(table_name)
+            .map_err(|e| DataFusionError::External(Box::new(e)))?
+        else {
+            return Ok(None);
+        };
+        match self
+            .db_schema
+            .table_definition(Arc::clone(&table.table_name))
+            .filter(|def| !def.computed_columns.is_empty())
+        {
+            Some(def) => {
+                computed_columns::with_computed_columns(table_name, table, &def.computed_columns)
+                    .map(Some)
+            }
+            None => Ok(Some(table as _)),
+        }
     }
 
     fn table_exist(&self, name: &str) -> bool {
-        self.db_schema.table_name_to_id(name).is_some()
+        self.options.table_rewrites.contains_key(name)
+            || self.db_schema.table_name_to_id(name).is_some()
+    }
+}
+
+impl DistinctCacheFallback for Database {
+    fn table_provider(
+        &self,
+        table_name: &str,
+    ) -> Result<Option<Arc<dyn TableProvider>>, DataFusionError> {
+        self.query_table(table_name)
+            .map(|qt| qt.map(|qt| qt as _))
+            .map_err(|e| DataFusionError::External(Box::new(e)))
     }
 }
 
@@ -556,26 +2680,80 @@ pub struct QueryTable {
     table_name: Arc<str>,
     schema: Schema,
     write_buffer: Arc<dyn WriteBuffer>,
+    options: Arc<QueryOptions>,
+    dictionary_stats: Option<Arc<DictionaryStatsCollector>>,
+    /// Scan only this chunk instead of the table's chunks in the write buffer
+    file_chunk: Option<Arc<dyn QueryChunk>>,
+    chunk_snapshots: Arc<ChunkSnapshots>,
+    progress: Option<Arc<ScanProgress>>,
+    max_time_range: Option<Duration>,
+    time_bucket_limit: Option<TimeBucketLimit>,
+    read_ahead: Option<ReadAhead>,
+    request_budget: Option<Arc<RequestBudget>>,
+    scan_filters: Option<Arc<ScanFilters>>,
 }
 
 impl QueryTable {
+    /// The chunks to scan for this table
+    ///
+    /// The chunks are fetched from the write buffer on the first scan of the table with the
+    /// given `filters` and `projection` in a query, and reused for any later such scans in the
+    /// same query, e.g., when the query is re-planned, so that they see the same data, even if
+    /// ingest persists new chunks in the meantime.
     fn chunks(
         &self,
         ctx: &dyn Session,
-        projection: Option<&Vec<usize>>,
         filters: &[Expr],
-        _limit: Option<usize>,
+        projection: Option<&Vec<usize>>,
     ) -> Result<Vec<Arc<dyn QueryChunk>>, DataFusionError> {
-        self.write_buffer.get_table_chunks(
-            &self.db_schema.name,
-            &self.table_name,
-            filters,
-            projection,
-            ctx,
-        )
+        if let Some(chunk) = &self.file_chunk {
+            return Ok(vec![Arc::clone(chunk)]);
+        }
+        let mut chunks = {
+            let scan = ScanKey {
+                table_name: Arc::clone(&self.table_name),
+                filters: filters.to_vec(),
+                projection: projection.cloned(),
+            };
+            let mut snapshots = self.chunk_snapshots.lock();
+            match snapshots.get(&scan) {
+                Some(chunks) => chunks.clone(),
+                None => {
+                    let mut chunks = self.write_buffer.get_table_chunks(
+                        &self.db_schema.name,
+                        &self.table_name,
+                        filters,
+                        projection,
+                        ctx,
+                    )?;
+                    if let Some(cutoff) =
+                        retention::retention_cutoff(&self.db_schema, query_start_time(ctx))
+                    {
+                        retention::remove_expired(&mut chunks, cutoff);
+                    }
+                    snapshots.insert(scan, chunks.clone());
+                    chunks
+                }
+            }
+        };
+        match self.options.storage {
+            StorageHint::Both => (),
+            StorageHint::ReadBuffer => chunks.retain(|c| c.as_any().is::<BufferChunk>()),
+            StorageHint::ObjectStore => chunks.retain(|c| c.as_any().is::<ParquetChunk>()),
+        }
+        chunk_order::order_chunks(&mut chunks, self.options.chunk_order);
+        Ok(chunks)
     }
 }
 
+/// The time at which the query being run in the `ctx` started, in nanoseconds
+fn query_start_time(ctx: &dyn Session) -> i64 {
+    ctx.execution_props()
+        .query_execution_start_time
+        .timestamp_nanos_opt()
+        .unwrap_or(i64::MAX)
+}
+
 #[async_trait]
 impl TableProvider for QueryTable {
     fn as_any(&self) -> &dyn Any {
@@ -594,7 +2772,16 @@ impl TableProvider for QueryTable {
         &self,
         filters: &[&Expr],
     ) -> datafusion::common::Result<Vec<TableProviderFilterPushDown>> {
-        Ok(vec![TableProviderFilterPushDown::Inexact; filters.len()])
+        Ok(filters
+            .iter()
+            .map(|filter| {
+                if time_range::is_exact_time_filter(filter) {
+                    TableProviderFilterPushDown::Exact
+                } else {
+                    TableProviderFilterPushDown::Inexact
+                }
+            })
+            .collect())
     }
 
     async fn scan(
@@ -604,16 +2791,65 @@ impl TableProvider for QueryTable {
         filters: &[Expr],
         limit: Option<usize>,
     ) -> datafusion::common::Result<Arc<dyn ExecutionPlan>> {
-        let filters = filters.to_vec();
+        let mut filters = filters.to_vec();
         debug!(
             ?projection,
             ?filters,
             ?limit,
             "QueryTable as TableProvider::scan"
         );
+        let now = query_start_time(ctx);
+        let time_predicate_required = self
+            .db_schema
+            .table_definition(Arc::clone(&self.table_name))
+            .is_some_and(|table| table.require_time_predicate);
+        if time_predicate_required {
+            time_range::check_time_predicate(&filters, &self.table_name)
+                .map_err(|e| DataFusionError::External(Box::new(e)))?;
+        }
+        if let Some(max) = self.max_time_range {
+            time_range::check_time_range(&filters, now, max)
+                .map_err(|e| DataFusionError::External(Box::new(e)))?;
+        }
+        if let Some(limit) = self.time_bucket_limit {
+            time_range::check_time_buckets(&filters, now, limit)
+                .map_err(|e| DataFusionError::External(Box::new(e)))?;
+        }
+        if let Some(cutoff) = retention::retention_cutoff(&self.db_schema, now) {
+            filters.push(retention::retention_filter(cutoff));
+        }
+        if let Some(scan_filters) = &self.scan_filters {
+            scan_filters
+                .lock()
+                .entry(Arc::clone(&self.table_name))
+                .or_default()
+                .push(filters.clone());
+        }
         let mut builder = ProviderBuilder::new(Arc::clone(&self.table_name), self.schema.clone());
 
-        let chunks = self.chunks(ctx, projection, &filters, limit)?;
+        let chunks = self.chunks(ctx, &filters, projection)?;
+        if self.options.strict_field_types {
+            field_types::check_field_types(chunks.iter().map(|c| c.schema()))
+                .map_err(|e| DataFusionError::External(Box::new(e)))?;
+        }
+        if let Some(budget) = &self.request_budget {
+            budget
+                .charge(&chunks)
+                .map_err(|e| DataFusionError::External(Box::new(e)))?;
+        }
+        let estimated_rows = chunks
+            .iter()
+            .filter_map(|c| c.stats().num_rows.get_value().copied())
+            .sum::<usize>();
+        if let Some(read_ahead) = &self.read_ahead {
+            read_ahead.prefetch(
+                chunks
+                    .iter()
+                    .filter_map(|c| c.as_any().downcast_ref::<ParquetChunk>())
+                    .map(|c| c.object_meta().clone())
+                    .collect(),
+            );
+        }
         for chunk in chunks {
             builder = builder.add_chunk(chunk);
         }
@@ -623,41 +2859,92 @@ impl TableProvider for QueryTable {
             Err(e) => panic!("unexpected error: {e:?}"),
         };
 
-        provider.scan(ctx, projection, &filters, limit).await
+        let mut scan = provider.scan(ctx, projection, &filters, limit).await?;
+        if let Some(progress) = &self.progress {
+            progress.add_estimate(estimated_rows);
+            scan = progress::track_scan(scan, progress)?;
+        }
+        Ok(match &self.dictionary_stats {
+            Some(collector) => dictionary_stats::observe_scan(scan, Arc::clone(collector)),
+            None => scan,
+        })
     }
 }
 #[cfg(test)]
 mod tests {
-    use std::{num::NonZeroUsize, sync::Arc, time::Duration};
+    use std::{
+        collections::{BTreeMap, HashMap},
+        num::NonZeroUsize,
+        pin::pin,
+        sync::Arc,
+        time::Duration,
+    };
 
-    use crate::query_executor::QueryExecutorImpl;
-    use arrow::array::RecordBatch;
+    use crate::query_executor::{
+        merge_datafusion_config, merge_partials, ColumnNames, Database, ExecutionStats,
+        QueryExecutorImpl, QueryFailed, QueryJobStatus, ReplayPolicy, SeriesMetadata, SeriesTag,
+        SlowQuery, AGGREGATE_MEM_POOL_SIZE_CONFIG_KEY, AUTOGEN_RETENTION_POLICY,
+        DEFAULT_QUERY_COST_ROW_WEIGHT, DEFAULT_QUERY_JOB_EXPIRY_INTERVAL, DEFAULT_QUERY_JOB_TTL,
+        QUERY_RESULT_UDTF_NAME, QUERY_TIMEOUT_CONFIG_KEY, ROW_ID_COLUMN_NAME, SERIES_METADATA_KEY,
+    };
+    use arrow::array::{AsArray, RecordBatch};
+    use arrow::compute::concat_batches;
+    use arrow::datatypes::{DataType, Float64Type, Int64Type, TimeUnit, UInt64Type};
     use data_types::NamespaceName;
-    use datafusion::assert_batches_sorted_eq;
-    use futures::TryStreamExt;
+    use datafusion::datasource::TableProvider;
+    use datafusion::error::DataFusionError;
+    use datafusion::execution::memory_pool::MemoryConsumer;
+    use datafusion::physical_plan::collect;
+    use datafusion::scalar::ScalarValue;
+    use datafusion::{assert_batches_eq, assert_batches_sorted_eq};
+    use futures::{StreamExt, TryStreamExt};
     use influxdb3_cache::{
-        distinct_cache::DistinctCacheProvider, last_cache::LastCacheProvider,
+        distinct_cache::{
+            CreateDistinctCacheArgs, DistinctCacheProvider, DISTINCT_CACHE_COMPLETE_METADATA_KEY,
+        },
+        last_cache::LastCacheProvider,
         parquet_cache::test_cached_obj_store_and_oracle,
     };
     use influxdb3_catalog::catalog::Catalog;
-    use influxdb3_internal_api::query_executor::{QueryExecutor, QueryKind};
+    use influxdb3_id::{ParquetFileId, TableId};
+    use influxdb3_internal_api::query_executor::{
+        BooleanFormat, ChunkOrderHint, Collation, DistinctCacheMode, DuplicateColumnPolicy,
+        QueryExecutor, QueryExecutorError, QueryKind, QueryOptions, QueryPriority, StorageHint,
+        TimePrecision,
+    };
     use influxdb3_sys_events::SysEventStore;
     use influxdb3_telemetry::store::TelemetryStore;
-    use influxdb3_wal::{Gen1Duration, WalConfig};
+    use influxdb3_wal::{
+        CatalogBatch, CatalogOp, Gen1Duration, RetentionPeriodDefinition, TableDefinition,
+        WalConfig,
+    };
     use influxdb3_write::{
         persister::Persister,
-        write_buffer::{persisted_files::PersistedFiles, WriteBufferImpl, WriteBufferImplArgs},
+        write_buffer::{
+            persisted_files::PersistedFiles, Error as WriteBufferError, WriteBufferImpl,
+            WriteBufferImplArgs,
+        },
         WriteBuffer,
     };
-    use iox_query::exec::{DedicatedExecutor, Executor, ExecutorConfig};
+    use iox_query::exec::{DedicatedExecutor, Executor, ExecutorConfig, IOxSessionContext};
+    use iox_query::QueryNamespace;
     use iox_time::{MockProvider, Time};
     use metric::Registry;
-    use object_store::{local::LocalFileSystem, ObjectStore};
+    use object_store::{
+        local::LocalFileSystem, memory::InMemory, path::Path as ObjPath, ObjectStore,
+    };
+    use parquet::basic::Compression;
     use parquet_file::storage::{ParquetStorage, StorageId};
+    use tracker::AsyncSemaphoreMetrics;
 
     use super::CreateQueryExecutorArgs;
+    use super::ResultSource;
 
-    fn make_exec(object_store: Arc<dyn ObjectStore>) -> Arc<Executor> {
+    fn make_exec(
+        object_store: Arc<dyn ObjectStore>,
+        executor: DedicatedExecutor,
+        mem_pool_size: usize,
+    ) -> Arc<Executor> {
         let metrics = Arc::new(metric::Registry::default());
 
         let parquet_store = ParquetStorage::new(
@@ -672,14 +2959,34 @@ mod tests {
                     .map(|store| (store.id(), Arc::clone(store.object_store())))
                     .collect(),
                 metric_registry: Arc::clone(&metrics),
-                // Default to 1gb
-                mem_pool_size: 1024 * 1024 * 1024, // 1024 (b/kb) * 1024 (kb/mb) * 1024 (mb/gb)
+                mem_pool_size,
             },
-            DedicatedExecutor::new_testing(),
+            executor,
         ))
     }
 
     async fn setup() -> (Arc<dyn WriteBuffer>, QueryExecutorImpl, Arc<MockProvider>) {
+        setup_with_args(HashMap::new(), |_| {}).await
+    }
+
+    async fn setup_with_column_compression(
+        column_compression: HashMap<String, Compression>,
+    ) -> (Arc<dyn WriteBuffer>, QueryExecutorImpl, Arc<MockProvider>) {
+        setup_with_args(column_compression, |_| {}).await
+    }
+
+    /// Set up as for [`setup`], but with the arguments of the query executor modified by
+    /// `configure`, so that tests can enable the features that are off by default
+    async fn setup_with(
+        configure: impl FnOnce(&mut CreateQueryExecutorArgs),
+    ) -> (Arc<dyn WriteBuffer>, QueryExecutorImpl, Arc<MockProvider>) {
+        setup_with_args(HashMap::new(), configure).await
+    }
+
+    async fn setup_with_args(
+        column_compression: HashMap<String, Compression>,
+        configure: impl FnOnce(&mut CreateQueryExecutorArgs),
+    ) -> (Arc<dyn WriteBuffer>, QueryExecutorImpl, Arc<MockProvider>) {
         // Set up QueryExecutor
         let object_store: Arc<dyn ObjectStore> =
             Arc::new(LocalFileSystem::new_with_prefix(test_helpers::tmp_dir().unwrap()).unwrap());
@@ -689,13 +2996,21 @@ mod tests {
             Arc::clone(&time_provider) as _,
             Default::default(),
         );
-        let persister = Arc::new(Persister::new(Arc::clone(&object_store), "test_host"));
-        let exec = make_exec(Arc::clone(&object_store));
+        let persister = Arc::new(
+            Persister::new(Arc::clone(&object_store), "test_host")
+                .with_column_compression(column_compression),
+        );
+        // Default to 1gb
+        let exec = make_exec(
+            Arc::clone(&object_store),
+            DedicatedExecutor::new_testing(),
+            1024 * 1024 * 1024, // 1024 (b/kb) * 1024 (kb/mb) * 1024 (mb/gb)
+        );
         let host_id = Arc::from("sample-host-id");
         let instance_id = Arc::from("instance-id");
         let catalog = Arc::new(Catalog::new(host_id, instance_id));
         let write_buffer_impl = WriteBufferImpl::new(WriteBufferImplArgs {
-            persister,
+            persister: Arc::clone(&persister),
             catalog: Arc::clone(&catalog),
             last_cache: LastCacheProvider::new_from_catalog(Arc::clone(&catalog)).unwrap(),
             distinct_cache: DistinctCacheProvider::new_from_catalog(
@@ -711,7 +3026,7 @@ mod tests {
                 flush_interval: Duration::from_millis(10),
                 snapshot_size: 1,
             },
-            parquet_cache: Some(parquet_cache),
+            parquet_cache: Some(Arc::clone(&parquet_cache)),
             metric_registry: Default::default(),
         })
         .await
@@ -725,16 +3040,45 @@ mod tests {
         let write_buffer: Arc<dyn WriteBuffer> = write_buffer_impl;
         let metrics = Arc::new(Registry::new());
         let datafusion_config = Arc::new(Default::default());
-        let query_executor = QueryExecutorImpl::new(CreateQueryExecutorArgs {
+        let mut args = CreateQueryExecutorArgs {
             catalog: write_buffer.catalog(),
             write_buffer: Arc::clone(&write_buffer),
             exec,
+            batch_exec: None,
             metrics,
+            time_provider: Arc::<MockProvider>::clone(&time_provider),
             datafusion_config,
             query_log_size: 10,
             telemetry_store,
             sys_events_store,
-        });
+            persister,
+            query_job_ttl: DEFAULT_QUERY_JOB_TTL,
+            aggregate_mem_pool_size: None,
+            mem_pool_high_water_mark: None,
+            max_transient_retries: 0,
+            max_planning_time: None,
+            slow_planning_threshold: None,
+            slow_query_threshold: None,
+            cross_join_row_limit: None,
+            max_query_time_range: None,
+            max_time_buckets: None,
+            max_storage_requests: None,
+            max_plan_nodes: None,
+            max_query_cost: None,
+            query_cost_row_weight: DEFAULT_QUERY_COST_ROW_WEIGHT,
+            query_timeout: None,
+            result_cache_size: None,
+            coalesce_buffer_size: None,
+            default_retention_policy: AUTOGEN_RETENTION_POLICY.to_string(),
+            parquet_cache: Some(parquet_cache),
+            scan_read_ahead: None,
+            scan_read_ahead_bytes: 0,
+            replay_policy: Default::default(),
+            column_names: Default::default(),
+            max_concurrent_queries: None,
+        };
+        configure(&mut args);
+        let query_executor = QueryExecutorImpl::new(args);
 
         (write_buffer, query_executor, time_provider)
     }
@@ -849,4 +3193,5823 @@ mod tests {
             assert_batches_sorted_eq!(t.expected, &batches);
         }
     }
+
+    #[test_log::test(tokio::test)]
+    async fn mixed_column_codecs() {
+        let (write_buffer, query_executor, time_provider) =
+            setup_with_column_compression(HashMap::from([
+                ("host".to_string(), Compression::SNAPPY),
+                ("usage".to_string(), Compression::UNCOMPRESSED),
+            ]))
+            .await;
+        let db_name = "test_db";
+        for i in 0..3 {
+            write_buffer
+                .write_lp(
+                    NamespaceName::new(db_name).unwrap(),
+                    &format!("cpu,host=h{i} usage={i}"),
+                    Time::from_timestamp_nanos(i * 10),
+                    false,
+                    influxdb3_write::Precision::Nanosecond,
+                )
+                .await
+                .unwrap();
+            time_provider.set(Time::from_timestamp(i * 10 + 1, 0).unwrap());
+        }
+        time_provider.set(Time::from_timestamp(20, 0).unwrap());
+        tokio::time::sleep(Duration::from_millis(500)).await;
+
+        let batches: Vec<RecordBatch> = query_executor
+            .query(
+                db_name,
+                "SELECT DISTINCT column_codecs FROM system.parquet_files WHERE table_name = 'cpu'",
+                None,
+                QueryKind::Sql,
+                None,
+                None,
+            )
+            .await
+            .unwrap()
+            .try_collect()
+            .await
+            .unwrap();
+        assert_batches_sorted_eq!(
+            [
+                "+--------------------------------------------+",
+                "| column_codecs                              |",
+                "+--------------------------------------------+",
+                "| host=snappy, time=zstd, usage=uncompressed |",
+                "+--------------------------------------------+",
+            ],
+            &batches
+        );
+
+        // the persisted files are read back regardless of the codec of each column:
+        let batches: Vec<RecordBatch> = query_executor
+            .query(
+                db_name,
+                "SELECT host, usage FROM cpu",
+                None,
+                QueryKind::Sql,
+                None,
+                None,
+            )
+            .await
+            .unwrap()
+            .try_collect()
+            .await
+            .unwrap();
+        assert_batches_sorted_eq!(
+            [
+                "+------+-------+",
+                "| host | usage |",
+                "+------+-------+",
+                "| h0   | 0.0   |",
+                "| h1   | 1.0   |",
+                "| h2   | 2.0   |",
+                "+------+-------+",
+            ],
+            &batches
+        );
+    }
+
+    #[test_log::test(tokio::test)]
+    async fn unknown_column_suggestions() {
+        let (write_buffer, query_executor, _) = setup().await;
+        let db_name = "test_db";
+        write_buffer
+            .write_lp(
+                NamespaceName::new(db_name).unwrap(),
+                "cpu,host=a,region=us-east usage=250",
+                Time::from_timestamp_nanos(0),
+                false,
+                influxdb3_write::Precision::Nanosecond,
+            )
+            .await
+            .unwrap();
+
+        // a typo yields a suggestion:
+        let err = query_executor
+            .query(
+                db_name,
+                "SELECT usge FROM cpu",
+                None,
+                QueryKind::Sql,
+                None,
+                None,
+            )
+            .await
+            .unwrap_err();
+        assert!(
+            matches!(
+                &err,
+                QueryExecutorError::UnknownColumn { name, suggestions }
+                    if name == "usge" && suggestions == &["usage"]
+            ),
+            "unexpected error: {err}"
+        );
+        assert_eq!(
+            "column 'usge' not found, did you mean: usage?",
+            err.to_string()
+        );
+
+        // an unrelated name yields no suggestions:
+        let err = query_executor
+            .query(
+                db_name,
+                "SELECT temperature FROM cpu",
+                None,
+                QueryKind::Sql,
+                None,
+                None,
+            )
+            .await
+            .unwrap_err();
+        assert!(
+            matches!(
+                &err,
+                QueryExecutorError::UnknownColumn { name, suggestions }
+                    if name == "temperature" && suggestions.is_empty()
+            ),
+            "unexpected error: {err}"
+        );
+    }
+
+    #[test_log::test(tokio::test)]
+    async fn partition_row_distribution_in_query_log() {
+        let (write_buffer, query_executor, _) = setup().await;
+        let db_name = "test_db";
+        // write a skewed dataset, where cpu has many more rows than mem:
+        write_buffer
+            .write_lp(
+                NamespaceName::new(db_name).unwrap(),
+                "\
+                cpu,host=a usage=1 1\n\
+                cpu,host=a usage=2 2\n\
+                cpu,host=a usage=3 3\n\
+                cpu,host=a usage=4 4\n\
+                cpu,host=a usage=5 5\n\
+                mem,host=a usage=1 1\n\
+                ",
+                Time::from_timestamp_nanos(0),
+                false,
+                influxdb3_write::Precision::Nanosecond,
+            )
+            .await
+            .unwrap();
+
+        // the union produces an output partition for each of its inputs:
+        let batches: Vec<RecordBatch> = query_executor
+            .query(
+                db_name,
+                "SELECT usage FROM cpu UNION ALL SELECT usage FROM mem",
+                None,
+                QueryKind::Sql,
+                None,
+                None,
+            )
+            .await
+            .unwrap()
+            .try_collect()
+            .await
+            .unwrap();
+        assert_eq!(6, batches.iter().map(|b| b.num_rows()).sum::<usize>());
+
+        let batches: Vec<RecordBatch> = query_executor
+            .query(
+                db_name,
+                "SELECT query_text, output_partitions, partition_min_rows, \
+                    partition_max_rows, partition_avg_rows \
+                FROM system.queries \
+                WHERE output_partitions IS NOT NULL",
+                None,
+                QueryKind::Sql,
+                None,
+                None,
+            )
+            .await
+            .unwrap()
+            .try_collect()
+            .await
+            .unwrap();
+        assert_batches_sorted_eq!(
+            [
+                "+-------------------------------------------------------+-------------------+--------------------+--------------------+--------------------+",
+                "| query_text                                            | output_partitions | partition_min_rows | partition_max_rows | partition_avg_rows |",
+                "+-------------------------------------------------------+-------------------+--------------------+--------------------+--------------------+",
+                "| SELECT usage FROM cpu UNION ALL SELECT usage FROM mem | 2                 | 1                  | 5                  | 3.0                |",
+                "+-------------------------------------------------------+-------------------+--------------------+--------------------+--------------------+",
+            ],
+            &batches
+        );
+    }
+
+    #[test_log::test(tokio::test)]
+    async fn column_casts() {
+        let (write_buffer, query_executor, _) = setup().await;
+        let db_name = "test_db";
+        write_buffer
+            .write_lp(
+                NamespaceName::new(db_name).unwrap(),
+                "\
+                cpu,host=a count=5i 1\n\
+                cpu,host=b count=7i 2\n\
+                ",
+                Time::from_timestamp_nanos(0),
+                false,
+                influxdb3_write::Precision::Nanosecond,
+            )
+            .await
+            .unwrap();
+
+        // cast the integer field to a string:
+        let options = QueryOptions {
+            column_casts: HashMap::from([("count".to_string(), DataType::Utf8)]),
+            ..Default::default()
+        };
+        let batches: Vec<RecordBatch> = query_executor
+            .query_with_options(
+                db_name,
+                "SELECT host, count FROM cpu",
+                None,
+                QueryKind::Sql,
+                options,
+                None,
+                None,
+            )
+            .await
+            .unwrap()
+            .try_collect()
+            .await
+            .unwrap();
+        assert_eq!(
+            &DataType::Utf8,
+            batches[0]
+                .schema()
+                .field_with_name("count")
+                .unwrap()
+                .data_type()
+        );
+        assert_batches_sorted_eq!(
+            [
+                "+------+-------+",
+                "| host | count |",
+                "+------+-------+",
+                "| a    | 5     |",
+                "| b    | 7     |",
+                "+------+-------+",
+            ],
+            &batches
+        );
+
+        // a timestamp cannot be cast to a boolean:
+        let options = QueryOptions {
+            column_casts: HashMap::from([("time".to_string(), DataType::Boolean)]),
+            ..Default::default()
+        };
+        let err = query_executor
+            .query_with_options(
+                db_name,
+                "SELECT host, time FROM cpu",
+                None,
+                QueryKind::Sql,
+                options,
+                None,
+                None,
+            )
+            .await
+            .unwrap_err();
+        assert!(
+            matches!(
+                &err,
+                QueryExecutorError::InvalidColumnCast { column, .. } if column == "time"
+            ),
+            "unexpected error: {err}"
+        );
+    }
+
+    #[test_log::test(tokio::test)]
+    async fn query_per_measurement() {
+        let (write_buffer, query_executor, _) = setup().await;
+        let db_name = "test_db";
+        write_buffer
+            .write_lp(
+                NamespaceName::new(db_name).unwrap(),
+                "\
+                cpu,host=a usage=1 1\n\
+                cpu,host=a usage=2 2\n\
+                mem,host=a used=10 1\n\
+                mem,host=a used=20 200\n\
+                ",
+                Time::from_timestamp_nanos(0),
+                false,
+                influxdb3_write::Precision::Nanosecond,
+            )
+            .await
+            .unwrap();
+
+        let streams = query_executor
+            .query_per_measurement(
+                db_name,
+                &["cpu", "mem"],
+                Time::from_timestamp_nanos(0)..Time::from_timestamp_nanos(100),
+                QueryOptions::default(),
+            )
+            .await
+            .unwrap();
+        // each table is logged as a query of its own, which runs until its results are read:
+        let entries = query_executor.query_log.entries().entries;
+        assert_eq!(2, entries.len());
+        assert!(entries.iter().all(|e| e.state().running));
+        let mut results = vec![];
+        for (measurement, stream) in streams {
+            let batches: Vec<RecordBatch> = stream.try_collect().await.unwrap();
+            results.push((measurement, batches));
+        }
+        let entries = query_executor.query_log.entries().entries;
+        assert!(entries.iter().all(|e| {
+            let state = e.state();
+            !state.running && state.success
+        }));
+
+        assert_eq!(2, results.len());
+        assert_eq!("cpu", results[0].0);
+        assert_batches_sorted_eq!(
+            [
+                "+------+-------------------------------+-------+",
+                "| host | time                          | usage |",
+                "+------+-------------------------------+-------+",
+                "| a    | 1970-01-01T00:00:00.000000001 | 1.0   |",
+                "| a    | 1970-01-01T00:00:00.000000002 | 2.0   |",
+                "+------+-------------------------------+-------+",
+            ],
+            &results[0].1
+        );
+        // only rows within the time range are returned:
+        assert_eq!("mem", results[1].0);
+        assert_batches_sorted_eq!(
+            [
+                "+------+-------------------------------+------+",
+                "| host | time                          | used |",
+                "+------+-------------------------------+------+",
+                "| a    | 1970-01-01T00:00:00.000000001 | 10.0 |",
+                "+------+-------------------------------+------+",
+            ],
+            &results[1].1
+        );
+
+        // the options apply to the query of each table:
+        let options = QueryOptions {
+            log: false,
+            ..Default::default()
+        };
+        let streams = query_executor
+            .query_per_measurement(
+                db_name,
+                &["cpu", "mem"],
+                Time::from_timestamp_nanos(0)..Time::from_timestamp_nanos(100),
+                options,
+            )
+            .await
+            .unwrap();
+        for (_, stream) in streams {
+            stream.try_collect::<Vec<RecordBatch>>().await.unwrap();
+        }
+        assert_eq!(2, query_executor.query_log.entries().entries.len());
+    }
+
+    #[test_log::test(tokio::test)]
+    async fn table_without_columns_is_not_ready() {
+        let (write_buffer, query_executor, _) = setup().await;
+        let db_name = "test_db";
+        write_buffer
+            .write_lp(
+                NamespaceName::new(db_name).unwrap(),
+                "cpu,host=a usage=1 1",
+                Time::from_timestamp_nanos(0),
+                false,
+                influxdb3_write::Precision::Nanosecond,
+            )
+            .await
+            .unwrap();
+
+        // add a table to the catalog without any columns, as if it were still being created:
+        let catalog = write_buffer.catalog();
+        let db_id = catalog.db_name_to_id(db_name).unwrap();
+        catalog
+            .apply_catalog_batch(&CatalogBatch {
+                database_id: db_id,
+                database_name: db_name.into(),
+                time_ns: 0,
+                ops: vec![CatalogOp::CreateTable(TableDefinition {
+                    database_id: db_id,
+                    database_name: db_name.into(),
+                    table_name: "pending".into(),
+                    table_id: TableId::new(),
+                    field_definitions: vec![],
+                    key: vec![],
+                })],
+            })
+            .unwrap();
+
+        let err = query_executor
+            .query(
+                db_name,
+                "SELECT * FROM pending",
+                None,
+                QueryKind::Sql,
+                None,
+                None,
+            )
+            .await
+            .unwrap_err();
+        assert!(
+            matches!(
+                &err,
+                QueryExecutorError::TableNotReady { table } if table == "pending"
+            ),
+            "unexpected error: {err}"
+        );
+    }
+
+    #[test_log::test(tokio::test)]
+    async fn storage_hints() {
+        let (write_buffer, query_executor, time_provider) = setup().await;
+        let db_name = "test_db";
+        // perform writes over time so that some of the data is persisted, while the most recent
+        // write remains in the buffer:
+        for i in 0..10 {
+            let time = i * 10;
+            write_buffer
+                .write_lp(
+                    NamespaceName::new(db_name).unwrap(),
+                    "cpu,host=a,region=us-east usage=250",
+                    Time::from_timestamp_nanos(time),
+                    false,
+                    influxdb3_write::Precision::Nanosecond,
+                )
+                .await
+                .unwrap();
+
+            time_provider.set(Time::from_timestamp(time + 1, 0).unwrap());
+        }
+        time_provider.set(Time::from_timestamp(20, 0).unwrap());
+        tokio::time::sleep(Duration::from_millis(500)).await;
+
+        struct TestCase<'a> {
+            storage: StorageHint,
+            expected: &'a [&'a str],
+        }
+
+        let test_cases = [
+            TestCase {
+                storage: StorageHint::Both,
+                expected: &[
+                    "+----------+",
+                    "| count(*) |",
+                    "+----------+",
+                    "| 10       |",
+                    "+----------+",
+                ],
+            },
+            TestCase {
+                storage: StorageHint::ObjectStore,
+                expected: &[
+                    "+----------+",
+                    "| count(*) |",
+                    "+----------+",
+                    "| 9        |",
+                    "+----------+",
+                ],
+            },
+            TestCase {
+                storage: StorageHint::ReadBuffer,
+                expected: &[
+                    "+----------+",
+                    "| count(*) |",
+                    "+----------+",
+                    "| 1        |",
+                    "+----------+",
+                ],
+            },
+        ];
+
+        for t in test_cases {
+            let options = QueryOptions {
+                storage: t.storage,
+                ..Default::default()
+            };
+            let batches: Vec<RecordBatch> = query_executor
+                .query_with_options(
+                    db_name,
+                    "SELECT COUNT(*) FROM cpu",
+                    None,
+                    QueryKind::Sql,
+                    options,
+                    None,
+                    None,
+                )
+                .await
+                .unwrap()
+                .try_collect()
+                .await
+                .unwrap();
+            assert_batches_sorted_eq!(t.expected, &batches);
+        }
+    }
+
+    #[test_log::test(tokio::test)]
+    async fn chunk_order_hints() {
+        let (write_buffer, query_executor, time_provider) = setup().await;
+        let db_name = "test_db";
+        // overwrite the same row over time, so that the earlier writes are persisted, while the
+        // most recent write remains in the buffer:
+        for i in 0..10 {
+            let time = i * 10;
+            write_buffer
+                .write_lp(
+                    NamespaceName::new(db_name).unwrap(),
+                    format!("cpu,host=a usage={i} 5").as_str(),
+                    Time::from_timestamp_nanos(time),
+                    false,
+                    influxdb3_write::Precision::Nanosecond,
+                )
+                .await
+                .unwrap();
+
+            time_provider.set(Time::from_timestamp(time + 1, 0).unwrap());
+        }
+        time_provider.set(Time::from_timestamp(20, 0).unwrap());
+        tokio::time::sleep(Duration::from_millis(500)).await;
+
+        let usage = |chunk_order: ChunkOrderHint| {
+            let query_executor = &query_executor;
+            async move {
+                let options = QueryOptions {
+                    chunk_order,
+                    ..Default::default()
+                };
+                let batches: Vec<RecordBatch> = query_executor
+                    .query_with_options(
+                        db_name,
+                        "SELECT usage FROM cpu",
+                        None,
+                        QueryKind::Sql,
+                        options,
+                        None,
+                        None,
+                    )
+                    .await
+                    .unwrap()
+                    .try_collect()
+                    .await
+                    .unwrap();
+                let rows = batches
+                    .iter()
+                    .filter(|batch| batch.num_rows() > 0)
+                    .collect::<Vec<_>>();
+                assert_eq!(1, rows.len(), "duplicate rows were not deduplicated");
+                assert_eq!(
+                    1,
+                    rows[0].num_rows(),
+                    "duplicate rows were not deduplicated"
+                );
+                rows[0].column(0).as_primitive::<Float64Type>().value(0)
+            }
+        };
+
+        // the most recent write, which is held in the buffer, wins:
+        assert_eq!(9.0, usage(ChunkOrderHint::Stored).await);
+        assert_eq!(9.0, usage(ChunkOrderHint::Sequence).await);
+        // the chunks all hold the same time, so they are left in the order they are stored:
+        assert_eq!(9.0, usage(ChunkOrderHint::MinTime).await);
+        // a persisted, earlier, write wins:
+        let reversed = usage(ChunkOrderHint::Reversed).await;
+        assert!(reversed < 9.0, "unexpected value {reversed}");
+    }
+
+    #[test_log::test(tokio::test)]
+    async fn dictionary_stats_in_query_log() {
+        let (write_buffer, query_executor, _) = setup().await;
+        let db_name = "test_db";
+        write_buffer
+            .write_lp(
+                NamespaceName::new(db_name).unwrap(),
+                "\
+                cpu,host=a usage=1 1\n\
+                cpu,host=b usage=2 2\n\
+                cpu,host=c usage=3 3\n\
+                cpu,host=a usage=4 4\n\
+                ",
+                Time::from_timestamp_nanos(0),
+                false,
+                influxdb3_write::Precision::Nanosecond,
+            )
+            .await
+            .unwrap();
+
+        let options = QueryOptions {
+            dictionary_stats: true,
+            ..Default::default()
+        };
+        let _: Vec<RecordBatch> = query_executor
+            .query_with_options(
+                db_name,
+                "SELECT host, usage FROM cpu",
+                None,
+                QueryKind::Sql,
+                options,
+                None,
+                None,
+            )
+            .await
+            .unwrap()
+            .try_collect()
+            .await
+            .unwrap();
+
+        let id = query_executor
+            .query_log
+            .entries()
+            .entries
+            .iter()
+            .next_back()
+            .map(|e| e.state().id.to_string())
+            .unwrap();
+        let dictionary_stats = query_executor
+            .query_log_stats
+            .get(&id)
+            .and_then(|s| s.dictionary_stats)
+            .expect("dictionary stats should be recorded");
+        let host = dictionary_stats
+            .iter()
+            .find(|s| s.column == "host")
+            .expect("host column should be dictionary encoded");
+        assert_eq!(3, host.distinct_values);
+        assert_eq!(4, host.value_count);
+    }
+
+    #[test_log::test(tokio::test)]
+    async fn query_constants() {
+        let (write_buffer, query_executor, _) = setup().await;
+        let db_name = "test_db";
+        write_buffer
+            .write_lp(
+                NamespaceName::new(db_name).unwrap(),
+                "\
+                cpu,host=a usage=1 1\n\
+                cpu,host=b usage=2 2\n\
+                cpu,host=c usage=3 3\n\
+                ",
+                Time::from_timestamp_nanos(0),
+                false,
+                influxdb3_write::Precision::Nanosecond,
+            )
+            .await
+            .unwrap();
+
+        // the constant is used in both the projection and the filter:
+        let options = QueryOptions {
+            constants: HashMap::from([("threshold".to_string(), ScalarValue::Float64(Some(2.0)))]),
+            ..Default::default()
+        };
+        let batches: Vec<RecordBatch> = query_executor
+            .query_with_options(
+                db_name,
+                "SELECT host, usage * threshold() AS scaled FROM cpu WHERE usage >= threshold()",
+                None,
+                QueryKind::Sql,
+                options,
+                None,
+                None,
+            )
+            .await
+            .unwrap()
+            .try_collect()
+            .await
+            .unwrap();
+        assert_batches_sorted_eq!(
+            [
+                "+------+--------+",
+                "| host | scaled |",
+                "+------+--------+",
+                "| b    | 4.0    |",
+                "| c    | 6.0    |",
+                "+------+--------+",
+            ],
+            &batches
+        );
+    }
+
+    #[test_log::test(tokio::test)]
+    async fn subscribe_query_log() {
+        let (write_buffer, query_executor, _) = setup().await;
+        let db_name = "test_db";
+        write_buffer
+            .write_lp(
+                NamespaceName::new(db_name).unwrap(),
+                "cpu,host=a usage=1 1",
+                Time::from_timestamp_nanos(0),
+                false,
+                influxdb3_write::Precision::Nanosecond,
+            )
+            .await
+            .unwrap();
+
+        let mut subscription = pin!(query_executor.subscribe_query_log());
+        let query = "SELECT host, usage FROM cpu";
+        let _: Vec<RecordBatch> = query_executor
+            .query(db_name, query, None, QueryKind::Sql, None, None)
+            .await
+            .unwrap()
+            .try_collect()
+            .await
+            .unwrap();
+
+        let entry = tokio::time::timeout(Duration::from_secs(1), subscription.next())
+            .await
+            .expect("subscriber should receive the completed query")
+            .unwrap();
+        assert_eq!(query, entry.query_text.to_string());
+        assert!(entry.success);
+    }
+
+    #[test_log::test(tokio::test)]
+    async fn query_file() {
+        let (write_buffer, mut query_executor, time_provider) = setup().await;
+        let db_name = "test_db";
+        // perform writes over time so that the data is persisted to several files:
+        for i in 0..10 {
+            let time = i * 10;
+            write_buffer
+                .write_lp(
+                    NamespaceName::new(db_name).unwrap(),
+                    "\
+                    cpu,host=a usage=1\n\
+                    cpu,host=b usage=2\n\
+                    ",
+                    Time::from_timestamp_nanos(time),
+                    false,
+                    influxdb3_write::Precision::Nanosecond,
+                )
+                .await
+                .unwrap();
+
+            time_provider.set(Time::from_timestamp(time + 1, 0).unwrap());
+        }
+        time_provider.set(Time::from_timestamp(20, 0).unwrap());
+        tokio::time::sleep(Duration::from_millis(500)).await;
+
+        let db_schema = write_buffer.catalog().db_schema(db_name).unwrap();
+        let table_id = db_schema.table_name_to_id("cpu").unwrap();
+        let files = write_buffer.parquet_files(db_schema.id, table_id);
+        assert!(files.len() > 1, "expected several persisted files");
+        let file = &files[0];
+
+        let batches: Vec<RecordBatch> = query_executor
+            .query_file(db_name, "cpu", file.id, "SELECT COUNT(*) AS n FROM cpu")
+            .await
+            .unwrap()
+            .try_collect()
+            .await
+            .unwrap();
+        let n = batches[0].column(0).as_primitive::<Int64Type>().value(0);
+        assert_eq!(file.row_count, n as u64);
+
+        let error = query_executor
+            .query_file(
+                db_name,
+                "cpu",
+                ParquetFileId::from(u64::MAX),
+                "SELECT * FROM cpu",
+            )
+            .await
+            .map(|_| ())
+            .unwrap_err();
+        assert!(
+            matches!(error, QueryExecutorError::ParquetFileNotFound { .. }),
+            "unexpected error: {error}"
+        );
+
+        // the query holds a permit from the query execution semaphore until its results are
+        // dropped:
+        let semaphore_metrics = Arc::new(AsyncSemaphoreMetrics::new(
+            &Registry::new(),
+            &[("semaphore", "query_execution")],
+        ));
+        query_executor.query_execution_semaphore = Arc::new(semaphore_metrics.new_semaphore(1));
+        let query = || query_executor.query_file(db_name, "cpu", file.id, "SELECT * FROM cpu");
+        let first = query().await.unwrap();
+        let mut second = Box::pin(query());
+        assert!(
+            tokio::time::timeout(Duration::from_millis(200), &mut second)
+                .await
+                .is_err(),
+            "query was executed beyond the concurrency limit"
+        );
+        drop(first);
+        tokio::time::timeout(Duration::from_secs(10), second)
+            .await
+            .expect("query was not executed once a permit was released")
+            .unwrap();
+    }
+
+    #[test_log::test(tokio::test)]
+    async fn system_catalog_table() {
+        let (write_buffer, query_executor, _) = setup().await;
+        for (db_name, lp) in [
+            ("foo", "cpu,host=a usage=1 1\nmem,host=a used=2 1\n"),
+            ("bar", "disk,host=a free=3 1\n"),
+        ] {
+            write_buffer
+                .write_lp(
+                    NamespaceName::new(db_name).unwrap(),
+                    lp,
+                    Time::from_timestamp_nanos(0),
+                    false,
+                    influxdb3_write::Precision::Nanosecond,
+                )
+                .await
+                .unwrap();
+        }
+
+        // the catalog table lists all databases, whichever database it is queried from:
+        for db_name in ["foo", "bar"] {
+            let batches: Vec<RecordBatch> = query_executor
+                .query(
+                    db_name,
+                    "SELECT database_name, deleted, table_count, retention_period_ns \
+                    FROM system.catalog",
+                    None,
+                    QueryKind::Sql,
+                    None,
+                    None,
+                )
+                .await
+                .unwrap()
+                .try_collect()
+                .await
+                .unwrap();
+            assert_batches_sorted_eq!(
+                [
+                    "+---------------+---------+-------------+---------------------+",
+                    "| database_name | deleted | table_count | retention_period_ns |",
+                    "+---------------+---------+-------------+---------------------+",
+                    "| bar           | false   | 1           |                     |",
+                    "| foo           | false   | 2           |                     |",
+                    "+---------------+---------+-------------+---------------------+",
+                ],
+                &batches
+            );
+        }
+    }
+
+    #[test_log::test(tokio::test)]
+    async fn system_events_table() {
+        #[derive(Debug)]
+        #[allow(dead_code)]
+        struct SnapshotPersisted {
+            file_count: u64,
+        }
+
+        let (write_buffer, query_executor, time_provider) = setup().await;
+        write_buffer
+            .write_lp(
+                NamespaceName::new("foo").unwrap(),
+                "cpu,host=a usage=1 1\n",
+                Time::from_timestamp_nanos(0),
+                false,
+                influxdb3_write::Precision::Nanosecond,
+            )
+            .await
+            .unwrap();
+        time_provider.set(Time::from_timestamp_nanos(1_000));
+        query_executor
+            .sys_events_store
+            .record(SnapshotPersisted { file_count: 3 });
+
+        let batches: Vec<RecordBatch> = query_executor
+            .query(
+                "foo",
+                "SELECT event_time, event_type, detail FROM system.events",
+                None,
+                QueryKind::Sql,
+                None,
+                None,
+            )
+            .await
+            .unwrap()
+            .try_collect()
+            .await
+            .unwrap();
+        assert_batches_sorted_eq!(
+            [
+                "+----------------------------+-------------------+-------------------------------------+",
+                "| event_time                 | event_type        | detail                              |",
+                "+----------------------------+-------------------+-------------------------------------+",
+                "| 1970-01-01T00:00:00.000001 | SnapshotPersisted | SnapshotPersisted { file_count: 3 } |",
+                "+----------------------------+-------------------+-------------------------------------+",
+            ],
+            &batches
+        );
+    }
+
+    #[test_log::test(tokio::test)]
+    async fn failed_and_slow_query_events() {
+        let (write_buffer, mut query_executor, _) = setup().await;
+        let db_name = "test_db";
+        write_buffer
+            .write_lp(
+                NamespaceName::new(db_name).unwrap(),
+                "cpu,host=a usage=1 1",
+                Time::from_timestamp_nanos(0),
+                false,
+                influxdb3_write::Precision::Nanosecond,
+            )
+            .await
+            .unwrap();
+        query_executor.slow_query_threshold = Some(Duration::from_millis(100));
+        let events = Arc::clone(&query_executor.sys_events_store);
+
+        // a query that fails to plan:
+        query_executor
+            .query(
+                db_name,
+                "SELECT * FROM does_not_exist",
+                None,
+                QueryKind::Sql,
+                None,
+                None,
+            )
+            .await
+            .unwrap_err();
+        let failed = events.as_vec::<QueryFailed>();
+        assert_eq!(1, failed.len());
+        assert_eq!(db_name, failed[0].data.database);
+        assert_eq!("SELECT * FROM does_not_exist", failed[0].data.query_text);
+        assert_eq!("planning", failed[0].data.category);
+
+        // a query whose results are read quickly is not slow:
+        let query = || {
+            query_executor.query(
+                db_name,
+                "SELECT * FROM cpu",
+                None,
+                QueryKind::Sql,
+                None,
+                None,
+            )
+        };
+        let _: Vec<RecordBatch> = query().await.unwrap().try_collect().await.unwrap();
+        assert!(events.as_vec::<SlowQuery>().is_empty());
+
+        // while one whose results are read slowly is:
+        let results = query().await.unwrap();
+        tokio::time::sleep(Duration::from_millis(200)).await;
+        let _: Vec<RecordBatch> = results.try_collect().await.unwrap();
+        let slow = events.as_vec::<SlowQuery>();
+        assert_eq!(1, slow.len());
+        assert_eq!("SELECT * FROM cpu", slow[0].data.query_text);
+        assert!(slow[0].data.duration >= Duration::from_millis(200));
+
+        // and neither records a failure:
+        assert_eq!(1, events.as_vec::<QueryFailed>().len());
+    }
+
+    #[test_log::test(tokio::test)]
+    async fn system_table_summary() {
+        let (write_buffer, query_executor, time_provider) = setup().await;
+        let db_name = "test_db";
+        // write over time for several files to be persisted for each table:
+        for i in 0..10 {
+            let time = i * 10;
+            write_buffer
+                .write_lp(
+                    NamespaceName::new(db_name).unwrap(),
+                    "\
+                    cpu,host=a,region=us-east usage=250\n\
+                    mem,host=a,region=us-east usage=150000\n\
+                    ",
+                    Time::from_timestamp_nanos(time),
+                    false,
+                    influxdb3_write::Precision::Nanosecond,
+                )
+                .await
+                .unwrap();
+            time_provider.set(Time::from_timestamp(time + 1, 0).unwrap());
+        }
+        time_provider.set(Time::from_timestamp(20, 0).unwrap());
+        tokio::time::sleep(Duration::from_millis(500)).await;
+
+        let batches: Vec<RecordBatch> = query_executor
+            .query(
+                db_name,
+                "SELECT table_name, file_count, size_bytes, row_count, min_time, max_time \
+                FROM system.table_summary",
+                None,
+                QueryKind::Sql,
+                None,
+                None,
+            )
+            .await
+            .unwrap()
+            .try_collect()
+            .await
+            .unwrap();
+        let batch = concat_batches(&batches[0].schema(), &batches).unwrap();
+        assert_eq!(2, batch.num_rows());
+
+        // the summary of each table matches the files listed for it individually:
+        let db_schema = write_buffer.catalog().db_schema(db_name).unwrap();
+        for (row, table_name) in ["cpu", "mem"].into_iter().enumerate() {
+            assert_eq!(table_name, batch.column(0).as_string::<i32>().value(row));
+            let table_id = db_schema.table_name_to_id(table_name).unwrap();
+            let files = write_buffer.parquet_files(db_schema.id, table_id);
+            assert!(files.len() > 1, "expected several files for {table_name}");
+
+            let column = |i: usize| batch.column(i).as_primitive::<UInt64Type>().value(row);
+            assert_eq!(files.len() as u64, column(1));
+            assert_eq!(files.iter().map(|f| f.size_bytes).sum::<u64>(), column(2));
+            assert_eq!(files.iter().map(|f| f.row_count).sum::<u64>(), column(3));
+            let time = |i: usize| batch.column(i).as_primitive::<Int64Type>().value(row);
+            assert_eq!(files.iter().map(|f| f.min_time).min().unwrap(), time(4));
+            assert_eq!(files.iter().map(|f| f.max_time).max().unwrap(), time(5));
+        }
+    }
+
+    #[test_log::test(tokio::test)]
+    async fn system_chunks() {
+        let (write_buffer, query_executor, time_provider) = setup().await;
+        let db_name = "test_db";
+        // write over time for several files to be persisted:
+        for i in 0..10 {
+            let time = i * 10;
+            write_buffer
+                .write_lp(
+                    NamespaceName::new(db_name).unwrap(),
+                    "\
+                    cpu,host=a,region=us-east usage=250\n\
+                    mem,host=a,region=us-east usage=150000\n\
+                    ",
+                    Time::from_timestamp_nanos(time),
+                    false,
+                    influxdb3_write::Precision::Nanosecond,
+                )
+                .await
+                .unwrap();
+            time_provider.set(Time::from_timestamp(time + 1, 0).unwrap());
+        }
+        time_provider.set(Time::from_timestamp(20, 0).unwrap());
+        tokio::time::sleep(Duration::from_millis(500)).await;
+        // and one more that is left in the buffer:
+        write_buffer
+            .write_lp(
+                NamespaceName::new(db_name).unwrap(),
+                "cpu,host=a,region=us-east usage=250",
+                Time::from_timestamp(20, 0).unwrap(),
+                false,
+                influxdb3_write::Precision::Nanosecond,
+            )
+            .await
+            .unwrap();
+
+        let batches: Vec<RecordBatch> = query_executor
+            .query(
+                db_name,
+                "SELECT chunk_id, storage, row_count, memory_bytes \
+                FROM system.chunks \
+                WHERE table_name = 'cpu'",
+                None,
+                QueryKind::Sql,
+                None,
+                None,
+            )
+            .await
+            .unwrap()
+            .try_collect()
+            .await
+            .unwrap();
+        let batch = concat_batches(&batches[0].schema(), &batches).unwrap();
+        let chunk_ids = batch.column(0).as_primitive::<UInt64Type>();
+        let storage = batch.column(1).as_string::<i32>();
+        let row_counts = batch.column(2).as_primitive::<UInt64Type>();
+        let memory_bytes = batch.column(3).as_primitive::<UInt64Type>();
+
+        // the persisted chunks are those listed as parquet files, which are not held in memory:
+        let db_schema = write_buffer.catalog().db_schema(db_name).unwrap();
+        let table_id = db_schema.table_name_to_id("cpu").unwrap();
+        let files = write_buffer.parquet_files(db_schema.id, table_id);
+        assert!(files.len() > 1, "expected several files");
+        let mut persisted = (0..batch.num_rows())
+            .filter(|&row| storage.value(row) == "object_store")
+            .inspect(|&row| assert!(memory_bytes.is_null(row)))
+            .map(|row| chunk_ids.value(row))
+            .collect::<Vec<_>>();
+        persisted.sort_unstable();
+        let mut file_ids = files.iter().map(|f| f.id.as_u64()).collect::<Vec<_>>();
+        file_ids.sort_unstable();
+        assert_eq!(file_ids, persisted);
+
+        // while the buffered chunks have no id, and are held in memory:
+        let buffered = (0..batch.num_rows())
+            .filter(|&row| storage.value(row) == "mutable")
+            .inspect(|&row| {
+                assert!(chunk_ids.is_null(row));
+                assert!(memory_bytes.value(row) > 0);
+            })
+            .count();
+        assert!(buffered > 0, "expected a buffered chunk");
+
+        // and together they hold every row written to the table:
+        assert_eq!(11, row_counts.values().iter().sum::<u64>());
+    }
+
+    #[test_log::test(tokio::test)]
+    async fn parquet_cache_function() {
+        let (write_buffer, query_executor, time_provider) = setup().await;
+        let db_name = "test_db";
+        // write over time for several files to be persisted, and cached:
+        for i in 0..10 {
+            let time = i * 10;
+            write_buffer
+                .write_lp(
+                    NamespaceName::new(db_name).unwrap(),
+                    "cpu,host=a,region=us-east usage=250",
+                    Time::from_timestamp_nanos(time),
+                    false,
+                    influxdb3_write::Precision::Nanosecond,
+                )
+                .await
+                .unwrap();
+            time_provider.set(Time::from_timestamp(time + 1, 0).unwrap());
+        }
+        time_provider.set(Time::from_timestamp(20, 0).unwrap());
+        tokio::time::sleep(Duration::from_millis(500)).await;
+
+        let query = |sql: &'static str| {
+            let query_executor = &query_executor;
+            async move {
+                let batches: Vec<RecordBatch> = query_executor
+                    .query(db_name, sql, None, QueryKind::Sql, None, None)
+                    .await
+                    .unwrap()
+                    .try_collect()
+                    .await
+                    .unwrap();
+                batches
+            }
+        };
+        // the cached files have not been read yet:
+        let batches = query("SELECT path, hit_count FROM parquet_cache()").await;
+        let batch = concat_batches(&batches[0].schema(), &batches).unwrap();
+        let db_schema = write_buffer.catalog().db_schema(db_name).unwrap();
+        let table_id = db_schema.table_name_to_id("cpu").unwrap();
+        let mut paths = write_buffer
+            .parquet_files(db_schema.id, table_id)
+            .into_iter()
+            .map(|file| file.path)
+            .collect::<Vec<_>>();
+        paths.sort_unstable();
+        assert!(!paths.is_empty(), "expected persisted files");
+        let cached = batch
+            .column(0)
+            .as_string::<i32>()
+            .iter()
+            .map(|path| path.unwrap().to_string())
+            .collect::<Vec<_>>();
+        assert_eq!(paths, cached);
+        assert!(batch
+            .column(1)
+            .as_primitive::<UInt64Type>()
+            .values()
+            .iter()
+            .all(|hits| *hits == 0));
+
+        // until a query reads them from the cache:
+        query("SELECT * FROM cpu").await;
+        let batches = query(
+            "SELECT size_bytes, last_access, hit_count \
+            FROM parquet_cache() ORDER BY hit_count DESC",
+        )
+        .await;
+        let batch = concat_batches(&batches[0].schema(), &batches).unwrap();
+        assert_eq!(paths.len(), batch.num_rows());
+        assert!(batch
+            .column(0)
+            .as_primitive::<UInt64Type>()
+            .values()
+            .iter()
+            .all(|size| *size > 0));
+        assert!(batch.column(2).as_primitive::<UInt64Type>().value(0) > 0);
+    }
+
+    #[test_log::test(tokio::test)]
+    async fn storage_request_budget() {
+        let (write_buffer, mut query_executor, time_provider) = setup().await;
+        let db_name = "test_db";
+        // write over time for several files to be persisted:
+        for i in 0..10 {
+            let time = i * 10;
+            write_buffer
+                .write_lp(
+                    NamespaceName::new(db_name).unwrap(),
+                    "cpu,host=a,region=us-east usage=250",
+                    Time::from_timestamp_nanos(time),
+                    false,
+                    influxdb3_write::Precision::Nanosecond,
+                )
+                .await
+                .unwrap();
+            time_provider.set(Time::from_timestamp(time + 1, 0).unwrap());
+        }
+        time_provider.set(Time::from_timestamp(20, 0).unwrap());
+        tokio::time::sleep(Duration::from_millis(500)).await;
+        let db_schema = write_buffer.catalog().db_schema(db_name).unwrap();
+        let table_id = db_schema.table_name_to_id("cpu").unwrap();
+        let files = write_buffer.parquet_files(db_schema.id, table_id).len();
+        assert!(files > 2, "expected several files, got {files}");
+
+        let count = |query_executor: &QueryExecutorImpl, max_storage_requests: Option<usize>| {
+            let query_executor = query_executor.clone();
+            async move {
+                let options = QueryOptions {
+                    max_storage_requests,
+                    ..Default::default()
+                };
+                let batches: Vec<RecordBatch> = query_executor
+                    .query_with_options(
+                        db_name,
+                        "SELECT count(*) FROM cpu",
+                        None,
+                        QueryKind::Sql,
+                        options,
+                        None,
+                        None,
+                    )
+                    .await?
+                    .try_collect()
+                    .await
+                    .unwrap();
+                Ok::<_, QueryExecutorError>(
+                    batches[0].column(0).as_primitive::<Int64Type>().value(0),
+                )
+            }
+        };
+
+        // the query fetches a file at a time, so is rejected by a budget of fewer requests:
+        query_executor.max_storage_requests = Some(2);
+        let error = count(&query_executor, None).await.unwrap_err();
+        assert!(
+            matches!(
+                error,
+                QueryExecutorError::StorageRequestBudgetExceeded { count, max: 2 }
+                    if count == files
+            ),
+            "unexpected error: {error}"
+        );
+
+        // unless the budget is raised for the query:
+        assert_eq!(10, count(&query_executor, Some(files)).await.unwrap());
+
+        // which may also lower it:
+        query_executor.max_storage_requests = None;
+        assert_eq!(10, count(&query_executor, None).await.unwrap());
+        let error = count(&query_executor, Some(1)).await.unwrap_err();
+        assert!(
+            matches!(
+                error,
+                QueryExecutorError::StorageRequestBudgetExceeded { max: 1, .. }
+            ),
+            "unexpected error: {error}"
+        );
+    }
+
+    #[test_log::test(tokio::test)]
+    async fn row_ids() {
+        let (write_buffer, query_executor, _) = setup().await;
+        let db_name = "test_db";
+        write_buffer
+            .write_lp(
+                NamespaceName::new(db_name).unwrap(),
+                "\
+                cpu,host=a usage=1 1\n\
+                cpu,host=a usage=2 2\n\
+                cpu,host=b usage=3 3\n\
+                cpu,host=c usage=4 4\n\
+                cpu,host=c usage=5 5\n\
+                cpu,host=c usage=6 6\n\
+                ",
+                Time::from_timestamp_nanos(0),
+                false,
+                influxdb3_write::Precision::Nanosecond,
+            )
+            .await
+            .unwrap();
+
+        let options = QueryOptions {
+            row_ids: true,
+            ..Default::default()
+        };
+        // each branch of the union outputs its own batches:
+        let batches: Vec<RecordBatch> = query_executor
+            .query_with_options(
+                db_name,
+                "SELECT host, usage FROM cpu WHERE host = 'a' \
+                UNION ALL SELECT host, usage FROM cpu WHERE host = 'b' \
+                UNION ALL SELECT host, usage FROM cpu WHERE host = 'c'",
+                None,
+                QueryKind::Sql,
+                options,
+                None,
+                None,
+            )
+            .await
+            .unwrap()
+            .try_collect()
+            .await
+            .unwrap();
+        let batches = batches
+            .into_iter()
+            .filter(|batch| batch.num_rows() > 0)
+            .collect::<Vec<_>>();
+        assert!(batches.len() > 1, "expected several batches");
+
+        let mut ids = vec![];
+        for batch in &batches {
+            let schema = batch.schema();
+            let (index, field) = schema.column_with_name(ROW_ID_COLUMN_NAME).unwrap();
+            assert_eq!(index, batch.num_columns() - 1);
+            assert_eq!(field.data_type(), &DataType::UInt64);
+            assert!(!field.is_nullable());
+            ids.extend(
+                batch
+                    .column(index)
+                    .as_primitive::<UInt64Type>()
+                    .values()
+                    .iter(),
+            );
+        }
+        assert_eq!(ids, (0..6).collect::<Vec<u64>>());
+
+        // the column is only added when asked for:
+        let batches: Vec<RecordBatch> = query_executor
+            .query(
+                db_name,
+                "SELECT host, usage FROM cpu",
+                None,
+                QueryKind::Sql,
+                None,
+                None,
+            )
+            .await
+            .unwrap()
+            .try_collect()
+            .await
+            .unwrap();
+        assert!(batches[0]
+            .schema()
+            .column_with_name(ROW_ID_COLUMN_NAME)
+            .is_none());
+    }
+
+    #[test_log::test(tokio::test)]
+    async fn plan_complexity_limit() {
+        let (write_buffer, mut query_executor, _) = setup().await;
+        let db_name = "test_db";
+        write_buffer
+            .write_lp(
+                NamespaceName::new(db_name).unwrap(),
+                "cpu,host=a usage=1 1",
+                Time::from_timestamp_nanos(0),
+                false,
+                influxdb3_write::Precision::Nanosecond,
+            )
+            .await
+            .unwrap();
+        query_executor.max_plan_nodes = Some(20);
+
+        let query = |sql: String| {
+            let query_executor = query_executor.clone();
+            async move {
+                query_executor
+                    .query(db_name, &sql, None, QueryKind::Sql, None, None)
+                    .await?
+                    .try_collect::<Vec<RecordBatch>>()
+                    .await
+                    .map_err(QueryExecutorError::ExecuteStream)
+            }
+        };
+
+        // a normal query is planned:
+        let batches = query("SELECT usage FROM cpu WHERE host = 'a'".to_string())
+            .await
+            .unwrap();
+        assert_batches_eq!(
+            [
+                "+-------+",
+                "| usage |",
+                "+-------+",
+                "| 1.0   |",
+                "+-------+",
+            ],
+            &batches
+        );
+
+        // while one nesting many subqueries is rejected:
+        let nested = (0..12).fold("SELECT usage FROM cpu".to_string(), |sql, _| {
+            format!("SELECT usage FROM ({sql})")
+        });
+        let error = query(nested).await.unwrap_err();
+        assert!(
+            matches!(
+                error,
+                QueryExecutorError::PlanTooComplex { nodes, max: 20 } if nodes > 20
+            ),
+            "unexpected error: {error}"
+        );
+    }
+
+    #[test_log::test(tokio::test)]
+    async fn duplicate_output_columns() {
+        let (write_buffer, query_executor, _) = setup().await;
+        let db_name = "test_db";
+        write_buffer
+            .write_lp(
+                NamespaceName::new(db_name).unwrap(),
+                "\
+                cpu,host=a usage=1 1\n\
+                mem,host=b usage=2 1\n\
+                ",
+                Time::from_timestamp_nanos(0),
+                false,
+                influxdb3_write::Precision::Nanosecond,
+            )
+            .await
+            .unwrap();
+
+        let query = |duplicate_columns: DuplicateColumnPolicy| {
+            let query_executor = query_executor.clone();
+            async move {
+                let options = QueryOptions {
+                    duplicate_columns,
+                    ..Default::default()
+                };
+                query_executor
+                    .query_with_options(
+                        db_name,
+                        "SELECT cpu.host, mem.host, cpu.usage AS host_1 \
+                        FROM cpu JOIN mem ON cpu.time = mem.time",
+                        None,
+                        QueryKind::Sql,
+                        options,
+                        None,
+                        None,
+                    )
+                    .await?
+                    .try_collect::<Vec<RecordBatch>>()
+                    .await
+                    .map_err(QueryExecutorError::ExecuteStream)
+            }
+        };
+
+        // the columns of both tables are output under the same name by default:
+        let batches = query(DuplicateColumnPolicy::Allow).await.unwrap();
+        let names = batches[0]
+            .schema()
+            .fields()
+            .iter()
+            .map(|f| f.name().clone())
+            .collect::<Vec<_>>();
+        assert_eq!(names, ["host", "host", "host_1"]);
+
+        // or are renamed to suffixes that are not already taken:
+        assert_batches_eq!(
+            [
+                "+------+--------+--------+",
+                "| host | host_2 | host_1 |",
+                "+------+--------+--------+",
+                "| a    | b      | 1.0    |",
+                "+------+--------+--------+",
+            ],
+            &query(DuplicateColumnPolicy::Rename).await.unwrap()
+        );
+
+        // or fail the query:
+        let error = query(DuplicateColumnPolicy::Reject).await.unwrap_err();
+        assert!(
+            matches!(
+                &error,
+                QueryExecutorError::DuplicateOutputColumn { name } if name == "host"
+            ),
+            "unexpected error: {error}"
+        );
+    }
+
+    #[test_log::test(tokio::test)]
+    async fn resource_budget() {
+        let (write_buffer, mut query_executor, _) = setup().await;
+        let db_name = "test_db";
+        let lp = (0..1_000)
+            .map(|i| format!("cpu,host=h{} usage={i} {i}", i % 10))
+            .collect::<Vec<_>>()
+            .join("\n");
+        write_buffer
+            .write_lp(
+                NamespaceName::new(db_name).unwrap(),
+                &lp,
+                Time::from_timestamp_nanos(0),
+                false,
+                influxdb3_write::Precision::Nanosecond,
+            )
+            .await
+            .unwrap();
+
+        let query = |query_executor: &QueryExecutorImpl| {
+            let query_executor = query_executor.clone();
+            async move {
+                query_executor
+                    .query(
+                        db_name,
+                        "SELECT host, sum(usage * usage) FROM cpu GROUP BY host ORDER BY host",
+                        None,
+                        QueryKind::Sql,
+                        None,
+                        None,
+                    )
+                    .await
+                    .unwrap()
+                    .try_collect::<Vec<RecordBatch>>()
+                    .await
+            }
+        };
+
+        // the query fits within a generous budget:
+        query_executor.max_query_cost = Some(u64::MAX);
+        let batches = query(&query_executor).await.unwrap();
+        assert_eq!(10, batches.iter().map(|b| b.num_rows()).sum::<usize>());
+
+        // but is cancelled part way through by a tiny one:
+        query_executor.max_query_cost = Some(1_000);
+        let error = query(&query_executor).await.unwrap_err();
+        let DataFusionError::External(error) = error.find_root() else {
+            panic!("unexpected error: {error}");
+        };
+        assert!(
+            matches!(
+                error.downcast_ref(),
+                Some(QueryExecutorError::ResourceBudgetExceeded { used, max: 1_000 })
+                    if *used > 1_000
+            ),
+            "unexpected error: {error}"
+        );
+    }
+
+    #[test_log::test(tokio::test)]
+    async fn influxql_v1_compat_series_metadata() {
+        let (write_buffer, query_executor, _) = setup().await;
+        let db_name = "test_db";
+        write_buffer
+            .write_lp(
+                NamespaceName::new(db_name).unwrap(),
+                "\
+                cpu,host=a,region=us usage=1 1\n\
+                cpu,host=b,region=us usage=2 1\n\
+                ",
+                Time::from_timestamp_nanos(0),
+                false,
+                influxdb3_write::Precision::Nanosecond,
+            )
+            .await
+            .unwrap();
+
+        let query = "SELECT usage, region FROM cpu GROUP BY host";
+        let stream = query_executor
+            .query(
+                db_name,
+                query,
+                None,
+                QueryKind::InfluxQlV1Compat,
+                None,
+                None,
+            )
+            .await
+            .unwrap();
+        let schema = stream.schema();
+        let series: SeriesMetadata =
+            serde_json::from_str(schema.metadata().get(SERIES_METADATA_KEY).unwrap()).unwrap();
+        assert_eq!(
+            series,
+            SeriesMetadata {
+                measurement_column: "iox::measurement".to_string(),
+                tags: vec![SeriesTag {
+                    key: "host".to_string(),
+                    column: "host".to_string(),
+                }],
+                columns: vec![
+                    "time".to_string(),
+                    "usage".to_string(),
+                    "region".to_string()
+                ],
+            }
+        );
+        // the rows are left as they are, and carry the metadata:
+        let batches: Vec<RecordBatch> = stream.try_collect().await.unwrap();
+        assert_eq!(2, batches.iter().map(|b| b.num_rows()).sum::<usize>());
+        assert!(batches.iter().all(|b| b.schema() == schema));
+
+        // while the results of the same query as plain InfluxQL do not:
+        let stream = query_executor
+            .query(db_name, query, None, QueryKind::InfluxQl, None, None)
+            .await
+            .unwrap();
+        assert!(!stream.schema().metadata().contains_key(SERIES_METADATA_KEY));
+    }
+
+    #[test_log::test(tokio::test)]
+    async fn query_influxql_series() {
+        let (write_buffer, query_executor, _) = setup().await;
+        let db_name = "test_db";
+        write_buffer
+            .write_lp(
+                NamespaceName::new(db_name).unwrap(),
+                "\
+                cpu,host=a,region=us usage=1 1\n\
+                cpu,host=b,region=us usage=2 1\n\
+                cpu,host=a,region=eu usage=3 2\n\
+                mem,host=a usage=4 1\n\
+                mem usage=5 2\n\
+                ",
+                Time::from_timestamp_nanos(0),
+                false,
+                influxdb3_write::Precision::Nanosecond,
+            )
+            .await
+            .unwrap();
+
+        let series = query_executor
+            .query_influxql_series(db_name, "SELECT usage FROM cpu, mem GROUP BY host", None)
+            .await
+            .unwrap();
+        let mut summary = series
+            .iter()
+            .map(|series| {
+                let rows = series.values.iter().map(|b| b.num_rows()).sum::<usize>();
+                (series.name.as_str(), series.tags.clone(), rows)
+            })
+            .collect::<Vec<_>>();
+        summary.sort();
+        let tags = |host: &str| BTreeMap::from([("host".to_string(), host.to_string())]);
+        assert_eq!(
+            summary,
+            [
+                ("cpu", tags("a"), 2),
+                ("cpu", tags("b"), 1),
+                // a series without a value for the tag has an empty one:
+                ("mem", tags(""), 1),
+                ("mem", tags("a"), 1),
+            ]
+        );
+        // the tag that is only grouped by is not a column of the rows:
+        for series in &series {
+            assert_eq!(series.columns, ["time", "usage"]);
+            for batch in &series.values {
+                assert_eq!(batch.num_columns(), 2);
+            }
+        }
+        assert_batches_eq!(
+            [
+                "+-------------------------------+-------+",
+                "| time                          | usage |",
+                "+-------------------------------+-------+",
+                "| 1970-01-01T00:00:00.000000001 | 1.0   |",
+                "| 1970-01-01T00:00:00.000000002 | 3.0   |",
+                "+-------------------------------+-------+",
+            ],
+            &series[0].values
+        );
+
+        // the tags that are selected are also columns of the rows:
+        let series = query_executor
+            .query_influxql_series(db_name, "SELECT usage, region FROM cpu GROUP BY host", None)
+            .await
+            .unwrap();
+        assert_eq!(series.len(), 2);
+        assert_eq!(series[0].columns, ["time", "usage", "region"]);
+
+        // and the results of a query without a GROUP BY clause are split by measurement:
+        let series = query_executor
+            .query_influxql_series(db_name, "SELECT usage FROM cpu, mem", None)
+            .await
+            .unwrap();
+        let names = series.iter().map(|s| s.name.as_str()).collect::<Vec<_>>();
+        assert_eq!(names, ["cpu", "mem"]);
+        assert!(series.iter().all(|s| s.tags.is_empty()));
+    }
+
+    #[test_log::test(tokio::test)]
+    async fn query_concurrency_limit() {
+        let (write_buffer, mut query_executor, _) = setup().await;
+        write_buffer
+            .write_lp(
+                NamespaceName::new("test_db").unwrap(),
+                "cpu,host=a usage=1 1",
+                Time::from_timestamp_nanos(0),
+                false,
+                influxdb3_write::Precision::Nanosecond,
+            )
+            .await
+            .unwrap();
+        let semaphore_metrics = Arc::new(AsyncSemaphoreMetrics::new(
+            &Registry::new(),
+            &[("semaphore", "query_execution")],
+        ));
+        query_executor.query_execution_semaphore = Arc::new(semaphore_metrics.new_semaphore(2));
+
+        // the queries differ, so that they are not coalesced into one execution:
+        let query = |limit: usize| {
+            let query_executor = &query_executor;
+            async move {
+                query_executor
+                    .query(
+                        "test_db",
+                        &format!("SELECT * FROM cpu LIMIT {limit}"),
+                        None,
+                        QueryKind::Sql,
+                        None,
+                        None,
+                    )
+                    .await
+                    .unwrap()
+            }
+        };
+        let first = query(1).await;
+        let second = query(2).await;
+
+        let mut third = Box::pin(query(3));
+        assert!(
+            tokio::time::timeout(Duration::from_millis(200), &mut third)
+                .await
+                .is_err(),
+            "query was executed beyond the concurrency limit"
+        );
+
+        // reading the results of a query to the end releases its permit:
+        let batches: Vec<RecordBatch> = first.try_collect().await.unwrap();
+        assert_eq!(1, batches.iter().map(RecordBatch::num_rows).sum::<usize>());
+        let third = tokio::time::timeout(Duration::from_secs(10), third)
+            .await
+            .expect("query was not executed once a permit was released");
+
+        // as does dropping the results:
+        let mut fourth = Box::pin(query(4));
+        assert!(
+            tokio::time::timeout(Duration::from_millis(200), &mut fourth)
+                .await
+                .is_err()
+        );
+        drop(second);
+        tokio::time::timeout(Duration::from_secs(10), fourth)
+            .await
+            .expect("query was not executed once a permit was released");
+        drop(third);
+    }
+
+    #[test_log::test(tokio::test)]
+    async fn dropping_results_cancels_query() {
+        let (write_buffer, mut query_executor, _) = setup().await;
+        write_buffer
+            .write_lp(
+                NamespaceName::new("test_db").unwrap(),
+                "cpu,host=a usage=1 1",
+                Time::from_timestamp_nanos(0),
+                false,
+                influxdb3_write::Precision::Nanosecond,
+            )
+            .await
+            .unwrap();
+        let semaphore_metrics = Arc::new(AsyncSemaphoreMetrics::new(
+            &Registry::new(),
+            &[("semaphore", "query_execution")],
+        ));
+        query_executor.query_execution_semaphore = Arc::new(semaphore_metrics.new_semaphore(1));
+        let mut subscription = pin!(query_executor.subscribe_query_log());
+
+        // each side of the union outputs its own batch:
+        let query = "SELECT host FROM cpu UNION ALL SELECT host FROM cpu";
+        let mut results = query_executor
+            .query("test_db", query, None, QueryKind::Sql, None, None)
+            .await
+            .unwrap();
+        results.next().await.unwrap().unwrap();
+        let entry = query_executor
+            .query_log
+            .entries()
+            .entries
+            .back()
+            .unwrap()
+            .state();
+        assert!(entry.running, "query should run until its results are read");
+
+        // dropping the results part way through fails the query, and releases its permit:
+        drop(results);
+        let entry = tokio::time::timeout(Duration::from_secs(1), subscription.next())
+            .await
+            .expect("subscriber should receive the cancelled query")
+            .unwrap();
+        assert_eq!(query, entry.query_text.to_string());
+        assert!(!entry.running);
+        assert!(!entry.success);
+        let results: Vec<RecordBatch> = tokio::time::timeout(
+            Duration::from_secs(10),
+            query_executor.query("test_db", query, None, QueryKind::Sql, None, None),
+        )
+        .await
+        .expect("query was not executed once a permit was released")
+        .unwrap()
+        .try_collect()
+        .await
+        .unwrap();
+        assert_eq!(2, results.iter().map(RecordBatch::num_rows).sum::<usize>());
+
+        // whereas reading them to the end succeeds:
+        let entry = tokio::time::timeout(Duration::from_secs(1), subscription.next())
+            .await
+            .expect("subscriber should receive the completed query")
+            .unwrap();
+        assert!(entry.success);
+    }
+
+    #[test_log::test(tokio::test)]
+    async fn query_timeout() {
+        let (write_buffer, mut query_executor, _) = setup().await;
+        write_buffer
+            .write_lp(
+                NamespaceName::new("test_db").unwrap(),
+                "cpu,host=a usage=1 1",
+                Time::from_timestamp_nanos(0),
+                false,
+                influxdb3_write::Precision::Nanosecond,
+            )
+            .await
+            .unwrap();
+        let query = |query_executor: &QueryExecutorImpl| {
+            let query_executor = query_executor.clone();
+            async move {
+                let stream = query_executor
+                    .query(
+                        "test_db",
+                        "SELECT * FROM cpu",
+                        None,
+                        QueryKind::Sql,
+                        None,
+                        None,
+                    )
+                    .await?;
+                stream
+                    .try_collect::<Vec<RecordBatch>>()
+                    .await
+                    .map_err(QueryExecutorError::ExecuteStream)
+            }
+        };
+        let is_timeout = |error: &QueryExecutorError| match error {
+            QueryExecutorError::Timeout { .. } => true,
+            QueryExecutorError::ExecuteStream(DataFusionError::External(error)) => matches!(
+                error.downcast_ref::<QueryExecutorError>(),
+                Some(QueryExecutorError::Timeout { .. })
+            ),
+            _ => false,
+        };
+
+        query_executor.query_timeout = Some(Duration::from_nanos(1));
+        let error = query(&query_executor).await.unwrap_err();
+        assert!(is_timeout(&error), "unexpected error: {error}");
+
+        // the timeout of the DataFusion config overrides that of the executor:
+        query_executor.datafusion_config = Arc::new(HashMap::from([(
+            QUERY_TIMEOUT_CONFIG_KEY.to_string(),
+            "1h".to_string(),
+        )]));
+        let batches = query(&query_executor).await.unwrap();
+        assert_eq!(1, batches.iter().map(RecordBatch::num_rows).sum::<usize>());
+
+        query_executor.query_timeout = None;
+        query_executor.datafusion_config = Arc::new(HashMap::from([(
+            QUERY_TIMEOUT_CONFIG_KEY.to_string(),
+            "1ns".to_string(),
+        )]));
+        let error = query(&query_executor).await.unwrap_err();
+        assert!(is_timeout(&error), "unexpected error: {error}");
+
+        // as do the other query methods:
+        let errors = [
+            query_executor
+                .query_per_measurement(
+                    "test_db",
+                    &["cpu"],
+                    Time::from_timestamp_nanos(0)..Time::from_timestamp_nanos(100),
+                    QueryOptions::default(),
+                )
+                .await
+                .map(|_| ())
+                .unwrap_err(),
+            query_executor
+                .query_multi(
+                    "test_db",
+                    "SELECT * FROM cpu",
+                    None,
+                    QueryKind::Sql,
+                    QueryOptions::default(),
+                )
+                .await
+                .and_then(|mut streams| streams.remove(0))
+                .map(|_| ())
+                .unwrap_err(),
+            query_executor
+                .explain_chunks("test_db", "SELECT * FROM cpu", None, QueryKind::Sql)
+                .await
+                .map(|_| ())
+                .unwrap_err(),
+        ];
+        for error in errors {
+            assert!(is_timeout(&error), "unexpected error: {error}");
+        }
+    }
+
+    #[test]
+    fn merge_datafusion_config_options() {
+        let server = Arc::new(HashMap::from([
+            (
+                "datafusion.execution.batch_size".to_string(),
+                "1024".to_string(),
+            ),
+            (
+                "datafusion.execution.target_partitions".to_string(),
+                "4".to_string(),
+            ),
+        ]));
+        // without options of its own, a database shares the options of the server:
+        let merged = merge_datafusion_config(&server, &BTreeMap::new());
+        assert!(Arc::ptr_eq(&server, &merged));
+
+        let database = BTreeMap::from([
+            (
+                "datafusion.execution.target_partitions".to_string(),
+                "2".to_string(),
+            ),
+            (QUERY_TIMEOUT_CONFIG_KEY.to_string(), "1h".to_string()),
+        ]);
+        let merged = merge_datafusion_config(&server, &database);
+        assert_eq!(
+            merged.iter().collect::<BTreeMap<_, _>>(),
+            BTreeMap::from([
+                (
+                    &"datafusion.execution.batch_size".to_string(),
+                    &"1024".to_string()
+                ),
+                (
+                    &"datafusion.execution.target_partitions".to_string(),
+                    &"2".to_string()
+                ),
+                (&QUERY_TIMEOUT_CONFIG_KEY.to_string(), &"1h".to_string()),
+            ])
+        );
+    }
+
+    #[test_log::test(tokio::test)]
+    async fn database_datafusion_config() {
+        let (write_buffer, mut query_executor, _) = setup().await;
+        let db_name = "test_db";
+        write_buffer
+            .write_lp(
+                NamespaceName::new(db_name).unwrap(),
+                "cpu,host=a usage=1 1",
+                Time::from_timestamp_nanos(0),
+                false,
+                influxdb3_write::Precision::Nanosecond,
+            )
+            .await
+            .unwrap();
+        query_executor.datafusion_config = Arc::new(HashMap::from([
+            (
+                "datafusion.execution.batch_size".to_string(),
+                "1024".to_string(),
+            ),
+            (
+                "datafusion.execution.target_partitions".to_string(),
+                "4".to_string(),
+            ),
+        ]));
+        let config = |query_executor: &QueryExecutorImpl| {
+            let db = query_executor.database(db_name).unwrap();
+            let ctx = db.new_query_context(None, None);
+            let state = ctx.inner().state();
+            (
+                state.config().batch_size(),
+                state.config().target_partitions(),
+                db.query_timeout(),
+            )
+        };
+        assert_eq!((1024, 4, None), config(&query_executor));
+
+        // the options of the database override those of the server:
+        write_buffer
+            .set_database_config(
+                db_name.to_string(),
+                vec![
+                    (
+                        "datafusion.execution.target_partitions".to_string(),
+                        Some("2".to_string()),
+                    ),
+                    (QUERY_TIMEOUT_CONFIG_KEY.to_string(), Some("1h".to_string())),
+                ],
+            )
+            .await
+            .unwrap();
+        assert_eq!(
+            (1024, 2, Some(Duration::from_secs(3_600))),
+            config(&query_executor)
+        );
+
+        // until they are unset:
+        write_buffer
+            .set_database_config(
+                db_name.to_string(),
+                vec![("datafusion.execution.target_partitions".to_string(), None)],
+            )
+            .await
+            .unwrap();
+        assert_eq!(
+            (1024, 4, Some(Duration::from_secs(3_600))),
+            config(&query_executor)
+        );
+    }
+
+    #[test_log::test(tokio::test)]
+    async fn time_precision() {
+        let (write_buffer, query_executor, _) = setup().await;
+        let db_name = "test_db";
+        write_buffer
+            .write_lp(
+                NamespaceName::new(db_name).unwrap(),
+                "\
+                cpu,host=a usage=1 1500000123\n\
+                cpu,host=b usage=2 2250000456\n\
+                ",
+                Time::from_timestamp_nanos(0),
+                false,
+                influxdb3_write::Precision::Nanosecond,
+            )
+            .await
+            .unwrap();
+
+        struct TestCase<'a> {
+            precision: TimePrecision,
+            unit: TimeUnit,
+            expected: &'a [&'a str],
+        }
+
+        let test_cases = [
+            TestCase {
+                precision: TimePrecision::Millisecond,
+                unit: TimeUnit::Millisecond,
+                expected: &[
+                    "+------+-------------------------+",
+                    "| host | time                    |",
+                    "+------+-------------------------+",
+                    "| a    | 1970-01-01T00:00:01.500 |",
+                    "| b    | 1970-01-01T00:00:02.250 |",
+                    "+------+-------------------------+",
+                ],
+            },
+            TestCase {
+                precision: TimePrecision::Second,
+                unit: TimeUnit::Second,
+                expected: &[
+                    "+------+---------------------+",
+                    "| host | time                |",
+                    "+------+---------------------+",
+                    "| a    | 1970-01-01T00:00:01 |",
+                    "| b    | 1970-01-01T00:00:02 |",
+                    "+------+---------------------+",
+                ],
+            },
+        ];
+
+        for t in test_cases {
+            let options = QueryOptions {
+                time_precision: t.precision,
+                ..Default::default()
+            };
+            let batches: Vec<RecordBatch> = query_executor
+                .query_with_options(
+                    db_name,
+                    "SELECT host, time FROM cpu",
+                    None,
+                    QueryKind::Sql,
+                    options,
+                    None,
+                    None,
+                )
+                .await
+                .unwrap()
+                .try_collect()
+                .await
+                .unwrap();
+            assert!(matches!(
+                batches[0].schema().field_with_name("time").unwrap().data_type(),
+                DataType::Timestamp(unit, _) if *unit == t.unit
+            ));
+            assert_batches_sorted_eq!(t.expected, &batches);
+        }
+    }
+
+    #[test_log::test(tokio::test)]
+    async fn time_precision_bucket_boundaries() {
+        let (write_buffer, query_executor, _) = setup().await;
+        let db_name = "test_db";
+        // rows a nanosecond either side of the boundaries of 1s buckets, including the epoch:
+        write_buffer
+            .write_lp(
+                NamespaceName::new(db_name).unwrap(),
+                "\
+                cpu,host=a usage=1 -1\n\
+                cpu,host=a usage=2 999999999\n\
+                cpu,host=a usage=3 1000000000\n\
+                cpu,host=a usage=4 1999999999\n\
+                ",
+                Time::from_timestamp_nanos(0),
+                false,
+                influxdb3_write::Precision::Nanosecond,
+            )
+            .await
+            .unwrap();
+
+        let query = |query: &'static str, kind: QueryKind| {
+            let query_executor = &query_executor;
+            async move {
+                let options = QueryOptions {
+                    time_precision: TimePrecision::Second,
+                    ..Default::default()
+                };
+                query_executor
+                    .query_with_options(db_name, query, None, kind, options, None, None)
+                    .await
+                    .unwrap()
+                    .try_collect::<Vec<RecordBatch>>()
+                    .await
+                    .unwrap()
+            }
+        };
+
+        // the buckets are output at their start, in seconds:
+        let batches = query(
+            "SELECT count(usage) FROM cpu \
+            WHERE time >= '1969-12-31T23:59:59Z' AND time < '1970-01-01T00:00:02Z' \
+            GROUP BY time(1s)",
+            QueryKind::InfluxQl,
+        )
+        .await;
+        assert_batches_sorted_eq!(
+            [
+                "+------------------+---------------------+-------+",
+                "| iox::measurement | time                | count |",
+                "+------------------+---------------------+-------+",
+                "| cpu              | 1969-12-31T23:59:59 | 1     |",
+                "| cpu              | 1970-01-01T00:00:00 | 1     |",
+                "| cpu              | 1970-01-01T00:00:01 | 2     |",
+                "+------------------+---------------------+-------+",
+            ],
+            &batches
+        );
+
+        // and each row is output at the start of the second, and so the bucket, that it falls in,
+        // rather than being truncated towards the epoch:
+        let batches = query("SELECT time, usage FROM cpu", QueryKind::Sql).await;
+        assert_batches_sorted_eq!(
+            [
+                "+---------------------+-------+",
+                "| time                | usage |",
+                "+---------------------+-------+",
+                "| 1969-12-31T23:59:59 | 1.0   |",
+                "| 1970-01-01T00:00:00 | 2.0   |",
+                "| 1970-01-01T00:00:01 | 3.0   |",
+                "| 1970-01-01T00:00:01 | 4.0   |",
+                "+---------------------+-------+",
+            ],
+            &batches
+        );
+    }
+
+    #[test_log::test(tokio::test)]
+    async fn group_by_time_nanosecond_boundaries() {
+        let (write_buffer, query_executor, _) = setup().await;
+        let db_name = "test_db";
+        // rows written in nanoseconds either side of the boundary of a 1s bucket, and one written
+        // in seconds, which lands exactly on it:
+        for (lp, precision) in [
+            (
+                "\
+                cpu,host=a usage=1 999999998\n\
+                cpu,host=a usage=2 999999999\n\
+                cpu,host=a usage=3 1000000001\n\
+                ",
+                influxdb3_write::Precision::Nanosecond,
+            ),
+            ("cpu,host=a usage=4 1", influxdb3_write::Precision::Second),
+        ] {
+            write_buffer
+                .write_lp(
+                    NamespaceName::new(db_name).unwrap(),
+                    lp,
+                    Time::from_timestamp_nanos(0),
+                    false,
+                    precision,
+                )
+                .await
+                .unwrap();
+        }
+
+        // the lower bound of the query is at nanosecond precision, and excludes the first row,
+        // while the upper bound is at millisecond precision:
+        let batches: Vec<RecordBatch> = query_executor
+            .query(
+                db_name,
+                "SELECT count(usage), sum(usage) FROM cpu \
+                WHERE time >= '1970-01-01T00:00:00.999999999Z' \
+                AND time < '1970-01-01T00:00:02.000Z' \
+                GROUP BY time(1s)",
+                None,
+                QueryKind::InfluxQl,
+                None,
+                None,
+            )
+            .await
+            .unwrap()
+            .try_collect()
+            .await
+            .unwrap();
+        assert_batches_sorted_eq!(
+            [
+                "+------------------+---------------------+-------+-----+",
+                "| iox::measurement | time                | count | sum |",
+                "+------------------+---------------------+-------+-----+",
+                "| cpu              | 1970-01-01T00:00:00 | 1     | 2.0 |",
+                "| cpu              | 1970-01-01T00:00:01 | 2     | 7.0 |",
+                "+------------------+---------------------+-------+-----+",
+            ],
+            &batches
+        );
+    }
+
+    #[test_log::test(tokio::test)]
+    async fn unbounded_cross_joins() {
+        let (write_buffer, query_executor, _) =
+            setup_with(|args| args.cross_join_row_limit = Some(100)).await;
+        let db_name = "test_db";
+        let mut lp = String::new();
+        for (table, rows) in [("big_a", 20), ("big_b", 20), ("small_a", 5), ("small_b", 5)] {
+            for i in 0..rows {
+                lp.push_str(&format!("{table},host=h{i} usage={i} {i}\n"));
+            }
+        }
+        write_buffer
+            .write_lp(
+                NamespaceName::new(db_name).unwrap(),
+                &lp,
+                Time::from_timestamp_nanos(0),
+                false,
+                influxdb3_write::Precision::Nanosecond,
+            )
+            .await
+            .unwrap();
+
+        let count = |query: &'static str, allow_cross_joins: bool| {
+            let query_executor = &query_executor;
+            async move {
+                let options = QueryOptions {
+                    allow_cross_joins,
+                    ..Default::default()
+                };
+                let batches: Vec<RecordBatch> = query_executor
+                    .query_with_options(db_name, query, None, QueryKind::Sql, options, None, None)
+                    .await?
+                    .try_collect()
+                    .await
+                    .map_err(QueryExecutorError::ExecuteStream)?;
+                Ok::<_, QueryExecutorError>(
+                    batches[0].column(0).as_primitive::<Int64Type>().value(0),
+                )
+            }
+        };
+
+        // 20 x 20 rows exceeds the limit of 100 rows used in the tests:
+        let big = "SELECT COUNT(*) FROM big_a CROSS JOIN big_b";
+        let error = count(big, false).await.unwrap_err();
+        assert!(
+            matches!(
+                error,
+                QueryExecutorError::UnboundedJoin {
+                    estimated_rows: 400,
+                    limit: 100
+                }
+            ),
+            "unexpected error: {error}"
+        );
+        // unless cross joins are explicitly allowed for the query:
+        assert_eq!(400, count(big, true).await.unwrap());
+
+        // 5 x 5 rows is within the limit:
+        let small = "SELECT COUNT(*) FROM small_a, small_b";
+        assert_eq!(25, count(small, false).await.unwrap());
+    }
+
+    #[test_log::test(tokio::test)]
+    async fn time_filters_pushed_down_exactly() {
+        use datafusion::logical_expr::{col, lit, TableProviderFilterPushDown};
+
+        let (write_buffer, query_executor, _) = setup().await;
+        let db_name = "test_db";
+        write_buffer
+            .write_lp(
+                NamespaceName::new(db_name).unwrap(),
+                "\
+                cpu,host=a usage=1 1\n\
+                cpu,host=b usage=2 2\n\
+                cpu,host=a usage=3 3\n\
+                cpu,host=b usage=4 4\n\
+                ",
+                Time::from_timestamp_nanos(0),
+                false,
+                influxdb3_write::Precision::Nanosecond,
+            )
+            .await
+            .unwrap();
+        let db = query_executor.database(db_name).unwrap();
+        let table = db.query_table("cpu").unwrap().unwrap();
+        let ts = |nanos: i64| lit(ScalarValue::TimestampNanosecond(Some(nanos), None));
+
+        let filters = [
+            col("time").gt_eq(ts(2)),
+            ts(4).gt(col("time")),
+            col("time").gt_eq(ts(1)).and(col("time").lt(ts(4))),
+            col("time").between(ts(1), ts(3)),
+            col("host").eq(lit("a")),
+            col("time").not_eq(ts(2)),
+            col("time").gt_eq(ts(1)).or(col("host").eq(lit("a"))),
+            col("usage").gt(lit(1.0)),
+        ];
+        assert_eq!(
+            vec![
+                TableProviderFilterPushDown::Exact,
+                TableProviderFilterPushDown::Exact,
+                TableProviderFilterPushDown::Exact,
+                TableProviderFilterPushDown::Exact,
+                TableProviderFilterPushDown::Inexact,
+                TableProviderFilterPushDown::Inexact,
+                TableProviderFilterPushDown::Inexact,
+                TableProviderFilterPushDown::Inexact,
+            ],
+            table
+                .supports_filters_pushdown(&filters.iter().collect::<Vec<_>>())
+                .unwrap()
+        );
+
+        // the scan applies the exact filters to its rows, so they are not applied again:
+        let ctx = db.new_query_context(None, None);
+        let plan = table
+            .scan(&ctx.inner().state(), None, &filters[..2], None)
+            .await
+            .unwrap();
+        let rows = collect(plan, ctx.inner().task_ctx())
+            .await
+            .unwrap()
+            .iter()
+            .map(|b| b.num_rows())
+            .sum::<usize>();
+        assert_eq!(2, rows);
+    }
+
+    #[test_log::test(tokio::test)]
+    async fn chunk_snapshot_per_query() {
+        let (write_buffer, query_executor, time_provider) = setup().await;
+        let db_name = "test_db";
+        // write a row at a time, advancing time so that earlier rows are persisted, then wait for
+        // the newly persisted files to be listed:
+        async fn write_rows(
+            write_buffer: &Arc<dyn WriteBuffer>,
+            time_provider: &MockProvider,
+            db_name: &str,
+            rows: std::ops::Range<i64>,
+        ) {
+            let persisted_files = || {
+                write_buffer
+                    .catalog()
+                    .db_schema(db_name)
+                    .and_then(|db| Some((db.id, db.table_name_to_id("cpu")?)))
+                    .map_or(0, |(db_id, table_id)| {
+                        write_buffer.parquet_files(db_id, table_id).len()
+                    })
+            };
+            let before = persisted_files();
+            for i in rows {
+                let time = i * 10;
+                write_buffer
+                    .write_lp(
+                        NamespaceName::new(db_name.to_string()).unwrap(),
+                        "cpu,host=a,region=us-east usage=250",
+                        Time::from_timestamp_nanos(time),
+                        false,
+                        influxdb3_write::Precision::Nanosecond,
+                    )
+                    .await
+                    .unwrap();
+                time_provider.set(Time::from_timestamp(time + 1, 0).unwrap());
+            }
+            tokio::time::timeout(Duration::from_secs(10), async {
+                while persisted_files() <= before {
+                    tokio::time::sleep(Duration::from_millis(10)).await;
+                }
+            })
+            .await
+            .expect("rows were not persisted");
+        }
+        async fn scan_rows(db: &Database, ctx: &IOxSessionContext) -> usize {
+            let table = db.query_table("cpu").unwrap().unwrap();
+            let plan = table
+                .scan(&ctx.inner().state(), None, &[], None)
+                .await
+                .unwrap();
+            collect(plan, ctx.inner().task_ctx())
+                .await
+                .unwrap()
+                .iter()
+                .map(|b| b.num_rows())
+                .sum()
+        }
+
+        write_rows(&write_buffer, &time_provider, db_name, 0..5).await;
+
+        let db = query_executor.database(db_name).unwrap();
+        let ctx = db.new_query_context(None, None);
+        assert_eq!(5, scan_rows(&db, &ctx).await);
+
+        // ingest continues, and persists more data, while the query is running:
+        write_rows(&write_buffer, &time_provider, db_name, 5..10).await;
+
+        // a later scan in the same query sees the same chunks, without duplicated or missing
+        // rows:
+        assert_eq!(5, scan_rows(&db, &ctx).await);
+
+        // while a new query sees all of the data:
+        let db = query_executor.database(db_name).unwrap();
+        let ctx = db.new_query_context(None, None);
+        assert_eq!(10, scan_rows(&db, &ctx).await);
+    }
+
+    #[test_log::test(tokio::test)]
+    async fn influxql_boolean_format() {
+        let (write_buffer, query_executor, _) = setup().await;
+        let db_name = "test_db";
+        write_buffer
+            .write_lp(
+                NamespaceName::new(db_name).unwrap(),
+                "\
+                cpu,host=a up=true 1\n\
+                cpu,host=b up=false 2\n\
+                cpu,host=c up=true 3\n\
+                ",
+                Time::from_timestamp_nanos(0),
+                false,
+                influxdb3_write::Precision::Nanosecond,
+            )
+            .await
+            .unwrap();
+
+        let query = |query: &'static str, kind: QueryKind, format: BooleanFormat| {
+            let query_executor = &query_executor;
+            async move {
+                let options = QueryOptions {
+                    influxql_boolean_format: format,
+                    ..Default::default()
+                };
+                let batches: Vec<RecordBatch> = query_executor
+                    .query_with_options(db_name, query, None, kind, options, None, None)
+                    .await
+                    .unwrap()
+                    .try_collect()
+                    .await
+                    .unwrap();
+                concat_batches(&batches[0].schema(), &batches).unwrap()
+            }
+        };
+
+        // the SQL output is unaffected by the format:
+        let sql = query(
+            "SELECT time, up FROM cpu ORDER BY time",
+            QueryKind::Sql,
+            BooleanFormat::Letters,
+        )
+        .await;
+        let sql = sql.column_by_name("up").unwrap().as_boolean();
+
+        for (format, t, f) in [
+            (BooleanFormat::Words, "true", "false"),
+            (BooleanFormat::Letters, "t", "f"),
+        ] {
+            let influxql = query("SELECT up FROM cpu", QueryKind::InfluxQl, format).await;
+            let influxql = influxql.column_by_name("up").unwrap().as_string::<i32>();
+            let expected = sql
+                .iter()
+                .map(|up| up.map(|up| if up { t } else { f }))
+                .collect::<Vec<_>>();
+            assert_eq!(expected, influxql.iter().collect::<Vec<_>>());
+        }
+    }
+
+    #[test_log::test(tokio::test)]
+    async fn influxql_epoch() {
+        let (write_buffer, query_executor, _) = setup().await;
+        let db_name = "test_db";
+        write_buffer
+            .write_lp(
+                NamespaceName::new(db_name).unwrap(),
+                "\
+                cpu,host=a usage=1 1500000123\n\
+                cpu,host=b usage=2 2250000456\n\
+                ",
+                Time::from_timestamp_nanos(0),
+                false,
+                influxdb3_write::Precision::Nanosecond,
+            )
+            .await
+            .unwrap();
+
+        for (epoch, expected) in [
+            (TimePrecision::Second, [1, 2]),
+            (TimePrecision::Millisecond, [1_500, 2_250]),
+        ] {
+            let options = QueryOptions {
+                influxql_epoch: Some(epoch),
+                ..Default::default()
+            };
+            let batches: Vec<RecordBatch> = query_executor
+                .query_with_options(
+                    db_name,
+                    "SELECT usage FROM cpu",
+                    None,
+                    QueryKind::InfluxQl,
+                    options,
+                    None,
+                    None,
+                )
+                .await
+                .unwrap()
+                .try_collect()
+                .await
+                .unwrap();
+            let batch = concat_batches(&batches[0].schema(), &batches).unwrap();
+            let time = batch
+                .column_by_name("time")
+                .unwrap()
+                .as_primitive::<Int64Type>();
+            assert_eq!(expected, time.values().as_ref(), "epoch: {epoch:?}");
+        }
+    }
+
+    #[test_log::test(tokio::test)]
+    async fn influxql_strict_group_by() {
+        let (write_buffer, query_executor, _) = setup().await;
+        let db_name = "test_db";
+        write_buffer
+            .write_lp(
+                NamespaceName::new(db_name).unwrap(),
+                "\
+                cpu,host=a usage=1,zone=\"us-east-1a\" 1\n\
+                cpu,host=b usage=2,zone=\"us-east-1b\" 2\n\
+                ",
+                Time::from_timestamp_nanos(0),
+                false,
+                influxdb3_write::Precision::Nanosecond,
+            )
+            .await
+            .unwrap();
+
+        let query = |query: &'static str, strict: bool| {
+            let options = QueryOptions {
+                influxql_strict_group_by: strict,
+                ..Default::default()
+            };
+            query_executor.query_with_options(
+                db_name,
+                query,
+                None,
+                QueryKind::InfluxQl,
+                options,
+                None,
+                None,
+            )
+        };
+
+        // grouping by a tag produces a group for each of its values:
+        let batches: Vec<RecordBatch> = query("SELECT count(usage) FROM cpu GROUP BY host", true)
+            .await
+            .unwrap()
+            .try_collect()
+            .await
+            .unwrap();
+        assert_eq!(2, batches.iter().map(|b| b.num_rows()).sum::<usize>());
+
+        // whereas grouping by a field is rejected, including in subqueries:
+        for q in [
+            "SELECT count(usage) FROM cpu GROUP BY zone",
+            "SELECT count(usage) FROM cpu GROUP BY time(1s), zone",
+            "SELECT sum(count) FROM (SELECT count(usage) FROM cpu GROUP BY zone)",
+        ] {
+            let Err(error) = query(q, true).await else {
+                panic!("grouping by a field should fail: {q}");
+            };
+            assert!(
+                matches!(
+                    &error,
+                    QueryExecutorError::GroupByField { measurement, column }
+                        if measurement == "cpu" && column == "zone"
+                ),
+                "unexpected error for {q}: {error}"
+            );
+        }
+
+        // the field is only rejected when the mode is on:
+        query("SELECT count(usage) FROM cpu GROUP BY zone", false)
+            .await
+            .unwrap();
+    }
+
+    #[test_log::test(tokio::test)]
+    async fn maintenance_mode() {
+        let (write_buffer, query_executor, _) = setup().await;
+        let db_name = "test_db";
+        write_buffer
+            .write_lp(
+                NamespaceName::new(db_name).unwrap(),
+                "cpu,host=a usage=1 1",
+                Time::from_timestamp_nanos(0),
+                false,
+                influxdb3_write::Precision::Nanosecond,
+            )
+            .await
+            .unwrap();
+
+        let query = "SELECT host, usage FROM cpu";
+        let in_flight = query_executor
+            .query(db_name, query, None, QueryKind::Sql, None, None)
+            .await
+            .unwrap();
+
+        query_executor.set_maintenance_mode(true);
+        let error = query_executor
+            .query(db_name, query, None, QueryKind::Sql, None, None)
+            .await
+            .map(|_| ())
+            .unwrap_err();
+        assert!(
+            matches!(error, QueryExecutorError::Unavailable { .. }),
+            "unexpected error: {error}"
+        );
+        // as are queries made through any of the other query methods:
+        let errors = [
+            query_executor
+                .query_per_measurement(
+                    db_name,
+                    &["cpu"],
+                    Time::from_timestamp_nanos(0)..Time::from_timestamp_nanos(100),
+                    QueryOptions::default(),
+                )
+                .await
+                .map(|_| ())
+                .unwrap_err(),
+            query_executor
+                .query_multi(
+                    db_name,
+                    query,
+                    None,
+                    QueryKind::Sql,
+                    QueryOptions::default(),
+                )
+                .await
+                .map(|_| ())
+                .unwrap_err(),
+            query_executor
+                .explain_chunks(db_name, query, None, QueryKind::Sql)
+                .await
+                .map(|_| ())
+                .unwrap_err(),
+            query_executor
+                .query_file(db_name, "cpu", ParquetFileId::from(0), query)
+                .await
+                .map(|_| ())
+                .unwrap_err(),
+        ];
+        for error in errors {
+            assert!(
+                matches!(error, QueryExecutorError::Unavailable { .. }),
+                "unexpected error: {error}"
+            );
+        }
+
+        // the query that started before maintenance mode was enabled is still active:
+        tokio::time::timeout(Duration::from_millis(100), query_executor.drain())
+            .await
+            .unwrap_err();
+        let batches: Vec<RecordBatch> = in_flight.try_collect().await.unwrap();
+        assert_batches_sorted_eq!(
+            [
+                "+------+-------+",
+                "| host | usage |",
+                "+------+-------+",
+                "| a    | 1.0   |",
+                "+------+-------+",
+            ],
+            &batches
+        );
+        tokio::time::timeout(Duration::from_secs(1), query_executor.drain())
+            .await
+            .expect("all queries should have been drained");
+
+        query_executor.set_maintenance_mode(false);
+        query_executor
+            .query(db_name, query, None, QueryKind::Sql, None, None)
+            .await
+            .unwrap();
+    }
+
+    #[test_log::test(tokio::test)]
+    async fn reject_queries_under_memory_pressure() {
+        let (write_buffer, mut query_executor, _) = setup().await;
+        // a tiny memory pool, with a high-water mark at half of its size:
+        query_executor.exec = make_exec(
+            Arc::new(InMemory::new()),
+            DedicatedExecutor::new_testing(),
+            1024,
+        );
+        query_executor.mem_pool_high_water_mark = Some(512);
+
+        let db_name = "test_db";
+        write_buffer
+            .write_lp(
+                NamespaceName::new(db_name).unwrap(),
+                "cpu,host=a usage=1 1",
+                Time::from_timestamp_nanos(0),
+                false,
+                influxdb3_write::Precision::Nanosecond,
+            )
+            .await
+            .unwrap();
+
+        // memory reserved by another query puts the pool above its high-water mark:
+        let pool = Arc::clone(
+            &query_executor
+                .exec
+                .new_context()
+                .inner()
+                .runtime_env()
+                .memory_pool,
+        );
+        let mut reservation = MemoryConsumer::new("other query").register(&pool);
+        reservation.try_grow(1000).unwrap();
+
+        let Err(error) = query_executor
+            .query(
+                db_name,
+                "SELECT host, usage FROM cpu",
+                None,
+                QueryKind::Sql,
+                None,
+                None,
+            )
+            .await
+        else {
+            panic!("query should be rejected while the memory pool is under pressure");
+        };
+        assert!(
+            matches!(
+                error,
+                QueryExecutorError::MemoryPressure {
+                    reserved: 1000,
+                    high_water_mark: 512
+                }
+            ),
+            "unexpected error: {error}"
+        );
+
+        // as are queries made through the other query methods:
+        let errors = [
+            query_executor
+                .query_per_measurement(
+                    db_name,
+                    &["cpu"],
+                    Time::from_timestamp_nanos(0)..Time::from_timestamp_nanos(100),
+                    QueryOptions::default(),
+                )
+                .await
+                .map(|_| ())
+                .unwrap_err(),
+            query_executor
+                .query_multi(
+                    db_name,
+                    "SELECT host FROM cpu",
+                    None,
+                    QueryKind::Sql,
+                    QueryOptions::default(),
+                )
+                .await
+                .and_then(|mut streams| streams.remove(0))
+                .map(|_| ())
+                .unwrap_err(),
+        ];
+        for error in errors {
+            assert!(
+                matches!(error, QueryExecutorError::MemoryPressure { .. }),
+                "unexpected error: {error}"
+            );
+        }
+
+        // once the memory is released, queries are admitted again:
+        reservation.free();
+        assert!(query_executor
+            .query(
+                db_name,
+                "SELECT host, usage FROM cpu",
+                None,
+                QueryKind::Sql,
+                None,
+                None,
+            )
+            .await
+            .is_ok());
+    }
+
+    #[test_log::test(tokio::test)]
+    async fn interactive_queries_not_blocked_by_batch_pool() {
+        let (write_buffer, mut query_executor, _) = setup().await;
+        // a batch pool with a single thread, which is kept busy until it is unblocked:
+        let batch_executor = DedicatedExecutor::new(
+            "datafusion_batch",
+            {
+                let mut builder = tokio::runtime::Builder::new_multi_thread();
+                builder.worker_threads(1);
+                builder
+            },
+            Default::default(),
+        );
+        query_executor.batch_exec = Some(make_exec(
+            Arc::new(InMemory::new()),
+            batch_executor.clone(),
+            1024 * 1024 * 1024,
+        ));
+        let (unblock, blocked) = std::sync::mpsc::channel::<()>();
+        let saturated = batch_executor.spawn(async move {
+            let _ = blocked.recv();
+        });
+
+        let db_name = "test_db";
+        write_buffer
+            .write_lp(
+                NamespaceName::new(db_name).unwrap(),
+                "cpu,host=a usage=1 1",
+                Time::from_timestamp_nanos(0),
+                false,
+                influxdb3_write::Precision::Nanosecond,
+            )
+            .await
+            .unwrap();
+        let query = |priority| {
+            let query_executor = &query_executor;
+            async move {
+                let options = QueryOptions {
+                    priority,
+                    ..Default::default()
+                };
+                query_executor
+                    .query_with_options(
+                        db_name,
+                        "SELECT host, usage FROM cpu",
+                        None,
+                        QueryKind::Sql,
+                        options,
+                        None,
+                        None,
+                    )
+                    .await
+                    .unwrap()
+                    .try_collect::<Vec<RecordBatch>>()
+                    .await
+                    .unwrap()
+            }
+        };
+        let expected = [
+            "+------+-------+",
+            "| host | usage |",
+            "+------+-------+",
+            "| a    | 1.0   |",
+            "+------+-------+",
+        ];
+
+        let batches =
+            tokio::time::timeout(Duration::from_secs(5), query(QueryPriority::Interactive))
+                .await
+                .expect("interactive query should not wait for the batch pool");
+        assert_batches_sorted_eq!(expected, &batches);
+
+        let mut batch_query = pin!(query(QueryPriority::Batch));
+        tokio::time::timeout(Duration::from_millis(100), &mut batch_query)
+            .await
+            .expect_err("batch query should wait for the batch pool");
+        unblock.send(()).unwrap();
+        saturated.await.unwrap();
+        let batches = batch_query.await;
+        assert_batches_sorted_eq!(expected, &batches);
+    }
+
+    #[test_log::test(tokio::test)]
+    async fn output_columns() {
+        let (write_buffer, query_executor, _) = setup().await;
+        let db_name = "test_db";
+        write_buffer
+            .write_lp(
+                NamespaceName::new(db_name).unwrap(),
+                "cpu,host=a,region=us usage=1 1",
+                Time::from_timestamp_nanos(0),
+                false,
+                influxdb3_write::Precision::Nanosecond,
+            )
+            .await
+            .unwrap();
+
+        let query = |output_columns: &[&str]| {
+            let query_executor = &query_executor;
+            let output_columns = output_columns.iter().map(|c| c.to_string()).collect();
+            async move {
+                let options = QueryOptions {
+                    output_columns,
+                    ..Default::default()
+                };
+                query_executor
+                    .query_with_options(
+                        db_name,
+                        "SELECT host, region, usage FROM cpu",
+                        None,
+                        QueryKind::Sql,
+                        options,
+                        None,
+                        None,
+                    )
+                    .await?
+                    .try_collect::<Vec<RecordBatch>>()
+                    .await
+                    .map_err(QueryExecutorError::ExecuteStream)
+            }
+        };
+
+        // reorder:
+        let batches = query(&["usage", "region", "host"]).await.unwrap();
+        assert_batches_sorted_eq!(
+            [
+                "+-------+--------+------+",
+                "| usage | region | host |",
+                "+-------+--------+------+",
+                "| 1.0   | us     | a    |",
+                "+-------+--------+------+",
+            ],
+            &batches
+        );
+
+        // restrict:
+        let batches = query(&["usage", "host"]).await.unwrap();
+        assert_batches_sorted_eq!(
+            [
+                "+-------+------+",
+                "| usage | host |",
+                "+-------+------+",
+                "| 1.0   | a    |",
+                "+-------+------+",
+            ],
+            &batches
+        );
+
+        // a column that is not in the result:
+        let error = query(&["host", "usag"]).await.unwrap_err();
+        assert!(
+            matches!(
+                &error,
+                QueryExecutorError::UnknownColumn { name, suggestions }
+                    if name == "usag" && suggestions == &["usage"]
+            ),
+            "unexpected error: {error}"
+        );
+    }
+
+    #[test_log::test(tokio::test)]
+    async fn approx_count_distinct() {
+        let (write_buffer, query_executor, _) = setup().await;
+        let db_name = "test_db";
+        let lp = (0..2000)
+            .map(|i| format!("cpu,host=h{} usage={i} {i}", i % 1000))
+            .collect::<Vec<_>>()
+            .join("\n");
+        write_buffer
+            .write_lp(
+                NamespaceName::new(db_name).unwrap(),
+                &lp,
+                Time::from_timestamp_nanos(0),
+                false,
+                influxdb3_write::Precision::Nanosecond,
+            )
+            .await
+            .unwrap();
+
+        let batches: Vec<RecordBatch> = query_executor
+            .query(
+                db_name,
+                "SELECT COUNT(DISTINCT host) AS exact, approx_count_distinct(host) AS approx FROM cpu",
+                None,
+                QueryKind::Sql,
+                None,
+                None,
+            )
+            .await
+            .unwrap()
+            .try_collect()
+            .await
+            .unwrap();
+        let batch = concat_batches(&batches[0].schema(), &batches).unwrap();
+        let exact = batch
+            .column_by_name("exact")
+            .unwrap()
+            .as_primitive::<Int64Type>()
+            .value(0);
+        let approx = batch
+            .column_by_name("approx")
+            .unwrap()
+            .as_primitive::<UInt64Type>()
+            .value(0);
+        assert_eq!(1000, exact);
+        let error = (approx as f64 - exact as f64).abs() / exact as f64;
+        assert!(
+            error < 0.05,
+            "approximate count {approx} is too far from {exact}"
+        );
+
+        // InfluxQL counts of distinct values are made approximate by the query option, keeping
+        // the type of the exact count:
+        let influxql_count = |query: &'static str, approx_count_distinct: bool| {
+            let query_executor = &query_executor;
+            async move {
+                let batches: Vec<RecordBatch> = query_executor
+                    .query_with_options(
+                        db_name,
+                        query,
+                        None,
+                        QueryKind::InfluxQl,
+                        QueryOptions {
+                            approx_count_distinct,
+                            ..Default::default()
+                        },
+                        None,
+                        None,
+                    )
+                    .await
+                    .unwrap()
+                    .try_collect()
+                    .await
+                    .unwrap();
+                concat_batches(&batches[0].schema(), &batches).unwrap()
+            }
+        };
+        let query = "SELECT count(distinct(usage)) FROM cpu";
+        let count = |batch: &RecordBatch| {
+            batch
+                .column_by_name("count")
+                .unwrap()
+                .as_primitive::<Int64Type>()
+                .value(0)
+        };
+        let exact = count(&influxql_count(query, false).await);
+        let approx = count(&influxql_count(query, true).await);
+        assert_eq!(2000, exact);
+        let error = (approx as f64 - exact as f64).abs() / exact as f64;
+        assert!(
+            error < 0.05,
+            "approximate count {approx} is too far from {exact}"
+        );
+        let explain = influxql_count("EXPLAIN SELECT count(distinct(usage)) FROM cpu", true).await;
+        let plans = explain
+            .column_by_name("plan")
+            .unwrap()
+            .as_string::<i32>()
+            .iter()
+            .flatten()
+            .collect::<Vec<_>>();
+        assert!(
+            plans.iter().any(|plan| plan.contains("approx_distinct")),
+            "the plan does not count approximately: {plans:?}"
+        );
+    }
+
+    #[test_log::test(tokio::test)]
+    async fn capture_and_replay_workload() {
+        let (write_buffer, query_executor, _) = setup().await;
+        let db_name = "test_db";
+        write_buffer
+            .write_lp(
+                NamespaceName::new(db_name).unwrap(),
+                "cpu,host=a usage=1 1\ncpu,host=b usage=2 2",
+                Time::from_timestamp_nanos(0),
+                false,
+                influxdb3_write::Precision::Nanosecond,
+            )
+            .await
+            .unwrap();
+
+        for (query, kind) in [
+            ("SELECT host, usage FROM cpu", QueryKind::Sql),
+            (
+                "SELECT usage FROM cpu WHERE host = 'a'",
+                QueryKind::InfluxQl,
+            ),
+        ] {
+            let _: Vec<RecordBatch> = query_executor
+                .query(db_name, query, None, kind, None, None)
+                .await
+                .unwrap()
+                .try_collect()
+                .await
+                .unwrap();
+        }
+
+        let dir = test_helpers::tmp_dir().unwrap();
+        let path = dir.path().join("workload.jsonl");
+        assert_eq!(2, query_executor.capture_workload(&path).await.unwrap());
+
+        let replayed = query_executor.replay_workload(&path).await.unwrap();
+        let results = replayed
+            .iter()
+            .map(|r| {
+                (
+                    r.query.query_type.as_str(),
+                    r.query.query.as_str(),
+                    *r.result.as_ref().unwrap(),
+                )
+            })
+            .collect::<Vec<_>>();
+        assert_eq!(
+            vec![
+                ("sql", "SELECT host, usage FROM cpu", 2),
+                ("influxql", "SELECT usage FROM cpu WHERE host = 'a'", 1),
+            ],
+            results
+        );
+    }
+
+    #[test_log::test(tokio::test)]
+    async fn result_cache() {
+        let (write_buffer, query_executor, _) =
+            setup_with(|args| args.result_cache_size = Some(1024 * 1024)).await;
+        let db_name = "test_db";
+        let write = |lp: &'static str| {
+            let write_buffer = Arc::clone(&write_buffer);
+            async move {
+                write_buffer
+                    .write_lp(
+                        NamespaceName::new(db_name).unwrap(),
+                        lp,
+                        Time::from_timestamp_nanos(0),
+                        false,
+                        influxdb3_write::Precision::Nanosecond,
+                    )
+                    .await
+                    .unwrap();
+            }
+        };
+        write("hosts,host=a region=\"us-east\" 1\ncpu,host=a usage=1 1").await;
+
+        let query = || async {
+            let options = QueryOptions {
+                cache_results: true,
+                ..Default::default()
+            };
+            query_executor
+                .query_with_options(
+                    db_name,
+                    "SELECT host, region FROM hosts",
+                    None,
+                    QueryKind::Sql,
+                    options,
+                    None,
+                    None,
+                )
+                .await
+                .unwrap()
+                .try_collect::<Vec<RecordBatch>>()
+                .await
+                .unwrap()
+        };
+        let hits = || query_executor.result_cache.as_ref().unwrap().hits();
+
+        let expected = [
+            "+------+---------+",
+            "| host | region  |",
+            "+------+---------+",
+            "| a    | us-east |",
+            "+------+---------+",
+        ];
+        assert_batches_sorted_eq!(expected, &query().await);
+        assert_eq!(0, hits());
+        assert_batches_sorted_eq!(expected, &query().await);
+        assert_eq!(1, hits());
+
+        // a write to another table does not affect the cached results:
+        write("cpu,host=a usage=2 2").await;
+        assert_batches_sorted_eq!(expected, &query().await);
+        assert_eq!(2, hits());
+
+        // whereas a write to the queried table does:
+        write("hosts,host=b region=\"us-west\" 2").await;
+        assert_batches_sorted_eq!(
+            [
+                "+------+---------+",
+                "| host | region  |",
+                "+------+---------+",
+                "| a    | us-east |",
+                "| b    | us-west |",
+                "+------+---------+",
+            ],
+            &query().await
+        );
+        assert_eq!(2, hits());
+        query().await;
+        assert_eq!(3, hits());
+
+        // the queries that hit the cache are logged as such:
+        let batches: Vec<RecordBatch> = query_executor
+            .query(
+                db_name,
+                "SELECT query_text, result_source FROM system.queries \
+                WHERE query_text LIKE '%FROM hosts' ORDER BY issue_time",
+                None,
+                QueryKind::Sql,
+                None,
+                None,
+            )
+            .await
+            .unwrap()
+            .try_collect()
+            .await
+            .unwrap();
+        assert_batches_eq!(
+            [
+                "+--------------------------------+---------------+",
+                "| query_text                     | result_source |",
+                "+--------------------------------+---------------+",
+                "| SELECT host, region FROM hosts |               |",
+                "| SELECT host, region FROM hosts | cache         |",
+                "| SELECT host, region FROM hosts | cache         |",
+                "| SELECT host, region FROM hosts |               |",
+                "| SELECT host, region FROM hosts | cache         |",
+                "+--------------------------------+---------------+",
+            ],
+            &batches
+        );
+
+        // the results of queries against a database with a retention period are not cached, as
+        // they change as the data expires:
+        let db_schema = write_buffer.catalog().db_schema(db_name).unwrap();
+        write_buffer
+            .catalog()
+            .apply_catalog_batch(&CatalogBatch {
+                database_id: db_schema.id,
+                database_name: Arc::clone(&db_schema.name),
+                time_ns: 0,
+                ops: vec![CatalogOp::SetRetentionPeriod(RetentionPeriodDefinition {
+                    database_id: db_schema.id,
+                    database_name: Arc::clone(&db_schema.name),
+                    retention_period_ns: Some(Duration::from_secs(3600).as_nanos() as i64),
+                })],
+            })
+            .unwrap();
+        query().await;
+        query().await;
+        assert_eq!(3, hits());
+    }
+
+    #[test_log::test(tokio::test)]
+    async fn query_grouped() {
+        let (write_buffer, query_executor, _) = setup().await;
+        let db_name = "test_db";
+        write_buffer
+            .write_lp(
+                NamespaceName::new(db_name).unwrap(),
+                "\
+                cpu,host=a,region=us-east usage=1 1\n\
+                cpu,host=b,region=us-east usage=2 2\n\
+                cpu,host=a,region=us-east usage=3 3\n\
+                ",
+                Time::from_timestamp_nanos(0),
+                false,
+                influxdb3_write::Precision::Nanosecond,
+            )
+            .await
+            .unwrap();
+
+        let groups = query_executor
+            .query_grouped(
+                db_name,
+                "SELECT host, region, usage FROM cpu ORDER BY time",
+                None,
+                QueryKind::Sql,
+                &["host", "region"],
+            )
+            .await
+            .unwrap();
+        let mut results = vec![];
+        for (key, stream) in groups {
+            let batches: Vec<RecordBatch> = stream.try_collect().await.unwrap();
+            results.push((key, batches));
+        }
+        assert_eq!(2, results.len());
+
+        let key = |host: &str| {
+            vec![
+                ScalarValue::Utf8(Some(host.to_string())),
+                ScalarValue::Utf8(Some("us-east".to_string())),
+            ]
+        };
+        assert_eq!(key("a"), results[0].0);
+        assert_batches_eq!(
+            [
+                "+------+---------+-------+",
+                "| host | region  | usage |",
+                "+------+---------+-------+",
+                "| a    | us-east | 1.0   |",
+                "| a    | us-east | 3.0   |",
+                "+------+---------+-------+",
+            ],
+            &results[0].1
+        );
+        assert_eq!(key("b"), results[1].0);
+        assert_batches_eq!(
+            [
+                "+------+---------+-------+",
+                "| host | region  | usage |",
+                "+------+---------+-------+",
+                "| b    | us-east | 2.0   |",
+                "+------+---------+-------+",
+            ],
+            &results[1].1
+        );
+
+        // a group key that is not output by the query fails:
+        let Err(error) = query_executor
+            .query_grouped(
+                db_name,
+                "SELECT host, usage FROM cpu",
+                None,
+                QueryKind::Sql,
+                &["hosts"],
+            )
+            .await
+        else {
+            panic!("grouping by a column that is not output should fail");
+        };
+        assert!(
+            matches!(
+                &error,
+                QueryExecutorError::UnknownColumn { name, suggestions }
+                    if name == "hosts" && suggestions == &["host"]
+            ),
+            "unexpected error: {error}"
+        );
+    }
+
+    #[test_log::test(tokio::test)]
+    async fn invalid_params_in_every_query_method() {
+        let (write_buffer, query_executor, _) = setup().await;
+        let db_name = "test_db";
+        write_buffer
+            .write_lp(
+                NamespaceName::new(db_name).unwrap(),
+                "cpu,host=a usage=1 1",
+                Time::from_timestamp_nanos(0),
+                false,
+                influxdb3_write::Precision::Nanosecond,
+            )
+            .await
+            .unwrap();
+        let query = "SELECT host, usage FROM cpu WHERE host = $host";
+        let is_invalid_param = |error: &QueryExecutorError| matches!(error, QueryExecutorError::InvalidParameter { name, .. } if name == "host");
+
+        // the statement of a multi-statement query that is missing a parameter fails in its
+        // position:
+        let mut streams = query_executor
+            .query_multi(
+                db_name,
+                &format!("SELECT host FROM cpu; {query}"),
+                None,
+                QueryKind::Sql,
+                QueryOptions::default(),
+            )
+            .await
+            .unwrap();
+        let error = streams.pop().unwrap().map(|_| ()).unwrap_err();
+        assert!(is_invalid_param(&error), "unexpected error: {error}");
+        assert!(streams.pop().unwrap().is_ok());
+
+        let error = query_executor
+            .explain_chunks(db_name, query, None, QueryKind::Sql)
+            .await
+            .unwrap_err();
+        assert!(is_invalid_param(&error), "unexpected error: {error}");
+        let error = query_executor
+            .query_file(db_name, "cpu", ParquetFileId::from(0), query)
+            .await
+            .map(|_| ())
+            .unwrap_err();
+        assert!(is_invalid_param(&error), "unexpected error: {error}");
+    }
+
+    #[test_log::test(tokio::test)]
+    async fn query_multi() {
+        let (write_buffer, query_executor, _) = setup().await;
+        let db_name = "test_db";
+        write_buffer
+            .write_lp(
+                NamespaceName::new(db_name).unwrap(),
+                "cpu,host=a usage=1 1\nmem,host=a used=2 1",
+                Time::from_timestamp_nanos(0),
+                false,
+                influxdb3_write::Precision::Nanosecond,
+            )
+            .await
+            .unwrap();
+
+        let streams = query_executor
+            .query_multi(
+                db_name,
+                "SELECT host, usage FROM cpu; SELECT host, used FROM mem;",
+                None,
+                QueryKind::Sql,
+                QueryOptions::default(),
+            )
+            .await
+            .unwrap();
+        assert_eq!(2, streams.len());
+        let mut results = vec![];
+        for stream in streams {
+            let batches: Vec<RecordBatch> = stream.unwrap().try_collect().await.unwrap();
+            results.push(batches);
+        }
+        assert_batches_sorted_eq!(
+            [
+                "+------+-------+",
+                "| host | usage |",
+                "+------+-------+",
+                "| a    | 1.0   |",
+                "+------+-------+",
+            ],
+            &results[0]
+        );
+        assert_batches_sorted_eq!(
+            [
+                "+------+------+",
+                "| host | used |",
+                "+------+------+",
+                "| a    | 2.0  |",
+                "+------+------+",
+            ],
+            &results[1]
+        );
+
+        // a statement that fails is reported in its position:
+        let streams = query_executor
+            .query_multi(
+                db_name,
+                "SELECT usage FROM cpu; SELECT usage FROM nope; SELECT used FROM mem",
+                None,
+                QueryKind::Sql,
+                QueryOptions::default(),
+            )
+            .await
+            .unwrap();
+        assert_eq!(
+            vec![true, false, true],
+            streams.iter().map(Result::is_ok).collect::<Vec<_>>()
+        );
+    }
+
+    #[test_log::test(tokio::test)]
+    async fn query_multi_checks_each_statement() {
+        let (write_buffer, query_executor, _) =
+            setup_with(|args| args.cross_join_row_limit = Some(1)).await;
+        let db_name = "test_db";
+        write_buffer
+            .write_lp(
+                NamespaceName::new(db_name).unwrap(),
+                "cpu,host=a usage=1 1\ncpu,host=b usage=2 2",
+                Time::from_timestamp_nanos(0),
+                false,
+                influxdb3_write::Precision::Nanosecond,
+            )
+            .await
+            .unwrap();
+        let query = "SELECT host FROM cpu; SELECT a.host FROM cpu a CROSS JOIN cpu b";
+
+        // each statement is checked as a query of its own, so the cross join is rejected in its
+        // position:
+        let streams = query_executor
+            .query_multi(
+                db_name,
+                query,
+                None,
+                QueryKind::Sql,
+                QueryOptions::default(),
+            )
+            .await
+            .unwrap();
+        assert!(streams[0].is_ok());
+        assert!(
+            matches!(
+                streams[1],
+                Err(QueryExecutorError::UnboundedJoin {
+                    estimated_rows: 4,
+                    limit: 1
+                })
+            ),
+            "unexpected result: {:?}",
+            streams[1].as_ref().map(|_| ())
+        );
+
+        // unless the options allow it:
+        let options = QueryOptions {
+            allow_cross_joins: true,
+            ..Default::default()
+        };
+        let streams = query_executor
+            .query_multi(db_name, query, None, QueryKind::Sql, options)
+            .await
+            .unwrap();
+        assert!(streams.iter().all(Result::is_ok));
+    }
+
+    #[test_log::test(tokio::test)]
+    async fn query_costs_by_tag() {
+        let (write_buffer, query_executor, _) = setup().await;
+        let db_name = "test_db";
+        write_buffer
+            .write_lp(
+                NamespaceName::new(db_name).unwrap(),
+                "cpu,host=a usage=1 1\ncpu,host=b usage=2 2",
+                Time::from_timestamp_nanos(0),
+                false,
+                influxdb3_write::Precision::Nanosecond,
+            )
+            .await
+            .unwrap();
+
+        let query = |query: &'static str, tags: &[(&str, &str)]| {
+            let query_executor = &query_executor;
+            let tags = tags
+                .iter()
+                .map(|(k, v)| (k.to_string(), v.to_string()))
+                .collect();
+            async move {
+                let options = QueryOptions {
+                    tags,
+                    ..Default::default()
+                };
+                query_executor
+                    .query_with_options(db_name, query, None, QueryKind::Sql, options, None, None)
+                    .await
+                    .unwrap()
+                    .try_collect::<Vec<RecordBatch>>()
+                    .await
+                    .unwrap()
+            }
+        };
+        query(
+            "SELECT * FROM cpu",
+            &[("team", "infra"), ("dashboard", "latency")],
+        )
+        .await;
+        query("SELECT host FROM cpu", &[("team", "infra")]).await;
+        query("SELECT usage FROM cpu", &[("team", "web")]).await;
+        query("SELECT COUNT(*) FROM cpu", &[]).await;
+
+        let costs = query_executor.query_costs_by_tag();
+        assert_eq!(
+            vec![
+                ("dashboard", "latency", 1),
+                ("team", "infra", 2),
+                ("team", "web", 1)
+            ],
+            costs
+                .iter()
+                .map(|c| (c.key.as_str(), c.value.as_str(), c.queries))
+                .collect::<Vec<_>>()
+        );
+        assert!(costs.iter().all(|c| c.output_bytes > 0));
+        // the costs of a tag are the sum of the costs of its queries:
+        assert!(costs[1].output_bytes > costs[0].output_bytes);
+
+        let batches = query(
+            "SELECT query_text, tags FROM system.queries \
+            WHERE query_text LIKE '%FROM cpu' ORDER BY issue_time",
+            &[],
+        )
+        .await;
+        assert_batches_sorted_eq!(
+            [
+                "+--------------------------+-------------------------------+",
+                "| query_text               | tags                          |",
+                "+--------------------------+-------------------------------+",
+                "| SELECT * FROM cpu        | dashboard=latency, team=infra |",
+                "| SELECT host FROM cpu     | team=infra                    |",
+                "| SELECT usage FROM cpu    | team=web                      |",
+                "| SELECT COUNT(*) FROM cpu |                               |",
+                "+--------------------------+-------------------------------+",
+            ],
+            &batches
+        );
+    }
+
+    #[test_log::test(tokio::test)]
+    async fn identical_concurrent_queries_coalesced() {
+        let (write_buffer, query_executor, _) =
+            setup_with(|args| args.coalesce_buffer_size = Some(1024 * 1024)).await;
+        let db_name = "test_db";
+        write_buffer
+            .write_lp(
+                NamespaceName::new(db_name).unwrap(),
+                "cpu,host=a usage=1 1\ncpu,host=b usage=2 2",
+                Time::from_timestamp_nanos(0),
+                false,
+                influxdb3_write::Precision::Nanosecond,
+            )
+            .await
+            .unwrap();
+
+        // the queries differ in whitespace and tags, neither of which change their results:
+        let queries = (0..5).map(|i| {
+            let options = QueryOptions {
+                tags: HashMap::from([("dashboard".to_string(), i.to_string())]),
+                ..Default::default()
+            };
+            query_executor.query_with_options(
+                db_name,
+                if i % 2 == 0 {
+                    "SELECT host, usage FROM cpu"
+                } else {
+                    "SELECT host,  usage\nFROM cpu"
+                },
+                None,
+                QueryKind::Sql,
+                options,
+                None,
+                None,
+            )
+        });
+        let streams = futures::future::try_join_all(queries).await.unwrap();
+        let results = futures::future::try_join_all(
+            streams
+                .into_iter()
+                .map(|stream| stream.try_collect::<Vec<RecordBatch>>()),
+        )
+        .await
+        .unwrap();
+
+        // each query is logged, with those that followed the one that was executed marked as such:
+        let entries = query_executor.query_log.entries().entries;
+        assert_eq!(5, entries.len());
+        let sources = entries
+            .iter()
+            .map(|e| {
+                query_executor
+                    .query_log_stats
+                    .get(&e.state().id.to_string())
+                    .and_then(|stats| stats.result_source)
+            })
+            .collect::<Vec<_>>();
+        assert_eq!(
+            1,
+            sources.iter().filter(|source| source.is_none()).count(),
+            "{sources:?}"
+        );
+        assert_eq!(
+            4,
+            sources
+                .iter()
+                .filter(|source| **source == Some(ResultSource::Coalesced))
+                .count(),
+            "{sources:?}"
+        );
+        for batches in results {
+            assert_batches_sorted_eq!(
+                [
+                    "+------+-------+",
+                    "| host | usage |",
+                    "+------+-------+",
+                    "| a    | 1.0   |",
+                    "| b    | 2.0   |",
+                    "+------+-------+",
+                ],
+                &batches
+            );
+        }
+
+        // once the query is complete, the next identical one is executed again:
+        query_executor
+            .query(
+                db_name,
+                "SELECT host, usage FROM cpu",
+                None,
+                QueryKind::Sql,
+                None,
+                None,
+            )
+            .await
+            .unwrap()
+            .try_collect::<Vec<RecordBatch>>()
+            .await
+            .unwrap();
+        assert_eq!(6, query_executor.query_log.entries().entries.len());
+    }
+
+    #[test_log::test(tokio::test)]
+    async fn retention_period() {
+        let (write_buffer, query_executor, _) = setup().await;
+        let now = std::time::SystemTime::now()
+            .duration_since(std::time::UNIX_EPOCH)
+            .unwrap()
+            .as_nanos() as i64;
+        write_buffer
+            .write_lp(
+                NamespaceName::new("test_db").unwrap(),
+                format!("cpu,host=old usage=1 1\ncpu,host=new usage=2 {now}").as_str(),
+                Time::from_timestamp_nanos(0),
+                false,
+                influxdb3_write::Precision::Nanosecond,
+            )
+            .await
+            .unwrap();
+        let query = || async {
+            query_executor
+                .query(
+                    "test_db",
+                    "SELECT host, usage FROM cpu",
+                    None,
+                    QueryKind::Sql,
+                    None,
+                    None,
+                )
+                .await
+                .unwrap()
+                .try_collect::<Vec<RecordBatch>>()
+                .await
+                .unwrap()
+        };
+
+        write_buffer
+            .set_retention_period("test_db".into(), Some(Duration::from_secs(3600)))
+            .await
+            .unwrap();
+        assert_batches_sorted_eq!(
+            [
+                "+------+-------+",
+                "| host | usage |",
+                "+------+-------+",
+                "| new  | 2.0   |",
+                "+------+-------+",
+            ],
+            &query().await
+        );
+        let batches: Vec<RecordBatch> = query_executor
+            .show_retention_policies(Some("test_db"), None)
+            .await
+            .unwrap()
+            .try_collect()
+            .await
+            .unwrap();
+        assert_batches_sorted_eq!(
+            [
+                "+---------------+---------+---------------+---------+-------+",
+                "| iox::database | name    | duration      | default | error |",
+                "+---------------+---------+---------------+---------+-------+",
+                "| test_db       | autogen | 3600000000000 | true    |       |",
+                "+---------------+---------+---------------+---------+-------+",
+            ],
+            &batches
+        );
+
+        // a period that would expire all of the data is rejected:
+        let error = write_buffer
+            .set_retention_period("test_db".into(), Some(Duration::ZERO))
+            .await
+            .unwrap_err();
+        assert!(
+            matches!(error, WriteBufferError::InvalidRetentionPeriod { .. }),
+            "unexpected error: {error}"
+        );
+
+        // the expired data is kept, and is queried again once it is retained indefinitely:
+        write_buffer
+            .set_retention_period("test_db".into(), None)
+            .await
+            .unwrap();
+        assert_batches_sorted_eq!(
+            [
+                "+------+-------+",
+                "| host | usage |",
+                "+------+-------+",
+                "| new  | 2.0   |",
+                "| old  | 1.0   |",
+                "+------+-------+",
+            ],
+            &query().await
+        );
+
+        // an invalid period already in the catalog is not enforced, and is reported as invalid:
+        let db_schema = write_buffer.catalog().db_schema("test_db").unwrap();
+        for period in [0, -1] {
+            write_buffer
+                .catalog()
+                .apply_catalog_batch(&CatalogBatch {
+                    database_id: db_schema.id,
+                    database_name: Arc::clone(&db_schema.name),
+                    time_ns: 0,
+                    ops: vec![CatalogOp::SetRetentionPeriod(RetentionPeriodDefinition {
+                        database_id: db_schema.id,
+                        database_name: Arc::clone(&db_schema.name),
+                        retention_period_ns: Some(period),
+                    })],
+                })
+                .unwrap();
+            assert_eq!(2, query().await.iter().map(|b| b.num_rows()).sum::<usize>());
+            let batches: Vec<RecordBatch> = query_executor
+                .show_retention_policies(Some("test_db"), None)
+                .await
+                .unwrap()
+                .try_collect()
+                .await
+                .unwrap();
+            let batch = concat_batches(&batches[0].schema(), &batches).unwrap();
+            assert_eq!(period, batch.column(2).as_primitive::<Int64Type>().value(0));
+            let error = batch.column(4).as_string::<i32>().value(0);
+            assert!(error.contains("not positive"), "unexpected error: {error}");
+        }
+    }
+
+    #[test_log::test(tokio::test)]
+    async fn named_retention_policies() {
+        let (write_buffer, query_executor, _) = setup().await;
+        write_buffer
+            .write_lp(
+                NamespaceName::new("test_db").unwrap(),
+                "cpu,host=a usage=1 1",
+                Time::from_timestamp_nanos(0),
+                false,
+                influxdb3_write::Precision::Nanosecond,
+            )
+            .await
+            .unwrap();
+        for (name, duration, default) in [
+            (
+                "two_weeks",
+                Some(Duration::from_secs(14 * 24 * 3600)),
+                false,
+            ),
+            ("one_day", Some(Duration::from_secs(24 * 3600)), true),
+            ("forever", None, false),
+        ] {
+            write_buffer
+                .create_retention_policy("test_db".into(), name.into(), duration, default)
+                .await
+                .unwrap();
+        }
+
+        let show = || async {
+            query_executor
+                .show_retention_policies(Some("test_db"), None)
+                .await
+                .unwrap()
+                .try_collect::<Vec<RecordBatch>>()
+                .await
+                .unwrap()
+        };
+        // compared in order, as the policies are sorted by name:
+        assert_batches_eq!(
+            [
+                "+---------------+-----------+------------------+---------+-------+",
+                "| iox::database | name      | duration         | default | error |",
+                "+---------------+-----------+------------------+---------+-------+",
+                "| test_db       | forever   |                  | false   |       |",
+                "| test_db       | one_day   | 86400000000000   | true    |       |",
+                "| test_db       | two_weeks | 1209600000000000 | false   |       |",
+                "+---------------+-----------+------------------+---------+-------+",
+            ],
+            &show().await
+        );
+        assert_eq!(
+            Some(86_400_000_000_000),
+            write_buffer
+                .catalog()
+                .db_schema("test_db")
+                .unwrap()
+                .retention_period_ns
+        );
+
+        // making another policy the default replaces the previous default:
+        write_buffer
+            .create_retention_policy(
+                "test_db".into(),
+                "two_weeks".into(),
+                Some(Duration::from_secs(14 * 24 * 3600)),
+                true,
+            )
+            .await
+            .unwrap();
+        assert_batches_eq!(
+            [
+                "+---------------+-----------+------------------+---------+-------+",
+                "| iox::database | name      | duration         | default | error |",
+                "+---------------+-----------+------------------+---------+-------+",
+                "| test_db       | forever   |                  | false   |       |",
+                "| test_db       | one_day   | 86400000000000   | false   |       |",
+                "| test_db       | two_weeks | 1209600000000000 | true    |       |",
+                "+---------------+-----------+------------------+---------+-------+",
+            ],
+            &show().await
+        );
+        assert_eq!(
+            Some(1_209_600_000_000_000),
+            write_buffer
+                .catalog()
+                .db_schema("test_db")
+                .unwrap()
+                .retention_period_ns
+        );
+
+        // setting the retention period of the database sets the duration of its default policy:
+        write_buffer
+            .set_retention_period("test_db".into(), Some(Duration::from_secs(3600)))
+            .await
+            .unwrap();
+        assert_batches_eq!(
+            [
+                "+---------------+-----------+----------------+---------+-------+",
+                "| iox::database | name      | duration       | default | error |",
+                "+---------------+-----------+----------------+---------+-------+",
+                "| test_db       | forever   |                | false   |       |",
+                "| test_db       | one_day   | 86400000000000 | false   |       |",
+                "| test_db       | two_weeks | 3600000000000  | true    |       |",
+                "+---------------+-----------+----------------+---------+-------+",
+            ],
+            &show().await
+        );
+    }
+
+    #[test_log::test(tokio::test)]
+    async fn custom_default_retention_policy() {
+        let (write_buffer, mut query_executor, _) = setup().await;
+        write_buffer
+            .write_lp(
+                NamespaceName::new("test_db").unwrap(),
+                "cpu,host=a usage=1 1",
+                Time::from_timestamp_nanos(0),
+                false,
+                influxdb3_write::Precision::Nanosecond,
+            )
+            .await
+            .unwrap();
+        query_executor.default_retention_policy = "default".into();
+
+        let batches: Vec<RecordBatch> = query_executor
+            .show_retention_policies(Some("test_db"), None)
+            .await
+            .unwrap()
+            .try_collect()
+            .await
+            .unwrap();
+        assert_batches_sorted_eq!(
+            [
+                "+---------------+---------+----------+---------+-------+",
+                "| iox::database | name    | duration | default | error |",
+                "+---------------+---------+----------+---------+-------+",
+                "| test_db       | default |          | true    |       |",
+                "+---------------+---------+----------+---------+-------+",
+            ],
+            &batches
+        );
+    }
+
+    #[test_log::test(tokio::test)]
+    async fn show_databases_with_stats() {
+        let (write_buffer, query_executor, time_provider) = setup().await;
+        let db_name = "test_db";
+        // write over time for several files to be persisted:
+        for i in 0..10 {
+            let time = i * 10;
+            write_buffer
+                .write_lp(
+                    NamespaceName::new(db_name).unwrap(),
+                    "\
+                    cpu,host=a,region=us-east usage=250\n\
+                    mem,host=a,region=us-east usage=150000\n\
+                    ",
+                    Time::from_timestamp_nanos(time),
+                    false,
+                    influxdb3_write::Precision::Nanosecond,
+                )
+                .await
+                .unwrap();
+            time_provider.set(Time::from_timestamp(time + 1, 0).unwrap());
+        }
+        time_provider.set(Time::from_timestamp(20, 0).unwrap());
+        tokio::time::sleep(Duration::from_millis(500)).await;
+
+        // without stats, only the names are listed:
+        let batches: Vec<RecordBatch> = query_executor
+            .show_databases(false, false)
+            .unwrap()
+            .try_collect()
+            .await
+            .unwrap();
+        assert_batches_eq!(
+            [
+                "+---------------+",
+                "| iox::database |",
+                "+---------------+",
+                "| test_db       |",
+                "+---------------+",
+            ],
+            &batches
+        );
+
+        // with stats, the counts are those of the tables and persisted files of the database:
+        let batches: Vec<RecordBatch> = query_executor
+            .show_databases(false, true)
+            .unwrap()
+            .try_collect()
+            .await
+            .unwrap();
+        let batch = concat_batches(&batches[0].schema(), &batches).unwrap();
+        let schema = batch.schema();
+        let columns = schema
+            .fields()
+            .iter()
+            .map(|field| field.name().as_str())
+            .collect::<Vec<_>>();
+        assert_eq!(
+            [
+                "iox::database",
+                "table_count",
+                "total_row_count",
+                "estimated_size_bytes"
+            ],
+            columns.as_slice()
+        );
+        let db_schema = write_buffer.catalog().db_schema(db_name).unwrap();
+        let (rows, bytes) = ["cpu", "mem"]
+            .into_iter()
+            .flat_map(|table| {
+                let table_id = db_schema.table_name_to_id(table).unwrap();
+                write_buffer.parquet_files(db_schema.id, table_id)
+            })
+            .fold((0, 0), |(rows, bytes), file| {
+                (rows + file.row_count, bytes + file.size_bytes)
+            });
+        assert!(rows > 0, "expected persisted rows");
+        assert_eq!(1, batch.num_rows());
+        assert_eq!(2, batch.column(1).as_primitive::<UInt64Type>().value(0));
+        assert_eq!(rows, batch.column(2).as_primitive::<UInt64Type>().value(0));
+        assert_eq!(bytes, batch.column(3).as_primitive::<UInt64Type>().value(0));
+    }
+
+    #[test_log::test(tokio::test)]
+    async fn show_column_names() {
+        let (write_buffer, mut query_executor, _) = setup().await;
+        write_buffer
+            .write_lp(
+                NamespaceName::new("test_db").unwrap(),
+                "cpu,host=a usage=1 1",
+                Time::from_timestamp_nanos(0),
+                false,
+                influxdb3_write::Precision::Nanosecond,
+            )
+            .await
+            .unwrap();
+
+        for (column_names, database) in [
+            (ColumnNames::Legacy, "iox::database"),
+            (ColumnNames::Friendly, "database"),
+        ] {
+            query_executor.column_names = column_names;
+            let databases: Vec<RecordBatch> = query_executor
+                .show_databases(true, false)
+                .unwrap()
+                .try_collect()
+                .await
+                .unwrap();
+            let retention_policies: Vec<RecordBatch> = query_executor
+                .show_retention_policies(None, None)
+                .await
+                .unwrap()
+                .try_collect()
+                .await
+                .unwrap();
+            for batches in [databases, retention_policies] {
+                let schema = batches[0].schema();
+                assert_eq!(database, schema.field(0).name());
+                assert!(
+                    schema
+                        .fields()
+                        .iter()
+                        .all(|field| field.name() == database || !field.name().contains("::")),
+                    "unexpected columns in {schema:?}"
+                );
+            }
+        }
+    }
+
+    #[test_log::test(tokio::test(flavor = "multi_thread", worker_threads = 2))]
+    async fn show_retention_policies_while_deleting_databases() {
+        let (write_buffer, query_executor, _) = setup().await;
+        let databases = (0..20).map(|i| format!("db_{i:02}")).collect::<Vec<_>>();
+        for database in &databases {
+            write_buffer
+                .write_lp(
+                    NamespaceName::new(database.as_str()).unwrap(),
+                    "cpu,host=a usage=1 1",
+                    Time::from_timestamp_nanos(0),
+                    false,
+                    influxdb3_write::Precision::Nanosecond,
+                )
+                .await
+                .unwrap();
+        }
+
+        let delete = tokio::spawn({
+            let write_buffer = Arc::clone(&write_buffer);
+            let databases = databases.clone();
+            async move {
+                for database in databases {
+                    write_buffer.soft_delete_database(database).await.unwrap();
+                    tokio::task::yield_now().await;
+                }
+            }
+        });
+        // listing all of the databases does not fail on those deleted while it runs:
+        while !delete.is_finished() {
+            let batches: Vec<RecordBatch> = query_executor
+                .show_retention_policies(None, None)
+                .await
+                .unwrap()
+                .try_collect()
+                .await
+                .unwrap();
+            for batch in batches {
+                for name in batch.column(0).as_string::<i32>().iter().flatten() {
+                    assert!(databases.iter().any(|db| db == name), "unexpected {name}");
+                }
+            }
+        }
+        delete.await.unwrap();
+
+        // while showing a single deleted database still does:
+        let error = query_executor
+            .show_retention_policies(Some("db_00"), None)
+            .await
+            .unwrap_err();
+        assert!(
+            matches!(error, QueryExecutorError::DatabaseNotFound { .. }),
+            "unexpected error: {error}"
+        );
+    }
+
+    #[test_log::test(tokio::test)]
+    async fn merge_partial_aggregates() {
+        let (write_buffer, query_executor, _) = setup().await;
+        let db_name = "test_db";
+        write_buffer
+            .write_lp(
+                NamespaceName::new(db_name).unwrap(),
+                "\
+                cpu,host=a usage=1 1\n\
+                cpu,host=a usage=2 2\n\
+                cpu,host=a usage=4 3\n\
+                cpu,host=b usage=8 1\n\
+                cpu,host=b usage=16 3",
+                Time::from_timestamp_nanos(0),
+                false,
+                influxdb3_write::Precision::Second,
+            )
+            .await
+            .unwrap();
+
+        let query = |filter: &'static str, partial_aggregates: bool| {
+            let options = QueryOptions {
+                partial_aggregates,
+                ..Default::default()
+            };
+            let query_executor = &query_executor;
+            async move {
+                query_executor
+                    .query_with_options(
+                        db_name,
+                        &format!(
+                            "SELECT host, avg(usage), count(usage), sum(usage) FROM cpu \
+                            WHERE {filter} GROUP BY host"
+                        ),
+                        None,
+                        QueryKind::Sql,
+                        options,
+                        None,
+                        None,
+                    )
+                    .await
+                    .unwrap()
+                    .try_collect::<Vec<RecordBatch>>()
+                    .await
+                    .unwrap()
+            }
+        };
+
+        // the data is split between two partial queries, as it would be between two servers:
+        let mut partials = query("time < '1970-01-01T00:00:02Z'", true).await;
+        partials.extend(query("time >= '1970-01-01T00:00:02Z'", true).await);
+        let merged = merge_partials(partials).await.unwrap();
+        let single = query("true", false).await;
+        let expected = [
+            "+------+--------------------+------------------+----------------+",
+            "| host | avg(cpu.usage)     | count(cpu.usage) | sum(cpu.usage) |",
+            "+------+--------------------+------------------+----------------+",
+            "| a    | 2.3333333333333335 | 3                | 7.0            |",
+            "| b    | 12.0               | 2                | 24.0           |",
+            "+------+--------------------+------------------+----------------+",
+        ];
+        assert_batches_sorted_eq!(expected, &single);
+        assert_batches_sorted_eq!(expected, &merged);
+
+        // aggregates whose state cannot be merged are rejected:
+        let err = query_executor
+            .query_with_options(
+                db_name,
+                "SELECT host, max(usage) FROM cpu GROUP BY host",
+                None,
+                QueryKind::Sql,
+                QueryOptions {
+                    partial_aggregates: true,
+                    ..Default::default()
+                },
+                None,
+                None,
+            )
+            .await
+            .unwrap_err();
+        assert!(
+            matches!(err, QueryExecutorError::PartialAggregateUnsupported { .. }),
+            "{err}"
+        );
+    }
+
+    #[test_log::test(tokio::test)]
+    async fn max_query_time_range() {
+        let (write_buffer, mut query_executor, _) = setup().await;
+        query_executor.max_query_time_range = Some(Duration::from_secs(60));
+        let db_name = "test_db";
+        let lp = (0..10)
+            .map(|i| format!("cpu,host=h{i} usage={i} {}\n", i * 10))
+            .collect::<String>();
+        write_buffer
+            .write_lp(
+                NamespaceName::new(db_name).unwrap(),
+                &lp,
+                Time::from_timestamp_nanos(0),
+                false,
+                influxdb3_write::Precision::Second,
+            )
+            .await
+            .unwrap();
+
+        let count = |query: &'static str, allow_unbounded_time_range: bool| {
+            let query_executor = &query_executor;
+            async move {
+                let options = QueryOptions {
+                    allow_unbounded_time_range,
+                    ..Default::default()
+                };
+                let batches: Vec<RecordBatch> = query_executor
+                    .query_with_options(db_name, query, None, QueryKind::Sql, options, None, None)
+                    .await?
+                    .try_collect()
+                    .await
+                    .map_err(QueryExecutorError::ExecuteStream)?;
+                Ok::<_, QueryExecutorError>(
+                    batches[0].column(0).as_primitive::<Int64Type>().value(0),
+                )
+            }
+        };
+
+        // a range that is narrower than the limit of a minute:
+        let narrow = "SELECT COUNT(*) FROM cpu \
+            WHERE time >= '1970-01-01T00:00:10Z' AND time < '1970-01-01T00:00:40Z'";
+        assert_eq!(3, count(narrow, false).await.unwrap());
+
+        // a range that is wider than the limit:
+        let wide = "SELECT COUNT(*) FROM cpu \
+            WHERE time >= '1970-01-01T00:00:00Z' AND time < '1970-01-01T00:02:00Z'";
+        let error = count(wide, false).await.unwrap_err();
+        assert!(
+            matches!(
+                error,
+                QueryExecutorError::TimeRangeTooLarge {
+                    span: Some(span),
+                    max,
+                } if span == Duration::from_secs(120) && max == Duration::from_secs(60)
+            ),
+            "unexpected error: {error}"
+        );
+
+        // a range without a lower bound, which ends at the time the query is run:
+        let unbounded = "SELECT COUNT(*) FROM cpu WHERE host = 'h1'";
+        let error = count(unbounded, false).await.unwrap_err();
+        assert!(
+            matches!(
+                error,
+                QueryExecutorError::TimeRangeTooLarge { span: None, .. }
+            ),
+            "unexpected error: {error}"
+        );
+
+        // unless the query is allowed to span any range of time:
+        assert_eq!(10, count(wide, true).await.unwrap());
+        assert_eq!(1, count(unbounded, true).await.unwrap());
+    }
+
+    #[test_log::test(tokio::test)]
+    async fn require_time_predicate() {
+        let (write_buffer, query_executor, _) = setup().await;
+        let db_name = "test_db";
+        let lp = (0..10)
+            .map(|i| {
+                let time = i * 10;
+                format!("cpu,host=h{i} usage={i} {time}\nmem,host=h{i} used={i} {time}\n")
+            })
+            .collect::<String>();
+        write_buffer
+            .write_lp(
+                NamespaceName::new(db_name).unwrap(),
+                &lp,
+                Time::from_timestamp_nanos(0),
+                false,
+                influxdb3_write::Precision::Second,
+            )
+            .await
+            .unwrap();
+        write_buffer
+            .set_require_time_predicate(db_name.to_string(), "cpu".to_string(), true)
+            .await
+            .unwrap();
+
+        let count = |query: &'static str| {
+            let query_executor = &query_executor;
+            async move {
+                let batches: Vec<RecordBatch> = query_executor
+                    .query(db_name, query, None, QueryKind::Sql, None, None)
+                    .await?
+                    .try_collect()
+                    .await
+                    .map_err(QueryExecutorError::ExecuteStream)?;
+                Ok::<_, QueryExecutorError>(
+                    batches[0].column(0).as_primitive::<Int64Type>().value(0),
+                )
+            }
+        };
+
+        // a query with a lower bound on time is accepted:
+        let bounded = "SELECT COUNT(*) FROM cpu WHERE time >= '1970-01-01T00:00:30Z'";
+        assert_eq!(7, count(bounded).await.unwrap());
+
+        // one without is rejected, even if it has an upper bound:
+        let unbounded = "SELECT COUNT(*) FROM cpu WHERE time < '1970-01-01T00:00:30Z'";
+        let error = count(unbounded).await.unwrap_err();
+        assert!(
+            matches!(
+                &error,
+                QueryExecutorError::TimePredicateRequired { table } if table == "cpu"
+            ),
+            "unexpected error: {error}"
+        );
+
+        // tables that do not require a lower bound on time are not affected:
+        assert_eq!(10, count("SELECT COUNT(*) FROM mem").await.unwrap());
+
+        // nor is the table once the requirement is lifted:
+        write_buffer
+            .set_require_time_predicate(db_name.to_string(), "cpu".to_string(), false)
+            .await
+            .unwrap();
+        assert_eq!(3, count(unbounded).await.unwrap());
+    }
+
+    #[test_log::test(tokio::test)]
+    async fn max_time_buckets() {
+        let (write_buffer, mut query_executor, _) = setup().await;
+        query_executor.max_time_buckets = Some(100);
+        let db_name = "test_db";
+        let lp = (0..60)
+            .map(|i| format!("cpu,host=a usage={i} {}\n", i * 60))
+            .collect::<String>();
+        write_buffer
+            .write_lp(
+                NamespaceName::new(db_name).unwrap(),
+                &lp,
+                Time::from_timestamp_nanos(0),
+                false,
+                influxdb3_write::Precision::Second,
+            )
+            .await
+            .unwrap();
+
+        let query = |interval: &str| {
+            let query_executor = &query_executor;
+            let query = format!(
+                "SELECT mean(usage) FROM cpu \
+                WHERE time >= '1970-01-01T00:00:00Z' AND time < '1970-01-01T01:00:00Z' \
+                GROUP BY time({interval})"
+            );
+            async move {
+                let batches: Vec<RecordBatch> = query_executor
+                    .query(db_name, &query, None, QueryKind::InfluxQl, None, None)
+                    .await?
+                    .try_collect()
+                    .await
+                    .map_err(QueryExecutorError::ExecuteStream)?;
+                Ok::<_, QueryExecutorError>(batches.iter().map(|b| b.num_rows()).sum::<usize>())
+            }
+        };
+
+        // an hour in minutes is within the limit:
+        assert_eq!(60, query("1m").await.unwrap());
+
+        // but an hour in seconds is not:
+        let error = query("1s").await.unwrap_err();
+        assert!(
+            matches!(
+                error,
+                QueryExecutorError::TooManyTimeBuckets {
+                    count: 3600,
+                    max: 100
+                }
+            ),
+            "unexpected error: {error}"
+        );
+
+        // the limit only applies to InfluxQL queries:
+        let sql = "SELECT date_bin(INTERVAL '1 second', time) AS t, avg(usage) FROM cpu \
+            WHERE time >= '1970-01-01T00:00:00Z' AND time < '1970-01-01T01:00:00Z' \
+            GROUP BY t";
+        query_executor
+            .query(db_name, sql, None, QueryKind::Sql, None, None)
+            .await
+            .unwrap();
+    }
+
+    #[test_log::test(tokio::test)]
+    async fn unlogged_queries() {
+        let (write_buffer, query_executor, _) = setup().await;
+        let db_name = "test_db";
+        write_buffer
+            .write_lp(
+                NamespaceName::new(db_name).unwrap(),
+                "cpu,host=a usage=1 1\n",
+                Time::from_timestamp_nanos(0),
+                false,
+                influxdb3_write::Precision::Nanosecond,
+            )
+            .await
+            .unwrap();
+
+        let count = |log: bool| {
+            let query_executor = &query_executor;
+            async move {
+                let options = QueryOptions {
+                    log,
+                    ..Default::default()
+                };
+                let batches: Vec<RecordBatch> = query_executor
+                    .query_with_options(
+                        db_name,
+                        "SELECT COUNT(*) FROM cpu",
+                        None,
+                        QueryKind::Sql,
+                        options,
+                        None,
+                        None,
+                    )
+                    .await
+                    .unwrap()
+                    .try_collect()
+                    .await
+                    .unwrap();
+                batches[0].column(0).as_primitive::<Int64Type>().value(0)
+            }
+        };
+
+        // the query is executed, but not recorded in the log:
+        assert_eq!(1, count(false).await);
+        assert!(query_executor.query_log.entries().entries.is_empty());
+
+        // unlike queries that are logged, as they are by default:
+        assert_eq!(1, count(true).await);
+        assert_eq!(1, query_executor.query_log.entries().entries.len());
+    }
+
+    #[test_log::test(tokio::test)]
+    async fn case_insensitive_collation() {
+        let (write_buffer, query_executor, _) = setup().await;
+        let db_name = "test_db";
+        write_buffer
+            .write_lp(
+                NamespaceName::new(db_name).unwrap(),
+                "\
+                cpu,host=apple usage=1 1\n\
+                cpu,host=Banana usage=2 1\n\
+                cpu,host=cherry usage=3 1\n\
+                cpu,host=Date usage=4 1\n\
+                ",
+                Time::from_timestamp_nanos(0),
+                false,
+                influxdb3_write::Precision::Nanosecond,
+            )
+            .await
+            .unwrap();
+
+        let hosts = |query: &'static str, collation: Collation| {
+            let query_executor = &query_executor;
+            async move {
+                let options = QueryOptions {
+                    collation,
+                    ..Default::default()
+                };
+                let batches: Vec<RecordBatch> = query_executor
+                    .query_with_options(db_name, query, None, QueryKind::Sql, options, None, None)
+                    .await
+                    .unwrap()
+                    .try_collect()
+                    .await
+                    .unwrap();
+                batches
+                    .iter()
+                    .flat_map(|batch| {
+                        let hosts = arrow::compute::cast(batch.column(0), &DataType::Utf8).unwrap();
+                        hosts
+                            .as_string::<i32>()
+                            .iter()
+                            .map(|host| host.unwrap().to_string())
+                            .collect::<Vec<_>>()
+                    })
+                    .collect::<Vec<_>>()
+            }
+        };
+
+        for (query, binary, case_insensitive) in [
+            (
+                "SELECT host FROM cpu ORDER BY host",
+                vec!["Banana", "Date", "apple", "cherry"],
+                vec!["apple", "Banana", "cherry", "Date"],
+            ),
+            (
+                "SELECT host FROM cpu WHERE host = 'APPLE'",
+                vec![],
+                vec!["apple"],
+            ),
+            (
+                "SELECT host FROM cpu WHERE host IN ('banana', 'date') ORDER BY host",
+                vec![],
+                vec!["Banana", "Date"],
+            ),
+            (
+                "SELECT host FROM cpu WHERE host < 'c' ORDER BY host",
+                vec!["Banana", "Date", "apple"],
+                vec!["apple", "Banana"],
+            ),
+        ] {
+            assert_eq!(binary, hosts(query, Collation::Binary).await, "{query}");
+            assert_eq!(
+                case_insensitive,
+                hosts(query, Collation::CaseInsensitive).await,
+                "{query}"
+            );
+        }
+    }
+
+    #[test_log::test(tokio::test)]
+    async fn wildcard_exclusions() {
+        let (write_buffer, query_executor, _) = setup().await;
+        let db_name = "test_db";
+        write_buffer
+            .write_lp(
+                NamespaceName::new(db_name).unwrap(),
+                "\
+                cpu,host=a usage=1,internal_field=10 1\n\
+                cpu,host=b usage=2,internal_field=20 2\n\
+                ",
+                Time::from_timestamp_nanos(0),
+                false,
+                influxdb3_write::Precision::Nanosecond,
+            )
+            .await
+            .unwrap();
+        let query = |query: &'static str| {
+            query_executor.query(db_name, query, None, QueryKind::Sql, None, None)
+        };
+        let columns = |batches: &[RecordBatch]| {
+            batches[0]
+                .schema()
+                .fields()
+                .iter()
+                .map(|f| f.name().to_string())
+                .collect::<Vec<_>>()
+        };
+
+        // the excluded column is left out of the columns of the wildcard, in their usual order:
+        let all: Vec<RecordBatch> = query("SELECT * FROM cpu")
+            .await
+            .unwrap()
+            .try_collect()
+            .await
+            .unwrap();
+        let batches: Vec<RecordBatch> =
+            query("SELECT * EXCEPT (internal_field) FROM cpu ORDER BY host")
+                .await
+                .unwrap()
+                .try_collect()
+                .await
+                .unwrap();
+        let mut expected = columns(&all);
+        expected.retain(|c| c != "internal_field");
+        assert_eq!(expected, columns(&batches));
+        assert_eq!(2, batches.iter().map(|b| b.num_rows()).sum::<usize>());
+
+        // as it is from a subquery:
+        let batches: Vec<RecordBatch> =
+            query("SELECT count(*) FROM (SELECT * EXCLUDE (internal_field, usage) FROM cpu) AS c")
+                .await
+                .unwrap()
+                .try_collect()
+                .await
+                .unwrap();
+        assert_batches_sorted_eq!(
+            [
+                "+----------+",
+                "| count(*) |",
+                "+----------+",
+                "| 2        |",
+                "+----------+",
+            ],
+            &batches
+        );
+
+        // excluding a column that is not in the table fails the query:
+        let Err(error) = query("SELECT * EXCEPT (internal_feild) FROM cpu").await else {
+            panic!("excluding an unknown column should fail");
+        };
+        assert!(
+            matches!(
+                &error,
+                QueryExecutorError::UnknownColumn { name, suggestions }
+                    if name == "internal_feild" && suggestions == &["internal_field"]
+            ),
+            "unexpected error: {error}"
+        );
+    }
+
+    #[test_log::test(tokio::test)]
+    async fn table_rewrites() {
+        let (write_buffer, query_executor, _) = setup().await;
+        let db_name = "test_db";
+        write_buffer
+            .write_lp(
+                NamespaceName::new(db_name).unwrap(),
+                "\
+                cpu,host=a usage=1 1\n\
+                cpu_v2,host=a usage=2 1\n\
+                ",
+                Time::from_timestamp_nanos(0),
+                false,
+                influxdb3_write::Precision::Nanosecond,
+            )
+            .await
+            .unwrap();
+
+        let query = |query: &'static str, kind: QueryKind, rewrites: &[(&str, &str)]| {
+            let query_executor = &query_executor;
+            let options = QueryOptions {
+                table_rewrites: rewrites
+                    .iter()
+                    .map(|(from, to)| (from.to_string(), to.to_string()))
+                    .collect(),
+                ..Default::default()
+            };
+            async move {
+                let batches: Vec<RecordBatch> = query_executor
+                    .query_with_options(db_name, query, None, kind, options, None, None)
+                    .await?
+                    .try_collect()
+                    .await
+                    .map_err(QueryExecutorError::ExecuteStream)?;
+                Ok::<_, QueryExecutorError>(batches)
+            }
+        };
+
+        let batches = query(
+            "SELECT host, usage FROM cpu",
+            QueryKind::Sql,
+            &[("cpu", "cpu_v2")],
+        )
+        .await
+        .unwrap();
+        assert_batches_sorted_eq!(
+            [
+                "+------+-------+",
+                "| host | usage |",
+                "+------+-------+",
+                "| a    | 2.0   |",
+                "+------+-------+",
+            ],
+            &batches
+        );
+        let batches = query(
+            "SELECT usage FROM cpu",
+            QueryKind::InfluxQl,
+            &[("cpu", "cpu_v2")],
+        )
+        .await
+        .unwrap();
+        let batch = concat_batches(&batches[0].schema(), &batches).unwrap();
+        let usage = batch
+            .column_by_name("usage")
+            .unwrap()
+            .as_primitive::<Float64Type>();
+        assert_eq!([2.0], usage.values().as_ref());
+
+        // a table can be rewritten to one that does not exist yet, as long as it is not queried:
+        query(
+            "SELECT host, usage FROM cpu",
+            QueryKind::Sql,
+            &[("mem", "mem_v2")],
+        )
+        .await
+        .unwrap();
+        let error = query(
+            "SELECT host, usage FROM cpu",
+            QueryKind::Sql,
+            &[("cpu", "cpu_v3")],
+        )
+        .await
+        .unwrap_err();
+        assert!(
+            matches!(
+                &error,
+                QueryExecutorError::TableRewriteTargetNotFound { table, target }
+                    if table == "cpu" && target == "cpu_v3"
+            ),
+            "unexpected error: {error}"
+        );
+
+        // system tables are not rewritten:
+        query(
+            "SELECT * FROM system.queries",
+            QueryKind::Sql,
+            &[("queries", "cpu_v2")],
+        )
+        .await
+        .unwrap();
+    }
+
+    #[test_log::test(tokio::test)]
+    async fn aggregate_budget_spills() {
+        let (write_buffer, mut query_executor, _) = setup().await;
+        let db_name = "test_db";
+        let hosts = 20_000;
+        for (table, field) in [("cpu", "usage"), ("mem", "used")] {
+            let lp = (0..hosts)
+                .map(|i| format!("{table},host=host-{i:05} {field}={i} {i}\n"))
+                .collect::<String>();
+            write_buffer
+                .write_lp(
+                    NamespaceName::new(db_name).unwrap(),
+                    &lp,
+                    Time::from_timestamp_nanos(0),
+                    false,
+                    influxdb3_write::Precision::Nanosecond,
+                )
+                .await
+                .unwrap();
+        }
+        // the budget is set for the session, and is well below the memory needed to aggregate
+        // all of the hosts, or to build the join:
+        let budget = 512 * 1024;
+        query_executor.datafusion_config = Arc::new(HashMap::from([
+            (
+                AGGREGATE_MEM_POOL_SIZE_CONFIG_KEY.to_string(),
+                budget.to_string(),
+            ),
+            (
+                "datafusion.execution.batch_size".to_string(),
+                "1024".to_string(),
+            ),
+        ]));
+        let query = "SELECT cpu.host, sum(cpu.usage + mem.used) AS total \
+            FROM cpu JOIN mem ON cpu.host = mem.host GROUP BY cpu.host";
+
+        let batches: Vec<RecordBatch> = query_executor
+            .query(
+                db_name,
+                &format!("EXPLAIN ANALYZE {query}"),
+                None,
+                QueryKind::Sql,
+                None,
+                None,
+            )
+            .await
+            .unwrap()
+            .try_collect()
+            .await
+            .unwrap();
+        let plan = batches
+            .iter()
+            .flat_map(|batch| {
+                let types = batch
+                    .column_by_name("plan_type")
+                    .unwrap()
+                    .as_string::<i32>();
+                let plans = batch.column_by_name("plan").unwrap().as_string::<i32>();
+                types.iter().zip(plans.iter())
+            })
+            .find_map(|(plan_type, plan)| {
+                (plan_type == Some("Plan with Metrics")).then(|| plan.unwrap().to_string())
+            })
+            .expect("EXPLAIN ANALYZE should output the plan with metrics");
+        // the value of the named metric of the first operator with the given name in the plan:
+        let metric = |operator: &str, name: &str| -> usize {
+            let line = plan
+                .lines()
+                .find(|line| line.trim_start().starts_with(operator))
+                .unwrap_or_else(|| panic!("no {operator} in plan:\n{plan}"));
+            let (_, value) = line
+                .split_once(&format!("{name}="))
+                .unwrap_or_else(|| panic!("no {name} metric for {operator}:\n{line}"));
+            value
+                .chars()
+                .take_while(char::is_ascii_digit)
+                .collect::<String>()
+                .parse()
+                .unwrap()
+        };
+        // the aggregate spilled under its budget, which it would not do were it not recognized
+        // as an aggregate by the budget:
+        assert!(metric("AggregateExec", "spill_count") > 0, "{plan}");
+        // while the join used more memory than the aggregate budget, from the executor's pool:
+        assert!(metric("HashJoinExec", "build_mem_used") > budget, "{plan}");
+
+        // and the results are complete, in spite of the spilling:
+        let batches: Vec<RecordBatch> = query_executor
+            .query(
+                db_name,
+                &format!("SELECT count(*) AS hosts, sum(total) AS total FROM ({query})"),
+                None,
+                QueryKind::Sql,
+                None,
+                None,
+            )
+            .await
+            .unwrap()
+            .try_collect()
+            .await
+            .unwrap();
+        assert_batches_eq!(
+            [
+                "+-------+-------------+",
+                "| hosts | total       |",
+                "+-------+-------------+",
+                "| 20000 | 399980000.0 |",
+                "+-------+-------------+",
+            ],
+            &batches
+        );
+    }
+
+    #[test_log::test(tokio::test)]
+    async fn query_job_submit_poll_and_fetch() {
+        let (write_buffer, query_executor, _) = setup().await;
+        let db_name = "test_db";
+        write_buffer
+            .write_lp(
+                NamespaceName::new(db_name).unwrap(),
+                "\
+                cpu,host=a,region=us-east usage=250\n\
+                cpu,host=b,region=us-east usage=150\n\
+                ",
+                Time::from_timestamp_nanos(0),
+                false,
+                influxdb3_write::Precision::Nanosecond,
+            )
+            .await
+            .unwrap();
+
+        let id = query_executor
+            .submit_query(db_name, "SELECT host, usage FROM cpu", None, QueryKind::Sql)
+            .await
+            .unwrap();
+
+        // poll the job until it has finished:
+        let status = tokio::time::timeout(Duration::from_secs(10), async {
+            loop {
+                let status = query_executor.job_status(id).unwrap();
+                if status.is_finished() {
+                    break status;
+                }
+                tokio::time::sleep(Duration::from_millis(10)).await;
+            }
+        })
+        .await
+        .expect("query job should finish");
+        assert_eq!(QueryJobStatus::Done, status);
+
+        let batches: Vec<RecordBatch> = query_executor
+            .fetch_result(id)
+            .await
+            .unwrap()
+            .try_collect()
+            .await
+            .unwrap();
+        assert_batches_sorted_eq!(
+            [
+                "+------+-------+",
+                "| host | usage |",
+                "+------+-------+",
+                "| a    | 250.0 |",
+                "| b    | 150.0 |",
+                "+------+-------+",
+            ],
+            &batches
+        );
+    }
+
+    #[test_log::test(tokio::test)]
+    async fn query_job_stale_results_removed() {
+        let (write_buffer, query_executor, _) = setup().await;
+        let db_name = "test_db";
+        write_buffer
+            .write_lp(
+                NamespaceName::new(db_name).unwrap(),
+                "cpu,host=a usage=250",
+                Time::from_timestamp_nanos(0),
+                false,
+                influxdb3_write::Precision::Nanosecond,
+            )
+            .await
+            .unwrap();
+        let store = query_executor.persister.object_store();
+        let host_prefix = query_executor
+            .persister
+            .host_identifier_prefix()
+            .to_string();
+        let list_results = || {
+            let (store, host_prefix) = (&store, &host_prefix);
+            async move {
+                let mut paths = store
+                    .list(Some(&ObjPath::from(format!("{host_prefix}/query_jobs"))))
+                    .map_ok(|meta| meta.location.to_string())
+                    .try_collect::<Vec<_>>()
+                    .await
+                    .unwrap();
+                paths.sort();
+                paths
+            }
+        };
+
+        let id = query_executor
+            .submit_query(db_name, "SELECT host, usage FROM cpu", None, QueryKind::Sql)
+            .await
+            .unwrap();
+        tokio::time::timeout(Duration::from_secs(10), async {
+            while !query_executor.job_status(id).unwrap().is_finished() {
+                tokio::time::sleep(Duration::from_millis(10)).await;
+            }
+        })
+        .await
+        .expect("query job should finish");
+        // results left by a previous run of the server, with an id that is not known:
+        let stale_id = "00000000-0000-4000-8000-000000000000";
+        assert_ne!(stale_id, id.to_string());
+        store
+            .put(
+                &ObjPath::from(format!("{host_prefix}/query_jobs/{stale_id}.arrows")),
+                bytes::Bytes::from_static(b"stale").into(),
+            )
+            .await
+            .unwrap();
+        let mut expected = [
+            format!("{host_prefix}/query_jobs/{id}.arrows"),
+            format!("{host_prefix}/query_jobs/{stale_id}.arrows"),
+        ];
+        expected.sort();
+        assert_eq!(expected, list_results().await.as_slice());
+
+        let expiry = query_executor
+            .start_query_job_expiry(DEFAULT_QUERY_JOB_EXPIRY_INTERVAL)
+            .await;
+        assert_eq!(
+            [format!("{host_prefix}/query_jobs/{id}.arrows")],
+            list_results().await.as_slice()
+        );
+        // the results of the known job are still there to be fetched:
+        let batches: Vec<RecordBatch> = query_executor
+            .fetch_result(id)
+            .await
+            .unwrap()
+            .try_collect()
+            .await
+            .unwrap();
+        assert_eq!(1, batches.iter().map(|b| b.num_rows()).sum::<usize>());
+        expiry.abort();
+    }
+
+    #[test_log::test(tokio::test)]
+    async fn result_ticket_refetch() {
+        let (write_buffer, query_executor, _) = setup().await;
+        for db_name in ["test_db", "other_db"] {
+            write_buffer
+                .write_lp(
+                    NamespaceName::new(db_name).unwrap(),
+                    "\
+                    cpu,host=a,region=us-east usage=250\n\
+                    cpu,host=b,region=us-east usage=150\n\
+                    ",
+                    Time::from_timestamp_nanos(0),
+                    false,
+                    influxdb3_write::Precision::Nanosecond,
+                )
+                .await
+                .unwrap();
+        }
+        let query = "SELECT host, usage FROM cpu ORDER BY host";
+
+        let expected: Vec<RecordBatch> = query_executor
+            .query("test_db", query, None, QueryKind::Sql, None, None)
+            .await
+            .unwrap()
+            .try_collect()
+            .await
+            .unwrap();
+        let ticket = query_executor
+            .issue_ticket("test_db", query, None, QueryKind::Sql)
+            .await
+            .unwrap();
+
+        // the query in the ticket is what a Flight DoGet would plan and execute:
+        let batches: Vec<RecordBatch> = query_executor
+            .query("test_db", &ticket.sql(), None, QueryKind::Sql, None, None)
+            .await
+            .unwrap()
+            .try_collect()
+            .await
+            .unwrap();
+        assert_eq!(expected, batches);
+
+        // the ticket can only be redeemed against the database that it was issued for:
+        let Err(error) = query_executor
+            .query("other_db", &ticket.sql(), None, QueryKind::Sql, None, None)
+            .await
+        else {
+            panic!("ticket should not be redeemable against another database");
+        };
+        assert!(
+            error.to_string().contains("no results found for ticket"),
+            "unexpected error: {error}"
+        );
+
+        // nor without its token, or with a token other than the one it was issued with:
+        let forged = format!("{}.{:032x}", ticket.id(), 0);
+        for ticket in [ticket.id().to_string(), forged] {
+            let sql = format!("SELECT * FROM {QUERY_RESULT_UDTF_NAME}('{ticket}')");
+            let Err(error) = query_executor
+                .query("test_db", &sql, None, QueryKind::Sql, None, None)
+                .await
+            else {
+                panic!("ticket '{ticket}' should not be redeemable");
+            };
+            assert!(
+                error.to_string().contains("result ticket")
+                    || error.to_string().contains("no results found for ticket"),
+                "unexpected error: {error}"
+            );
+        }
+    }
+
+    #[test_log::test(tokio::test)]
+    async fn explain_chunks() {
+        let (write_buffer, query_executor, _) = setup().await;
+        let db_name = "test_db";
+        // a chunk for each minute, i.e., for each gen1 block of the write buffer:
+        write_buffer
+            .write_lp(
+                NamespaceName::new(db_name).unwrap(),
+                "\
+                cpu,host=a usage=1 10\n\
+                cpu,host=a usage=2 70\n\
+                cpu,host=a usage=3 130\n\
+                mem,host=a used=4 70\n\
+                ",
+                Time::from_timestamp_nanos(0),
+                false,
+                influxdb3_write::Precision::Second,
+            )
+            .await
+            .unwrap();
+
+        let chunk_times = |query: &'static str| {
+            let query_executor = &query_executor;
+            async move {
+                let batch = query_executor
+                    .explain_chunks(db_name, query, None, QueryKind::Sql)
+                    .await
+                    .unwrap();
+                let column = |name: &str| {
+                    batch
+                        .column_by_name(name)
+                        .unwrap()
+                        .as_primitive::<Int64Type>()
+                        .values()
+                        .to_vec()
+                };
+                let mut times = column("min_time")
+                    .into_iter()
+                    .zip(column("max_time"))
+                    .map(|(min, max)| (min / 1_000_000_000, max / 1_000_000_000))
+                    .collect::<Vec<_>>();
+                times.sort();
+                times
+            }
+        };
+
+        assert_eq!(
+            vec![(10, 10), (70, 70), (130, 130)],
+            chunk_times("SELECT * FROM cpu").await
+        );
+        // only the chunk that overlaps the time range of the query is reported:
+        assert_eq!(
+            vec![(70, 70)],
+            chunk_times(
+                "SELECT * FROM cpu \
+                WHERE time >= '1970-01-01T00:01:00Z' AND time < '1970-01-01T00:02:00Z'"
+            )
+            .await
+        );
+        // as are the chunks of each table that the query reads:
+        assert_eq!(
+            vec![(70, 70), (70, 70), (130, 130)],
+            chunk_times(
+                "SELECT cpu.usage, mem.used FROM cpu JOIN mem ON cpu.host = mem.host \
+                WHERE cpu.time >= '1970-01-01T00:01:00Z' AND mem.time >= '1970-01-01T00:01:00Z'"
+            )
+            .await
+        );
+    }
+
+    #[test_log::test(tokio::test)]
+    async fn effective_query() {
+        let (write_buffer, query_executor, _) = setup().await;
+        let db_name = "test_db";
+        write_buffer
+            .write_lp(
+                NamespaceName::new(db_name).unwrap(),
+                "cpu,host=a usage=1,internal=2 1",
+                Time::from_timestamp_nanos(0),
+                false,
+                influxdb3_write::Precision::Nanosecond,
+            )
+            .await
+            .unwrap();
+        write_buffer
+            .set_retention_period(db_name.into(), Some(Duration::from_secs(3600)))
+            .await
+            .unwrap();
+
+        let batch = query_executor
+            .effective_query(
+                db_name,
+                "SELECT * EXCEPT (internal) FROM cpu WHERE host = 'a'",
+                None,
+                QueryKind::Sql,
+            )
+            .await
+            .unwrap();
+        let table_name = batch
+            .column_by_name("table_name")
+            .unwrap()
+            .as_string::<i32>();
+        let effective = batch
+            .column_by_name("effective")
+            .unwrap()
+            .as_string::<i32>();
+        assert_eq!(2, batch.num_rows());
+        // the query is reported with its wildcard expanded:
+        assert!(table_name.is_null(0));
+        assert!(
+            !effective.value(0).contains("EXCEPT") && !effective.value(0).contains("internal"),
+            "unexpected query: {}",
+            effective.value(0)
+        );
+        // and the scan of the table with the filter of the query and that of the retention
+        // period of the database:
+        assert_eq!("cpu", table_name.value(1));
+        assert!(
+            effective.value(1).contains("host") && effective.value(1).contains("time >="),
+            "unexpected filters: {}",
+            effective.value(1)
+        );
+    }
+
+    #[test_log::test(tokio::test)]
+    async fn execution_stats() {
+        let (write_buffer, query_executor, _) = setup().await;
+        let db_name = "test_db";
+        write_buffer
+            .write_lp(
+                NamespaceName::new(db_name).unwrap(),
+                "\
+                cpu,host=a usage=1 1\n\
+                cpu,host=b usage=2 2\n\
+                mem,host=a used=3 1\n\
+                ",
+                Time::from_timestamp_nanos(0),
+                false,
+                influxdb3_write::Precision::Nanosecond,
+            )
+            .await
+            .unwrap();
+
+        let query = || async {
+            let options = QueryOptions {
+                cache_results: true,
+                ..Default::default()
+            };
+            let (results, stats) = query_executor
+                .query_with_stats(
+                    db_name,
+                    "SELECT host, usage FROM cpu",
+                    None,
+                    QueryKind::Sql,
+                    options,
+                    None,
+                    None,
+                )
+                .await
+                .unwrap();
+            let batches: Vec<RecordBatch> = results.try_collect().await.unwrap();
+            (batches, stats.await)
+        };
+
+        // the stats of an executed query cover the single buffer chunk of the scanned table:
+        let (batches, stats) = query().await;
+        assert!(stats.elapsed > Duration::ZERO);
+        assert_eq!(
+            ExecutionStats {
+                rows: 2,
+                bytes: batches.iter().map(|b| b.get_array_memory_size()).sum(),
+                chunks: 1,
+                elapsed: stats.elapsed,
+                cache_hit: false,
+                replay_in_progress: false,
+            },
+            stats
+        );
+
+        // whereas the same query served from the result cache scans no chunks:
+        let (batches, stats) = query().await;
+        assert_eq!(
+            ExecutionStats {
+                rows: 2,
+                bytes: batches.iter().map(|b| b.get_array_memory_size()).sum(),
+                chunks: 0,
+                elapsed: stats.elapsed,
+                cache_hit: true,
+                replay_in_progress: false,
+            },
+            stats
+        );
+
+        // the stats are finalized with what was read when the results are dropped early:
+        let (results, stats) = query_executor
+            .query_with_stats(
+                db_name,
+                "SELECT host, usage FROM cpu",
+                None,
+                QueryKind::Sql,
+                QueryOptions::default(),
+                None,
+                None,
+            )
+            .await
+            .unwrap();
+        drop(results);
+        assert_eq!(0, stats.await.rows);
+    }
+
+    #[test_log::test(tokio::test)]
+    async fn query_during_replay() {
+        let (write_buffer, mut query_executor, _) = setup().await;
+        let db_name = "test_db";
+        write_buffer
+            .write_lp(
+                NamespaceName::new(db_name).unwrap(),
+                "cpu,host=a usage=1 1\n",
+                Time::from_timestamp_nanos(0),
+                false,
+                influxdb3_write::Precision::Nanosecond,
+            )
+            .await
+            .unwrap();
+
+        let query = |query_executor: &QueryExecutorImpl| {
+            let query_executor = query_executor.clone();
+            async move {
+                let (results, stats) = query_executor
+                    .query_with_stats(
+                        db_name,
+                        "SELECT host, usage FROM cpu",
+                        None,
+                        QueryKind::Sql,
+                        QueryOptions::default(),
+                        None,
+                        None,
+                    )
+                    .await?;
+                let batches: Vec<RecordBatch> = results.try_collect().await.unwrap();
+                Ok::<_, QueryExecutorError>((batches, stats.await))
+            }
+        };
+
+        // simulate a replay of the WAL that is still going on:
+        let replay_state = write_buffer.replay_state();
+        replay_state.start();
+
+        // which fails queries with the reject policy:
+        query_executor.replay_policy = ReplayPolicy::Reject;
+        let err = query(&query_executor).await.unwrap_err();
+        assert!(
+            matches!(err, QueryExecutorError::ReplayInProgress),
+            "unexpected error: {err}"
+        );
+        // whichever query method they are made through:
+        let errors = [
+            query_executor
+                .query_per_measurement(
+                    db_name,
+                    &["cpu"],
+                    Time::from_timestamp_nanos(0)..Time::from_timestamp_nanos(100),
+                    QueryOptions::default(),
+                )
+                .await
+                .map(|_| ())
+                .unwrap_err(),
+            query_executor
+                .query_multi(
+                    db_name,
+                    "SELECT host FROM cpu",
+                    None,
+                    QueryKind::Sql,
+                    QueryOptions::default(),
+                )
+                .await
+                .and_then(|mut streams| streams.remove(0))
+                .map(|_| ())
+                .unwrap_err(),
+            query_executor
+                .explain_chunks(db_name, "SELECT host FROM cpu", None, QueryKind::Sql)
+                .await
+                .map(|_| ())
+                .unwrap_err(),
+        ];
+        for err in errors {
+            assert!(
+                matches!(err, QueryExecutorError::ReplayInProgress),
+                "unexpected error: {err}"
+            );
+        }
+
+        // and flags the results of queries with the warn policy:
+        query_executor.replay_policy = ReplayPolicy::Warn;
+        let (batches, stats) = query(&query_executor).await.unwrap();
+        assert_batches_eq!(
+            [
+                "+------+-------+",
+                "| host | usage |",
+                "+------+-------+",
+                "| a    | 1.0   |",
+                "+------+-------+",
+            ],
+            &batches
+        );
+        assert!(stats.replay_in_progress);
+
+        // neither of which apply once the replay has finished:
+        replay_state.finish();
+        query_executor.replay_policy = ReplayPolicy::Reject;
+        let (batches, stats) = query(&query_executor).await.unwrap();
+        assert_eq!(1, batches.iter().map(RecordBatch::num_rows).sum::<usize>());
+        assert!(!stats.replay_in_progress);
+    }
+
+    #[test_log::test(tokio::test)]
+    async fn computed_columns() {
+        let (write_buffer, query_executor, _) = setup().await;
+        let db_name = "test_db";
+        write_buffer
+            .write_lp(
+                NamespaceName::new(db_name).unwrap(),
+                "\
+                weather,city=a temp_c=10 1\n\
+                weather,city=b temp_c=30 2\n\
+                ",
+                Time::from_timestamp_nanos(0),
+                false,
+                influxdb3_write::Precision::Nanosecond,
+            )
+            .await
+            .unwrap();
+        write_buffer
+            .create_computed_column(
+                db_name.to_string(),
+                "weather".to_string(),
+                "temp_f".to_string(),
+                "temp_c * 9 / 5 + 32".to_string(),
+            )
+            .await
+            .unwrap();
+
+        let query = |sql: &'static str| {
+            let query_executor = query_executor.clone();
+            async move {
+                query_executor
+                    .query(db_name, sql, None, QueryKind::Sql, None, None)
+                    .await
+                    .unwrap()
+                    .try_collect::<Vec<RecordBatch>>()
+                    .await
+                    .unwrap()
+            }
+        };
+        // the computed column is selected like a stored column:
+        assert_batches_eq!(
+            [
+                "+------+--------+--------+",
+                "| city | temp_c | temp_f |",
+                "+------+--------+--------+",
+                "| a    | 10.0   | 50.0   |",
+                "| b    | 30.0   | 86.0   |",
+                "+------+--------+--------+",
+            ],
+            &query("SELECT city, temp_c, temp_f FROM weather ORDER BY city").await
+        );
+        // and can be filtered on:
+        assert_batches_eq!(
+            [
+                "+------+--------+",
+                "| city | temp_f |",
+                "+------+--------+",
+                "| b    | 86.0   |",
+                "+------+--------+",
+            ],
+            &query("SELECT city, temp_f FROM weather WHERE temp_f > 60").await
+        );
+        // and follows the stored columns in the table's schema:
+        assert_batches_sorted_eq!(
+            [
+                "+------+--------+--------------------------------+--------+",
+                "| city | temp_c | time                           | temp_f |",
+                "+------+--------+--------------------------------+--------+",
+                "| a    | 10.0   | 1970-01-01T00:00:00.000000001Z | 50.0   |",
+                "| b    | 30.0   | 1970-01-01T00:00:00.000000002Z | 86.0   |",
+                "+------+--------+--------------------------------+--------+",
+            ],
+            &query("SELECT * FROM weather").await
+        );
+
+        // the expression must be over the stored columns of the table:
+        let error = write_buffer
+            .create_computed_column(
+                db_name.to_string(),
+                "weather".to_string(),
+                "humidity_pct".to_string(),
+                "humidity * 100".to_string(),
+            )
+            .await
+            .unwrap_err();
+        assert!(
+            matches!(
+                error,
+                influxdb3_write::write_buffer::Error::InvalidComputedColumn { .. }
+            ),
+            "unexpected error: {error}"
+        );
+        // and cannot take the name of a stored column:
+        write_buffer
+            .create_computed_column(
+                db_name.to_string(),
+                "weather".to_string(),
+                "temp_c".to_string(),
+                "temp_c + 1".to_string(),
+            )
+            .await
+            .unwrap_err();
+    }
+
+    #[test_log::test(tokio::test)]
+    async fn create_table_as() {
+        let (write_buffer, query_executor, _) = setup().await;
+        let db_name = "test_db";
+        write_buffer
+            .write_lp(
+                NamespaceName::new(db_name).unwrap(),
+                "\
+                cpu,host=a usage=1 1000000000\n\
+                cpu,host=a usage=3 2000000000\n\
+                cpu,host=b usage=5 1000000000\n\
+                ",
+                Time::from_timestamp_nanos(0),
+                false,
+                influxdb3_write::Precision::Nanosecond,
+            )
+            .await
+            .unwrap();
+
+        let query = |query: &'static str| {
+            let query_executor = &query_executor;
+            async move {
+                let results = query_executor
+                    .query_with_options(
+                        db_name,
+                        query,
+                        None,
+                        QueryKind::Sql,
+                        QueryOptions::default(),
+                        None,
+                        None,
+                    )
+                    .await?;
+                Ok::<_, QueryExecutorError>(
+                    results.try_collect::<Vec<RecordBatch>>().await.unwrap(),
+                )
+            }
+        };
+        let rollup = "\
+            CREATE TABLE cpu_1h AS \
+            SELECT date_bin(INTERVAL '1 hour', time) AS time, host, avg(usage) AS usage \
+            FROM cpu GROUP BY 1, 2";
+
+        let batches = query(rollup).await.unwrap();
+        assert_batches_eq!(
+            [
+                "+-------+",
+                "| count |",
+                "+-------+",
+                "| 2     |",
+                "+-------+"
+            ],
+            &batches
+        );
+        let batches = query("SELECT host, time, usage FROM cpu_1h ORDER BY host")
+            .await
+            .unwrap();
+        assert_batches_eq!(
+            [
+                "+------+---------------------+-------+",
+                "| host | time                | usage |",
+                "+------+---------------------+-------+",
+                "| a    | 1970-01-01T00:00:00 | 2.0   |",
+                "| b    | 1970-01-01T00:00:00 | 5.0   |",
+                "+------+---------------------+-------+",
+            ],
+            &batches
+        );
+        // the grouped tag is a tag of the new table:
+        let db_schema = write_buffer.catalog().db_schema(db_name).unwrap();
+        let table_def = db_schema.table_definition("cpu_1h").unwrap();
+        assert_eq!(
+            vec![table_def.column_name_to_id_unchecked("host")],
+            table_def.series_key
+        );
+
+        // the table is not overwritten unless asked to be:
+        let err = query(rollup).await.unwrap_err();
+        assert!(
+            matches!(&err, QueryExecutorError::TableAlreadyExists { table } if table == "cpu_1h"),
+            "unexpected error: {err}"
+        );
+        let batches = query(
+            "\
+            CREATE OR REPLACE TABLE cpu_1h AS \
+            SELECT date_bin(INTERVAL '1 hour', time) AS time, host, max(usage) AS usage \
+            FROM cpu WHERE host = 'a' GROUP BY 1, 2",
+        )
+        .await
+        .unwrap();
+        assert_batches_eq!(
+            [
+                "+-------+",
+                "| count |",
+                "+-------+",
+                "| 1     |",
+                "+-------+"
+            ],
+            &batches
+        );
+        let batches = query("SELECT host, time, usage FROM cpu_1h ORDER BY host")
+            .await
+            .unwrap();
+        assert_batches_eq!(
+            [
+                "+------+---------------------+-------+",
+                "| host | time                | usage |",
+                "+------+---------------------+-------+",
+                "| a    | 1970-01-01T00:00:00 | 3.0   |",
+                "+------+---------------------+-------+",
+            ],
+            &batches
+        );
+        // the results were written under a staging name, which was swapped in for the table:
+        let db_schema = write_buffer.catalog().db_schema(db_name).unwrap();
+        assert!(
+            db_schema
+                .tables()
+                .filter(|table_def| !table_def.deleted)
+                .all(|table_def| !table_def.table_name.contains("staging")),
+            "staging table left behind: {:?}",
+            db_schema.table_names()
+        );
+
+        // results that cannot be written fail the statement, and leave the table as it was:
+        for replace in [
+            "CREATE OR REPLACE TABLE cpu_1h AS \
+            SELECT date_bin(INTERVAL '1 hour', time) AS time, host, max(usage) / 0.0 AS usage \
+            FROM cpu GROUP BY 1, 2",
+            "CREATE OR REPLACE TABLE cpu_1h AS \
+            SELECT date_bin(INTERVAL '1 hour', time) AS time, host, \
+            CAST(NULL AS DOUBLE) AS usage \
+            FROM cpu GROUP BY 1, 2",
+        ] {
+            let err = query(replace).await.unwrap_err();
+            assert!(
+                matches!(err, QueryExecutorError::InvalidCreateTableAs { .. }),
+                "unexpected error: {err}"
+            );
+            let batches = query("SELECT host, time, usage FROM cpu_1h ORDER BY host")
+                .await
+                .unwrap();
+            assert_batches_eq!(
+                [
+                    "+------+---------------------+-------+",
+                    "| host | time                | usage |",
+                    "+------+---------------------+-------+",
+                    "| a    | 1970-01-01T00:00:00 | 3.0   |",
+                    "+------+---------------------+-------+",
+                ],
+                &batches
+            );
+        }
+    }
+
+    #[test_log::test(tokio::test)]
+    async fn distinct_cache_modes() {
+        let (write_buffer, query_executor, time_provider) = setup().await;
+        let db_name = "test_db";
+        let write = |lp: &'static str| {
+            let write_buffer = Arc::clone(&write_buffer);
+            async move {
+                write_buffer
+                    .write_lp(
+                        NamespaceName::new(db_name).unwrap(),
+                        lp,
+                        Time::from_timestamp_nanos(0),
+                        false,
+                        influxdb3_write::Precision::Nanosecond,
+                    )
+                    .await
+                    .unwrap();
+            }
+        };
+        let query = |mode: DistinctCacheMode| {
+            let query_executor = &query_executor;
+            async move {
+                let options = QueryOptions {
+                    distinct_cache_mode: mode,
+                    ..Default::default()
+                };
+                let stream = query_executor
+                    .query_with_options(
+                        db_name,
+                        "SELECT * FROM distinct_cache('cpu')",
+                        None,
+                        QueryKind::Sql,
+                        options,
+                        None,
+                        None,
+                    )
+                    .await
+                    .unwrap();
+                let complete = stream
+                    .schema()
+                    .metadata()
+                    .get(DISTINCT_CACHE_COMPLETE_METADATA_KEY)
+                    .cloned();
+                let batches: Vec<RecordBatch> = stream.try_collect().await.unwrap();
+                (complete, batches)
+            }
+        };
+
+        // the cache is created after the first host was written, so does not hold it:
+        write("cpu,region=us-east,host=a usage=1 1").await;
+        let db_schema = write_buffer.catalog().db_schema(db_name).unwrap();
+        let table_def = db_schema.table_definition("cpu").unwrap();
+        let column_ids = ["region", "host"]
+            .into_iter()
+            .map(|name| table_def.column_name_to_id_unchecked(name))
+            .collect();
+        write_buffer
+            .create_distinct_cache(
+                db_schema,
+                None,
+                CreateDistinctCacheArgs {
+                    table_def,
+                    max_cardinality: Default::default(),
+                    max_age: Duration::from_secs(10).into(),
+                    column_ids,
+                },
+            )
+            .await
+            .unwrap();
+        write("cpu,region=us-east,host=b usage=1 1").await;
+
+        // so the modes differ until the cache has been populated for its max age:
+        let (complete, fast) = query(DistinctCacheMode::Fast).await;
+        assert_eq!(Some("false"), complete.as_deref());
+        assert_batches_eq!(
+            [
+                "+---------+------+",
+                "| region  | host |",
+                "+---------+------+",
+                "| us-east | b    |",
+                "+---------+------+",
+            ],
+            &fast
+        );
+        let (_, exact) = query(DistinctCacheMode::Exact).await;
+        assert_batches_eq!(
+            [
+                "+---------+------+",
+                "| region  | host |",
+                "+---------+------+",
+                "| us-east | a    |",
+                "| us-east | b    |",
+                "+---------+------+",
+            ],
+            &exact
+        );
+
+        // once it has, both are answered from the cache:
+        time_provider.set(Time::from_timestamp(20, 0).unwrap());
+        write(
+            "\
+            cpu,region=us-west,host=d usage=1 15000000000\n\
+            cpu,region=us-east,host=c usage=1 15000000000\n\
+            ",
+        )
+        .await;
+        let (complete, fast) = query(DistinctCacheMode::Fast).await;
+        assert_eq!(Some("true"), complete.as_deref());
+        let expected = [
+            "+---------+------+",
+            "| region  | host |",
+            "+---------+------+",
+            "| us-east | c    |",
+            "| us-west | d    |",
+            "+---------+------+",
+        ];
+        assert_batches_eq!(expected, &fast);
+        let (complete, exact) = query(DistinctCacheMode::Exact).await;
+        assert_eq!(Some("true"), complete.as_deref());
+        assert_batches_eq!(expected, &exact);
+    }
 }
@@ -0,0 +1,103 @@
+//! Reordering of query output columns to the order requested in
+//! [`QueryOptions::output_columns`], and handling of output columns that share a name according
+//! to [`QueryOptions::duplicate_columns`]
+//!
+//! [`QueryOptions::output_columns`]: influxdb3_internal_api::query_executor::QueryOptions
+//! [`QueryOptions::duplicate_columns`]: influxdb3_internal_api::query_executor::QueryOptions
+use std::{collections::HashSet, sync::Arc};
+
+use datafusion::physical_plan::{
+    expressions::{col, Column},
+    projection::ProjectionExec,
+    ExecutionPlan, PhysicalExpr,
+};
+use influxdb3_internal_api::query_executor::{DuplicateColumnPolicy, QueryExecutorError};
+
+use super::suggestions;
+
+/// Apply a final projection to the `plan` that outputs exactly the `columns`, in the given
+/// order
+///
+/// The `plan` is returned unchanged if no `columns` are given.
+pub(super) fn apply_output_columns(
+    plan: Arc<dyn ExecutionPlan>,
+    columns: &[String],
+) -> Result<Arc<dyn ExecutionPlan>, QueryExecutorError> {
+    if columns.is_empty() {
+        return Ok(plan);
+    }
+    let schema = plan.schema();
+
+    let exprs = columns
+        .iter()
+        .map(|name| {
+            if schema.column_with_name(name).is_none() {
+                let candidates = schema
+                    .fields()
+                    .iter()
+                    .map(|f| f.name().to_owned())
+                    .collect::<Vec<_>>();
+                return Err(QueryExecutorError::UnknownColumn {
+                    name: name.to_owned(),
+                    suggestions: suggestions::closest_matches(name, &candidates),
+                });
+            }
+            let expr = col(name, &schema).map_err(QueryExecutorError::QueryPlanning)?;
+            Ok((expr, name.to_owned()))
+        })
+        .collect::<Result<Vec<_>, _>>()?;
+
+    Ok(Arc::new(
+        ProjectionExec::try_new(exprs, plan).map_err(QueryExecutorError::QueryPlanning)?,
+    ))
+}
+
+/// Apply the `policy` to the output columns of the `plan` that share a name with an earlier
+/// column, renaming them with a final projection, or failing with
+/// [`QueryExecutorError::DuplicateOutputColumn`]
+///
+/// The `plan` is returned unchanged if its columns are already uniquely named.
+pub(super) fn apply_duplicate_columns(
+    plan: Arc<dyn ExecutionPlan>,
+    policy: DuplicateColumnPolicy,
+) -> Result<Arc<dyn ExecutionPlan>, QueryExecutorError> {
+    if policy == DuplicateColumnPolicy::Allow {
+        return Ok(plan);
+    }
+    let schema = plan.schema();
+    let mut taken = schema
+        .fields()
+        .iter()
+        .map(|f| f.name().to_owned())
+        .collect::<HashSet<_>>();
+    let mut seen = HashSet::new();
+    let mut renamed = false;
+    let mut exprs = Vec::with_capacity(schema.fields().len());
+    for (index, field) in schema.fields().iter().enumerate() {
+        let name = field.name();
+        let mut output = name.to_owned();
+        if !seen.insert(name) {
+            if policy == DuplicateColumnPolicy::Reject {
+                return Err(QueryExecutorError::DuplicateOutputColumn {
+                    name: name.to_owned(),
+                });
+            }
+            output = (1..)
+                .map(|n| format!("{name}_{n}"))
+                .find(|candidate| !taken.contains(candidate))
+                .expect("there is always an unused suffix");
+            taken.insert(output.clone());
+            renamed = true;
+        }
+        // the column is referred to by its index, as its name may be shared:
+        let expr = Arc::new(Column::new(name, index)) as Arc<dyn PhysicalExpr>;
+        exprs.push((expr, output));
+    }
+    if !renamed {
+        return Ok(plan);
+    }
+
+    Ok(Arc::new(
+        ProjectionExec::try_new(exprs, plan).map_err(QueryExecutorError::QueryPlanning)?,
+    ))
+}
@@ -0,0 +1,140 @@
+//! Validation of the parameters of a query before it is planned
+//!
+//! A placeholder without a value, or a value of the wrong type, otherwise only surfaces as an
+//! error from deep within the planner, which does not say which parameter is at fault. The
+//! placeholders, e.g., `$host`, are found in the query text, and each must be given a value.
+//! Values used where only a number makes sense, i.e., as an operand of arithmetic, or as a
+//! `LIMIT` or `OFFSET`, must be numbers.
+use influxdb3_internal_api::query_executor::QueryExecutorError;
+use iox_query_params::StatementParams;
+use serde_json::Value;
+
+use super::durations::{tokenize, Token};
+
+/// Check that each of the placeholders in the `query` is given a value by the `params`, and that
+/// the values of those used in a numeric context are numbers
+///
+/// Placeholders are only found outside of string literals, quoted identifiers, regular
+/// expressions, and comments; anything else, including errors in the rest of the query, is left
+/// for the planner to report.
+pub(super) fn validate_params(
+    query: &str,
+    params: Option<&StatementParams>,
+) -> Result<(), QueryExecutorError> {
+    // params are checked through their JSON representation, which is a map from each name to a
+    // null, boolean, number, or string value:
+    let values = params
+        .map(serde_json::to_value)
+        .transpose()
+        .ok()
+        .flatten()
+        .and_then(|values| match values {
+            Value::Object(values) => Some(values),
+            _ => None,
+        })
+        .unwrap_or_default();
+    let tokens = tokenize(query);
+    for (i, token) in tokens.iter().enumerate() {
+        let name = match (token, tokens.get(i + 1)) {
+            (Token::Punct("$"), Some(Token::Word(name) | Token::Number(name))) => *name,
+            _ => continue,
+        };
+        let Some(value) = values.get(name) else {
+            return Err(QueryExecutorError::InvalidParameter {
+                name: name.to_string(),
+                reason: "no value was given for it".to_string(),
+            });
+        };
+        let numeric = i
+            .checked_sub(1)
+            .and_then(|prev| tokens.get(prev))
+            .is_some_and(|prev| is_numeric_context(prev))
+            || tokens
+                .get(i + 2)
+                .is_some_and(|next| matches!(next, Token::Punct("+" | "-" | "*" | "/" | "%")));
+        let kind = match value {
+            Value::String(_) => "a string",
+            Value::Bool(_) => "a boolean",
+            _ => continue,
+        };
+        if numeric {
+            return Err(QueryExecutorError::InvalidParameter {
+                name: name.to_string(),
+                reason: format!("{kind} was given where a number is expected"),
+            });
+        }
+    }
+    Ok(())
+}
+
+/// Whether a placeholder following the `prev` token must be a number
+fn is_numeric_context(prev: &Token<'_>) -> bool {
+    match prev {
+        Token::Punct(p) => matches!(*p, "+" | "-" | "*" | "/" | "%"),
+        Token::Word(w) => ["LIMIT", "OFFSET", "SLIMIT", "SOFFSET"]
+            .iter()
+            .any(|keyword| w.eq_ignore_ascii_case(keyword)),
+        Token::Number(_) => false,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use influxdb3_internal_api::query_executor::QueryExecutorError;
+    use iox_query_params::StatementParams;
+
+    use super::validate_params;
+
+    fn params(values: serde_json::Value) -> StatementParams {
+        serde_json::from_value(values).unwrap()
+    }
+
+    #[test]
+    fn valid_params() {
+        let params = params(serde_json::json!({
+            "host": "a",
+            "limit": 10,
+            "scale": 1.5,
+            "on": true,
+        }));
+        for query in [
+            "SELECT usage FROM cpu WHERE host = $host LIMIT $limit",
+            "SELECT usage * $scale FROM cpu WHERE host = $host AND enabled = $on",
+            "SELECT usage FROM cpu WHERE host = '$missing' -- AND region = $missing",
+            "SELECT usage FROM cpu WHERE host =~ /^a$/ LIMIT $limit OFFSET $limit",
+            "SELECT \"$missing\" FROM cpu",
+        ] {
+            validate_params(query, Some(&params)).unwrap_or_else(|e| panic!("{query}: {e}"));
+        }
+        validate_params("SELECT usage FROM cpu", None).unwrap();
+    }
+
+    #[test]
+    fn invalid_params() {
+        let params = params(serde_json::json!({
+            "host": "a",
+            "limit": "10",
+            "on": true,
+        }));
+        for (query, expected) in [
+            ("SELECT usage FROM cpu WHERE region = $region", "region"),
+            (
+                "SELECT usage FROM cpu WHERE host = $host LIMIT $limit",
+                "limit",
+            ),
+            ("SELECT usage + $host FROM cpu", "host"),
+            ("SELECT $on * usage FROM cpu", "on"),
+        ] {
+            match validate_params(query, Some(&params)) {
+                Err(QueryExecutorError::InvalidParameter { name, .. }) => {
+                    assert_eq!(expected, name, "{query}")
+                }
+                other => panic!("{query}: expected an invalid parameter, got {other:?}"),
+            }
+        }
+        assert!(matches!(
+            validate_params("SELECT usage FROM cpu WHERE host = $host", None),
+            Err(QueryExecutorError::InvalidParameter { name, .. }) if name == "host"
+        ));
+    }
+}
@@ -0,0 +1,203 @@
+//! Output of the partial state of aggregates, see [`QueryOptions::partial_aggregates`], and the
+//! merging of partial states from several servers into final results
+//!
+//! The partial state of an aggregate is output in the columns that DataFusion uses to exchange
+//! state between the partial and final stages of an aggregation, named after the aggregate with a
+//! suffix for each part of its state, e.g., `avg(cpu.usage)[count]` and `avg(cpu.usage)[sum]`.
+//!
+//! [`QueryOptions::partial_aggregates`]: influxdb3_internal_api::query_executor::QueryOptions
+use std::sync::Arc;
+
+use arrow::{datatypes::DataType, record_batch::RecordBatch};
+use datafusion::{
+    common::Column,
+    error::DataFusionError,
+    functions_aggregate::expr_fn::sum,
+    logical_expr::{cast, Expr},
+    physical_plan::{
+        aggregates::{AggregateExec, AggregateMode},
+        expressions::Column as ColumnExpr,
+        projection::ProjectionExec,
+        ExecutionPlan,
+    },
+    prelude::SessionContext,
+};
+use influxdb3_internal_api::query_executor::QueryExecutorError;
+
+/// The aggregate functions whose partial state can be output and merged
+const SUPPORTED_FUNCTIONS: [&str; 4] = ["avg", "mean", "count", "sum"];
+
+/// The operators that may be planned above the aggregate of a query, which are dropped from the
+/// plan when outputting partial state, as long as projections only select or rename columns
+const SUPPORTED_PARENTS: [&str; 6] = [
+    "ProjectionExec",
+    "SortExec",
+    "SortPreservingMergeExec",
+    "CoalescePartitionsExec",
+    "CoalesceBatchesExec",
+    "RepartitionExec",
+];
+
+/// Replace the `plan` with the partial stage of its aggregate, if `enabled`
+///
+/// Fails if the query does not aggregate, uses aggregate functions other than `mean`, `count`,
+/// and `sum`, or operates on the aggregated values, e.g., with a `HAVING` or `LIMIT` clause.
+pub(super) fn apply_partial_aggregates(
+    plan: Arc<dyn ExecutionPlan>,
+    enabled: bool,
+) -> Result<Arc<dyn ExecutionPlan>, QueryExecutorError> {
+    if !enabled {
+        return Ok(plan);
+    }
+    let unsupported = |reason: String| QueryExecutorError::PartialAggregateUnsupported { reason };
+
+    let mut node = plan;
+    while !node.as_any().is::<AggregateExec>() {
+        if !SUPPORTED_PARENTS.contains(&node.name()) {
+            return Err(unsupported(format!(
+                "{} is applied to the aggregated values",
+                node.name()
+            )));
+        }
+        if node
+            .as_any()
+            .downcast_ref::<ProjectionExec>()
+            .is_some_and(|p| p.expr().iter().any(|(e, _)| !e.as_any().is::<ColumnExpr>()))
+        {
+            return Err(unsupported(
+                "an expression is applied to the aggregated values".to_string(),
+            ));
+        }
+        let [child] = node.children()[..] else {
+            return Err(unsupported("the query does not aggregate".to_string()));
+        };
+        node = Arc::clone(child);
+    }
+    let aggregate = node
+        .as_any()
+        .downcast_ref::<AggregateExec>()
+        .expect("node is an aggregate");
+
+    if let Some(aggr) = aggregate.aggr_expr().iter().find(|aggr| {
+        let function = aggr.name().split('(').next().unwrap_or_default();
+        !SUPPORTED_FUNCTIONS.contains(&function.to_lowercase().as_str())
+    }) {
+        return Err(unsupported(format!(
+            "{} is not one of {}",
+            aggr.name(),
+            SUPPORTED_FUNCTIONS.join(", ")
+        )));
+    }
+
+    match *aggregate.mode() {
+        AggregateMode::Partial => Ok(node),
+        // the partial stage is below the final one, past any repartitioning between them:
+        AggregateMode::Final | AggregateMode::FinalPartitioned => {
+            let mut node = Arc::clone(aggregate.input());
+            loop {
+                if node.as_any().is::<AggregateExec>() {
+                    return Ok(node);
+                }
+                let [child] = node.children()[..] else {
+                    return Err(unsupported(
+                        "the partial stage of the aggregate was not found".to_string(),
+                    ));
+                };
+                node = Arc::clone(child);
+            }
+        }
+        // the partial and final stages were combined into one, so the partial stage is recreated
+        // from it:
+        AggregateMode::Single | AggregateMode::SinglePartitioned => Ok(Arc::new(
+            AggregateExec::try_new(
+                AggregateMode::Partial,
+                aggregate.group_expr().clone(),
+                aggregate.aggr_expr().to_vec(),
+                aggregate.filter_expr().to_vec(),
+                Arc::clone(aggregate.input()),
+                aggregate.input_schema(),
+            )
+            .map_err(QueryExecutorError::QueryPlanning)?,
+        )),
+    }
+}
+
+/// Merge the partial aggregate states output by several servers for the same query, with
+/// [`QueryOptions::partial_aggregates`] set, into the final results of the query
+///
+/// The results have a column for each of the grouping columns of the query, followed by a
+/// column for each aggregate, named after it.
+///
+/// [`QueryOptions::partial_aggregates`]: influxdb3_internal_api::query_executor::QueryOptions
+pub async fn merge_partials(
+    partials: Vec<RecordBatch>,
+) -> Result<Vec<RecordBatch>, DataFusionError> {
+    let Some(schema) = partials.first().map(RecordBatch::schema) else {
+        return Ok(vec![]);
+    };
+
+    let mut groups = vec![];
+    // each aggregate, with the names of its count and sum state columns:
+    let mut aggregates: Vec<(String, Option<String>, Option<String>)> = vec![];
+    for field in schema.fields() {
+        let name = field.name();
+        let state = ["count", "sum"].into_iter().find_map(|state| {
+            name.strip_suffix(&format!("[{state}]"))
+                .map(|aggregate| (aggregate, state))
+        });
+        let Some((aggregate, state)) = state else {
+            groups.push(column(name));
+            continue;
+        };
+        let index = match aggregates.iter().position(|(a, ..)| a == aggregate) {
+            Some(index) => index,
+            None => {
+                aggregates.push((aggregate.to_string(), None, None));
+                aggregates.len() - 1
+            }
+        };
+        match state {
+            "count" => aggregates[index].1 = Some(name.clone()),
+            _ => aggregates[index].2 = Some(name.clone()),
+        }
+    }
+
+    // the states of each group are summed, then finalized:
+    let states = aggregates
+        .iter()
+        .flat_map(|(_, count_state, sum_state)| count_state.iter().chain(sum_state))
+        .map(|state| sum(column(state)).alias(state))
+        .collect::<Vec<_>>();
+    let finals = groups
+        .iter()
+        .cloned()
+        .chain(
+            aggregates
+                .iter()
+                .map(|(aggregate, count_state, sum_state)| {
+                    match (count_state, sum_state) {
+                        (Some(count), Some(sum)) => {
+                            cast(column(sum), DataType::Float64)
+                                / cast(column(count), DataType::Float64)
+                        }
+                        (Some(count), None) => cast(column(count), DataType::Int64),
+                        (None, Some(sum)) => column(sum),
+                        (None, None) => unreachable!("aggregates have at least one state column"),
+                    }
+                    .alias(aggregate)
+                }),
+        )
+        .collect::<Vec<_>>();
+
+    SessionContext::new()
+        .read_batches(partials)?
+        .aggregate(groups, states)?
+        .select(finals)?
+        .collect()
+        .await
+}
+
+/// Refer to a column by its name as is, as the names of aggregates include dots and parentheses
+fn column(name: &str) -> Expr {
+    Expr::Column(Column::from_name(name))
+}
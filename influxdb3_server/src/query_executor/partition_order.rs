@@ -0,0 +1,200 @@
+//! Output of the partitions of a query's plan that preserves the order of the rows within each
+//! partition, see [`QueryOptions::preserve_partition_order`][preserve]
+//!
+//! The partitions of a plan are otherwise combined as their batches become ready, which
+//! interleaves the rows of different partitions nondeterministically. When the partitions share
+//! an ordering, e.g., on time, because the chunks that they scan are sorted, they are merged on
+//! that ordering, which also orders the output as a whole without sorting it again. Otherwise the
+//! partitions are output one after another, in order.
+//!
+//! [preserve]: influxdb3_internal_api::query_executor::QueryOptions::preserve_partition_order
+use std::{any::Any, fmt, sync::Arc};
+
+use datafusion::{
+    error::DataFusionError,
+    execution::{SendableRecordBatchStream, TaskContext},
+    physical_expr::EquivalenceProperties,
+    physical_plan::{
+        sorts::sort_preserving_merge::SortPreservingMergeExec, stream::RecordBatchStreamAdapter,
+        DisplayAs, DisplayFormatType, ExecutionPlan, Partitioning, PlanProperties,
+    },
+};
+use futures::{stream, StreamExt, TryStreamExt};
+
+/// Combine the partitions of the `plan` into a single partition that preserves the order of the
+/// rows within each of them, if `preserve` is set
+pub(super) fn apply_partition_order(
+    plan: Arc<dyn ExecutionPlan>,
+    preserve: bool,
+) -> Arc<dyn ExecutionPlan> {
+    if !preserve || plan.output_partitioning().partition_count() <= 1 {
+        return plan;
+    }
+    if let Some(ordering) = plan.output_ordering() {
+        let ordering = ordering.to_vec();
+        return Arc::new(SortPreservingMergeExec::new(ordering, plan));
+    }
+    Arc::new(ConcatPartitionsExec::new(plan))
+}
+
+/// Outputs all of the partitions of its input, one after another, as a single partition
+#[derive(Debug)]
+struct ConcatPartitionsExec {
+    input: Arc<dyn ExecutionPlan>,
+    properties: PlanProperties,
+}
+
+impl ConcatPartitionsExec {
+    fn new(input: Arc<dyn ExecutionPlan>) -> Self {
+        let properties = PlanProperties::new(
+            EquivalenceProperties::new(input.schema()),
+            Partitioning::UnknownPartitioning(1),
+            input.properties().execution_mode,
+        );
+        Self { input, properties }
+    }
+}
+
+impl DisplayAs for ConcatPartitionsExec {
+    fn fmt_as(&self, t: DisplayFormatType, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match t {
+            DisplayFormatType::Default | DisplayFormatType::Verbose => {
+                write!(f, "ConcatPartitionsExec")
+            }
+        }
+    }
+}
+
+impl ExecutionPlan for ConcatPartitionsExec {
+    fn name(&self) -> &str {
+        "ConcatPartitionsExec"
+    }
+
+    fn as_any(&self) -> &dyn Any {
+        self
+    }
+
+    fn properties(&self) -> &PlanProperties {
+        &self.properties
+    }
+
+    fn benefits_from_input_partitioning(&self) -> Vec<bool> {
+        vec![false]
+    }
+
+    fn children(&self) -> Vec<&Arc<dyn ExecutionPlan>> {
+        vec![&self.input]
+    }
+
+    fn with_new_children(
+        self: Arc<Self>,
+        mut children: Vec<Arc<dyn ExecutionPlan>>,
+    ) -> Result<Arc<dyn ExecutionPlan>, DataFusionError> {
+        if children.len() != 1 {
+            return Err(DataFusionError::Internal(format!(
+                "ConcatPartitionsExec expects a single child, got {}",
+                children.len()
+            )));
+        }
+        Ok(Arc::new(Self::new(children.remove(0))))
+    }
+
+    fn execute(
+        &self,
+        partition: usize,
+        context: Arc<TaskContext>,
+    ) -> Result<SendableRecordBatchStream, DataFusionError> {
+        if partition != 0 {
+            return Err(DataFusionError::Internal(format!(
+                "ConcatPartitionsExec has a single partition, got {partition}"
+            )));
+        }
+        let input = Arc::clone(&self.input);
+        let partitions = input.output_partitioning().partition_count();
+        // each partition is only executed once the one before it is exhausted:
+        let batches = stream::iter(0..partitions)
+            .map(move |partition| input.execute(partition, Arc::clone(&context)))
+            .try_flatten();
+        Ok(Box::pin(RecordBatchStreamAdapter::new(
+            self.input.schema(),
+            batches,
+        )))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::sync::Arc;
+
+    use arrow::{
+        array::{AsArray, Int64Array},
+        compute::SortOptions,
+        datatypes::Int64Type,
+        record_batch::RecordBatch,
+    };
+    use datafusion::{
+        execution::TaskContext,
+        physical_expr::{expressions::col, PhysicalSortExpr},
+        physical_plan::{collect, memory::MemoryExec, ExecutionPlan},
+    };
+
+    use super::apply_partition_order;
+
+    /// A scan of partitions that each hold the given times, in two batches
+    fn scan(partitions: &[&[i64]], sorted: bool) -> Arc<dyn ExecutionPlan> {
+        let batch = |times: &[i64]| {
+            RecordBatch::try_from_iter([("time", Arc::new(Int64Array::from(times.to_vec())) as _)])
+                .unwrap()
+        };
+        let partitions = partitions
+            .iter()
+            .map(|times| {
+                let (first, second) = times.split_at(times.len() / 2);
+                vec![batch(first), batch(second)]
+            })
+            .collect::<Vec<_>>();
+        let schema = partitions[0][0].schema();
+        let mut exec = MemoryExec::try_new(&partitions, Arc::clone(&schema), None).unwrap();
+        if sorted {
+            exec = exec.with_sort_information(vec![vec![PhysicalSortExpr {
+                expr: col("time", &schema).unwrap(),
+                options: SortOptions::default(),
+            }]]);
+        }
+        Arc::new(exec)
+    }
+
+    async fn output_times(plan: Arc<dyn ExecutionPlan>) -> Vec<i64> {
+        let plan = apply_partition_order(plan, true);
+        assert_eq!(1, plan.output_partitioning().partition_count());
+        collect(plan, Arc::new(TaskContext::default()))
+            .await
+            .unwrap()
+            .iter()
+            .flat_map(|batch| {
+                batch
+                    .column(0)
+                    .as_primitive::<Int64Type>()
+                    .values()
+                    .to_vec()
+            })
+            .collect()
+    }
+
+    #[tokio::test]
+    async fn partitions_ordered_on_time_are_merged() {
+        let times = output_times(scan(
+            &[&[1, 4, 7, 10], &[2, 5, 8, 11], &[3, 6, 9, 12]],
+            true,
+        ))
+        .await;
+        assert_eq!((1..=12).collect::<Vec<_>>(), times);
+    }
+
+    #[tokio::test]
+    async fn unordered_partitions_are_concatenated() {
+        let times = output_times(scan(&[&[3, 1, 2, 4], &[8, 9, 5, 6], &[7, 10]], false)).await;
+        // the rows of each partition are output together, in their order within the partition:
+        assert_eq!(vec![3, 1, 2, 4, 8, 9, 5, 6, 7, 10], times);
+    }
+}
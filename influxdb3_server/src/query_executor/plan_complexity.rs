@@ -0,0 +1,62 @@
+//! A limit on the complexity of the queries that are planned, see
+//! [`CreateQueryExecutorArgs::max_plan_nodes`][max]
+//!
+//! The nodes of the logical plan of a query, including those of its subqueries, are counted once
+//! the plan has been analyzed, before it is optimized and converted to a physical plan, so that a
+//! deeply nested query is rejected before most of the work of planning it is done.
+//!
+//! [max]: super::CreateQueryExecutorArgs::max_plan_nodes
+use datafusion::{
+    common::tree_node::{TreeNode, TreeNodeRecursion},
+    config::ConfigOptions,
+    error::DataFusionError,
+    logical_expr::LogicalPlan,
+    optimizer::analyzer::AnalyzerRule,
+};
+use influxdb3_internal_api::query_executor::QueryExecutorError;
+
+/// Reject the plans of queries that have more than `max` nodes with
+/// [`QueryExecutorError::PlanTooComplex`]
+#[derive(Debug)]
+pub(super) struct PlanComplexityLimit {
+    max: usize,
+}
+
+impl PlanComplexityLimit {
+    pub(super) fn new(max: usize) -> Self {
+        Self { max }
+    }
+}
+
+impl AnalyzerRule for PlanComplexityLimit {
+    fn analyze(
+        &self,
+        plan: LogicalPlan,
+        _config: &ConfigOptions,
+    ) -> Result<LogicalPlan, DataFusionError> {
+        let nodes = count_nodes(&plan)?;
+        if nodes > self.max {
+            return Err(DataFusionError::External(Box::new(
+                QueryExecutorError::PlanTooComplex {
+                    nodes,
+                    max: self.max,
+                },
+            )));
+        }
+        Ok(plan)
+    }
+
+    fn name(&self) -> &str {
+        "plan_complexity_limit"
+    }
+}
+
+/// Count the nodes of the `plan`, and of the plans of its subqueries
+fn count_nodes(plan: &LogicalPlan) -> Result<usize, DataFusionError> {
+    let mut nodes = 0;
+    plan.apply_with_subqueries(|_| {
+        nodes += 1;
+        Ok(TreeNodeRecursion::Continue)
+    })?;
+    Ok(nodes)
+}
@@ -0,0 +1,118 @@
+//! Limits on the time spent planning a query, separately from the time spent executing it
+use std::{future::Future, time::Duration};
+
+use influxdb3_internal_api::query_executor::QueryExecutorError;
+use observability_deps::tracing::warn;
+
+/// Run the `planning` future, failing with [`QueryExecutorError::PlanningTimeout`] if it does not
+/// complete within the `limit`, if one is given
+///
+/// The `planning` future is dropped when the limit is reached, which, for planning spawned on the
+/// executor's dedicated threadpool with `IOxSessionContext::run`, cancels the planning task.
+pub(super) async fn with_planning_timeout<T>(
+    limit: Option<Duration>,
+    planning: impl Future<Output = T> + Send,
+) -> Result<T, QueryExecutorError> {
+    match limit {
+        Some(limit) => tokio::time::timeout(limit, planning)
+            .await
+            .map_err(|_| QueryExecutorError::PlanningTimeout { limit }),
+        None => Ok(planning.await),
+    }
+}
+
+/// Log the `query` if its planning took `elapsed`, which is longer than the `threshold`, if one is
+/// given, returning whether it was logged
+pub(super) fn log_slow_planning(
+    threshold: Option<Duration>,
+    elapsed: Duration,
+    database: &str,
+    query: &str,
+) -> bool {
+    match threshold {
+        Some(threshold) if elapsed > threshold => {
+            warn!(
+                database,
+                query,
+                planning_time = ?elapsed,
+                ?threshold,
+                "slow query planning"
+            );
+            true
+        }
+        _ => false,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::{
+        sync::{
+            atomic::{AtomicBool, Ordering},
+            Arc,
+        },
+        time::Duration,
+    };
+
+    use influxdb3_internal_api::query_executor::QueryExecutorError;
+
+    use super::{log_slow_planning, with_planning_timeout};
+
+    /// Records that the planning future was dropped, i.e., cancelled
+    struct DropGuard(Arc<AtomicBool>);
+
+    impl Drop for DropGuard {
+        fn drop(&mut self) {
+            self.0.store(true, Ordering::SeqCst);
+        }
+    }
+
+    #[tokio::test]
+    async fn slow_planning_times_out() {
+        let cancelled = Arc::new(AtomicBool::new(false));
+        let guard = DropGuard(Arc::clone(&cancelled));
+        let error = with_planning_timeout(Some(Duration::from_millis(10)), async move {
+            let _guard = guard;
+            tokio::time::sleep(Duration::from_secs(60)).await;
+        })
+        .await
+        .unwrap_err();
+        assert!(
+            matches!(error, QueryExecutorError::PlanningTimeout { .. }),
+            "unexpected error: {error}"
+        );
+        assert!(cancelled.load(Ordering::SeqCst));
+    }
+
+    #[tokio::test]
+    async fn fast_planning_completes() {
+        let plan = with_planning_timeout(Some(Duration::from_secs(10)), async { "plan" })
+            .await
+            .unwrap();
+        assert_eq!("plan", plan);
+        let plan = with_planning_timeout(None, async { "plan" }).await.unwrap();
+        assert_eq!("plan", plan);
+    }
+    #[test]
+    fn slow_planning_is_logged() {
+        let threshold = Some(Duration::from_millis(100));
+        assert!(log_slow_planning(
+            threshold,
+            Duration::from_millis(150),
+            "foo",
+            "SELECT * FROM cpu"
+        ));
+        assert!(!log_slow_planning(
+            threshold,
+            Duration::from_millis(50),
+            "foo",
+            "SELECT * FROM cpu"
+        ));
+        assert!(!log_slow_planning(
+            None,
+            Duration::from_secs(60),
+            "foo",
+            "SELECT * FROM cpu"
+        ));
+    }
+}
@@ -0,0 +1,208 @@
+//! Best-effort estimates of how far along running queries are, based on the number of rows
+//! scanned so far versus the number of rows in the chunks being scanned
+use std::{
+    any::Any,
+    collections::HashMap,
+    fmt,
+    sync::{
+        atomic::{AtomicUsize, Ordering},
+        Arc, Weak,
+    },
+};
+
+use datafusion::{
+    error::DataFusionError,
+    execution::{SendableRecordBatchStream, TaskContext},
+    physical_plan::{
+        stream::RecordBatchStreamAdapter, DisplayAs, DisplayFormatType, ExecutionPlan,
+        PlanProperties,
+    },
+};
+use futures::StreamExt;
+use parking_lot::Mutex;
+
+/// The progress of the scans in a single query
+#[derive(Debug, Default)]
+pub(super) struct ScanProgress {
+    estimated_rows: AtomicUsize,
+    scanned_rows: AtomicUsize,
+}
+
+impl ScanProgress {
+    /// Add the rows of a scan to the estimated total
+    pub(super) fn add_estimate(&self, rows: usize) {
+        self.estimated_rows.fetch_add(rows, Ordering::Relaxed);
+    }
+
+    /// The estimated completion percentage, between 0 and 100
+    ///
+    /// Scans of persisted files may skip rows that cannot match the query's filters, so this can
+    /// fall short of 100 before the query completes.
+    pub(super) fn percentage(&self) -> f64 {
+        let estimated = self.estimated_rows.load(Ordering::Relaxed);
+        let scanned = self.scanned_rows.load(Ordering::Relaxed);
+        if estimated == 0 {
+            return 0.0;
+        }
+        (scanned as f64 / estimated as f64 * 100.0).clamp(0.0, 100.0)
+    }
+}
+
+/// The [`ScanProgress`] of running queries, keyed by the id of their query log entry
+///
+/// Only weak references are held, so queries are no longer reported once their plan, which
+/// holds the progress, is dropped.
+#[derive(Debug, Default)]
+pub(super) struct QueryProgress {
+    queries: Mutex<HashMap<String, Weak<ScanProgress>>>,
+}
+
+impl QueryProgress {
+    /// Start tracking the progress of the query with the given query log entry `id`
+    pub(super) fn register(&self, id: &str) -> Arc<ScanProgress> {
+        let progress = Arc::new(ScanProgress::default());
+        let mut queries = self.queries.lock();
+        queries.retain(|_, progress| progress.strong_count() > 0);
+        queries.insert(id.to_string(), Arc::downgrade(&progress));
+        progress
+    }
+
+    pub(super) fn get(&self, id: &str) -> Option<Arc<ScanProgress>> {
+        self.queries.lock().get(id).and_then(Weak::upgrade)
+    }
+}
+
+/// Wrap each of the leaves of the `scan`, which read the data of the scanned chunks, so that
+/// the rows they produce are counted toward the `progress`
+pub(super) fn track_scan(
+    scan: Arc<dyn ExecutionPlan>,
+    progress: &Arc<ScanProgress>,
+) -> Result<Arc<dyn ExecutionPlan>, DataFusionError> {
+    let children = scan.children();
+    if children.is_empty() {
+        return Ok(Arc::new(ScanProgressExec {
+            input: scan,
+            progress: Arc::clone(progress),
+        }));
+    }
+    let children = children
+        .into_iter()
+        .map(|child| track_scan(Arc::clone(child), progress))
+        .collect::<Result<Vec<_>, _>>()?;
+    scan.with_new_children(children)
+}
+
+#[derive(Debug)]
+struct ScanProgressExec {
+    input: Arc<dyn ExecutionPlan>,
+    progress: Arc<ScanProgress>,
+}
+
+impl DisplayAs for ScanProgressExec {
+    fn fmt_as(&self, t: DisplayFormatType, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match t {
+            DisplayFormatType::Default | DisplayFormatType::Verbose => {
+                write!(f, "ScanProgressExec")
+            }
+        }
+    }
+}
+
+impl ExecutionPlan for ScanProgressExec {
+    fn name(&self) -> &str {
+        "ScanProgressExec"
+    }
+
+    fn as_any(&self) -> &dyn Any {
+        self
+    }
+
+    fn properties(&self) -> &PlanProperties {
+        self.input.properties()
+    }
+
+    fn maintains_input_order(&self) -> Vec<bool> {
+        vec![true]
+    }
+
+    fn benefits_from_input_partitioning(&self) -> Vec<bool> {
+        vec![false]
+    }
+
+    fn children(&self) -> Vec<&Arc<dyn ExecutionPlan>> {
+        vec![&self.input]
+    }
+
+    fn with_new_children(
+        self: Arc<Self>,
+        mut children: Vec<Arc<dyn ExecutionPlan>>,
+    ) -> Result<Arc<dyn ExecutionPlan>, DataFusionError> {
+        if children.len() != 1 {
+            return Err(DataFusionError::Internal(format!(
+                "ScanProgressExec expects a single child, got {}",
+                children.len()
+            )));
+        }
+        Ok(Arc::new(Self {
+            input: children.remove(0),
+            progress: Arc::clone(&self.progress),
+        }))
+    }
+
+    fn execute(
+        &self,
+        partition: usize,
+        context: Arc<TaskContext>,
+    ) -> Result<SendableRecordBatchStream, DataFusionError> {
+        let stream = self.input.execute(partition, context)?;
+        let schema = stream.schema();
+        let progress = Arc::clone(&self.progress);
+        Ok(Box::pin(RecordBatchStreamAdapter::new(
+            schema,
+            stream.inspect(move |batch| {
+                if let Ok(batch) = batch {
+                    progress
+                        .scanned_rows
+                        .fetch_add(batch.num_rows(), Ordering::Relaxed);
+                }
+            }),
+        )))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::sync::Arc;
+
+    use arrow::{array::Int64Array, record_batch::RecordBatch};
+    use datafusion::{execution::TaskContext, physical_plan::memory::MemoryExec};
+    use futures::StreamExt;
+
+    use super::{track_scan, QueryProgress};
+
+    #[tokio::test]
+    async fn progress_of_partial_scan() {
+        let batch =
+            RecordBatch::try_from_iter([("v", Arc::new(Int64Array::from_iter_values(0..25)) as _)])
+                .unwrap();
+        let scan =
+            Arc::new(MemoryExec::try_new(&[vec![batch.clone(); 4]], batch.schema(), None).unwrap());
+
+        let registry = QueryProgress::default();
+        let progress = registry.register("query");
+        progress.add_estimate(100);
+        let plan = track_scan(scan, &progress).unwrap();
+        drop(progress);
+
+        let mut stream = plan.execute(0, Arc::new(TaskContext::default())).unwrap();
+        assert_eq!(0.0, registry.get("query").unwrap().percentage());
+        stream.next().await.unwrap().unwrap();
+        stream.next().await.unwrap().unwrap();
+        assert_eq!(50.0, registry.get("query").unwrap().percentage());
+
+        // once the plan is dropped, the query is no longer reported:
+        drop(stream);
+        drop(plan);
+        assert!(registry.get("query").is_none());
+    }
+}
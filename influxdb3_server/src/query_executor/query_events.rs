@@ -0,0 +1,173 @@
+//! Events recorded in the [`SysEventStore`] for queries that fail, and for queries that take
+//! longer than [`CreateQueryExecutorArgs::slow_query_threshold`][threshold], which are listed in
+//! the `system.events` table
+//!
+//! [threshold]: super::CreateQueryExecutorArgs::slow_query_threshold
+use std::{
+    pin::Pin,
+    sync::Arc,
+    task::{Context, Poll},
+    time::{Duration, Instant},
+};
+
+use arrow::{datatypes::SchemaRef, record_batch::RecordBatch};
+use datafusion::{
+    error::DataFusionError,
+    execution::{RecordBatchStream, SendableRecordBatchStream},
+};
+use futures::{Stream, StreamExt};
+use influxdb3_internal_api::query_executor::QueryExecutorError;
+use influxdb3_sys_events::SysEventStore;
+
+/// A query that failed, either while it was planned, or while its results were read
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct QueryFailed {
+    pub database: String,
+    pub query_text: String,
+    /// What failed, one of `planning`, `execution`, `timeout`, or `unavailable`
+    pub category: &'static str,
+    pub error: String,
+    /// The time from the query being issued until it failed
+    pub duration: Duration,
+}
+
+/// A query whose results took longer than the slow query threshold to be read
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct SlowQuery {
+    pub database: String,
+    pub query_text: String,
+    /// The time from the query being issued until its results were read
+    pub duration: Duration,
+}
+
+/// The category of a query that failed with the `error`, see [`QueryFailed::category`]
+fn error_category(error: &QueryExecutorError) -> &'static str {
+    match error {
+        QueryExecutorError::Timeout { .. } | QueryExecutorError::PlanningTimeout { .. } => {
+            "timeout"
+        }
+        QueryExecutorError::Unavailable { .. }
+        | QueryExecutorError::ReplayInProgress
+        | QueryExecutorError::MemoryPressure { .. } => "unavailable",
+        QueryExecutorError::ExecuteStream(_)
+        | QueryExecutorError::QueryJobResults(_)
+        | QueryExecutorError::WriteResults { .. } => "execution",
+        _ => "planning",
+    }
+}
+
+/// Records the events of a single query
+#[derive(Debug)]
+pub(super) struct QueryEvents {
+    store: Arc<SysEventStore>,
+    slow_query_threshold: Option<Duration>,
+    database: String,
+    query_text: String,
+    started: Instant,
+}
+
+impl QueryEvents {
+    /// Record the events of the `query_text` on the `database`, which is issued now
+    pub(super) fn new(
+        store: Arc<SysEventStore>,
+        slow_query_threshold: Option<Duration>,
+        database: &str,
+        query_text: &str,
+    ) -> Self {
+        Self {
+            store,
+            slow_query_threshold,
+            database: database.to_string(),
+            query_text: query_text.to_string(),
+            started: Instant::now(),
+        }
+    }
+
+    /// Record that the query failed with the `error` before producing results
+    pub(super) fn failed(&self, error: &QueryExecutorError) {
+        self.record_failure(error_category(error), error.to_string());
+    }
+
+    /// Record the events of the query once its `results` have been read, i.e., whether reading
+    /// them fails, or takes longer than the slow query threshold
+    pub(super) fn track(self, results: SendableRecordBatchStream) -> SendableRecordBatchStream {
+        Box::pin(QueryEventsStream {
+            inner: results,
+            events: Some(self),
+        })
+    }
+
+    fn record_failure(&self, category: &'static str, error: String) {
+        self.store.record(QueryFailed {
+            database: self.database.clone(),
+            query_text: self.query_text.clone(),
+            category,
+            error,
+            duration: self.started.elapsed(),
+        });
+    }
+
+    fn finish(self) {
+        let duration = self.started.elapsed();
+        if self
+            .slow_query_threshold
+            .is_some_and(|threshold| duration > threshold)
+        {
+            self.store.record(SlowQuery {
+                database: self.database,
+                query_text: self.query_text,
+                duration,
+            });
+        }
+    }
+}
+
+struct QueryEventsStream {
+    inner: SendableRecordBatchStream,
+    /// Taken once an event has been recorded for the query, so that each query records at most
+    /// one event
+    events: Option<QueryEvents>,
+}
+
+impl Stream for QueryEventsStream {
+    type Item = Result<RecordBatch, DataFusionError>;
+
+    fn poll_next(mut self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Option<Self::Item>> {
+        let poll = self.inner.poll_next_unpin(cx);
+        match &poll {
+            Poll::Ready(Some(Err(e))) => {
+                if let Some(events) = self.events.take() {
+                    let category = match e {
+                        DataFusionError::External(e) => e
+                            .downcast_ref::<QueryExecutorError>()
+                            .map_or("execution", error_category),
+                        _ => "execution",
+                    };
+                    events.record_failure(category, e.to_string());
+                }
+            }
+            Poll::Ready(None) => {
+                if let Some(events) = self.events.take() {
+                    events.finish();
+                }
+            }
+            _ => (),
+        }
+        poll
+    }
+}
+
+impl RecordBatchStream for QueryEventsStream {
+    fn schema(&self) -> SchemaRef {
+        self.inner.schema()
+    }
+}
+
+impl Drop for QueryEventsStream {
+    fn drop(&mut self) {
+        // results that are dropped before they have all been read may still have been slow:
+        if let Some(events) = self.events.take() {
+            events.finish();
+        }
+    }
+}
@@ -0,0 +1,192 @@
+//! Prefetching of the parquet files scanned by a query into the parquet cache, so that the scan
+//! does not wait on the object store for each file in turn, see
+//! [`CreateQueryExecutorArgs::scan_read_ahead`][read_ahead]
+//!
+//! The files are fetched in the order that they are scanned, keeping a bounded window of fetches
+//! outstanding: at most `depth` files, and at most `budget` bytes, are being fetched at a time,
+//! and the window moves on as each fetch completes. Files larger than the budget are left to be
+//! read on demand by the scan.
+//!
+//! [read_ahead]: super::CreateQueryExecutorArgs::scan_read_ahead
+use std::{collections::VecDeque, num::NonZeroUsize, sync::Arc};
+
+use futures::{stream::FuturesUnordered, StreamExt};
+use influxdb3_cache::parquet_cache::{CacheRequest, ParquetCacheOracle};
+use object_store::ObjectMeta;
+use observability_deps::tracing::debug;
+use tokio::task::JoinHandle;
+
+/// Prefetches the parquet files of the chunks scanned by queries
+#[derive(Debug, Clone)]
+pub(super) struct ReadAhead {
+    oracle: Arc<dyn ParquetCacheOracle>,
+    depth: NonZeroUsize,
+    budget: usize,
+}
+
+impl ReadAhead {
+    /// Prefetch up to `depth` files, and up to `budget` bytes, at a time into the parquet cache
+    /// behind the `oracle`
+    pub(super) fn new(
+        oracle: Arc<dyn ParquetCacheOracle>,
+        depth: NonZeroUsize,
+        budget: usize,
+    ) -> Self {
+        Self {
+            oracle,
+            depth,
+            budget,
+        }
+    }
+
+    /// Start prefetching the given `files`, in order, in the background
+    pub(super) fn prefetch(&self, files: Vec<ObjectMeta>) -> JoinHandle<()> {
+        let Self {
+            oracle,
+            depth,
+            budget,
+        } = self.clone();
+        tokio::spawn(async move {
+            let mut pending = files
+                .into_iter()
+                .filter(|file| file.size <= budget)
+                .collect::<VecDeque<_>>();
+            let mut in_flight = FuturesUnordered::new();
+            let mut in_flight_bytes = 0;
+            loop {
+                while in_flight.len() < depth.get() {
+                    let Some(file) = pending.front() else {
+                        break;
+                    };
+                    if in_flight_bytes + file.size > budget {
+                        break;
+                    }
+                    let file = pending.pop_front().expect("front of the queue was checked");
+                    let (request, notifier) = CacheRequest::create(file.location);
+                    oracle.register(request);
+                    in_flight_bytes += file.size;
+                    in_flight.push(async move {
+                        // a failed fetch is retried by the scan, which reads the file on demand:
+                        let _ = notifier.await;
+                        file.size
+                    });
+                }
+                let Some(size) = in_flight.next().await else {
+                    break;
+                };
+                in_flight_bytes -= size;
+            }
+            debug!("finished reading ahead of scan");
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::{
+        num::NonZeroUsize,
+        sync::{
+            atomic::{AtomicUsize, Ordering},
+            Arc,
+        },
+        time::{Duration, Instant},
+    };
+
+    use influxdb3_cache::parquet_cache::{CacheRequest, ParquetCacheOracle};
+    use object_store::{path::Path, ObjectMeta};
+    use tokio::sync::watch;
+
+    use super::ReadAhead;
+
+    const FETCH_LATENCY: Duration = Duration::from_millis(20);
+
+    /// Fetches each file with a fixed latency, tracking the bytes being fetched at a time
+    #[derive(Debug, Default)]
+    struct SlowOracle {
+        fetched: AtomicUsize,
+        in_flight_bytes: Arc<AtomicUsize>,
+        max_in_flight_bytes: Arc<AtomicUsize>,
+    }
+
+    impl ParquetCacheOracle for SlowOracle {
+        fn register(&self, cache_request: CacheRequest) {
+            self.fetched.fetch_add(1, Ordering::SeqCst);
+            let size = cache_request.get_path().as_ref().len();
+            let in_flight = self.in_flight_bytes.fetch_add(size, Ordering::SeqCst) + size;
+            self.max_in_flight_bytes
+                .fetch_max(in_flight, Ordering::SeqCst);
+            let in_flight_bytes = Arc::clone(&self.in_flight_bytes);
+            tokio::spawn(async move {
+                tokio::time::sleep(FETCH_LATENCY).await;
+                in_flight_bytes.fetch_sub(size, Ordering::SeqCst);
+                drop(cache_request);
+            });
+        }
+
+        fn prune_notifier(&self) -> watch::Receiver<usize> {
+            watch::channel(0).1
+        }
+    }
+
+    /// A file whose size is the length of its path, so that the oracle can tell its size
+    fn file(size: usize) -> ObjectMeta {
+        ObjectMeta {
+            location: Path::from("f".repeat(size)),
+            last_modified: Default::default(),
+            size,
+            e_tag: None,
+            version: None,
+        }
+    }
+
+    /// Prefetch the `files`, returning how long it took, the number of files fetched, and the
+    /// most bytes fetched at a time
+    async fn prefetch(
+        depth: usize,
+        budget: usize,
+        files: Vec<ObjectMeta>,
+    ) -> (Duration, usize, usize) {
+        let oracle = Arc::new(SlowOracle::default());
+        let read_ahead = ReadAhead::new(
+            Arc::clone(&oracle) as _,
+            NonZeroUsize::new(depth).unwrap(),
+            budget,
+        );
+        let started = Instant::now();
+        read_ahead.prefetch(files).await.unwrap();
+        (
+            started.elapsed(),
+            oracle.fetched.load(Ordering::SeqCst),
+            oracle.max_in_flight_bytes.load(Ordering::SeqCst),
+        )
+    }
+
+    #[tokio::test]
+    async fn read_ahead_is_bounded() {
+        let files = || (0..8).map(|_| file(10)).collect::<Vec<_>>();
+
+        // one file at a time, as the scan would read them on demand:
+        let (sequential, fetched, max_bytes) = prefetch(1, 1_000, files()).await;
+        assert_eq!(8, fetched);
+        assert_eq!(10, max_bytes);
+        assert!(sequential >= FETCH_LATENCY * 8);
+
+        let (read_ahead, fetched, max_bytes) = prefetch(4, 1_000, files()).await;
+        assert_eq!(8, fetched);
+        assert_eq!(40, max_bytes);
+        assert!(
+            read_ahead < sequential,
+            "reading ahead took {read_ahead:?}, reading sequentially took {sequential:?}"
+        );
+
+        // the budget holds back the window before its depth does:
+        let (_, fetched, max_bytes) = prefetch(4, 25, files()).await;
+        assert_eq!(8, fetched);
+        assert_eq!(20, max_bytes);
+
+        // files larger than the budget are left to the scan:
+        let (_, fetched, max_bytes) = prefetch(4, 25, vec![file(10), file(30), file(10)]).await;
+        assert_eq!(2, fetched);
+        assert_eq!(20, max_bytes);
+    }
+}
@@ -0,0 +1,95 @@
+//! A pull-based interface to the results of a query
+use std::{fmt, sync::Arc};
+
+use arrow::{datatypes::SchemaRef, record_batch::RecordBatch};
+use datafusion::{error::DataFusionError, execution::SendableRecordBatchStream};
+use futures::StreamExt;
+
+/// Reads the results of a query one record batch at a time, as requested by the client
+///
+/// Unlike forwarding a [`SendableRecordBatchStream`] to a transport that buffers what it is sent,
+/// the underlying stream is only polled when [`next_batch`][Self::next_batch] is called, so a slow
+/// client holds back the production of results rather than having them buffered on the server.
+pub struct QueryResultReader {
+    schema: SchemaRef,
+    stream: SendableRecordBatchStream,
+    done: bool,
+}
+
+impl QueryResultReader {
+    pub fn new(stream: SendableRecordBatchStream) -> Self {
+        Self {
+            schema: stream.schema(),
+            stream,
+            done: false,
+        }
+    }
+
+    /// The schema of the record batches that are read
+    pub fn schema(&self) -> SchemaRef {
+        Arc::clone(&self.schema)
+    }
+
+    /// Produce the next record batch of the results, or `None` once they are exhausted
+    ///
+    /// No further batches are read once an error has been returned.
+    pub async fn next_batch(&mut self) -> Option<Result<RecordBatch, DataFusionError>> {
+        if self.done {
+            return None;
+        }
+        let next = self.stream.next().await;
+        self.done = !matches!(next, Some(Ok(_)));
+        next
+    }
+}
+
+impl fmt::Debug for QueryResultReader {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("QueryResultReader")
+            .field("schema", &self.schema)
+            .field("done", &self.done)
+            .finish_non_exhaustive()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::sync::{
+        atomic::{AtomicUsize, Ordering},
+        Arc,
+    };
+
+    use arrow::{array::Int64Array, record_batch::RecordBatch};
+    use datafusion::physical_plan::stream::RecordBatchStreamAdapter;
+    use futures::{stream, StreamExt};
+
+    use super::QueryResultReader;
+
+    #[tokio::test]
+    async fn batches_produced_on_request() {
+        let batch =
+            RecordBatch::try_from_iter([("v", Arc::new(Int64Array::from(vec![1, 2])) as _)])
+                .unwrap();
+        let produced = Arc::new(AtomicUsize::new(0));
+        let batches = {
+            let produced = Arc::clone(&produced);
+            let batch = batch.clone();
+            stream::iter(0..3).map(move |_| {
+                produced.fetch_add(1, Ordering::SeqCst);
+                Ok(batch.clone())
+            })
+        };
+        let mut reader = QueryResultReader::new(Box::pin(RecordBatchStreamAdapter::new(
+            batch.schema(),
+            batches,
+        )));
+
+        assert_eq!(0, produced.load(Ordering::SeqCst));
+        for requested in 1..=3 {
+            assert_eq!(batch, reader.next_batch().await.unwrap().unwrap());
+            assert_eq!(requested, produced.load(Ordering::SeqCst));
+        }
+        assert!(reader.next_batch().await.is_none());
+        assert_eq!(3, produced.load(Ordering::SeqCst));
+    }
+}
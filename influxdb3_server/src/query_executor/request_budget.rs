@@ -0,0 +1,52 @@
+//! A budget on the number of GET requests that a query makes to the object store, see
+//! [`CreateQueryExecutorArgs::max_storage_requests`][max]
+//!
+//! Each parquet file scanned by a query is fetched from the object store with at least one GET
+//! request, so the requests are counted as the chunks of each scan are fetched, before the scan
+//! runs. This rejects queries that would fan out to many small files up front, rather than once
+//! they have made the requests.
+//!
+//! [max]: super::CreateQueryExecutorArgs::max_storage_requests
+use std::sync::{
+    atomic::{AtomicUsize, Ordering},
+    Arc,
+};
+
+use influxdb3_internal_api::query_executor::QueryExecutorError;
+use influxdb3_write::chunk::ParquetChunk;
+use iox_query::QueryChunk;
+
+/// Counts the object store GET requests of a single query against its budget
+#[derive(Debug)]
+pub(super) struct RequestBudget {
+    max: usize,
+    count: AtomicUsize,
+}
+
+impl RequestBudget {
+    /// Allow the query up to `max` requests to the object store
+    pub(super) fn new(max: usize) -> Self {
+        Self {
+            max,
+            count: AtomicUsize::new(0),
+        }
+    }
+
+    /// Count the requests made to fetch the `chunks` of a scan, failing with
+    /// [`QueryExecutorError::StorageRequestBudgetExceeded`] if they take the query over its
+    /// budget
+    pub(super) fn charge(&self, chunks: &[Arc<dyn QueryChunk>]) -> Result<(), QueryExecutorError> {
+        let requests = chunks
+            .iter()
+            .filter(|chunk| chunk.as_any().is::<ParquetChunk>())
+            .count();
+        let count = self.count.fetch_add(requests, Ordering::Relaxed) + requests;
+        if count > self.max {
+            return Err(QueryExecutorError::StorageRequestBudgetExceeded {
+                count,
+                max: self.max,
+            });
+        }
+        Ok(())
+    }
+}
@@ -0,0 +1,180 @@
+//! A budget on the resources that a query consumes while it runs, see
+//! [`CreateQueryExecutorArgs::max_query_cost`][max]
+//!
+//! The cost of a query combines the work done by its operators, as the number of rows that each
+//! of them outputs multiplied by a weight, with the bytes that it scans. Costs are charged as the
+//! batches of each operator are produced, so that a query is cancelled part way through once it
+//! exceeds its budget, which catches queries that are expensive for reasons that a limit on their
+//! duration is too coarse to tell apart, e.g., heavy computation on each row.
+//!
+//! [max]: super::CreateQueryExecutorArgs::max_query_cost
+use std::{
+    any::Any,
+    fmt,
+    sync::{
+        atomic::{AtomicU64, Ordering},
+        Arc,
+    },
+};
+
+use datafusion::{
+    error::DataFusionError,
+    execution::{SendableRecordBatchStream, TaskContext},
+    physical_plan::{
+        stream::RecordBatchStreamAdapter, DisplayAs, DisplayFormatType, ExecutionPlan,
+        PlanProperties,
+    },
+};
+use futures::StreamExt;
+use influxdb3_internal_api::query_executor::QueryExecutorError;
+
+/// The resources consumed by a single query, against its budget
+#[derive(Debug)]
+pub(super) struct ResourceBudget {
+    max: u64,
+    row_weight: u64,
+    used: AtomicU64,
+}
+
+impl ResourceBudget {
+    /// Allow the query a cost of up to `max`, where each row output by an operator costs
+    /// `row_weight`, and each byte scanned costs one
+    pub(super) fn new(max: u64, row_weight: u64) -> Self {
+        Self {
+            max,
+            row_weight,
+            used: AtomicU64::new(0),
+        }
+    }
+
+    /// Charge the cost of a batch of `rows` rows, of which `bytes` bytes were scanned, failing
+    /// with [`QueryExecutorError::ResourceBudgetExceeded`] once the budget is exceeded
+    fn charge(&self, rows: usize, bytes: usize) -> Result<(), DataFusionError> {
+        let cost = (rows as u64)
+            .saturating_mul(self.row_weight)
+            .saturating_add(bytes as u64);
+        let used = self
+            .used
+            .fetch_add(cost, Ordering::Relaxed)
+            .saturating_add(cost);
+        if used > self.max {
+            return Err(DataFusionError::External(Box::new(
+                QueryExecutorError::ResourceBudgetExceeded {
+                    used,
+                    max: self.max,
+                },
+            )));
+        }
+        Ok(())
+    }
+}
+
+/// Wrap each of the operators of the `plan` so that the batches they produce are charged to the
+/// `budget`, where the bytes of the batches produced by the leaves, which read the data of the
+/// scanned chunks, are charged as scanned
+pub(super) fn track_plan(
+    plan: Arc<dyn ExecutionPlan>,
+    budget: &Arc<ResourceBudget>,
+) -> Result<Arc<dyn ExecutionPlan>, DataFusionError> {
+    let children = plan.children();
+    let scan = children.is_empty();
+    let plan = if scan {
+        plan
+    } else {
+        let children = children
+            .into_iter()
+            .map(|child| track_plan(Arc::clone(child), budget))
+            .collect::<Result<Vec<_>, _>>()?;
+        plan.with_new_children(children)?
+    };
+    Ok(Arc::new(ResourceBudgetExec {
+        input: plan,
+        budget: Arc::clone(budget),
+        scan,
+    }))
+}
+
+#[derive(Debug)]
+struct ResourceBudgetExec {
+    input: Arc<dyn ExecutionPlan>,
+    budget: Arc<ResourceBudget>,
+    /// Whether the input is a scan, whose batches are charged as scanned bytes
+    scan: bool,
+}
+
+impl DisplayAs for ResourceBudgetExec {
+    fn fmt_as(&self, t: DisplayFormatType, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match t {
+            DisplayFormatType::Default | DisplayFormatType::Verbose => {
+                write!(f, "ResourceBudgetExec: max={}", self.budget.max)
+            }
+        }
+    }
+}
+
+impl ExecutionPlan for ResourceBudgetExec {
+    fn name(&self) -> &str {
+        "ResourceBudgetExec"
+    }
+
+    fn as_any(&self) -> &dyn Any {
+        self
+    }
+
+    fn properties(&self) -> &PlanProperties {
+        self.input.properties()
+    }
+
+    fn maintains_input_order(&self) -> Vec<bool> {
+        vec![true]
+    }
+
+    fn benefits_from_input_partitioning(&self) -> Vec<bool> {
+        vec![false]
+    }
+
+    fn children(&self) -> Vec<&Arc<dyn ExecutionPlan>> {
+        vec![&self.input]
+    }
+
+    fn with_new_children(
+        self: Arc<Self>,
+        mut children: Vec<Arc<dyn ExecutionPlan>>,
+    ) -> Result<Arc<dyn ExecutionPlan>, DataFusionError> {
+        if children.len() != 1 {
+            return Err(DataFusionError::Internal(format!(
+                "ResourceBudgetExec expects a single child, got {}",
+                children.len()
+            )));
+        }
+        Ok(Arc::new(Self {
+            input: children.remove(0),
+            budget: Arc::clone(&self.budget),
+            scan: self.scan,
+        }))
+    }
+
+    fn execute(
+        &self,
+        partition: usize,
+        context: Arc<TaskContext>,
+    ) -> Result<SendableRecordBatchStream, DataFusionError> {
+        let stream = self.input.execute(partition, context)?;
+        let schema = stream.schema();
+        let budget = Arc::clone(&self.budget);
+        let scan = self.scan;
+        Ok(Box::pin(RecordBatchStreamAdapter::new(
+            schema,
+            stream.map(move |batch| {
+                let batch = batch?;
+                let bytes = if scan {
+                    batch.get_array_memory_size()
+                } else {
+                    0
+                };
+                budget.charge(batch.num_rows(), bytes)?;
+                Ok(batch)
+            }),
+        )))
+    }
+}
@@ -0,0 +1,529 @@
+//! An opt-in cache of query results, see [`QueryOptions::cache_results`]
+//!
+//! Cached results are tagged with the generation of each of the tables that the query scanned.
+//! The generation of a table is bumped on every write to it, as the WAL notifies the cache of the
+//! writes it persists, at which point results that scanned the table are no longer served.
+//!
+//! [`QueryOptions::cache_results`]: influxdb3_internal_api::query_executor::QueryOptions
+use std::{
+    any::Any,
+    collections::{BTreeMap, HashMap},
+    pin::Pin,
+    sync::Arc,
+    task::{Context, Poll},
+};
+
+use arrow::{datatypes::SchemaRef, record_batch::RecordBatch};
+use async_trait::async_trait;
+use datafusion::{
+    error::DataFusionError,
+    execution::{RecordBatchStream, SendableRecordBatchStream},
+    physical_plan::stream::RecordBatchStreamAdapter,
+};
+use futures::{stream, Stream, StreamExt};
+use influxdb3_id::{DbId, TableId};
+use influxdb3_internal_api::query_executor::{QueryKind, QueryOptions};
+use influxdb3_wal::{SnapshotDetails, WalContents, WalFileNotifier, WalOp};
+use iox_query_params::StatementParams;
+use metric::{Registry, U64Counter};
+use parking_lot::Mutex;
+use tokio::sync::oneshot;
+
+pub(super) const RESULT_CACHE_ACCESS_NAME: &str = "influxdb3_query_result_cache_access";
+
+/// Identifies the results of a query
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub(super) struct CacheKey {
+    database: String,
+    query_type: &'static str,
+    query: String,
+    params: String,
+    options: String,
+}
+
+impl CacheKey {
+    /// Create the key for a query, where queries that only differ by whitespace, or by options
+    /// that do not change their results, share a key
+    pub(super) fn new(
+        database: &str,
+        kind: QueryKind,
+        query: &str,
+        params: &StatementParams,
+        options: &QueryOptions,
+    ) -> Self {
+        let QueryOptions {
+            column_casts,
+            storage,
+            dictionary_stats: _,
+            constants,
+            strict_field_types,
+            time_precision,
+            allow_cross_joins: _,
+            allow_unbounded_time_range: _,
+            influxql_boolean_format,
+            influxql_epoch,
+            influxql_strict_group_by: _,
+            priority: _,
+            output_columns,
+            cache_results: _,
+            partial_aggregates,
+            tags: _,
+            log: _,
+            collation,
+            table_rewrites,
+            distinct_cache_mode,
+            max_storage_requests: _,
+            // the row ids are added to the results once they are read from the cache:
+            row_ids: _,
+            preserve_partition_order,
+            duplicate_columns,
+            chunk_order,
+            approx_count_distinct,
+        } = options;
+        Self {
+            database: database.to_string(),
+            query_type: kind.query_type(),
+            query: query.split_whitespace().collect::<Vec<_>>().join(" "),
+            // params are serialized through a JSON value, whose maps are sorted by key, so that
+            // the key does not depend on the order in which the params were given:
+            params: serde_json::to_value(params)
+                .map(|params| params.to_string())
+                .unwrap_or_default(),
+            options: format!(
+                "{:?}",
+                (
+                    column_casts.iter().collect::<BTreeMap<_, _>>(),
+                    storage,
+                    constants.iter().collect::<BTreeMap<_, _>>(),
+                    strict_field_types,
+                    time_precision,
+                    influxql_boolean_format,
+                    influxql_epoch,
+                    output_columns,
+                    partial_aggregates,
+                    collation,
+                    table_rewrites.iter().collect::<BTreeMap<_, _>>(),
+                    (
+                        distinct_cache_mode,
+                        preserve_partition_order,
+                        duplicate_columns,
+                        chunk_order,
+                        approx_count_distinct,
+                    ),
+                )
+            ),
+        }
+    }
+}
+
+/// The generation of each of the tables scanned by a query, at the time it was planned
+pub(super) type TableGenerations = Vec<(TableId, u64)>;
+
+#[derive(Debug)]
+struct CachedResult {
+    db_id: DbId,
+    tables: TableGenerations,
+    schema: SchemaRef,
+    batches: Vec<RecordBatch>,
+    size: usize,
+    /// Whether the query took longer than the slow planning threshold to plan, in which case its
+    /// results are only evicted once there are no results of faster queries left to evict
+    slow_to_plan: bool,
+    last_used: u64,
+}
+
+#[derive(Debug, Default)]
+struct CacheState {
+    entries: HashMap<CacheKey, CachedResult>,
+    /// The generation of each table that has been written to since the cache was created
+    generations: HashMap<(DbId, TableId), u64>,
+    /// The total size of the cached record batches, in bytes
+    size: usize,
+    /// Incremented on each access, to find the least recently used entry
+    clock: u64,
+}
+
+impl CacheState {
+    fn generation(&self, db_id: DbId, table_id: TableId) -> u64 {
+        self.generations
+            .get(&(db_id, table_id))
+            .copied()
+            .unwrap_or_default()
+    }
+
+    fn remove(&mut self, key: &CacheKey) {
+        if let Some(entry) = self.entries.remove(key) {
+            self.size -= entry.size;
+        }
+    }
+}
+
+/// Caches query results in memory, up to a total size in bytes, evicting the least recently used
+/// results to make room for new ones, where the results of queries that were slow to plan are
+/// evicted last
+#[derive(Debug)]
+pub(super) struct ResultCache {
+    max_bytes: usize,
+    state: Mutex<CacheState>,
+    hits: U64Counter,
+    misses: U64Counter,
+}
+
+impl ResultCache {
+    pub(super) fn new(max_bytes: usize, metric_registry: &Registry) -> Self {
+        let access = metric_registry.register_metric::<U64Counter>(
+            RESULT_CACHE_ACCESS_NAME,
+            "track accesses to the query result cache",
+        );
+        Self {
+            max_bytes,
+            state: Default::default(),
+            hits: access.recorder(&[("status", "cached")]),
+            misses: access.recorder(&[("status", "miss")]),
+        }
+    }
+
+    /// The current generation of each of the tables in the database, which have to be taken
+    /// before the query scans the tables, to be given to [`Self::cache_results`]
+    pub(super) fn generations(&self, db_id: DbId) -> HashMap<TableId, u64> {
+        self.state
+            .lock()
+            .generations
+            .iter()
+            .filter(|((db, _), _)| *db == db_id)
+            .map(|((_, table), generation)| (*table, *generation))
+            .collect()
+    }
+
+    /// Get the cached results for the query with the given `key`, if none of the tables that the
+    /// query scanned have been written to since
+    pub(super) fn get(&self, key: &CacheKey) -> Option<SendableRecordBatchStream> {
+        let mut state = self.state.lock();
+        let valid = state.entries.get(key).map(|entry| {
+            entry
+                .tables
+                .iter()
+                .all(|(table, generation)| state.generation(entry.db_id, *table) == *generation)
+        });
+        match valid {
+            Some(true) => {
+                self.hits.inc(1);
+                state.clock += 1;
+                let clock = state.clock;
+                let entry = state.entries.get_mut(key).expect("entry was found above");
+                entry.last_used = clock;
+                Some(Box::pin(RecordBatchStreamAdapter::new(
+                    Arc::clone(&entry.schema),
+                    stream::iter(entry.batches.clone().into_iter().map(Ok)),
+                )))
+            }
+            Some(false) => {
+                self.misses.inc(1);
+                state.remove(key);
+                None
+            }
+            None => {
+                self.misses.inc(1);
+                None
+            }
+        }
+    }
+
+    /// Cache the record batches produced by the `results` of the query with the given `key` once
+    /// they have all been produced, unless the query fails or they do not fit in the cache
+    ///
+    /// The results of a query that was `slow_to_plan` are kept in preference to others.
+    pub(super) fn cache_results(
+        self: &Arc<Self>,
+        key: CacheKey,
+        db_id: DbId,
+        tables: TableGenerations,
+        slow_to_plan: bool,
+        results: SendableRecordBatchStream,
+    ) -> SendableRecordBatchStream {
+        Box::pin(CachingStream {
+            inner: results,
+            pending: Some(Pending {
+                key,
+                db_id,
+                tables,
+                slow_to_plan,
+                batches: vec![],
+                size: 0,
+            }),
+            cache: Arc::clone(self),
+        })
+    }
+
+    fn insert(&self, schema: SchemaRef, pending: Pending) {
+        let Pending {
+            key,
+            db_id,
+            tables,
+            slow_to_plan,
+            batches,
+            size,
+        } = pending;
+        let mut state = self.state.lock();
+        // the tables may have been written to while the query was running:
+        if tables
+            .iter()
+            .any(|(table, generation)| state.generation(db_id, *table) != *generation)
+        {
+            return;
+        }
+        state.remove(&key);
+        while state.size + size > self.max_bytes {
+            let Some(lru) = state
+                .entries
+                .iter()
+                .min_by_key(|(_, entry)| (entry.slow_to_plan, entry.last_used))
+                .map(|(key, _)| key.clone())
+            else {
+                break;
+            };
+            state.remove(&lru);
+        }
+        state.clock += 1;
+        let last_used = state.clock;
+        state.size += size;
+        state.entries.insert(
+            key,
+            CachedResult {
+                db_id,
+                tables,
+                schema,
+                batches,
+                size,
+                slow_to_plan,
+                last_used,
+            },
+        );
+    }
+
+    /// Bump the generation of the tables written to by the WAL `contents`, and drop all of the
+    /// results for databases whose catalog changed, e.g., because a table was deleted
+    fn invalidate(&self, contents: &WalContents) {
+        let mut state = self.state.lock();
+        for op in &contents.ops {
+            match op {
+                WalOp::Write(batch) => {
+                    for table_id in batch.table_chunks.keys() {
+                        *state
+                            .generations
+                            .entry((batch.database_id, *table_id))
+                            .or_default() += 1;
+                    }
+                }
+                WalOp::Catalog(batch) => {
+                    let db_id = batch.batch().database_id;
+                    let stale = state
+                        .entries
+                        .iter()
+                        .filter(|(_, entry)| entry.db_id == db_id)
+                        .map(|(key, _)| key.clone())
+                        .collect::<Vec<_>>();
+                    for key in stale {
+                        state.remove(&key);
+                    }
+                }
+                WalOp::Noop(_) => (),
+            }
+        }
+    }
+
+    #[cfg(test)]
+    pub(super) fn hits(&self) -> u64 {
+        self.hits.fetch()
+    }
+}
+
+#[async_trait]
+impl WalFileNotifier for ResultCache {
+    async fn notify(&self, write: Arc<WalContents>) {
+        self.invalidate(&write);
+    }
+
+    async fn notify_and_snapshot(
+        &self,
+        write: Arc<WalContents>,
+        snapshot_details: SnapshotDetails,
+    ) -> oneshot::Receiver<SnapshotDetails> {
+        self.invalidate(&write);
+
+        // the cache does not take part in snapshots, so signal that it is done right away:
+        let (tx, rx) = oneshot::channel();
+        tx.send(snapshot_details).ok();
+        rx
+    }
+
+    fn as_any(&self) -> &dyn Any {
+        self
+    }
+}
+
+/// The results of a query that are gathered as they are produced, to be cached once complete
+#[derive(Debug)]
+struct Pending {
+    key: CacheKey,
+    db_id: DbId,
+    tables: TableGenerations,
+    slow_to_plan: bool,
+    batches: Vec<RecordBatch>,
+    size: usize,
+}
+
+struct CachingStream {
+    inner: SendableRecordBatchStream,
+    /// Set to `None` once the results are known not to be cached
+    pending: Option<Pending>,
+    cache: Arc<ResultCache>,
+}
+
+impl Stream for CachingStream {
+    type Item = Result<RecordBatch, DataFusionError>;
+
+    fn poll_next(mut self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Option<Self::Item>> {
+        let next = self.inner.poll_next_unpin(cx);
+        match &next {
+            Poll::Ready(Some(Ok(batch))) => {
+                let max_bytes = self.cache.max_bytes;
+                if let Some(pending) = &mut self.pending {
+                    pending.size += batch.get_array_memory_size();
+                    pending.batches.push(batch.clone());
+                    if pending.size > max_bytes {
+                        self.pending = None;
+                    }
+                }
+            }
+            Poll::Ready(Some(Err(_))) => self.pending = None,
+            Poll::Ready(None) => {
+                if let Some(pending) = self.pending.take() {
+                    let schema = self.inner.schema();
+                    self.cache.insert(schema, pending);
+                }
+            }
+            Poll::Pending => (),
+        }
+        next
+    }
+}
+
+impl RecordBatchStream for CachingStream {
+    fn schema(&self) -> SchemaRef {
+        self.inner.schema()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::sync::Arc;
+
+    use arrow::{array::Int64Array, record_batch::RecordBatch};
+    use datafusion::physical_plan::stream::RecordBatchStreamAdapter;
+    use futures::{stream, TryStreamExt};
+    use influxdb3_id::{DbId, TableId};
+    use influxdb3_internal_api::query_executor::QueryKind;
+    use metric::Registry;
+
+    use super::{CacheKey, ResultCache};
+
+    fn batch(rows: i64) -> RecordBatch {
+        RecordBatch::try_from_iter([("v", Arc::new(Int64Array::from_iter_values(0..rows)) as _)])
+            .unwrap()
+    }
+
+    async fn cache_query(cache: &Arc<ResultCache>, query: &str, table_id: TableId) {
+        cache_query_with(cache, query, table_id, false).await
+    }
+
+    async fn cache_query_with(
+        cache: &Arc<ResultCache>,
+        query: &str,
+        table_id: TableId,
+        slow_to_plan: bool,
+    ) {
+        let batch = batch(100);
+        let results = Box::pin(RecordBatchStreamAdapter::new(
+            batch.schema(),
+            stream::iter([Ok(batch)]),
+        ));
+        let key = CacheKey::new(
+            "db",
+            QueryKind::Sql,
+            query,
+            &Default::default(),
+            &Default::default(),
+        );
+        cache
+            .cache_results(
+                key,
+                DbId::from(0),
+                vec![(table_id, 0)],
+                slow_to_plan,
+                results,
+            )
+            .try_collect::<Vec<_>>()
+            .await
+            .unwrap();
+    }
+
+    fn is_cached(cache: &ResultCache, query: &str) -> bool {
+        let key = CacheKey::new(
+            "db",
+            QueryKind::Sql,
+            query,
+            &Default::default(),
+            &Default::default(),
+        );
+        cache.get(&key).is_some()
+    }
+
+    #[tokio::test]
+    async fn least_recently_used_evicted() {
+        // room for two results:
+        let size = batch(100).get_array_memory_size();
+        let cache = Arc::new(ResultCache::new(size * 2, &Registry::new()));
+
+        cache_query(&cache, "SELECT 1", TableId::from(0)).await;
+        cache_query(&cache, "SELECT 2", TableId::from(1)).await;
+        assert!(is_cached(&cache, "SELECT  1"));
+
+        // the second query is evicted, as the first was used more recently:
+        cache_query(&cache, "SELECT 3", TableId::from(2)).await;
+        assert!(is_cached(&cache, "SELECT 1"));
+        assert!(!is_cached(&cache, "SELECT 2"));
+        assert!(is_cached(&cache, "SELECT 3"));
+        assert_eq!(3, cache.hits());
+
+        // results that do not fit at all are not cached:
+        let cache = Arc::new(ResultCache::new(size / 2, &Registry::new()));
+        cache_query(&cache, "SELECT 1", TableId::from(0)).await;
+        assert!(!is_cached(&cache, "SELECT 1"));
+    }
+
+    #[tokio::test]
+    async fn slow_to_plan_evicted_last() {
+        // room for two results:
+        let size = batch(100).get_array_memory_size();
+        let cache = Arc::new(ResultCache::new(size * 2, &Registry::new()));
+
+        cache_query_with(&cache, "SELECT 1", TableId::from(0), true).await;
+        cache_query(&cache, "SELECT 2", TableId::from(1)).await;
+        assert!(is_cached(&cache, "SELECT 2"));
+
+        // the first query is least recently used, but was slow to plan, so the second is evicted:
+        cache_query(&cache, "SELECT 3", TableId::from(2)).await;
+        assert!(is_cached(&cache, "SELECT 1"));
+        assert!(!is_cached(&cache, "SELECT 2"));
+        assert!(is_cached(&cache, "SELECT 3"));
+
+        // results that were slow to plan are evicted once there are no others left to evict:
+        cache_query_with(&cache, "SELECT 4", TableId::from(3), true).await;
+        cache_query_with(&cache, "SELECT 5", TableId::from(4), true).await;
+        assert!(!is_cached(&cache, "SELECT 1"));
+        assert!(!is_cached(&cache, "SELECT 3"));
+        assert!(is_cached(&cache, "SELECT 4"));
+        assert!(is_cached(&cache, "SELECT 5"));
+    }
+}
@@ -0,0 +1,62 @@
+//! Enforcement of the retention period of a database, see
+//! [`DatabaseSchema::retention_period_ns`][period]
+//!
+//! Data older than the retention period is left in storage until it is removed, but is not
+//! queried: chunks that hold only expired data are not scanned, and the expired rows of the
+//! remaining chunks are filtered out of each scan.
+//!
+//! A period that is not positive would expire all of the data of the database, so it is treated
+//! as a configuration error, which is logged, and reported by `SHOW RETENTION POLICIES`, rather
+//! than enforced.
+//!
+//! [period]: influxdb3_catalog::catalog::DatabaseSchema::retention_period_ns
+use std::sync::Arc;
+
+use datafusion::{
+    logical_expr::{col, lit},
+    prelude::Expr,
+    scalar::ScalarValue,
+};
+use influxdb3_catalog::catalog::DatabaseSchema;
+use iox_query::QueryChunk;
+use observability_deps::tracing::warn;
+use schema::TIME_COLUMN_NAME;
+
+use super::time_range;
+
+/// The time before which the data of the database is expired, for a query made at `now`, if the
+/// database has a retention period
+pub(super) fn retention_cutoff(db_schema: &DatabaseSchema, now: i64) -> Option<i64> {
+    retention_period(db_schema).map(|period| now.saturating_sub(period))
+}
+
+/// The retention period of the database in nanoseconds, if it has one that is valid
+pub(super) fn retention_period(db_schema: &DatabaseSchema) -> Option<i64> {
+    let period = db_schema.retention_period_ns?;
+    if let Some(reason) = invalid_period(Some(period)) {
+        warn!(database = %db_schema.name, %reason, "ignoring invalid retention period");
+        return None;
+    }
+    Some(period)
+}
+
+/// Why the retention `period`, in nanoseconds, is invalid, if it is
+pub(super) fn invalid_period(period: Option<i64>) -> Option<String> {
+    period.filter(|period| *period <= 0).map(|period| {
+        format!("retention period of {period}ns is not positive, and is not enforced")
+    })
+}
+
+/// Remove the `chunks` that hold only data from before the `cutoff`
+///
+/// A chunk without statistics on time is kept, to be filtered when it is scanned.
+pub(super) fn remove_expired(chunks: &mut Vec<Arc<dyn QueryChunk>>, cutoff: i64) {
+    chunks.retain(|chunk| {
+        time_range::chunk_time_range(chunk.as_ref()).is_none_or(|(_, max)| max >= cutoff)
+    });
+}
+
+/// A filter on the rows of a scan that leaves out those from before the `cutoff`
+pub(super) fn retention_filter(cutoff: i64) -> Expr {
+    col(TIME_COLUMN_NAME).gt_eq(lit(ScalarValue::TimestampNanosecond(Some(cutoff), None)))
+}
@@ -0,0 +1,225 @@
+//! Retries of query execution that fails with a transient error before producing any results
+use std::{future::Future, io, sync::Arc, time::Duration};
+
+use datafusion::{
+    error::DataFusionError,
+    execution::SendableRecordBatchStream,
+    physical_plan::{stream::RecordBatchStreamAdapter, ExecutionPlan},
+};
+use futures::{stream, StreamExt};
+use observability_deps::tracing::warn;
+
+/// The delay before the first retry, which is doubled for each subsequent retry
+pub(super) const TRANSIENT_ERROR_BACKOFF: Duration = Duration::from_millis(100);
+
+/// Execute the `plan` with `execute`, retrying up to `max_retries` times if either the
+/// execution, or the first poll of the resulting stream, fails with a transient error
+///
+/// The first record batch is polled before returning, so that a failure to produce it can be
+/// retried. Once the first batch has been produced the stream is returned as is, and errors
+/// encountered later on are not retried, since results may already have been sent to the client.
+///
+/// Each retry executes a fresh copy of the `plan`, which is returned along with the stream.
+pub(super) async fn execute_with_retry<F, Fut>(
+    plan: Arc<dyn ExecutionPlan>,
+    max_retries: usize,
+    backoff: Duration,
+    mut execute: F,
+) -> Result<(SendableRecordBatchStream, Arc<dyn ExecutionPlan>), DataFusionError>
+where
+    F: FnMut(Arc<dyn ExecutionPlan>) -> Fut + Send,
+    Fut: Future<Output = Result<SendableRecordBatchStream, DataFusionError>> + Send,
+{
+    let mut attempt = 0;
+    let mut current = Arc::clone(&plan);
+    loop {
+        let can_retry = attempt < max_retries;
+        let error = match execute(Arc::clone(&current)).await {
+            Ok(mut results) => match results.next().await {
+                Some(Err(e)) if can_retry && is_transient(&e) => e,
+                first => {
+                    let schema = results.schema();
+                    let results = stream::iter(first).chain(results);
+                    return Ok((
+                        Box::pin(RecordBatchStreamAdapter::new(schema, results)),
+                        current,
+                    ));
+                }
+            },
+            Err(e) if can_retry && is_transient(&e) => e,
+            Err(e) => return Err(e),
+        };
+        attempt += 1;
+        let delay = backoff.saturating_mul(1_u32 << (attempt - 1).min(16));
+        warn!(
+            %error,
+            attempt,
+            ?delay,
+            "retrying query execution after transient error"
+        );
+        tokio::time::sleep(delay).await;
+        current = fresh_plan(&plan)?;
+    }
+}
+
+/// Copy the `plan`, so that any execution state held by its nodes is not shared with the
+/// original, e.g., the channels set up by a `RepartitionExec`
+///
+/// Leaf nodes do not hold execution state and are shared.
+fn fresh_plan(plan: &Arc<dyn ExecutionPlan>) -> Result<Arc<dyn ExecutionPlan>, DataFusionError> {
+    let children = plan
+        .children()
+        .into_iter()
+        .map(fresh_plan)
+        .collect::<Result<Vec<_>, _>>()?;
+    if children.is_empty() {
+        return Ok(Arc::clone(plan));
+    }
+    Arc::clone(plan).with_new_children(children)
+}
+
+/// Whether the `error` is likely to succeed if the query is executed again, e.g., because it was
+/// caused by a network error while reading from the object store
+fn is_transient(error: &DataFusionError) -> bool {
+    match error.find_root() {
+        DataFusionError::ObjectStore(e) => is_transient_object_store_error(e),
+        DataFusionError::IoError(e) => is_transient_io_error(e),
+        DataFusionError::External(e) => {
+            e.downcast_ref::<object_store::Error>()
+                .is_some_and(is_transient_object_store_error)
+                || e.downcast_ref::<io::Error>()
+                    .is_some_and(is_transient_io_error)
+        }
+        _ => false,
+    }
+}
+
+/// Errors from the object store that have a specific cause, e.g., a missing object or invalid
+/// credentials, will not go away on retry, whereas [`object_store::Error::Generic`] is used for
+/// request failures
+fn is_transient_object_store_error(error: &object_store::Error) -> bool {
+    matches!(error, object_store::Error::Generic { .. })
+}
+
+fn is_transient_io_error(error: &io::Error) -> bool {
+    matches!(
+        error.kind(),
+        io::ErrorKind::ConnectionReset
+            | io::ErrorKind::ConnectionAborted
+            | io::ErrorKind::BrokenPipe
+            | io::ErrorKind::TimedOut
+            | io::ErrorKind::Interrupted
+            | io::ErrorKind::UnexpectedEof
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use std::{
+        sync::{
+            atomic::{AtomicUsize, Ordering},
+            Arc,
+        },
+        time::Duration,
+    };
+
+    use arrow::{array::Int64Array, record_batch::RecordBatch};
+    use datafusion::{
+        error::DataFusionError,
+        execution::SendableRecordBatchStream,
+        physical_plan::{empty::EmptyExec, stream::RecordBatchStreamAdapter, ExecutionPlan},
+    };
+    use futures::{stream, TryStreamExt};
+
+    use super::execute_with_retry;
+
+    fn batch() -> RecordBatch {
+        RecordBatch::try_from_iter([("v", Arc::new(Int64Array::from(vec![1, 2])) as _)]).unwrap()
+    }
+
+    fn results(items: Vec<Result<RecordBatch, DataFusionError>>) -> SendableRecordBatchStream {
+        Box::pin(RecordBatchStreamAdapter::new(
+            batch().schema(),
+            stream::iter(items),
+        ))
+    }
+
+    fn transient() -> DataFusionError {
+        DataFusionError::ObjectStore(object_store::Error::Generic {
+            store: "flaky",
+            source: "connection reset by peer".into(),
+        })
+    }
+
+    fn not_found() -> DataFusionError {
+        DataFusionError::ObjectStore(object_store::Error::NotFound {
+            path: "missing.parquet".to_string(),
+            source: "not found".into(),
+        })
+    }
+
+    fn plan() -> Arc<dyn ExecutionPlan> {
+        Arc::new(EmptyExec::new(batch().schema()))
+    }
+
+    /// Execution fails transiently, then the first poll fails transiently, then it succeeds
+    #[tokio::test]
+    async fn transient_then_success() {
+        let attempts = AtomicUsize::new(0);
+        let (stream, _) = execute_with_retry(plan(), 3, Duration::ZERO, |_| {
+            let attempt = attempts.fetch_add(1, Ordering::SeqCst);
+            async move {
+                match attempt {
+                    0 => Err(transient()),
+                    1 => Ok(results(vec![Err(transient())])),
+                    _ => Ok(results(vec![Ok(batch()), Ok(batch())])),
+                }
+            }
+        })
+        .await
+        .unwrap();
+        let batches = stream.try_collect::<Vec<_>>().await.unwrap();
+        assert_eq!(vec![batch(), batch()], batches);
+        assert_eq!(3, attempts.load(Ordering::SeqCst));
+    }
+
+    #[tokio::test]
+    async fn retries_exhausted() {
+        let attempts = AtomicUsize::new(0);
+        let (stream, _) = execute_with_retry(plan(), 2, Duration::ZERO, |_| {
+            attempts.fetch_add(1, Ordering::SeqCst);
+            async { Ok(results(vec![Err(transient())])) }
+        })
+        .await
+        .unwrap();
+        stream.try_collect::<Vec<_>>().await.unwrap_err();
+        assert_eq!(3, attempts.load(Ordering::SeqCst));
+    }
+
+    #[tokio::test]
+    async fn no_retry_of_permanent_errors() {
+        let attempts = AtomicUsize::new(0);
+        let error = execute_with_retry(plan(), 2, Duration::ZERO, |_| {
+            attempts.fetch_add(1, Ordering::SeqCst);
+            async { Err::<SendableRecordBatchStream, _>(not_found()) }
+        })
+        .await
+        .map(|_| ())
+        .unwrap_err();
+        assert!(matches!(error, DataFusionError::ObjectStore(_)));
+        assert_eq!(1, attempts.load(Ordering::SeqCst));
+    }
+
+    #[tokio::test]
+    async fn no_retry_after_first_batch() {
+        let attempts = AtomicUsize::new(0);
+        let (stream, _) = execute_with_retry(plan(), 2, Duration::ZERO, |_| {
+            attempts.fetch_add(1, Ordering::SeqCst);
+            async { Ok(results(vec![Ok(batch()), Err(transient())])) }
+        })
+        .await
+        .unwrap();
+        stream.try_collect::<Vec<_>>().await.unwrap_err();
+        assert_eq!(1, attempts.load(Ordering::SeqCst));
+    }
+}
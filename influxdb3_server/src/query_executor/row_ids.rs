@@ -0,0 +1,48 @@
+//! Numbering of the rows output by a query, see [`QueryOptions::row_ids`]
+//!
+//! [`QueryOptions::row_ids`]: influxdb3_internal_api::query_executor::QueryOptions::row_ids
+use std::sync::Arc;
+
+use arrow::{
+    array::{ArrayRef, UInt64Array},
+    datatypes::{DataType, Field, Schema},
+    record_batch::RecordBatch,
+};
+use datafusion::{
+    error::DataFusionError, execution::SendableRecordBatchStream,
+    physical_plan::stream::RecordBatchStreamAdapter,
+};
+use futures::StreamExt;
+
+/// The name of the column that holds the id of each row output by a query
+pub const ROW_ID_COLUMN_NAME: &str = "__row_id";
+
+/// Append a [`ROW_ID_COLUMN_NAME`] column to the `results`, numbering their rows from zero in the
+/// order that they are output, across all of the batches of the stream
+pub(super) fn add_row_ids(results: SendableRecordBatchStream) -> SendableRecordBatchStream {
+    let input_schema = results.schema();
+    let mut fields = input_schema.fields().to_vec();
+    fields.push(Arc::new(Field::new(
+        ROW_ID_COLUMN_NAME,
+        DataType::UInt64,
+        false,
+    )));
+    let schema = Arc::new(Schema::new_with_metadata(
+        fields,
+        input_schema.metadata().clone(),
+    ));
+    let mut next_id = 0;
+    let batches = results.map({
+        let schema = Arc::clone(&schema);
+        move |batch| {
+            let batch = batch?;
+            let end = next_id + batch.num_rows() as u64;
+            let ids = Arc::new(UInt64Array::from_iter_values(next_id..end)) as ArrayRef;
+            next_id = end;
+            let mut columns = batch.columns().to_vec();
+            columns.push(ids);
+            RecordBatch::try_new(Arc::clone(&schema), columns).map_err(DataFusionError::from)
+        }
+    });
+    Box::pin(RecordBatchStreamAdapter::new(schema, batches))
+}
@@ -0,0 +1,427 @@
+//! Coalescing of identical queries that are made concurrently, so that they share a single
+//! execution
+//!
+//! The first of a set of identical queries leads: it is planned and executed as usual, and its
+//! results are buffered as they are produced. Identical queries that arrive while it is in flight
+//! follow it, reading the buffered results rather than being executed themselves. Any of the
+//! queries that have caught up with the buffer polls the shared execution for the next batch, so a
+//! slow client does not hold back the others.
+//!
+//! The buffer holds every batch produced until it reaches its size limit, at which point late
+//! arrivals can no longer follow, and batches are dropped once all of the queries that are
+//! following have read them.
+use std::{
+    collections::{HashMap, VecDeque},
+    fmt,
+    sync::{Arc, Weak},
+};
+
+use arrow::{datatypes::SchemaRef, record_batch::RecordBatch};
+use datafusion::{
+    error::DataFusionError, execution::SendableRecordBatchStream,
+    physical_plan::stream::RecordBatchStreamAdapter,
+};
+use futures::{stream, StreamExt};
+use metric::{Registry, U64Counter};
+use parking_lot::Mutex;
+use tokio::sync::watch;
+
+use super::result_cache::CacheKey;
+
+pub(super) const COALESCED_QUERIES_NAME: &str = "influxdb3_query_coalesced";
+
+/// The identical queries that are currently in flight, by [`CacheKey`]
+#[derive(Debug)]
+pub(super) struct InFlightQueries {
+    max_bytes: usize,
+    flights: Mutex<HashMap<CacheKey, Weak<Flight>>>,
+    coalesced: U64Counter,
+}
+
+impl InFlightQueries {
+    pub(super) fn new(max_bytes: usize, metric_registry: &Registry) -> Self {
+        Self {
+            max_bytes,
+            flights: Default::default(),
+            coalesced: metric_registry
+                .register_metric::<U64Counter>(
+                    COALESCED_QUERIES_NAME,
+                    "queries served from the execution of an identical concurrent query",
+                )
+                .recorder(&[]),
+        }
+    }
+
+    /// Join the in-flight execution of the query with the given `key` if there is one, or else
+    /// start one that identical queries made before it completes can join
+    pub(super) fn join(self: &Arc<Self>, key: CacheKey) -> Joined {
+        let mut flights = self.flights.lock();
+        flights.retain(|_, flight| flight.strong_count() > 0);
+        if let Some(flight) = flights.get(&key).and_then(Weak::upgrade) {
+            return Joined::Follower(Follower {
+                consumer: Consumer::new(flight),
+            });
+        }
+        let (status, _) = watch::channel(Status::Planning);
+        let flight = Arc::new(Flight {
+            key: key.clone(),
+            status,
+            stream: Default::default(),
+            buffer: Default::default(),
+            max_bytes: self.max_bytes,
+            in_flight: Arc::downgrade(self),
+        });
+        flights.insert(key, Arc::downgrade(&flight));
+        Joined::Leader(Leader {
+            consumer: Some(Consumer::new(flight)),
+        })
+    }
+
+    /// No longer let queries join the `flight`
+    fn close(&self, flight: &Flight) {
+        let mut flights = self.flights.lock();
+        if flights
+            .get(&flight.key)
+            .is_some_and(|f| std::ptr::eq(f.as_ptr(), flight))
+        {
+            flights.remove(&flight.key);
+        }
+    }
+}
+
+pub(super) enum Joined {
+    /// The query is the first of its kind in flight, and has to be executed
+    Leader(Leader),
+    /// An identical query is in flight, whose results are shared
+    Follower(Follower),
+}
+
+/// Held by the query that executes the flight, until it has started producing results
+#[derive(Debug)]
+pub(super) struct Leader {
+    consumer: Option<Consumer>,
+}
+
+impl Leader {
+    /// Share the `results` of the executed query with those that follow it, returning the
+    /// results for the query itself
+    pub(super) fn share(mut self, results: SendableRecordBatchStream) -> SendableRecordBatchStream {
+        let consumer = self.consumer.take().expect("results are only shared once");
+        let schema = results.schema();
+        *consumer
+            .flight
+            .stream
+            .try_lock()
+            .expect("stream is not polled before it is shared") = Some(results);
+        consumer
+            .flight
+            .status
+            .send_replace(Status::Running(Arc::clone(&schema)));
+        consume(consumer, schema)
+    }
+}
+
+impl Drop for Leader {
+    fn drop(&mut self) {
+        // the leader failed before it produced any results, so those following it have to run the
+        // query themselves:
+        if let Some(consumer) = self.consumer.take() {
+            consumer.flight.close();
+            consumer.flight.status.send_replace(Status::Failed);
+        }
+    }
+}
+
+/// Held by a query that follows the execution of an identical query
+#[derive(Debug)]
+pub(super) struct Follower {
+    consumer: Consumer,
+}
+
+impl Follower {
+    /// Wait for the query being followed to start producing results, then read them from the
+    /// start, or `None` if it failed, in which case the query has to be run by itself
+    pub(super) async fn results(
+        self,
+        in_flight: &InFlightQueries,
+    ) -> Option<SendableRecordBatchStream> {
+        let mut status = self.consumer.flight.status.subscribe();
+        let schema = match &*status
+            .wait_for(|status| !matches!(status, Status::Planning))
+            .await
+            .ok()?
+        {
+            Status::Running(schema) => Arc::clone(schema),
+            Status::Planning | Status::Failed => return None,
+        };
+        in_flight.coalesced.inc(1);
+        Some(consume(self.consumer, schema))
+    }
+}
+
+#[derive(Debug, Clone)]
+enum Status {
+    Planning,
+    Running(SchemaRef),
+    Failed,
+}
+
+struct Flight {
+    key: CacheKey,
+    status: watch::Sender<Status>,
+    /// The results of the query, which are polled by whichever consumer needs the next batch
+    stream: tokio::sync::Mutex<Option<SendableRecordBatchStream>>,
+    buffer: Mutex<Buffer>,
+    max_bytes: usize,
+    in_flight: Weak<InFlightQueries>,
+}
+
+impl fmt::Debug for Flight {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("Flight")
+            .field("key", &self.key)
+            .field("status", &*self.status.borrow())
+            .finish_non_exhaustive()
+    }
+}
+
+impl Flight {
+    fn close(&self) {
+        if let Some(in_flight) = self.in_flight.upgrade() {
+            in_flight.close(self);
+        }
+    }
+}
+
+#[derive(Debug, Default)]
+struct Buffer {
+    batches: VecDeque<RecordBatch>,
+    /// The index of the front of `batches` among all of the batches produced
+    first: usize,
+    size: usize,
+    /// Set once the results are exhausted, to the error they ended with, if any
+    end: Option<Option<String>>,
+    /// Set once the buffer reached its size limit, or the results are exhausted
+    closed: bool,
+    /// The index of the next batch to be read by each consumer
+    cursors: HashMap<usize, usize>,
+    next_consumer: usize,
+}
+
+impl Buffer {
+    /// Drop the batches that have been read by all of the consumers, once no more consumers can
+    /// join to read them from the start
+    fn trim(&mut self) {
+        if !self.closed {
+            return;
+        }
+        let read = self.cursors.values().min().copied().unwrap_or(usize::MAX);
+        while self.first < read {
+            let Some(batch) = self.batches.pop_front() else {
+                break;
+            };
+            self.size -= batch.get_array_memory_size();
+            self.first += 1;
+        }
+    }
+}
+
+enum Next {
+    Batch(RecordBatch),
+    End(Option<String>),
+    /// The consumer has read all of the buffered batches
+    Pending,
+}
+
+/// Reads the results of a [`Flight`] for one of the queries that share it
+///
+/// Consumers are created as queries join the flight, so that the batches produced before they
+/// start reading are kept for them.
+#[derive(Debug)]
+struct Consumer {
+    flight: Arc<Flight>,
+    id: usize,
+    done: bool,
+}
+
+impl Consumer {
+    fn new(flight: Arc<Flight>) -> Self {
+        let id = {
+            let mut buffer = flight.buffer.lock();
+            let id = buffer.next_consumer;
+            buffer.next_consumer += 1;
+            let first = buffer.first;
+            buffer.cursors.insert(id, first);
+            id
+        };
+        Self {
+            flight,
+            id,
+            done: false,
+        }
+    }
+
+    fn buffered(&self) -> Next {
+        let mut buffer = self.flight.buffer.lock();
+        let cursor = buffer.cursors[&self.id];
+        if let Some(batch) = buffer.batches.get(cursor - buffer.first).cloned() {
+            buffer.cursors.insert(self.id, cursor + 1);
+            buffer.trim();
+            return Next::Batch(batch);
+        }
+        match &buffer.end {
+            Some(error) => Next::End(error.clone()),
+            None => Next::Pending,
+        }
+    }
+
+    async fn next(&mut self) -> Option<Result<RecordBatch, DataFusionError>> {
+        if self.done {
+            return None;
+        }
+        loop {
+            match self.buffered() {
+                Next::Batch(batch) => return Some(Ok(batch)),
+                Next::End(error) => {
+                    self.done = true;
+                    return error.map(|e| Err(DataFusionError::Execution(e)));
+                }
+                Next::Pending => (),
+            }
+            let mut stream = self.flight.stream.lock().await;
+            // another consumer may have polled the results while this one waited for the lock:
+            if !self.caught_up() {
+                continue;
+            }
+            match stream.as_mut()?.next().await {
+                Some(Err(e)) => {
+                    // the consumer that polled the error gets it as is, the others get its message:
+                    self.push(Some(Err(e.to_string())));
+                    self.done = true;
+                    return Some(Err(e));
+                }
+                next => self.push(next.map(|next| next.map_err(|e| e.to_string()))),
+            }
+        }
+    }
+
+    fn caught_up(&self) -> bool {
+        let buffer = self.flight.buffer.lock();
+        buffer.end.is_none() && buffer.cursors[&self.id] - buffer.first == buffer.batches.len()
+    }
+
+    fn push(&self, next: Option<Result<RecordBatch, String>>) {
+        let close = {
+            let mut buffer = self.flight.buffer.lock();
+            match next {
+                Some(Ok(batch)) => {
+                    buffer.size += batch.get_array_memory_size();
+                    buffer.batches.push_back(batch);
+                }
+                Some(Err(e)) => buffer.end = Some(Some(e)),
+                None => buffer.end = Some(None),
+            }
+            let close =
+                !buffer.closed && (buffer.end.is_some() || buffer.size > self.flight.max_bytes);
+            buffer.closed |= close;
+            buffer.trim();
+            close
+        };
+        // the buffer is released first, as it is locked while joining the flight:
+        if close {
+            self.flight.close();
+        }
+    }
+}
+
+impl Drop for Consumer {
+    fn drop(&mut self) {
+        let mut buffer = self.flight.buffer.lock();
+        buffer.cursors.remove(&self.id);
+        buffer.trim();
+    }
+}
+
+fn consume(consumer: Consumer, schema: SchemaRef) -> SendableRecordBatchStream {
+    Box::pin(RecordBatchStreamAdapter::new(
+        schema,
+        stream::unfold(consumer, |mut consumer| async move {
+            consumer.next().await.map(|next| (next, consumer))
+        }),
+    ))
+}
+
+#[cfg(test)]
+mod tests {
+    use std::sync::Arc;
+
+    use arrow::{array::Int64Array, record_batch::RecordBatch};
+    use datafusion::physical_plan::stream::RecordBatchStreamAdapter;
+    use futures::{stream, TryStreamExt};
+    use influxdb3_internal_api::query_executor::QueryKind;
+    use metric::Registry;
+
+    use super::{InFlightQueries, Joined};
+    use crate::query_executor::result_cache::CacheKey;
+
+    fn batch(rows: i64) -> RecordBatch {
+        RecordBatch::try_from_iter([("v", Arc::new(Int64Array::from_iter_values(0..rows)) as _)])
+            .unwrap()
+    }
+
+    fn key() -> CacheKey {
+        CacheKey::new(
+            "db",
+            QueryKind::Sql,
+            "SELECT v FROM t",
+            &Default::default(),
+            &Default::default(),
+        )
+    }
+
+    #[tokio::test]
+    async fn late_arrivals_read_from_the_start_until_buffer_is_full() {
+        let size = batch(100).get_array_memory_size();
+        let in_flight = Arc::new(InFlightQueries::new(size * 2, &Registry::new()));
+
+        let Joined::Leader(leader) = in_flight.join(key()) else {
+            panic!("first query leads");
+        };
+        let Joined::Follower(early) = in_flight.join(key()) else {
+            panic!("identical query follows");
+        };
+        let batches = stream::iter((0..3).map(|_| Ok(batch(100))));
+        let mut leader = leader.share(Box::pin(RecordBatchStreamAdapter::new(
+            batch(100).schema(),
+            batches,
+        )));
+        let early = early.results(&in_flight).await.unwrap();
+
+        // a query arriving after a batch was produced still reads all of the results:
+        leader.try_next().await.unwrap().unwrap();
+        let Joined::Follower(late) = in_flight.join(key()) else {
+            panic!("identical query follows");
+        };
+        let late = late.results(&in_flight).await.unwrap();
+        assert_eq!(3, late.try_collect::<Vec<_>>().await.unwrap().len());
+
+        // once the buffer is full, queries can no longer join:
+        assert!(matches!(in_flight.join(key()), Joined::Leader(_)));
+        assert_eq!(2, leader.try_collect::<Vec<_>>().await.unwrap().len());
+        assert_eq!(3, early.try_collect::<Vec<_>>().await.unwrap().len());
+    }
+
+    #[tokio::test]
+    async fn followers_run_query_if_leader_fails() {
+        let in_flight = Arc::new(InFlightQueries::new(1024, &Registry::new()));
+        let Joined::Leader(leader) = in_flight.join(key()) else {
+            panic!("first query leads");
+        };
+        let Joined::Follower(follower) = in_flight.join(key()) else {
+            panic!("identical query follows");
+        };
+        drop(leader);
+        assert!(follower.results(&in_flight).await.is_none());
+        assert!(matches!(in_flight.join(key()), Joined::Leader(_)));
+    }
+}
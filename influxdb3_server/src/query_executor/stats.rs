@@ -0,0 +1,397 @@
+//! Statistics recorded for queries in addition to those held in the [`QueryLog`]
+//!
+//! [`QueryLog`]: iox_query::query_log::QueryLog
+use std::{
+    collections::{BTreeMap, HashMap, VecDeque},
+    fmt::Display,
+    pin::Pin,
+    sync::Arc,
+    task::{Context, Poll},
+    time::{Duration, Instant},
+};
+
+use arrow::{datatypes::SchemaRef, record_batch::RecordBatch};
+use datafusion::{
+    error::DataFusionError,
+    execution::{RecordBatchStream, SendableRecordBatchStream},
+    physical_plan::{metrics::MetricValue, ExecutionPlan},
+};
+use futures::{Stream, StreamExt};
+use iox_query::query_log::{QueryCompletedToken, StatePermit};
+use parking_lot::Mutex;
+use tokio::sync::broadcast;
+
+use super::dictionary_stats::{DictionaryColumnStats, DictionaryStatsCollector};
+
+/// How many completed queries a subscriber to the query log can fall behind by before its
+/// subscription is ended
+const SUBSCRIPTION_CAPACITY: usize = 1024;
+
+/// Statistics for queries in the query log, keyed by the id of their query log entry
+///
+/// Only as many entries as the query log holds are retained, older entries are evicted first.
+#[derive(Debug)]
+pub(crate) struct QueryLogStats {
+    entries: Mutex<StatsEntries>,
+    /// Sends the query log entry id of each query as it completes
+    completed: broadcast::Sender<String>,
+    /// The cost of the tagged queries that have completed, by tag key and value
+    tag_costs: Mutex<BTreeMap<(String, String), QueryTagCost>>,
+}
+
+#[derive(Debug)]
+struct StatsEntries {
+    capacity: usize,
+    order: VecDeque<String>,
+    stats: HashMap<String, QueryStats>,
+}
+
+impl QueryLogStats {
+    pub(crate) fn new(capacity: usize) -> Self {
+        Self {
+            entries: Mutex::new(StatsEntries {
+                capacity,
+                order: VecDeque::with_capacity(capacity),
+                stats: HashMap::with_capacity(capacity),
+            }),
+            completed: broadcast::channel(SUBSCRIPTION_CAPACITY).0,
+            tag_costs: Default::default(),
+        }
+    }
+
+    /// Record the `tags` given to the query with the given query log entry `id`
+    pub(super) fn set_tags(&self, id: &str, tags: BTreeMap<String, String>) {
+        self.update(id, |stats| stats.tags = tags);
+    }
+
+    /// Record that the query with the given query log entry `id` was not executed, as its
+    /// results were read from the given `source`
+    pub(super) fn set_result_source(&self, id: &str, source: ResultSource) {
+        self.update(id, |stats| stats.result_source = Some(source));
+    }
+
+    /// The cost of the completed queries with each tag, ordered by tag key and value
+    pub(super) fn tag_costs(&self) -> Vec<QueryTagCost> {
+        self.tag_costs.lock().values().cloned().collect()
+    }
+
+    /// Add the cost of a completed query to each of its `tags`
+    fn add_tag_costs(
+        &self,
+        tags: &BTreeMap<String, String>,
+        output_bytes: usize,
+        duration: Duration,
+    ) {
+        let mut costs = self.tag_costs.lock();
+        for (key, value) in tags {
+            let cost = costs
+                .entry((key.clone(), value.clone()))
+                .or_insert_with(|| QueryTagCost {
+                    key: key.clone(),
+                    value: value.clone(),
+                    ..Default::default()
+                });
+            cost.queries += 1;
+            cost.output_bytes += output_bytes as u64;
+            cost.duration += duration;
+        }
+    }
+
+    /// Receive the query log entry ids of queries as they complete
+    pub(super) fn subscribe(&self) -> broadcast::Receiver<String> {
+        self.completed.subscribe()
+    }
+
+    /// Notify subscribers that the query with the given query log entry `id` has completed
+    pub(super) fn complete(&self, id: Option<&str>) {
+        if let Some(id) = id {
+            // there being no subscribers is not an error:
+            let _ = self.completed.send(id.to_string());
+        }
+    }
+
+    /// Get the statistics recorded for the query log entry with the given `id`
+    pub(crate) fn get(&self, id: &str) -> Option<QueryStats> {
+        self.entries.lock().stats.get(id).cloned()
+    }
+
+    fn update(&self, id: &str, f: impl FnOnce(&mut QueryStats)) {
+        let mut entries = self.entries.lock();
+        if !entries.stats.contains_key(id) {
+            if entries.capacity == 0 {
+                return;
+            }
+            while entries.order.len() >= entries.capacity {
+                if let Some(evicted) = entries.order.pop_front() {
+                    entries.stats.remove(&evicted);
+                }
+            }
+            entries.order.push_back(id.to_string());
+        }
+        f(entries.stats.entry(id.to_string()).or_default());
+    }
+}
+
+/// Statistics recorded for an individual query
+#[derive(Debug, Clone, Default)]
+pub(crate) struct QueryStats {
+    /// How the rows output by the query were distributed over the partitions of its plan, which
+    /// is recorded once the query's output stream has been fully consumed
+    pub(crate) partition_rows: Option<PartitionRowStats>,
+    /// Statistics for the dictionary encoded columns scanned by the query, if they were requested
+    pub(crate) dictionary_stats: Option<Vec<DictionaryColumnStats>>,
+    /// The tags given to the query, see [`QueryOptions::tags`]
+    ///
+    /// [`QueryOptions::tags`]: influxdb3_internal_api::query_executor::QueryOptions
+    pub(crate) tags: BTreeMap<String, String>,
+    /// Where the results of the query were read from, if it was not executed itself
+    pub(crate) result_source: Option<ResultSource>,
+}
+
+/// Where the results of a query that was not executed itself were read from
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub(crate) enum ResultSource {
+    /// The results were cached, see [`QueryOptions::cache_results`]
+    ///
+    /// [`QueryOptions::cache_results`]: influxdb3_internal_api::query_executor::QueryOptions
+    Cache,
+    /// The results were shared by an identical query that was in flight, see
+    /// [`CreateQueryExecutorArgs::coalesce_buffer_size`]
+    ///
+    /// [`CreateQueryExecutorArgs::coalesce_buffer_size`]: super::CreateQueryExecutorArgs
+    Coalesced,
+}
+
+impl Display for ResultSource {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Self::Cache => write!(f, "cache"),
+            Self::Coalesced => write!(f, "coalesced"),
+        }
+    }
+}
+
+/// The total cost of the queries made with a tag
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct QueryTagCost {
+    pub key: String,
+    pub value: String,
+    /// The number of queries whose results were read to completion
+    pub queries: u64,
+    /// The in-memory size of the record batches output by the queries
+    pub output_bytes: u64,
+    /// The time taken by the queries, from being issued until their results were read
+    pub duration: Duration,
+}
+
+/// The distribution of rows over the output partitions of a query plan
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub(crate) struct PartitionRowStats {
+    pub(crate) partitions: usize,
+    pub(crate) min_rows: usize,
+    pub(crate) max_rows: usize,
+    pub(crate) avg_rows: f64,
+}
+
+impl PartitionRowStats {
+    fn from_row_counts(row_counts: &[usize]) -> Option<Self> {
+        let min_rows = row_counts.iter().copied().min()?;
+        let max_rows = row_counts.iter().copied().max()?;
+        let total = row_counts.iter().sum::<usize>();
+        Some(Self {
+            partitions: row_counts.len(),
+            min_rows,
+            max_rows,
+            avg_rows: total as f64 / row_counts.len() as f64,
+        })
+    }
+}
+
+/// Find the row counts of each partition of the first node in the `plan` that has more than
+/// one output partition, using the output row metrics recorded during execution.
+///
+/// Returns `None` if there is no such node, or it did not record its output rows.
+fn partition_row_counts(plan: &Arc<dyn ExecutionPlan>) -> Option<Vec<usize>> {
+    let partitions = plan.properties().output_partitioning().partition_count();
+    if partitions > 1 {
+        if let Some(metrics) = plan.metrics() {
+            let mut row_counts = vec![0; partitions];
+            let mut recorded = false;
+            for metric in metrics.iter() {
+                if let (MetricValue::OutputRows(count), Some(partition)) =
+                    (metric.value(), metric.partition())
+                {
+                    if let Some(rows) = row_counts.get_mut(partition) {
+                        *rows += count.value();
+                        recorded = true;
+                    }
+                }
+            }
+            if recorded {
+                return Some(row_counts);
+            }
+        }
+    }
+    plan.children().into_iter().find_map(partition_row_counts)
+}
+
+/// Wraps the output stream of a query to record its [`PartitionRowStats`] in the
+/// [`QueryLogStats`] once the stream has been fully consumed
+pub(super) struct StatsRecordingStream {
+    inner: SendableRecordBatchStream,
+    plan: Arc<dyn ExecutionPlan>,
+    log_stats: Arc<QueryLogStats>,
+    /// The query log entry id, which is taken once the stats have been recorded
+    query_id: Option<String>,
+    /// Completes the query log entry once the results have been read to the end, see
+    /// [`StatsRecordingStream::with_token`]
+    token: Option<QueryCompletedToken<StatePermit>>,
+    dictionary_stats: Option<Arc<DictionaryStatsCollector>>,
+    /// The tags of the query along with when it was issued, to record its cost under each tag
+    tags: Option<(BTreeMap<String, String>, Instant)>,
+    total_rows: usize,
+    total_bytes: usize,
+}
+
+impl StatsRecordingStream {
+    pub(super) fn new(
+        inner: SendableRecordBatchStream,
+        plan: Arc<dyn ExecutionPlan>,
+        log_stats: Arc<QueryLogStats>,
+        query_id: Option<String>,
+    ) -> Self {
+        Self {
+            inner,
+            plan,
+            log_stats,
+            query_id,
+            token: None,
+            dictionary_stats: None,
+            tags: None,
+            total_rows: 0,
+            total_bytes: 0,
+        }
+    }
+
+    /// Also record the statistics gathered by the dictionary stats `collector`
+    pub(super) fn with_dictionary_stats(
+        mut self,
+        collector: Option<Arc<DictionaryStatsCollector>>,
+    ) -> Self {
+        self.dictionary_stats = collector;
+        self
+    }
+
+    /// Mark the query log entry of the `token` as succeeded once the results have been read to
+    /// the end, or as failed if they end with an error, or are dropped before then, which also
+    /// cancels the execution of the query
+    pub(super) fn with_token(mut self, token: QueryCompletedToken<StatePermit>) -> Self {
+        self.token = Some(token);
+        self
+    }
+
+    /// Also record the cost of the query, which was issued at `started`, under each of its `tags`
+    pub(super) fn with_tags(mut self, tags: BTreeMap<String, String>, started: Instant) -> Self {
+        if !tags.is_empty() {
+            self.tags = Some((tags, started));
+        }
+        self
+    }
+
+    fn record(&mut self) {
+        if let Some((tags, started)) = self.tags.take() {
+            self.log_stats
+                .add_tag_costs(&tags, self.total_bytes, started.elapsed());
+        }
+        let Some(query_id) = self.query_id.take() else {
+            return;
+        };
+        // a plan without multiple partitions output all of its rows from a single partition:
+        let row_counts = partition_row_counts(&self.plan).unwrap_or_else(|| vec![self.total_rows]);
+        let partition_rows = PartitionRowStats::from_row_counts(&row_counts);
+        let dictionary_stats = self.dictionary_stats.as_ref().map(|c| c.stats());
+        self.log_stats.update(&query_id, |stats| {
+            stats.partition_rows = partition_rows;
+            stats.dictionary_stats = dictionary_stats;
+        });
+        self.log_stats.complete(Some(&query_id));
+    }
+}
+
+impl Stream for StatsRecordingStream {
+    type Item = Result<RecordBatch, DataFusionError>;
+
+    fn poll_next(mut self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Option<Self::Item>> {
+        let poll = self.inner.poll_next_unpin(cx);
+        match &poll {
+            Poll::Ready(Some(Ok(batch))) => {
+                self.total_rows += batch.num_rows();
+                self.total_bytes += batch.get_array_memory_size();
+            }
+            Poll::Ready(Some(Err(_))) => {
+                if let Some(token) = self.token.take() {
+                    token.fail();
+                }
+            }
+            Poll::Ready(None) => {
+                if let Some(token) = self.token.take() {
+                    token.success();
+                }
+                self.record();
+            }
+            Poll::Pending => (),
+        }
+        poll
+    }
+}
+
+impl Drop for StatsRecordingStream {
+    fn drop(&mut self) {
+        // the results were dropped before they were read to the end, where the execution of the
+        // query is cancelled along with the `inner` stream:
+        if let Some(token) = self.token.take() {
+            token.fail();
+        }
+        if let Some(query_id) = self.query_id.take() {
+            self.log_stats.complete(Some(&query_id));
+        }
+    }
+}
+
+impl RecordBatchStream for StatsRecordingStream {
+    fn schema(&self) -> SchemaRef {
+        self.inner.schema()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{PartitionRowStats, QueryLogStats};
+
+    #[test]
+    fn partition_row_stats() {
+        assert_eq!(None, PartitionRowStats::from_row_counts(&[]));
+        assert_eq!(
+            Some(PartitionRowStats {
+                partitions: 4,
+                min_rows: 0,
+                max_rows: 10,
+                avg_rows: 3.5,
+            }),
+            PartitionRowStats::from_row_counts(&[10, 0, 2, 2])
+        );
+    }
+
+    #[test]
+    fn evicts_oldest_entries() {
+        let log_stats = QueryLogStats::new(2);
+        for id in ["a", "b", "c"] {
+            log_stats.update(id, |stats| {
+                stats.partition_rows = PartitionRowStats::from_row_counts(&[1])
+            });
+        }
+        assert!(log_stats.get("a").is_none());
+        assert!(log_stats.get("b").is_some());
+        assert!(log_stats.get("c").is_some());
+    }
+}
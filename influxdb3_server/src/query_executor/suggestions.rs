@@ -0,0 +1,111 @@
+//! Suggestions for queries that reference columns that do not exist
+use datafusion::common::SchemaError;
+use datafusion::error::DataFusionError;
+use influxdb3_catalog::catalog::DatabaseSchema;
+
+/// The maximum number of column names suggested for an unknown column
+const MAX_SUGGESTIONS: usize = 3;
+
+/// Check if the planning `error` was caused by a reference to a column that does not exist, and
+/// if so, return the name of the column along with the closest matching column names found in
+/// the tables referenced by the query.
+pub(super) fn unknown_column(
+    error: &DataFusionError,
+    db_schema: &DatabaseSchema,
+) -> Option<(String, Vec<String>)> {
+    let DataFusionError::SchemaError(
+        SchemaError::FieldNotFound {
+            field,
+            valid_fields,
+        },
+        _,
+    ) = error.find_root()
+    else {
+        return None;
+    };
+
+    // gather candidates from the table definitions for any tables the query referenced, and fall
+    // back to the fields reported as valid by the planner, e.g., for aliased tables:
+    let mut candidates = valid_fields
+        .iter()
+        .filter_map(|c| c.relation.as_ref())
+        .filter_map(|t| db_schema.table_definition(t.table()))
+        .flat_map(|table_def| {
+            table_def
+                .column_map
+                .right_values()
+                .map(|name| name.to_string())
+                .collect::<Vec<_>>()
+        })
+        .collect::<Vec<_>>();
+    if candidates.is_empty() {
+        candidates = valid_fields.iter().map(|c| c.name.clone()).collect();
+    }
+    candidates.sort_unstable();
+    candidates.dedup();
+
+    Some((
+        field.name.clone(),
+        closest_matches(&field.name, &candidates),
+    ))
+}
+
+/// Find the `candidates` that are within an edit distance of `name` that is proportional to the
+/// length of `name`, ordered closest first.
+pub(super) fn closest_matches(name: &str, candidates: &[String]) -> Vec<String> {
+    let max_distance = (name.chars().count() / 3).max(1);
+    let mut matches = candidates
+        .iter()
+        .map(|c| (edit_distance(name, c), c))
+        .filter(|(d, _)| *d <= max_distance)
+        .collect::<Vec<_>>();
+    matches.sort();
+    matches
+        .into_iter()
+        .take(MAX_SUGGESTIONS)
+        .map(|(_, c)| c.to_owned())
+        .collect()
+}
+
+/// The Levenshtein distance between `a` and `b`, compared case-insensitively
+fn edit_distance(a: &str, b: &str) -> usize {
+    let a = a.to_lowercase().chars().collect::<Vec<_>>();
+    let b = b.to_lowercase().chars().collect::<Vec<_>>();
+    let mut prev = (0..=b.len()).collect::<Vec<_>>();
+    let mut curr = vec![0; b.len() + 1];
+    for (i, ca) in a.iter().enumerate() {
+        curr[0] = i + 1;
+        for (j, cb) in b.iter().enumerate() {
+            let substitution = prev[j] + usize::from(ca != cb);
+            curr[j + 1] = substitution.min(prev[j + 1] + 1).min(curr[j] + 1);
+        }
+        std::mem::swap(&mut prev, &mut curr);
+    }
+    prev[b.len()]
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{closest_matches, edit_distance};
+
+    #[test]
+    fn edit_distances() {
+        assert_eq!(0, edit_distance("usage", "usage"));
+        assert_eq!(1, edit_distance("usge", "usage"));
+        assert_eq!(1, edit_distance("Usage", "usages"));
+        assert_eq!(3, edit_distance("kitten", "sitting"));
+    }
+
+    #[test]
+    fn closest_matches_are_ordered() {
+        let candidates = ["host", "region", "time", "usage", "usages"]
+            .map(String::from)
+            .to_vec();
+        assert_eq!(vec!["usage"], closest_matches("usge", &candidates));
+        assert_eq!(
+            vec!["usage", "usages"],
+            closest_matches("usaage", &candidates)
+        );
+        assert!(closest_matches("temperature", &candidates).is_empty());
+    }
+}
@@ -0,0 +1,208 @@
+//! Tickets for re-fetching the results of a query, see [`QueryExecutorImpl::issue_ticket`][issue]
+//!
+//! The results of a ticketed query are stored alongside those of submitted query jobs, and are
+//! retrieved through the [`QUERY_RESULT_UDTF_NAME`] table function, so that a Flight `DoGet`
+//! carrying the query from [`ResultTicket::sql`] streams them back without running the original
+//! query again.
+//!
+//! Each ticket carries a random token, which is stored with the results, so the results can only
+//! be fetched by whoever holds the ticket.
+//!
+//! [issue]: super::QueryExecutorImpl::issue_ticket
+use std::{any::Any, fmt::Display, str::FromStr, sync::Arc};
+
+use arrow::datatypes::SchemaRef;
+use async_trait::async_trait;
+use datafusion::{
+    catalog::{Session, TableProvider},
+    common::{plan_err, Result},
+    datasource::{function::TableFunctionImpl, TableType},
+    execution::{SendableRecordBatchStream, TaskContext},
+    physical_plan::{
+        streaming::{PartitionStream, StreamingTableExec},
+        ExecutionPlan,
+    },
+    prelude::Expr,
+    scalar::ScalarValue,
+};
+use iox_time::Time;
+use rand::{rngs::OsRng, RngCore};
+
+use super::jobs::{QueryJobId, QueryJobs};
+
+/// The name used to call the results of a ticketed query in SQL queries
+pub const QUERY_RESULT_UDTF_NAME: &str = "query_result";
+
+/// The secret part of a [`ResultTicket`], which must be presented along with the id of the
+/// results in order to redeem it
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub(super) struct TicketToken(u128);
+
+impl TicketToken {
+    pub(super) fn random() -> Self {
+        let mut bytes = [0u8; 16];
+        OsRng.fill_bytes(&mut bytes);
+        Self(u128::from_be_bytes(bytes))
+    }
+}
+
+impl Display for TicketToken {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{:032x}", self.0)
+    }
+}
+
+impl FromStr for TicketToken {
+    type Err = ();
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        if s.len() != 32 {
+            return Err(());
+        }
+        u128::from_str_radix(s, 16).map(Self).map_err(|_| ())
+    }
+}
+
+/// A ticket for the stored results of a query, which can be redeemed until it expires
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct ResultTicket {
+    id: QueryJobId,
+    token: TicketToken,
+    expires_at: Time,
+}
+
+impl ResultTicket {
+    pub(super) fn new(id: QueryJobId, token: TicketToken, expires_at: Time) -> Self {
+        Self {
+            id,
+            token,
+            expires_at,
+        }
+    }
+
+    pub fn id(&self) -> QueryJobId {
+        self.id
+    }
+
+    /// The time after which the results can no longer be fetched with this ticket
+    pub fn expires_at(&self) -> Time {
+        self.expires_at
+    }
+
+    /// The SQL query that fetches the results, to be sent in a Flight ticket against the
+    /// database that the original query was run on
+    pub fn sql(&self) -> String {
+        format!("SELECT * FROM {QUERY_RESULT_UDTF_NAME}('{self}')")
+    }
+}
+
+impl Display for ResultTicket {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}.{}", self.id, self.token)
+    }
+}
+
+/// Table function that produces the stored results of a [`ResultTicket`] issued against the
+/// database
+#[derive(Debug)]
+pub(super) struct QueryResultFunction {
+    database: Arc<str>,
+    query_jobs: Arc<QueryJobs>,
+}
+
+impl QueryResultFunction {
+    pub(super) fn new(database: Arc<str>, query_jobs: Arc<QueryJobs>) -> Self {
+        Self {
+            database,
+            query_jobs,
+        }
+    }
+}
+
+impl TableFunctionImpl for QueryResultFunction {
+    fn call(&self, args: &[Expr]) -> Result<Arc<dyn TableProvider>> {
+        let Some(Expr::Literal(ScalarValue::Utf8(Some(ticket)))) = args.first() else {
+            return plan_err!("{QUERY_RESULT_UDTF_NAME} expects a ticket as its only argument");
+        };
+        let Some((id, token)) = ticket.split_once('.').and_then(|(id, token)| {
+            Some((
+                id.parse::<QueryJobId>().ok()?,
+                token.parse::<TicketToken>().ok()?,
+            ))
+        }) else {
+            return plan_err!("invalid result ticket: '{ticket}'");
+        };
+        let Some(schema) = self
+            .query_jobs
+            .ticket_result_schema(id, token, &self.database)
+        else {
+            return plan_err!("no results found for ticket '{ticket}', it may have expired");
+        };
+        Ok(Arc::new(QueryResultProvider {
+            results: Arc::new(QueryResults {
+                id,
+                schema,
+                query_jobs: Arc::clone(&self.query_jobs),
+            }),
+        }))
+    }
+}
+
+/// Implementor of the [`TableProvider`] trait that is produced by a call to the
+/// [`QueryResultFunction`]
+#[derive(Debug)]
+struct QueryResultProvider {
+    results: Arc<QueryResults>,
+}
+
+/// The stored results of a query, which are streamed from object storage when executed
+#[derive(Debug)]
+struct QueryResults {
+    id: QueryJobId,
+    schema: SchemaRef,
+    query_jobs: Arc<QueryJobs>,
+}
+
+impl PartitionStream for QueryResults {
+    fn schema(&self) -> &SchemaRef {
+        &self.schema
+    }
+
+    fn execute(&self, _ctx: Arc<TaskContext>) -> SendableRecordBatchStream {
+        self.query_jobs
+            .read_results(self.id, Arc::clone(&self.schema))
+    }
+}
+
+#[async_trait]
+impl TableProvider for QueryResultProvider {
+    fn as_any(&self) -> &dyn Any {
+        self as &dyn Any
+    }
+
+    fn schema(&self) -> SchemaRef {
+        Arc::clone(&self.results.schema)
+    }
+
+    fn table_type(&self) -> TableType {
+        TableType::Temporary
+    }
+
+    async fn scan(
+        &self,
+        _ctx: &dyn Session,
+        projection: Option<&Vec<usize>>,
+        _filters: &[Expr],
+        limit: Option<usize>,
+    ) -> Result<Arc<dyn ExecutionPlan>> {
+        let results: Arc<dyn PartitionStream> = Arc::clone(&self.results) as _;
+        Ok(Arc::new(StreamingTableExec::try_new(
+            self.schema(),
+            vec![results],
+            projection,
+            vec![],
+            false,
+            limit,
+        )?))
+    }
+}
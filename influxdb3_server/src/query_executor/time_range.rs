@@ -0,0 +1,309 @@
+//! Rejection of queries that span too much time, see
+//! [`CreateQueryExecutorArgs::max_query_time_range`][max], that produce too many
+//! `GROUP BY time()` buckets, see [`CreateQueryExecutorArgs::max_time_buckets`][buckets], or that
+//! have no lower bound on time for a table that requires one, see
+//! [`TableDefinition::require_time_predicate`][required]
+//!
+//! The same comparisons of the `time` column are pushed down to table scans exactly, see
+//! [`is_exact_time_filter`].
+//!
+//! [max]: super::CreateQueryExecutorArgs::max_query_time_range
+//! [buckets]: super::CreateQueryExecutorArgs::max_time_buckets
+//! [required]: influxdb3_catalog::catalog::TableDefinition::require_time_predicate
+use std::time::Duration;
+
+use datafusion::{
+    common::Column,
+    logical_expr::{utils::split_conjunction, Between, BinaryExpr, Expr, Operator},
+    scalar::ScalarValue,
+};
+use influxdb3_internal_api::query_executor::QueryExecutorError;
+use iox_query::QueryChunk;
+use schema::TIME_COLUMN_NAME;
+
+/// Check that the time range selected by the conjunction of `filters` on a table scan spans no
+/// more than `max`
+///
+/// The range is resolved from comparisons of the `time` column against timestamp literals, which
+/// includes `now()` and any arithmetic on it, since those are simplified to literals before the
+/// table is scanned. A range without an upper bound ends at `now`, and one without a lower bound
+/// is unbounded, so it is always rejected.
+pub(super) fn check_time_range(
+    filters: &[Expr],
+    now: i64,
+    max: Duration,
+) -> Result<(), QueryExecutorError> {
+    let span = time_range(filters, now).map(|(lower, upper)| {
+        let nanos = upper.saturating_sub(lower).max(0);
+        Duration::from_nanos(nanos as u64)
+    });
+    match span {
+        Some(span) if span <= max => Ok(()),
+        span => Err(QueryExecutorError::TimeRangeTooLarge { span, max }),
+    }
+}
+
+/// Check that the conjunction of `filters` on a scan of the `table` has a lower bound on time,
+/// resolved in the same way as by [`check_time_range`]
+pub(super) fn check_time_predicate(
+    filters: &[Expr],
+    table: &str,
+) -> Result<(), QueryExecutorError> {
+    match time_range(filters, 0) {
+        Some(_) => Ok(()),
+        None => Err(QueryExecutorError::TimePredicateRequired {
+            table: table.to_string(),
+        }),
+    }
+}
+
+/// The limit on the number of buckets produced by the `GROUP BY time()` of an InfluxQL query
+#[derive(Debug, Clone, Copy)]
+pub(super) struct TimeBucketLimit {
+    /// The interval of the query's `GROUP BY time()`, or the smallest of them if it has several
+    pub(super) interval: Duration,
+    pub(super) max: usize,
+}
+
+/// Check that the time range selected by the conjunction of `filters` on a table scan is divided
+/// into no more than `limit.max` buckets of `limit.interval`
+///
+/// The range is resolved in the same way as by [`check_time_range`], and taken to exclude its
+/// upper bound, as it does in the common case of `time < <upper>`. Buckets are aligned to the
+/// epoch, and every bucket that the range touches is counted, since each is output. A range
+/// without a lower bound is not checked, since the number of buckets then depends on the data.
+pub(super) fn check_time_buckets(
+    filters: &[Expr],
+    now: i64,
+    limit: TimeBucketLimit,
+) -> Result<(), QueryExecutorError> {
+    let interval = i64::try_from(limit.interval.as_nanos()).unwrap_or(i64::MAX);
+    let Some((lower, upper)) = time_range(filters, now).filter(|_| interval > 0) else {
+        return Ok(());
+    };
+    let first = lower.div_euclid(interval) as i128;
+    let last = upper.saturating_sub(1).div_euclid(interval) as i128;
+    let count = usize::try_from((last - first + 1).max(0)).unwrap_or(usize::MAX);
+    if count <= limit.max {
+        Ok(())
+    } else {
+        Err(QueryExecutorError::TooManyTimeBuckets {
+            count,
+            max: limit.max,
+        })
+    }
+}
+
+/// The inclusive lower and upper bounds on the `time` column selected by the conjunction of
+/// `filters`, where a range without an upper bound ends at `now`, or `None` if there is no lower
+/// bound
+fn time_range(filters: &[Expr], now: i64) -> Option<(i64, i64)> {
+    let (lower, upper) = time_bounds(filters);
+    lower.map(|lower| (lower, upper.unwrap_or(now)))
+}
+
+/// The inclusive lower and upper bounds on the `time` column selected by the conjunction of
+/// `filters`, either of which is `None` if the filters do not bound it
+pub(super) fn time_bounds(filters: &[Expr]) -> (Option<i64>, Option<i64>) {
+    let mut lower: Option<i64> = None;
+    let mut upper: Option<i64> = None;
+    for (op, value) in filters.iter().flat_map(split_conjunction).flat_map(bounds) {
+        if matches!(op, Operator::Eq | Operator::Gt | Operator::GtEq) {
+            lower = Some(lower.map_or(value, |lower| lower.max(value)));
+        }
+        if matches!(op, Operator::Eq | Operator::Lt | Operator::LtEq) {
+            upper = Some(upper.map_or(value, |upper| upper.min(value)));
+        }
+    }
+    (lower, upper)
+}
+
+/// The min and max of the `time` column of the `chunk`, taken from its statistics
+pub(super) fn chunk_time_range(chunk: &dyn QueryChunk) -> Option<(i64, i64)> {
+    let index = chunk.schema().find_index_of(TIME_COLUMN_NAME)?;
+    let stats = chunk.stats();
+    let column = stats.column_statistics.get(index)?;
+    match (column.min_value.get_value()?, column.max_value.get_value()?) {
+        (
+            ScalarValue::TimestampNanosecond(Some(min), _),
+            ScalarValue::TimestampNanosecond(Some(max), _),
+        ) => Some((*min, *max)),
+        _ => None,
+    }
+}
+
+/// Whether the `filter` is a conjunction of comparisons of the `time` column against timestamp
+/// literals, which the scan of a table applies exactly to the rows that it outputs, so that the
+/// filter need not be applied again after the scan
+pub(super) fn is_exact_time_filter(filter: &Expr) -> bool {
+    split_conjunction(filter).into_iter().all(|expr| {
+        let bounds = bounds(expr);
+        !bounds.is_empty()
+            && bounds.iter().all(|(op, _)| {
+                matches!(
+                    op,
+                    Operator::Eq | Operator::Lt | Operator::LtEq | Operator::Gt | Operator::GtEq
+                )
+            })
+    })
+}
+
+/// The bounds placed on the `time` column by the `expr`, as comparisons against the timestamp in
+/// nanoseconds, normalized with the column on the left, e.g., `10 <= time` is `time >= 10`
+fn bounds(expr: &Expr) -> Vec<(Operator, i64)> {
+    match expr {
+        Expr::BinaryExpr(BinaryExpr { left, op, right }) => {
+            let (column, op, value) = match (left.as_ref(), right.as_ref()) {
+                (Expr::Column(column), Expr::Literal(value)) => (column, *op, value),
+                (Expr::Literal(value), Expr::Column(column)) => match op.swap() {
+                    Some(op) => (column, op, value),
+                    None => return vec![],
+                },
+                _ => return vec![],
+            };
+            match (is_time(column), nanos(value)) {
+                (true, Some(value)) => vec![(op, value)],
+                _ => vec![],
+            }
+        }
+        Expr::Between(Between {
+            expr,
+            negated: false,
+            low,
+            high,
+        }) => match (expr.as_ref(), low.as_ref(), high.as_ref()) {
+            (Expr::Column(column), Expr::Literal(low), Expr::Literal(high)) if is_time(column) => {
+                match (nanos(low), nanos(high)) {
+                    (Some(low), Some(high)) => vec![(Operator::GtEq, low), (Operator::LtEq, high)],
+                    _ => vec![],
+                }
+            }
+            _ => vec![],
+        },
+        _ => vec![],
+    }
+}
+
+fn is_time(column: &Column) -> bool {
+    column.name == TIME_COLUMN_NAME
+}
+
+/// The timestamp in nanoseconds of a literal compared with the `time` column
+fn nanos(value: &ScalarValue) -> Option<i64> {
+    match value {
+        ScalarValue::TimestampNanosecond(Some(v), _) | ScalarValue::Int64(Some(v)) => Some(*v),
+        ScalarValue::TimestampMicrosecond(Some(v), _) => v.checked_mul(1_000),
+        ScalarValue::TimestampMillisecond(Some(v), _) => v.checked_mul(1_000_000),
+        ScalarValue::TimestampSecond(Some(v), _) => v.checked_mul(1_000_000_000),
+        _ => None,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::time::Duration;
+
+    use datafusion::{
+        logical_expr::{col, lit},
+        scalar::ScalarValue,
+    };
+    use influxdb3_internal_api::query_executor::QueryExecutorError;
+
+    use super::{check_time_buckets, check_time_range, is_exact_time_filter, TimeBucketLimit};
+
+    const HOUR: i64 = 3_600_000_000_000;
+
+    fn ts(nanos: i64) -> datafusion::logical_expr::Expr {
+        lit(ScalarValue::TimestampNanosecond(Some(nanos), None))
+    }
+
+    #[test]
+    fn exact_time_filters() {
+        for (filter, exact) in [
+            (col("time").gt_eq(ts(HOUR)), true),
+            (ts(HOUR).lt(col("time")), true),
+            (col("time").eq(lit(HOUR)), true),
+            (
+                col("time")
+                    .gt_eq(ts(HOUR))
+                    .and(col("time").lt(ts(2 * HOUR))),
+                true,
+            ),
+            (col("time").between(ts(HOUR), ts(2 * HOUR)), true),
+            (col("time").not_eq(ts(HOUR)), false),
+            (col("time").gt_eq(ts(HOUR)).or(col("time").lt(ts(0))), false),
+            (
+                col("time").gt_eq(ts(HOUR)).and(col("host").eq(lit("a"))),
+                false,
+            ),
+            (col("time").gt_eq(lit("2024-01-01")), false),
+            (col("host").eq(lit("a")), false),
+        ] {
+            assert_eq!(exact, is_exact_time_filter(&filter), "{filter}");
+        }
+    }
+
+    #[test]
+    fn resolved_spans() {
+        let now = 10 * HOUR;
+        let max = Duration::from_secs(2 * 3600);
+        for (filters, expected) in [
+            (vec![col("time").gt(ts(9 * HOUR))], Some(1)),
+            (vec![ts(9 * HOUR).lt_eq(col("time"))], Some(1)),
+            (
+                vec![col("time")
+                    .gt_eq(ts(HOUR))
+                    .and(col("time").lt(ts(3 * HOUR)))],
+                Some(2),
+            ),
+            (vec![col("time").between(ts(HOUR), ts(2 * HOUR))], Some(1)),
+            // the narrowest of several bounds is taken:
+            (
+                vec![col("time").gt(ts(0)), col("time").gt(ts(8 * HOUR))],
+                Some(2),
+            ),
+            (vec![col("time").gt(ts(0))], Some(10)),
+            (vec![col("host").eq(lit("a"))], None),
+            (vec![], None),
+        ] {
+            let result = check_time_range(&filters, now, max);
+            match expected {
+                Some(hours) if hours <= 2 => result.unwrap(),
+                expected => match result {
+                    Err(QueryExecutorError::TimeRangeTooLarge { span, max: m }) => {
+                        assert_eq!(
+                            expected.map(|hours| Duration::from_secs(hours as u64 * 3600)),
+                            span
+                        );
+                        assert_eq!(max, m);
+                    }
+                    other => panic!("{filters:?}: expected the range to be rejected: {other:?}"),
+                },
+            }
+        }
+    }
+
+    #[test]
+    fn counted_buckets() {
+        const MINUTE: i64 = 60_000_000_000;
+        let interval = Duration::from_secs(60);
+        for (lower, upper, expected) in [
+            // an hour of whole minutes:
+            (0, HOUR, 60),
+            // an hour that starts and ends part way through a minute:
+            (MINUTE / 2, HOUR + MINUTE / 2, 61),
+            // before the epoch:
+            (-HOUR, 0, 60),
+        ] {
+            let filters = vec![col("time").gt_eq(ts(lower)).and(col("time").lt(ts(upper)))];
+            let limit = |max| TimeBucketLimit { interval, max };
+            check_time_buckets(&filters, 0, limit(expected)).unwrap();
+            match check_time_buckets(&filters, 0, limit(expected - 1)) {
+                Err(QueryExecutorError::TooManyTimeBuckets { count, max }) => {
+                    assert_eq!((expected, expected - 1), (count, max))
+                }
+                other => panic!("{filters:?}: expected the buckets to be rejected: {other:?}"),
+            }
+        }
+    }
+}
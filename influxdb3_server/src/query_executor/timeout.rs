@@ -0,0 +1,210 @@
+//! A limit on the time taken by a query, from when it is received until its results have all
+//! been read, see [`CreateQueryExecutorArgs::query_timeout`][timeout]
+//!
+//! The limit covers planning, starting the execution, and reading the results, so that a query
+//! is cancelled wherever it is when the limit is reached. It can be overridden by the
+//! [`QUERY_TIMEOUT_CONFIG_KEY`] option of the DataFusion config that a query is run with, which
+//! can also be set for each database.
+//!
+//! [timeout]: super::CreateQueryExecutorArgs::query_timeout
+use std::{
+    collections::HashMap,
+    future::Future,
+    pin::Pin,
+    sync::Arc,
+    task::{Context, Poll},
+    time::{Duration, Instant},
+};
+
+use arrow::{datatypes::SchemaRef, record_batch::RecordBatch};
+use datafusion::{
+    error::DataFusionError,
+    execution::{RecordBatchStream, SendableRecordBatchStream},
+};
+use futures::{ready, Stream};
+use influxdb3_internal_api::query_executor::QueryExecutorError;
+use observability_deps::tracing::warn;
+use tokio::time::Sleep;
+
+/// The DataFusion config option that overrides the query timeout, as a human-readable time,
+/// e.g., "30s"
+///
+/// This is not passed on to DataFusion, which does not know of it.
+pub const QUERY_TIMEOUT_CONFIG_KEY: &str = "influxdb3.query_timeout";
+
+/// Get the query timeout from the DataFusion `config`, if it is given and valid
+pub(super) fn config_timeout(config: &HashMap<String, String>) -> Option<Duration> {
+    let value = config.get(QUERY_TIMEOUT_CONFIG_KEY)?;
+    match humantime::parse_duration(value) {
+        Ok(timeout) => Some(timeout),
+        Err(error) => {
+            warn!(%value, %error, "ignoring invalid {QUERY_TIMEOUT_CONFIG_KEY} config option");
+            None
+        }
+    }
+}
+
+/// When a query started, and the time that it has to complete, if it is limited
+#[derive(Debug, Clone, Copy)]
+pub(super) struct Deadline {
+    started: Instant,
+    timeout: Option<Duration>,
+}
+
+impl Deadline {
+    /// Give a query that started at `started` up to `timeout` to complete, if one is given
+    pub(super) fn new(started: Instant, timeout: Option<Duration>) -> Self {
+        Self { started, timeout }
+    }
+
+    fn error(&self) -> QueryExecutorError {
+        QueryExecutorError::Timeout {
+            elapsed: self.started.elapsed(),
+        }
+    }
+
+    fn expired(&self) -> bool {
+        self.timeout
+            .is_some_and(|timeout| self.started.elapsed() >= timeout)
+    }
+
+    fn sleep(&self) -> Option<Sleep> {
+        let deadline = self.started + self.timeout?;
+        Some(tokio::time::sleep_until(deadline.into()))
+    }
+
+    /// Run the `future`, failing with [`QueryExecutorError::Timeout`] if it does not complete
+    /// before the deadline
+    ///
+    /// The `future` is dropped when the deadline is reached, which cancels it.
+    pub(super) async fn run<T>(
+        &self,
+        future: impl Future<Output = T> + Send,
+    ) -> Result<T, QueryExecutorError> {
+        if self.expired() {
+            return Err(self.error());
+        }
+        match self.sleep() {
+            Some(sleep) => tokio::select! {
+                biased;
+                output = future => Ok(output),
+                _ = sleep => Err(self.error()),
+            },
+            None => Ok(future.await),
+        }
+    }
+
+    /// Fail the `stream` with [`QueryExecutorError::Timeout`] if it is still being read at the
+    /// deadline, which drops the rest of the stream, and so cancels the execution of the query
+    pub(super) fn limit_stream(
+        self,
+        stream: SendableRecordBatchStream,
+    ) -> SendableRecordBatchStream {
+        let Some(sleep) = self.sleep() else {
+            return stream;
+        };
+        Box::pin(DeadlineStream {
+            schema: stream.schema(),
+            inner: Some(stream),
+            sleep: Box::pin(sleep),
+            deadline: self,
+        })
+    }
+}
+
+struct DeadlineStream {
+    schema: SchemaRef,
+    /// Dropped once the deadline is reached
+    inner: Option<SendableRecordBatchStream>,
+    sleep: Pin<Box<Sleep>>,
+    deadline: Deadline,
+}
+
+impl Stream for DeadlineStream {
+    type Item = Result<RecordBatch, DataFusionError>;
+
+    fn poll_next(mut self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Option<Self::Item>> {
+        let this = &mut *self;
+        let Some(inner) = this.inner.as_mut() else {
+            return Poll::Ready(None);
+        };
+        if this.deadline.expired() || this.sleep.as_mut().poll(cx).is_ready() {
+            this.inner = None;
+            return Poll::Ready(Some(Err(DataFusionError::External(Box::new(
+                this.deadline.error(),
+            )))));
+        }
+        let next = ready!(inner.as_mut().poll_next(cx));
+        if next.is_none() {
+            this.inner = None;
+        }
+        Poll::Ready(next)
+    }
+}
+
+impl RecordBatchStream for DeadlineStream {
+    fn schema(&self) -> SchemaRef {
+        Arc::clone(&self.schema)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::time::{Duration, Instant};
+
+    use arrow::{datatypes::Schema, record_batch::RecordBatch};
+    use datafusion::{error::DataFusionError, physical_plan::stream::RecordBatchStreamAdapter};
+    use futures::{StreamExt, TryStreamExt};
+    use influxdb3_internal_api::query_executor::QueryExecutorError;
+
+    use super::Deadline;
+
+    fn is_timeout(error: &QueryExecutorError) -> bool {
+        matches!(error, QueryExecutorError::Timeout { .. })
+    }
+
+    #[tokio::test]
+    async fn slow_future_times_out() {
+        let deadline = Deadline::new(Instant::now(), Some(Duration::from_millis(10)));
+        let error = deadline
+            .run(tokio::time::sleep(Duration::from_secs(60)))
+            .await
+            .unwrap_err();
+        assert!(is_timeout(&error), "unexpected error: {error}");
+
+        let deadline = Deadline::new(Instant::now(), Some(Duration::from_secs(10)));
+        assert_eq!("done", deadline.run(async { "done" }).await.unwrap());
+        let deadline = Deadline::new(Instant::now(), None);
+        assert_eq!("done", deadline.run(async { "done" }).await.unwrap());
+    }
+
+    #[tokio::test]
+    async fn slow_stream_times_out() {
+        let slow = || {
+            let stream = futures::stream::once(async {
+                tokio::time::sleep(Duration::from_secs(60)).await;
+                Ok(RecordBatch::new_empty(Schema::empty().into()))
+            });
+            Box::pin(RecordBatchStreamAdapter::new(
+                Schema::empty().into(),
+                stream,
+            )) as _
+        };
+        let deadline = Deadline::new(Instant::now(), Some(Duration::from_millis(10)));
+        let mut stream = deadline.limit_stream(slow());
+        let error = stream.next().await.unwrap().unwrap_err();
+        let DataFusionError::External(error) = error else {
+            panic!("unexpected error: {error}");
+        };
+        let error = error.downcast_ref::<QueryExecutorError>().unwrap();
+        assert!(is_timeout(error), "unexpected error: {error}");
+        // the stream ends once it has timed out:
+        assert!(stream.next().await.is_none());
+
+        let fast = futures::stream::iter([Ok(RecordBatch::new_empty(Schema::empty().into()))]);
+        let fast = Box::pin(RecordBatchStreamAdapter::new(Schema::empty().into(), fast));
+        let deadline = Deadline::new(Instant::now(), Some(Duration::from_secs(10)));
+        let batches: Vec<_> = deadline.limit_stream(fast).try_collect().await.unwrap();
+        assert_eq!(1, batches.len());
+    }
+}
@@ -0,0 +1,193 @@
+//! Expansion of wildcards with exclusions in SQL queries, e.g.:
+//!
+//! ```text
+//! SELECT * EXCEPT (internal_field) FROM cpu
+//! ```
+//!
+//! The wildcard is expanded to the columns of the table in the catalog, less those excluded,
+//! before the query is planned, so that an excluded column that is not in the table fails the
+//! query with the columns it may have meant, rather than only with the columns of the table.
+use std::{collections::HashMap, sync::Arc};
+
+use datafusion::sql::{
+    parser::{DFParser, Statement as DFStatement},
+    sqlparser::ast::{
+        ExcludeSelectItem, Expr, Ident, Query, Select, SelectItem, SetExpr, Statement, TableFactor,
+        WildcardAdditionalOptions,
+    },
+};
+use datafusion_util::config::DEFAULT_SCHEMA;
+use influxdb3_catalog::catalog::{DatabaseSchema, TableDefinition};
+use influxdb3_internal_api::query_executor::QueryExecutorError;
+
+use super::suggestions::closest_matches;
+
+/// Rewrite the SQL `query` with each of its wildcards that exclude columns from a single table
+/// expanded to the columns of the table that are not excluded, returning `None` if the query has
+/// no such wildcards
+///
+/// The tables are resolved through the `table_rewrites` of the query. Queries that do not parse
+/// are left for the planner to report, as are wildcards that cannot be resolved against the
+/// catalog, e.g., those on joins or system tables.
+pub(super) fn expand_wildcard_exclusions(
+    query: &str,
+    db_schema: &DatabaseSchema,
+    table_rewrites: &HashMap<String, String>,
+) -> Result<Option<String>, QueryExecutorError> {
+    let upper = query.to_uppercase();
+    if !upper.contains("EXCEPT") && !upper.contains("EXCLUDE") {
+        return Ok(None);
+    }
+    let Ok(mut statements) = DFParser::parse_sql(query) else {
+        return Ok(None);
+    };
+    let [DFStatement::Statement(statement)] = statements.make_contiguous() else {
+        return Ok(None);
+    };
+    let Statement::Query(query) = statement.as_mut() else {
+        return Ok(None);
+    };
+    let expander = Expander {
+        db_schema,
+        table_rewrites,
+    };
+    if !expander.expand_query(query)? {
+        return Ok(None);
+    }
+    Ok(Some(statement.to_string()))
+}
+
+struct Expander<'a> {
+    db_schema: &'a DatabaseSchema,
+    table_rewrites: &'a HashMap<String, String>,
+}
+
+impl Expander<'_> {
+    /// Expand the wildcards of the `query` and of its subqueries, returning whether any were
+    /// expanded
+    fn expand_query(&self, query: &mut Query) -> Result<bool, QueryExecutorError> {
+        let mut expanded = false;
+        if let Some(with) = &mut query.with {
+            for cte in &mut with.cte_tables {
+                expanded |= self.expand_query(&mut cte.query)?;
+            }
+        }
+        Ok(self.expand_set_expr(&mut query.body)? || expanded)
+    }
+
+    fn expand_set_expr(&self, set_expr: &mut SetExpr) -> Result<bool, QueryExecutorError> {
+        match set_expr {
+            SetExpr::Select(select) => self.expand_select(select),
+            SetExpr::Query(query) => self.expand_query(query),
+            SetExpr::SetOperation { left, right, .. } => {
+                let left = self.expand_set_expr(left)?;
+                Ok(self.expand_set_expr(right)? || left)
+            }
+            _ => Ok(false),
+        }
+    }
+
+    fn expand_select(&self, select: &mut Select) -> Result<bool, QueryExecutorError> {
+        let mut expanded = false;
+        for table in &mut select.from {
+            if let TableFactor::Derived { subquery, .. } = &mut table.relation {
+                expanded |= self.expand_query(subquery)?;
+            }
+        }
+
+        // only a wildcard over a single table of the database can be resolved against its columns:
+        let table_def = match select.from.as_slice() {
+            [table] if table.joins.is_empty() => match &table.relation {
+                TableFactor::Table { name, .. } => self.table_definition(&name.0),
+                _ => None,
+            },
+            _ => None,
+        };
+        let Some(table_def) = table_def else {
+            return Ok(expanded);
+        };
+        let columns = table_def
+            .schema
+            .as_arrow()
+            .fields()
+            .iter()
+            .map(|field| field.name().to_string())
+            .collect::<Vec<_>>();
+
+        let mut projection = Vec::with_capacity(select.projection.len());
+        for item in select.projection.drain(..) {
+            let excluded = match &item {
+                SelectItem::Wildcard(options) => excluded_columns(options),
+                _ => None,
+            };
+            let Some(excluded) = excluded else {
+                projection.push(item);
+                continue;
+            };
+            if let Some(name) = excluded.iter().find(|name| !columns.contains(name)) {
+                return Err(QueryExecutorError::UnknownColumn {
+                    name: name.clone(),
+                    suggestions: closest_matches(name, &columns),
+                });
+            }
+            projection.extend(
+                columns
+                    .iter()
+                    .filter(|column| !excluded.contains(column))
+                    .map(|column| {
+                        SelectItem::UnnamedExpr(Expr::Identifier(Ident::with_quote('"', column)))
+                    }),
+            );
+            expanded = true;
+        }
+        select.projection = projection;
+        Ok(expanded)
+    }
+
+    /// The definition of the table of the database named by the parts of a table `name`
+    fn table_definition(&self, name: &[Ident]) -> Option<Arc<TableDefinition>> {
+        let table = match name {
+            [table] => table,
+            [schema, table] if normalize(schema) == DEFAULT_SCHEMA => table,
+            _ => return None,
+        };
+        let table = normalize(table);
+        let table = self.table_rewrites.get(&table).unwrap_or(&table);
+        self.db_schema.table_definition(table.as_str())
+    }
+}
+
+/// The columns excluded by a wildcard with the given `options`, if it only excludes columns
+fn excluded_columns(options: &WildcardAdditionalOptions) -> Option<Vec<String>> {
+    let WildcardAdditionalOptions {
+        opt_ilike: None,
+        opt_exclude,
+        opt_except,
+        opt_replace: None,
+        opt_rename: None,
+        ..
+    } = options
+    else {
+        return None;
+    };
+    let mut excluded = vec![];
+    if let Some(except) = opt_except {
+        excluded.push(&except.first_element);
+        excluded.extend(&except.additional_elements);
+    }
+    match opt_exclude {
+        Some(ExcludeSelectItem::Single(ident)) => excluded.push(ident),
+        Some(ExcludeSelectItem::Multiple(idents)) => excluded.extend(idents),
+        None => (),
+    }
+    (!excluded.is_empty()).then(|| excluded.into_iter().map(normalize).collect())
+}
+
+/// The name referred to by the `ident`, where unquoted identifiers are lowercased as they are by
+/// the planner
+fn normalize(ident: &Ident) -> String {
+    match ident.quote_style {
+        Some(_) => ident.value.clone(),
+        None => ident.value.to_lowercase(),
+    }
+}
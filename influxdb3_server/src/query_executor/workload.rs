@@ -0,0 +1,99 @@
+//! Capture of the queries in the query log to a file, so that they can be replayed later on,
+//! e.g., to benchmark a change against a realistic workload
+use std::{path::Path, time::Duration};
+
+use influxdb3_internal_api::query_executor::{QueryExecutorError, QueryKind};
+use iox_query::query_log::QueryLogEntryState;
+use iox_query_params::StatementParams;
+use serde::{Deserialize, Serialize};
+
+#[derive(Debug, thiserror::Error)]
+pub enum WorkloadError {
+    #[error("unable to read or write workload file: {0}")]
+    Io(#[from] std::io::Error),
+    #[error("invalid workload query on line {line}: {source}")]
+    InvalidQuery {
+        line: usize,
+        #[source]
+        source: serde_json::Error,
+    },
+    #[error("unable to serialize workload query: {0}")]
+    Serialize(#[source] serde_json::Error),
+    #[error("unknown query type '{0}', expected one of 'sql' or 'influxql'")]
+    UnknownQueryType(String),
+}
+
+/// A query captured from the query log
+///
+/// Workload files hold one of these per line, as JSON.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct WorkloadQuery {
+    pub database: String,
+    /// Either `sql` or `influxql`
+    pub query_type: String,
+    pub query: String,
+    pub params: StatementParams,
+    /// How long the query took end-to-end when it was captured, if it had completed
+    pub duration_ns: Option<u64>,
+}
+
+impl WorkloadQuery {
+    pub(super) fn kind(&self) -> Result<QueryKind, WorkloadError> {
+        match self.query_type.as_str() {
+            "sql" => Ok(QueryKind::Sql),
+            "influxql" => Ok(QueryKind::InfluxQl),
+            other => Err(WorkloadError::UnknownQueryType(other.to_string())),
+        }
+    }
+}
+
+impl From<&QueryLogEntryState> for WorkloadQuery {
+    fn from(entry: &QueryLogEntryState) -> Self {
+        Self {
+            database: entry.namespace_name.to_string(),
+            query_type: entry.query_type.to_string(),
+            query: entry.query_text.to_string(),
+            params: entry.query_params.clone(),
+            duration_ns: entry.end2end_duration.map(|d| d.as_nanos() as u64),
+        }
+    }
+}
+
+/// The outcome of replaying a [`WorkloadQuery`]
+#[derive(Debug)]
+pub struct ReplayedQuery {
+    pub query: WorkloadQuery,
+    /// How long it took to run the query and read all of its results
+    pub latency: Duration,
+    pub result: Result<usize, QueryExecutorError>,
+}
+
+/// Write the `queries` to the file at `path`, replacing its contents
+pub(super) async fn write(
+    path: impl AsRef<Path>,
+    queries: &[WorkloadQuery],
+) -> Result<(), WorkloadError> {
+    let mut contents = String::new();
+    for query in queries {
+        contents.push_str(&serde_json::to_string(query).map_err(WorkloadError::Serialize)?);
+        contents.push('\n');
+    }
+    tokio::fs::write(path, contents).await?;
+    Ok(())
+}
+
+/// Read the queries from the file at `path`
+pub(super) async fn read(path: impl AsRef<Path>) -> Result<Vec<WorkloadQuery>, WorkloadError> {
+    tokio::fs::read_to_string(path)
+        .await?
+        .lines()
+        .enumerate()
+        .filter(|(_, line)| !line.trim().is_empty())
+        .map(|(i, line)| {
+            serde_json::from_str(line).map_err(|source| WorkloadError::InvalidQuery {
+                line: i + 1,
+                source,
+            })
+        })
+        .collect()
+}
@@ -0,0 +1,82 @@
+use std::sync::Arc;
+
+use arrow_array::{ArrayRef, BooleanArray, Int64Array, RecordBatch, StringArray, UInt32Array};
+use arrow_schema::{DataType, Field, Schema, SchemaRef};
+use async_trait::async_trait;
+use datafusion::{error::DataFusionError, logical_expr::Expr};
+use influxdb3_catalog::catalog::Catalog;
+use iox_system_tables::IoxSystemTable;
+
+/// Lists every database in the catalog, regardless of the database being queried
+#[derive(Debug)]
+pub(super) struct CatalogTable {
+    schema: SchemaRef,
+    catalog: Arc<Catalog>,
+}
+
+impl CatalogTable {
+    pub(super) fn new(catalog: Arc<Catalog>) -> Self {
+        Self {
+            schema: catalog_schema(),
+            catalog,
+        }
+    }
+}
+
+fn catalog_schema() -> SchemaRef {
+    let columns = vec![
+        Field::new("database_id", DataType::UInt32, false),
+        Field::new("database_name", DataType::Utf8, false),
+        Field::new("deleted", DataType::Boolean, false),
+        Field::new("table_count", DataType::Int64, false),
+        Field::new("retention_period_ns", DataType::Int64, true),
+    ];
+    Arc::new(Schema::new(columns))
+}
+
+#[async_trait]
+impl IoxSystemTable for CatalogTable {
+    fn schema(&self) -> SchemaRef {
+        Arc::clone(&self.schema)
+    }
+
+    async fn scan(
+        &self,
+        _filters: Option<Vec<Expr>>,
+        _limit: Option<usize>,
+    ) -> Result<RecordBatch, DataFusionError> {
+        let mut databases = self.catalog.list_db_schema();
+        databases.sort_unstable_by(|a, b| a.name.cmp(&b.name));
+
+        let columns: Vec<ArrayRef> = vec![
+            Arc::new(
+                databases
+                    .iter()
+                    .map(|db| Some(db.id.as_u32()))
+                    .collect::<UInt32Array>(),
+            ),
+            Arc::new(
+                databases
+                    .iter()
+                    .map(|db| Some(db.name.as_ref()))
+                    .collect::<StringArray>(),
+            ),
+            Arc::new(
+                databases
+                    .iter()
+                    .map(|db| Some(db.deleted))
+                    .collect::<BooleanArray>(),
+            ),
+            Arc::new(
+                databases
+                    .iter()
+                    .map(|db| Some(db.tables().filter(|t| !t.deleted).count() as i64))
+                    .collect::<Int64Array>(),
+            ),
+            // databases do not have a retention period, so their data is retained indefinitely:
+            Arc::new(Int64Array::new_null(databases.len())),
+        ];
+
+        Ok(RecordBatch::try_new(self.schema(), columns)?)
+    }
+}
@@ -0,0 +1,143 @@
+use std::sync::Arc;
+
+use arrow_array::{ArrayRef, Int64Array, RecordBatch, StringArray, UInt64Array};
+use arrow_schema::{DataType, Field, Schema, SchemaRef};
+use async_trait::async_trait;
+use datafusion::{error::DataFusionError, logical_expr::Expr};
+use influxdb3_id::DbId;
+use influxdb3_write::{ChunkSummary, WriteBuffer};
+use iox_system_tables::IoxSystemTable;
+
+use crate::system_tables::find_table_name_in_filter;
+
+/// Lists the chunks of each table in the database, both those buffered in memory and those
+/// persisted as parquet files, along with where each is stored
+#[derive(Debug)]
+pub(super) struct ChunksTable {
+    db_id: DbId,
+    schema: SchemaRef,
+    buffer: Arc<dyn WriteBuffer>,
+}
+
+impl ChunksTable {
+    pub(super) fn new(db_id: DbId, buffer: Arc<dyn WriteBuffer>) -> Self {
+        Self {
+            db_id,
+            schema: chunks_schema(),
+            buffer,
+        }
+    }
+}
+
+fn chunks_schema() -> SchemaRef {
+    let columns = vec![
+        Field::new("table_name", DataType::Utf8, false),
+        Field::new("chunk_id", DataType::UInt64, true),
+        Field::new("partition_key", DataType::Utf8, false),
+        Field::new("storage", DataType::Utf8, false),
+        Field::new("row_count", DataType::UInt64, false),
+        Field::new("min_time", DataType::Int64, false),
+        Field::new("max_time", DataType::Int64, false),
+        Field::new("memory_bytes", DataType::UInt64, true),
+    ];
+    Arc::new(Schema::new(columns))
+}
+
+#[async_trait]
+impl IoxSystemTable for ChunksTable {
+    fn schema(&self) -> SchemaRef {
+        Arc::clone(&self.schema)
+    }
+
+    async fn scan(
+        &self,
+        filters: Option<Vec<Expr>>,
+        limit: Option<usize>,
+    ) -> Result<RecordBatch, DataFusionError> {
+        let schema = self.schema();
+        let limit = limit.unwrap_or(usize::MAX);
+
+        let Some(db_schema) = self.buffer.catalog().db_schema_by_id(&self.db_id) else {
+            return Ok(RecordBatch::new_empty(schema));
+        };
+
+        // extract `table_name` from filters
+        let table_name = find_table_name_in_filter(filters);
+
+        let chunks = db_schema
+            .tables()
+            .filter(|table_def| {
+                table_name
+                    .as_ref()
+                    .is_none_or(|name| name == &table_def.table_name)
+            })
+            .flat_map(|table_def| {
+                self.buffer
+                    .chunk_summaries(self.db_id, table_def.table_id)
+                    .into_iter()
+                    .map(move |chunk| (Arc::clone(&table_def.table_name), chunk))
+            })
+            .take(limit)
+            .collect::<Vec<_>>();
+
+        from_chunk_summaries(schema, chunks)
+    }
+}
+
+fn from_chunk_summaries(
+    schema: SchemaRef,
+    chunks: Vec<(Arc<str>, ChunkSummary)>,
+) -> Result<RecordBatch, DataFusionError> {
+    let columns: Vec<ArrayRef> = vec![
+        Arc::new(
+            chunks
+                .iter()
+                .map(|(table_name, _)| Some(table_name))
+                .collect::<StringArray>(),
+        ),
+        Arc::new(
+            chunks
+                .iter()
+                .map(|(_, c)| c.file_id.map(|id| id.as_u64()))
+                .collect::<UInt64Array>(),
+        ),
+        Arc::new(
+            chunks
+                .iter()
+                .map(|(_, c)| Some(c.chunk_time.to_string()))
+                .collect::<StringArray>(),
+        ),
+        Arc::new(
+            chunks
+                .iter()
+                .map(|(_, c)| Some(c.storage.as_str()))
+                .collect::<StringArray>(),
+        ),
+        Arc::new(
+            chunks
+                .iter()
+                .map(|(_, c)| Some(c.row_count))
+                .collect::<UInt64Array>(),
+        ),
+        Arc::new(
+            chunks
+                .iter()
+                .map(|(_, c)| Some(c.min_time))
+                .collect::<Int64Array>(),
+        ),
+        Arc::new(
+            chunks
+                .iter()
+                .map(|(_, c)| Some(c.max_time))
+                .collect::<Int64Array>(),
+        ),
+        Arc::new(
+            chunks
+                .iter()
+                .map(|(_, c)| c.memory_bytes)
+                .collect::<UInt64Array>(),
+        ),
+    ];
+
+    Ok(RecordBatch::try_new(schema, columns)?)
+}
@@ -0,0 +1,76 @@
+use std::sync::Arc;
+
+use arrow_array::{ArrayRef, RecordBatch, StringArray, TimestampNanosecondArray};
+use arrow_schema::{DataType, Field, Schema, SchemaRef, TimeUnit};
+use async_trait::async_trait;
+use datafusion::{error::DataFusionError, logical_expr::Expr};
+use influxdb3_sys_events::SysEventStore;
+use iox_system_tables::IoxSystemTable;
+
+/// Lists the most recent events of every type recorded in the [`SysEventStore`], in the order
+/// that they were recorded
+#[derive(Debug)]
+pub(super) struct EventsTable {
+    schema: SchemaRef,
+    sys_events_store: Arc<SysEventStore>,
+}
+
+impl EventsTable {
+    pub(super) fn new(sys_events_store: Arc<SysEventStore>) -> Self {
+        Self {
+            schema: events_schema(),
+            sys_events_store,
+        }
+    }
+}
+
+fn events_schema() -> SchemaRef {
+    let columns = vec![
+        Field::new(
+            "event_time",
+            DataType::Timestamp(TimeUnit::Nanosecond, None),
+            false,
+        ),
+        Field::new("event_type", DataType::Utf8, false),
+        Field::new("detail", DataType::Utf8, false),
+    ];
+    Arc::new(Schema::new(columns))
+}
+
+#[async_trait]
+impl IoxSystemTable for EventsTable {
+    fn schema(&self) -> SchemaRef {
+        Arc::clone(&self.schema)
+    }
+
+    async fn scan(
+        &self,
+        _filters: Option<Vec<Expr>>,
+        _limit: Option<usize>,
+    ) -> Result<RecordBatch, DataFusionError> {
+        let events = self.sys_events_store.all_events();
+
+        let columns: Vec<ArrayRef> = vec![
+            Arc::new(
+                events
+                    .iter()
+                    .map(|e| Some(e.time))
+                    .collect::<TimestampNanosecondArray>(),
+            ),
+            Arc::new(
+                events
+                    .iter()
+                    .map(|e| Some(e.event_type))
+                    .collect::<StringArray>(),
+            ),
+            Arc::new(
+                events
+                    .iter()
+                    .map(|e| Some(e.detail.as_str()))
+                    .collect::<StringArray>(),
+            ),
+        ];
+
+        Ok(RecordBatch::try_new(self.schema(), columns)?)
+    }
+}
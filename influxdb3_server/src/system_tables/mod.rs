@@ -1,5 +1,7 @@
 use std::{any::Any, collections::HashMap, ops::Deref, sync::Arc};
 
+use catalog::CatalogTable;
+use chunks::ChunksTable;
 use datafusion::{
     catalog::SchemaProvider,
     datasource::TableProvider,
@@ -8,18 +10,27 @@ use datafusion::{
     scalar::ScalarValue,
 };
 use distinct_caches::DistinctCachesTable;
+use events::EventsTable;
 use influxdb3_catalog::catalog::DatabaseSchema;
 use influxdb3_sys_events::SysEventStore;
 use influxdb3_write::WriteBuffer;
 use iox_query::query_log::QueryLog;
 use iox_system_tables::SystemTableProvider;
+use overlapping_chunks::OverlappingChunksTable;
 use parquet_files::ParquetFilesTable;
+use table_summary::TableSummaryTable;
 use tonic::async_trait;
 
+use crate::query_executor::QueryLogStats;
+
 use self::{last_caches::LastCachesTable, queries::QueriesTable};
 
+mod catalog;
+mod chunks;
 mod distinct_caches;
+mod events;
 mod last_caches;
+mod overlapping_chunks;
 mod parquet_files;
 use crate::system_tables::python_call::{
     ProcessingEnginePluginTable, ProcessingEngineTriggerTable,
@@ -27,6 +38,7 @@ use crate::system_tables::python_call::{
 
 mod python_call;
 mod queries;
+mod table_summary;
 
 pub const SYSTEM_SCHEMA_NAME: &str = "system";
 pub const TABLE_NAME_PREDICATE: &str = "table_name";
@@ -35,6 +47,11 @@ pub(crate) const QUERIES_TABLE_NAME: &str = "queries";
 pub(crate) const LAST_CACHES_TABLE_NAME: &str = "last_caches";
 pub(crate) const DISTINCT_CACHES_TABLE_NAME: &str = "distinct_caches";
 pub(crate) const PARQUET_FILES_TABLE_NAME: &str = "parquet_files";
+pub(crate) const OVERLAPPING_CHUNKS_TABLE_NAME: &str = "overlapping_chunks";
+pub(crate) const CHUNKS_TABLE_NAME: &str = "chunks";
+pub(crate) const CATALOG_TABLE_NAME: &str = "catalog";
+pub(crate) const EVENTS_TABLE_NAME: &str = "events";
+pub(crate) const TABLE_SUMMARY_TABLE_NAME: &str = "table_summary";
 
 const PROCESSING_ENGINE_PLUGINS_TABLE_NAME: &str = "processing_engine_plugins";
 
@@ -89,12 +106,14 @@ impl AllSystemSchemaTablesProvider {
     pub(crate) fn new(
         db_schema: Arc<DatabaseSchema>,
         query_log: Arc<QueryLog>,
+        query_log_stats: Arc<QueryLogStats>,
         buffer: Arc<dyn WriteBuffer>,
-        _sys_events_store: Arc<SysEventStore>,
+        sys_events_store: Arc<SysEventStore>,
     ) -> Self {
         let mut tables = HashMap::<&'static str, Arc<dyn TableProvider>>::new();
         let queries = Arc::new(SystemTableProvider::new(Arc::new(QueriesTable::new(
             query_log,
+            query_log_stats,
         ))));
         tables.insert(QUERIES_TABLE_NAME, queries);
         let last_caches = Arc::new(SystemTableProvider::new(Arc::new(LastCachesTable::new(
@@ -106,6 +125,28 @@ impl AllSystemSchemaTablesProvider {
             DistinctCachesTable::new(Arc::clone(&db_schema), buffer.distinct_cache_provider()),
         )));
         tables.insert(DISTINCT_CACHES_TABLE_NAME, distinct_caches);
+        let overlapping_chunks = Arc::new(SystemTableProvider::new(Arc::new(
+            OverlappingChunksTable::new(db_schema.id, Arc::clone(&buffer)),
+        )));
+        tables.insert(OVERLAPPING_CHUNKS_TABLE_NAME, overlapping_chunks);
+        let chunks = Arc::new(SystemTableProvider::new(Arc::new(ChunksTable::new(
+            db_schema.id,
+            Arc::clone(&buffer),
+        ))));
+        tables.insert(CHUNKS_TABLE_NAME, chunks);
+        let catalog = Arc::new(SystemTableProvider::new(Arc::new(CatalogTable::new(
+            buffer.catalog(),
+        ))));
+        tables.insert(CATALOG_TABLE_NAME, catalog);
+        let events = Arc::new(SystemTableProvider::new(Arc::new(EventsTable::new(
+            sys_events_store,
+        ))));
+        tables.insert(EVENTS_TABLE_NAME, events);
+        let table_summary = Arc::new(SystemTableProvider::new(Arc::new(TableSummaryTable::new(
+            Arc::clone(&db_schema),
+            Arc::clone(&buffer),
+        ))));
+        tables.insert(TABLE_SUMMARY_TABLE_NAME, table_summary);
         let parquet_files = Arc::new(SystemTableProvider::new(Arc::new(ParquetFilesTable::new(
             db_schema.id,
             buffer,
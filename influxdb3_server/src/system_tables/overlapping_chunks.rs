@@ -0,0 +1,207 @@
+use std::sync::Arc;
+
+use arrow_array::{ArrayRef, Int64Array, RecordBatch, StringArray, UInt64Array};
+use arrow_schema::{DataType, Field, Schema, SchemaRef};
+use async_trait::async_trait;
+use datafusion::{error::DataFusionError, logical_expr::Expr};
+use influxdb3_id::DbId;
+use influxdb3_write::{ParquetFile, WriteBuffer};
+use iox_system_tables::IoxSystemTable;
+
+use crate::system_tables::find_table_name_in_filter;
+
+/// Lists pairs of persisted chunks in the same table whose time ranges overlap, which are
+/// candidates for compaction
+#[derive(Debug)]
+pub(super) struct OverlappingChunksTable {
+    db_id: DbId,
+    schema: SchemaRef,
+    buffer: Arc<dyn WriteBuffer>,
+}
+
+impl OverlappingChunksTable {
+    pub(super) fn new(db_id: DbId, buffer: Arc<dyn WriteBuffer>) -> Self {
+        Self {
+            db_id,
+            schema: overlapping_chunks_schema(),
+            buffer,
+        }
+    }
+}
+
+fn overlapping_chunks_schema() -> SchemaRef {
+    let columns = vec![
+        Field::new("table_name", DataType::Utf8, false),
+        Field::new("chunk_id_a", DataType::UInt64, false),
+        Field::new("path_a", DataType::Utf8, false),
+        Field::new("min_time_a", DataType::Int64, false),
+        Field::new("max_time_a", DataType::Int64, false),
+        Field::new("chunk_id_b", DataType::UInt64, false),
+        Field::new("path_b", DataType::Utf8, false),
+        Field::new("min_time_b", DataType::Int64, false),
+        Field::new("max_time_b", DataType::Int64, false),
+    ];
+    Arc::new(Schema::new(columns))
+}
+
+#[async_trait]
+impl IoxSystemTable for OverlappingChunksTable {
+    fn schema(&self) -> SchemaRef {
+        Arc::clone(&self.schema)
+    }
+
+    async fn scan(
+        &self,
+        filters: Option<Vec<Expr>>,
+        limit: Option<usize>,
+    ) -> Result<RecordBatch, DataFusionError> {
+        let schema = self.schema();
+        let limit = limit.unwrap_or(usize::MAX);
+
+        let Some(db_schema) = self.buffer.catalog().db_schema_by_id(&self.db_id) else {
+            return Ok(RecordBatch::new_empty(schema));
+        };
+
+        // extract `table_name` from filters
+        let table_name = find_table_name_in_filter(filters);
+
+        let pairs = db_schema
+            .tables()
+            .filter(|table_def| {
+                table_name
+                    .as_ref()
+                    .is_none_or(|name| name == &table_def.table_name)
+            })
+            .flat_map(|table_def| {
+                let files = self.buffer.parquet_files(self.db_id, table_def.table_id);
+                overlapping_pairs(files)
+                    .into_iter()
+                    .map(move |(a, b)| (Arc::clone(&table_def.table_name), a, b))
+            })
+            .take(limit)
+            .collect::<Vec<_>>();
+
+        from_overlapping_pairs(schema, pairs)
+    }
+}
+
+/// Find the pairs of `files` whose time ranges overlap, where the time ranges are inclusive of
+/// their min and max times
+///
+/// Each pair is ordered by min time, and the pairs are ordered by the min time of their first
+/// file.
+fn overlapping_pairs(mut files: Vec<ParquetFile>) -> Vec<(ParquetFile, ParquetFile)> {
+    files.sort_by_key(|f| (f.min_time, f.max_time));
+    let mut pairs = vec![];
+    for (i, a) in files.iter().enumerate() {
+        // files are sorted by their min time, so only the files that start before this one ends
+        // can overlap with it:
+        for b in files[i + 1..]
+            .iter()
+            .take_while(|b| b.min_time <= a.max_time)
+        {
+            pairs.push((a.clone(), b.clone()));
+        }
+    }
+    pairs
+}
+
+fn from_overlapping_pairs(
+    schema: SchemaRef,
+    pairs: Vec<(Arc<str>, ParquetFile, ParquetFile)>,
+) -> Result<RecordBatch, DataFusionError> {
+    let columns: Vec<ArrayRef> = vec![
+        Arc::new(
+            pairs
+                .iter()
+                .map(|(table_name, _, _)| Some(table_name))
+                .collect::<StringArray>(),
+        ),
+        Arc::new(
+            pairs
+                .iter()
+                .map(|(_, a, _)| Some(a.id.as_u64()))
+                .collect::<UInt64Array>(),
+        ),
+        Arc::new(
+            pairs
+                .iter()
+                .map(|(_, a, _)| Some(a.path.as_str()))
+                .collect::<StringArray>(),
+        ),
+        Arc::new(
+            pairs
+                .iter()
+                .map(|(_, a, _)| Some(a.min_time))
+                .collect::<Int64Array>(),
+        ),
+        Arc::new(
+            pairs
+                .iter()
+                .map(|(_, a, _)| Some(a.max_time))
+                .collect::<Int64Array>(),
+        ),
+        Arc::new(
+            pairs
+                .iter()
+                .map(|(_, _, b)| Some(b.id.as_u64()))
+                .collect::<UInt64Array>(),
+        ),
+        Arc::new(
+            pairs
+                .iter()
+                .map(|(_, _, b)| Some(b.path.as_str()))
+                .collect::<StringArray>(),
+        ),
+        Arc::new(
+            pairs
+                .iter()
+                .map(|(_, _, b)| Some(b.min_time))
+                .collect::<Int64Array>(),
+        ),
+        Arc::new(
+            pairs
+                .iter()
+                .map(|(_, _, b)| Some(b.max_time))
+                .collect::<Int64Array>(),
+        ),
+    ];
+
+    Ok(RecordBatch::try_new(schema, columns)?)
+}
+
+#[cfg(test)]
+mod tests {
+    use influxdb3_id::ParquetFileId;
+    use influxdb3_write::ParquetFile;
+
+    use super::overlapping_pairs;
+
+    fn file(path: &str, min_time: i64, max_time: i64) -> ParquetFile {
+        ParquetFile {
+            id: ParquetFileId::new(),
+            path: path.to_string(),
+            size_bytes: 0,
+            row_count: 0,
+            chunk_time: min_time,
+            min_time,
+            max_time,
+            column_codecs: Default::default(),
+        }
+    }
+
+    #[test]
+    fn overlapping_chunks_are_paired() {
+        let files = vec![
+            file("c", 60, 80),
+            file("b", 15, 40),
+            file("a", 0, 20),
+            file("d", 81, 90),
+        ];
+        let pairs = overlapping_pairs(files)
+            .into_iter()
+            .map(|(a, b)| (a.path, b.path))
+            .collect::<Vec<_>>();
+        assert_eq!(vec![("a".to_string(), "b".to_string())], pairs);
+    }
+}
@@ -3,7 +3,12 @@ use std::sync::Arc;
 use arrow_array::{ArrayRef, Int64Array, RecordBatch, StringArray, UInt64Array};
 use arrow_schema::{DataType, Field, Schema, SchemaRef};
 use async_trait::async_trait;
-use datafusion::{error::DataFusionError, logical_expr::Expr};
+use datafusion::{
+    common::Column,
+    error::DataFusionError,
+    logical_expr::{utils::split_conjunction, BinaryExpr, Expr, Operator},
+    scalar::ScalarValue,
+};
 use influxdb3_id::DbId;
 use influxdb3_write::{ParquetFile, WriteBuffer};
 use iox_system_tables::IoxSystemTable;
@@ -35,6 +40,8 @@ fn parquet_files_schema() -> SchemaRef {
         Field::new("row_count", DataType::UInt64, false),
         Field::new("min_time", DataType::Int64, false),
         Field::new("max_time", DataType::Int64, false),
+        // files persisted before codecs were recorded have none:
+        Field::new("column_codecs", DataType::Utf8, true),
     ];
     Arc::new(Schema::new(columns))
 }
@@ -53,42 +60,133 @@ impl IoxSystemTable for ParquetFilesTable {
         let schema = self.schema();
         let limit = limit.unwrap_or(usize::MAX);
 
+        // extract predicates on `min_time` and `max_time` from filters, to only materialize the
+        // files whose time range matches them:
+        let time_predicates = filters
+            .as_deref()
+            .map(find_time_predicates)
+            .unwrap_or_default();
+
         // extract `table_name` from filters
         let table_name = find_table_name_in_filter(filters);
 
-        let parquet_files = if let Some(table_name) = table_name {
-            let table_id = self
-                .buffer
-                .catalog()
-                .db_schema_by_id(&self.db_id)
-                .expect("db exists")
+        let db_schema = self
+            .buffer
+            .catalog()
+            .db_schema_by_id(&self.db_id)
+            .expect("db exists");
+        let tables = if let Some(table_name) = table_name {
+            let table_id = db_schema
                 .table_name_to_id(Arc::clone(&table_name))
                 .expect("table exists");
-            self.buffer
-                .parquet_files(self.db_id, table_id)
-                .into_iter()
-                .map(|file| (Arc::clone(&table_name), file))
-                .collect()
+            vec![(table_name, table_id)]
         } else {
-            self.buffer
-                .catalog()
-                .list_db_schema()
-                .iter()
-                .flat_map(|db| db.tables())
-                .flat_map(|table_def| {
-                    self.buffer
-                        .parquet_files(self.db_id, table_def.table_id)
-                        .into_iter()
-                        .map(move |file| (Arc::clone(&table_def.table_name), file))
-                })
-                .take(limit)
+            db_schema
+                .tables()
+                .map(|table_def| (Arc::clone(&table_def.table_name), table_def.table_id))
                 .collect()
         };
 
+        // the files of each table are only listed, and only as many as are needed, until the
+        // limit is reached:
+        let mut parquet_files = vec![];
+        for (table_name, table_id) in tables {
+            let remaining = limit - parquet_files.len();
+            if remaining == 0 {
+                break;
+            }
+            let files = self.buffer.parquet_files_filtered(
+                self.db_id,
+                table_id,
+                &|file| time_predicates.iter().all(|p| p.matches(file)),
+                remaining,
+            );
+            parquet_files.extend(
+                files
+                    .into_iter()
+                    .map(|file| (Arc::clone(&table_name), file)),
+            );
+        }
+
         from_parquet_files(schema, parquet_files)
     }
 }
 
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum TimeColumn {
+    MinTime,
+    MaxTime,
+}
+
+/// A comparison of the `min_time` or `max_time` column against a literal, which can be checked
+/// against each file before it is materialized as a row
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+struct TimePredicate {
+    column: TimeColumn,
+    op: Operator,
+    value: i64,
+}
+
+impl TimePredicate {
+    fn matches(&self, file: &ParquetFile) -> bool {
+        let time = match self.column {
+            TimeColumn::MinTime => file.min_time,
+            TimeColumn::MaxTime => file.max_time,
+        };
+        match self.op {
+            Operator::Eq => time == self.value,
+            Operator::Lt => time < self.value,
+            Operator::LtEq => time <= self.value,
+            Operator::Gt => time > self.value,
+            Operator::GtEq => time >= self.value,
+            _ => true,
+        }
+    }
+}
+
+/// Find the comparisons of `min_time` or `max_time` against an integer literal in the
+/// conjunction of `filters`, e.g., for the files that overlap a time range:
+///
+/// ```text
+/// max_time >= 10 AND min_time < 20
+/// ```
+fn find_time_predicates(filters: &[Expr]) -> Vec<TimePredicate> {
+    filters
+        .iter()
+        .flat_map(split_conjunction)
+        .filter_map(|expr| {
+            let Expr::BinaryExpr(BinaryExpr { left, op, right }) = expr else {
+                return None;
+            };
+            // normalize comparisons with the literal on the left, e.g., `10 <= max_time`:
+            let (column, op, value) = match (left.as_ref(), right.as_ref()) {
+                (Expr::Column(column), Expr::Literal(value)) => (column, *op, value),
+                (Expr::Literal(value), Expr::Column(column)) => (column, op.swap()?, value),
+                _ => return None,
+            };
+            let column = match column {
+                Column { name, .. } if name == "min_time" => TimeColumn::MinTime,
+                Column { name, .. } if name == "max_time" => TimeColumn::MaxTime,
+                _ => return None,
+            };
+            if !matches!(
+                op,
+                Operator::Eq | Operator::Lt | Operator::LtEq | Operator::Gt | Operator::GtEq
+            ) {
+                return None;
+            }
+            let ScalarValue::Int64(Some(value)) = value else {
+                return None;
+            };
+            Some(TimePredicate {
+                column,
+                op,
+                value: *value,
+            })
+        })
+        .collect()
+}
+
 /// Produce a record batch listing parquet file information based on the given `schema` and
 /// `parquet_files`, a list of table name and parquet file pairs.
 fn from_parquet_files(
@@ -132,7 +230,75 @@ fn from_parquet_files(
                 .map(|(_, f)| Some(f.max_time))
                 .collect::<Int64Array>(),
         ),
+        Arc::new(
+            parquet_files
+                .iter()
+                .map(|(_, f)| {
+                    (!f.column_codecs.is_empty()).then(|| {
+                        f.column_codecs
+                            .iter()
+                            .map(|(column, codec)| format!("{column}={codec}"))
+                            .collect::<Vec<_>>()
+                            .join(", ")
+                    })
+                })
+                .collect::<StringArray>(),
+        ),
     ];
 
     Ok(RecordBatch::try_new(schema, columns)?)
 }
+
+#[cfg(test)]
+mod tests {
+    use datafusion::logical_expr::{col, lit};
+    use influxdb3_id::ParquetFileId;
+    use influxdb3_write::ParquetFile;
+
+    use super::find_time_predicates;
+
+    fn file(path: &str, min_time: i64, max_time: i64) -> ParquetFile {
+        ParquetFile {
+            id: ParquetFileId::new(),
+            path: path.to_string(),
+            size_bytes: 0,
+            row_count: 0,
+            chunk_time: min_time,
+            min_time,
+            max_time,
+            column_codecs: Default::default(),
+        }
+    }
+
+    #[test]
+    fn time_range_filter_pushed_down() {
+        let files = vec![
+            file("before", 0, 9),
+            file("overlaps_start", 5, 15),
+            file("within", 12, 18),
+            file("overlaps_end", 15, 25),
+            file("after", 20, 30),
+        ];
+        // the files that overlap the time range [10, 20):
+        let filters = vec![
+            col("max_time").gt_eq(lit(10_i64)),
+            lit(20_i64).gt(col("min_time")),
+            col("table_name").eq(lit("cpu")),
+        ];
+        let predicates = find_time_predicates(&filters);
+        assert_eq!(2, predicates.len());
+
+        let matching = files
+            .into_iter()
+            .filter(|file| predicates.iter().all(|p| p.matches(file)))
+            .map(|file| file.path)
+            .collect::<Vec<_>>();
+        assert_eq!(vec!["overlaps_start", "within", "overlaps_end"], matching);
+
+        // the predicates are also found within a conjunction:
+        let filters = vec![col("max_time")
+            .gt_eq(lit(10_i64))
+            .and(col("min_time").lt(lit(20_i64)))];
+        assert_eq!(predicates, find_time_predicates(&filters));
+    }
+}
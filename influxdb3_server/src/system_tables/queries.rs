@@ -1,25 +1,34 @@
 use std::sync::Arc;
 
 use arrow_array::{
-    ArrayRef, BooleanArray, DurationNanosecondArray, Int64Array, RecordBatch, StringArray,
-    TimestampNanosecondArray,
+    ArrayRef, BooleanArray, DurationNanosecondArray, Float64Array, Int64Array, RecordBatch,
+    StringArray, TimestampNanosecondArray,
 };
 use arrow_schema::{DataType, Field, Schema, SchemaRef, TimeUnit};
-use datafusion::{error::DataFusionError, logical_expr::Expr};
+use datafusion::{
+    common::Column,
+    error::DataFusionError,
+    logical_expr::{expr::InList, utils::split_conjunction, BinaryExpr, Expr, Operator},
+    scalar::ScalarValue,
+};
 use iox_query::query_log::{QueryLog, QueryLogEntryState, QueryPhase};
 use iox_system_tables::IoxSystemTable;
 
+use crate::query_executor::{QueryLogStats, QueryStats};
+
 #[derive(Debug)]
 pub(super) struct QueriesTable {
     schema: SchemaRef,
     query_log: Arc<QueryLog>,
+    query_log_stats: Arc<QueryLogStats>,
 }
 
 impl QueriesTable {
-    pub(super) fn new(query_log: Arc<QueryLog>) -> Self {
+    pub(super) fn new(query_log: Arc<QueryLog>, query_log_stats: Arc<QueryLogStats>) -> Self {
         Self {
             schema: queries_schema(),
             query_log,
+            query_log_stats,
         }
     }
 }
@@ -32,23 +41,85 @@ impl IoxSystemTable for QueriesTable {
 
     async fn scan(
         &self,
-        _filters: Option<Vec<Expr>>,
+        filters: Option<Vec<Expr>>,
         _limit: Option<usize>,
     ) -> Result<RecordBatch, DataFusionError> {
         let schema = self.schema();
 
+        // extract the query types from filters, to only materialize the entries of those types:
+        let query_types = filters.as_deref().map(find_query_types).unwrap_or_default();
+
         let entries = self
             .query_log
             .entries()
             .entries
             .into_iter()
             .map(|e| e.state())
+            .filter(|e| {
+                query_types
+                    .iter()
+                    .all(|types| types.iter().any(|t| *t == *e.query_type))
+            })
+            .collect::<Vec<_>>();
+        let stats = entries
+            .iter()
+            .map(|e| {
+                self.query_log_stats
+                    .get(&e.id.to_string())
+                    .unwrap_or_default()
+            })
             .collect::<Vec<_>>();
 
-        from_query_log_entries(Arc::clone(&schema), &entries)
+        from_query_log_entries(Arc::clone(&schema), &entries, &stats)
     }
 }
 
+/// Find the query types that each of the conjunction of `filters` allows, from comparisons of
+/// the `query_type` column with string literals, e.g.:
+///
+/// ```text
+/// query_type = 'sql' AND query_type IN ('sql', 'influxql')
+/// ```
+///
+/// An entry matches the filters only if its query type is allowed by each of the sets returned.
+fn find_query_types(filters: &[Expr]) -> Vec<Vec<String>> {
+    let is_query_type =
+        |expr: &Expr| matches!(expr, Expr::Column(Column { name, .. }) if name == "query_type");
+    let as_string = |expr: &Expr| match expr {
+        Expr::Literal(
+            ScalarValue::Utf8(Some(s))
+            | ScalarValue::LargeUtf8(Some(s))
+            | ScalarValue::Utf8View(Some(s)),
+        ) => Some(s.clone()),
+        _ => None,
+    };
+    filters
+        .iter()
+        .flat_map(split_conjunction)
+        .filter_map(|expr| match expr {
+            Expr::BinaryExpr(BinaryExpr {
+                left,
+                op: Operator::Eq,
+                right,
+            }) => {
+                if is_query_type(left) {
+                    as_string(right).map(|s| vec![s])
+                } else if is_query_type(right) {
+                    as_string(left).map(|s| vec![s])
+                } else {
+                    None
+                }
+            }
+            Expr::InList(InList {
+                expr,
+                list,
+                negated: false,
+            }) if is_query_type(expr) => list.iter().map(as_string).collect(),
+            _ => None,
+        })
+        .collect()
+}
+
 fn queries_schema() -> SchemaRef {
     let columns = vec![
         Field::new("id", DataType::Utf8, false),
@@ -92,6 +163,13 @@ fn queries_schema() -> SchemaRef {
         Field::new("running", DataType::Boolean, false),
         Field::new("cancelled", DataType::Boolean, false),
         Field::new("trace_id", DataType::Utf8, true),
+        Field::new("output_partitions", DataType::Int64, true),
+        Field::new("partition_min_rows", DataType::Int64, true),
+        Field::new("partition_max_rows", DataType::Int64, true),
+        Field::new("partition_avg_rows", DataType::Float64, true),
+        Field::new("dictionary_stats", DataType::Utf8, true),
+        Field::new("tags", DataType::Utf8, true),
+        Field::new("result_source", DataType::Utf8, true),
     ];
 
     Arc::new(Schema::new(columns))
@@ -100,6 +178,7 @@ fn queries_schema() -> SchemaRef {
 fn from_query_log_entries(
     schema: SchemaRef,
     entries: &[Arc<QueryLogEntryState>],
+    stats: &[QueryStats],
 ) -> Result<RecordBatch, DataFusionError> {
     let mut columns: Vec<ArrayRef> = vec![];
 
@@ -220,6 +299,110 @@ fn from_query_log_entries(
             .collect::<StringArray>(),
     ));
 
+    columns.push(Arc::new(
+        stats
+            .iter()
+            .map(|s| s.partition_rows.map(|p| p.partitions as i64))
+            .collect::<Int64Array>(),
+    ));
+
+    columns.push(Arc::new(
+        stats
+            .iter()
+            .map(|s| s.partition_rows.map(|p| p.min_rows as i64))
+            .collect::<Int64Array>(),
+    ));
+
+    columns.push(Arc::new(
+        stats
+            .iter()
+            .map(|s| s.partition_rows.map(|p| p.max_rows as i64))
+            .collect::<Int64Array>(),
+    ));
+
+    columns.push(Arc::new(
+        stats
+            .iter()
+            .map(|s| s.partition_rows.map(|p| p.avg_rows))
+            .collect::<Float64Array>(),
+    ));
+
+    columns.push(Arc::new(
+        stats
+            .iter()
+            .map(|s| {
+                s.dictionary_stats.as_ref().map(|columns| {
+                    columns
+                        .iter()
+                        .map(ToString::to_string)
+                        .collect::<Vec<_>>()
+                        .join(", ")
+                })
+            })
+            .collect::<StringArray>(),
+    ));
+
+    columns.push(Arc::new(
+        stats
+            .iter()
+            .map(|s| {
+                (!s.tags.is_empty()).then(|| {
+                    s.tags
+                        .iter()
+                        .map(|(key, value)| format!("{key}={value}"))
+                        .collect::<Vec<_>>()
+                        .join(", ")
+                })
+            })
+            .collect::<StringArray>(),
+    ));
+
+    columns.push(Arc::new(
+        stats
+            .iter()
+            .map(|s| s.result_source.map(|source| source.to_string()))
+            .collect::<StringArray>(),
+    ));
+
     let batch = RecordBatch::try_new(schema, columns)?;
     Ok(batch)
 }
+
+#[cfg(test)]
+mod tests {
+    use datafusion::logical_expr::{col, lit};
+
+    use super::find_query_types;
+
+    #[test]
+    fn query_type_filter_pushed_down() {
+        let filters = vec![
+            col("query_type").eq(lit("sql")),
+            col("success").eq(lit(true)),
+        ];
+        assert_eq!(vec![vec!["sql".to_string()]], find_query_types(&filters));
+
+        // with the literal on either side, in a list, and within a conjunction:
+        let filters = vec![lit("influxql")
+            .eq(col("query_type"))
+            .and(col("query_type").in_list(vec![lit("sql"), lit("influxql")], false))];
+        assert_eq!(
+            vec![
+                vec!["influxql".to_string()],
+                vec!["sql".to_string(), "influxql".to_string()]
+            ],
+            find_query_types(&filters)
+        );
+
+        // filters that cannot be checked against the query type alone are left to the scan:
+        let filters = vec![
+            col("query_type").not_eq(lit("sql")),
+            col("query_type").in_list(vec![lit("sql")], true),
+            col("query_type")
+                .eq(lit("sql"))
+                .or(col("query_type").eq(lit("influxql"))),
+            col("query_type").in_list(vec![lit("sql"), col("query_text")], false),
+        ];
+        assert!(find_query_types(&filters).is_empty());
+    }
+}
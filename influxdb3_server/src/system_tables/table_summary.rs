@@ -0,0 +1,107 @@
+use std::sync::Arc;
+
+use arrow_array::{ArrayRef, Int64Array, RecordBatch, StringArray, UInt64Array};
+use arrow_schema::{DataType, Field, Schema, SchemaRef};
+use async_trait::async_trait;
+use datafusion::{error::DataFusionError, logical_expr::Expr};
+use influxdb3_catalog::catalog::DatabaseSchema;
+use influxdb3_write::WriteBuffer;
+use iox_system_tables::IoxSystemTable;
+
+/// Lists the totals of the parquet files persisted for each table in the database, taken from
+/// the summaries kept by the write buffer rather than from the individual files, which are listed
+/// in `system.parquet_files`
+#[derive(Debug)]
+pub(super) struct TableSummaryTable {
+    db_schema: Arc<DatabaseSchema>,
+    schema: SchemaRef,
+    buffer: Arc<dyn WriteBuffer>,
+}
+
+impl TableSummaryTable {
+    pub(super) fn new(db_schema: Arc<DatabaseSchema>, buffer: Arc<dyn WriteBuffer>) -> Self {
+        Self {
+            db_schema,
+            schema: table_summary_schema(),
+            buffer,
+        }
+    }
+}
+
+fn table_summary_schema() -> SchemaRef {
+    let columns = vec![
+        Field::new("table_name", DataType::Utf8, false),
+        Field::new("file_count", DataType::UInt64, false),
+        Field::new("size_bytes", DataType::UInt64, false),
+        Field::new("row_count", DataType::UInt64, false),
+        Field::new("min_time", DataType::Int64, false),
+        Field::new("max_time", DataType::Int64, false),
+    ];
+    Arc::new(Schema::new(columns))
+}
+
+#[async_trait]
+impl IoxSystemTable for TableSummaryTable {
+    fn schema(&self) -> SchemaRef {
+        Arc::clone(&self.schema)
+    }
+
+    async fn scan(
+        &self,
+        _filters: Option<Vec<Expr>>,
+        _limit: Option<usize>,
+    ) -> Result<RecordBatch, DataFusionError> {
+        let mut summaries = self
+            .buffer
+            .table_summaries(self.db_schema.id)
+            .into_iter()
+            .filter_map(|(table_id, summary)| {
+                self.db_schema
+                    .table_id_to_name(&table_id)
+                    .map(|name| (name, summary))
+            })
+            .collect::<Vec<_>>();
+        summaries.sort_unstable_by(|a, b| a.0.cmp(&b.0));
+
+        let columns: Vec<ArrayRef> = vec![
+            Arc::new(
+                summaries
+                    .iter()
+                    .map(|(name, _)| Some(name.as_ref()))
+                    .collect::<StringArray>(),
+            ),
+            Arc::new(
+                summaries
+                    .iter()
+                    .map(|(_, s)| Some(s.file_count))
+                    .collect::<UInt64Array>(),
+            ),
+            Arc::new(
+                summaries
+                    .iter()
+                    .map(|(_, s)| Some(s.size_bytes))
+                    .collect::<UInt64Array>(),
+            ),
+            Arc::new(
+                summaries
+                    .iter()
+                    .map(|(_, s)| Some(s.row_count))
+                    .collect::<UInt64Array>(),
+            ),
+            Arc::new(
+                summaries
+                    .iter()
+                    .map(|(_, s)| Some(s.min_time))
+                    .collect::<Int64Array>(),
+            ),
+            Arc::new(
+                summaries
+                    .iter()
+                    .map(|(_, s)| Some(s.max_time))
+                    .collect::<Int64Array>(),
+            ),
+        ];
+
+        Ok(RecordBatch::try_new(self.schema(), columns)?)
+    }
+}
@@ -33,10 +33,12 @@ pub trait ToRecordBatch<E> {
 /// avoiding clones.
 ///
 /// Every time a new event is introduced, the system table had to be setup
-/// following the same pattern as in `influxdb3_server::system_tables`
+/// following the same pattern as in `influxdb3_server::system_tables`, though
+/// events of every type are also listed in the `system.events` table through
+/// [`SysEventStore::all_events`]
 #[derive(Debug)]
 pub struct SysEventStore {
-    events: dashmap::DashMap<TypeId, Box<dyn Any + Send + Sync>>,
+    events: dashmap::DashMap<TypeId, Box<dyn AnyEventBuffer>>,
     time_provider: Arc<dyn TimeProvider>,
 }
 
@@ -64,7 +66,8 @@ impl SysEventStore {
 
         // unwrap here is fine, we just used the same type above for
         // get or insert
-        buf.downcast_mut::<RingBuffer<Event<E>>>()
+        buf.as_any_mut()
+            .downcast_mut::<RingBuffer<Event<E>>>()
             .unwrap()
             .push(wrapped);
     }
@@ -80,7 +83,8 @@ impl SysEventStore {
             .map(|buf| {
                 // unwrap here is fine, we just used the same type above to
                 // get
-                buf.downcast_ref::<RingBuffer<Event<E>>>()
+                buf.as_any()
+                    .downcast_ref::<RingBuffer<Event<E>>>()
                     .unwrap()
                     .in_order()
                     .cloned()
@@ -100,9 +104,83 @@ impl SysEventStore {
         let buf_ref = map_ref
             .as_ref()
             // unwrap here is fine, we just used the same type above to get
-            .map(|buf| buf.downcast_ref::<RingBuffer<Event<E>>>().unwrap());
+            .map(|buf| buf.as_any().downcast_ref::<RingBuffer<Event<E>>>().unwrap());
         E::to_record_batch(buf_ref)
     }
+
+    /// Summarizes the events of every type recorded in this store, ordered
+    /// by the time they were recorded. Unlike [`Self::as_record_batch`], this
+    /// does not need to know the event types, so the details of each event
+    /// are given by its `Debug` output.
+    pub fn all_events(&self) -> Vec<EventSummary> {
+        let mut events = self
+            .events
+            .iter()
+            .flat_map(|buf| buf.summaries())
+            .collect::<Vec<_>>();
+        events.sort_by_key(|event| event.time);
+        events
+    }
+}
+
+/// An event of any type, as returned by [`SysEventStore::all_events`]
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct EventSummary {
+    /// time the event was recorded, in nanoseconds since the epoch
+    pub time: i64,
+    /// name of the event's type, without its module path
+    pub event_type: &'static str,
+    /// `Debug` output of the event
+    pub detail: String,
+}
+
+/// Ring buffer of events of some type, which can be summarized without
+/// knowing that type
+trait AnyEventBuffer: Send + Sync {
+    fn as_any(&self) -> &dyn Any;
+
+    fn as_any_mut(&mut self) -> &mut dyn Any;
+
+    fn summaries(&self) -> Vec<EventSummary>;
+}
+
+impl Debug for dyn AnyEventBuffer {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("AnyEventBuffer").finish_non_exhaustive()
+    }
+}
+
+impl<E> AnyEventBuffer for RingBuffer<Event<E>>
+where
+    E: 'static + Debug + Sync + Send,
+{
+    fn as_any(&self) -> &dyn Any {
+        self
+    }
+
+    fn as_any_mut(&mut self) -> &mut dyn Any {
+        self
+    }
+
+    fn summaries(&self) -> Vec<EventSummary> {
+        let event_type = short_type_name::<E>();
+        self.in_order()
+            .map(|event| EventSummary {
+                time: event.time,
+                event_type,
+                detail: format!("{:?}", event.data),
+            })
+            .collect()
+    }
+}
+
+/// The name of type `E` without the module path, e.g., `SampleEvent1`
+/// rather than `influxdb3_sys_events::tests::SampleEvent1`
+fn short_type_name<E>() -> &'static str {
+    let name = std::any::type_name::<E>();
+    let end = name.find('<').unwrap_or(name.len());
+    let start = name[..end].rfind("::").map_or(0, |i| i + 2);
+    &name[start..]
 }
 
 // we've increased the max capacity to 10k by default, it makes
@@ -152,7 +230,6 @@ impl<T> RingBufferVec<T> {
 }
 
 /// This is wrapper type adds the time of event
-#[allow(dead_code)]
 #[derive(Default, Clone, Debug)]
 pub struct Event<D> {
     time: i64,
@@ -375,4 +452,31 @@ mod tests {
         );
         debug!(all_events = ?all_events, "all SampleEvent1 events as record batch");
     }
+
+    #[test_log::test(test)]
+    fn test_all_events() {
+        let time_provider = Arc::new(MockProvider::new(Time::from_timestamp_nanos(100)));
+        let event_store = SysEventStore::new(Arc::clone(&time_provider) as _);
+        event_store.record(SampleEvent2 {
+            start_time: 0,
+            time_taken: 10,
+            generation_id: 100,
+        });
+        time_provider.set(Time::from_timestamp_nanos(50));
+        event_store.record(SampleEvent1 {
+            start_time: 0,
+            time_taken: 10,
+            total_fetched: 10,
+            random_name: "foo".to_owned(),
+        });
+
+        let all_events = event_store.all_events();
+        assert_eq!(2, all_events.len());
+        assert_eq!(50, all_events[0].time);
+        assert_eq!("SampleEvent1", all_events[0].event_type);
+        assert!(all_events[0].detail.contains("random_name: \"foo\""));
+        assert_eq!(100, all_events[1].time);
+        assert_eq!("SampleEvent2", all_events[1].event_type);
+        assert!(all_events[1].detail.contains("generation_id: 100"));
+    }
 }
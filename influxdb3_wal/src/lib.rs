@@ -333,12 +333,18 @@ pub enum CatalogOp {
     DeleteLastCache(LastCacheDelete),
     DeleteDatabase(DeleteDatabaseDefinition),
     DeleteTable(DeleteTableDefinition),
+    RenameTable(RenameTableDefinition),
     CreatePlugin(PluginDefinition),
     DeletePlugin(DeletePluginDefinition),
     CreateTrigger(TriggerDefinition),
     DeleteTrigger(DeleteTriggerDefinition),
     EnableTrigger(TriggerIdentifier),
     DisableTrigger(TriggerIdentifier),
+    SetTablePolicy(TablePolicyDefinition),
+    CreateComputedColumn(ComputedColumnDefinition),
+    SetRetentionPeriod(RetentionPeriodDefinition),
+    CreateRetentionPolicy(RetentionPolicyDefinition),
+    SetDatabaseConfig(DatabaseConfigDefinition),
 }
 
 #[derive(Debug, Clone, Eq, PartialEq, Serialize, Deserialize)]
@@ -363,6 +369,69 @@ pub struct DeleteTableDefinition {
     pub deletion_time: i64,
 }
 
+/// Renames a table, e.g., to swap a table whose data was written under a staging name into the
+/// place of the table that it replaces
+#[derive(Debug, Clone, Eq, PartialEq, Serialize, Deserialize)]
+pub struct RenameTableDefinition {
+    pub database_id: DbId,
+    pub database_name: Arc<str>,
+    pub table_id: TableId,
+    pub table_name: Arc<str>,
+    pub new_table_name: Arc<str>,
+}
+
+/// Sets the period for which the data of a database is queried, after which it is expired
+#[derive(Debug, Clone, Eq, PartialEq, Serialize, Deserialize)]
+pub struct RetentionPeriodDefinition {
+    pub database_id: DbId,
+    pub database_name: Arc<str>,
+    /// The retention period in nanoseconds, or `None` to retain data indefinitely
+    pub retention_period_ns: Option<i64>,
+}
+
+/// Creates, or replaces, a named retention policy of a database, as reported by `SHOW RETENTION
+/// POLICIES` for clients migrating from InfluxDB 1.x
+#[derive(Debug, Clone, Eq, PartialEq, Serialize, Deserialize)]
+pub struct RetentionPolicyDefinition {
+    pub database_id: DbId,
+    pub database_name: Arc<str>,
+    pub name: Arc<str>,
+    /// The duration of the policy in nanoseconds, or `None` if data is retained indefinitely
+    pub duration_ns: Option<i64>,
+    /// Make this the default policy of the database, in place of any other, whose duration is
+    /// the retention period of the database
+    pub default: bool,
+}
+
+/// Sets, or unsets, options of the DataFusion config that queries against a database are run
+/// with, which override the options of the server's config
+#[derive(Debug, Clone, Eq, PartialEq, Serialize, Deserialize)]
+pub struct DatabaseConfigDefinition {
+    pub database_id: DbId,
+    pub database_name: Arc<str>,
+    /// The options to set, in order, where an option without a value is unset
+    pub options: Vec<(String, Option<String>)>,
+}
+
+/// Sets the policies that apply to queries against a table
+#[derive(Debug, Clone, Eq, PartialEq, Serialize, Deserialize)]
+pub struct TablePolicyDefinition {
+    pub table_name: Arc<str>,
+    pub table_id: TableId,
+    /// Reject queries against the table that have no lower bound on time
+    pub require_time_predicate: bool,
+}
+
+/// Defines a column of a table that is computed from its stored columns when the table is queried
+#[derive(Debug, Clone, Eq, PartialEq, Serialize, Deserialize)]
+pub struct ComputedColumnDefinition {
+    pub table_name: Arc<str>,
+    pub table_id: TableId,
+    pub column_name: Arc<str>,
+    /// The SQL expression over the stored columns of the table that computes the column
+    pub expression: Arc<str>,
+}
+
 #[derive(Debug, Clone, Eq, PartialEq, Serialize, Deserialize)]
 pub struct TableDefinition {
     pub database_id: DbId,
@@ -1,8 +1,10 @@
 use arrow::array::RecordBatch;
 use data_types::{ChunkId, ChunkOrder, TransitionPartitionId};
 use datafusion::common::Statistics;
+use influxdb3_id::ParquetFileId;
 use iox_query::chunk_statistics::ChunkStatistics;
 use iox_query::{QueryChunk, QueryChunkData};
+use object_store::ObjectMeta;
 use parquet_file::storage::ParquetExecInput;
 use schema::sort::SortKey;
 use schema::Schema;
@@ -20,6 +22,22 @@ pub struct BufferChunk {
     pub chunk_order: data_types::ChunkOrder,
 }
 
+impl BufferChunk {
+    /// A copy of this chunk with the given `chunk_order`, which decides the rows of which chunks
+    /// win deduplication
+    pub fn with_chunk_order(&self, chunk_order: ChunkOrder) -> Self {
+        Self {
+            batches: self.batches.clone(),
+            schema: self.schema.clone(),
+            stats: Arc::clone(&self.stats),
+            partition_id: self.partition_id.clone(),
+            sort_key: self.sort_key.clone(),
+            id: self.id,
+            chunk_order,
+        }
+    }
+}
+
 impl QueryChunk for BufferChunk {
     fn stats(&self) -> Arc<Statistics> {
         Arc::clone(&self.stats.statistics())
@@ -71,6 +89,34 @@ pub struct ParquetChunk {
     pub(crate) id: ChunkId,
     pub(crate) chunk_order: ChunkOrder,
     pub(crate) parquet_exec: ParquetExecInput,
+    pub(crate) file_id: ParquetFileId,
+}
+
+impl ParquetChunk {
+    /// The id of the parquet file that this chunk reads
+    pub fn file_id(&self) -> ParquetFileId {
+        self.file_id
+    }
+
+    /// The location and size of the parquet file that this chunk reads
+    pub fn object_meta(&self) -> &ObjectMeta {
+        &self.parquet_exec.object_meta
+    }
+
+    /// A copy of this chunk with the given `chunk_order`, which decides the rows of which chunks
+    /// win deduplication
+    pub fn with_chunk_order(&self, chunk_order: ChunkOrder) -> Self {
+        Self {
+            schema: self.schema.clone(),
+            stats: Arc::clone(&self.stats),
+            partition_id: self.partition_id.clone(),
+            sort_key: self.sort_key.clone(),
+            id: self.id,
+            chunk_order,
+            parquet_exec: self.parquet_exec.clone(),
+            file_id: self.file_id,
+        }
+    }
 }
 
 impl QueryChunk for ParquetChunk {
@@ -25,7 +25,7 @@ use influxdb3_wal::{
 use iox_query::QueryChunk;
 use iox_time::Time;
 use serde::{Deserialize, Serialize};
-use std::{fmt::Debug, sync::Arc, time::Duration};
+use std::{collections::BTreeMap, fmt::Debug, sync::Arc, time::Duration};
 use thiserror::Error;
 
 #[derive(Debug, Error)]
@@ -64,6 +64,55 @@ pub trait DatabaseManager: Debug + Send + Sync + 'static {
         db_name: String,
         table_name: String,
     ) -> Result<(), write_buffer::Error>;
+    /// Rename the table, failing if another table already has the `new_table_name`
+    async fn rename_table(
+        &self,
+        db_name: String,
+        table_name: String,
+        new_table_name: String,
+    ) -> Result<(), write_buffer::Error>;
+    /// Set whether queries against the table must have a lower bound on time, recording the
+    /// policy in the catalog so that it is preserved on server restarts
+    async fn set_require_time_predicate(
+        &self,
+        db_name: String,
+        table_name: String,
+        require_time_predicate: bool,
+    ) -> Result<(), write_buffer::Error>;
+    /// Define a column of the table that is computed from its stored columns by the SQL
+    /// `expression` when the table is queried, replacing any previous definition of the column
+    async fn create_computed_column(
+        &self,
+        db_name: String,
+        table_name: String,
+        column_name: String,
+        expression: String,
+    ) -> Result<(), write_buffer::Error>;
+    /// Set the period after which the data of the database is expired, and no longer queried,
+    /// or retain it indefinitely if `retention_period` is `None`, recording it in the catalog
+    async fn set_retention_period(
+        &self,
+        db_name: String,
+        retention_period: Option<Duration>,
+    ) -> Result<(), write_buffer::Error>;
+    /// Create, or replace, the named retention policy of the database, whose data is retained
+    /// for `duration`, or indefinitely if it is `None`. Making the policy the `default` also
+    /// sets the retention period of the database to its duration.
+    async fn create_retention_policy(
+        &self,
+        db_name: String,
+        name: String,
+        duration: Option<Duration>,
+        default: bool,
+    ) -> Result<(), write_buffer::Error>;
+    /// Set the DataFusion config `options` that queries against the database are run with,
+    /// in order, overriding the options of the server's config, where an option without a
+    /// value is unset, leaving the server's option, if any, to apply
+    async fn set_database_config(
+        &self,
+        db_name: String,
+        options: Vec<(String, Option<String>)>,
+    ) -> Result<(), write_buffer::Error>;
 }
 
 /// The buffer is for buffering data in memory and in the wal before it is persisted as parquet files in storage.
@@ -89,8 +138,26 @@ pub trait Bufferer: Debug + Send + Sync + 'static {
     /// Returns the parquet files for a given database and table
     fn parquet_files(&self, db_id: DbId, table_id: TableId) -> Vec<ParquetFile>;
 
+    /// Returns up to `limit` of the parquet files for a given database and table that match the
+    /// `filter`, in the same order as [`Self::parquet_files`], without copying the files that are
+    /// not returned
+    fn parquet_files_filtered(
+        &self,
+        db_id: DbId,
+        table_id: TableId,
+        filter: &dyn Fn(&ParquetFile) -> bool,
+        limit: usize,
+    ) -> Vec<ParquetFile>;
+
+    /// Returns the aggregates of the parquet files of each table in the given database that has
+    /// any, without going through the individual files
+    fn table_summaries(&self, db_id: DbId) -> Vec<(TableId, TableSummary)>;
+
     /// A channel to watch for when new persisted snapshots are created
     fn watch_persisted_snapshots(&self) -> tokio::sync::watch::Receiver<Option<PersistedSnapshot>>;
+
+    /// Whether the buffer is replaying the WAL, during which queries see incomplete data
+    fn replay_state(&self) -> Arc<write_buffer::ReplayState>;
 }
 
 /// ChunkContainer is used by the query engine to get chunks for a given table. Chunks will generally be in the
@@ -104,6 +171,10 @@ pub trait ChunkContainer: Debug + Send + Sync + 'static {
         projection: Option<&Vec<usize>>,
         ctx: &dyn Session,
     ) -> Result<Vec<Arc<dyn QueryChunk>>, DataFusionError>;
+
+    /// Summarize the chunks of a given database and table, both those buffered in memory and
+    /// those persisted as parquet files, without copying their data
+    fn chunk_summaries(&self, db_id: DbId, table_id: TableId) -> Vec<ChunkSummary>;
 }
 
 /// [`DistinctCacheManager`] is used to manage interaction with a [`DistinctCacheProvider`]. This enables
@@ -277,6 +348,9 @@ pub struct ParquetFile {
     pub chunk_time: i64,
     pub min_time: i64,
     pub max_time: i64,
+    /// The compression codec of each column in the file, by column name
+    #[serde(default, skip_serializing_if = "BTreeMap::is_empty")]
+    pub column_codecs: BTreeMap<String, String>,
 }
 
 impl ParquetFile {
@@ -288,6 +362,77 @@ impl ParquetFile {
     }
 }
 
+/// The totals of the parquet files persisted for a table, along with the time range that they
+/// cover, see [`Bufferer::table_summaries`]
+#[derive(Debug, Clone, Copy, Eq, PartialEq)]
+pub struct TableSummary {
+    pub file_count: u64,
+    pub size_bytes: u64,
+    pub row_count: u64,
+    pub min_time: i64,
+    pub max_time: i64,
+}
+
+/// Where the data of a chunk is held, see [`ChunkSummary`]
+#[derive(Debug, Clone, Copy, Eq, PartialEq)]
+pub enum ChunkStorage {
+    /// Buffered in memory, and still accepting writes
+    Mutable,
+    /// Buffered in memory, and being persisted by a snapshot
+    ReadBuffer,
+    /// Persisted as a parquet file in the object store
+    ObjectStore,
+}
+
+impl ChunkStorage {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            Self::Mutable => "mutable",
+            Self::ReadBuffer => "read_buffer",
+            Self::ObjectStore => "object_store",
+        }
+    }
+}
+
+/// A summary of a chunk of a table, see [`ChunkContainer::chunk_summaries`]
+#[derive(Debug, Clone, Copy, Eq, PartialEq)]
+pub struct ChunkSummary {
+    /// The id of the parquet file of a persisted chunk, buffered chunks have no id
+    pub file_id: Option<ParquetFileId>,
+    /// The start of the period of time that the chunk is for, which partitions the data of the
+    /// table
+    pub chunk_time: i64,
+    pub storage: ChunkStorage,
+    pub row_count: u64,
+    pub min_time: i64,
+    pub max_time: i64,
+    /// The memory used by a buffered chunk, persisted chunks are not held in memory
+    pub memory_bytes: Option<u64>,
+}
+
+impl TableSummary {
+    /// Include the given `file` in the summary
+    pub(crate) fn add_file(&mut self, file: &ParquetFile) {
+        self.file_count += 1;
+        self.size_bytes += file.size_bytes;
+        self.row_count += file.row_count;
+        self.min_time = self.min_time.min(file.min_time);
+        self.max_time = self.max_time.max(file.max_time);
+    }
+}
+
+impl From<&ParquetFile> for TableSummary {
+    fn from(file: &ParquetFile) -> Self {
+        Self {
+            file_count: 1,
+            size_bytes: file.size_bytes,
+            row_count: file.row_count,
+            min_time: file.min_time,
+            max_time: file.max_time,
+        }
+    }
+}
+
 #[cfg(test)]
 impl ParquetFile {
     pub(crate) fn create_for_test(path: impl Into<String>) -> Self {
@@ -299,6 +444,7 @@ impl ParquetFile {
             chunk_time: 0,
             min_time: 0,
             max_time: 1,
+            column_codecs: Default::default(),
         }
     }
 }
@@ -27,8 +27,10 @@ use observability_deps::tracing::info;
 use parquet::arrow::ArrowWriter;
 use parquet::basic::Compression;
 use parquet::file::properties::WriterProperties;
-use parquet::format::FileMetaData;
+use parquet::format::{CompressionCodec, FileMetaData};
+use parquet::schema::types::ColumnPath;
 use std::any::Any;
+use std::collections::{BTreeMap, HashMap};
 use std::io::Write;
 use std::sync::Arc;
 use thiserror::Error;
@@ -86,6 +88,9 @@ pub struct Persister {
     /// Prefix used for all paths in the object store for this persister
     host_identifier_prefix: String,
     pub(crate) mem_pool: Arc<dyn MemoryPool>,
+    /// The compression used for the named columns of persisted parquet files, in place of the
+    /// default of zstd
+    column_compression: HashMap<String, Compression>,
 }
 
 impl Persister {
@@ -98,9 +103,20 @@ impl Persister {
             object_store,
             host_identifier_prefix: host_identifier_prefix.into(),
             mem_pool: Arc::new(UnboundedMemoryPool::default()),
+            column_compression: HashMap::new(),
         }
     }
 
+    /// Compress the named columns of persisted parquet files with the given codecs, e.g., to
+    /// trade write CPU for smaller large string fields, while the rest use zstd
+    pub fn with_column_compression(
+        mut self,
+        column_compression: HashMap<String, Compression>,
+    ) -> Self {
+        self.column_compression = column_compression;
+        self
+    }
+
     /// Get the Object Store URL
     pub fn object_store_url(&self) -> &ObjectStoreUrl {
         &self.object_store_url
@@ -110,7 +126,12 @@ impl Persister {
         &self,
         batches: SendableRecordBatchStream,
     ) -> Result<ParquetBytes> {
-        serialize_to_parquet(Arc::clone(&self.mem_pool), batches).await
+        serialize_to_parquet_with_compression(
+            Arc::clone(&self.mem_pool),
+            batches,
+            &self.column_compression,
+        )
+        .await
     }
 
     /// Get the host identifier prefix
@@ -348,6 +369,16 @@ impl Persister {
 pub async fn serialize_to_parquet(
     mem_pool: Arc<dyn MemoryPool>,
     batches: SendableRecordBatchStream,
+) -> Result<ParquetBytes> {
+    serialize_to_parquet_with_compression(mem_pool, batches, &HashMap::new()).await
+}
+
+/// Serialize the `batches` to parquet, compressing the columns named in `column_compression`
+/// with the given codecs and the rest with zstd
+pub async fn serialize_to_parquet_with_compression(
+    mem_pool: Arc<dyn MemoryPool>,
+    batches: SendableRecordBatchStream,
+    column_compression: &HashMap<String, Compression>,
 ) -> Result<ParquetBytes> {
     // The ArrowWriter::write() call will return an error if any subsequent
     // batch does not match this schema, enforcing schema uniformity.
@@ -359,7 +390,12 @@ pub async fn serialize_to_parquet(
 
     // Construct the arrow serializer with the metadata as part of the parquet
     // file properties.
-    let mut writer = TrackedMemoryArrowWriter::try_new(&mut bytes, Arc::clone(&schema), mem_pool)?;
+    let mut writer = TrackedMemoryArrowWriter::try_new_with_compression(
+        &mut bytes,
+        Arc::clone(&schema),
+        mem_pool,
+        column_compression,
+    )?;
 
     while let Some(batch) = stream.try_next().await? {
         writer.write(batch)?;
@@ -397,9 +433,27 @@ pub const ROW_GROUP_WRITE_SIZE: usize = 100_000;
 impl<W: Write + Send> TrackedMemoryArrowWriter<W> {
     /// create a new `TrackedMemoryArrowWriter<`
     pub fn try_new(sink: W, schema: SchemaRef, mem_pool: Arc<dyn MemoryPool>) -> Result<Self> {
-        let props = WriterProperties::builder()
-            .set_compression(Compression::ZSTD(Default::default()))
-            .set_max_row_group_size(ROW_GROUP_WRITE_SIZE)
+        Self::try_new_with_compression(sink, schema, mem_pool, &HashMap::new())
+    }
+
+    /// create a new `TrackedMemoryArrowWriter` that compresses the columns named in
+    /// `column_compression` with the given codecs, and the rest with zstd
+    pub fn try_new_with_compression(
+        sink: W,
+        schema: SchemaRef,
+        mem_pool: Arc<dyn MemoryPool>,
+        column_compression: &HashMap<String, Compression>,
+    ) -> Result<Self> {
+        let props = column_compression
+            .iter()
+            .fold(
+                WriterProperties::builder()
+                    .set_compression(Compression::ZSTD(Default::default()))
+                    .set_max_row_group_size(ROW_GROUP_WRITE_SIZE),
+                |props, (column, compression)| {
+                    props.set_column_compression(ColumnPath::from(column.as_str()), *compression)
+                },
+            )
             .build();
         let inner = ArrowWriter::try_new(sink, schema, Some(props))?;
         let consumer = MemoryConsumer::new("InfluxDB3 ParquetWriter (TrackedMemoryArrowWriter)");
@@ -433,6 +487,39 @@ impl<W: Write + Send> TrackedMemoryArrowWriter<W> {
     }
 }
 
+/// The compression codec of each of the columns in a parquet file, by column name
+///
+/// Codecs are set per column chunk, so a column whose codec differs between the row groups of
+/// the file reports that of the first.
+pub fn column_codecs(meta_data: &FileMetaData) -> BTreeMap<String, String> {
+    let mut codecs = BTreeMap::new();
+    for column in meta_data
+        .row_groups
+        .iter()
+        .flat_map(|row_group| row_group.columns.iter())
+        .filter_map(|column| column.meta_data.as_ref())
+    {
+        codecs
+            .entry(column.path_in_schema.join("."))
+            .or_insert_with(|| codec_name(column.codec).to_string());
+    }
+    codecs
+}
+
+fn codec_name(codec: CompressionCodec) -> &'static str {
+    match codec {
+        CompressionCodec::UNCOMPRESSED => "uncompressed",
+        CompressionCodec::SNAPPY => "snappy",
+        CompressionCodec::GZIP => "gzip",
+        CompressionCodec::LZO => "lzo",
+        CompressionCodec::BROTLI => "brotli",
+        CompressionCodec::LZ4 => "lz4",
+        CompressionCodec::ZSTD => "zstd",
+        CompressionCodec::LZ4_RAW => "lz4_raw",
+        _ => "unknown",
+    }
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -448,8 +535,11 @@ mod tests {
     use pretty_assertions::assert_eq;
     use tokio::time::{sleep, Duration};
     use {
-        arrow::array::Int32Array, arrow::datatypes::DataType, arrow::datatypes::Field,
-        arrow::datatypes::Schema, chrono::Utc,
+        arrow::array::{Int32Array, StringArray},
+        arrow::datatypes::DataType,
+        arrow::datatypes::Field,
+        arrow::datatypes::Schema,
+        chrono::Utc,
         datafusion::physical_plan::stream::RecordBatchReceiverStreamBuilder,
         object_store::local::LocalFileSystem,
     };
@@ -795,6 +885,7 @@ mod tests {
                 chunk_time: 5,
                 min_time: 0,
                 max_time: 1,
+                column_codecs: Default::default(),
             },
         );
         persister.persist_snapshot(&info_file).await.unwrap();
@@ -910,6 +1001,40 @@ mod tests {
         assert_eq!(parquet.meta_data.num_rows, 10);
     }
 
+    #[tokio::test]
+    async fn column_compression() {
+        let persister = Persister::new(Arc::new(InMemory::new()), "test_host")
+            .with_column_compression(HashMap::from([("name".to_string(), Compression::SNAPPY)]));
+
+        let schema = Arc::new(Schema::new(vec![
+            Field::new("id", DataType::Int32, false),
+            Field::new("name", DataType::Utf8, false),
+        ]));
+        let stream_builder = RecordBatchReceiverStreamBuilder::new(schema.clone(), 5);
+        let batch = RecordBatch::try_new(
+            schema.clone(),
+            vec![
+                Arc::new(Int32Array::from(vec![1, 2, 3])),
+                Arc::new(StringArray::from(vec!["a", "b", "c"])),
+            ],
+        )
+        .unwrap();
+        stream_builder.tx().send(Ok(batch)).await.unwrap();
+
+        let parquet = persister
+            .serialize_to_parquet(stream_builder.build())
+            .await
+            .unwrap();
+
+        assert_eq!(
+            BTreeMap::from([
+                ("id".to_string(), "zstd".to_string()),
+                ("name".to_string(), "snappy".to_string()),
+            ]),
+            column_codecs(&parquet.meta_data)
+        );
+    }
+
     #[tokio::test]
     async fn persist_and_load_parquet_bytes() {
         let local_disk =
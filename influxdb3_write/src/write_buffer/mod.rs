@@ -12,8 +12,9 @@ use crate::write_buffer::queryable_buffer::QueryableBuffer;
 use crate::write_buffer::validator::WriteValidator;
 use crate::{chunk::ParquetChunk, DatabaseManager};
 use crate::{
-    BufferedWriteRequest, Bufferer, ChunkContainer, DistinctCacheManager, LastCacheManager,
-    ParquetFile, PersistedSnapshot, Precision, WriteBuffer, WriteLineError,
+    BufferedWriteRequest, Bufferer, ChunkContainer, ChunkStorage, ChunkSummary,
+    DistinctCacheManager, LastCacheManager, ParquetFile, PersistedSnapshot, Precision,
+    TableSummary, WriteBuffer, WriteLineError,
 };
 use async_trait::async_trait;
 use data_types::{
@@ -21,9 +22,10 @@ use data_types::{
     PartitionId,
 };
 use datafusion::catalog::Session;
-use datafusion::common::DataFusionError;
+use datafusion::common::{DFSchema, DataFusionError};
 use datafusion::datasource::object_store::ObjectStoreUrl;
-use datafusion::logical_expr::Expr;
+use datafusion::execution::context::SessionContext;
+use datafusion::logical_expr::{Expr, ExprSchemable};
 use influxdb3_cache::distinct_cache::{self, CreateDistinctCacheArgs, DistinctCacheProvider};
 use influxdb3_cache::last_cache::{self, LastCacheProvider};
 use influxdb3_cache::parquet_cache::ParquetCacheOracle;
@@ -37,7 +39,11 @@ use influxdb3_wal::{
     LastCacheDelete, LastCacheSize, Wal, WalConfig, WalFileNotifier, WalOp,
 };
 use influxdb3_wal::{CatalogOp::CreateLastCache, DeleteTableDefinition};
-use influxdb3_wal::{DatabaseDefinition, FieldDefinition};
+use influxdb3_wal::{
+    ComputedColumnDefinition, DatabaseConfigDefinition, DatabaseDefinition, FieldDefinition,
+    RenameTableDefinition, RetentionPeriodDefinition, RetentionPolicyDefinition,
+    TablePolicyDefinition,
+};
 use iox_query::chunk_statistics::{create_chunk_statistics, NoColumnRanges};
 use iox_query::QueryChunk;
 use iox_time::{Time, TimeProvider};
@@ -49,6 +55,7 @@ use observability_deps::tracing::{debug, warn};
 use parquet_file::storage::ParquetExecInput;
 use queryable_buffer::QueryableBufferArgs;
 use schema::Schema;
+use std::sync::atomic::{AtomicBool, Ordering};
 use std::sync::Arc;
 use std::time::Duration;
 use thiserror::Error;
@@ -117,6 +124,12 @@ pub enum Error {
     #[error("error in distinct value cache: {0}")]
     DistinctCacheError(#[from] distinct_cache::ProviderError),
 
+    #[error("invalid expression for computed column {column_name:?}: {reason}")]
+    InvalidComputedColumn { column_name: String, reason: String },
+
+    #[error("invalid retention period for database {db_name:?}: the period must be positive")]
+    InvalidRetentionPeriod { db_name: String },
+
     #[error("error: {0}")]
     AnyhowError(#[from] anyhow::Error),
 }
@@ -146,6 +159,31 @@ pub struct WriteBufferImpl {
     metrics: WriteMetrics,
     distinct_cache: Arc<DistinctCacheProvider>,
     last_cache: Arc<LastCacheProvider>,
+    replay_state: Arc<ReplayState>,
+}
+
+/// Tracks whether the contents of the WAL are being replayed into a write buffer, e.g., on
+/// startup, in which case the data that the buffer holds is not yet complete
+#[derive(Debug, Default)]
+pub struct ReplayState {
+    in_progress: AtomicBool,
+}
+
+impl ReplayState {
+    /// Whether a replay is in progress
+    pub fn in_progress(&self) -> bool {
+        self.in_progress.load(Ordering::Acquire)
+    }
+
+    /// Mark a replay as started
+    pub fn start(&self) {
+        self.in_progress.store(true, Ordering::Release);
+    }
+
+    /// Mark the replay as finished
+    pub fn finish(&self) {
+        self.in_progress.store(false, Ordering::Release);
+    }
 }
 
 /// The maximum number of snapshots to load on start
@@ -178,6 +216,9 @@ impl WriteBufferImpl {
             metric_registry,
         }: WriteBufferImplArgs,
     ) -> Result<Arc<Self>> {
+        let replay_state = Arc::new(ReplayState::default());
+        replay_state.start();
+
         // load snapshots and replay the wal into the in memory buffer
         let persisted_snapshots = persister
             .load_snapshots(N_SNAPSHOTS_TO_LOAD_ON_START)
@@ -221,6 +262,7 @@ impl WriteBufferImpl {
             last_snapshot_sequence_number,
         )
         .await?;
+        replay_state.finish();
 
         let result = Arc::new(Self {
             catalog,
@@ -234,6 +276,7 @@ impl WriteBufferImpl {
             persisted_files,
             buffer: queryable_buffer,
             metrics: WriteMetrics::new(&metric_registry),
+            replay_state,
         });
         Ok(result)
     }
@@ -397,6 +440,7 @@ pub fn parquet_chunk_from_file(
         id: ChunkId::new(),
         chunk_order: ChunkOrder::new(chunk_order),
         parquet_exec,
+        file_id: parquet_file.id,
     }
 }
 
@@ -426,9 +470,28 @@ impl Bufferer for WriteBufferImpl {
         self.buffer.persisted_parquet_files(db_id, table_id)
     }
 
+    fn parquet_files_filtered(
+        &self,
+        db_id: DbId,
+        table_id: TableId,
+        filter: &dyn Fn(&ParquetFile) -> bool,
+        limit: usize,
+    ) -> Vec<ParquetFile> {
+        self.persisted_files
+            .get_files_filtered(db_id, table_id, filter, limit)
+    }
+
+    fn table_summaries(&self, db_id: DbId) -> Vec<(TableId, TableSummary)> {
+        self.persisted_files.get_table_summaries(db_id)
+    }
+
     fn watch_persisted_snapshots(&self) -> Receiver<Option<PersistedSnapshot>> {
         self.buffer.persisted_snapshot_notify_rx()
     }
+
+    fn replay_state(&self) -> Arc<ReplayState> {
+        Arc::clone(&self.replay_state)
+    }
 }
 
 impl ChunkContainer for WriteBufferImpl {
@@ -442,6 +505,25 @@ impl ChunkContainer for WriteBufferImpl {
     ) -> crate::Result<Vec<Arc<dyn QueryChunk>>, DataFusionError> {
         self.get_table_chunks(database_name, table_name, filters, projection, ctx)
     }
+
+    fn chunk_summaries(&self, db_id: DbId, table_id: TableId) -> Vec<ChunkSummary> {
+        let mut summaries = self.buffer.buffered_chunk_summaries(db_id, table_id);
+        summaries.extend(
+            self.persisted_files
+                .get_files(db_id, table_id)
+                .into_iter()
+                .map(|file| ChunkSummary {
+                    file_id: Some(file.id),
+                    chunk_time: file.chunk_time,
+                    storage: ChunkStorage::ObjectStore,
+                    row_count: file.row_count,
+                    min_time: file.min_time,
+                    max_time: file.max_time,
+                    memory_bytes: None,
+                }),
+        );
+        summaries
+    }
 }
 
 #[async_trait::async_trait]
@@ -797,6 +879,253 @@ impl DatabaseManager for WriteBufferImpl {
         );
         Ok(())
     }
+
+    async fn rename_table(
+        &self,
+        db_name: String,
+        table_name: String,
+        new_table_name: String,
+    ) -> crate::Result<(), self::Error> {
+        let (db_id, db_schema) = self.catalog.db_id_and_schema(&db_name).ok_or_else(|| {
+            self::Error::DatabaseNotFound {
+                db_name: db_name.to_owned(),
+            }
+        })?;
+
+        let (table_id, table_defn) = db_schema
+            .table_id_and_definition(table_name.as_str())
+            .ok_or_else(|| self::Error::TableNotFound {
+                db_name: db_name.to_owned(),
+                table_name: table_name.to_owned(),
+            })?;
+        let catalog_batch = CatalogBatch {
+            time_ns: self.time_provider.now().timestamp_nanos(),
+            database_id: db_id,
+            database_name: Arc::clone(&db_schema.name),
+            ops: vec![CatalogOp::RenameTable(RenameTableDefinition {
+                database_id: db_id,
+                database_name: Arc::clone(&db_schema.name),
+                table_id,
+                table_name: Arc::clone(&table_defn.table_name),
+                new_table_name: new_table_name.as_str().into(),
+            })],
+        };
+        if let Some(catalog_batch) = self.catalog.apply_catalog_batch(&catalog_batch)? {
+            self.wal
+                .write_ops(vec![WalOp::Catalog(catalog_batch)])
+                .await?;
+        }
+        debug!(
+            db_id = ?db_id,
+            table_id = ?table_id,
+            table_name = ?table_defn.table_name,
+            new_table_name,
+            "successfully renamed table"
+        );
+        Ok(())
+    }
+
+    async fn set_require_time_predicate(
+        &self,
+        db_name: String,
+        table_name: String,
+        require_time_predicate: bool,
+    ) -> crate::Result<(), self::Error> {
+        let (db_id, db_schema) = self.catalog.db_id_and_schema(&db_name).ok_or_else(|| {
+            self::Error::DatabaseNotFound {
+                db_name: db_name.to_owned(),
+            }
+        })?;
+
+        let (table_id, table_defn) = db_schema
+            .table_id_and_definition(table_name.as_str())
+            .ok_or_else(|| self::Error::TableNotFound {
+                db_name: db_name.to_owned(),
+                table_name: table_name.to_owned(),
+            })?;
+        let catalog_batch = CatalogBatch {
+            time_ns: self.time_provider.now().timestamp_nanos(),
+            database_id: db_id,
+            database_name: Arc::clone(&db_schema.name),
+            ops: vec![CatalogOp::SetTablePolicy(TablePolicyDefinition {
+                table_name: Arc::clone(&table_defn.table_name),
+                table_id,
+                require_time_predicate,
+            })],
+        };
+        if let Some(catalog_batch) = self.catalog.apply_catalog_batch(&catalog_batch)? {
+            self.wal
+                .write_ops(vec![WalOp::Catalog(catalog_batch)])
+                .await?;
+        }
+        debug!(
+            db_id = ?db_id,
+            table_id = ?table_id,
+            require_time_predicate,
+            "set table policy"
+        );
+        Ok(())
+    }
+
+    async fn create_computed_column(
+        &self,
+        db_name: String,
+        table_name: String,
+        column_name: String,
+        expression: String,
+    ) -> crate::Result<(), self::Error> {
+        let (db_id, db_schema) = self.catalog.db_id_and_schema(&db_name).ok_or_else(|| {
+            self::Error::DatabaseNotFound {
+                db_name: db_name.to_owned(),
+            }
+        })?;
+
+        let (table_id, table_defn) = db_schema
+            .table_id_and_definition(table_name.as_str())
+            .ok_or_else(|| self::Error::TableNotFound {
+                db_name: db_name.to_owned(),
+                table_name: table_name.to_owned(),
+            })?;
+        // check that the expression can be planned against the stored columns, so that a bad
+        // definition does not break every query of the table:
+        DFSchema::try_from(table_defn.schema.as_arrow())
+            .and_then(|schema| {
+                SessionContext::new()
+                    .parse_sql_expr(&expression, &schema)?
+                    .get_type(&schema)
+            })
+            .map_err(|e| self::Error::InvalidComputedColumn {
+                column_name: column_name.clone(),
+                reason: e.to_string(),
+            })?;
+        let catalog_batch = CatalogBatch {
+            time_ns: self.time_provider.now().timestamp_nanos(),
+            database_id: db_id,
+            database_name: Arc::clone(&db_schema.name),
+            ops: vec![CatalogOp::CreateComputedColumn(ComputedColumnDefinition {
+                table_name: Arc::clone(&table_defn.table_name),
+                table_id,
+                column_name: column_name.as_str().into(),
+                expression: expression.as_str().into(),
+            })],
+        };
+        if let Some(catalog_batch) = self.catalog.apply_catalog_batch(&catalog_batch)? {
+            self.wal
+                .write_ops(vec![WalOp::Catalog(catalog_batch)])
+                .await?;
+        }
+        debug!(
+            db_id = ?db_id,
+            table_id = ?table_id,
+            %column_name,
+            %expression,
+            "created computed column"
+        );
+        Ok(())
+    }
+
+    async fn set_retention_period(
+        &self,
+        db_name: String,
+        retention_period: Option<Duration>,
+    ) -> crate::Result<(), self::Error> {
+        if retention_period.is_some_and(|period| period.is_zero()) {
+            return Err(self::Error::InvalidRetentionPeriod { db_name });
+        }
+        let (db_id, db_schema) = self.catalog.db_id_and_schema(&db_name).ok_or_else(|| {
+            self::Error::DatabaseNotFound {
+                db_name: db_name.to_owned(),
+            }
+        })?;
+        let retention_period_ns =
+            retention_period.map(|period| i64::try_from(period.as_nanos()).unwrap_or(i64::MAX));
+        let catalog_batch = CatalogBatch {
+            time_ns: self.time_provider.now().timestamp_nanos(),
+            database_id: db_id,
+            database_name: Arc::clone(&db_schema.name),
+            ops: vec![CatalogOp::SetRetentionPeriod(RetentionPeriodDefinition {
+                database_id: db_id,
+                database_name: Arc::clone(&db_schema.name),
+                retention_period_ns,
+            })],
+        };
+        if let Some(catalog_batch) = self.catalog.apply_catalog_batch(&catalog_batch)? {
+            self.wal
+                .write_ops(vec![WalOp::Catalog(catalog_batch)])
+                .await?;
+        }
+        debug!(db_id = ?db_id, ?retention_period, "set retention period");
+        Ok(())
+    }
+
+    async fn create_retention_policy(
+        &self,
+        db_name: String,
+        name: String,
+        duration: Option<Duration>,
+        default: bool,
+    ) -> crate::Result<(), self::Error> {
+        if duration.is_some_and(|duration| duration.is_zero()) {
+            return Err(self::Error::InvalidRetentionPeriod { db_name });
+        }
+        let (db_id, db_schema) = self.catalog.db_id_and_schema(&db_name).ok_or_else(|| {
+            self::Error::DatabaseNotFound {
+                db_name: db_name.to_owned(),
+            }
+        })?;
+        let duration_ns =
+            duration.map(|duration| i64::try_from(duration.as_nanos()).unwrap_or(i64::MAX));
+        let catalog_batch = CatalogBatch {
+            time_ns: self.time_provider.now().timestamp_nanos(),
+            database_id: db_id,
+            database_name: Arc::clone(&db_schema.name),
+            ops: vec![CatalogOp::CreateRetentionPolicy(
+                RetentionPolicyDefinition {
+                    database_id: db_id,
+                    database_name: Arc::clone(&db_schema.name),
+                    name: name.as_str().into(),
+                    duration_ns,
+                    default,
+                },
+            )],
+        };
+        if let Some(catalog_batch) = self.catalog.apply_catalog_batch(&catalog_batch)? {
+            self.wal
+                .write_ops(vec![WalOp::Catalog(catalog_batch)])
+                .await?;
+        }
+        debug!(db_id = ?db_id, %name, ?duration, default, "created retention policy");
+        Ok(())
+    }
+
+    async fn set_database_config(
+        &self,
+        db_name: String,
+        options: Vec<(String, Option<String>)>,
+    ) -> crate::Result<(), self::Error> {
+        let (db_id, db_schema) = self.catalog.db_id_and_schema(&db_name).ok_or_else(|| {
+            self::Error::DatabaseNotFound {
+                db_name: db_name.to_owned(),
+            }
+        })?;
+        let catalog_batch = CatalogBatch {
+            time_ns: self.time_provider.now().timestamp_nanos(),
+            database_id: db_id,
+            database_name: Arc::clone(&db_schema.name),
+            ops: vec![CatalogOp::SetDatabaseConfig(DatabaseConfigDefinition {
+                database_id: db_id,
+                database_name: Arc::clone(&db_schema.name),
+                options: options.clone(),
+            })],
+        };
+        if let Some(catalog_batch) = self.catalog.apply_catalog_batch(&catalog_batch)? {
+            self.wal
+                .write_ops(vec![WalOp::Catalog(catalog_batch)])
+                .await?;
+        }
+        debug!(db_id = ?db_id, ?options, "set database config");
+        Ok(())
+    }
 }
 
 impl WriteBuffer for WriteBufferImpl {}
@@ -1605,6 +1934,7 @@ mod tests {
                     chunk_time: 1,
                     min_time: 0,
                     max_time: 1,
+                    column_codecs: Default::default(),
                 },
             );
         }
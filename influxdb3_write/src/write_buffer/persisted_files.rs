@@ -2,7 +2,7 @@
 //! When queries come in they will combine whatever chunks exist from `QueryableBuffer` with
 //! the persisted files to get the full set of data to query.
 
-use crate::{ParquetFile, PersistedSnapshot};
+use crate::{ParquetFile, PersistedSnapshot, TableSummary};
 use hashbrown::HashMap;
 use influxdb3_id::DbId;
 use influxdb3_id::TableId;
@@ -11,6 +11,7 @@ use parking_lot::RwLock;
 
 type DatabaseToTables = HashMap<DbId, TableToFiles>;
 type TableToFiles = HashMap<TableId, Vec<ParquetFile>>;
+type DatabaseToSummaries = HashMap<DbId, HashMap<TableId, TableSummary>>;
 
 #[derive(Debug, Default)]
 pub struct PersistedFiles {
@@ -29,6 +30,7 @@ impl PersistedFiles {
     /// Add a file to the list of persisted files
     pub fn add_file(&self, db_id: DbId, table_id: TableId, file: ParquetFile) {
         let mut inner = self.inner.write();
+        add_to_summary(&mut inner.summaries, db_id, table_id, &file);
         let tables = inner.files.entry(db_id).or_default();
         let table_files = tables.entry(table_id).or_default();
         table_files.push(file);
@@ -56,6 +58,50 @@ impl PersistedFiles {
 
         files
     }
+
+    /// Get up to `limit` of the files for a given database and table that match the `filter`, in
+    /// descending order of min_time as for [`Self::get_files`]
+    ///
+    /// Only the files that are returned are cloned, so that listing the most recent files of a
+    /// table does not copy every file that it has.
+    pub fn get_files_filtered(
+        &self,
+        db_id: DbId,
+        table_id: TableId,
+        filter: impl Fn(&ParquetFile) -> bool,
+        limit: usize,
+    ) -> Vec<ParquetFile> {
+        let inner = self.inner.read();
+        let Some(files) = inner
+            .files
+            .get(&db_id)
+            .and_then(|tables| tables.get(&table_id))
+        else {
+            return vec![];
+        };
+        let mut files = files
+            .iter()
+            .filter(|&file| filter(file))
+            .collect::<Vec<_>>();
+        let by_min_time = |a: &&ParquetFile, b: &&ParquetFile| b.min_time.cmp(&a.min_time);
+        if limit < files.len() {
+            files.select_nth_unstable_by(limit, by_min_time);
+            files.truncate(limit);
+        }
+        files.sort_by(by_min_time);
+        files.into_iter().cloned().collect()
+    }
+
+    /// Get the summary of the files of each table in a given database that has any, which is
+    /// kept up to date as files are added, rather than computed from the files
+    pub fn get_table_summaries(&self, db_id: DbId) -> Vec<(TableId, TableSummary)> {
+        self.inner
+            .read()
+            .summaries
+            .get(&db_id)
+            .map(|tables| tables.iter().map(|(id, summary)| (*id, *summary)).collect())
+            .unwrap_or_default()
+    }
 }
 
 impl ParquetMetrics for PersistedFiles {
@@ -80,6 +126,8 @@ struct Inner {
     pub parquet_files_size_mb: f64,
     /// Overall row count within the parquet files
     pub parquet_files_row_count: u64,
+    /// The summary of the files of each table
+    pub summaries: DatabaseToSummaries,
 }
 
 impl Inner {
@@ -87,14 +135,19 @@ impl Inner {
         let mut file_count = 0;
         let mut size_in_mb = 0.0;
         let mut row_count = 0;
+        let mut summaries = DatabaseToSummaries::new();
 
         let files = persisted_snapshots.into_iter().fold(
             hashbrown::HashMap::new(),
             |mut files, persisted_snapshot| {
                 size_in_mb += as_mb(persisted_snapshot.parquet_size_bytes);
                 row_count += persisted_snapshot.row_count;
-                let parquet_files_added =
-                    update_persisted_files_with_snapshot(true, persisted_snapshot, &mut files);
+                let parquet_files_added = update_persisted_files_with_snapshot(
+                    true,
+                    persisted_snapshot,
+                    &mut files,
+                    &mut summaries,
+                );
                 file_count += parquet_files_added;
                 files
             },
@@ -105,14 +158,19 @@ impl Inner {
             parquet_files_count: file_count,
             parquet_files_row_count: row_count,
             parquet_files_size_mb: size_in_mb,
+            summaries,
         }
     }
 
     pub fn add_persisted_snapshot(&mut self, persisted_snapshot: PersistedSnapshot) {
         self.parquet_files_row_count += persisted_snapshot.row_count;
         self.parquet_files_size_mb += as_mb(persisted_snapshot.parquet_size_bytes);
-        let file_count =
-            update_persisted_files_with_snapshot(false, persisted_snapshot, &mut self.files);
+        let file_count = update_persisted_files_with_snapshot(
+            false,
+            persisted_snapshot,
+            &mut self.files,
+            &mut self.summaries,
+        );
         self.parquet_files_count += file_count;
     }
 }
@@ -126,6 +184,7 @@ fn update_persisted_files_with_snapshot(
     initial_load: bool,
     persisted_snapshot: PersistedSnapshot,
     db_to_tables: &mut HashMap<DbId, HashMap<TableId, Vec<ParquetFile>>>,
+    summaries: &mut DatabaseToSummaries,
 ) -> u64 {
     let mut file_count = 0;
     persisted_snapshot
@@ -140,22 +199,33 @@ fn update_persisted_files_with_snapshot(
                 .into_iter()
                 .for_each(|(table_id, mut new_parquet_files)| {
                     let table_files = db_tables.entry(table_id).or_default();
-                    if initial_load {
-                        file_count += new_parquet_files.len() as u64;
-                        table_files.append(&mut new_parquet_files);
-                    } else {
-                        let mut filtered_files: Vec<ParquetFile> = new_parquet_files
-                            .into_iter()
-                            .filter(|file| !table_files.contains(file))
-                            .collect();
-                        file_count += filtered_files.len() as u64;
-                        table_files.append(&mut filtered_files);
+                    if !initial_load {
+                        new_parquet_files.retain(|file| !table_files.contains(file));
+                    }
+                    for file in &new_parquet_files {
+                        add_to_summary(summaries, db_id, table_id, file);
                     }
+                    file_count += new_parquet_files.len() as u64;
+                    table_files.append(&mut new_parquet_files);
                 });
         });
     file_count
 }
 
+fn add_to_summary(
+    summaries: &mut DatabaseToSummaries,
+    db_id: DbId,
+    table_id: TableId,
+    file: &ParquetFile,
+) {
+    summaries
+        .entry(db_id)
+        .or_default()
+        .entry(table_id)
+        .and_modify(|summary| summary.add_file(file))
+        .or_insert_with(|| TableSummary::from(file));
+}
+
 #[cfg(test)]
 mod tests {
 
@@ -238,6 +308,96 @@ mod tests {
         assert_eq!(150, row_count);
     }
 
+    #[test_log::test(test)]
+    fn test_table_summaries_after_update_with_duplicate_file() {
+        let all_persisted_snapshot_files = build_persisted_snapshots();
+        let already_existing_file = all_persisted_snapshot_files
+            .first()
+            .unwrap()
+            .databases
+            .get(&DbId::from(0))
+            .unwrap()
+            .tables
+            .get(&TableId::from(0))
+            .unwrap()
+            .first()
+            .cloned()
+            .unwrap();
+        let persisted_file =
+            PersistedFiles::new_from_persisted_snapshots(all_persisted_snapshot_files);
+
+        let mut parquet_files = build_parquet_files(4);
+        parquet_files[0].min_time = 5;
+        parquet_files[1].max_time = 500;
+        parquet_files.push(already_existing_file);
+        persisted_file.add_persisted_snapshot_files(build_snapshot(parquet_files, 3, 3, 3));
+
+        // unlike the overall metrics, the duplicate file is not counted:
+        assert_eq!(
+            vec![(
+                TableId::from(0),
+                TableSummary {
+                    file_count: 14,
+                    size_bytes: 700_000,
+                    row_count: 140,
+                    min_time: 5,
+                    max_time: 500,
+                }
+            )],
+            persisted_file.get_table_summaries(DbId::from(0))
+        );
+        assert!(persisted_file.get_table_summaries(DbId::from(1)).is_empty());
+    }
+
+    #[test_log::test(test)]
+    fn test_get_files_filtered_with_limit() {
+        let mut parquet_files = build_parquet_files(1_000);
+        for (i, file) in parquet_files.iter_mut().enumerate() {
+            file.min_time = i as i64;
+            file.max_time = i as i64 + 10;
+        }
+        let persisted_file = PersistedFiles::default();
+        persisted_file.add_persisted_snapshot_files(build_snapshot(parquet_files, 1, 1, 1));
+
+        // only the most recent files are returned, in the same order as all of the files:
+        let files = persisted_file.get_files_filtered(DbId::from(0), TableId::from(0), |_| true, 3);
+        assert_eq!(
+            vec![999, 998, 997],
+            files.iter().map(|f| f.min_time).collect::<Vec<_>>()
+        );
+        assert_eq!(
+            persisted_file.get_files(DbId::from(0), TableId::from(0))[..3],
+            files[..]
+        );
+
+        // the limit applies to the files that match the filter:
+        let files = persisted_file.get_files_filtered(
+            DbId::from(0),
+            TableId::from(0),
+            |f| f.min_time % 100 == 0,
+            2,
+        );
+        assert_eq!(
+            vec![900, 800],
+            files.iter().map(|f| f.min_time).collect::<Vec<_>>()
+        );
+
+        // as many files as there are are returned for a limit beyond their number:
+        let files = persisted_file.get_files_filtered(
+            DbId::from(0),
+            TableId::from(0),
+            |f| f.min_time < 5,
+            100,
+        );
+        assert_eq!(
+            vec![4, 3, 2, 1, 0],
+            files.iter().map(|f| f.min_time).collect::<Vec<_>>()
+        );
+        assert!(persisted_file
+            .get_files_filtered(DbId::from(1), TableId::from(0), |_| true, 10)
+            .is_empty());
+    }
+
     fn build_persisted_snapshots() -> Vec<PersistedSnapshot> {
         let mut all_persisted_snapshot_files = Vec::new();
         let parquet_files_1 = build_parquet_files(5);
@@ -279,6 +439,7 @@ mod tests {
                 chunk_time: 10,
                 min_time: 10,
                 max_time: 200,
+                column_codecs: Default::default(),
             })
             .collect();
         parquet_files
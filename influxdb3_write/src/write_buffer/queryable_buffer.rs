@@ -1,9 +1,9 @@
 use crate::chunk::BufferChunk;
 use crate::paths::ParquetFilePath;
-use crate::persister::Persister;
+use crate::persister::{column_codecs, Persister};
 use crate::write_buffer::persisted_files::PersistedFiles;
 use crate::write_buffer::table_buffer::TableBuffer;
-use crate::{ParquetFile, ParquetFileId, PersistedSnapshot};
+use crate::{ChunkSummary, ParquetFile, ParquetFileId, PersistedSnapshot};
 use anyhow::Context;
 use arrow::record_batch::RecordBatch;
 use async_trait::async_trait;
@@ -325,6 +325,7 @@ impl QueryableBuffer {
                         chunk_time,
                         min_time,
                         max_time,
+                        column_codecs: column_codecs(&file_meta_data),
                     },
                 )
             }
@@ -370,6 +371,17 @@ impl QueryableBuffer {
         receiver
     }
 
+    /// Summarize the chunks of the table that are buffered in memory
+    pub fn buffered_chunk_summaries(&self, db_id: DbId, table_id: TableId) -> Vec<ChunkSummary> {
+        let buffer = self.buffer.read();
+        buffer
+            .db_to_table
+            .get(&db_id)
+            .and_then(|db_buffer| db_buffer.get(&table_id))
+            .map(|table_buffer| table_buffer.chunk_summaries())
+            .unwrap_or_default()
+    }
+
     pub fn persisted_parquet_files(&self, db_id: DbId, table_id: TableId) -> Vec<ParquetFile> {
         self.persisted_files.get_files(db_id, table_id)
     }
@@ -516,12 +528,18 @@ impl BufferState {
                                     table_buffer_map.remove(&table_definition.table_id);
                                 }
                             }
+                            CatalogOp::RenameTable(_) => {}
                             CatalogOp::CreatePlugin(_) => {}
                             CatalogOp::DeletePlugin(_) => {}
                             CatalogOp::CreateTrigger(_) => {}
                             CatalogOp::DeleteTrigger(_) => {}
                             CatalogOp::EnableTrigger(_) => {}
                             CatalogOp::DisableTrigger(_) => {}
+                            CatalogOp::SetTablePolicy(_) => {}
+                            CatalogOp::CreateComputedColumn(_) => {}
+                            CatalogOp::SetRetentionPeriod(_) => {}
+                            CatalogOp::CreateRetentionPolicy(_) => {}
+                            CatalogOp::SetDatabaseConfig(_) => {}
                         }
                     }
                 }
@@ -1,5 +1,6 @@
 //! The in memory buffer of a table that can be quickly added to and queried
 
+use crate::{ChunkStorage, ChunkSummary};
 use arrow::array::{
     Array, ArrayBuilder, ArrayRef, BooleanBuilder, Float64Builder, GenericByteDictionaryBuilder,
     Int64Builder, StringArray, StringBuilder, StringDictionaryBuilder, TimestampNanosecondBuilder,
@@ -176,6 +177,41 @@ impl TableBuffer {
         size
     }
 
+    /// Summarize the chunks being snapshotted and the chunks still accepting writes, in that
+    /// order, each ordered by chunk time
+    pub fn chunk_summaries(&self) -> Vec<ChunkSummary> {
+        let snapshotting = self.snapshotting_chunks.iter().map(|sc| ChunkSummary {
+            file_id: None,
+            chunk_time: sc.chunk_time,
+            storage: ChunkStorage::ReadBuffer,
+            row_count: sc.record_batch.num_rows() as u64,
+            min_time: sc.timestamp_min_max.min,
+            max_time: sc.timestamp_min_max.max,
+            memory_bytes: Some(sc.record_batch.get_array_memory_size() as u64),
+        });
+        let mutable = self.chunk_time_to_chunks.iter().map(|(chunk_time, c)| {
+            let size = c
+                .data
+                .values()
+                .map(|builder| size_of::<ColumnId>() + size_of::<String>() + builder.size())
+                .sum::<usize>()
+                + c.index.size();
+            ChunkSummary {
+                file_id: None,
+                chunk_time: *chunk_time,
+                storage: ChunkStorage::Mutable,
+                row_count: c.row_count as u64,
+                min_time: c.timestamp_min,
+                max_time: c.timestamp_max,
+                memory_bytes: Some(size as u64),
+            }
+        });
+        let mut summaries = snapshotting.collect::<Vec<_>>();
+        summaries.sort_by_key(|s| s.chunk_time);
+        summaries.extend(mutable);
+        summaries
+    }
+
     pub fn snapshot(
         &mut self,
         table_def: Arc<TableDefinition>,